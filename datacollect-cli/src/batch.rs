@@ -0,0 +1,141 @@
+//! `--stdin` batch mode: looking up one key per line of stdin, and writing each result as
+//! soon as it's found.
+//!
+//! Commands use one client for the whole batch, so the cache and rate limits are shared.
+
+use std::fmt::Display;
+
+use anyhow::bail;
+use erased_serde::Serializer;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{stdin, AsyncBufReadExt, BufReader, Lines, Stdin};
+
+use crate::output;
+
+/// The keys to look up, read from stdin one per line. Blank lines are skipped.
+pub struct Keys(Lines<BufReader<Stdin>>);
+
+impl Keys {
+    pub fn stdin() -> Self {
+        Self(BufReader::new(stdin()).lines())
+    }
+
+    /// The next key, or `None` at the end of stdin.
+    ///
+    /// # Errors
+    /// Errors if stdin could not be read.
+    pub async fn next(&mut self) -> anyhow::Result<Option<String>> {
+        while let Some(line) = self.0.next_line().await? {
+            let key = line.trim();
+            if !key.is_empty() {
+                return Ok(Some(key.to_string()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// How a batch went. Written to stderr once the batch is done, so that it isn't mixed into
+/// the results.
+#[derive(Default)]
+pub struct Report {
+    items: usize,
+    failed: usize,
+    /// The results, if they aren't [streamed](output::streaming).
+    records: Vec<Value>,
+}
+
+impl Report {
+    /// Write the result of looking up `key` as soon as it's found, or report the error on
+    /// stderr.
+    ///
+    /// # Errors
+    /// Errors if the result could not be serialized.
+    pub async fn record<T: Serialize>(
+        &mut self,
+        key: &str,
+        result: anyhow::Result<T>,
+    ) -> anyhow::Result<()> {
+        self.items += 1;
+        match result {
+            Ok(item) => {
+                if let Some(record) = output::send(serde_json::to_value(&item)?).await {
+                    self.records.push(record);
+                }
+            }
+            Err(e) => {
+                self.failed += 1;
+                eprintln!("{} failed: {:#}", key, e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the results that weren't streamed as the output, and the report to stderr,
+    /// failing if any of the lookups did.
+    ///
+    /// # Errors
+    /// Errors if the output could not be written, or if any lookup failed.
+    pub fn finish(self, ser: &mut (dyn Serializer + Send)) -> anyhow::Result<()> {
+        erased_serde::serialize(&self.records, ser)?;
+        eprintln!("{}", self);
+        if self.failed > 0 {
+            bail!("{} of {} lookups failed", self.failed, self.items);
+        }
+        Ok(())
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "looked up {} keys, {} failed", self.items, self.failed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use erased_serde::Serializer;
+    use serde_json::{json, Value};
+
+    use super::Report;
+    use crate::output;
+
+    async fn lookups(report: &mut Report) {
+        report.record("1", Ok(json!({"id": 1}))).await.unwrap();
+        report
+            .record::<Value>("2", Err(anyhow!("not found")))
+            .await
+            .unwrap();
+        report.record("3", Ok(json!({"id": 3}))).await.unwrap();
+    }
+
+    fn finish(report: Report) -> (Value, anyhow::Result<()>) {
+        let mut json = Vec::new();
+        let result = report.finish(&mut <dyn Serializer>::erase(
+            &mut serde_json::Serializer::new(&mut json),
+        ));
+        (serde_json::from_slice(&json).unwrap(), result)
+    }
+
+    #[tokio::test]
+    async fn test_report() {
+        /* the results are the output, and the report isn't */
+        let mut report = Report::default();
+        lookups(&mut report).await;
+        assert_eq!(report.to_string(), "looked up 3 keys, 1 failed");
+        let (output, result) = finish(report);
+        assert_eq!(output, json!([{"id": 1}, {"id": 3}]));
+        assert!(result.is_err());
+
+        /* streamed results are left out of the output */
+        let (sender, mut receiver) = tokio::sync::mpsc::channel(8);
+        let mut report = Report::default();
+        output::streaming(Some(sender), lookups(&mut report)).await;
+        let (output, _) = finish(report);
+        assert_eq!(output, json!([]));
+        assert_eq!(receiver.recv().await, Some(json!({"id": 1})));
+        assert_eq!(receiver.recv().await, Some(json!({"id": 3})));
+    }
+}
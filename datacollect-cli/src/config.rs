@@ -0,0 +1,368 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+use datacollect::common::{
+    cache::Cache, cookies::CookieJars, dns::DohResolver, ratelimit::RateLimit, ClientConfig,
+    Connections, Detail, Timeouts,
+};
+use serde::{Deserialize, Deserializer};
+
+use crate::output::{self, Output};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// The configuration for this run: the config file, with command-line overrides applied.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Set the configuration for this run. Only the first call has any effect.
+pub fn set(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+/// How command output is written to stdout: one of the [`output::OUTPUTS`], by name.
+#[derive(Clone, Copy)]
+pub struct Format(&'static dyn Output);
+
+impl Format {
+    pub fn output(self) -> &'static dyn Output {
+        self.0
+    }
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self(&output::Json)
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match output::find(s) {
+            Some(output) => Ok(Self(output)),
+            None => bail!(
+                "no such format (expected one of: {})",
+                output::OUTPUTS
+                    .iter()
+                    .map(|output| output.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Parse a field with its [`FromStr`] implementation.
+fn from_str<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| s.parse().map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Seconds between requests to the same host.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// For every host without its own entry.
+    pub default: Option<f64>,
+    #[serde(default)]
+    pub hosts: BTreeMap<String, f64>,
+}
+
+/// How long requests may take, in seconds (see [`Timeouts`]). Each one left out keeps its
+/// default.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    pub connect: Option<f64>,
+    pub read: Option<f64>,
+    pub total: Option<f64>,
+}
+
+/// How connections are kept open between requests (see [`Connections`]). Times are in
+/// seconds; each setting left out keeps its default.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectionsConfig {
+    pub max_idle_per_host: Option<usize>,
+    pub idle_timeout: Option<f64>,
+    pub tcp_keepalive: Option<f64>,
+    /// Speak HTTP/2 without asking the server first.
+    #[serde(default)]
+    pub http2_prior_knowledge: bool,
+    pub http2_keep_alive: Option<f64>,
+}
+
+/// Settings for one module.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ModuleConfig {
+    /// The default `--detail` for the module's commands.
+    #[serde(default, deserialize_with = "from_str")]
+    pub detail: Option<Detail>,
+    /// The base URL of the site or API the module talks to, e.g. `https://www.ebay.co.uk`.
+    pub base: Option<String>,
+}
+
+/// The contents of the CLI's configuration file, `datacollect.toml`.
+///
+/// By default this is read from `~/.config/datacollect/datacollect.toml`, if it exists.
+/// Command-line options take priority over the file.
+///
+/// ## Example
+/// ```toml
+/// format = "json-compact"
+/// cache_dir = "/home/me/.cache/datacollect"
+/// cache_ttl = 3600
+/// cookie_dir = "/home/me/.local/share/datacollect/cookies"
+/// proxy = "socks5://127.0.0.1:1080"
+/// doh = "cloudflare"
+/// max_response_size = 16777216
+///
+/// [timeouts]
+/// read = 20
+///
+/// [connections]
+/// max_idle_per_host = 8
+/// tcp_keepalive = 60
+///
+/// [rate_limit]
+/// default = 0.5
+/// hosts = { "www.ebay.com" = 2.0 }
+///
+/// [modules.ebay]
+/// detail = "full"
+/// base = "https://www.ebay.co.uk"
+/// ```
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default, deserialize_with = "from_str")]
+    pub format: Option<Format>,
+    /// Where to cache responses. Nothing is cached if this isn't set.
+    pub cache_dir: Option<PathBuf>,
+    /// How long cached responses are used for, in seconds.
+    pub cache_ttl: Option<u64>,
+    /// Where to keep each module's cookies between runs (by default
+    /// `~/.local/share/datacollect/cookies`).
+    pub cookie_dir: Option<PathBuf>,
+    /// A proxy for all requests, e.g. `http://127.0.0.1:3128`.
+    pub proxy: Option<String>,
+    /// Look up hosts with DNS-over-HTTPS: `cloudflare`, `google`, or the URL of a DoH server
+    /// (e.g. `https://dns.quad9.net:5053/dns-query`).
+    #[serde(default, deserialize_with = "from_str")]
+    pub doh: Option<DohResolver>,
+    /// The largest response to download, in bytes (64 MiB by default).
+    pub max_response_size: Option<u64>,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+    #[serde(default)]
+    pub connections: ConnectionsConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    /// Settings by module name, e.g. `ebay`.
+    #[serde(default)]
+    pub modules: BTreeMap<String, ModuleConfig>,
+}
+
+impl Config {
+    /// The default location of the config file.
+    pub fn default_path() -> Option<PathBuf> {
+        datacollect::common::config_dir().map(|dir| dir.join("datacollect.toml"))
+    }
+
+    /// Read a [`Config`] from a TOML file.
+    ///
+    /// # Errors
+    /// Errors if the file could not be read or is not a valid configuration.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("could not parse {}", path.display()))
+    }
+
+    /// Read the config file at `path`, or at the default location if that exists.
+    ///
+    /// # Errors
+    /// Errors if the file could not be read or is not a valid configuration.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        match path
+            .map(Path::to_path_buf)
+            .or_else(|| Self::default_path().filter(|path| path.exists()))
+        {
+            Some(path) => Self::from_file(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// The settings for a module, which are all empty if the file has none.
+    pub fn module(&self, name: &str) -> &ModuleConfig {
+        static EMPTY: ModuleConfig = ModuleConfig {
+            detail: None,
+            base: None,
+        };
+        self.modules.get(name).unwrap_or(&EMPTY)
+    }
+
+    /// The cookie jars for the commands that need cookies, one per module, kept in the
+    /// cookie directory (or only in memory, if there is no home directory).
+    pub fn cookie_jars(&self) -> CookieJars {
+        self.cookie_dir
+            .clone()
+            .or_else(|| datacollect::common::data_dir().map(|dir| dir.join("cookies")))
+            .map(CookieJars::in_dir)
+            .unwrap_or_default()
+    }
+
+    /// The settings for the clients that commands create.
+    pub fn client_config(&self) -> ClientConfig {
+        let seconds = |s: f64| Duration::from_secs_f64(s.max(0.0));
+
+        let rate_limit = (self.rate_limit.default.is_some() || !self.rate_limit.hosts.is_empty())
+            .then(|| {
+                self.rate_limit.hosts.iter().fold(
+                    RateLimit::per_host(seconds(self.rate_limit.default.unwrap_or(0.0))),
+                    |limit, (host, s)| limit.host(host, seconds(*s)),
+                )
+            });
+        let cache = self.cache_dir.as_ref().map(|dir| {
+            let cache = Cache::new(dir);
+            match self.cache_ttl {
+                Some(ttl) => cache.ttl(Duration::from_secs(ttl)),
+                None => cache,
+            }
+        });
+
+        let defaults = Timeouts::default();
+        let timeouts = Timeouts {
+            connect: self.timeouts.connect.map(seconds).or(defaults.connect),
+            read: self.timeouts.read.map(seconds).or(defaults.read),
+            total: self.timeouts.total.map(seconds).or(defaults.total),
+        };
+
+        let defaults = Connections::default();
+        let connections = Connections {
+            max_idle_per_host: self
+                .connections
+                .max_idle_per_host
+                .or(defaults.max_idle_per_host),
+            idle_timeout: self
+                .connections
+                .idle_timeout
+                .map(seconds)
+                .or(defaults.idle_timeout),
+            tcp_keepalive: self.connections.tcp_keepalive.map(seconds),
+            http2_prior_knowledge: self.connections.http2_prior_knowledge,
+            http2_keep_alive: self.connections.http2_keep_alive.map(seconds),
+        };
+
+        ClientConfig {
+            proxy: self.proxy.clone(),
+            cache: cache.map(Arc::new),
+            rate_limit: rate_limit.map(Arc::new),
+            max_response_size: self.max_response_size,
+            timeouts: Some(timeouts),
+            connections: Some(connections),
+            doh: self
+                .doh
+                .clone()
+                .map(|doh| Arc::new(doh.with_timeouts(timeouts))),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use clap::Parser;
+    use datacollect::common::Detail;
+
+    use crate::options::Options;
+
+    #[test]
+    fn test_merge() {
+        let path = std::env::temp_dir().join(format!("datacollect-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            r#"
+            format = "yaml"
+            cache_dir = "/var/cache/datacollect"
+            proxy = "http://127.0.0.1:3128"
+
+            [rate_limit]
+            default = 0.5
+            hosts = { "www.ebay.com" = 2.0 }
+
+            [modules.ebay]
+            detail = "full"
+            base = "https://www.ebay.co.uk"
+            "#,
+        )
+        .unwrap();
+        let options = |args: &[&str]| {
+            let path = path.to_str().unwrap();
+            Options::try_parse_from(
+                ["datacollect-cli", "--config", path]
+                    .iter()
+                    .chain(args)
+                    .chain(&["query", "example.com"]),
+            )
+            .unwrap()
+        };
+
+        /* the file's settings, when no flags override them */
+        let config = options(&[]).config().unwrap();
+        assert_eq!(config.format.unwrap().output().name(), "yaml");
+        assert_eq!(
+            config.cache_dir,
+            Some(PathBuf::from("/var/cache/datacollect"))
+        );
+        assert_eq!(config.proxy.as_deref(), Some("http://127.0.0.1:3128"));
+        assert_eq!(config.rate_limit.default, Some(0.5));
+        assert_eq!(config.module("ebay").detail, Some(Detail::Full));
+        assert_eq!(
+            config.module("ebay").base.as_deref(),
+            Some("https://www.ebay.co.uk")
+        );
+        assert!(config.module("passmark").base.is_none());
+
+        /* flags win over the file, and leave the rest of it alone */
+        let config = options(&[
+            "--format",
+            "json-compact",
+            "--proxy",
+            "socks5://127.0.0.1:1080",
+            "--rate-limit",
+            "1",
+        ])
+        .config()
+        .unwrap();
+        assert_eq!(config.format.unwrap().output().name(), "json-compact");
+        assert_eq!(config.proxy.as_deref(), Some("socks5://127.0.0.1:1080"));
+        assert_eq!(config.rate_limit.default, Some(1.0));
+        assert_eq!(config.rate_limit.hosts["www.ebay.com"], 2.0);
+        assert_eq!(
+            config.cache_dir,
+            Some(PathBuf::from("/var/cache/datacollect"))
+        );
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,214 @@
+use std::str::FromStr;
+
+use anyhow::bail;
+use datacollect::common::{
+    http::{BlockedError, StatusError, TimeoutError, TooLargeError},
+    ParseError, PriceError,
+};
+use serde::Serialize;
+
+/// What went wrong, as far as a script running the CLI needs to know. Each kind has its
+/// own exit code.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// Anything else, e.g. an invalid config file.
+    Other,
+    /// A request couldn't be sent, or the server responded with an error.
+    Network,
+    /// A response couldn't be parsed, e.g. a page without the parts a module reads.
+    Parse,
+    /// The server says the thing asked for doesn't exist.
+    NotFound,
+    /// The command line couldn't be parsed, e.g. an unknown option (including a module's
+    /// own options).
+    Usage,
+}
+
+impl ErrorKind {
+    /// The kind of the first error in the chain that has one.
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<StatusError>() {
+                    return Some(match e.status.as_u16() {
+                        404 | 410 => Self::NotFound,
+                        _ => Self::Network,
+                    });
+                }
+                if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                    return Some(match e.status().map(|s| s.as_u16()) {
+                        Some(404 | 410) => Self::NotFound,
+                        _ if e.is_decode() => Self::Parse,
+                        _ => Self::Network,
+                    });
+                }
+                if cause.is::<clap::Error>() {
+                    return Some(Self::Usage);
+                }
+                /* errors of the client's own, rather than from reqwest */
+                if cause.is::<TimeoutError>()
+                    || cause.is::<TooLargeError>()
+                    || cause.is::<BlockedError>()
+                {
+                    return Some(Self::Network);
+                }
+                /* the modules' own parse errors, and JSON that serde couldn't read */
+                if cause.is::<ParseError>()
+                    || cause.is::<PriceError>()
+                    || cause.is::<serde_json::Error>()
+                {
+                    return Some(Self::Parse);
+                }
+                None
+            })
+            .unwrap_or(Self::Other)
+    }
+
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Other => 1,
+            Self::Network => 2,
+            Self::Parse => 3,
+            Self::NotFound => 4,
+            Self::Usage => 5,
+        }
+    }
+}
+
+/// How errors are written to stderr.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ErrorFormat {
+    /// `error: ` and the message, with its causes.
+    Text,
+    /// A JSON object with the message, causes, kind and exit code.
+    Json,
+}
+
+impl FromStr for ErrorFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => bail!("no such error format (expected `text` or `json`)"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    message: String,
+    /// The errors that led to this one, outermost first.
+    causes: Vec<String>,
+    kind: ErrorKind,
+    exit_code: i32,
+}
+
+/// Write an error to stderr, returning the exit code for it.
+pub fn report(error: &anyhow::Error, format: ErrorFormat) -> i32 {
+    let kind = ErrorKind::of(error);
+    match format {
+        ErrorFormat::Text => match error.downcast_ref::<clap::Error>() {
+            /* with clap's own `error: `, usage and suggestions */
+            Some(e) => {
+                let _ = e.print();
+            }
+            None => eprintln!("error: {:#}", error),
+        },
+        ErrorFormat::Json => {
+            let report = Report {
+                message: error.to_string(),
+                causes: error.chain().skip(1).map(ToString::to_string).collect(),
+                kind,
+                exit_code: kind.exit_code(),
+            };
+            match serde_json::to_string(&report) {
+                Ok(json) => eprintln!("{}", json),
+                Err(_) => eprintln!("error: {:#}", error),
+            }
+        }
+    }
+    kind.exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use anyhow::anyhow;
+    use datacollect::{
+        common::{
+            http::{BlockKind, BlockedError, StatusError, TimeoutError, TooLargeError},
+            Client, Detail, PriceError,
+        },
+        modules::ebay::{Endpoints, Product},
+        testing::MockServer,
+    };
+
+    use super::ErrorKind;
+
+    #[test]
+    fn test_kind() {
+        let url: reqwest::Url = "https://www.ebay.com/itm/1".parse().unwrap();
+        let kind = |error: anyhow::Error| ErrorKind::of(&error.context("could not get item 1"));
+
+        assert_eq!(
+            kind(anyhow!(TimeoutError {
+                url: url.clone(),
+                after: Duration::from_secs(30),
+            })),
+            ErrorKind::Network
+        );
+        assert_eq!(
+            kind(anyhow!(TooLargeError {
+                url: url.clone(),
+                limit: 1024,
+            })),
+            ErrorKind::Network
+        );
+        assert_eq!(
+            kind(anyhow!(BlockedError {
+                url: url.clone(),
+                kind: BlockKind::Captcha,
+            })),
+            ErrorKind::Network
+        );
+        assert_eq!(
+            kind(anyhow!(StatusError {
+                url: Some(url),
+                status: reqwest::StatusCode::NOT_FOUND,
+            })),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            kind(serde_json::from_str::<u32>("x").unwrap_err().into()),
+            ErrorKind::Parse
+        );
+        assert_eq!(
+            kind(anyhow!(PriceError::Malformed("8.8.4.4".to_string()))),
+            ErrorKind::Parse
+        );
+        assert_eq!(kind(anyhow!("bad config")), ErrorKind::Other);
+    }
+
+    #[tokio::test]
+    async fn test_kind_of_unreadable_page() {
+        /* a listing page without a title, as if eBay had changed its layout */
+        let server = MockServer::start().await.unwrap();
+        server.mock("/itm/foo/42", 200, "<h1>Graphics card</h1>");
+
+        let error = Product::by_id_with(
+            &Endpoints::new(server.uri()),
+            &mut Client::default(),
+            42,
+            Detail::Minimal,
+        )
+        .await
+        .err()
+        .unwrap();
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Parse);
+        assert_eq!(ErrorKind::of(&error).exit_code(), 3);
+    }
+}
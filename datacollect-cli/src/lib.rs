@@ -0,0 +1,186 @@
+//! The `datacollect-cli` command line, which other crates can add commands to; see
+//! [`Module`].
+
+mod batch;
+pub(crate) mod common;
+mod config;
+mod errors;
+pub mod module;
+mod modules;
+mod options;
+mod output;
+mod progress;
+
+use std::io::stderr;
+
+use clap::{CommandFactory, FromArgMatches};
+use datacollect::{
+    common::{metrics, set_default_config},
+    sinks::{Dedup, HashStore, Sink},
+};
+use tokio::sync::mpsc;
+use tracing_subscriber::EnvFilter;
+
+pub use crate::module::{register, Module};
+use crate::{
+    common::Run,
+    errors::ErrorKind,
+    options::Options,
+    output::{Destination, Selected, Shape, Wrapped},
+};
+
+/// Run the CLI with the arguments it was started with, and exit if the command failed.
+pub async fn main() {
+    let opt = match Options::command()
+        .try_get_matches()
+        .and_then(|matches| Options::from_arg_matches(&matches))
+    {
+        Ok(opt) => opt,
+        /* `--help` and `--version`, which aren't errors */
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            let _ = e.print();
+            std::process::exit(ErrorKind::Usage.exit_code());
+        }
+    };
+
+    /* RUST_LOG (e.g. `RUST_LOG=datacollect_core=debug`) takes priority over -v and -q */
+    let level = match (opt.quiet, opt.verbose) {
+        (true, _) => "off",
+        (false, 0) => "error",
+        (false, 1) => "info",
+        (false, 2) => "debug",
+        (false, _) => "trace",
+    };
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(level)),
+        )
+        .with_writer(stderr)
+        .init();
+    progress::set_enabled(!opt.quiet);
+
+    if opt.stats {
+        metrics::enable();
+    }
+
+    let result = run(&opt).await;
+
+    if opt.stats {
+        eprintln!(
+            "{}",
+            serde_json::to_string_pretty(&metrics::snapshot()).unwrap()
+        );
+    }
+
+    if let Err(error) = result {
+        std::process::exit(errors::report(&error, opt.errors));
+    }
+}
+
+/// Load the configuration and run the command, writing the records in its output to each
+/// `--output` destination (stdout by default).
+async fn run(opt: &Options) -> anyhow::Result<()> {
+    let config = opt.config()?;
+    set_default_config(config.client_config())?;
+    let format = config.format.unwrap_or_default().output();
+    config::set(config);
+
+    let mut destinations = Vec::new();
+    for destination in opt.output.iter().map(String::as_str).chain(
+        /* stdout, unless output goes somewhere else */
+        opt.output.is_empty().then_some("-"),
+    ) {
+        destinations.push(Destination::open(destination, format).await?);
+    }
+
+    let (wrapped, selected);
+    let mut command: &(dyn Run + Sync) = &opt.command.0;
+    if opt.wrap {
+        wrapped = Wrapped {
+            command,
+            /* the arguments, without the program's name or `--wrap` itself */
+            query: std::env::args()
+                .skip(1)
+                .filter(|arg| arg != "--wrap")
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        command = &wrapped;
+    }
+    if let Some(selection) = &opt.select {
+        selected = Selected { command, selection };
+        command = &selected;
+    }
+
+    /* records in lists are written as they're found, while the command runs */
+    let mut records = Records {
+        destinations,
+        store: opt.dedup.as_deref().map(HashStore::open).transpose()?,
+        keys: &opt.dedup_key,
+        sink: None,
+    };
+    let (sender, mut receiver) = mpsc::channel(64);
+    let (output, written) = tokio::join!(
+        output::streaming(Some(sender), output::collect(command)),
+        async {
+            while let Some(record) = receiver.recv().await {
+                /* `Selected` only sees what's left in the output */
+                let record = match &opt.select {
+                    Some(selection) => selection.project(&record),
+                    None => record,
+                };
+                records.sink(&Shape::List).write(&record).await?;
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+    );
+    written?;
+
+    /* if nothing was streamed, the records are in the output */
+    if records.sink.is_none() {
+        let (shape, output) = output::split(output?);
+        let sink = records.sink(&shape);
+        for record in &output {
+            sink.write(record).await?;
+        }
+        return sink.close().await;
+    }
+    /* what was streamed is kept, even if the command failed later on */
+    records.sink(&Shape::List).close().await?;
+    output.map(drop)
+}
+
+/// Where the records in a command's output go: to every `--output` destination, through
+/// `--dedup` if it's given.
+struct Records<'a> {
+    destinations: Vec<Destination>,
+    store: Option<HashStore>,
+    /// `--dedup-key`
+    keys: &'a [String],
+    sink: Option<Box<dyn Sink>>,
+}
+
+impl Records<'_> {
+    /// The sink to write the records to, opened the first time, once the `shape` of the
+    /// output is known.
+    fn sink(&mut self, shape: &Shape) -> &mut Box<dyn Sink> {
+        let Self {
+            destinations,
+            store,
+            keys,
+            sink,
+        } = self;
+        sink.get_or_insert_with(|| {
+            let sinks = std::mem::take(destinations)
+                .into_iter()
+                .map(|destination| destination.into_sink(shape))
+                .collect::<Vec<_>>();
+            match store.take() {
+                Some(store) if keys.is_empty() => Box::new(Dedup::new(sinks, store)),
+                Some(store) => Box::new(Dedup::new(sinks, store).key(keys.iter().cloned())),
+                None => Box::new(sinks),
+            }
+        })
+    }
+}
@@ -0,0 +1,178 @@
+//! Modules from other crates, e.g. for sources that can't be made public, which add
+//! commands of their own to the CLI.
+//!
+//! A crate [registers](register) its [`Module`]s, then runs the CLI from its own `main`.
+//! Each module's output is written like any other command's, so `--format`, `--select` and
+//! `--wrap` work on it, and it can be run by `collect` and `daemon` jobs.
+//!
+//! ## Example
+//! ```txt
+//! struct Acme;
+//!
+//! #[async_trait]
+//! impl Module for Acme {
+//!     fn command(&self) -> clap::Command {
+//!         clap::Command::new("acme").about("Look up Acme parts").arg(clap::Arg::new("id"))
+//!     }
+//!     async fn run(&self, args: &ArgMatches) -> anyhow::Result<Value> {
+//!         let id = args.get_one::<String>("id").context("no id given")?;
+//!         Ok(acme::lookup(id).await?)
+//!     }
+//! }
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     datacollect_cli::register(Acme);
+//!     datacollect_cli::main().await
+//! }
+//!
+//! acme-datacollect acme 42 --format yaml
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use clap::{ArgMatches, FromArgMatches, Subcommand};
+use erased_serde::Serializer;
+use serde_json::Value;
+
+use crate::{common::Run, options::Command};
+
+/// A command added to the CLI by another crate; see the [module documentation](self).
+#[async_trait]
+pub trait Module: Send + Sync + 'static {
+    /// The command: its name (e.g. `acme`, for `datacollect-cli acme ...`), arguments and
+    /// help.
+    fn command(&self) -> clap::Command;
+
+    /// Run the command with the arguments it was given, returning its output.
+    async fn run(&self, args: &ArgMatches) -> anyhow::Result<Value>;
+}
+
+static MODULES: RwLock<Vec<Arc<dyn Module>>> = RwLock::new(Vec::new());
+
+/// Add a module's command to the CLI. It must be registered before [`main`](crate::main)
+/// runs, and its name can't be one of the CLI's own commands.
+pub fn register(module: impl Module) {
+    MODULES.write().unwrap().push(Arc::new(module));
+}
+
+fn find(name: &str) -> Option<Arc<dyn Module>> {
+    MODULES
+        .read()
+        .unwrap()
+        .iter()
+        .find(|module| module.command().get_name() == name)
+        .cloned()
+}
+
+/// A registered module's command, with the arguments it was given.
+pub struct Invocation {
+    module: Arc<dyn Module>,
+    matches: ArgMatches,
+}
+
+#[async_trait]
+impl Run for Invocation {
+    async fn run(&self, serializer: &mut (dyn Serializer + Send)) -> anyhow::Result<()> {
+        erased_serde::serialize(&self.module.run(&self.matches).await?, serializer)?;
+        Ok(())
+    }
+}
+
+/// One of the CLI's own [`Command`]s, or a registered module's.
+///
+/// The modules' commands are subcommands of the CLI like its own, so the global options
+/// (e.g. `--format`) can be given after a module's name too.
+pub struct AnyCommand(pub Command);
+
+impl FromArgMatches for AnyCommand {
+    fn from_arg_matches(matches: &ArgMatches) -> Result<Self, clap::Error> {
+        if let Some((name, args)) = matches.subcommand() {
+            if let Some(module) = find(name) {
+                return Ok(Self(Command::Module(Invocation {
+                    module,
+                    matches: args.clone(),
+                })));
+            }
+        }
+        Command::from_arg_matches(matches).map(Self)
+    }
+
+    fn update_from_arg_matches(&mut self, matches: &ArgMatches) -> Result<(), clap::Error> {
+        *self = Self::from_arg_matches(matches)?;
+        Ok(())
+    }
+}
+
+impl Subcommand for AnyCommand {
+    fn augment_subcommands(command: clap::Command) -> clap::Command {
+        let modules = MODULES.read().unwrap();
+        Command::augment_subcommands(command)
+            .subcommands(modules.iter().map(|module| module.command()))
+    }
+
+    fn augment_subcommands_for_update(command: clap::Command) -> clap::Command {
+        Self::augment_subcommands(command)
+    }
+
+    fn has_subcommand(name: &str) -> bool {
+        Command::has_subcommand(name) || find(name).is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use clap::{ArgMatches, Parser};
+    use serde_json::{json, Value};
+
+    use super::{register, Module};
+    use crate::{errors::ErrorKind, options::Options, output};
+
+    struct Acme;
+
+    #[async_trait]
+    impl Module for Acme {
+        fn command(&self) -> clap::Command {
+            clap::Command::new("acme")
+                .about("Look up Acme parts")
+                .arg(clap::Arg::new("id").required(true))
+        }
+
+        async fn run(&self, args: &ArgMatches) -> anyhow::Result<Value> {
+            Ok(json!({ "id": args.get_one::<String>("id").unwrap() }))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_module() {
+        register(Acme);
+
+        /* global options work after the module's name, and its own arguments still do */
+        let opt =
+            Options::try_parse_from(["datacollect-cli", "acme", "42", "--format", "yaml"]).unwrap();
+        assert_eq!(opt.format.unwrap().output().name(), "yaml");
+        assert_eq!(
+            output::collect(&opt.command.0).await.unwrap(),
+            json!({"id": "42"})
+        );
+
+        let error = Options::try_parse_from(["datacollect-cli", "acme", "42", "--colour"])
+            .err()
+            .unwrap();
+        assert_eq!(ErrorKind::of(&error.into()), ErrorKind::Usage);
+    }
+
+    #[test]
+    fn test_unknown_command() {
+        let error = Options::try_parse_from(["datacollect-cli", "ebya", "42"])
+            .err()
+            .unwrap();
+        assert_eq!(error.kind(), clap::error::ErrorKind::InvalidSubcommand);
+        assert!(error.to_string().contains("ebay"));
+        let error = anyhow::Error::from(error);
+        assert_eq!(ErrorKind::of(&error), ErrorKind::Usage);
+        assert_eq!(ErrorKind::of(&error).exit_code(), 5);
+    }
+}
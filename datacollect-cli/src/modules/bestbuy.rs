@@ -0,0 +1,93 @@
+use clap::{Args, Subcommand};
+
+use crate::common::Run;
+
+#[derive(Args)]
+pub struct Bestbuy {
+    /// A Best Buy API key, from https://developer.bestbuy.com.
+    /// Defaults to the configured credential (see `credentials list`).
+    #[arg(long)]
+    key: Option<String>,
+    #[command(subcommand)]
+    query_type: QueryType,
+}
+
+#[async_trait::async_trait]
+impl Run for Bestbuy {
+    async fn run(&self, ser: &mut (dyn erased_serde::Serializer + Send)) -> anyhow::Result<()> {
+        let key = match &self.key {
+            Some(key) => key.clone(),
+            None => datacollect::common::Credentials::load()?
+                .require("bestbuy", "api_key")?
+                .to_string(),
+        };
+        self.query_type.run(&key, ser).await
+    }
+}
+
+#[derive(Subcommand)]
+enum QueryType {
+    #[command(subcommand)]
+    Product(product::SubCommand),
+}
+
+impl QueryType {
+    async fn run(
+        &self,
+        key: &str,
+        ser: &mut (dyn erased_serde::Serializer + Send),
+    ) -> anyhow::Result<()> {
+        match self {
+            Self::Product(p) => p.run(key, ser).await,
+        }
+    }
+}
+
+mod product {
+    use datacollect::{schemas::product::Product, stream::StreamExt};
+
+    use crate::output;
+    use clap::Subcommand;
+
+    /// Products are written in the common product schema, so they can be compared
+    /// with products from other modules.
+    #[derive(Subcommand)]
+    pub(super) enum SubCommand {
+        Sku { sku: u64 },
+        Search { query: String, limit: usize },
+    }
+
+    impl SubCommand {
+        pub(super) async fn run(
+            &self,
+            key: &str,
+            ser: &mut (dyn erased_serde::Serializer + Send),
+        ) -> anyhow::Result<()> {
+            match self {
+                Self::Sku { sku } => {
+                    erased_serde::serialize(
+                        &datacollect::modules::bestbuy::Product::by_sku(
+                            &mut Default::default(),
+                            key,
+                            *sku,
+                        )
+                        .await?
+                        .map(Product::from),
+                        ser,
+                    )?;
+                }
+                Self::Search { query, limit } => {
+                    output::write_stream(
+                        datacollect::modules::bestbuy::Product::search(key, query)
+                            .filter_map(|r| async move { r.ok().map(Product::from) }),
+                        *limit,
+                        "Best Buy products",
+                        ser,
+                    )
+                    .await?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
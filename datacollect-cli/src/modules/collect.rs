@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, fmt::Display, path::PathBuf};
+
+use anyhow::{bail, Context};
+use clap::Args;
+use datacollect::stream::{self, StreamExt};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use super::daemon::{parse_command, run_value, write_line};
+use crate::{options::Command, output, progress, run_impl_enum};
+
+/// Run a batch of jobs once, several at a time, writing each job's output to its own file.
+/// How each job went is reported on stderr.
+///
+/// Requests from every job share the rate limits from the config file, so running jobs
+/// together doesn't make them any harder on a site.
+#[derive(Args)]
+pub struct Collect {
+    #[arg(long)]
+    config: PathBuf,
+    /// How many commands to run at once, instead of the file's `concurrency`.
+    #[arg(long)]
+    concurrency: Option<usize>,
+}
+
+/// The contents of a collection file (e.g. `jobs.toml`).
+///
+/// A job with `each` runs its command once per value, with `{}` in the command replaced by
+/// the value.
+///
+/// ## Example
+/// ```toml
+/// concurrency = 8
+///
+/// [[jobs]]
+/// name = "cpus"
+/// command = ["passmark", "cpu", "mega-list"]
+/// output = "cpus.jsonl"
+///
+/// [[jobs]]
+/// name = "listings"
+/// command = ["ebay", "product", "{}"]
+/// each = ["123456789012", "210987654321"]
+/// output = "listings.jsonl"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    #[serde(default = "default_concurrency")]
+    concurrency: usize,
+    jobs: Vec<Job>,
+}
+
+fn default_concurrency() -> usize {
+    4
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Job {
+    name: String,
+    /// The arguments to run, as if they were given to `datacollect-cli` on the command line.
+    command: Vec<String>,
+    #[serde(default)]
+    each: Vec<String>,
+    /// A file to append the output of each run to, as a line of JSON.
+    /// If this is not given, each run's output is a record in the command's output.
+    output: Option<PathBuf>,
+}
+
+impl Job {
+    /// The commands to run: one, or one per value of `each`.
+    fn commands(&self) -> anyhow::Result<Vec<Command>> {
+        if self.each.is_empty() {
+            return Ok(vec![parse_command(&self.name, &self.command)?]);
+        }
+        self.each
+            .iter()
+            .map(|value| {
+                let args = self
+                    .command
+                    .iter()
+                    .map(|arg| arg.replace("{}", value))
+                    .collect::<Vec<_>>();
+                parse_command(&self.name, &args)
+            })
+            .collect()
+    }
+}
+
+/// How a job went.
+#[derive(Default)]
+struct Report {
+    runs: usize,
+    failed: usize,
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} runs, {} failed", self.runs, self.failed)
+    }
+}
+
+/// How every job went, one line per job.
+fn summary(reports: &BTreeMap<&str, Report>) -> String {
+    reports
+        .iter()
+        .map(|(name, report)| format!("job {}: {}", name, report))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+run_impl_enum!(Collect, self, ser, {
+    let text = std::fs::read_to_string(&self.config)
+        .with_context(|| format!("could not read {}", self.config.display()))?;
+    let config: Config = toml::from_str(&text)
+        .with_context(|| format!("could not parse {}", self.config.display()))?;
+    let concurrency = self.concurrency.unwrap_or(config.concurrency).max(1);
+
+    /* check every job before starting any of them */
+    let mut runs = Vec::new();
+    for (i, job) in config.jobs.iter().enumerate() {
+        runs.extend(job.commands()?.into_iter().map(|command| (i, command)));
+    }
+
+    /* commands run at the same time, but their output is written one line at a time */
+    let (write, records) = (Mutex::new(()), Mutex::new(Vec::new()));
+    let (jobs, write, records) = (&config.jobs, &write, &records);
+    let bar = progress::bar(runs.len() as u64, "collecting");
+    let results = stream::iter(runs)
+        .map(|(i, command)| async move {
+            let result = async {
+                let value = run_value(&command).await?;
+                match jobs[i].output.as_deref() {
+                    Some(path) => {
+                        let _guard = write.lock().await;
+                        write_line(Some(path), &value).await?;
+                    }
+                    None => {
+                        if let Some(record) = output::send(value).await {
+                            records.lock().await.push(record);
+                        }
+                    }
+                }
+                Ok::<_, anyhow::Error>(())
+            }
+            .await;
+            (i, result)
+        })
+        .buffer_unordered(concurrency)
+        .inspect(|_| bar.inc(1))
+        .collect::<Vec<_>>()
+        .await;
+    bar.finish_and_clear();
+
+    let mut reports = BTreeMap::new();
+    for (i, result) in &results {
+        let job = &jobs[*i];
+        let report: &mut Report = reports.entry(job.name.as_str()).or_default();
+        report.runs += 1;
+        if let Err(e) = result {
+            report.failed += 1;
+            eprintln!("job {} failed: {:#}", job.name, e);
+        }
+    }
+    erased_serde::serialize(&*records.lock().await, ser)?;
+    eprintln!("{}", summary(&reports));
+
+    let failed = reports.values().map(|r| r.failed).sum::<usize>();
+    if failed > 0 {
+        bail!("{} of {} runs failed", failed, results.len());
+    }
+});
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{summary, Report};
+
+    #[test]
+    fn test_summary() {
+        let mut reports = BTreeMap::new();
+        reports.insert("listings", Report { runs: 2, failed: 1 });
+        reports.insert("cpus", Report { runs: 1, failed: 0 });
+        assert_eq!(
+            summary(&reports),
+            "job cpus: 1 runs, 0 failed\njob listings: 2 runs, 1 failed"
+        );
+    }
+}
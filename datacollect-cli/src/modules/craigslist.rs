@@ -0,0 +1,58 @@
+use clap::{Args, Subcommand};
+
+use crate::{run_impl_enum, run_impl_struct};
+
+#[derive(Args)]
+pub struct Craigslist {
+    #[command(subcommand)]
+    query_type: QueryType,
+}
+
+run_impl_struct!(Craigslist, query_type);
+
+#[derive(Subcommand)]
+enum QueryType {
+    #[command(subcommand)]
+    Listing(listing::SubCommand),
+}
+
+run_impl_enum!(QueryType, self, ser, {
+    match self {
+        Self::Listing(l) => l.run(ser).await?,
+    }
+});
+
+mod listing {
+    use crate::{output, run_impl_enum};
+    use clap::Subcommand;
+    use datacollect::stream::StreamExt;
+
+    #[derive(Subcommand)]
+    pub(super) enum SubCommand {
+        Search {
+            /// The Craigslist region, e.g. `sfbay` or `newyork`.
+            region: String,
+            query: String,
+            limit: usize,
+        },
+    }
+
+    run_impl_enum!(SubCommand, self, ser, {
+        match self {
+            Self::Search {
+                region,
+                query,
+                limit,
+            } => {
+                output::write_stream(
+                    datacollect::modules::craigslist::Listing::search(region, query)
+                        .filter_map(|r| async move { r.ok() }),
+                    *limit,
+                    "Craigslist listings",
+                    ser,
+                )
+                .await?;
+            }
+        }
+    });
+}
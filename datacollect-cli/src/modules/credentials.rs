@@ -0,0 +1,27 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum Credentials {
+    /// List the credentials modules can use, and where each one is configured (never their values).
+    List {
+        /// Read this credentials file instead of `~/.config/datacollect/credentials.toml`.
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+}
+
+run_impl_enum!(Credentials, self, ser, {
+    match self {
+        Self::List { file } => {
+            let credentials = match file {
+                Some(file) => datacollect::common::Credentials::from_file(file)?,
+                None => datacollect::common::Credentials::load()?,
+            };
+            erased_serde::serialize(&credentials.statuses(), ser)?;
+        }
+    }
+});
@@ -0,0 +1,280 @@
+use std::{
+    iter,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use clap::{Args, Parser};
+use cron::Schedule;
+use datacollect::{
+    chrono::Utc,
+    sinks::{JsonSink, Sink, StdoutSink},
+};
+use erased_serde::Serializer;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::watch;
+
+use crate::{common::Run, module::AnyCommand, options::Command, output};
+
+/// Run collection jobs on a schedule until interrupted.
+#[derive(Args)]
+pub struct Daemon {
+    #[arg(long)]
+    config: PathBuf,
+}
+
+/// The contents of a daemon configuration file (e.g. `collect.toml`).
+///
+/// ## Example
+/// ```toml
+/// [[jobs]]
+/// name = "cpus"
+/// schedule = "0 0 6 * * *"
+/// command = ["passmark", "cpu", "mega-list"]
+/// output = "cpus.jsonl"
+/// ```
+#[derive(Deserialize)]
+struct Config {
+    jobs: Vec<Job>,
+}
+
+#[derive(Deserialize, Clone)]
+struct Job {
+    name: String,
+    /// A cron expression, including seconds: `sec min hour day-of-month month day-of-week [year]`.
+    schedule: String,
+    /// The arguments to run, as if they were given to `datacollect-cli` on the command line.
+    command: Vec<String>,
+    /// A file to append the output of each run to, as a line of JSON.
+    /// If this is not given, output goes to stdout.
+    output: Option<PathBuf>,
+}
+
+/// Parse a job's command, given as the arguments to `datacollect-cli`.
+///
+/// Commands that run other commands (like `daemon`) are refused, so that jobs can't nest, and
+/// so are commands that run until interrupted (like `domains watch`).
+pub(crate) fn parse_command(job: &str, args: &[String]) -> anyhow::Result<Command> {
+    /* a job is just a command, without the global options */
+    #[derive(Parser)]
+    #[command(name = "datacollect-cli")]
+    struct Job {
+        #[command(subcommand)]
+        command: AnyCommand,
+    }
+
+    let Job {
+        command: AnyCommand(command),
+    } = Job::try_parse_from(iter::once("datacollect-cli").chain(args.iter().map(String::as_str)))
+        .with_context(|| format!("bad command for job {}", job))?;
+    if let Command::Daemon(_) | Command::Collect(_) = command {
+        bail!("job {} cannot run other jobs", job);
+    }
+    if let Command::Domains(_) = command {
+        bail!("job {} would never finish", job);
+    }
+    Ok(command)
+}
+
+/// Run a command once, and return its output.
+pub(crate) async fn run_value(command: &Command) -> anyhow::Result<Value> {
+    output::collect_all(command).await
+}
+
+/// Append `output` as a line of JSON to `path`, or write it to stdout if there is no `path`.
+pub(crate) async fn write_line(path: Option<&Path>, output: &Value) -> anyhow::Result<()> {
+    let mut sink: Box<dyn Sink> = match path {
+        Some(path) => Box::new(
+            JsonSink::append(path).with_context(|| format!("could not open {}", path.display()))?,
+        ),
+        None => Box::new(StdoutSink::new()),
+    };
+    sink.write(output).await?;
+    sink.close().await
+}
+
+impl Job {
+    fn parse(&self) -> anyhow::Result<(Schedule, Command)> {
+        let schedule = Schedule::from_str(&self.schedule)
+            .map_err(|e| anyhow::anyhow!("bad schedule for job {}: {}", self.name, e))?;
+        Ok((schedule, parse_command(&self.name, &self.command)?))
+    }
+
+    /// Run the command once, writing its output to the configured destination.
+    async fn run_once(&self, command: &Command) -> anyhow::Result<()> {
+        write_line(self.output.as_deref(), &run_value(command).await?).await
+    }
+}
+
+/// Run a job every time its schedule comes up, until `shutdown` changes.
+///
+/// Each run happens in its own task, so a run that errors (or panics) is
+/// reported and the job carries on with its next scheduled run.
+async fn run_job(
+    job: Job,
+    schedule: Schedule,
+    command: Command,
+    mut shutdown: watch::Receiver<bool>,
+) {
+    let job = Arc::new(job);
+    let command = Arc::new(command);
+
+    for next in schedule.upcoming(Utc) {
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shutdown.changed() => break,
+        }
+
+        let (job, command) = (job.clone(), command.clone());
+        let name = job.name.clone();
+        match tokio::spawn(async move { job.run_once(&command).await }).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("job {} failed: {:#}", name, e),
+            Err(e) => eprintln!("job {} panicked: {}", name, e),
+        }
+    }
+}
+
+#[async_trait]
+impl Run for Daemon {
+    async fn run(&self, _serializer: &mut (dyn Serializer + Send)) -> anyhow::Result<()> {
+        let text = std::fs::read_to_string(&self.config)
+            .with_context(|| format!("could not read {}", self.config.display()))?;
+        let config: Config = toml::from_str(&text)
+            .with_context(|| format!("could not parse {}", self.config.display()))?;
+
+        /* check every job before starting any of them */
+        let jobs = config
+            .jobs
+            .into_iter()
+            .map(|job| {
+                job.parse()
+                    .map(|(schedule, command)| (job, schedule, command))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let (tx, rx) = watch::channel(false);
+        let handles = jobs
+            .into_iter()
+            .map(|(job, schedule, command)| {
+                tokio::spawn(run_job(job, schedule, command, rx.clone()))
+            })
+            .collect::<Vec<_>>();
+
+        tokio::signal::ctrl_c().await?;
+        eprintln!("shutting down; waiting for running jobs to finish...");
+        tx.send(true)?;
+
+        for handle in handles {
+            handle.await?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use datacollect::{
+        chrono::{Timelike, Utc},
+        testing::MockServer,
+    };
+    use serde_json::{json, Value};
+    use tokio::sync::watch;
+
+    use super::{run_job, Config, Job};
+
+    fn job(schedule: &str, command: &[&str]) -> Job {
+        Job {
+            name: "cpus".to_string(),
+            schedule: schedule.to_string(),
+            command: command.iter().map(|arg| arg.to_string()).collect(),
+            output: None,
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let config: Config = toml::from_str(
+            r#"
+            [[jobs]]
+            name = "cpus"
+            schedule = "0 0 6 * * *"
+            command = ["passmark", "cpu", "mega-list"]
+            output = "cpus.jsonl"
+            "#,
+        )
+        .unwrap();
+        let (schedule, _) = config.jobs[0].parse().unwrap();
+        let next = schedule.upcoming(Utc).next().unwrap();
+        assert_eq!((next.hour(), next.minute(), next.second()), (6, 0, 0));
+
+        let error = |job: Job| format!("{:#}", job.parse().err().unwrap());
+        assert!(error(job("every day", &["passmark", "cpu", "mega-list"]))
+            .starts_with("bad schedule for job cpus"));
+        assert!(error(job("0 0 6 * * *", &["passmark", "gpu"])).starts_with("bad command"));
+        assert_eq!(
+            error(job("0 0 6 * * *", &["daemon", "--config", "collect.toml"])),
+            "job cpus cannot run other jobs"
+        );
+        assert_eq!(
+            error(job(
+                "0 0 6 * * *",
+                &["domains", "watch", "--file", "domains.toml"]
+            )),
+            "job cpus would never finish"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_tick() {
+        let server = MockServer::start().await.unwrap();
+        server.mock(
+            "/product",
+            200,
+            r#"<div itemscope itemtype="https://schema.org/Product">
+                <span itemprop="name">AMD Ryzen 5 2600</span>
+            </div>"#,
+        );
+        let output =
+            std::env::temp_dir().join(format!("datacollect-daemon-{}.jsonl", std::process::id()));
+        let url = format!("{}/product", server.uri());
+        let job = Job {
+            output: Some(output.clone()),
+            ..job("* * * * * *", &["scrape", "microdata", &url])
+        };
+        let (schedule, command) = job.parse().unwrap();
+
+        /* every second, until the first run has written its output */
+        let (tx, rx) = watch::channel(false);
+        let handle = tokio::spawn(run_job(job, schedule, command, rx));
+        let mut text = String::new();
+        for _ in 0..50 {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            text = std::fs::read_to_string(&output).unwrap_or_default();
+            if !text.is_empty() {
+                break;
+            }
+        }
+        tx.send(true).unwrap();
+        handle.await.unwrap();
+
+        let line = text.lines().next().unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(line).unwrap(),
+            json!({"items": [{
+                "type": ["https://schema.org/Product"],
+                "properties": {"name": ["AMD Ryzen 5 2600"]}
+            }]})
+        );
+        assert_eq!(server.requests()[0], "/product");
+        std::fs::remove_file(&output).unwrap();
+    }
+}
@@ -0,0 +1,38 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use clap::Args;
+use datacollect::diff::{diff_lists, diff_values};
+use serde_json::Value;
+
+use crate::{output, run_impl_enum};
+
+/// Compare two outputs (e.g. yesterday's and today's), listing what changed field by field.
+/// Lists of records are matched by `--key`, and list the records added, removed and changed;
+/// anything else is compared as a single record.
+#[derive(Args)]
+pub struct Diff {
+    old: PathBuf,
+    new: PathBuf,
+    /// The field that identifies a record in a list.
+    #[arg(long, default_value = "id")]
+    key: String,
+}
+
+fn read(path: &Path) -> anyhow::Result<Value> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("could not read {}", path.display()))?;
+    serde_json::from_str(&text).with_context(|| format!("could not parse {}", path.display()))
+}
+
+run_impl_enum!(Diff, self, ser, {
+    let (old, new) = (read(&self.old)?, read(&self.new)?);
+    match (output::split(old.clone()), output::split(new.clone())) {
+        ((output::Shape::Single, _), _) | (_, (output::Shape::Single, _)) => {
+            erased_serde::serialize(&diff_values(&old, &new), ser)?;
+        }
+        ((_, old), (_, new)) => {
+            erased_serde::serialize(&diff_lists(&old, &new, &self.key), ser)?;
+        }
+    }
+});
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+use datacollect::{
+    dropcatch::Watcher,
+    modules::rdap::Endpoints,
+    notify::{self, Notifier},
+};
+
+use crate::{config, run_impl_enum};
+
+#[derive(Subcommand)]
+pub enum Domains {
+    /// Watch domains until they can be registered, checking each more often as it nears its
+    /// deletion, and notify when one becomes available. Runs until interrupted, then outputs
+    /// what's known about each domain.
+    Watch {
+        /// A file listing the domains to watch, one per line.
+        #[arg(long)]
+        file: PathBuf,
+        /// A URL to POST each notification to, as JSON; can be given more than once.
+        /// Notifications are always printed to stderr too.
+        #[arg(long = "webhook")]
+        webhooks: Vec<String>,
+    },
+}
+
+run_impl_enum!(Domains, self, ser, {
+    match self {
+        Self::Watch { file, webhooks } => {
+            let base = config::get().module("rdap").base.clone();
+            let endpoints = base
+                .as_deref()
+                .map_or_else(Endpoints::default, Endpoints::new);
+            let mut watcher = Watcher::from_file(endpoints, file)?;
+            let notifiers: Vec<Box<dyn Notifier>> = notify::Config {
+                stdout: false,
+                stderr: true,
+                webhooks: webhooks.clone(),
+            }
+            .notifiers();
+
+            watcher
+                .run(&mut Default::default(), &notifiers, async {
+                    let _ = tokio::signal::ctrl_c().await;
+                })
+                .await;
+            erased_serde::serialize(watcher.domains(), ser)?;
+        }
+    }
+});
@@ -1,17 +1,18 @@
-use structopt::StructOpt;
+use clap::{Args, Subcommand};
 
 use crate::{run_impl_enum, run_impl_struct};
 
-#[derive(StructOpt)]
+#[derive(Args)]
 pub struct Ebay {
-    #[structopt(subcommand)]
+    #[command(subcommand)]
     query_type: QueryType,
 }
 
 run_impl_struct!(Ebay, query_type);
 
-#[derive(StructOpt)]
+#[derive(Subcommand)]
 enum QueryType {
+    #[command(subcommand)]
     Product(product::SubCommand),
 }
 
@@ -21,33 +22,296 @@ run_impl_enum!(QueryType, self, ser, {
     }
 });
 
-mod product {
-    use crate::run_impl_enum;
-    use datacollect::stream::StreamExt;
-    use structopt::StructOpt;
+pub(crate) mod product {
+    use std::path::PathBuf;
 
-    #[derive(StructOpt)]
+    use crate::{
+        batch::{Keys, Report},
+        config, output, progress, run_impl_enum,
+    };
+    use anyhow::Context;
+    use clap::Subcommand;
+    use datacollect::{
+        checkpoint::SearchCheckpoint,
+        common::{
+            auth::{self, Session},
+            Client, Credentials, Detail,
+        },
+        modules::ebay::{
+            Category, Endpoints, PriceGuide, Product, PurchaseHistory, SearchInterrupted,
+            SearchOptions, Store,
+        },
+        stream::StreamExt,
+    };
+
+    /// The `[modules.ebay]` settings from the config file, with `detail` taking priority.
+    pub(crate) fn settings(detail: Option<Detail>) -> (Endpoints, Detail) {
+        let module = config::get().module("ebay");
+        let endpoints = module
+            .base
+            .as_deref()
+            .map_or_else(Endpoints::default, Endpoints::new);
+        (endpoints, detail.or(module.detail).unwrap_or_default())
+    }
+
+    #[derive(Subcommand)]
     pub(super) enum SubCommand {
-        Id { id: u64 },
-        Search { query: String, limit: usize },
+        Id {
+            #[arg(required_unless_present = "stdin")]
+            id: Option<u64>,
+            /// How much to collect: `minimal`, `default` or `full`.
+            #[arg(long)]
+            detail: Option<Detail>,
+            /// Look up each item ID on stdin, one per line, writing each product as soon as
+            /// it's found.
+            #[arg(long, conflicts_with = "id")]
+            stdin: bool,
+            /// Output the product with where and when it was collected, and the fields that
+            /// couldn't be found on its page (`parse_report`).
+            #[arg(long, conflicts_with = "stdin")]
+            report: bool,
+        },
+        Search {
+            query: String,
+            limit: usize,
+            /// How much to collect about each product: `minimal`, `default` or `full`.
+            #[arg(long)]
+            detail: Option<Detail>,
+            /// Save progress to this file, and resume from it if it exists. It's removed once
+            /// the search is finished.
+            #[arg(long)]
+            checkpoint: Option<PathBuf>,
+            /// Also output listings that eBay repeats on later results pages.
+            #[arg(long)]
+            include_duplicates: bool,
+            /// Leave out sponsored listings.
+            #[arg(long)]
+            skip_sponsored: bool,
+        },
+        /// List search results page by page, as shown in the results, without visiting each
+        /// product.
+        Pages { query: String, pages: usize },
+        /// List a store's active listings, as shown in its item list, given the seller's
+        /// username.
+        Store { name: String, limit: usize },
+        /// List a category's active listings, as shown in its results, given the category ID
+        /// (e.g. from a product's breadcrumbs).
+        Category { id: u64, limit: usize },
+        /// Summarize what a query's listings actually sold for (count, min/median/mean/max
+        /// and a histogram of prices), from the sold listings of the last `days` days.
+        PriceGuide {
+            query: String,
+            /// eBay keeps about 90 days of sold listings.
+            #[arg(long, default_value_t = 90)]
+            days: i64,
+        },
+        /// List the purchases of the account in the `[ebay]` credentials (`username` and
+        /// `password`), newest first. The session is kept in the `ebay` cookie jar.
+        Purchases { limit: usize },
+        /// Find other offers of the same product, using the UPC/EAN in its item specifics.
+        Compare { id: u64 },
+        /// Look up the book a listing is for on Open Library, using the ISBN in its item
+        /// specifics.
+        Book { id: u64 },
     }
 
     run_impl_enum!(SubCommand, self, ser, {
         match self {
-            Self::Id { id } => {
+            Self::Id {
+                detail,
+                stdin: true,
+                ..
+            } => {
+                let (endpoints, detail) = settings(*detail);
+                let (mut keys, mut report) = (Keys::stdin(), Report::default());
+                let mut client = Default::default();
+                while let Some(key) = keys.next().await? {
+                    let product = match key.parse() {
+                        Ok(id) => Product::by_id_with(&endpoints, &mut client, id, detail).await,
+                        Err(_) => Err(anyhow::anyhow!("`{}` is not an item ID", key)),
+                    };
+                    report.record(&key, product).await?;
+                }
+                report.finish(ser)?;
+            }
+            Self::Id {
+                id, detail, report, ..
+            } => {
+                let id = id.context("no item ID given")?;
+                let (endpoints, detail) = settings(*detail);
+                let product =
+                    Product::by_id_enveloped_with(&endpoints, &mut Default::default(), id, detail)
+                        .await?;
+                if *report {
+                    erased_serde::serialize(&product, ser)?;
+                } else {
+                    erased_serde::serialize(&product.data, ser)?;
+                }
+            }
+            Self::Search {
+                query,
+                limit,
+                detail,
+                checkpoint: None,
+                include_duplicates,
+                skip_sponsored,
+            } => {
+                let (endpoints, detail) = settings(*detail);
+                let options = SearchOptions {
+                    include_duplicates: *include_duplicates,
+                    skip_sponsored: *skip_sponsored,
+                    ..Default::default()
+                };
+                output::write_stream(
+                    Product::search_with_options(&endpoints, query, detail, options)
+                        .filter_map(|r| async move { r.ok() }),
+                    *limit,
+                    "eBay listings",
+                    ser,
+                )
+                .await?;
+            }
+            Self::Search {
+                query,
+                limit,
+                detail,
+                checkpoint: Some(path),
+                include_duplicates,
+                skip_sponsored,
+            } => {
+                let (endpoints, detail) = settings(*detail);
+                let options = SearchOptions {
+                    include_duplicates: *include_duplicates,
+                    skip_sponsored: *skip_sponsored,
+                    ..Default::default()
+                };
+                let mut checkpoint = SearchCheckpoint::resume(path, query)?;
+
+                let bar = progress::bar(*limit as u64, "eBay listings");
+                bar.set_position(checkpoint.results.len() as u64);
+                let mut products =
+                    Product::search_resume_with(&endpoints, query, detail, options, &checkpoint)
+                        .boxed();
+                while checkpoint.results.len() < *limit {
+                    match products.next().await {
+                        Some(Ok((page, product))) => {
+                            /* saved once a page is done, rather than after every product */
+                            if page != checkpoint.page {
+                                checkpoint.save(path)?;
+                            }
+                            checkpoint.record(page, product.id, product);
+                            bar.inc(1);
+                        }
+                        Some(Err(e)) if e.downcast_ref::<SearchInterrupted>().is_some() => {
+                            bar.finish_and_clear();
+                            checkpoint.save(path)?;
+                            return Err(e.context(format!(
+                                "run the search again with --checkpoint {} to resume it",
+                                path.display()
+                            )));
+                        }
+                        /* products that failed are left out */
+                        Some(Err(_)) => {}
+                        None => break,
+                    }
+                }
+                bar.finish_and_clear();
+
+                /* the search is done, so the checkpoint isn't needed */
+                erased_serde::serialize(&checkpoint.results, ser)?;
+                if path.exists() {
+                    std::fs::remove_file(path).with_context(|| {
+                        format!("could not remove checkpoint {}", path.display())
+                    })?;
+                }
+            }
+            Self::Pages { query, pages } => {
+                let (endpoints, _) = settings(None);
+                output::write_stream(
+                    Product::search_pages_with(&endpoints, query)
+                        .filter_map(|r| async move { r.ok() }),
+                    *pages,
+                    "eBay results pages",
+                    ser,
+                )
+                .await?;
+            }
+            Self::Store { name, limit } => {
+                let (endpoints, _) = settings(None);
+                output::write_stream(
+                    Store::listings_with(&endpoints, Default::default(), name)
+                        .filter_map(|r| async move { r.ok() }),
+                    *limit,
+                    "eBay store listings",
+                    ser,
+                )
+                .await?;
+            }
+            Self::Category { id, limit } => {
+                let (endpoints, _) = settings(None);
+                output::write_stream(
+                    Category::browse_with(&endpoints, Default::default(), *id)
+                        .filter_map(|r| async move { r.ok() }),
+                    *limit,
+                    "eBay category listings",
+                    ser,
+                )
+                .await?;
+            }
+            Self::PriceGuide { query, days } => {
+                let (endpoints, _) = settings(None);
+                let guide = PriceGuide::for_query_with(
+                    &endpoints,
+                    Default::default(),
+                    query,
+                    datacollect::chrono::Duration::days(*days),
+                )
+                .await?;
+                erased_serde::serialize(&guide, ser)?;
+            }
+            Self::Purchases { limit } => {
+                let (endpoints, _) = settings(None);
+                let credentials = Credentials::load()?;
+                let flow = PurchaseHistory::login_flow_with(&endpoints);
+                auth::check_credentials("ebay", &flow, &credentials)?;
+                let client = Client::<true>::default()
+                    .with_cookie_jar(config::get().cookie_jars().jar("ebay"))
+                    .with_session(Session::new("ebay", flow, credentials));
+                /* unlike listings, a failed page is reported, since it's often a failed login */
+                let purchases = PurchaseHistory::purchases_with(&endpoints, client)
+                    .take(*limit)
+                    .collect::<Vec<_>>()
+                    .await
+                    .into_iter()
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                erased_serde::serialize(&purchases, ser)?;
+            }
+            Self::Compare { id } => {
+                let (endpoints, _) = settings(None);
+                let product =
+                    Product::by_id_with(&endpoints, &mut Default::default(), *id, Detail::Default)
+                        .await?;
+                let sources: Vec<Box<dyn datacollect::enrichment::RetailSource>> =
+                    vec![Box::new(datacollect::enrichment::EbaySource::default())];
                 erased_serde::serialize(
-                    &datacollect::modules::ebay::Product::by_id(&mut Default::default(), *id)
-                        .await?,
+                    &datacollect::enrichment::ProductComparison::for_ebay_product(
+                        product, &sources,
+                    )
+                    .await?,
                     ser,
                 )?;
             }
-            Self::Search { query, limit } => {
+            Self::Book { id } => {
+                let (endpoints, _) = settings(None);
+                let product =
+                    Product::by_id_with(&endpoints, &mut Default::default(), *id, Detail::Default)
+                        .await?;
                 erased_serde::serialize(
-                    &datacollect::modules::ebay::Product::search(query)
-                        .filter_map(|r| async move { r.ok() })
-                        .take(*limit)
-                        .collect::<Vec<_>>()
-                        .await,
+                    &datacollect::enrichment::BookListing::for_ebay_product(
+                        product,
+                        &mut Default::default(),
+                    )
+                    .await?,
                     ser,
                 )?;
             }
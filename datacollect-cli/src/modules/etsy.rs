@@ -0,0 +1,71 @@
+use clap::{Args, Subcommand};
+
+use crate::{run_impl_enum, run_impl_struct};
+
+#[derive(Args)]
+pub struct Etsy {
+    #[command(subcommand)]
+    query_type: QueryType,
+}
+
+run_impl_struct!(Etsy, query_type);
+
+#[derive(Subcommand)]
+enum QueryType {
+    #[command(subcommand)]
+    Listing(listing::SubCommand),
+}
+
+run_impl_enum!(QueryType, self, ser, {
+    match self {
+        Self::Listing(l) => l.run(ser).await?,
+    }
+});
+
+mod listing {
+    use crate::{output, run_impl_enum};
+    use clap::Subcommand;
+    use datacollect::stream::StreamExt;
+
+    #[derive(Subcommand)]
+    pub(super) enum SubCommand {
+        Id {
+            id: u64,
+            /// Output the listing with where and when it was collected, and the fields that
+            /// couldn't be found on its page (`parse_report`).
+            #[arg(long)]
+            report: bool,
+        },
+        Search {
+            query: String,
+            limit: usize,
+        },
+    }
+
+    run_impl_enum!(SubCommand, self, ser, {
+        match self {
+            Self::Id { id, report } => {
+                let listing = datacollect::modules::etsy::Listing::by_id_enveloped(
+                    &mut Default::default(),
+                    *id,
+                )
+                .await?;
+                if *report {
+                    erased_serde::serialize(&listing, ser)?;
+                } else {
+                    erased_serde::serialize(&listing.data, ser)?;
+                }
+            }
+            Self::Search { query, limit } => {
+                output::write_stream(
+                    datacollect::modules::etsy::Listing::search(query)
+                        .filter_map(|r| async move { r.ok() }),
+                    *limit,
+                    "Etsy listings",
+                    ser,
+                )
+                .await?;
+            }
+        }
+    });
+}
@@ -0,0 +1,66 @@
+use clap::{Args, Subcommand};
+
+use crate::{run_impl_enum, run_impl_struct};
+
+#[derive(Args)]
+pub struct Geekbench {
+    #[command(subcommand)]
+    data_type: DataType,
+}
+
+run_impl_struct!(Geekbench, data_type);
+
+#[derive(Subcommand)]
+enum DataType {
+    #[command(subcommand)]
+    Cpu(cpu::SubCommand),
+}
+
+run_impl_enum!(DataType, self, ser, {
+    match self {
+        Self::Cpu(cpu) => cpu.run(ser).await?,
+    }
+});
+
+mod cpu {
+    use crate::run_impl_enum;
+    use clap::Subcommand;
+
+    #[derive(Subcommand)]
+    pub(super) enum SubCommand {
+        Id {
+            id: u64,
+        },
+        Search {
+            query: String,
+            #[arg(long, default_value = "1")]
+            page: u32,
+        },
+    }
+
+    run_impl_enum!(SubCommand, self, ser, {
+        match self {
+            Self::Id { id } => {
+                erased_serde::serialize(
+                    &datacollect::modules::geekbench::BenchmarkResult::by_id(
+                        &mut Default::default(),
+                        *id,
+                    )
+                    .await?,
+                    ser,
+                )?;
+            }
+            Self::Search { query, page } => {
+                erased_serde::serialize(
+                    &datacollect::modules::geekbench::BenchmarkResult::search(
+                        &mut Default::default(),
+                        query,
+                        *page,
+                    )
+                    .await?,
+                    ser,
+                )?;
+            }
+        }
+    });
+}
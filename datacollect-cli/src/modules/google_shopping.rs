@@ -0,0 +1,24 @@
+use clap::Subcommand;
+use datacollect::modules::google_shopping::Offer;
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum GoogleShopping {
+    /// The offers on the first page of Google Shopping results.
+    Search { query: String },
+    /// The lowest and highest price among the offers for a search.
+    Spread { query: String },
+}
+
+run_impl_enum!(GoogleShopping, self, ser, {
+    match self {
+        Self::Search { query } => {
+            erased_serde::serialize(&Offer::search(&mut Default::default(), query).await?, ser)?;
+        }
+        Self::Spread { query } => {
+            let offers = Offer::search(&mut Default::default(), query).await?;
+            erased_serde::serialize(&Offer::spread(&offers), ser)?;
+        }
+    }
+});
@@ -1,3 +1,25 @@
+pub mod bestbuy;
+pub mod collect;
+pub mod craigslist;
+pub mod credentials;
+pub mod daemon;
+pub mod diff;
+pub mod domains;
 pub mod ebay;
+pub mod etsy;
+pub mod geekbench;
+pub mod google_shopping;
+pub mod netprobe;
+pub mod openlibrary;
 pub mod passmark;
+pub mod pcpartpicker;
+pub mod query;
 pub mod rdap;
+pub mod schema;
+pub mod scrape;
+pub mod stocks;
+pub mod techpowerup;
+pub mod track;
+pub mod userbenchmark;
+pub mod weather;
+pub mod wikidata;
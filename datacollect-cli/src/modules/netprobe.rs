@@ -0,0 +1,36 @@
+use clap::Args;
+use datacollect::{
+    common::Client,
+    modules::netprobe::{measure_all, Probe},
+};
+
+use crate::{progress, run_impl_enum};
+
+/// Measure the latency and download speed of some URLs, to tell connection problems from
+/// site problems.
+#[derive(Args)]
+pub struct Netprobe {
+    /// The URLs to measure. By default, a small page, eBay, and a 10 MB download.
+    urls: Vec<String>,
+    /// Stop downloading each URL after this many bytes.
+    #[arg(long)]
+    max_bytes: Option<u64>,
+}
+
+run_impl_enum!(Netprobe, self, ser, {
+    let mut probes = if self.urls.is_empty() {
+        Probe::defaults()
+    } else {
+        self.urls.iter().map(|url| Probe::new(url)).collect()
+    };
+    if let Some(max_bytes) = self.max_bytes {
+        for probe in &mut probes {
+            probe.max_bytes = Some(max_bytes);
+        }
+    }
+
+    let spinner = progress::spinner("measuring");
+    let measurements = measure_all(&Client::<false>::default(), &probes).await;
+    spinner.finish_and_clear();
+    erased_serde::serialize(&measurements, ser)?;
+});
@@ -0,0 +1,19 @@
+use clap::Subcommand;
+use datacollect::modules::openlibrary::Book;
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum Openlibrary {
+    /// A book's title, authors, publish date and cover, by its ISBN-10 or ISBN-13. Outputs
+    /// `null` if Open Library doesn't know the book.
+    Isbn { isbn: String },
+}
+
+run_impl_enum!(Openlibrary, self, ser, {
+    match self {
+        Self::Isbn { isbn } => {
+            erased_serde::serialize(&Book::by_isbn(&mut Default::default(), isbn).await?, ser)?;
+        }
+    }
+});
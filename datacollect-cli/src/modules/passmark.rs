@@ -1,16 +1,18 @@
-use crate::{run_impl_enum, run_impl_struct};
-use structopt::StructOpt;
+use crate::{config, run_impl_enum, run_impl_struct};
+use clap::{Args, Subcommand};
+use datacollect::common::Client;
 
-#[derive(StructOpt)]
+#[derive(Args)]
 pub struct Passmark {
-    #[structopt(subcommand)]
+    #[command(subcommand)]
     data_type: DataType,
 }
 
 run_impl_struct!(Passmark, data_type);
 
-#[derive(StructOpt)]
+#[derive(Subcommand)]
 enum DataType {
+    #[command(subcommand)]
     Cpu(cpu::SubCommand),
 }
 
@@ -20,23 +22,226 @@ run_impl_enum!(DataType, self, ser, {
     }
 });
 
+/// A client with the Passmark cookie jar, so that the session cookie the mega list needs
+/// is kept between runs.
+pub fn client() -> Client<true> {
+    Client::<true>::default().with_cookie_jar(config::get().cookie_jars().jar("passmark"))
+}
+
 mod cpu {
-    use crate::run_impl_enum;
-    use structopt::StructOpt;
+    use super::client;
+    use crate::{progress, run_impl_enum};
+    use anyhow::bail;
+    use clap::{Args, Subcommand};
+    use datacollect::{
+        chrono::NaiveDate,
+        common::{Client, Currency, Money},
+        modules::{
+            ebay::Product,
+            passmark::{CPUMegaList, ParseFailure, Query, Sort},
+            techpowerup::CPUSpecs,
+            userbenchmark::{Category, Part},
+        },
+        schemas::computing::{merge, normalize_name, MergeStrategy, CPU},
+        stream::StreamExt,
+    };
 
-    #[derive(StructOpt)]
+    #[derive(Subcommand)]
     pub(super) enum SubCommand {
-        MegaList,
+        MegaList {
+            /// Get the list as it was on this date (e.g. `2016-01-31`), from the Wayback
+            /// Machine.
+            #[arg(long)]
+            date: Option<NaiveDate>,
+            /// Fail if any CPU in the list couldn't be read, instead of leaving it out.
+            #[arg(long)]
+            strict: bool,
+            #[command(flatten)]
+            query: QueryArgs,
+        },
+        /// CPU's within a budget, best value for money (CPU mark per dollar) first.
+        BestValue {
+            /// The most to spend on a CPU (e.g. `300`).
+            #[arg(long)]
+            budget: Money,
+            /// Price each CPU by the median of its eBay listings, instead of Passmark's price.
+            /// This costs a search per CPU, so only the CPU's left after the filters are
+            /// looked up: by default the 20 with the highest CPU mark.
+            #[arg(long)]
+            ebay: bool,
+            #[command(flatten)]
+            query: QueryArgs,
+        },
+        /// The mega list, merged with data from other sources into one record per CPU.
+        Merged {
+            /// Look up these CPU's in TechPowerUp's database and merge in their specifications.
+            #[arg(long)]
+            techpowerup: Vec<String>,
+            /// Merge in the scores from UserBenchmark's list of CPU's.
+            #[arg(long)]
+            userbenchmark: bool,
+            /// Which source wins when two disagree: `first` (Passmark) or `last`.
+            #[arg(long, default_value = "first")]
+            strategy: MergeStrategy,
+        },
+    }
+
+    /* filters for the mega list, applied after downloading it */
+    #[derive(Args)]
+    pub(super) struct QueryArgs {
+        /// Only CPU's for this socket (e.g. `AM4`).
+        #[arg(long)]
+        socket: Option<String>,
+        /// Only CPU's in this category (e.g. `Desktop`).
+        #[arg(long)]
+        category: Option<String>,
+        #[arg(long)]
+        min_cpumark: Option<u32>,
+        /// In watts.
+        #[arg(long)]
+        max_tdp: Option<f64>,
+        #[arg(long)]
+        max_price: Option<Money>,
+        /// Sort by `cpumark`, `thread`, `price`, `tdp` or `cores`, optionally followed by
+        /// `:asc` or `:desc` (e.g. `cpumark:desc`).
+        #[arg(long)]
+        sort: Option<Sort>,
+        /// Keep only this many CPU's, after sorting.
+        #[arg(long)]
+        top: Option<usize>,
+    }
+
+    impl From<&QueryArgs> for Query {
+        fn from(args: &QueryArgs) -> Self {
+            Self {
+                socket: args.socket.clone(),
+                category: args.category.clone(),
+                min_cpumark: args.min_cpumark,
+                max_tdp: args.max_tdp,
+                max_price: args.max_price.clone(),
+                sort: args.sort,
+                top: args.top,
+            }
+        }
+    }
+
+    /// Warn about the CPU's left out of the mega list, or fail if the list should be `strict`.
+    fn report_failures(failures: &[ParseFailure], strict: bool) -> anyhow::Result<()> {
+        if strict {
+            if let Some(failure) = failures.first() {
+                bail!(
+                    "could not read {} of the CPU's in the mega list, e.g. CPU {}: {}: {}",
+                    failures.len(),
+                    failure.index,
+                    failure.path,
+                    failure.message
+                );
+            }
+        }
+        for failure in failures {
+            eprintln!(
+                "warning: left out CPU {}: {}: {}",
+                failure.index, failure.path, failure.message
+            );
+        }
+        Ok(())
     }
 
     run_impl_enum!(SubCommand, self, ser, {
         match self {
-            Self::MegaList => {
-                erased_serde::serialize(
-                    &datacollect::modules::passmark::CPUMegaList::get(&mut Default::default())
-                        .await?,
-                    ser,
-                )?;
+            Self::MegaList {
+                date: Some(date),
+                strict,
+                query,
+            } => {
+                let spinner = progress::spinner("downloading an archived Passmark mega list");
+                let list =
+                    CPUMegaList::get_historical_checked(&mut Client::<false>::default(), *date)
+                        .await;
+                spinner.finish_and_clear();
+                let (mut list, failures) = list?;
+                report_failures(&failures, *strict)?;
+                list.item = list.item.query(&query.into());
+                erased_serde::serialize(&list, ser)?;
+            }
+            Self::MegaList {
+                date: None,
+                strict,
+                query,
+            } => {
+                let spinner = progress::spinner("downloading the Passmark mega list");
+                let list = CPUMegaList::get_checked(&mut client()).await;
+                spinner.finish_and_clear();
+                let (list, failures) = list?;
+                report_failures(&failures, *strict)?;
+                erased_serde::serialize(&list.query(&query.into()), ser)?;
+            }
+            Self::BestValue {
+                budget,
+                ebay,
+                query,
+            } => {
+                if budget.currency() != Currency::USD {
+                    bail!("the budget has to be in dollars, to compare CPU marks per dollar");
+                }
+                let spinner = progress::spinner("downloading the Passmark mega list");
+                let list = CPUMegaList::get(&mut client()).await;
+                spinner.finish_and_clear();
+
+                let mut query = Query::from(query);
+                if *ebay && query.top.is_none() {
+                    query.sort = query.sort.or_else(|| "cpumark:desc".parse().ok());
+                    query.top = Some(20);
+                }
+                let mut list = list?.query(&query);
+
+                if *ebay {
+                    let bar = progress::bar(list.data.len() as u64, "eBay prices");
+                    for cpu in &mut list.data {
+                        let name = normalize_name(&cpu.name);
+                        let page = Product::search_pages(&name)
+                            .boxed()
+                            .next()
+                            .await
+                            .transpose()?;
+                        /* marks per dollar need a price in dollars */
+                        cpu.price = page
+                            .and_then(|page| page.median_price())
+                            .filter(|price| price.currency() == Currency::USD);
+                        bar.inc(1);
+                    }
+                    bar.finish_and_clear();
+                }
+
+                erased_serde::serialize(&list.best_value(budget), ser)?;
+            }
+            Self::Merged {
+                techpowerup,
+                userbenchmark,
+                strategy,
+            } => {
+                let spinner = progress::spinner("downloading the Passmark mega list");
+                let list = CPUMegaList::get(&mut client()).await;
+                spinner.finish_and_clear();
+                let passmark = list?.data.into_iter().map(CPU::from).collect();
+
+                let mut client = Default::default();
+                let mut specs = Vec::new();
+                for query in techpowerup {
+                    if let Some(result) = CPUSpecs::search(&mut client, query).await?.first() {
+                        specs.push(CPU::from(CPUSpecs::by_url(&mut client, &result.url).await?));
+                    }
+                }
+
+                let mut sources = vec![passmark, specs];
+                if *userbenchmark {
+                    let spinner = progress::spinner("downloading the UserBenchmark list");
+                    let parts = Part::list(&mut client, Category::CPU).await;
+                    spinner.finish_and_clear();
+                    sources.push(parts?.into_iter().map(CPU::from).collect());
+                }
+
+                erased_serde::serialize(&merge(sources, *strategy), ser)?;
             }
         }
     });
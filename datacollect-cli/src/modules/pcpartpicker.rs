@@ -0,0 +1,49 @@
+use clap::{Args, Subcommand};
+
+use crate::{run_impl_enum, run_impl_struct};
+
+#[derive(Args)]
+pub struct Pcpartpicker {
+    #[command(subcommand)]
+    data_type: DataType,
+}
+
+run_impl_struct!(Pcpartpicker, data_type);
+
+#[derive(Subcommand)]
+enum DataType {
+    #[command(subcommand)]
+    Motherboard(motherboard::SubCommand),
+}
+
+run_impl_enum!(DataType, self, ser, {
+    match self {
+        Self::Motherboard(motherboard) => motherboard.run(ser).await?,
+    }
+});
+
+mod motherboard {
+    use crate::run_impl_enum;
+    use clap::Subcommand;
+
+    #[derive(Subcommand)]
+    pub(super) enum SubCommand {
+        /// The specifications of a motherboard, from its product page.
+        Specs { url: String },
+    }
+
+    run_impl_enum!(SubCommand, self, ser, {
+        match self {
+            Self::Specs { url } => {
+                erased_serde::serialize(
+                    &datacollect::modules::pcpartpicker::MotherboardSpecs::by_url(
+                        &mut Default::default(),
+                        url,
+                    )
+                    .await?,
+                    ser,
+                )?;
+            }
+        }
+    });
+}
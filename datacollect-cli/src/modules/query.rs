@@ -0,0 +1,73 @@
+use anyhow::Context;
+use clap::Args;
+use datacollect::{
+    common::{Client, Detail},
+    modules::{
+        ebay::Product, etsy::Listing, geekbench::BenchmarkResult, passmark::CPUMegaList,
+        techpowerup::CPUSpecs, wikidata::Entity, Target,
+    },
+};
+
+use crate::{
+    common::Run,
+    modules::{ebay, passmark, rdap},
+    progress,
+};
+
+/// Look up whatever a link points to, with the module it's for: an eBay or Etsy listing, a
+/// Geekbench result, a Passmark or TechPowerUp CPU, or a Wikidata entity. Any other link (or
+/// a bare domain) is looked up in RDAP.
+#[derive(Args)]
+pub struct Query {
+    url: String,
+    /// How much to collect, for modules that have a choice: `minimal`, `default` or `full`.
+    #[arg(long)]
+    detail: Option<Detail>,
+}
+
+#[async_trait::async_trait]
+impl Run for Query {
+    async fn run(&self, ser: &mut (dyn erased_serde::Serializer + Send)) -> anyhow::Result<()> {
+        let mut client = Client::<false>::default();
+        match Target::from_url(&self.url)? {
+            Target::EbayProduct(id) => {
+                let (endpoints, detail) = ebay::product::settings(self.detail);
+                erased_serde::serialize(
+                    &Product::by_id_with(&endpoints, &mut client, id, detail).await?,
+                    ser,
+                )?;
+            }
+            Target::EtsyListing(id) => {
+                erased_serde::serialize(&Listing::by_id(&mut client, id).await?, ser)?;
+            }
+            Target::GeekbenchResult(id) => {
+                erased_serde::serialize(&BenchmarkResult::by_id(&mut client, id).await?, ser)?;
+            }
+            Target::PassmarkCPU(id) => {
+                let spinner = progress::spinner("downloading the Passmark mega list");
+                let list = CPUMegaList::get(&mut passmark::client()).await;
+                spinner.finish_and_clear();
+                let cpu = list?
+                    .data
+                    .into_iter()
+                    .find(|cpu| cpu.id == id)
+                    .with_context(|| format!("CPU {} is not in the Passmark mega list", id))?;
+                erased_serde::serialize(&cpu, ser)?;
+            }
+            Target::TechpowerupCPU(url) => {
+                erased_serde::serialize(&CPUSpecs::by_url(&mut client, &url).await?, ser)?;
+            }
+            Target::WikidataEntity(id) => {
+                erased_serde::serialize(&Entity::by_id(&mut client, &id).await?, ser)?;
+            }
+            Target::Domain(name) => {
+                erased_serde::serialize(
+                    &rdap::domain::get(&mut client, &name, self.detail).await?,
+                    ser,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,51 +1,110 @@
-use structopt::StructOpt;
+use clap::{Args, Subcommand};
 
 use crate::{run_impl_enum, run_impl_struct};
 
-#[derive(StructOpt)]
+#[derive(Args)]
 pub struct Rdap {
-    #[structopt(subcommand)]
+    #[command(subcommand)]
     query_type: QueryType,
 }
 
 run_impl_struct!(Rdap, query_type);
 
-#[derive(StructOpt)]
+#[derive(Subcommand)]
 enum QueryType {
+    #[command(subcommand)]
     Domain(domain::SubCommand),
+    #[command(subcommand)]
+    Entity(entity::SubCommand),
 }
 
 run_impl_enum!(QueryType, self, ser, {
     match self {
         Self::Domain(d) => d.run(ser).await?,
+        Self::Entity(e) => e.run(ser).await?,
     }
 });
 
-mod domain {
-    use crate::run_impl_enum;
-    use datacollect::chrono::Utc;
-    use structopt::StructOpt;
+pub(crate) mod domain {
+    use crate::{
+        batch::{Keys, Report},
+        config, run_impl_enum,
+    };
+    use anyhow::Context;
+    use clap::Subcommand;
+    use datacollect::{
+        chrono::Utc,
+        common::{Client, Detail},
+        modules::rdap::{DomainRecord, Endpoints},
+    };
 
-    #[derive(StructOpt)]
+    /// Look a domain up, using the `[modules.rdap]` settings from the config file.
+    pub(crate) async fn get(
+        client: &mut Client<false>,
+        name: &str,
+        detail: Option<Detail>,
+    ) -> anyhow::Result<Option<DomainRecord>> {
+        let module = config::get().module("rdap");
+        let endpoints = module
+            .base
+            .as_deref()
+            .map_or_else(Endpoints::default, Endpoints::new);
+        DomainRecord::get_with(
+            &endpoints,
+            client,
+            name,
+            detail.or(module.detail).unwrap_or_default(),
+        )
+        .await
+    }
+
+    #[derive(Subcommand)]
     pub(super) enum SubCommand {
-        Json { name: String },
-        IsRegistered { name: String },
-        IsLocked { name: String },
-        CanPurchase { name: String },
+        Json {
+            #[arg(required_unless_present = "stdin")]
+            name: Option<String>,
+            /// How much to collect: `minimal`, `default` or `full`.
+            #[arg(long)]
+            detail: Option<Detail>,
+            /// Look up each domain named on stdin, one per line, writing each record as soon
+            /// as it's found.
+            #[arg(long, conflicts_with = "name")]
+            stdin: bool,
+        },
+        IsRegistered {
+            name: String,
+        },
+        IsLocked {
+            name: String,
+        },
+        CanPurchase {
+            name: String,
+        },
     }
 
     run_impl_enum!(SubCommand, self, ser, {
         match self {
-            Self::Json { name } => {
-                erased_serde::serialize(
-                    &datacollect::modules::rdap::DomainRecord::get(&mut Default::default(), name)
-                        .await?,
-                    ser,
-                )?;
+            Self::Json {
+                detail,
+                stdin: true,
+                ..
+            } => {
+                let (mut keys, mut report) = (Keys::stdin(), Report::default());
+                let mut client = Default::default();
+                while let Some(name) = keys.next().await? {
+                    report
+                        .record(&name, get(&mut client, &name, *detail).await)
+                        .await?;
+                }
+                report.finish(ser)?;
+            }
+            Self::Json { name, detail, .. } => {
+                let name = name.as_ref().context("no domain given")?;
+                erased_serde::serialize(&get(&mut Default::default(), name, *detail).await?, ser)?;
             }
             Self::IsRegistered { name } => {
                 erased_serde::serialize(
-                    &datacollect::modules::rdap::DomainRecord::get(&mut Default::default(), name)
+                    &get(&mut Default::default(), name, Some(Detail::Minimal))
                         .await?
                         .map(|record| record.is_registered_at(&Utc::now()))
                         .unwrap_or(false),
@@ -54,7 +113,7 @@ mod domain {
             }
             Self::IsLocked { name } => {
                 erased_serde::serialize(
-                    &datacollect::modules::rdap::DomainRecord::get(&mut Default::default(), name)
+                    &get(&mut Default::default(), name, Some(Detail::Minimal))
                         .await?
                         .map(|record| record.is_locked_at(&Utc::now()))
                         .unwrap_or(false),
@@ -63,7 +122,7 @@ mod domain {
             }
             Self::CanPurchase { name } => {
                 erased_serde::serialize(
-                    &datacollect::modules::rdap::DomainRecord::get(&mut Default::default(), name)
+                    &get(&mut Default::default(), name, Some(Detail::Minimal))
                         .await?
                         .map(|record| record.is_buyable_at(&Utc::now()))
                         .unwrap_or(true),
@@ -73,3 +132,76 @@ mod domain {
         }
     });
 }
+
+mod entity {
+    use crate::{config, run_impl_enum};
+    use anyhow::Context;
+    use clap::Subcommand;
+    use datacollect::modules::rdap::{Endpoints, Entity};
+
+    /// The registry to ask: the one given, or else `base` in `[modules.rdap]`.
+    fn registry(registry: &Option<String>) -> anyhow::Result<Endpoints> {
+        registry
+            .clone()
+            .or_else(|| config::get().module("rdap").base.clone())
+            .map(|base| Endpoints { base })
+            .context("entities are looked up at a registry; pass --registry")
+    }
+
+    #[derive(Subcommand)]
+    pub(super) enum SubCommand {
+        /// Get an entity's record by its handle.
+        Json {
+            handle: String,
+            /// The registry's RDAP base URL, e.g. `https://rdap.verisign.com/com/v1`.
+            #[arg(long)]
+            registry: Option<String>,
+        },
+        /// Find entities by name; a trailing `*` matches any suffix.
+        Search {
+            name: String,
+            /// A registry to search; can be given more than once.
+            #[arg(long = "registry", required = true)]
+            registries: Vec<String>,
+        },
+        /// List the domains an entity is related to, e.g. a registrant's other domains.
+        Domains {
+            handle: String,
+            #[arg(long)]
+            registry: Option<String>,
+        },
+    }
+
+    run_impl_enum!(SubCommand, self, ser, {
+        match self {
+            Self::Json {
+                handle,
+                registry: r,
+            } => {
+                let entity = Entity::get(&mut Default::default(), handle, &registry(r)?).await?;
+                erased_serde::serialize(&entity, ser)?;
+            }
+            Self::Search { name, registries } => {
+                let registries = registries
+                    .iter()
+                    .map(|base| Endpoints { base: base.clone() })
+                    .collect::<Vec<_>>();
+                erased_serde::serialize(
+                    &Entity::search(&mut Default::default(), name, &registries).await?,
+                    ser,
+                )?;
+            }
+            Self::Domains {
+                handle,
+                registry: r,
+            } => {
+                let registry = registry(r)?;
+                let mut client = Default::default();
+                let entity = Entity::get(&mut client, handle, &registry)
+                    .await?
+                    .with_context(|| format!("{} has no entity {}", registry.base, handle))?;
+                erased_serde::serialize(&entity.domains(&mut client, &registry).await?, ser)?;
+            }
+        }
+    });
+}
@@ -0,0 +1,23 @@
+use anyhow::anyhow;
+use clap::Args;
+use datacollect::schemas::{json_schema, MODULES};
+
+use crate::run_impl_enum;
+
+/// Print the JSON Schema of the records a module outputs.
+#[derive(Args)]
+pub struct Schema {
+    /// The module's name, e.g. `ebay` or `passmark`.
+    module: String,
+}
+
+run_impl_enum!(Schema, self, ser, {
+    let schema = json_schema(&self.module).ok_or_else(|| {
+        anyhow!(
+            "no schema for module `{}` (expected one of: {})",
+            self.module,
+            MODULES.join(", ")
+        )
+    })?;
+    erased_serde::serialize(&schema, ser)?;
+});
@@ -0,0 +1,38 @@
+use clap::Subcommand;
+use datacollect::{common::Client, schema_org::Items};
+use serde_json::json;
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum Scrape {
+    /// The schema.org items on any page, from both its microdata and its JSON-LD.
+    Url {
+        url: String,
+        /// Only output items of this type (e.g. `https://schema.org/Product`, or just
+        /// `Product`), including ones nested in other items.
+        #[arg(long = "type")]
+        item_type: Option<String>,
+    },
+    /// The microdata items on any page, in the JSON form the HTML spec gives them
+    /// (`{"items": [...]}`).
+    Microdata {
+        url: String,
+        /// Only output items of this type, including ones nested in other items.
+        #[arg(long = "type")]
+        item_type: Option<String>,
+    },
+}
+
+run_impl_enum!(Scrape, self, ser, {
+    match self {
+        Self::Url { url, item_type } => {
+            let items = Items::get(&Client::<false>::default(), url, item_type.as_deref()).await?;
+            erased_serde::serialize(&items, ser)?;
+        }
+        Self::Microdata { url, item_type } => {
+            let items = Items::get(&Client::<false>::default(), url, item_type.as_deref()).await?;
+            erased_serde::serialize(&json!({ "items": items.microdata }), ser)?;
+        }
+    }
+});
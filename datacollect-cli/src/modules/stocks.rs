@@ -0,0 +1,27 @@
+use clap::Subcommand;
+use datacollect::modules::stocks::{Quote, Range};
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum Stocks {
+    /// The latest quote for a US stock (e.g. `AAPL`). Outputs `null` if the symbol is unknown.
+    Quote { symbol: String },
+    /// The daily prices of a US stock over a range of days, e.g. `2021-01-01..2021-12-31`, or
+    /// `2021-01-01..` for up to today.
+    History { symbol: String, range: Range },
+}
+
+run_impl_enum!(Stocks, self, ser, {
+    match self {
+        Self::Quote { symbol } => {
+            erased_serde::serialize(&Quote::get(&mut Default::default(), symbol).await?, ser)?;
+        }
+        Self::History { symbol, range } => {
+            erased_serde::serialize(
+                &Quote::history(&mut Default::default(), symbol, *range).await?,
+                ser,
+            )?;
+        }
+    }
+});
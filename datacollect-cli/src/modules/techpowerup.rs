@@ -0,0 +1,59 @@
+use clap::{Args, Subcommand};
+
+use crate::{run_impl_enum, run_impl_struct};
+
+#[derive(Args)]
+pub struct Techpowerup {
+    #[command(subcommand)]
+    data_type: DataType,
+}
+
+run_impl_struct!(Techpowerup, data_type);
+
+#[derive(Subcommand)]
+enum DataType {
+    #[command(subcommand)]
+    Cpu(cpu::SubCommand),
+}
+
+run_impl_enum!(DataType, self, ser, {
+    match self {
+        Self::Cpu(cpu) => cpu.run(ser).await?,
+    }
+});
+
+mod cpu {
+    use crate::run_impl_enum;
+    use clap::Subcommand;
+
+    #[derive(Subcommand)]
+    pub(super) enum SubCommand {
+        Search { query: String },
+        Specs { url: String },
+    }
+
+    run_impl_enum!(SubCommand, self, ser, {
+        match self {
+            Self::Search { query } => {
+                erased_serde::serialize(
+                    &datacollect::modules::techpowerup::CPUSpecs::search(
+                        &mut Default::default(),
+                        query,
+                    )
+                    .await?,
+                    ser,
+                )?;
+            }
+            Self::Specs { url } => {
+                erased_serde::serialize(
+                    &datacollect::modules::techpowerup::CPUSpecs::by_url(
+                        &mut Default::default(),
+                        url,
+                    )
+                    .await?,
+                    ser,
+                )?;
+            }
+        }
+    });
+}
@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum Track {
+    /// Fetch the current price of every tracked item and report changes since the last run.
+    Run {
+        #[arg(long)]
+        config: PathBuf,
+    },
+}
+
+run_impl_enum!(Track, self, ser, {
+    match self {
+        Self::Run { config } => {
+            let config = datacollect::tracking::Config::from_file(config)?;
+            erased_serde::serialize(
+                &datacollect::tracking::run(&mut Default::default(), &config).await?,
+                ser,
+            )?;
+        }
+    }
+});
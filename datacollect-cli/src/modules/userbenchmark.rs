@@ -0,0 +1,45 @@
+use anyhow::bail;
+use clap::Subcommand;
+use datacollect::modules::userbenchmark::{Category, Part};
+
+use crate::{progress, run_impl_enum};
+
+#[derive(Subcommand)]
+pub enum Userbenchmark {
+    /// Every part in a category (`cpu`, `gpu` or `ssd`), from UserBenchmark's published lists.
+    List {
+        category: Category,
+        /// Fail if any part in the list couldn't be read, instead of leaving it out.
+        #[arg(long)]
+        strict: bool,
+    },
+}
+
+run_impl_enum!(Userbenchmark, self, ser, {
+    match self {
+        Self::List { category, strict } => {
+            let spinner = progress::spinner("downloading the UserBenchmark list");
+            let parts = Part::list_checked(&mut Default::default(), *category).await;
+            spinner.finish_and_clear();
+            let (parts, failures) = parts?;
+            if *strict {
+                if let Some(failure) = failures.first() {
+                    bail!(
+                        "could not read {} of the parts in the list, e.g. part {}: {}: {}",
+                        failures.len(),
+                        failure.index,
+                        failure.path,
+                        failure.message
+                    );
+                }
+            }
+            for failure in &failures {
+                eprintln!(
+                    "warning: left out part {}: {}: {}",
+                    failure.index, failure.path, failure.message
+                );
+            }
+            erased_serde::serialize(&parts, ser)?;
+        }
+    }
+});
@@ -0,0 +1,30 @@
+use clap::Subcommand;
+use datacollect::modules::weather::Observation;
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum Weather {
+    /// The latest observation from the weather station closest to a place in the US, from
+    /// the National Weather Service.
+    Current {
+        latitude: f64,
+        /// Negative for the western hemisphere, e.g. `-97.0892`.
+        #[arg(allow_hyphen_values = true)]
+        longitude: f64,
+    },
+}
+
+run_impl_enum!(Weather, self, ser, {
+    match self {
+        Self::Current {
+            latitude,
+            longitude,
+        } => {
+            erased_serde::serialize(
+                &Observation::current(&mut Default::default(), *latitude, *longitude).await?,
+                ser,
+            )?;
+        }
+    }
+});
@@ -0,0 +1,24 @@
+use clap::Subcommand;
+use datacollect::modules::wikidata::Entity;
+
+use crate::run_impl_enum;
+
+#[derive(Subcommand)]
+pub enum Wikidata {
+    /// An entity's labels, descriptions and claims, by its id (e.g. `Q42`). Outputs `null`
+    /// if there is no such entity.
+    Entity { id: String },
+    /// Search for entities by their English labels and aliases.
+    Search { query: String },
+}
+
+run_impl_enum!(Wikidata, self, ser, {
+    match self {
+        Self::Entity { id } => {
+            erased_serde::serialize(&Entity::by_id(&mut Default::default(), id).await?, ser)?;
+        }
+        Self::Search { query } => {
+            erased_serde::serialize(&Entity::search(&mut Default::default(), query).await?, ser)?;
+        }
+    }
+});
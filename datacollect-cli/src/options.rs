@@ -1,15 +1,150 @@
 use crate::{
-    modules::{ebay::Ebay, passmark::Passmark, rdap::Rdap},
+    module::{AnyCommand, Invocation},
+    modules::{
+        bestbuy::Bestbuy, collect::Collect, craigslist::Craigslist, credentials::Credentials,
+        daemon::Daemon, diff::Diff, domains::Domains, ebay::Ebay, etsy::Etsy, geekbench::Geekbench,
+        google_shopping::GoogleShopping, netprobe::Netprobe, openlibrary::Openlibrary,
+        passmark::Passmark, pcpartpicker::Pcpartpicker, query::Query, rdap::Rdap, schema::Schema,
+        scrape::Scrape, stocks::Stocks, techpowerup::Techpowerup, track::Track,
+        userbenchmark::Userbenchmark, weather::Weather, wikidata::Wikidata,
+    },
     run_impl_enum,
 };
-use structopt::StructOpt;
+use std::path::PathBuf;
 
-#[derive(StructOpt)]
-#[structopt(name = "datacollect-cli")]
+use clap::{ArgAction, Parser, Subcommand};
+
+use crate::{
+    config::{Config, Format},
+    errors::ErrorFormat,
+    output::Selection,
+};
+
+#[derive(Parser)]
+#[command(name = "datacollect-cli")]
+pub struct Options {
+    /// Log more to stderr: `-v` for info, `-vv` for debug, `-vvv` for everything.
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+    /// Don't log or show progress bars; only errors and output are written.
+    #[arg(short, long, conflicts_with = "verbose", global = true)]
+    pub quiet: bool,
+    /// Print the number of requests, errors and bytes downloaded per host to stderr when done.
+    #[arg(long, global = true)]
+    pub stats: bool,
+    /// How to write errors to stderr: `text`, or `json` for a JSON object with the message,
+    /// causes and kind. The exit code is 2 for network errors, 3 for parse errors, 4 if
+    /// something wasn't found, 5 for a bad command line and 1 for anything else.
+    #[arg(long, default_value = "text", global = true)]
+    pub errors: ErrorFormat,
+    /// Read settings from this file instead of `~/.config/datacollect/datacollect.toml`.
+    /// Unlike the other options, this goes before the command, since `daemon`, `collect`
+    /// and `track` have a `--config` of their own.
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    /// How to write output: `json`, `json-compact`, `yaml`, `xml` or `parquet`.
+    #[arg(long, global = true)]
+    pub format: Option<Format>,
+    /// Where to write the records in the output, instead of stdout; repeat it to write them
+    /// to several places. A file, by extension (`.jsonl`, `.csv`, `.json`, `.yaml`, `.xml` or
+    /// `.parquet`), `-` for stdout, or a table to upsert records into: a `postgres://` or
+    /// `sqlite://` URL with the table and the columns that identify a record, e.g.
+    /// `postgres://me@warehouse/prices?table=cpus&key=name` (`key` is `id` by default).
+    #[arg(long, global = true)]
+    pub output: Vec<String>,
+    /// Only write the records that have changed since the last run with the same FILE, which
+    /// keeps a hash of the last version of each record written. Fields that change on every
+    /// fetch (e.g. `fetched_at`) are ignored.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub dedup: Option<PathBuf>,
+    /// The field that says which record a record is, for `--dedup`; repeat it to try
+    /// several, in order. By default `id`, or `url` for records without one.
+    #[arg(long, global = true, value_name = "FIELD", requires = "dedup")]
+    pub dedup_key: Vec<String>,
+    /// Only output these fields, e.g. `seller.name,price`. Lists are projected item by item.
+    #[arg(long, global = true)]
+    pub select: Option<Selection>,
+    /// Wrap single-value output (e.g. `true`) in an object with the command line it answers
+    /// and when it was checked: `{"query": ..., "result": ..., "checked_at": ...}`.
+    #[arg(long, global = true)]
+    pub wrap: bool,
+    /// Cache responses in this directory.
+    #[arg(long, global = true)]
+    pub cache_dir: Option<PathBuf>,
+    /// Send all requests through this proxy, e.g. `http://127.0.0.1:3128`.
+    #[arg(long, global = true)]
+    pub proxy: Option<String>,
+    /// Leave at least this many seconds between requests to the same host.
+    #[arg(long, global = true)]
+    pub rate_limit: Option<f64>,
+    #[command(subcommand)]
+    pub command: AnyCommand,
+}
+
+impl Options {
+    /// Read the config file, and apply the command-line overrides to it.
+    ///
+    /// # Errors
+    /// Errors if the config file could not be read or parsed.
+    pub fn config(&self) -> anyhow::Result<Config> {
+        let mut config = Config::load(self.config.as_deref())?;
+        if let Some(format) = self.format {
+            config.format = Some(format);
+        }
+        if let Some(cache_dir) = &self.cache_dir {
+            config.cache_dir = Some(cache_dir.clone());
+        }
+        if let Some(proxy) = &self.proxy {
+            config.proxy = Some(proxy.clone());
+        }
+        if let Some(rate_limit) = self.rate_limit {
+            config.rate_limit.default = Some(rate_limit);
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Subcommand)]
+#[command(name = "datacollect-cli")]
 pub enum Command {
     Passmark(Passmark),
     Ebay(Ebay),
     Rdap(Rdap),
+    #[command(subcommand)]
+    Domains(Domains),
+    Geekbench(Geekbench),
+    Techpowerup(Techpowerup),
+    Pcpartpicker(Pcpartpicker),
+    #[command(subcommand)]
+    Track(Track),
+    Daemon(Daemon),
+    Collect(Collect),
+    Diff(Diff),
+    Craigslist(Craigslist),
+    Etsy(Etsy),
+    Bestbuy(Bestbuy),
+    #[command(subcommand)]
+    Credentials(Credentials),
+    Schema(Schema),
+    #[command(subcommand)]
+    Userbenchmark(Userbenchmark),
+    #[command(subcommand)]
+    GoogleShopping(GoogleShopping),
+    #[command(subcommand)]
+    Openlibrary(Openlibrary),
+    #[command(subcommand)]
+    Stocks(Stocks),
+    #[command(subcommand)]
+    Weather(Weather),
+    #[command(subcommand)]
+    Wikidata(Wikidata),
+    Netprobe(Netprobe),
+    #[command(subcommand)]
+    Scrape(Scrape),
+    Query(Query),
+    /// A [`Module`](crate::Module) from another crate; see [`AnyCommand`].
+    #[command(skip)]
+    Module(Invocation),
 }
 
 run_impl_enum!(Command, self, ser, {
@@ -17,5 +152,28 @@ run_impl_enum!(Command, self, ser, {
         Self::Passmark(p) => p.run(ser).await?,
         Self::Ebay(e) => e.run(ser).await?,
         Self::Rdap(r) => r.run(ser).await?,
+        Self::Domains(d) => d.run(ser).await?,
+        Self::Geekbench(g) => g.run(ser).await?,
+        Self::Techpowerup(t) => t.run(ser).await?,
+        Self::Pcpartpicker(p) => p.run(ser).await?,
+        Self::Track(t) => t.run(ser).await?,
+        Self::Daemon(d) => d.run(ser).await?,
+        Self::Collect(c) => c.run(ser).await?,
+        Self::Diff(d) => d.run(ser).await?,
+        Self::Craigslist(c) => c.run(ser).await?,
+        Self::Etsy(e) => e.run(ser).await?,
+        Self::Bestbuy(b) => b.run(ser).await?,
+        Self::Credentials(c) => c.run(ser).await?,
+        Self::Schema(s) => s.run(ser).await?,
+        Self::Userbenchmark(u) => u.run(ser).await?,
+        Self::GoogleShopping(g) => g.run(ser).await?,
+        Self::Openlibrary(o) => o.run(ser).await?,
+        Self::Stocks(s) => s.run(ser).await?,
+        Self::Weather(w) => w.run(ser).await?,
+        Self::Wikidata(w) => w.run(ser).await?,
+        Self::Netprobe(n) => n.run(ser).await?,
+        Self::Scrape(s) => s.run(ser).await?,
+        Self::Query(q) => q.run(ser).await?,
+        Self::Module(m) => m.run(ser).await?,
     }
 });
@@ -0,0 +1,177 @@
+use std::{
+    fs::File,
+    io::{stdout, BufWriter, IsTerminal, Write},
+    path::Path,
+};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use datacollect::sinks::{postgres::PostgresSink, sqlite::SqliteSink, CsvSink, JsonSink, Sink};
+use serde_json::{Map, Value};
+
+use super::{find, Output};
+
+/// How the records were arranged in a command's output, to put them back the same way.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Shape {
+    /// A list of records.
+    List,
+    /// A list of records in an object with a single field, e.g. Passmark's mega list,
+    /// `{"data": [...]}`.
+    Wrapped(String),
+    /// A single record (or value, e.g. `true`).
+    Single,
+}
+
+/// The records in a command's output, which is usually a list of them; see [`Shape`].
+pub fn split(output: Value) -> (Shape, Vec<Value>) {
+    match output {
+        Value::Array(records) => (Shape::List, records),
+        Value::Object(object) if object.len() == 1 && object.values().all(Value::is_array) => {
+            let Some((field, Value::Array(records))) = object.into_iter().next() else {
+                unreachable!()
+            };
+            (Shape::Wrapped(field), records)
+        }
+        output => (Shape::Single, vec![output]),
+    }
+}
+
+impl Shape {
+    /// The output that [`split`] would split into `records`.
+    pub fn join(&self, mut records: Vec<Value>) -> Value {
+        match self {
+            Self::List => Value::Array(records),
+            Self::Wrapped(field) => Value::Object(
+                std::iter::once((field.clone(), Value::Array(records))).collect::<Map<_, _>>(),
+            ),
+            Self::Single => records.pop().unwrap_or_default(),
+        }
+    }
+}
+
+/// Writes the records it's given as one document in a format.
+///
+/// A list is written an item at a time if the format [streams](Output::streams); otherwise
+/// the records are kept, and written when the sink is closed.
+struct FormatSink {
+    format: &'static dyn Output,
+    shape: Shape,
+    records: Vec<Value>,
+    /// How many records were written as they came.
+    written: usize,
+    writer: Box<dyn Write + Send>,
+}
+
+impl FormatSink {
+    fn streams(&self) -> bool {
+        self.shape == Shape::List && self.format.streams()
+    }
+}
+
+#[async_trait]
+impl Sink for FormatSink {
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        if !self.streams() {
+            self.records.push(record.clone());
+            return Ok(());
+        }
+        self.format
+            .write_item(self.written, record, &mut self.writer)?;
+        self.written += 1;
+        self.flush().await
+    }
+
+    /// Only flushes the writer: records that are kept can only be written as a whole
+    /// document.
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(self.writer.flush()?)
+    }
+
+    async fn close(&mut self) -> anyhow::Result<()> {
+        if self.streams() {
+            self.format.end_list(self.written, &mut self.writer)?;
+        } else {
+            let output = self.shape.join(std::mem::take(&mut self.records));
+            self.format.write(&output, &mut self.writer)?;
+        }
+        self.flush().await
+    }
+}
+
+/// Somewhere `--output` writes the records in a command's output to.
+pub enum Destination {
+    /// A document in a format (e.g. `-` for stdout, or `out.yaml`), which is written as the
+    /// output was arranged; see [`Destination::into_sink`].
+    Document(&'static dyn Output, Box<dyn Write + Send>),
+    Sink(Box<dyn Sink>),
+}
+
+impl Destination {
+    /// Open a destination, before the command runs, so that a bad one fails early:
+    ///
+    /// - `-` is stdout, in `format`
+    /// - `postgres://` and `sqlite://` URLs are tables to upsert records into (see
+    ///   [`Target::parse`](datacollect::sinks::Target::parse))
+    /// - other paths are files, whose format is picked by extension: `.jsonl` for JSON
+    ///   lines, `.csv`, or one of the [`OUTPUTS`](super::OUTPUTS) (`.json`, `.yaml`, ...)
+    ///
+    /// # Errors
+    /// Errors if the destination isn't one of these or could not be opened, or if it's a
+    /// terminal and `format` is binary.
+    pub async fn open(destination: &str, format: &'static dyn Output) -> anyhow::Result<Self> {
+        if destination == "-" {
+            if format.is_binary() && stdout().is_terminal() {
+                bail!(
+                    "{} output is binary; redirect it to a file, e.g. `> out.{}`",
+                    format.name(),
+                    format.name()
+                );
+            }
+            return Ok(Self::Document(format, Box::new(stdout())));
+        }
+        if let Some((scheme, _)) = destination.split_once("://") {
+            return Ok(Self::Sink(match scheme {
+                "postgres" | "postgresql" => Box::new(PostgresSink::from_url(destination).await?),
+                "sqlite" => Box::new(SqliteSink::from_url(destination).await?),
+                _ => bail!("cannot write to {}:// URLs", scheme),
+            }));
+        }
+
+        let path = Path::new(destination);
+        let context = || format!("could not create {}", destination);
+        let create = || File::create(path).with_context(context);
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        Ok(match extension {
+            "jsonl" => Self::Sink(Box::new(JsonSink::create(path).with_context(context)?)),
+            "csv" => Self::Sink(Box::new(CsvSink::create(path).with_context(context)?)),
+            "yml" => Self::Document(&super::Yaml, Box::new(BufWriter::new(create()?))),
+            extension => match find(extension) {
+                Some(format) => Self::Document(format, Box::new(BufWriter::new(create()?))),
+                None => bail!(
+                    "cannot tell what to write to {} (expected a .jsonl, .csv, .json, .yaml, \
+                     .xml or .parquet file, -, or a postgres:// or sqlite:// URL)",
+                    destination
+                ),
+            },
+        })
+    }
+
+    /// The sink to write the records to, once the command has run and the `shape` of its
+    /// output is known.
+    pub fn into_sink(self, shape: &Shape) -> Box<dyn Sink> {
+        match self {
+            Self::Document(format, writer) => Box::new(FormatSink {
+                format,
+                shape: shape.clone(),
+                records: Vec::new(),
+                written: 0,
+                writer,
+            }),
+            Self::Sink(sink) => sink,
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! The formats command output can be written in, and the destinations it can be written to.
+//!
+//! Each format is an [`Output`] in [`OUTPUTS`], picked by name with `--format` or in the
+//! config file. Each destination (`--output`) is a [`Sink`](datacollect::sinks::Sink)
+//! that the records in the output are written to; see [`Destination`].
+
+mod destination;
+mod parquet;
+mod select;
+mod stream;
+mod wrap;
+mod xml;
+
+use std::io::Write;
+
+use anyhow::{bail, Context};
+use erased_serde::Serializer;
+use serde_json::Value;
+
+pub use self::{
+    destination::{split, Destination, Shape},
+    select::{Selected, Selection},
+    stream::{send, streaming, write_stream},
+    wrap::Wrapped,
+};
+use crate::common::Run;
+
+/// A way of writing a command's output.
+pub trait Output: Sync {
+    /// The name that picks this format, e.g. `json`.
+    fn name(&self) -> &'static str;
+
+    /// Whether the output is binary, so shouldn't be written to a terminal.
+    fn is_binary(&self) -> bool {
+        false
+    }
+
+    /// Write `output` (all of it, e.g. a list of records) to `writer`.
+    fn write(&self, output: &Value, writer: &mut dyn Write) -> anyhow::Result<()>;
+
+    /// Whether a list can be written an item at a time, with [`Output::write_item`] and
+    /// [`Output::end_list`], rather than all at once.
+    fn streams(&self) -> bool {
+        false
+    }
+
+    /// Write the item at `index` in a list.
+    fn write_item(
+        &self,
+        _index: usize,
+        _item: &Value,
+        _writer: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        bail!("{} output cannot be written an item at a time", self.name())
+    }
+
+    /// Finish a list of `len` items written with [`Output::write_item`].
+    fn end_list(&self, _len: usize, _writer: &mut dyn Write) -> anyhow::Result<()> {
+        bail!("{} output cannot be written an item at a time", self.name())
+    }
+}
+
+/// Every output format.
+pub static OUTPUTS: &[&dyn Output] = &[
+    &Json,
+    &JsonCompact,
+    &Yaml,
+    &self::xml::Xml,
+    &self::parquet::Parquet,
+];
+
+/// Find an output format by name.
+pub fn find(name: &str) -> Option<&'static dyn Output> {
+    OUTPUTS.iter().copied().find(|output| output.name() == name)
+}
+
+/// Run `command`, keeping its output to convert or to split into records.
+///
+/// If the caller is [`streaming`], so is `command`, and the records it streams are left out
+/// of its output; see [`collect_all`].
+pub async fn collect(command: &(dyn Run + Sync)) -> anyhow::Result<Value> {
+    let mut json = Vec::new();
+    command
+        .run(&mut <dyn Serializer>::erase(
+            &mut serde_json::Serializer::new(&mut json),
+        ))
+        .await?;
+    serde_json::from_slice(&json).context("could not read output")
+}
+
+/// Run `command`, keeping all of its output, even if the caller is [`streaming`].
+pub async fn collect_all(command: &(dyn Run + Sync)) -> anyhow::Result<Value> {
+    streaming(None, collect(command)).await
+}
+
+/// Indented JSON.
+pub struct Json;
+
+impl Output for Json {
+    fn name(&self) -> &'static str {
+        "json"
+    }
+
+    fn write(&self, output: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        serde_json::to_writer_pretty(&mut *writer, output)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    fn streams(&self) -> bool {
+        true
+    }
+
+    /// Written the way [`Output::write`] would write it in the list, indented.
+    fn write_item(&self, index: usize, item: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        writer.write_all(if index == 0 { b"[\n" } else { b",\n" })?;
+        /* strings are escaped, so every line break is between tokens */
+        let item = serde_json::to_string_pretty(item)?;
+        for (i, line) in item.lines().enumerate() {
+            if i > 0 {
+                writeln!(writer)?;
+            }
+            write!(writer, "  {}", line)?;
+        }
+        Ok(())
+    }
+
+    fn end_list(&self, len: usize, writer: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(writer, "{}", if len == 0 { "[]" } else { "\n]" })?;
+        Ok(())
+    }
+}
+
+/// JSON on a single line.
+pub struct JsonCompact;
+
+impl Output for JsonCompact {
+    fn name(&self) -> &'static str {
+        "json-compact"
+    }
+
+    fn write(&self, output: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut *writer, output)?;
+        writeln!(writer)?;
+        Ok(())
+    }
+
+    fn streams(&self) -> bool {
+        true
+    }
+
+    fn write_item(&self, index: usize, item: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        writer.write_all(if index == 0 { b"[" } else { b"," })?;
+        serde_json::to_writer(&mut *writer, item)?;
+        Ok(())
+    }
+
+    fn end_list(&self, len: usize, writer: &mut dyn Write) -> anyhow::Result<()> {
+        writeln!(writer, "{}", if len == 0 { "[]" } else { "]" })?;
+        Ok(())
+    }
+}
+
+/// A YAML document.
+pub struct Yaml;
+
+impl Output for Yaml {
+    fn name(&self) -> &'static str {
+        "yaml"
+    }
+
+    fn write(&self, output: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        serde_yaml::to_writer(writer, output)?;
+        Ok(())
+    }
+
+    fn streams(&self) -> bool {
+        true
+    }
+
+    /// A YAML list is its items one after another, each starting with `- `.
+    fn write_item(
+        &self,
+        _index: usize,
+        item: &Value,
+        writer: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        serde_yaml::to_writer(writer, &[item])?;
+        Ok(())
+    }
+
+    fn end_list(&self, len: usize, writer: &mut dyn Write) -> anyhow::Result<()> {
+        if len == 0 {
+            writeln!(writer, "[]")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{Output, Yaml};
+
+    #[test]
+    fn test_yaml_round_trip() {
+        let output = json!([
+            {"name": "AMD Ryzen 5 2600", "cpumark": 13206, "price": ["USD", 79.99], "specs": {"cores": 6}},
+            {"name": "yes", "cpumark": null, "price": [], "specs": {"Brand Name": "Intel: Core"}}
+        ]);
+
+        let mut yaml = Vec::new();
+        Yaml.write(&output, &mut yaml).unwrap();
+        assert_eq!(serde_yaml::from_slice::<Value>(&yaml).unwrap(), output);
+
+        /* written an item at a time, it's the same list */
+        let mut streamed = Vec::new();
+        for (index, item) in output.as_array().unwrap().iter().enumerate() {
+            Yaml.write_item(index, item, &mut streamed).unwrap();
+        }
+        Yaml.end_list(2, &mut streamed).unwrap();
+        assert_eq!(serde_yaml::from_slice::<Value>(&streamed).unwrap(), output);
+
+        let mut empty = Vec::new();
+        Yaml.end_list(0, &mut empty).unwrap();
+        assert_eq!(serde_yaml::from_slice::<Value>(&empty).unwrap(), json!([]));
+    }
+}
@@ -0,0 +1,69 @@
+use std::io::Write;
+
+use datacollect::arrow;
+use parquet::{arrow::ArrowWriter, basic::Compression, file::properties::WriterProperties};
+use serde_json::Value;
+
+use super::{destination::split, Output};
+
+/// A Parquet file, with one row per record.
+///
+/// The columns are inferred from the records; nested objects become struct columns.
+pub struct Parquet;
+
+impl Output for Parquet {
+    fn name(&self) -> &'static str {
+        "parquet"
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn write(&self, output: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        let (_, records) = split(output.clone());
+        let batches = arrow::record_batches(&records)?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        /* the writer has to be `Send` */
+        let mut buffer = Vec::new();
+        let mut parquet = ArrowWriter::try_new(&mut buffer, batches[0].schema(), Some(properties))?;
+        for batch in &batches {
+            parquet.write(batch)?;
+        }
+        parquet.close()?;
+        writer.write_all(&buffer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use datacollect::arrow;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+    use serde_json::json;
+
+    use super::{Output, Parquet};
+
+    #[test]
+    fn test_round_trip() {
+        let records = vec![
+            json!({"name": "AMD Ryzen 5 2600", "cpumark": 13206, "price": ["USD", 79.99], "specs": {"cores": 6}}),
+            json!({"name": "Intel Core i7-4770", "cpumark": 9948, "price": null, "specs": {"cores": 4}}),
+        ];
+
+        /* a list wrapped in an object is written the same as the list */
+        for output in [json!(records), json!({ "cpus": records })] {
+            let mut file = Vec::new();
+            Parquet.write(&output, &mut file).unwrap();
+            let read = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(file))
+                .unwrap()
+                .build()
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap();
+            assert_eq!(read, arrow::record_batches(&records).unwrap());
+        }
+    }
+}
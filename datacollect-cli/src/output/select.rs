@@ -0,0 +1,164 @@
+use std::str::FromStr;
+
+use anyhow::bail;
+use async_trait::async_trait;
+use erased_serde::Serializer;
+use serde_json::{Map, Value};
+
+use super::collect;
+use crate::common::Run;
+
+/// The fields to keep from a command's output, as dotted paths, e.g. `seller.name,price`.
+///
+/// Lists are projected item by item, so `seller.name` works on a single eBay product and on
+/// a list of search results alike. Selected fields that are missing are `null`.
+///
+/// ## Example
+/// ```txt
+/// --select seller.name,price
+/// [{"id": 1, "seller": {"name": "a", "feedback": 99.5}, "price": ["USD", 5.0]}]
+///   -> [{"seller": {"name": "a"}, "price": ["USD", 5.0]}]
+/// ```
+#[derive(Clone, Debug)]
+pub struct Selection(Vec<Vec<String>>);
+
+impl FromStr for Selection {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let paths = s
+            .split(',')
+            .map(str::trim)
+            .filter(|path| !path.is_empty())
+            .map(|path| path.split('.').map(str::to_string).collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        if paths.is_empty() {
+            bail!("no fields to select");
+        }
+        if let Some(path) = paths.iter().find(|path| path.iter().any(String::is_empty)) {
+            bail!("invalid field path `{}`", path.join("."));
+        }
+        Ok(Self(paths))
+    }
+}
+
+impl Selection {
+    /// Keep only the selected fields of `value`.
+    pub fn project(&self, value: &Value) -> Value {
+        project(value, &self.0.iter().map(Vec::as_slice).collect::<Vec<_>>())
+    }
+}
+
+fn project(value: &Value, paths: &[&[String]]) -> Value {
+    /* a path that ends here selects the whole value */
+    if paths.iter().any(|path| path.is_empty()) {
+        return value.clone();
+    }
+
+    match value {
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| project(item, paths)).collect())
+        }
+        Value::Object(_) => {
+            let mut projected = Map::new();
+            for path in paths {
+                let field = &path[0];
+                if projected.contains_key(field) {
+                    continue;
+                }
+                let rest = paths
+                    .iter()
+                    .filter(|path| &path[0] == field)
+                    .map(|path| &path[1..])
+                    .collect::<Vec<_>>();
+                let child = value
+                    .get(field)
+                    .map_or(Value::Null, |child| project(child, &rest));
+                projected.insert(field.clone(), child);
+            }
+            Value::Object(projected)
+        }
+        /* scalars have no fields */
+        _ => Value::Null,
+    }
+}
+
+/// A command whose output is projected to a [`Selection`] before it is written.
+pub struct Selected<'a> {
+    pub command: &'a (dyn Run + Sync),
+    pub selection: &'a Selection,
+}
+
+#[async_trait]
+impl Run for Selected<'_> {
+    async fn run(&self, serializer: &mut (dyn Serializer + Send)) -> anyhow::Result<()> {
+        let output = collect(self.command).await?;
+        erased_serde::serialize(&self.selection.project(&output), serializer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::Selection;
+
+    #[test]
+    fn test_parse() {
+        let selection = " seller.name , price,".parse::<Selection>().unwrap();
+        assert_eq!(selection.0, vec![vec!["seller", "name"], vec!["price"]]);
+
+        assert_eq!(
+            "".parse::<Selection>().unwrap_err().to_string(),
+            "no fields to select"
+        );
+        assert_eq!(
+            " , ".parse::<Selection>().unwrap_err().to_string(),
+            "no fields to select"
+        );
+        assert_eq!(
+            "seller..name".parse::<Selection>().unwrap_err().to_string(),
+            "invalid field path `seller..name`"
+        );
+        assert_eq!(
+            "price.".parse::<Selection>().unwrap_err().to_string(),
+            "invalid field path `price.`"
+        );
+    }
+
+    #[test]
+    fn test_project() {
+        /* the example in the documentation */
+        let selection = "seller.name,price".parse::<Selection>().unwrap();
+        assert_eq!(
+            selection.project(&json!([
+                {"id": 1, "seller": {"name": "a", "feedback": 99.5}, "price": ["USD", 5.0]}
+            ])),
+            json!([{"seller": {"name": "a"}, "price": ["USD", 5.0]}])
+        );
+
+        /* paths with the same start share the object they select from */
+        let selection = "seller.name,seller.feedback".parse::<Selection>().unwrap();
+        let product = json!({"id": 1, "seller": {"name": "a", "feedback": 99.5, "sold": 12}});
+        assert_eq!(
+            selection.project(&product),
+            json!({"seller": {"name": "a", "feedback": 99.5}})
+        );
+
+        /* lists inside records are projected item by item too */
+        let selection = "items.id".parse::<Selection>().unwrap();
+        assert_eq!(
+            selection.project(&json!({"items": [{"id": 1, "title": "a"}, {"id": 2}]})),
+            json!({"items": [{"id": 1}, {"id": 2}]})
+        );
+
+        /* missing fields, and fields of scalars, are null */
+        let selection = "seller.name,shipping,id.value"
+            .parse::<Selection>()
+            .unwrap();
+        assert_eq!(
+            selection.project(&json!({"id": 1, "seller": null})),
+            json!({"seller": null, "shipping": null, "id": null})
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use std::future::Future;
+
+use datacollect::stream::{Stream, StreamExt};
+use erased_serde::Serializer;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::mpsc::Sender;
+
+use crate::progress;
+
+tokio::task_local! {
+    /// Where the records of a list are sent as they're found, if they're written as they
+    /// come; see [`streaming`].
+    static RECORDS: Option<Sender<Value>>;
+}
+
+/// Run `future` (usually a command), with the records of the lists it finds sent to
+/// `records` as soon as each one is found, rather than kept for its output.
+pub async fn streaming<F: Future>(records: Option<Sender<Value>>, future: F) -> F::Output {
+    RECORDS.scope(records, future).await
+}
+
+/// Send a record on if the command is [`streaming`], or give it back to be kept for the
+/// output if it isn't.
+pub async fn send(record: Value) -> Option<Value> {
+    match RECORDS.try_with(Clone::clone).ok().flatten() {
+        Some(records) => {
+            /* if the records aren't taken any more, writing them failed, and that's the error */
+            let _ = records.send(record).await;
+            None
+        }
+        None => Some(record),
+    }
+}
+
+/// Write up to `limit` items from a stream (e.g. search results) as a list, showing a
+/// progress bar.
+///
+/// If the command is [`streaming`], each item is sent on as it's found, and the output
+/// itself is an empty list. Otherwise, the items are collected and the output is the list.
+///
+/// # Errors
+/// Errors if an item could not be serialized, or the output could not be written.
+pub async fn write_stream<'a, T: Serialize + Send + 'a>(
+    stream: impl Stream<Item = T> + Send + 'a,
+    limit: usize,
+    message: &'static str,
+    ser: &mut (dyn Serializer + Send),
+) -> anyhow::Result<()> {
+    let Some(records) = RECORDS.try_with(Clone::clone).ok().flatten() else {
+        erased_serde::serialize(&progress::collect(stream, limit, message).await, ser)?;
+        return Ok(());
+    };
+
+    let bar = progress::bar(limit as u64, message);
+    let mut stream = stream.boxed().take(limit);
+    while let Some(item) = stream.next().await {
+        /* if the records aren't taken any more, writing them failed, and that's the error */
+        if records.send(serde_json::to_value(&item)?).await.is_err() {
+            break;
+        }
+        bar.inc(1);
+    }
+    bar.finish_and_clear();
+    erased_serde::serialize(&Vec::<Value>::new(), ser)?;
+    Ok(())
+}
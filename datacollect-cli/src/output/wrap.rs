@@ -0,0 +1,91 @@
+use async_trait::async_trait;
+use datacollect::chrono::Utc;
+use erased_serde::Serializer;
+use serde_json::{json, Value};
+
+use super::collect;
+use crate::common::Run;
+
+/// A command whose output, if it's a single value (e.g. `true` from `rdap domain
+/// is-registered`), is wrapped in an envelope saying what was asked and when.
+///
+/// Lists and objects are already self-describing, so they're written as they are.
+///
+/// ## Example
+/// ```txt
+/// datacollect-cli --wrap rdap domain is-registered example.com
+///   -> {"query": "rdap domain is-registered example.com", "result": true, "checked_at": "2021-11-20T14:53:00Z"}
+/// ```
+pub struct Wrapped<'a> {
+    pub command: &'a (dyn Run + Sync),
+    /// The command line that was run.
+    pub query: String,
+}
+
+#[async_trait]
+impl Run for Wrapped<'_> {
+    async fn run(&self, serializer: &mut (dyn Serializer + Send)) -> anyhow::Result<()> {
+        let output = match collect(self.command).await? {
+            output @ (Value::Array(_) | Value::Object(_)) => output,
+            result => json!({
+                "query": self.query,
+                "result": result,
+                "checked_at": Utc::now(),
+            }),
+        };
+        erased_serde::serialize(&output, serializer)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+    use datacollect::chrono::{DateTime, Utc};
+    use erased_serde::Serializer;
+    use serde_json::{json, Value};
+
+    use super::Wrapped;
+    use crate::{common::Run, output::collect};
+
+    /// A command that outputs a fixed value.
+    struct Fixed(Value);
+
+    #[async_trait]
+    impl Run for Fixed {
+        async fn run(&self, serializer: &mut (dyn Serializer + Send)) -> anyhow::Result<()> {
+            erased_serde::serialize(&self.0, serializer)?;
+            Ok(())
+        }
+    }
+
+    async fn wrapped(output: Value) -> Value {
+        let command = Fixed(output);
+        collect(&Wrapped {
+            command: &command,
+            query: "rdap domain is-registered example.com".to_string(),
+        })
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_wrap() {
+        /* a single value is wrapped */
+        let output = wrapped(json!(true)).await;
+        assert_eq!(output["query"], "rdap domain is-registered example.com");
+        assert_eq!(output["result"], true);
+        assert!(output["checked_at"]
+            .as_str()
+            .unwrap()
+            .parse::<DateTime<Utc>>()
+            .is_ok());
+        assert_eq!(output.as_object().unwrap().len(), 3);
+
+        /* records and lists of them are written as they are */
+        let record = json!({"name": "example.com", "registered": true});
+        assert_eq!(wrapped(record.clone()).await, record);
+        let list = json!([{"id": 1}, {"id": 2}]);
+        assert_eq!(wrapped(list.clone()).await, list);
+    }
+}
@@ -0,0 +1,163 @@
+use std::io::Write;
+
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Writer,
+};
+use serde_json::Value;
+
+use super::Output;
+
+/// An XML document, with the output in an `<output>` element.
+///
+/// Fields become elements named after them, or `<entry key="...">` if the name isn't a valid
+/// element name (e.g. item specifics like `Brand Name`). The items of lists are `<item>`s, and
+/// missing values are empty elements.
+pub struct Xml;
+
+impl Output for Xml {
+    fn name(&self) -> &'static str {
+        "xml"
+    }
+
+    fn write(&self, output: &Value, writer: &mut dyn Write) -> anyhow::Result<()> {
+        let mut writer = Writer::new_with_indent(writer, b' ', 2);
+        writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+        element(&mut writer, "output", output)?;
+        writeln!(writer.get_mut())?;
+        Ok(())
+    }
+}
+
+/// Whether a field name can be used as an element name as-is.
+fn is_element_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        /* names starting with `xml` are reserved */
+        && !name.to_lowercase().starts_with("xml")
+}
+
+fn element<W: Write>(writer: &mut Writer<W>, name: &str, value: &Value) -> anyhow::Result<()> {
+    let (tag, start) = if is_element_name(name) {
+        (name, BytesStart::new(name))
+    } else {
+        (
+            "entry",
+            BytesStart::new("entry").with_attributes([("key", name)]),
+        )
+    };
+
+    let children = match value {
+        Value::Array(items) => items.iter().map(|item| ("item", item)).collect(),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+            .collect(),
+        _ => Vec::new(),
+    };
+    let text = match value {
+        Value::Null | Value::Array(_) | Value::Object(_) => None,
+        Value::String(s) => Some(s.clone()),
+        other => Some(other.to_string()),
+    };
+
+    if children.is_empty() && text.is_none() {
+        writer.write_event(Event::Empty(start))?;
+        return Ok(());
+    }
+    writer.write_event(Event::Start(start))?;
+    if let Some(text) = text {
+        writer.write_event(Event::Text(BytesText::new(&text)))?;
+    }
+    for (name, value) in children {
+        element(writer, name, value)?;
+    }
+    writer.write_event(Event::End(BytesEnd::new(tag)))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use quick_xml::{
+        events::{BytesStart, Event},
+        Reader,
+    };
+    use serde_json::{json, Map, Value};
+
+    use super::{Output, Xml};
+
+    /// The name of an element, or its `key` if it's an `<entry>`.
+    fn name(start: &BytesStart) -> String {
+        match start.try_get_attribute("key").unwrap() {
+            Some(key) if start.name().as_ref() == b"entry" => {
+                key.unescape_value().unwrap().into_owned()
+            }
+            _ => String::from_utf8(start.name().as_ref().to_vec()).unwrap(),
+        }
+    }
+
+    /// Read [`Xml`] output back: text becomes strings, `<item>`s lists, other children
+    /// objects and empty elements nulls.
+    fn read(xml: &str) -> Value {
+        let mut reader = Reader::from_str(xml);
+        reader.config_mut().trim_text(true);
+        /* the open elements, with their children and text */
+        let mut open = vec![(String::new(), Vec::new(), None)];
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(start) => open.push((name(&start), Vec::new(), None)),
+                Event::Empty(start) => open.last_mut().unwrap().1.push((name(&start), Value::Null)),
+                Event::Text(text) => {
+                    open.last_mut().unwrap().2 = Some(text.unescape().unwrap().into_owned())
+                }
+                Event::End(_) => {
+                    let (name, children, text) = open.pop().unwrap();
+                    let value = match text {
+                        Some(text) => Value::String(text),
+                        None if children.iter().all(|(name, _)| name == "item") => {
+                            Value::Array(children.into_iter().map(|(_, value)| value).collect())
+                        }
+                        None => Value::Object(children.into_iter().collect::<Map<_, _>>()),
+                    };
+                    open.last_mut().unwrap().1.push((name, value));
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        let (_, mut children, _) = open.pop().unwrap();
+        let (name, output) = children.pop().unwrap();
+        assert_eq!(name, "output");
+        output
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let output = json!([{
+            "name": "AMD Ryzen 5 2600",
+            "cpumark": 13206,
+            "item_specifics": {"Brand Name": "AMD", "Socket": "AM4 <Ryzen & co>"},
+            "tags": ["cpu", "am4"],
+            "seller": null
+        }]);
+        let mut xml = Vec::new();
+        Xml.write(&output, &mut xml).unwrap();
+        let xml = String::from_utf8(xml).unwrap();
+        assert!(xml.starts_with(r#"<?xml version="1.0" encoding="UTF-8"?>"#));
+
+        /* everything but the types of the values comes back */
+        assert_eq!(
+            read(&xml),
+            json!([{
+                "name": "AMD Ryzen 5 2600",
+                "cpumark": "13206",
+                "item_specifics": {"Brand Name": "AMD", "Socket": "AM4 <Ryzen & co>"},
+                "tags": ["cpu", "am4"],
+                "seller": null
+            }])
+        );
+    }
+}
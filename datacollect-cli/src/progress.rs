@@ -0,0 +1,54 @@
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use datacollect::stream::{Stream, StreamExt};
+use indicatif::{ProgressBar, ProgressStyle};
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Show progress bars or not (`-q` hides them). They are only ever drawn on a terminal.
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// A progress bar on stderr, for `len` items.
+pub fn bar(len: u64, message: &'static str) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let bar = ProgressBar::new(len).with_message(message);
+    if let Ok(style) = ProgressStyle::with_template("{msg} [{bar:30}] {pos}/{len} ({elapsed})") {
+        bar.set_style(style.progress_chars("=> "));
+    }
+    bar
+}
+
+/// A spinner on stderr, for work with no known length (e.g. one big download).
+pub fn spinner(message: &'static str) -> ProgressBar {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return ProgressBar::hidden();
+    }
+    let spinner = ProgressBar::new_spinner().with_message(message);
+    spinner.enable_steady_tick(Duration::from_millis(100));
+    spinner
+}
+
+/// Collect up to `limit` items from a stream (e.g. search results), showing a progress bar.
+pub async fn collect<'a, T>(
+    stream: impl Stream<Item = T> + Send + 'a,
+    limit: usize,
+    message: &'static str,
+) -> Vec<T> {
+    let bar = bar(limit as u64, message);
+    /* boxed, so that the compiler can see this future is `Send` */
+    let items = stream
+        .boxed()
+        .take(limit)
+        .inspect(|_| bar.inc(1))
+        .collect::<Vec<_>>()
+        .await;
+    bar.finish_and_clear();
+    items
+}
@@ -0,0 +1,103 @@
+//! Benchmarks of the parsing that dominates processing a cached crawl.
+//!
+//! ```txt
+//! cargo bench -p datacollect-core
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use datacollect_core::{common::has_hidden_word, modules::ebay::SearchPage, schema_org::Scope};
+use kuchiki::{parse_html, traits::TendrilSink};
+
+/// A product page with microdata, padded out with `padding` rows of other markup (as item
+/// pages are).
+fn product_page(padding: usize) -> String {
+    let rows = (0..padding)
+        .map(|n| {
+            format!(
+                r#"<tr><td class="label">Row {0}</td><td><span>{0}</span></td></tr>"#,
+                n
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<html><body>
+            <div itemscope itemtype="https://schema.org/Product">
+                <h1 itemprop="name">Blend-O-Matic</h1>
+                <table>{}</table>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <span itemprop="price" content="19.95">$19.95</span>
+                    <meta itemprop="priceCurrency" content="USD" />
+                </div>
+                <div itemprop="aggregateRating" itemscope itemtype="https://schema.org/AggregateRating">
+                    <meta itemprop="ratingValue" content="4.5" />
+                    <meta itemprop="reviewCount" content="1,284" />
+                </div>
+                <img itemprop="image" src="https://example.com/1.jpg" />
+                <img itemprop="image" src="https://example.com/2.jpg" />
+            </div>
+        </body></html>"#,
+        rows
+    )
+}
+
+/// A search results page with `items` results, every tenth of them sponsored.
+fn search_page(items: usize) -> String {
+    let items = (0..items)
+        .map(|n| {
+            format!(
+                r#"<li class="s-item">
+                    <div class="s-item__image"><img src="https://i.ebayimg.com/images/g/{0}/s-l225.jpg"></div>
+                    <a href="https://www.ebay.com/itm/{0}?hash=item{0}"><h3 class="s-item__title">Item {0}</h3></a>
+                    <span class="s-item__price">${0}.99</span>
+                    <span class="s-item__detail">{1}</span>
+                </li>"#,
+                1000 + n,
+                if n % 10 == 0 {
+                    "<span>S</span><span style=\"display:none\">x</span><span>ponsored</span>"
+                } else {
+                    "Free shipping"
+                }
+            )
+        })
+        .collect::<String>();
+    format!(
+        r#"<div id="mainContent">
+            <h1 class="srp-controls__count-heading"><span>1,204</span> results</h1>
+            <ul>{}</ul>
+        </div>"#,
+        items
+    )
+}
+
+fn scope(c: &mut Criterion) {
+    let document = parse_html().one(product_page(500));
+    c.bench_function("scope properties", |b| {
+        b.iter(|| {
+            let product = Scope::find(document.clone(), "https://schema.org/Product").unwrap();
+            let name = product.get_value("name");
+            let images = product.get_values("image").count();
+            let offer = product.select_prop("offers").unwrap();
+            let price = (offer.get_value("price"), offer.get_value("priceCurrency"));
+            let rating = product.select_prop("aggregateRating").unwrap();
+            let count = rating.get_value("reviewCount");
+            black_box((name, images, price, count))
+        })
+    });
+}
+
+fn hidden_word(c: &mut Criterion) {
+    let haystack = "ddSQpOonhsortiedxx".repeat(20);
+    c.bench_function("has_hidden_word", |b| {
+        b.iter(|| has_hidden_word(black_box("Sponsored"), black_box(&haystack)))
+    });
+}
+
+fn ebay_search(c: &mut Criterion) {
+    let page = search_page(60);
+    c.bench_function("ebay search page", |b| {
+        b.iter(|| SearchPage::from_html(black_box(&page), 1).unwrap())
+    });
+}
+
+criterion_group!(benches, scope, hidden_word, ebay_search);
+criterion_main!(benches);
@@ -0,0 +1,152 @@
+//! Simple time-series helpers, e.g. for the price records of [`tracking`](crate::tracking),
+//! so that something can be said about a trend rather than a single reading.
+//!
+//! Every function takes the points in the order they were observed (oldest first), and
+//! windows are spans of time rather than numbers of points, since observations are rarely
+//! evenly spaced.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+
+/// How many earlier points a z-score needs, so that one odd reading among very few isn't
+/// taken for an anomaly.
+pub const MIN_HISTORY: usize = 3;
+
+/// One observation of a value.
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct Point {
+    pub at: DateTime<Utc>,
+    pub value: f64,
+}
+
+impl Point {
+    pub fn new(at: DateTime<Utc>, value: f64) -> Self {
+        Self { at, value }
+    }
+}
+
+/// A point that is far from the points before it; see [`anomalies`].
+#[derive(Serialize, Clone, Copy, PartialEq, Debug)]
+pub struct Anomaly {
+    pub at: DateTime<Utc>,
+    pub value: f64,
+    /// How many standard deviations the value is from the mean of the window before it
+    /// (negative if below).
+    pub z_score: f64,
+}
+
+/// The points of `series` that are within `window` before `end` (excluding `end` itself).
+fn before(series: &[Point], end: DateTime<Utc>, window: Duration) -> impl Iterator<Item = &Point> {
+    series
+        .iter()
+        .filter(move |p| p.at < end && p.at >= end - window)
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// The average of each point with the points in the `window` before it, at each point.
+pub fn rolling_average(series: &[Point], window: Duration) -> Vec<Point> {
+    series
+        .iter()
+        .map(|point| {
+            let values = before(series, point.at, window)
+                .chain(std::iter::once(point))
+                .map(|p| p.value)
+                .collect::<Vec<_>>();
+            Point::new(point.at, mean(&values).unwrap_or(point.value))
+        })
+        .collect()
+}
+
+/// The change from `from` to `to`, in percent of `from`; `None` if `from` is zero.
+pub fn percent_change(from: f64, to: f64) -> Option<f64> {
+    (from != 0.0).then(|| (to - from) / from.abs() * 100.0)
+}
+
+/// The change of the last point, in percent, from the last point at least `period` before
+/// it; `None` if there is no point that old.
+pub fn change_over(series: &[Point], period: Duration) -> Option<f64> {
+    let last = series.last()?;
+    let start = series.iter().rev().find(|p| p.at <= last.at - period)?;
+    percent_change(start.value, last.value)
+}
+
+/// How many standard deviations `value` is from the mean of `history`; `None` with fewer
+/// than [`MIN_HISTORY`] values, or if they are all the same.
+pub fn z_score(history: &[f64], value: f64) -> Option<f64> {
+    if history.len() < MIN_HISTORY {
+        return None;
+    }
+    let mean = mean(history)?;
+    let variance = history.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / history.len() as f64;
+    let deviation = variance.sqrt();
+    (deviation > f64::EPSILON).then(|| (value - mean) / deviation)
+}
+
+/// The z-score of each point against the points in the `window` before it, for points
+/// that have enough history (see [`z_score`]).
+pub fn z_scores(series: &[Point], window: Duration) -> Vec<Anomaly> {
+    series
+        .iter()
+        .filter_map(|point| {
+            let history = before(series, point.at, window)
+                .map(|p| p.value)
+                .collect::<Vec<_>>();
+            Some(Anomaly {
+                at: point.at,
+                value: point.value,
+                z_score: z_score(&history, point.value)?,
+            })
+        })
+        .collect()
+}
+
+/// The points at least `threshold` standard deviations (e.g. `3.0`) from the points in
+/// the `window` before them, either way.
+pub fn anomalies(series: &[Point], window: Duration, threshold: f64) -> Vec<Anomaly> {
+    z_scores(series, window)
+        .into_iter()
+        .filter(|anomaly| anomaly.z_score.abs() >= threshold)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{anomalies, change_over, percent_change, rolling_average, z_score, Point};
+
+    fn daily(values: &[f64]) -> Vec<Point> {
+        let start = Utc.with_ymd_and_hms(2023, 3, 1, 12, 0, 0).unwrap();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| Point::new(start + Duration::days(i as i64), *value))
+            .collect()
+    }
+
+    #[test]
+    fn test_trends() {
+        let series = daily(&[100.0, 102.0, 98.0, 101.0, 99.0, 60.0]);
+
+        let averages = rolling_average(&series, Duration::days(1))
+            .iter()
+            .map(|p| p.value)
+            .collect::<Vec<_>>();
+        assert_eq!(averages, vec![100.0, 101.0, 100.0, 99.5, 100.0, 79.5]);
+
+        assert_eq!(percent_change(200.0, 150.0), Some(-25.0));
+        assert_eq!(percent_change(0.0, 1.0), None);
+        assert_eq!(change_over(&series, Duration::days(5)), Some(-40.0));
+        assert_eq!(change_over(&series, Duration::days(6)), None);
+
+        assert_eq!(z_score(&[1.0, 1.0], 5.0), None);
+        assert_eq!(z_score(&[1.0, 1.0, 1.0], 5.0), None);
+        let flagged = anomalies(&series, Duration::days(7), 3.0);
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].at, series[5].at);
+        assert!(flagged[0].z_score < -3.0);
+    }
+}
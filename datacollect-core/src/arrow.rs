@@ -0,0 +1,247 @@
+//! Collecting records into Arrow [`RecordBatch`]es instead of a `Vec`, for handing a big
+//! collection (e.g. a mega list, or a long search) to polars or DataFusion in the same
+//! process without writing it out as JSON and reading it back.
+//!
+//! Records are serialized straight into Arrow's buffers. The columns are inferred from
+//! the records: nested structs become struct columns, lists become list columns, and
+//! [`Money`](crate::common::Money) (`["USD", 12.5]`) becomes a list of strings. Records that
+//! are all at hand ([`record_batches`]) are all looked at; records collected from a stream
+//! ([`collect`], [`BatchBuilder`]) only have the first batch looked at, so fields that only
+//! appear in later batches are left out.
+//!
+//! ## Example
+//! ```txt
+//! let products = Product::search("cpu", Detail::Minimal).filter_map(|p| async { p.ok() });
+//! let batches = arrow::collect(products.take(1000)).await?;
+//! let df = polars::prelude::DataFrame::try_from(batches[0].clone())?;
+//! ```
+
+use std::sync::Arc;
+
+use anyhow::{bail, Context};
+use arrow_array::RecordBatch;
+use arrow_json::reader::{infer_json_schema_from_iterator, Decoder, ReaderBuilder};
+use arrow_schema::{DataType, Field, Fields, Schema, SchemaRef};
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+
+/// How many records go in each [`RecordBatch`].
+pub const BATCH_SIZE: usize = 1024;
+
+/// A column's type, without the structs that have no fields (e.g. item specifics that are
+/// empty in every record), which Parquet and most dataframe libraries can't store. `None`
+/// if nothing is left.
+fn without_empty_structs(data_type: &DataType) -> Option<DataType> {
+    match data_type {
+        DataType::Struct(fields) => {
+            let fields = fields
+                .iter()
+                .filter_map(|field| {
+                    let data_type = without_empty_structs(field.data_type())?;
+                    Some(Field::clone(field).with_data_type(data_type))
+                })
+                .collect::<Fields>();
+            (!fields.is_empty()).then_some(DataType::Struct(fields))
+        }
+        DataType::List(item) => {
+            let data_type = without_empty_structs(item.data_type())?;
+            Some(DataType::List(Arc::new(
+                Field::clone(item).with_data_type(data_type),
+            )))
+        }
+        data_type => Some(data_type.clone()),
+    }
+}
+
+/// The columns for `records`, inferred from every one of them.
+///
+/// # Errors
+/// Errors if there are no records, if they aren't objects, or if they have no fields.
+pub fn schema_of<T: Serialize>(records: &[T]) -> anyhow::Result<SchemaRef> {
+    let values = records
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<Result<Vec<_>, _>>()?;
+    if values.is_empty() {
+        bail!("there are no records to infer columns from");
+    }
+    let inferred = infer_json_schema_from_iterator(values.iter().map(Ok))
+        .context("could not infer columns")?;
+    match without_empty_structs(&DataType::Struct(inferred.fields)) {
+        Some(DataType::Struct(fields)) => Ok(Arc::new(Schema::new(fields))),
+        _ => bail!("the records have no fields"),
+    }
+}
+
+fn decoder(schema: SchemaRef) -> anyhow::Result<Decoder> {
+    Ok(ReaderBuilder::new(schema)
+        .with_batch_size(BATCH_SIZE)
+        /* e.g. the amount in `["USD", 12.5]`, whose column is inferred as strings */
+        .with_coerce_primitive(true)
+        .build_decoder()?)
+}
+
+/// Builds [`RecordBatch`]es of [`BATCH_SIZE`] records from records added one at a time.
+/// The columns are inferred from the first batch (see [`schema_of`]).
+pub struct BatchBuilder<T> {
+    pending: Vec<T>,
+    decoder: Option<Decoder>,
+    batches: Vec<RecordBatch>,
+}
+
+impl<T: Serialize> Default for BatchBuilder<T> {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            decoder: None,
+            batches: Vec::new(),
+        }
+    }
+}
+
+impl<T: Serialize> BatchBuilder<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a record.
+    ///
+    /// # Errors
+    /// Errors if the columns could not be inferred, or if a record doesn't fit them.
+    pub fn push(&mut self, record: T) -> anyhow::Result<()> {
+        self.pending.push(record);
+        if self.pending.len() >= BATCH_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> anyhow::Result<()> {
+        let decoder = match &mut self.decoder {
+            Some(decoder) => decoder,
+            None => self.decoder.insert(decoder(schema_of(&self.pending)?)?),
+        };
+        decoder.serialize(&self.pending)?;
+        self.batches.extend(decoder.flush()?);
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// The batches of every record added.
+    ///
+    /// # Errors
+    /// Errors if no records were added, or as [`BatchBuilder::push`] does.
+    pub fn finish(mut self) -> anyhow::Result<Vec<RecordBatch>> {
+        if !self.pending.is_empty() || self.decoder.is_none() {
+            self.flush()?;
+        }
+        Ok(self.batches)
+    }
+}
+
+/// The records, as [`RecordBatch`]es of [`BATCH_SIZE`] records. The columns are inferred
+/// from all of the records.
+///
+/// # Errors
+/// Errors if there are no records, or if the columns could not be inferred from them.
+pub fn record_batches<T: Serialize>(records: &[T]) -> anyhow::Result<Vec<RecordBatch>> {
+    let mut decoder = decoder(schema_of(records)?)?;
+    let mut batches = Vec::new();
+    for chunk in records.chunks(BATCH_SIZE) {
+        decoder.serialize(chunk)?;
+        batches.extend(decoder.flush()?);
+    }
+    Ok(batches)
+}
+
+/// Collect a stream of records into [`RecordBatch`]es, like `collect::<Vec<_>>()` would
+/// collect them into a `Vec`. Only one batch of records is kept as they are at a time.
+///
+/// # Errors
+/// Errors if the stream had no records, or as [`BatchBuilder::push`] does.
+pub async fn collect<T: Serialize>(
+    records: impl Stream<Item = T>,
+) -> anyhow::Result<Vec<RecordBatch>> {
+    let mut builder = BatchBuilder::new();
+    futures::pin_mut!(records);
+    while let Some(record) = records.next().await {
+        builder.push(record)?;
+    }
+    builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow_array::{cast::AsArray, types::Int64Type, Array};
+    use futures::StreamExt;
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::{collect, record_batches, BATCH_SIZE};
+    use crate::common::Money;
+
+    #[derive(Serialize)]
+    struct Listing {
+        id: u64,
+        title: String,
+        price: Option<Money>,
+        specifics: std::collections::HashMap<String, String>,
+    }
+
+    fn listing(id: u64) -> Listing {
+        Listing {
+            id,
+            title: format!("CPU {}", id),
+            price: id.is_multiple_of(2).then(|| Money::from(12.5)),
+            specifics: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_collect() {
+        let count = BATCH_SIZE as u64 * 2 + 10;
+        let batches = collect(futures::stream::iter(0..count).map(listing))
+            .await
+            .unwrap();
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            vec![BATCH_SIZE, BATCH_SIZE, 10]
+        );
+
+        let schema = batches[0].schema();
+        /* `specifics` is always empty, so it has no column */
+        assert_eq!(
+            schema
+                .fields()
+                .iter()
+                .map(|f| f.name().as_str())
+                .collect::<Vec<_>>(),
+            vec!["id", "title", "price"]
+        );
+        let ids = batches[2].column(0).as_primitive::<Int64Type>();
+        assert_eq!(ids.value(9), count as i64 - 1);
+        let prices = batches[0].column(2).as_list::<i32>();
+        assert!(prices.is_null(1));
+        assert_eq!(
+            prices.value(0).as_string::<i32>().value(1),
+            "12.5",
+            "the amount of the price"
+        );
+
+        let batches = record_batches(&[listing(1), listing(2)]).unwrap();
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 2);
+        assert!(record_batches::<Listing>(&[]).is_err());
+
+        /* a field that only turns up after the first batch still gets a column */
+        let records = (0..BATCH_SIZE + 1)
+            .map(|i| match i {
+                BATCH_SIZE => json!({ "id": i, "sold": 3 }),
+                _ => json!({ "id": i }),
+            })
+            .collect::<Vec<_>>();
+        let batches = record_batches(&records).unwrap();
+        let sold = batches[1].column_by_name("sold").unwrap();
+        assert_eq!(sold.as_primitive::<Int64Type>().value(0), 3);
+    }
+}
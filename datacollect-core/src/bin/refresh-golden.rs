@@ -0,0 +1,41 @@
+//! Saves pages and records for the golden-file tests; see `datacollect_core::golden`.
+//!
+//! ```txt
+//! refresh-golden                          save every record again, from the saved pages
+//! refresh-golden <module> <name>          save one record again
+//! refresh-golden <module> <name> <url>    save the page at <url>, and its record
+//! ```
+
+use anyhow::bail;
+use datacollect_core::{common::Client, golden::Fixture};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    let fixtures = match args.as_slice() {
+        [] => Fixture::all()?,
+        [module, name] => vec![Fixture::new(module, name)],
+        [module, name, url] => {
+            let fixture = Fixture::new(module, name);
+            let client = Client::<false>::default();
+            let html = client
+                .get(url)
+                .await?
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            fixture.save_page(&html)?;
+            println!("saved {}", fixture.html_path().display());
+            vec![fixture]
+        }
+        _ => bail!("usage: refresh-golden [<module> <name> [<url>]]"),
+    };
+
+    for fixture in fixtures {
+        fixture.refresh()?;
+        println!("saved {}", fixture.json_path().display());
+    }
+    Ok(())
+}
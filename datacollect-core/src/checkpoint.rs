@@ -0,0 +1,173 @@
+//! Saving the progress of long-running collections, so that an interrupted run can pick up
+//! where it left off instead of fetching everything again.
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The progress of a search, e.g. [`Product::search_resume_with`](crate::modules::ebay::Product::search_resume_with).
+///
+/// Checkpoints are saved as JSON. The format is versioned: [`SearchCheckpoint::load`] refuses
+/// checkpoints written with a different [`VERSION`](SearchCheckpoint::VERSION).
+///
+/// ## Example
+/// ```txt
+/// {
+///   "version": 1,
+///   "query": "thinkpad x220",
+///   "page": 3,
+///   "done": [1234567890, 1234567891],
+///   "results": [...]
+/// }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: DeserializeOwned"))]
+pub struct SearchCheckpoint<T> {
+    /// The version of the checkpoint format.
+    pub version: u32,
+    /// What was searched for.
+    pub query: String,
+    /// The results page being collected, counting from 1.
+    pub page: u32,
+    /// The ids of the results already collected from `page`.
+    pub done: Vec<u64>,
+    /// Every result collected so far.
+    pub results: Vec<T>,
+}
+
+impl<T> SearchCheckpoint<T> {
+    /// The current version of the checkpoint format.
+    pub const VERSION: u32 = 1;
+
+    /// A checkpoint for a search that hasn't started yet.
+    pub fn new(query: &str) -> Self {
+        Self {
+            version: Self::VERSION,
+            query: query.to_string(),
+            page: 1,
+            done: Vec::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// Record a result that was collected from results page `page`.
+    pub fn record(&mut self, page: u32, id: u64, result: T) {
+        if page != self.page {
+            /* everything on the earlier pages is done */
+            self.page = page;
+            self.done.clear();
+        }
+        self.done.push(id);
+        self.results.push(result);
+    }
+}
+
+impl<T: DeserializeOwned> SearchCheckpoint<T> {
+    /// Read a checkpoint, or `None` if there isn't one at `path`.
+    ///
+    /// # Errors
+    /// Errors if the file could not be read, or isn't a checkpoint of the current version.
+    pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Option<Self>> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("could not read checkpoint {}", path.display()))?;
+        let version = serde_json::from_str::<serde_json::Value>(&text)
+            .ok()
+            .and_then(|v| v.get("version")?.as_u64());
+        if version != Some(Self::VERSION.into()) {
+            bail!(
+                "{} is not a version {} checkpoint",
+                path.display(),
+                Self::VERSION
+            );
+        }
+        serde_json::from_str(&text)
+            .map(Some)
+            .with_context(|| format!("bad checkpoint {}", path.display()))
+    }
+
+    /// Read the checkpoint at `path` if there is one, or start a new one. The checkpoint must
+    /// be for the same query.
+    ///
+    /// # Errors
+    /// Errors if the checkpoint could not be loaded, or is for a different query.
+    pub fn resume<P: AsRef<Path>>(path: P, query: &str) -> anyhow::Result<Self> {
+        match Self::load(&path)? {
+            Some(checkpoint) if checkpoint.query != query => bail!(
+                "{} is a checkpoint for `{}`, not `{}`",
+                path.as_ref().display(),
+                checkpoint.query,
+                query
+            ),
+            Some(checkpoint) => Ok(checkpoint),
+            None => Ok(Self::new(query)),
+        }
+    }
+}
+
+impl<T: Serialize> SearchCheckpoint<T> {
+    /// Write the checkpoint to `path`.
+    ///
+    /// The checkpoint is written next to `path` first, then moved there, so an interrupted
+    /// save leaves the previous checkpoint intact.
+    ///
+    /// # Errors
+    /// Errors if the file could not be written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let mut partial = path.as_os_str().to_owned();
+        partial.push(".partial");
+
+        fs::write(&partial, serde_json::to_vec(self)?)
+            .with_context(|| format!("could not write checkpoint {}", path.display()))?;
+        fs::rename(&partial, path)
+            .with_context(|| format!("could not write checkpoint {}", path.display()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut checkpoint = SearchCheckpoint::new("thinkpad");
+        checkpoint.record(1, 10, "a");
+        checkpoint.record(1, 11, "b");
+        assert_eq!((checkpoint.page, &checkpoint.done[..]), (1, &[10, 11][..]));
+
+        checkpoint.record(2, 20, "c");
+        assert_eq!((checkpoint.page, &checkpoint.done[..]), (2, &[20][..]));
+        assert_eq!(checkpoint.results, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn test_save_and_resume() {
+        let path = std::env::temp_dir().join(format!(
+            "datacollect-checkpoint-{}.json",
+            rand::random::<u64>()
+        ));
+
+        assert!(SearchCheckpoint::<String>::load(&path).unwrap().is_none());
+
+        let mut checkpoint = SearchCheckpoint::new("thinkpad");
+        checkpoint.record(2, 20, "c".to_string());
+        checkpoint.save(&path).unwrap();
+
+        let resumed = SearchCheckpoint::<String>::resume(&path, "thinkpad").unwrap();
+        assert_eq!(resumed.page, 2);
+        assert_eq!(resumed.results, vec!["c".to_string()]);
+        assert!(SearchCheckpoint::<String>::resume(&path, "x220").is_err());
+
+        fs::write(&path, r#"{"version": 0, "query": "thinkpad"}"#).unwrap();
+        assert!(SearchCheckpoint::<String>::load(&path).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,725 @@
+//! One configured entry point for every module.
+//!
+//! A [`Datacollect`] owns the [`Client`]s, cache, rate limit and [`Credentials`] for a
+//! collection, and hands out a handle per module that uses them, so that configuration
+//! is set up once and shared instead of repeated at every call site.
+//!
+//! ## Example
+//! ```txt
+//! let dc = Datacollect::builder()
+//!     .cache(Cache::new("/tmp/datacollect"))
+//!     .rate_limit(RateLimit::per_host(Duration::from_secs(1)))
+//!     .build()?;
+//! let product = dc.ebay().by_id(254625474154, Detail::Default).await?;
+//! let record = dc.rdap().domain("example.com", Detail::Minimal).await?;
+//! ```
+
+use std::{path::Path, sync::Arc};
+
+use chrono::NaiveDate;
+use futures::Stream;
+
+use crate::{
+    checkpoint::SearchCheckpoint,
+    common::{
+        auth::{LoginFlow, Session},
+        cache::Cache,
+        cookies::CookieJars,
+        ratelimit::RateLimit,
+        robots::RobotsPolicy,
+        Client, ClientConfig, Collected, Credentials, Detail,
+    },
+    modules::{
+        bestbuy, craigslist, ebay, etsy, geekbench, google_shopping, openlibrary, passmark, rdap,
+        stocks, techpowerup, userbenchmark,
+        wayback::{self, Archived},
+        weather, wikidata,
+    },
+};
+
+/// Builds a [`Datacollect`]; see [`Datacollect::builder`].
+#[derive(Default)]
+pub struct Builder {
+    config: ClientConfig,
+    credentials: Option<Credentials>,
+    cookie_jars: CookieJars,
+}
+
+impl Builder {
+    /// Send every request through a proxy, e.g. `http://127.0.0.1:3128`.
+    pub fn proxy(mut self, proxy: &str) -> Self {
+        self.config.proxy = Some(proxy.to_string());
+        self
+    }
+
+    /// Cache GET responses.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.config.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Leave time between requests to the same host, across every module.
+    pub fn rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.config.rate_limit = Some(Arc::new(rate_limit));
+        self
+    }
+
+    /// Follow robots.txt for every request.
+    pub fn robots(mut self, policy: RobotsPolicy) -> Self {
+        self.config.robots = Some(Arc::new(policy));
+        self
+    }
+
+    /// Keep each module's cookies in `<dir>/<module>.json`, so that sessions last between
+    /// runs. By default they're only kept in memory.
+    pub fn cookie_dir<P: AsRef<Path>>(mut self, dir: P) -> Self {
+        self.cookie_jars = CookieJars::in_dir(dir);
+        self
+    }
+
+    /// Use these credentials, instead of loading them with [`Credentials::load`].
+    pub fn credentials(mut self, credentials: Credentials) -> Self {
+        self.credentials = Some(credentials);
+        self
+    }
+
+    /// # Errors
+    /// Errors if the proxy URL is invalid, or if the credentials file could not be loaded.
+    pub fn build(self) -> anyhow::Result<Datacollect> {
+        Ok(Datacollect {
+            client: Client::from_config(&self.config)?,
+            cookie_client: Client::from_config(&self.config)?,
+            cookie_jars: self.cookie_jars,
+            credentials: match self.credentials {
+                Some(credentials) => credentials,
+                None => Credentials::load()?,
+            },
+            config: self.config,
+        })
+    }
+}
+
+/// A configured collector; see the [module documentation](self).
+#[derive(Clone)]
+pub struct Datacollect {
+    client: Client<false>,
+    /// For the modules that need cookies, which each get their own jar from `cookie_jars`,
+    /// so that their sessions persist between calls.
+    cookie_client: Client<true>,
+    cookie_jars: CookieJars,
+    credentials: Credentials,
+    config: ClientConfig,
+}
+
+impl Datacollect {
+    pub fn builder() -> Builder {
+        Builder::default()
+    }
+
+    /// A client without cookies, configured like the module handles are.
+    pub fn client(&self) -> Client<false> {
+        self.client.clone()
+    }
+
+    /// A client with cookies, configured like the module handles are.
+    pub fn cookie_client(&self) -> Client<true> {
+        self.cookie_client.clone()
+    }
+
+    /// A client with the cookie jar called `name` (e.g. a module's, or a host's), which no
+    /// other name's client shares.
+    pub fn cookie_client_for(&self, name: &str) -> Client<true> {
+        self.cookie_client
+            .clone()
+            .with_cookie_jar(self.cookie_jars.jar(name))
+    }
+
+    /// A client logged in to `module` with `flow`, using the [`Credentials`] and the
+    /// module's cookie jar (see [`auth`](crate::common::auth)).
+    pub fn session_client(&self, module: &str, flow: LoginFlow) -> Client<true> {
+        self.cookie_client_for(module).with_session(Session::new(
+            module,
+            flow,
+            self.credentials.clone(),
+        ))
+    }
+
+    pub fn cookie_jars(&self) -> &CookieJars {
+        &self.cookie_jars
+    }
+
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    pub fn ebay(&self) -> Ebay {
+        Ebay {
+            client: self.client(),
+            endpoints: ebay::Endpoints::default(),
+        }
+    }
+
+    /// The purchase history of the eBay account in the `ebay` credentials; see
+    /// [`ebay::PurchaseHistory`].
+    pub fn ebay_purchases(&self) -> impl Stream<Item = anyhow::Result<ebay::Purchase>> {
+        ebay::PurchaseHistory::purchases(
+            self.session_client("ebay", ebay::PurchaseHistory::login_flow()),
+        )
+    }
+
+    pub fn etsy(&self) -> Etsy {
+        Etsy {
+            client: self.client(),
+            endpoints: etsy::Endpoints::default(),
+        }
+    }
+
+    /// Craigslist, for one region (e.g. `sfbay`).
+    pub fn craigslist(&self, region: &str) -> Craigslist {
+        Craigslist {
+            client: self.client(),
+            endpoints: craigslist::for_region(region),
+        }
+    }
+
+    /// Best Buy, with the API key from the [`Credentials`].
+    ///
+    /// # Errors
+    /// Errors if there is no Best Buy API key configured.
+    pub fn bestbuy(&self) -> anyhow::Result<Bestbuy> {
+        Ok(Bestbuy {
+            client: self.client(),
+            endpoints: bestbuy::Endpoints::default(),
+            key: self.credentials.require("bestbuy", "api_key")?.to_string(),
+        })
+    }
+
+    pub fn rdap(&self) -> Rdap {
+        Rdap {
+            client: self.client(),
+            endpoints: rdap::Endpoints::default(),
+        }
+    }
+
+    pub fn passmark(&self) -> Passmark {
+        Passmark {
+            client: self.cookie_client_for("passmark"),
+            endpoints: passmark::Endpoints::default(),
+        }
+    }
+
+    pub fn geekbench(&self) -> Geekbench {
+        Geekbench {
+            client: self.client(),
+            endpoints: geekbench::Endpoints::default(),
+        }
+    }
+
+    pub fn techpowerup(&self) -> Techpowerup {
+        Techpowerup {
+            client: self.client(),
+            endpoints: techpowerup::Endpoints::default(),
+        }
+    }
+
+    pub fn google_shopping(&self) -> GoogleShopping {
+        GoogleShopping {
+            client: self.client(),
+            endpoints: google_shopping::Endpoints::default(),
+        }
+    }
+
+    pub fn userbenchmark(&self) -> Userbenchmark {
+        Userbenchmark {
+            client: self.client(),
+            endpoints: userbenchmark::Endpoints::default(),
+        }
+    }
+
+    pub fn openlibrary(&self) -> Openlibrary {
+        Openlibrary {
+            client: self.client(),
+            endpoints: openlibrary::Endpoints::default(),
+        }
+    }
+
+    pub fn stocks(&self) -> Stocks {
+        Stocks {
+            client: self.client(),
+            endpoints: stocks::Endpoints::default(),
+        }
+    }
+
+    pub fn weather(&self) -> Weather {
+        Weather {
+            client: self.client(),
+            endpoints: weather::Endpoints::default(),
+        }
+    }
+
+    pub fn wikidata(&self) -> Wikidata {
+        Wikidata {
+            client: self.client(),
+            endpoints: wikidata::Endpoints::default(),
+        }
+    }
+}
+
+/// Handles share their [`Datacollect`]'s clients; `endpoints` points a handle elsewhere
+/// (e.g. a regional site, or a mock server).
+macro_rules! handle {
+    ($(#[$meta:meta])* $name:ident, $module:ident, $cookies:literal) => {
+        $(#[$meta])*
+        #[derive(Clone)]
+        pub struct $name {
+            client: Client<$cookies>,
+            endpoints: $module::Endpoints,
+        }
+
+        impl $name {
+            /// Use different [`Endpoints`]($module::Endpoints) for this handle.
+            pub fn endpoints(self, endpoints: $module::Endpoints) -> Self {
+                Self { endpoints, ..self }
+            }
+        }
+    };
+}
+
+handle!(
+    /// The [`ebay`] module, from [`Datacollect::ebay`].
+    Ebay, ebay, false
+);
+handle!(
+    /// The [`etsy`] module, from [`Datacollect::etsy`].
+    Etsy, etsy, false
+);
+handle!(
+    /// The [`craigslist`] module, from [`Datacollect::craigslist`].
+    Craigslist, craigslist, false
+);
+handle!(
+    /// The [`rdap`] module, from [`Datacollect::rdap`].
+    Rdap, rdap, false
+);
+handle!(
+    /// The [`passmark`] module, from [`Datacollect::passmark`].
+    Passmark, passmark, true
+);
+handle!(
+    /// The [`geekbench`] module, from [`Datacollect::geekbench`].
+    Geekbench, geekbench, false
+);
+handle!(
+    /// The [`techpowerup`] module, from [`Datacollect::techpowerup`].
+    Techpowerup, techpowerup, false
+);
+handle!(
+    /// The [`google_shopping`] module, from [`Datacollect::google_shopping`].
+    GoogleShopping, google_shopping, false
+);
+handle!(
+    /// The [`userbenchmark`] module, from [`Datacollect::userbenchmark`].
+    Userbenchmark, userbenchmark, false
+);
+handle!(
+    /// The [`openlibrary`] module, from [`Datacollect::openlibrary`].
+    Openlibrary, openlibrary, false
+);
+handle!(
+    /// The [`stocks`] module, from [`Datacollect::stocks`].
+    Stocks, stocks, false
+);
+handle!(
+    /// The [`weather`] module, from [`Datacollect::weather`].
+    Weather, weather, false
+);
+handle!(
+    /// The [`wikidata`] module, from [`Datacollect::wikidata`].
+    Wikidata, wikidata, false
+);
+
+/// The [`bestbuy`] module, from [`Datacollect::bestbuy`].
+#[derive(Clone)]
+pub struct Bestbuy {
+    client: Client<false>,
+    endpoints: bestbuy::Endpoints,
+    key: String,
+}
+
+impl Bestbuy {
+    /// Use different [`Endpoints`](bestbuy::Endpoints) for this handle.
+    pub fn endpoints(self, endpoints: bestbuy::Endpoints) -> Self {
+        Self { endpoints, ..self }
+    }
+
+    /// See [`bestbuy::Product::by_sku`].
+    pub async fn by_sku(&self, sku: u64) -> anyhow::Result<Option<bestbuy::Product>> {
+        bestbuy::Product::by_sku_with(&self.endpoints, &mut self.client.clone(), &self.key, sku)
+            .await
+    }
+
+    /// See [`bestbuy::Product::by_sku_enveloped`].
+    pub async fn by_sku_enveloped(
+        &self,
+        sku: u64,
+    ) -> anyhow::Result<Option<Collected<bestbuy::Product>>> {
+        bestbuy::Product::by_sku_enveloped_with(
+            &self.endpoints,
+            &mut self.client.clone(),
+            &self.key,
+            sku,
+        )
+        .await
+    }
+
+    /// See [`bestbuy::Product::search`].
+    pub fn search<'a>(
+        &'a self,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<bestbuy::Product>> + 'a {
+        bestbuy::Product::search_using(self.client.clone(), &self.endpoints, &self.key, query)
+    }
+}
+
+impl Ebay {
+    /// See [`ebay::Product::by_id`].
+    pub async fn by_id(&self, id: u64, detail: Detail) -> anyhow::Result<ebay::Product> {
+        ebay::Product::by_id_with(&self.endpoints, &mut self.client.clone(), id, detail).await
+    }
+
+    /// See [`ebay::Product::by_id_enveloped`].
+    pub async fn by_id_enveloped(
+        &self,
+        id: u64,
+        detail: Detail,
+    ) -> anyhow::Result<Collected<ebay::Product>> {
+        ebay::Product::by_id_enveloped_with(&self.endpoints, &mut self.client.clone(), id, detail)
+            .await
+    }
+
+    /// See [`ebay::Product::search`].
+    pub fn search<'a>(
+        &self,
+        query: &'a str,
+        detail: Detail,
+    ) -> impl Stream<Item = anyhow::Result<ebay::Product>> + 'a {
+        self.search_with_options(query, detail, Default::default())
+    }
+
+    /// See [`ebay::Product::search_with_options`].
+    pub fn search_with_options<'a>(
+        &self,
+        query: &'a str,
+        detail: Detail,
+        options: ebay::SearchOptions,
+    ) -> impl Stream<Item = anyhow::Result<ebay::Product>> + 'a {
+        ebay::Product::search_using(self.client.clone(), &self.endpoints, query, detail, options)
+    }
+
+    /// See [`ebay::Product::search_pages`].
+    pub fn search_pages<'a>(
+        &self,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<ebay::SearchPage>> + 'a {
+        ebay::Product::search_pages_using(self.client.clone(), &self.endpoints, query)
+    }
+
+    /// See [`ebay::PriceGuide::for_query`].
+    pub async fn price_guide(
+        &self,
+        query: &str,
+        window: chrono::Duration,
+    ) -> anyhow::Result<ebay::PriceGuide> {
+        ebay::PriceGuide::for_query_with(&self.endpoints, self.client.clone(), query, window).await
+    }
+
+    /// See [`ebay::Store::listings`].
+    pub fn store_listings(
+        &self,
+        store: &str,
+    ) -> impl Stream<Item = anyhow::Result<ebay::SearchResultSummary>> {
+        ebay::Store::listings_with(&self.endpoints, self.client.clone(), store)
+    }
+
+    /// See [`ebay::Category::browse`].
+    pub fn category_listings(
+        &self,
+        id: u64,
+    ) -> impl Stream<Item = anyhow::Result<ebay::SearchResultSummary>> {
+        ebay::Category::browse_with(&self.endpoints, self.client.clone(), id)
+    }
+
+    /// See [`ebay::Product::search_resume_with`].
+    pub fn search_resume<'a>(
+        &self,
+        query: &'a str,
+        detail: Detail,
+        options: ebay::SearchOptions,
+        checkpoint: &SearchCheckpoint<ebay::Product>,
+    ) -> impl Stream<Item = anyhow::Result<(u32, ebay::Product)>> + 'a {
+        ebay::Product::search_from_using(
+            self.client.clone(),
+            &self.endpoints,
+            query,
+            detail,
+            options,
+            checkpoint.page,
+            checkpoint.done.iter().copied().collect(),
+        )
+    }
+}
+
+impl Etsy {
+    /// See [`etsy::Listing::by_id`].
+    pub async fn by_id(&self, id: u64) -> anyhow::Result<etsy::Listing> {
+        etsy::Listing::by_id_with(&self.endpoints, &mut self.client.clone(), id).await
+    }
+
+    /// See [`etsy::Listing::by_id_enveloped`].
+    pub async fn by_id_enveloped(&self, id: u64) -> anyhow::Result<Collected<etsy::Listing>> {
+        etsy::Listing::by_id_enveloped_with(&self.endpoints, &mut self.client.clone(), id).await
+    }
+
+    /// See [`etsy::Listing::search`].
+    pub fn search<'a>(
+        &self,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<etsy::Listing>> + 'a {
+        etsy::Listing::search_using(self.client.clone(), &self.endpoints, query)
+    }
+}
+
+impl Craigslist {
+    /// See [`craigslist::Listing::search`].
+    pub fn search<'a>(
+        &self,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<craigslist::Listing>> + 'a {
+        craigslist::Listing::search_using(self.client.clone(), &self.endpoints, query)
+    }
+}
+
+impl Rdap {
+    /// See [`rdap::DomainRecord::get`].
+    pub async fn domain(
+        &self,
+        domain: &str,
+        detail: Detail,
+    ) -> anyhow::Result<Option<rdap::DomainRecord>> {
+        rdap::DomainRecord::get_with(&self.endpoints, &mut self.client.clone(), domain, detail)
+            .await
+    }
+
+    /// See [`rdap::Entity::get`]. The registry asked is the one in this handle's
+    /// [`Endpoints`](rdap::Endpoints), which has to be set, as `rdap.org` doesn't serve entities.
+    pub async fn entity(&self, handle: &str) -> anyhow::Result<Option<rdap::Entity>> {
+        rdap::Entity::get(&mut self.client.clone(), handle, &self.endpoints).await
+    }
+
+    /// See [`rdap::Entity::search`], searching the registry in this handle's
+    /// [`Endpoints`](rdap::Endpoints).
+    pub async fn entity_search(&self, name: &str) -> anyhow::Result<Vec<rdap::Entity>> {
+        rdap::Entity::search(
+            &mut self.client.clone(),
+            name,
+            std::slice::from_ref(&self.endpoints),
+        )
+        .await
+    }
+}
+
+impl Passmark {
+    /// See [`passmark::CPUMegaList::get`].
+    pub async fn mega_list(&self) -> anyhow::Result<passmark::CPUMegaList> {
+        passmark::CPUMegaList::get_with(&self.endpoints, &mut self.client.clone()).await
+    }
+
+    /// See [`passmark::CPUMegaList::get_historical`].
+    pub async fn mega_list_at(
+        &self,
+        date: NaiveDate,
+    ) -> anyhow::Result<Archived<passmark::CPUMegaList>> {
+        passmark::CPUMegaList::get_historical_with(
+            &self.endpoints,
+            &wayback::Endpoints::default(),
+            &mut self.client.clone(),
+            date,
+        )
+        .await
+    }
+}
+
+impl Geekbench {
+    /// See [`geekbench::BenchmarkResult::by_id`].
+    pub async fn by_id(&self, id: u64) -> anyhow::Result<geekbench::BenchmarkResult> {
+        geekbench::BenchmarkResult::by_id_with(&self.endpoints, &mut self.client.clone(), id).await
+    }
+
+    /// See [`geekbench::BenchmarkResult::by_id_enveloped`].
+    pub async fn by_id_enveloped(
+        &self,
+        id: u64,
+    ) -> anyhow::Result<Collected<geekbench::BenchmarkResult>> {
+        geekbench::BenchmarkResult::by_id_enveloped_with(
+            &self.endpoints,
+            &mut self.client.clone(),
+            id,
+        )
+        .await
+    }
+
+    /// See [`geekbench::BenchmarkResult::search`].
+    pub async fn search(
+        &self,
+        query: &str,
+        page: u32,
+    ) -> anyhow::Result<Vec<geekbench::BenchmarkResult>> {
+        geekbench::BenchmarkResult::search_with(
+            &self.endpoints,
+            &mut self.client.clone(),
+            query,
+            page,
+        )
+        .await
+    }
+}
+
+impl Techpowerup {
+    /// See [`techpowerup::CPUSpecs::by_url`].
+    pub async fn by_url(&self, url: &str) -> anyhow::Result<techpowerup::CPUSpecs> {
+        techpowerup::CPUSpecs::by_url(&mut self.client.clone(), url).await
+    }
+
+    /// See [`techpowerup::CPUSpecs::by_url_enveloped`].
+    pub async fn by_url_enveloped(
+        &self,
+        url: &str,
+    ) -> anyhow::Result<Collected<techpowerup::CPUSpecs>> {
+        techpowerup::CPUSpecs::by_url_enveloped(&mut self.client.clone(), url).await
+    }
+
+    /// See [`techpowerup::CPUSpecs::search`].
+    pub async fn search(&self, query: &str) -> anyhow::Result<Vec<techpowerup::SearchResult>> {
+        techpowerup::CPUSpecs::search_with(&self.endpoints, &mut self.client.clone(), query).await
+    }
+}
+
+impl GoogleShopping {
+    /// See [`google_shopping::Offer::search`].
+    pub async fn search(&self, query: &str) -> anyhow::Result<Vec<google_shopping::Offer>> {
+        google_shopping::Offer::search_with(&self.endpoints, &mut self.client.clone(), query).await
+    }
+}
+
+impl Userbenchmark {
+    /// See [`userbenchmark::Part::list`].
+    pub async fn list(
+        &self,
+        category: userbenchmark::Category,
+    ) -> anyhow::Result<Vec<userbenchmark::Part>> {
+        userbenchmark::Part::list_with(&self.endpoints, &mut self.client.clone(), category).await
+    }
+}
+
+impl Openlibrary {
+    /// See [`openlibrary::Book::by_isbn`].
+    pub async fn by_isbn(&self, isbn: &str) -> anyhow::Result<Option<openlibrary::Book>> {
+        openlibrary::Book::by_isbn_with(&self.endpoints, &mut self.client.clone(), isbn).await
+    }
+}
+
+impl Stocks {
+    /// See [`stocks::Quote::get`].
+    pub async fn quote(&self, symbol: &str) -> anyhow::Result<Option<stocks::Quote>> {
+        stocks::Quote::get_with(&self.endpoints, &mut self.client.clone(), symbol).await
+    }
+
+    /// See [`stocks::Quote::history`].
+    pub async fn history(
+        &self,
+        symbol: &str,
+        range: stocks::Range,
+    ) -> anyhow::Result<Vec<stocks::Quote>> {
+        stocks::Quote::history_with(&self.endpoints, &mut self.client.clone(), symbol, range).await
+    }
+}
+
+impl Weather {
+    /// See [`weather::Observation::current`].
+    pub async fn current(
+        &self,
+        latitude: f64,
+        longitude: f64,
+    ) -> anyhow::Result<weather::Observation> {
+        weather::Observation::current_with(
+            &self.endpoints,
+            &mut self.client.clone(),
+            latitude,
+            longitude,
+        )
+        .await
+    }
+}
+
+impl Wikidata {
+    /// See [`wikidata::Entity::by_id`].
+    pub async fn by_id(&self, id: &str) -> anyhow::Result<Option<wikidata::Entity>> {
+        wikidata::Entity::by_id_with(&self.endpoints, &mut self.client.clone(), id).await
+    }
+
+    /// See [`wikidata::Entity::search`].
+    pub async fn search(&self, query: &str) -> anyhow::Result<Vec<wikidata::SearchResult>> {
+        wikidata::Entity::search_with(&self.endpoints, &mut self.client.clone(), query).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::Datacollect;
+    use crate::{
+        common::{Credentials, Detail},
+        modules::{craigslist, rdap},
+        testing::MockServer,
+    };
+
+    #[tokio::test]
+    async fn test_handles() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/domain/example.net", 404, "")
+            .mock("/search/sss", 200, "<p>nothing here</p>");
+
+        let dc = Datacollect::builder()
+            .credentials(Credentials::default())
+            .build()
+            .unwrap();
+        assert!(dc.bestbuy().is_err());
+
+        let record = dc
+            .rdap()
+            .endpoints(rdap::Endpoints { base: server.uri() })
+            .domain("example.net", Detail::Minimal)
+            .await
+            .unwrap();
+        assert!(record.is_none());
+
+        let listings = dc
+            .craigslist("sfbay")
+            .endpoints(craigslist::Endpoints { base: server.uri() })
+            .search("cpu")
+            .collect::<Vec<_>>()
+            .await;
+        assert!(listings.is_empty());
+
+        assert_eq!(
+            server.requests(),
+            vec!["/domain/example.net", "/search/sss?query=cpu&s=0"]
+        );
+    }
+}
@@ -0,0 +1,436 @@
+//! Logging in, for data that is only shown to a signed-in account.
+//!
+//! A module declares how to log in to its site with a [`LoginFlow`], and a [`Session`]
+//! attached to a [`Client<true>`](super::Client) (see [`Client::with_session`]) logs in
+//! before the first request that needs it, with credentials from the [`Credentials`]
+//! store. After that, every request is sent with the session: the cookies a login form
+//! set, or the token a token exchange returned. If the site says the session is over,
+//! the session logs in again and the request is retried once.
+//!
+//! ## Example
+//! ```txt
+//! let flow = LoginFlow::Form {
+//!     url: "https://example.com/login".to_string(),
+//!     fields: vec![
+//!         ("user".to_string(), Field::Credential("username".to_string())),
+//!         ("pass".to_string(), Field::Credential("password".to_string())),
+//!     ],
+//!     failure: Some("Incorrect password".to_string()),
+//! };
+//! let session = Session::new("example", flow, Credentials::load()?);
+//! let client = Client::<true>::default().with_session(session);
+//! ```
+
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use reqwest::{StatusCode, Url};
+use serde::Deserialize;
+use tokio::sync::Mutex;
+
+use super::{http::Response, Client, Credentials};
+
+/// The value of one field of a login request.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Field {
+    /// The module's credential with this name, e.g. `password` (see [`Credentials`]).
+    Credential(String),
+    /// A fixed value, e.g. `grant_type=client_credentials`.
+    Value(String),
+}
+
+/// How to log in to a site.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum LoginFlow {
+    /// POST a form, like a login page does. The session is the cookies the response sets.
+    Form {
+        url: String,
+        fields: Vec<(String, Field)>,
+        /// Text that only the page after a failed login has, e.g. `Incorrect password`,
+        /// for sites that answer a failed login with a 200.
+        failure: Option<String>,
+    },
+    /// POST a form to exchange credentials for a token (e.g. an OAuth 2 token endpoint),
+    /// which is sent with every request as `Authorization: Bearer <token>`.
+    ///
+    /// The response is JSON with the token in `access_token`, and optionally how many
+    /// seconds it lasts in `expires_in`.
+    Token {
+        url: String,
+        fields: Vec<(String, Field)>,
+    },
+}
+
+impl LoginFlow {
+    fn url(&self) -> &str {
+        match self {
+            Self::Form { url, .. } | Self::Token { url, .. } => url,
+        }
+    }
+
+    fn fields(&self) -> &[(String, Field)] {
+        match self {
+            Self::Form { fields, .. } | Self::Token { fields, .. } => fields,
+        }
+    }
+}
+
+/// A login that failed, as opposed to a login request that couldn't be sent.
+#[derive(Debug)]
+pub struct LoginError {
+    pub module: String,
+    pub reason: String,
+}
+
+impl Display for LoginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "could not log in to {}: {}", self.module, self.reason)
+    }
+}
+
+impl std::error::Error for LoginError {}
+
+/// The JSON response of a [`LoginFlow::Token`] exchange.
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+/* tokens are renewed a little early, so one doesn't run out in the middle of a request */
+const TOKEN_MARGIN: Duration = Duration::from_secs(30);
+
+enum State {
+    LoggedOut,
+    /// Logged in with a form; the session is in the client's cookie jar.
+    Cookies,
+    Token {
+        token: String,
+        expires: Option<Instant>,
+    },
+}
+
+/// A logged-in session with one module's site; see the [module documentation](self).
+pub struct Session {
+    module: String,
+    flow: LoginFlow,
+    credentials: Credentials,
+    state: Mutex<State>,
+}
+
+impl Session {
+    /// A session that logs in to `module` (e.g. `ebay`) with `flow`, taking the
+    /// [`Field::Credential`]s from `credentials`.
+    pub fn new(module: &str, flow: LoginFlow, credentials: Credentials) -> Self {
+        Self {
+            module: module.to_string(),
+            flow,
+            credentials,
+            state: Mutex::new(State::LoggedOut),
+        }
+    }
+
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    /// Whether the session has logged in (and its token, if it has one, hasn't expired).
+    pub async fn is_logged_in(&self) -> bool {
+        match &*self.state.lock().await {
+            State::LoggedOut => false,
+            State::Cookies => true,
+            State::Token { expires, .. } => expires.is_none_or(|at| at > Instant::now()),
+        }
+    }
+
+    /// Forget the session, so that the next request logs in again.
+    pub async fn log_out(&self) {
+        *self.state.lock().await = State::LoggedOut;
+    }
+
+    /// Log in if the session hasn't yet, and get the token to send, if the flow has one.
+    ///
+    /// A form session found in the client's cookie jar (e.g. kept from an earlier run)
+    /// is used as it is.
+    ///
+    /// # Errors
+    /// Errors if a credential is missing, if the login request failed, or with a
+    /// [`LoginError`] if the site refused the login.
+    pub async fn authorize(&self, client: &Client<true>) -> anyhow::Result<Option<String>> {
+        let mut state = self.state.lock().await;
+        match &*state {
+            State::Cookies => return Ok(None),
+            State::Token { token, expires } if expires.is_none_or(|at| at > Instant::now()) => {
+                return Ok(Some(token.clone()))
+            }
+            State::LoggedOut if self.has_saved_cookies(client) => {
+                *state = State::Cookies;
+                return Ok(None);
+            }
+            _ => {}
+        }
+
+        *state = self.log_in(client).await?;
+        Ok(match &*state {
+            State::Token { token, .. } => Some(token.clone()),
+            _ => None,
+        })
+    }
+
+    fn has_saved_cookies(&self, client: &Client<true>) -> bool {
+        matches!(self.flow, LoginFlow::Form { .. })
+            && client
+                .cookie_jar()
+                .zip(Url::parse(&client.resolve(self.flow.url())).ok())
+                .is_some_and(|(jar, url)| !jar.cookies_for(&url).is_empty())
+    }
+
+    #[tracing::instrument(skip_all, fields(module = %self.module), err)]
+    async fn log_in(&self, client: &Client<true>) -> anyhow::Result<State> {
+        let fields = self
+            .flow
+            .fields()
+            .iter()
+            .map(|(name, field)| {
+                let value = match field {
+                    Field::Credential(credential) => {
+                        self.credentials.require(&self.module, credential)?
+                    }
+                    Field::Value(value) => value,
+                };
+                Ok((name.as_str(), value))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        /* boxed, since sending a request is what logs in */
+        let response = Box::pin(client.post(self.flow.url()).form(&fields).send()).await?;
+        let refused = |reason: String| LoginError {
+            module: self.module.clone(),
+            reason,
+        };
+        if !response.status().is_success() {
+            return Err(refused(format!("the login returned a {}", response.status())).into());
+        }
+
+        match &self.flow {
+            LoginFlow::Form { failure, .. } => {
+                let text = response.text().await?;
+                if let Some(failure) = failure.as_deref().filter(|f| text.contains(f)) {
+                    return Err(refused(format!("the site said \"{}\"", failure)).into());
+                }
+                tracing::info!("logged in");
+                Ok(State::Cookies)
+            }
+            LoginFlow::Token { .. } => {
+                let token: TokenResponse = serde_json::from_slice(response.bytes())
+                    .context("the token response is not the expected JSON")?;
+                tracing::info!("logged in");
+                Ok(State::Token {
+                    token: token.access_token,
+                    expires: token.expires_in.map(|secs| {
+                        Instant::now() + Duration::from_secs(secs).saturating_sub(TOKEN_MARGIN)
+                    }),
+                })
+            }
+        }
+    }
+
+    /// Whether requests to `url` are sent with the session. A token is only sent to the site
+    /// it came from (the login URL's origin), so that it isn't given to others, e.g. image
+    /// hosts or redirect targets; cookies are kept to their own domains by the cookie jar.
+    pub(crate) fn covers(&self, client: &Client<true>, url: &Url) -> bool {
+        match &self.flow {
+            LoginFlow::Form { .. } => true,
+            LoginFlow::Token { url: login, .. } => {
+                Url::parse(&client.resolve(login)).is_ok_and(|login| login.origin() == url.origin())
+            }
+        }
+    }
+
+    /// Whether the response to a request for `url` says the session is over: a 401, or
+    /// (for forms) being sent to the login page, or to the login page's host from another.
+    pub(crate) fn is_expired(&self, client: &Client<true>, url: &Url, response: &Response) -> bool {
+        if response.status() == StatusCode::UNAUTHORIZED {
+            return true;
+        }
+        let login = match (&self.flow, Url::parse(&client.resolve(self.flow.url()))) {
+            (LoginFlow::Form { .. }, Ok(login)) => login,
+            _ => return false,
+        };
+        let landed = response.url();
+        landed.host_str() == login.host_str()
+            && (landed.path() == login.path() || url.host_str() != login.host_str())
+    }
+}
+
+/// Check that a module's flow only asks for credentials that are configured, so that a
+/// missing one is reported before any requests are made.
+///
+/// # Errors
+/// Errors (as [`Credentials::require`] does) for the first missing credential.
+pub fn check_credentials(
+    module: &str,
+    flow: &LoginFlow,
+    credentials: &Credentials,
+) -> anyhow::Result<()> {
+    for (_, field) in flow.fields() {
+        if let Field::Credential(name) = field {
+            credentials.require(module, name)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Field, LoginError, LoginFlow, Session};
+    use crate::{
+        common::{Client, Credentials},
+        testing::MockServer,
+    };
+
+    fn credentials() -> Credentials {
+        let file = toml::from_str(
+            r#"
+                [shop]
+                username = "me"
+                password = "hunter2"
+            "#,
+        )
+        .unwrap();
+        Credentials::from_sources(Some(file), |_| None)
+    }
+
+    #[tokio::test]
+    async fn test_token_session() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/token",
+                200,
+                r#"{"access_token": "t0k3n", "expires_in": 3600}"#,
+            )
+            .mock("/orders", 200, r#"{"orders": []}"#);
+        let flow = LoginFlow::Token {
+            url: format!("{}/token", server.uri()),
+            fields: vec![
+                (
+                    "grant_type".to_string(),
+                    Field::Value("password".to_string()),
+                ),
+                (
+                    "username".to_string(),
+                    Field::Credential("username".to_string()),
+                ),
+                (
+                    "password".to_string(),
+                    Field::Credential("password".to_string()),
+                ),
+            ],
+        };
+        let session = Session::new("shop", flow, credentials());
+        let client = Client::<true>::default().with_session(session);
+
+        let orders = format!("{}/orders", server.uri());
+        for _ in 0..2 {
+            let response = client.get(&orders).await.unwrap().send().await.unwrap();
+            assert_eq!(response.text().await.unwrap(), r#"{"orders": []}"#);
+        }
+        /* logged in once, for both requests */
+        assert_eq!(server.requests(), vec!["/token", "/orders", "/orders"]);
+        assert!(client.session().unwrap().is_logged_in().await);
+
+        /* a 401 logs in again, and the request is retried once */
+        server.mock("/orders", 401, "");
+        let response = client.get(&orders).await.unwrap().send().await.unwrap();
+        assert_eq!(response.status().as_u16(), 401);
+        assert_eq!(server.requests()[3..], ["/orders", "/token", "/orders"]);
+    }
+
+    #[tokio::test]
+    async fn test_token_origin() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/token", 200, r#"{"access_token": "t0k3n"}"#)
+            .mock("/orders", 200, r#"{"orders": []}"#);
+        let images = MockServer::start().await.unwrap();
+        images.mock("/1.jpg", 200, "");
+        let flow = LoginFlow::Token {
+            url: format!("{}/token", server.uri()),
+            fields: vec![],
+        };
+        let cassette = std::env::temp_dir().join(format!(
+            "datacollect-session-{}.json",
+            rand::random::<u64>()
+        ));
+        let client = Client::<true>::default()
+            .with_cassette(&cassette)
+            .unwrap()
+            .with_session(Session::new("shop", flow, credentials()));
+
+        /* another site gets neither the token nor a login */
+        let image = format!("{}/1.jpg", images.uri());
+        client.get(&image).await.unwrap().send().await.unwrap();
+        assert!(server.requests().is_empty());
+        assert!(!client.session().unwrap().is_logged_in().await);
+
+        let orders = format!("{}/orders", server.uri());
+        client.get(&orders).await.unwrap().send().await.unwrap();
+        assert_eq!(server.requests(), vec!["/token", "/orders"]);
+
+        /* the account's own pages aren't recorded */
+        let recorded = std::fs::read_to_string(&cassette).unwrap();
+        assert!(recorded.contains("/1.jpg") && !recorded.contains("/orders"));
+        std::fs::remove_file(&cassette).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_form_session() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/login", 200, "<p>Incorrect password</p>")
+            .mock("/account", 200, "<p>hello</p>");
+        let flow = LoginFlow::Form {
+            url: format!("{}/login", server.uri()),
+            fields: vec![
+                (
+                    "user".to_string(),
+                    Field::Credential("username".to_string()),
+                ),
+                (
+                    "pass".to_string(),
+                    Field::Credential("password".to_string()),
+                ),
+            ],
+            failure: Some("Incorrect password".to_string()),
+        };
+
+        let session = Session::new("shop", flow.clone(), credentials());
+        let client = Client::<true>::default().with_session(session);
+        let error = client
+            .get(&format!("{}/account", server.uri()))
+            .await
+            .unwrap()
+            .send()
+            .await
+            .err()
+            .unwrap();
+        assert!(error.downcast_ref::<LoginError>().is_some());
+
+        /* a missing credential fails before anything is sent */
+        let session = Session::new("other", flow, credentials());
+        let client = Client::<true>::default().with_session(session);
+        let requests = server.requests().len();
+        assert!(client
+            .get(&format!("{}/account", server.uri()))
+            .await
+            .unwrap()
+            .send()
+            .await
+            .is_err());
+        assert_eq!(server.requests().len(), requests);
+    }
+}
@@ -0,0 +1,45 @@
+//! Loading pages in a headless browser, for sites that fill in their content (e.g. prices)
+//! with JavaScript. See [`Client::render_js`](super::Client::render_js).
+//!
+//! This needs the `render-js` feature, and Chrome or Chromium installed.
+
+use anyhow::{anyhow, Context};
+use chromiumoxide::{Browser, BrowserConfig};
+use futures::StreamExt;
+
+/// Load `url` in a new headless browser, and return its HTML once the page has loaded.
+pub(crate) async fn render(url: &str, proxy: Option<&str>) -> anyhow::Result<String> {
+    let mut config = BrowserConfig::builder();
+    if let Some(proxy) = proxy {
+        config = config.arg(format!("--proxy-server={}", proxy));
+    }
+    let config = config.build().map_err(|e| anyhow!(e))?;
+
+    let (mut browser, mut handler) = Browser::launch(config)
+        .await
+        .context("could not start the browser (is Chrome or Chromium installed?)")?;
+    /* the handler drives the connection to the browser, so it has to be polled throughout */
+    let events = tokio::spawn(async move {
+        while let Some(event) = handler.next().await {
+            if event.is_err() {
+                break;
+            }
+        }
+    });
+
+    let result = async {
+        let page = browser.new_page(url).await?;
+        page.wait_for_navigation().await?;
+        page.content().await
+    }
+    .await
+    .with_context(|| format!("could not load {}", url));
+
+    /* close the browser even if the page failed, so that it isn't left running */
+    if let Err(e) = browser.close().await {
+        tracing::warn!(error = %e, "could not close the browser");
+    }
+    browser.wait().await.ok();
+    events.abort();
+    result
+}
@@ -0,0 +1,201 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Context;
+use chrono::{TimeZone, Utc};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+
+use super::http::Response;
+
+/// Headers worth keeping in the cache; the rest (cookies, dates, ...) are noise.
+const CACHED_HEADERS: &[&str] = &["content-type", "location"];
+
+#[derive(Deserialize, Serialize)]
+struct Entry {
+    url: String,
+    /// When the response was fetched, in seconds since the Unix epoch.
+    fetched_at: u64,
+    status: u16,
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
+    body: String,
+}
+
+/// An on-disk cache of GET responses, one file per URL.
+///
+/// Only successful responses with text bodies are cached. Unlike a [`Cassette`](super::cassette::Cassette),
+/// entries expire, and requests that aren't cached go to the network as usual.
+///
+/// ## Example
+/// ```txt
+/// let cache = Cache::new("~/.cache/datacollect").ttl(Duration::from_secs(3600));
+/// let client = Client::default().with_cache(cache);
+/// ```
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl Cache {
+    /// Cache responses in `dir` (created when the first response is cached), for a day.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: dir.as_ref().to_path_buf(),
+            ttl: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+
+    /// How long cached responses are used for.
+    pub fn ttl(self, ttl: Duration) -> Self {
+        Self { ttl, ..self }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file a URL's response is cached in.
+    fn path(&self, url: &Url) -> PathBuf {
+        /* FNV-1a, so that file names stay the same between builds */
+        let hash = url
+            .as_str()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+                (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+            });
+        self.dir.join(format!("{:016x}.json", hash))
+    }
+
+    fn now() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default()
+    }
+
+    /// The cached response for a URL, if there is one that hasn't expired.
+    ///
+    /// Unreadable entries are treated as missing.
+    pub(crate) fn get(&self, url: &Url) -> Option<Response> {
+        let file = File::open(self.path(url)).ok()?;
+        let entry: Entry = serde_json::from_reader(file).ok()?;
+        if entry.url != url.as_str()
+            || Self::now().saturating_sub(entry.fetched_at) >= self.ttl.as_secs()
+        {
+            return None;
+        }
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &entry.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes()).ok()?,
+                HeaderValue::from_str(value).ok()?,
+            );
+        }
+        Some(Response {
+            status: StatusCode::from_u16(entry.status).ok()?,
+            url: url.clone(),
+            headers,
+            body: entry.body.into_bytes(),
+            fetched_at: Utc.timestamp_opt(entry.fetched_at as i64, 0).single()?,
+        })
+    }
+
+    /// Cache a response, if it is worth caching.
+    ///
+    /// # Errors
+    /// Errors if the cache directory or file could not be written.
+    pub(crate) fn put(&self, url: &Url, response: &Response) -> anyhow::Result<()> {
+        if !response.status.is_success() {
+            return Ok(());
+        }
+        let body = match std::str::from_utf8(&response.body) {
+            Ok(body) => body.to_string(),
+            Err(_) => return Ok(()),
+        };
+
+        let headers = CACHED_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = response.headers.get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("could not create cache directory {}", self.dir.display()))?;
+        let path = self.path(url);
+        let file = File::create(&path)
+            .with_context(|| format!("could not write cache entry {}", path.display()))?;
+        serde_json::to_writer(
+            file,
+            &Entry {
+                url: url.to_string(),
+                fetched_at: response.fetched_at.timestamp().max(0) as u64,
+                status: response.status.as_u16(),
+                headers,
+                body,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chrono::{Duration as TimeDelta, Utc};
+    use reqwest::{StatusCode, Url};
+
+    use super::Cache;
+    use crate::common::http::Response;
+
+    #[test]
+    fn test_cache() {
+        let dir = std::env::temp_dir().join(format!("datacollect-cache-{}", rand::random::<u64>()));
+        let cache = Cache::new(&dir);
+        let url = Url::parse("https://example.com/a?b=c").unwrap();
+        let response = |status, body: &[u8]| Response {
+            status,
+            url: url.clone(),
+            headers: Default::default(),
+            body: body.to_vec(),
+            fetched_at: Utc::now() - TimeDelta::minutes(5),
+        };
+
+        assert!(cache.get(&url).is_none());
+        cache
+            .put(&url, &response(StatusCode::NOT_FOUND, b"gone"))
+            .unwrap();
+        assert!(cache.get(&url).is_none());
+        cache
+            .put(&url, &response(StatusCode::OK, &[0xff, 0xfe]))
+            .unwrap();
+        assert!(cache.get(&url).is_none());
+
+        cache
+            .put(&url, &response(StatusCode::OK, b"hello"))
+            .unwrap();
+        assert_eq!(cache.get(&url).unwrap().bytes(), b"hello");
+        /* a cached response was fetched when it was cached, not when it's read back */
+        let fetched_at = cache.get(&url).unwrap().fetched_at();
+        assert!(Utc::now() - fetched_at >= TimeDelta::minutes(5));
+
+        let other = Url::parse("https://example.com/a?b=d").unwrap();
+        assert!(cache.get(&other).is_none());
+
+        let expired = Cache::new(&dir).ttl(Duration::ZERO);
+        assert!(expired.get(&url).is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    StatusCode, Url,
+};
+use serde::{Deserialize, Serialize};
+
+use super::http::Response;
+
+/// Headers worth keeping in a cassette; the rest (cookies, dates, ...) are noise.
+const RECORDED_HEADERS: &[&str] = &["content-type", "location"];
+
+/// One recorded request and its response.
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Interaction {
+    pub method: String,
+    pub url: String,
+    pub status: u16,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: String,
+    /// How the body is encoded: `None` if it's the text itself, or `base64` for bodies that
+    /// aren't UTF-8 (e.g. images or gzipped files).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<String>,
+    /// When the response was recorded. Replays of cassettes recorded before this was kept
+    /// are reported as fetched when they're replayed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fetched_at: Option<DateTime<Utc>>,
+}
+
+impl Interaction {
+    /// Encode a body, as text if it is valid UTF-8 (so cassettes can be read and edited), and
+    /// in base64 otherwise.
+    fn encode(body: &[u8]) -> (String, Option<String>) {
+        match std::str::from_utf8(body) {
+            Ok(text) => (text.to_string(), None),
+            Err(_) => (BASE64.encode(body), Some("base64".to_string())),
+        }
+    }
+
+    /// The body as it was received.
+    ///
+    /// # Errors
+    /// Errors if the encoding is unknown, or the body isn't valid for it.
+    fn decode(&self) -> anyhow::Result<Vec<u8>> {
+        match self.encoding.as_deref() {
+            None => Ok(self.body.clone().into_bytes()),
+            Some("base64") => BASE64
+                .decode(&self.body)
+                .context("could not decode a base64 body"),
+            Some(encoding) => bail!("unknown body encoding {}", encoding),
+        }
+    }
+}
+
+/// A file of recorded HTTP interactions, for testing and working offline.
+///
+/// If the file doesn't exist when the cassette is opened, every response is recorded
+/// and the file is written as they come in. If it does exist, responses are replayed
+/// from it instead, and requests that weren't recorded fail.
+///
+/// Requests are matched by method and URL (including the query string). If the same
+/// request was recorded several times, the recordings are replayed in order.
+///
+/// ## Example
+/// ```txt
+/// let mut client = Client::default().with_cassette("fixtures/ebay_by_id.json")?;
+/// let product = Product::by_id(&mut client, 254625474154, Detail::Default).await?;
+/// ```
+pub struct Cassette {
+    path: PathBuf,
+    replaying: bool,
+    interactions: Mutex<Vec<Interaction>>,
+    /// How many times each interaction has been replayed.
+    used: Mutex<Vec<usize>>,
+}
+
+impl Cassette {
+    /// Open a cassette, replaying from it if it exists and recording to it otherwise.
+    ///
+    /// # Errors
+    /// Errors if the file exists but could not be read or parsed.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let replaying = path.exists();
+        let interactions: Vec<Interaction> = if replaying {
+            let file = File::open(&path)
+                .with_context(|| format!("could not open cassette {}", path.display()))?;
+            serde_json::from_reader(file)
+                .with_context(|| format!("could not parse cassette {}", path.display()))?
+        } else {
+            Vec::new()
+        };
+
+        Ok(Self {
+            path,
+            replaying,
+            used: Mutex::new(vec![0; interactions.len()]),
+            interactions: Mutex::new(interactions),
+        })
+    }
+
+    /// Whether responses are being replayed (as opposed to recorded).
+    pub fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    /// Find the recorded response to a request.
+    ///
+    /// # Errors
+    /// Errors if the request was never recorded.
+    pub(crate) fn replay(&self, method: &str, url: &Url) -> anyhow::Result<Response> {
+        let interactions = self.interactions.lock().unwrap();
+        let mut used = self.used.lock().unwrap();
+
+        let matching = interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| i.method == method && i.url == url.as_str())
+            .map(|(n, _)| n)
+            .collect::<Vec<_>>();
+        /* the first recording that hasn't been replayed yet, or the last one if they all have */
+        let n = *matching
+            .iter()
+            .find(|n| used[**n] == 0)
+            .or_else(|| matching.last())
+            .with_context(|| {
+                format!(
+                    "no recorded response for {} {} in {}",
+                    method,
+                    url,
+                    self.path.display()
+                )
+            })?;
+        used[n] += 1;
+
+        let interaction = &interactions[n];
+        let mut headers = HeaderMap::new();
+        for (name, value) in &interaction.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        Ok(Response {
+            status: StatusCode::from_u16(interaction.status)?,
+            url: url.clone(),
+            headers,
+            body: interaction.decode()?,
+            fetched_at: interaction.fetched_at.unwrap_or_else(Utc::now),
+        })
+    }
+
+    /// Add a response to the cassette, and write the cassette to disk.
+    ///
+    /// # Errors
+    /// Errors if the cassette could not be written.
+    pub(crate) fn record(
+        &self,
+        method: &str,
+        url: &Url,
+        response: &Response,
+    ) -> anyhow::Result<()> {
+        let headers = RECORDED_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = response.headers.get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        let (body, encoding) = Interaction::encode(&response.body);
+        let mut interactions = self.interactions.lock().unwrap();
+        interactions.push(Interaction {
+            method: method.to_string(),
+            url: url.to_string(),
+            status: response.status.as_u16(),
+            headers,
+            body,
+            encoding,
+            fetched_at: Some(response.fetched_at),
+        });
+
+        let file = File::create(&self.path)
+            .with_context(|| format!("could not write cassette {}", self.path.display()))?;
+        serde_json::to_writer_pretty(file, &*interactions)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+    use reqwest::Url;
+
+    use super::Cassette;
+    use crate::common::http::Response;
+
+    #[test]
+    fn test_record_and_replay() {
+        let path = std::env::temp_dir().join(format!(
+            "datacollect-cassette-{}.json",
+            rand::random::<u64>()
+        ));
+        let url = Url::parse("https://example.com/a?b=c").unwrap();
+
+        let cassette = Cassette::open(&path).unwrap();
+        assert!(!cassette.is_replaying());
+        for body in ["first", "second"] {
+            let response = Response {
+                status: reqwest::StatusCode::OK,
+                url: url.clone(),
+                headers: Default::default(),
+                body: body.as_bytes().to_vec(),
+                fetched_at: Utc::now() - Duration::days(1),
+            };
+            cassette.record("GET", &url, &response).unwrap();
+        }
+
+        let cassette = Cassette::open(&path).unwrap();
+        assert!(cassette.is_replaying());
+        let body = |r: Response| String::from_utf8(r.bytes().to_vec()).unwrap();
+        let first = cassette.replay("GET", &url).unwrap();
+        assert!(Utc::now() - first.fetched_at() >= Duration::days(1));
+        assert_eq!(body(first), "first");
+        assert_eq!(body(cassette.replay("GET", &url).unwrap()), "second");
+        assert_eq!(body(cassette.replay("GET", &url).unwrap()), "second");
+        assert!(cassette.replay("POST", &url).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_binary_body() {
+        let path = std::env::temp_dir().join(format!(
+            "datacollect-cassette-{}.json",
+            rand::random::<u64>()
+        ));
+        let url = Url::parse("https://example.com/image.png").unwrap();
+        /* not UTF-8: the start of a PNG, and of a gzip stream */
+        let body = [0x89, b'P', b'N', b'G', 0x1f, 0x8b, 0x08, 0x00, 0xff, 0xfe];
+
+        let cassette = Cassette::open(&path).unwrap();
+        let response = Response {
+            status: reqwest::StatusCode::OK,
+            url: url.clone(),
+            headers: Default::default(),
+            body: body.to_vec(),
+            fetched_at: Utc::now(),
+        };
+        cassette.record("GET", &url, &response).unwrap();
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains(r#""encoding": "base64""#));
+
+        let cassette = Cassette::open(&path).unwrap();
+        assert_eq!(cassette.replay("GET", &url).unwrap().bytes(), body);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
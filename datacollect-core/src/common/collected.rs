@@ -0,0 +1,169 @@
+//! Provenance for collected data: which module it came from, from where, and when.
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::report::FieldIssue;
+#[cfg(feature = "net")]
+use super::{
+    http::{Page, Response},
+    report::WithReport,
+};
+
+/// Data from one of the modules, with where and when it was collected, so that a dataset
+/// put together from several sources can be traced back to them.
+///
+/// ## Example
+/// ```txt
+/// {"source": "ebay", "fetched_at": "2021-11-20T14:53:00Z", "url": "https://www.ebay.com/itm/foo/254625474154", "data": {...}}
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct Collected<T> {
+    /// The module the data came from, e.g. `ebay`.
+    pub source: String,
+    /// When the response the data was read from came from the server; for a response from a
+    /// cache or a cassette, when it was first fetched.
+    pub fetched_at: DateTime<Utc>,
+    /// The page or API URL the data was read from, if it came from a single one. For pages,
+    /// this is their canonical URL where they give one, so it's the same however the page
+    /// was linked to.
+    pub url: Option<String>,
+    pub data: T,
+    /// The fields of `data` that the module couldn't fill in, for the modules that report
+    /// them (see [`WithReport`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parse_report: Vec<FieldIssue>,
+}
+
+impl<T> Collected<T> {
+    /// Wrap `data`, fetched at `fetched_at` from `url` by the `source` module.
+    pub fn new(source: &str, fetched_at: DateTime<Utc>, url: Option<String>, data: T) -> Self {
+        Self {
+            source: source.to_string(),
+            fetched_at,
+            url,
+            data,
+            parse_report: Vec::new(),
+        }
+    }
+
+    /// Wrap `data`, read from `page` by the `source` module, keeping its report.
+    #[cfg(feature = "net")]
+    pub fn from_page_with_report(source: &str, page: &Page, data: WithReport<T>) -> Self {
+        Self {
+            parse_report: data.parse_report,
+            ..Self::from_page(source, page, data.data)
+        }
+    }
+
+    /// Wrap `data`, read from `page` by the `source` module, with the page's canonical URL.
+    #[cfg(feature = "net")]
+    pub fn from_page(source: &str, page: &Page, data: T) -> Self {
+        Self::new(
+            source,
+            page.response.fetched_at(),
+            Some(page.canonical_url().to_string()),
+            data,
+        )
+    }
+
+    /// Wrap `data`, read from `response` by the `source` module. `url` is where the data can
+    /// be found again, if the response's own URL can't be given out (e.g. it has an API key).
+    #[cfg(feature = "net")]
+    pub fn from_response(source: &str, response: &Response, url: Option<String>, data: T) -> Self {
+        Self::new(source, response.fetched_at(), url, data)
+    }
+
+    /// Convert the data, keeping where it came from.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Collected<U> {
+        Collected {
+            source: self.source,
+            fetched_at: self.fetched_at,
+            url: self.url,
+            data: f(self.data),
+            parse_report: self.parse_report,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use super::Collected;
+    use crate::common::FieldIssue;
+    #[cfg(feature = "net")]
+    use crate::{common::WithReport, testing::MockServer};
+
+    #[test]
+    fn test_round_trip() {
+        let fetched_at = Utc.with_ymd_and_hms(2021, 11, 20, 14, 53, 0).unwrap();
+        let mut collected = Collected::new(
+            "ebay",
+            fetched_at,
+            Some("https://www.ebay.com/itm/254625474154".to_string()),
+            json!({"name": "The Rust Programming Language"}),
+        );
+
+        /* without a report, there's no `parse_report` at all */
+        let json = serde_json::to_value(&collected).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "source": "ebay",
+                "fetched_at": "2021-11-20T14:53:00Z",
+                "url": "https://www.ebay.com/itm/254625474154",
+                "data": {"name": "The Rust Programming Language"},
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<Collected<serde_json::Value>>(json).unwrap(),
+            collected
+        );
+
+        collected.parse_report = vec![
+            FieldIssue::missing("seller"),
+            FieldIssue::unreadable("price", "`free` is not a price"),
+        ];
+        let json = serde_json::to_string(&collected).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Collected<serde_json::Value>>(&json).unwrap(),
+            collected
+        );
+
+        /* converting the data keeps where it came from, and the report */
+        let mapped = collected.clone().map(|data| data["name"].to_string());
+        assert_eq!(mapped.source, "ebay");
+        assert_eq!(mapped.fetched_at, fetched_at);
+        assert_eq!(mapped.url, collected.url);
+        assert_eq!(mapped.parse_report, collected.parse_report);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_from_page_with_report() {
+        let server = MockServer::start().await.unwrap();
+        server.mock(
+            "/itm/foo/42",
+            200,
+            r#"<link rel="canonical" href="https://www.ebay.com/itm/42"><h1>Graphics card</h1>"#,
+        );
+        let page = server
+            .client::<false>()
+            .get_page(&format!("{}/itm/foo/42", server.uri()))
+            .await
+            .unwrap();
+
+        let mut data = WithReport::new("Graphics card");
+        data.missing_if(true, "seller");
+        let collected = Collected::from_page_with_report("ebay", &page, data);
+        assert_eq!(
+            collected.url.as_deref(),
+            Some("https://www.ebay.com/itm/42")
+        );
+        assert_eq!(collected.data, "Graphics card");
+        assert_eq!(collected.parse_report, [FieldIssue::missing("seller")]);
+    }
+}
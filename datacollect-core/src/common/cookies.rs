@@ -0,0 +1,321 @@
+//! Cookie jars that can be looked into, kept on disk between runs, and kept apart per
+//! module (or per host).
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, TimeZone, Utc};
+use cookie_store::{CookieExpiration, CookieStore};
+use reqwest::{header::HeaderValue, Url};
+use serde::Serialize;
+
+/// One cookie in a [`CookieJar`].
+#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// The host (or domain, for cookies shared with its subdomains) it is sent to.
+    pub domain: String,
+    pub path: String,
+    /// When it expires; `None` for session cookies, which are kept until the jar is cleared.
+    pub expires: Option<DateTime<Utc>>,
+    pub secure: bool,
+    pub http_only: bool,
+}
+
+impl From<&cookie_store::Cookie<'_>> for Cookie {
+    fn from(cookie: &cookie_store::Cookie<'_>) -> Self {
+        Self {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain: String::from(&cookie.domain),
+            path: String::from(&cookie.path),
+            expires: match &cookie.expires {
+                CookieExpiration::AtUtc(at) => Utc.timestamp_opt(at.unix_timestamp(), 0).single(),
+                CookieExpiration::SessionEnd => None,
+            },
+            secure: cookie.secure().unwrap_or_default(),
+            http_only: cookie.http_only().unwrap_or_default(),
+        }
+    }
+}
+
+/// The cookies of a [`Client<true>`](super::Client).
+///
+/// A jar opened from a file is written back to it whenever its cookies change, session
+/// cookies included, so that a session started in one run can be picked up by the next.
+///
+/// ## Example
+/// ```txt
+/// let jar = Arc::new(CookieJar::open("passmark.json")?);
+/// let mut client = Client::<true>::default().with_cookie_jar(jar.clone());
+/// CPUMegaList::get(&mut client).await?;
+/// println!("{:?}", jar.cookies());
+/// ```
+#[derive(Default)]
+pub struct CookieJar {
+    store: RwLock<CookieStore>,
+    path: Option<PathBuf>,
+}
+
+impl CookieJar {
+    /// An empty jar, kept in memory.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open the jar kept at `path`, or start an empty one there if it doesn't exist.
+    ///
+    /// # Errors
+    /// Errors if the file exists but could not be read or parsed.
+    pub fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let store = if path.exists() {
+            let file = File::open(&path)
+                .with_context(|| format!("could not open cookie jar {}", path.display()))?;
+            CookieStore::load_json(BufReader::new(file))
+                .map_err(|e| anyhow::anyhow!("{}", e))
+                .with_context(|| format!("could not parse cookie jar {}", path.display()))?
+        } else {
+            CookieStore::default()
+        };
+        Ok(Self {
+            store: RwLock::new(store),
+            path: Some(path),
+        })
+    }
+
+    /// Every cookie in the jar that hasn't expired.
+    pub fn cookies(&self) -> Vec<Cookie> {
+        self.store
+            .read()
+            .unwrap()
+            .iter_unexpired()
+            .map(Cookie::from)
+            .collect()
+    }
+
+    /// The cookies that would be sent with a request to `url`.
+    pub fn cookies_for(&self, url: &Url) -> Vec<Cookie> {
+        self.store
+            .read()
+            .unwrap()
+            .matches(url)
+            .into_iter()
+            .map(Cookie::from)
+            .collect()
+    }
+
+    /// Add a cookie, as if `url` had responded with a `Set-Cookie` header of `cookie`
+    /// (e.g. `session=abc; Path=/`).
+    ///
+    /// # Errors
+    /// Errors if the cookie could not be parsed, or isn't allowed for `url`.
+    pub fn insert(&self, url: &Url, cookie: &str) -> anyhow::Result<()> {
+        self.store
+            .write()
+            .unwrap()
+            .parse(cookie, url)
+            .map_err(|e| anyhow::anyhow!("bad cookie for {}: {}", url, e))?;
+        self.save()
+    }
+
+    /// Remove every cookie.
+    ///
+    /// # Errors
+    /// Errors if the jar's file could not be written.
+    pub fn clear(&self) -> anyhow::Result<()> {
+        self.store.write().unwrap().clear();
+        self.save()
+    }
+
+    /// Write the jar to its file, if it has one. The file is replaced all at once, so it's
+    /// never left half written, and only the user can read it (on Unix), as the cookies are
+    /// logins.
+    ///
+    /// # Errors
+    /// Errors if the file could not be written.
+    pub fn save(&self) -> anyhow::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let context = || format!("could not write cookie jar {}", path.display());
+        /* jars are saved from whichever request got cookies, so each save has its own file */
+        let mut temp = path.clone().into_os_string();
+        temp.push(format!(".{:x}.tmp", rand::random::<u32>()));
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(&temp).with_context(context)?;
+        let written = (|| -> anyhow::Result<()> {
+            let mut writer = BufWriter::new(file);
+            /* `CookieStore::save_json` leaves out session cookies, which are the ones we want */
+            for cookie in self.store.read().unwrap().iter_unexpired() {
+                writeln!(writer, "{}", serde_json::to_string(cookie)?)?;
+            }
+            writer.flush()?;
+            Ok(())
+        })();
+        if let Err(e) = written.and_then(|_| Ok(std::fs::rename(&temp, path)?)) {
+            let _ = std::fs::remove_file(&temp);
+            return Err(e.context(context()));
+        }
+        Ok(())
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let mut changed = false;
+        {
+            let mut store = self.store.write().unwrap();
+            for header in cookie_headers {
+                if let Ok(header) = header.to_str() {
+                    changed |= store.parse(header, url).is_ok();
+                }
+            }
+        }
+        if changed {
+            if let Err(e) = self.save() {
+                tracing::warn!("{:#}", e);
+            }
+        }
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .store
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+        if header.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&header).ok()
+    }
+}
+
+/// A [`CookieJar`] per name (e.g. per module, or per host), so that sites never see the
+/// cookies that were set for another.
+///
+/// With a directory, each jar is kept in `<dir>/<name>.json` between runs. Clones share
+/// the same jars.
+#[derive(Clone, Default)]
+pub struct CookieJars {
+    dir: Option<PathBuf>,
+    jars: Arc<Mutex<HashMap<String, Arc<CookieJar>>>>,
+}
+
+impl CookieJars {
+    /// Jars kept in memory, for this run only.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Jars kept in files in `dir`, which is created when the first one is written.
+    pub fn in_dir<P: AsRef<Path>>(dir: P) -> Self {
+        Self {
+            dir: Some(dir.as_ref().to_path_buf()),
+            ..Self::default()
+        }
+    }
+
+    /// The jar called `name`.
+    ///
+    /// A jar whose file could not be read is started again, with a warning, rather than
+    /// failing every request that would have used it.
+    pub fn jar(&self, name: &str) -> Arc<CookieJar> {
+        let mut jars = self.jars.lock().unwrap();
+        jars.entry(name.to_string())
+            .or_insert_with(|| {
+                let jar = match &self.dir {
+                    Some(dir) => {
+                        let path = dir.join(format!("{}.json", name));
+                        CookieJar::open(&path).unwrap_or_else(|e| {
+                            tracing::warn!("{:#}; starting a new one", e);
+                            CookieJar {
+                                path: Some(path),
+                                ..CookieJar::default()
+                            }
+                        })
+                    }
+                    None => CookieJar::new(),
+                };
+                Arc::new(jar)
+            })
+            .clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::{cookie::CookieStore, header::HeaderValue, Url};
+
+    use super::{CookieJar, CookieJars};
+
+    #[test]
+    fn test_cookie_jar() {
+        let dir =
+            std::env::temp_dir().join(format!("datacollect-cookies-{}", rand::random::<u64>()));
+        let url = Url::parse("https://www.cpubenchmark.net/CPU_mega_page.html").unwrap();
+        let other = Url::parse("https://www.ebay.com/").unwrap();
+
+        let jars = CookieJars::in_dir(&dir);
+        let jar = jars.jar("passmark");
+        jar.set_cookies(
+            &mut [
+                HeaderValue::from_static("PHPSESSID=abc; Path=/; HttpOnly"),
+                HeaderValue::from_static("old=1; Max-Age=0"),
+            ]
+            .iter(),
+            &url,
+        );
+        assert_eq!(jar.cookies_for(&url).len(), 1);
+        assert!(jar.cookies_for(&other).is_empty());
+        assert_eq!(
+            CookieStore::cookies(&*jar, &url),
+            Some(HeaderValue::from_static("PHPSESSID=abc"))
+        );
+        assert!(jars.jar("ebay").cookies().is_empty());
+
+        /* the session cookie is kept for the next run */
+        let reopened = CookieJars::in_dir(&dir).jar("passmark");
+        let cookies = reopened.cookies();
+        assert_eq!(cookies.len(), 1);
+        assert_eq!(
+            (cookies[0].name.as_str(), cookies[0].value.as_str()),
+            ("PHPSESSID", "abc")
+        );
+        assert!(cookies[0].http_only && cookies[0].expires.is_none());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let metadata = std::fs::metadata(dir.join("passmark.json")).unwrap();
+            assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        }
+
+        reopened.clear().unwrap();
+        assert!(CookieJar::open(dir.join("passmark.json"))
+            .unwrap()
+            .cookies()
+            .is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
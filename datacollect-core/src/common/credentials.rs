@@ -0,0 +1,267 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Serialize;
+
+/// A credential that some module knows how to use.
+struct Known {
+    module: &'static str,
+    name: &'static str,
+    /// The conventional environment variable for it, if there is one.
+    env: Option<&'static str>,
+}
+
+const KNOWN: &[Known] = &[
+    Known {
+        module: "bestbuy",
+        name: "api_key",
+        env: Some("BESTBUY_API_KEY"),
+    },
+    Known {
+        module: "ebay",
+        name: "username",
+        env: None,
+    },
+    Known {
+        module: "ebay",
+        name: "password",
+        env: None,
+    },
+    Known {
+        module: "github",
+        name: "token",
+        env: Some("GITHUB_TOKEN"),
+    },
+];
+
+/// Where a credential was found.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Env,
+    File,
+}
+
+/// Whether one credential is configured, without its value.
+#[derive(Serialize, Clone, Debug)]
+pub struct Status {
+    pub module: String,
+    pub name: String,
+    /// The environment variables that are checked for it, in order.
+    pub env: Vec<String>,
+    /// Where the credential was found, or `None` if it isn't configured.
+    pub source: Option<Source>,
+}
+
+/// API keys and tokens for the modules that need them.
+///
+/// Credentials are looked up by module and name (e.g. `bestbuy` and `api_key`) in:
+///
+/// 1. the environment variable `DATACOLLECT_<MODULE>_<NAME>` (e.g. `DATACOLLECT_BESTBUY_API_KEY`),
+/// 2. the module's conventional environment variable, if it has one (e.g. `BESTBUY_API_KEY`),
+/// 3. the credentials file, by default `~/.config/datacollect/credentials.toml`.
+///
+/// ## Example
+/// ```toml
+/// [bestbuy]
+/// api_key = "..."
+///
+/// [github]
+/// token = "..."
+/// ```
+#[derive(Clone, Default)]
+pub struct Credentials {
+    /// `(module, name) -> (value, source)`
+    values: BTreeMap<(String, String), (String, Source)>,
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        /* never print the values themselves */
+        f.debug_set().entries(self.values.keys()).finish()
+    }
+}
+
+impl Credentials {
+    /// The default location of the credentials file.
+    pub fn default_path() -> Option<PathBuf> {
+        super::config_dir().map(|dir| dir.join("credentials.toml"))
+    }
+
+    /// Load credentials from the environment and the default credentials file (if it exists).
+    ///
+    /// # Errors
+    /// Errors if the credentials file exists but could not be read or parsed.
+    pub fn load() -> anyhow::Result<Self> {
+        match Self::default_path().filter(|path| path.exists()) {
+            Some(path) => Self::from_file(path),
+            None => Ok(Self::from_sources(None, |var| std::env::var(var).ok())),
+        }
+    }
+
+    /// Load credentials from the environment and the given credentials file.
+    ///
+    /// # Errors
+    /// Errors if the file could not be read or parsed.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        let file: BTreeMap<String, BTreeMap<String, String>> =
+            toml::from_str(&text).with_context(|| format!("could not parse {}", path.display()))?;
+        Ok(Self::from_sources(Some(file), |var| {
+            std::env::var(var).ok()
+        }))
+    }
+
+    pub(crate) fn from_sources(
+        file: Option<BTreeMap<String, BTreeMap<String, String>>>,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let mut values = BTreeMap::new();
+        for (module, names) in file.into_iter().flatten() {
+            for (name, value) in names {
+                values.insert((module.clone(), name), (value, Source::File));
+            }
+        }
+
+        /* the environment wins over the file */
+        for known in KNOWN {
+            let value = Self::env_vars(known.module, known.name)
+                .iter()
+                .find_map(|var| env(var).filter(|v| !v.is_empty()));
+            if let Some(value) = value {
+                values.insert(
+                    (known.module.to_string(), known.name.to_string()),
+                    (value, Source::Env),
+                );
+            }
+        }
+
+        Self { values }
+    }
+
+    /// The environment variables checked for a credential, in order.
+    fn env_vars(module: &str, name: &str) -> Vec<String> {
+        let mut vars = vec![format!("DATACOLLECT_{}_{}", module, name).to_uppercase()];
+        vars.extend(
+            KNOWN
+                .iter()
+                .filter(|k| k.module == module && k.name == name)
+                .filter_map(|k| k.env.map(str::to_string)),
+        );
+        vars
+    }
+
+    /// Get a credential by module and name, e.g. `get("bestbuy", "api_key")`.
+    ///
+    /// Credentials that no module knows about can only come from the file.
+    pub fn get(&self, module: &str, name: &str) -> Option<&str> {
+        self.values
+            .get(&(module.to_string(), name.to_string()))
+            .map(|(value, _)| value.as_str())
+    }
+
+    /// Like [`Credentials::get`], but with an error saying how to configure a missing credential.
+    ///
+    /// # Errors
+    /// Errors if the credential isn't configured.
+    pub fn require(&self, module: &str, name: &str) -> anyhow::Result<&str> {
+        self.get(module, name).with_context(|| {
+            format!(
+                "no {} {} configured; set {} or add it to the [{}] section of {}",
+                module,
+                name,
+                Self::env_vars(module, name).join(" or "),
+                module,
+                Self::default_path()
+                    .map(|p| p.display().to_string())
+                    .unwrap_or_else(|| "credentials.toml".to_string()),
+            )
+        })
+    }
+
+    /// The Best Buy API key, for [`crate::modules::bestbuy`].
+    pub fn bestbuy_api_key(&self) -> Option<&str> {
+        self.get("bestbuy", "api_key")
+    }
+
+    /// A GitHub personal access token.
+    pub fn github_token(&self) -> Option<&str> {
+        self.get("github", "token")
+    }
+
+    /// Which credentials are configured: every credential a module knows about,
+    /// and every other one in the file.
+    pub fn statuses(&self) -> Vec<Status> {
+        let mut statuses = KNOWN
+            .iter()
+            .map(|k| (k.module.to_string(), k.name.to_string()))
+            .chain(self.values.keys().cloned())
+            .collect::<Vec<_>>();
+        statuses.sort();
+        statuses.dedup();
+
+        statuses
+            .into_iter()
+            .map(|(module, name)| Status {
+                env: Self::env_vars(&module, &name),
+                source: self
+                    .values
+                    .get(&(module.clone(), name.clone()))
+                    .map(|(_, source)| *source),
+                module,
+                name,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Credentials, Source};
+
+    #[test]
+    fn test_sources() {
+        let file = toml::from_str(
+            r#"
+                [bestbuy]
+                api_key = "from-file"
+
+                [github]
+                token = "gh-from-file"
+
+                [other]
+                secret = "shh"
+            "#,
+        )
+        .unwrap();
+        let credentials = Credentials::from_sources(Some(file), |var| match var {
+            "BESTBUY_API_KEY" => Some("from-env".to_string()),
+            "DATACOLLECT_GITHUB_TOKEN" => Some(String::new()),
+            _ => None,
+        });
+
+        assert_eq!(credentials.bestbuy_api_key(), Some("from-env"));
+        assert_eq!(credentials.github_token(), Some("gh-from-file"));
+        assert_eq!(credentials.get("other", "secret"), Some("shh"));
+        assert!(credentials.require("other", "missing").is_err());
+        assert!(!format!("{:?}", credentials).contains("shh"));
+
+        let statuses = credentials.statuses();
+        assert_eq!(statuses.len(), 5);
+        assert_eq!(statuses[0].module, "bestbuy");
+        assert_eq!(statuses[0].source, Some(Source::Env));
+        assert_eq!(
+            statuses[0].env,
+            vec!["DATACOLLECT_BESTBUY_API_KEY", "BESTBUY_API_KEY"]
+        );
+        assert_eq!(statuses[3].source, Some(Source::File));
+
+        let empty = Credentials::from_sources(None, |_| None);
+        assert!(empty.statuses().iter().all(|s| s.source.is_none()));
+    }
+}
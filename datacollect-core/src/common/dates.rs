@@ -0,0 +1,333 @@
+//! Reading dates and times as listing pages write them: with month names in English, German,
+//! French, Spanish or Italian, US or European day order, 12- or 24-hour clocks, time zone
+//! abbreviations, or as a time left (or ago).
+//!
+//! ## Example
+//! ```txt
+//! "Ends Jun 14, 2024 10:15 AM PDT" -> 2024-06-14T17:15:00Z
+//! "14. Juni 2024 10:15 MESZ"       -> 2024-06-14T08:15:00Z
+//! "2d 3h" (left)                   -> now + 2 days 3 hours
+//! "3 hours ago"                    -> now - 3 hours
+//! ```
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, TimeZone, Utc};
+use lazy_static::lazy_static;
+use regex::Regex;
+
+lazy_static! {
+    static ref ISO_DATE: Regex = Regex::new(r"\b(\d{4})-(\d{2})-(\d{2})").unwrap();
+    /* 06/14/2024, 14.06.2024, 14/06/24 */
+    static ref NUMERIC_DATE: Regex =
+        Regex::new(r"\b(\d{1,2})[./](\d{1,2})[./](\d{4}|\d{2})\b").unwrap();
+    /* 14 Jun 2024, 14. Juni 2024, 14th June */
+    static ref DAY_MONTH: Regex =
+        Regex::new(r"\b(\d{1,2})(?:st|nd|rd|th)?\.?\s+(\p{L}{3,})\.?,?(?:\s+(\d{4}))?").unwrap();
+    /* Jun 14, 2024, June 14th */
+    static ref MONTH_DAY: Regex =
+        Regex::new(r"(\p{L}{3,})\.?\s+(\d{1,2})(?:st|nd|rd|th)?\b,?(?:\s+(\d{4}))?").unwrap();
+    static ref TIME: Regex =
+        Regex::new(r"(?i)\b(\d{1,2})[:h](\d{2})(?::(\d{2}))?(?:\s*([ap])\.?m\b\.?)?").unwrap();
+    static ref ZONE: Regex =
+        Regex::new(r"(?:UTC|GMT)?([+-])(\d{1,2}):?(\d{2})?\b|\b([A-Z]{1,5})\b").unwrap();
+    static ref DURATION: Regex = Regex::new(
+        r"(?i)(\d+)\s*(d|days?|h|hrs?|hours?|m|mins?|minutes?|s|secs?|seconds?|w|weeks?)\b"
+    )
+    .unwrap();
+}
+
+/// Time zone abbreviations, and their offsets from UTC in hours.
+const ZONES: &[(&str, i32)] = &[
+    ("UTC", 0),
+    ("GMT", 0),
+    ("Z", 0),
+    ("WET", 0),
+    ("BST", 1),
+    ("IST", 1),
+    ("CET", 1),
+    ("MEZ", 1),
+    ("CEST", 2),
+    ("MESZ", 2),
+    ("EET", 2),
+    ("EEST", 3),
+    ("AEST", 10),
+    ("AEDT", 11),
+    ("NZST", 12),
+    ("NZDT", 13),
+    ("AKST", -9),
+    ("AKDT", -8),
+    ("PST", -8),
+    ("PDT", -7),
+    ("MST", -7),
+    ("MDT", -6),
+    ("CST", -6),
+    ("CDT", -5),
+    ("EST", -5),
+    ("EDT", -4),
+];
+
+/// The starts of month names, in English, German, French, Spanish and Italian.
+const MONTHS: &[(&str, u32)] = &[
+    ("jan", 1),
+    ("ene", 1),
+    ("gen", 1),
+    ("feb", 2),
+    ("fév", 2),
+    ("fev", 2),
+    ("mar", 3),
+    ("mär", 3),
+    ("mrz", 3),
+    ("apr", 4),
+    ("avr", 4),
+    ("abr", 4),
+    ("may", 5),
+    ("mai", 5),
+    ("mag", 5),
+    ("jun", 6),
+    ("juin", 6),
+    ("giu", 6),
+    ("jul", 7),
+    ("juil", 7),
+    ("lug", 7),
+    ("aug", 8),
+    ("ago", 8),
+    ("aoû", 8),
+    ("aou", 8),
+    ("sep", 9),
+    ("set", 9),
+    ("oct", 10),
+    ("okt", 10),
+    ("ott", 10),
+    ("nov", 11),
+    ("dec", 12),
+    ("dez", 12),
+    ("déc", 12),
+    ("dic", 12),
+];
+
+/// Which comes first in dates written with numbers only, like `06/07/2024`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum DateOrder {
+    /// `06/14/2024`, as in the US.
+    #[default]
+    MonthFirst,
+    /// `14/06/2024` or `14.06.2024`, as in most other places.
+    DayFirst,
+}
+
+/// The month a (possibly abbreviated) month name stands for, e.g. `Jun`, `Juni` or `juin`.
+fn month(name: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    /* the longest start that matches, so that `juil` isn't taken for `jui(n)` */
+    MONTHS
+        .iter()
+        .filter(|(start, _)| name.starts_with(start))
+        .max_by_key(|(start, _)| start.len())
+        .map(|(_, month)| *month)
+}
+
+/// `year`, or if there is none, whichever year puts `month` and `day` closest to `now`.
+fn date(year: Option<i32>, month: u32, day: u32, now: DateTime<Utc>) -> Option<NaiveDate> {
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year, month, day),
+        None => {
+            let today = now.date_naive();
+            [today.year() - 1, today.year(), today.year() + 1]
+                .iter()
+                .filter_map(|year| NaiveDate::from_ymd_opt(*year, month, day))
+                .min_by_key(|date| (*date - today).num_days().abs())
+        }
+    }
+}
+
+/// The first date in `text`, and where it ends.
+fn find_date(text: &str, now: DateTime<Utc>, order: DateOrder) -> Option<(NaiveDate, usize)> {
+    let number = |m: Option<regex::Match>| m?.as_str().parse::<u32>().ok();
+    let year = |m: Option<regex::Match>| {
+        let year = m?.as_str().parse::<i32>().ok()?;
+        Some(if year < 100 { 2000 + year } else { year })
+    };
+
+    let candidates = [
+        ISO_DATE.captures(text).and_then(|c| {
+            let date =
+                NaiveDate::from_ymd_opt(year(c.get(1))?, number(c.get(2))?, number(c.get(3))?)?;
+            Some((c.get(0)?, date))
+        }),
+        NUMERIC_DATE.captures(text).and_then(|c| {
+            let (a, b) = (number(c.get(1))?, number(c.get(2))?);
+            let (month, day) = match order {
+                DateOrder::MonthFirst => (a, b),
+                DateOrder::DayFirst => (b, a),
+            };
+            Some((c.get(0)?, date(year(c.get(3)), month, day, now)?))
+        }),
+        DAY_MONTH.captures_iter(text).find_map(|c| {
+            let month = month(c.get(2)?.as_str())?;
+            Some((
+                c.get(0)?,
+                date(year(c.get(3)), month, number(c.get(1))?, now)?,
+            ))
+        }),
+        MONTH_DAY.captures_iter(text).find_map(|c| {
+            let month = month(c.get(1)?.as_str())?;
+            Some((
+                c.get(0)?,
+                date(year(c.get(3)), month, number(c.get(2))?, now)?,
+            ))
+        }),
+    ];
+    candidates
+        .iter()
+        .flatten()
+        .min_by_key(|(m, _)| m.start())
+        .map(|(m, date)| (*date, m.end()))
+}
+
+/// The first date in `text` (without its time, if it has one), e.g. `Sold Jun 14, 2024`.
+/// Dates without a year are taken to be the closest to `now`.
+pub fn parse_date(text: &str, now: DateTime<Utc>, order: DateOrder) -> Option<NaiveDate> {
+    find_date(text, now, order).map(|(date, _)| date)
+}
+
+/// The time zone in `text`, as an abbreviation (`PDT`) or an offset (`+02:00`, `GMT-5`).
+fn find_zone(text: &str) -> Option<FixedOffset> {
+    ZONE.captures_iter(text).find_map(|c| {
+        let seconds = match (c.get(1), c.get(4)) {
+            (Some(sign), _) => {
+                let hours = c.get(2)?.as_str().parse::<i32>().ok()?;
+                let minutes = c
+                    .get(3)
+                    .map_or(Some(0), |m| m.as_str().parse::<i32>().ok())?;
+                let seconds = hours * 3600 + minutes * 60;
+                if sign.as_str() == "-" {
+                    -seconds
+                } else {
+                    seconds
+                }
+            }
+            (None, Some(name)) => ZONES
+                .iter()
+                .find(|(zone, _)| *zone == name.as_str())
+                .map(|(_, hours)| hours * 3600)?,
+            _ => return None,
+        };
+        FixedOffset::east_opt(seconds)
+    })
+}
+
+/// A length of time, as written for the time left on an auction, e.g. `2d 3h` or
+/// `1 hour 20 mins`.
+pub fn parse_duration(text: &str) -> Option<Duration> {
+    let seconds = DURATION
+        .captures_iter(text)
+        .filter_map(|c| {
+            let n = c.get(1)?.as_str().parse::<i64>().ok()?;
+            let unit = c.get(2)?.as_str().to_lowercase();
+            Some(match unit.chars().next()? {
+                'w' => n * 7 * 86400,
+                'd' => n * 86400,
+                'h' => n * 3600,
+                'm' => n * 60,
+                _ => n,
+            })
+        })
+        .sum::<i64>();
+    (seconds > 0).then(|| Duration::seconds(seconds))
+}
+
+/// The date and time in `text`; see the [module documentation](self).
+///
+/// A date without a time is taken at midnight, and a time without a zone as UTC. If there is
+/// no date, `text` is read as a time left from `now` (e.g. `2d 3h`), or ago (`3 hours ago`).
+pub fn parse_datetime(text: &str, now: DateTime<Utc>, order: DateOrder) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(text.trim()) {
+        return Some(time.with_timezone(&Utc));
+    }
+    let (date, end) = match find_date(text, now, order) {
+        Some(found) => found,
+        None => {
+            let duration = parse_duration(text)?;
+            return Some(if text.to_lowercase().contains("ago") {
+                now - duration
+            } else {
+                now + duration
+            });
+        }
+    };
+
+    let rest = &text[end..];
+    let time = TIME.captures(rest).and_then(|c| {
+        let mut hour = c.get(1)?.as_str().parse::<u32>().ok()?;
+        let minute = c.get(2)?.as_str().parse::<u32>().ok()?;
+        let second = c
+            .get(3)
+            .map_or(Some(0), |s| s.as_str().parse::<u32>().ok())?;
+        match c.get(4).map(|m| m.as_str().to_lowercase()) {
+            Some(half) if half == "a" && hour == 12 => hour = 0,
+            Some(half) if half == "p" && hour < 12 => hour += 12,
+            _ => {}
+        }
+        Some((date.and_hms_opt(hour, minute, second)?, c.get(0)?.end()))
+    });
+    let (local, rest) = match time {
+        Some((local, end)) => (local, &rest[end..]),
+        None => (date.and_hms_opt(0, 0, 0)?, rest),
+    };
+
+    let zone = find_zone(rest).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    Some(
+        zone.from_local_datetime(&local)
+            .single()?
+            .with_timezone(&Utc),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, NaiveDate, TimeZone, Utc};
+
+    use super::{parse_date, parse_datetime, parse_duration, DateOrder};
+
+    #[test]
+    fn test_parse_datetime() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 10, 12, 0, 0).unwrap();
+        let parse = |text| parse_datetime(text, now, DateOrder::MonthFirst);
+        let utc = |y, mo, d, h, mi| Some(Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap());
+
+        assert_eq!(
+            parse("Ends Jun 14, 2024 10:15 AM PDT"),
+            utc(2024, 6, 14, 17, 15)
+        );
+        assert_eq!(
+            parse("(Jun 14, 2024 12:05:00 PM PST)"),
+            utc(2024, 6, 14, 20, 5)
+        );
+        assert_eq!(parse("14. Juni 2024 10:15 MESZ"), utc(2024, 6, 14, 8, 15));
+        assert_eq!(parse("le 14 juil. 2024 à 22h30"), utc(2024, 7, 14, 22, 30));
+        assert_eq!(parse("2024-06-14 10:15 +02:00"), utc(2024, 6, 14, 8, 15));
+        assert_eq!(parse("2024-06-14T10:15:00Z"), utc(2024, 6, 14, 10, 15));
+        assert_eq!(parse("Jun 14, 2024 10:15 GMT-5"), utc(2024, 6, 14, 15, 15));
+        assert_eq!(parse("Sold Jun 14"), utc(2024, 6, 14, 0, 0));
+        /* without a year, the closest to now */
+        assert_eq!(parse("Dec 30 11:00 PM"), utc(2023, 12, 30, 23, 0));
+        assert_eq!(
+            parse_datetime("14/06/2024 10:15", now, DateOrder::DayFirst),
+            utc(2024, 6, 14, 10, 15)
+        );
+        assert_eq!(parse("06/14/2024"), utc(2024, 6, 14, 0, 0));
+
+        assert_eq!(parse("2d 3h"), Some(now + Duration::hours(51)));
+        assert_eq!(parse("3 hours ago"), Some(now - Duration::hours(3)));
+        assert_eq!(parse("Ending soon"), None);
+
+        assert_eq!(
+            parse_duration("1 hour 20 mins"),
+            Some(Duration::minutes(80))
+        );
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(
+            parse_date("Sold  Jun 14, 2024", now, DateOrder::MonthFirst),
+            NaiveDate::from_ymd_opt(2024, 6, 14)
+        );
+    }
+}
@@ -0,0 +1,252 @@
+//! Looking up hosts with DNS-over-HTTPS (DoH), for networks whose resolver is broken or
+//! censored.
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{bail, Context};
+use hyper::client::connect::dns::Name;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use serde::Deserialize;
+
+use super::Timeouts;
+
+/// Cloudflare's DoH server. It's addressed by IP, so that finding it needs no DNS.
+pub const CLOUDFLARE: &str = "https://1.1.1.1/dns-query";
+/// Google's DoH server, addressed by IP like [`CLOUDFLARE`].
+pub const GOOGLE: &str = "https://8.8.8.8/resolve";
+
+/// The record types to look up: `A` (IPv4) and `AAAA` (IPv6).
+const RECORD_TYPES: [u16; 2] = [1, 28];
+
+/// Addresses by host, and when they expire.
+type Answers = HashMap<String, (Vec<IpAddr>, Instant)>;
+
+/// Looks up hosts with a DoH server's JSON API, instead of the system's resolver.
+///
+/// Answers are cached for as long as their TTL says. Clones share the cache.
+///
+/// ## Example
+/// ```txt
+/// let client = Client::default().with_doh(DohResolver::cloudflare());
+/// ```
+#[derive(Clone)]
+pub struct DohResolver {
+    url: String,
+    client: reqwest::Client,
+    cache: Arc<Mutex<Answers>>,
+}
+
+/// A DoH JSON response (see <https://developers.google.com/speed/public-dns/docs/doh/json>).
+#[derive(Deserialize)]
+struct Response {
+    #[serde(rename = "Status")]
+    status: u32,
+    #[serde(rename = "Answer", default)]
+    answer: Vec<Answer>,
+}
+
+#[derive(Deserialize)]
+struct Answer {
+    #[serde(rename = "type")]
+    record_type: u16,
+    #[serde(rename = "TTL", default)]
+    ttl: u64,
+    data: String,
+}
+
+impl DohResolver {
+    /// Use the DoH server at `url`, e.g. [`CLOUDFLARE`] or `https://dns.quad9.net:5053/dns-query`.
+    ///
+    /// The server itself is found with the system's resolver, unless `url` has an IP. Lookups
+    /// use the default [`Timeouts`]; see [`DohResolver::with_timeouts`].
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: Self::build(Timeouts::default()),
+            cache: Default::default(),
+        }
+    }
+
+    fn build(timeouts: Timeouts) -> reqwest::Client {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = timeouts.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        /* an answer is tiny, so waiting for it is most of the request */
+        if let Some(timeout) = timeouts.total.or(timeouts.read) {
+            builder = builder.timeout(timeout);
+        }
+        builder.build().unwrap()
+    }
+
+    /// Give up on a lookup (and so on the request that needed it) that takes longer than
+    /// `timeouts` allow, e.g. a client's own [`Timeouts`]. The read timeout covers the whole
+    /// lookup, unless there's a total timeout.
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.client = Self::build(timeouts);
+        self
+    }
+
+    pub fn cloudflare() -> Self {
+        Self::new(CLOUDFLARE)
+    }
+
+    pub fn google() -> Self {
+        Self::new(GOOGLE)
+    }
+
+    /// The URL of the DoH server.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Find the addresses of `host`.
+    ///
+    /// # Errors
+    /// Errors if the DoH server could not be reached, or if it found no addresses.
+    pub async fn lookup(&self, host: &str) -> anyhow::Result<Vec<IpAddr>> {
+        let host = host.to_lowercase();
+        if let Some((addrs, expires)) = self.cache.lock().unwrap().get(&host) {
+            if *expires > Instant::now() {
+                return Ok(addrs.clone());
+            }
+        }
+
+        let mut addrs = Vec::new();
+        let mut ttl = u64::MAX;
+        for record_type in RECORD_TYPES {
+            let response: Response = self
+                .client
+                .get(&self.url)
+                .query(&[("name", host.as_str()), ("type", &record_type.to_string())])
+                .header(reqwest::header::ACCEPT, "application/dns-json")
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status)
+                .with_context(|| format!("DoH lookup of {} failed", host))?
+                .json()
+                .await
+                .with_context(|| format!("bad DoH response for {}", host))?;
+            /* anything but NOERROR (e.g. NXDOMAIN) means there are no addresses */
+            if response.status != 0 {
+                continue;
+            }
+            /* CNAMEs come with the records they point to, so they can be skipped */
+            for answer in response.answer {
+                if RECORD_TYPES.contains(&answer.record_type) {
+                    if let Ok(addr) = answer.data.parse::<IpAddr>() {
+                        ttl = ttl.min(answer.ttl);
+                        if !addrs.contains(&addr) {
+                            addrs.push(addr);
+                        }
+                    }
+                }
+            }
+        }
+        if addrs.is_empty() {
+            bail!("DoH found no addresses for {}", host);
+        }
+
+        let expires = Instant::now() + Duration::from_secs(ttl);
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(host, (addrs.clone(), expires));
+        Ok(addrs)
+    }
+}
+
+impl Resolve for DohResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            /* the port is filled in by the connector */
+            let addrs: Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
+
+/// A provider's name (`cloudflare` or `google`), or the URL of a DoH server.
+impl FromStr for DohResolver {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cloudflare" => Ok(Self::cloudflare()),
+            "google" => Ok(Self::google()),
+            _ => {
+                let url = reqwest::Url::parse(s).context("expected cloudflare, google or a URL")?;
+                if url.scheme() != "https" {
+                    bail!("the DoH server must use https");
+                }
+                Ok(Self::new(s))
+            }
+        }
+    }
+}
+
+impl Display for DohResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::IpAddr;
+
+    use super::DohResolver;
+    use crate::{common::Client, testing::MockServer};
+
+    #[tokio::test]
+    async fn test_doh() {
+        let server = MockServer::start().await.unwrap();
+        server.mock(
+            "/dns-query",
+            200,
+            r#"{"Status": 0, "Answer": [
+                {"name": "shop.test", "type": 5, "TTL": 300, "data": "cdn.test."},
+                {"name": "cdn.test", "type": 1, "TTL": 60, "data": "127.0.0.1"}
+            ]}"#,
+        );
+        let resolver = DohResolver::new(&format!("{}/dns-query", server.uri()));
+
+        assert_eq!(
+            resolver.lookup("Shop.test").await.unwrap(),
+            vec!["127.0.0.1".parse::<IpAddr>().unwrap()]
+        );
+        /* one request per record type, and then the answer is cached */
+        resolver.lookup("shop.test").await.unwrap();
+        assert_eq!(
+            server.requests(),
+            vec![
+                "/dns-query?name=shop.test&type=1",
+                "/dns-query?name=shop.test&type=28"
+            ]
+        );
+
+        /* a host that only the DoH server knows, served by the mock server */
+        server.mock("/page", 200, "found");
+        let port = server.uri().rsplit(':').next().unwrap().to_string();
+        let client = Client::<false>::default().with_doh(resolver);
+        let response = client
+            .get(&format!("http://shop.test:{}/page", port))
+            .await
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.text().await.unwrap(), "found");
+
+        assert!("google".parse::<DohResolver>().is_ok());
+        assert!("http://dns.test/dns-query".parse::<DohResolver>().is_err());
+    }
+}
@@ -0,0 +1,112 @@
+//! Where the modules send their requests, so that they can be pointed somewhere else.
+
+/// The server a module talks to, for a module without one of its own (e.g. Craigslist, whose
+/// sites are by region). Modules with their own server define their own `Endpoints` with
+/// `endpoints!`, which defaults to it.
+///
+/// ## Example
+/// ```txt
+/// let sfbay = craigslist::Endpoints::new("https://sfbay.craigslist.org");
+/// let listings = Listing::search_with(&sfbay, "ryzen");
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Endpoints {
+    /// The base URL of the server, e.g. `https://sfbay.craigslist.org`.
+    pub base: String,
+}
+
+impl Endpoints {
+    pub fn new<S: Into<String>>(base: S) -> Self {
+        Self { base: base.into() }
+    }
+
+    /// The URL of `path` (which starts with a `/`) on the server.
+    #[cfg(feature = "net")]
+    pub(crate) fn url(&self, path: &str) -> String {
+        join(&self.base, path)
+    }
+}
+
+/// The URL of `path` (which starts with a `/`) on the server at `base`.
+#[cfg(feature = "net")]
+pub(crate) fn join(base: &str, path: &str) -> String {
+    format!("{}{}", base.trim_end_matches('/'), path)
+}
+
+/// Define the `Endpoints` of a module: the servers it talks to, by default its own (the
+/// module's `BASE`, and the other hosts given here), or e.g. a regional site, a mirror, or
+/// a mock server in tests.
+///
+/// ## Example
+/// ```txt
+/// pub const BASE: &str = "https://www.ebay.com";
+///
+/// endpoints! {
+///     /// Where to sign in.
+///     signin = "https://signin.ebay.com",
+/// }
+///
+/// let uk = ebay::Endpoints::new("https://www.ebay.co.uk");
+/// let product = Product::by_id_with(&uk, &mut client, 254625474154, Detail::Default).await?;
+/// ```
+macro_rules! endpoints {
+    ($($(#[$meta:meta])* $host:ident = $default:expr),* $(,)?) => {
+        /// The servers the module talks to: its own by default (see [`BASE`]), or e.g. a
+        /// regional site, a mirror, or a mock server in tests.
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct Endpoints {
+            /// The base URL of the module's server.
+            pub base: String,
+            $($(#[$meta])* pub $host: String,)*
+        }
+
+        impl Endpoints {
+            /// The module's own endpoints, with another base URL.
+            pub fn new<S: Into<String>>(base: S) -> Self {
+                Self {
+                    base: base.into(),
+                    $($host: $default.to_string(),)*
+                }
+            }
+
+            /// The URL of `path` (which starts with a `/`) on the server.
+            #[cfg(feature = "net")]
+            pub(crate) fn url(&self, path: &str) -> String {
+                $crate::common::endpoints::join(&self.base, path)
+            }
+        }
+
+        impl Default for Endpoints {
+            fn default() -> Self {
+                Self::new(BASE)
+            }
+        }
+    };
+}
+
+pub(crate) use endpoints;
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use super::Endpoints;
+    use crate::modules::{ebay, rdap};
+
+    #[test]
+    fn test_url() {
+        assert_eq!(
+            Endpoints::new("https://sfbay.craigslist.org/").url("/search/sss"),
+            "https://sfbay.craigslist.org/search/sss"
+        );
+        assert_eq!(
+            rdap::Endpoints::new("https://rdap.org/").url("/domain/example.com"),
+            "https://rdap.org/domain/example.com"
+        );
+    }
+
+    #[test]
+    fn test_defaults() {
+        assert_eq!(rdap::Endpoints::default().base, rdap::BASE);
+        let uk = ebay::Endpoints::new("https://www.ebay.co.uk");
+        assert_eq!(uk.signin, ebay::Endpoints::default().signin);
+    }
+}
@@ -0,0 +1,295 @@
+//! Reading fields out of HTML pages, described as CSS selectors plus what to do with the
+//! text they match, instead of walking the document by hand.
+//!
+//! ## Example
+//! ```txt
+//! lazy_static! {
+//!     static ref FEEDBACK: Field =
+//!         Field::new("#si-fb").capture(Regex::new(r"([0-9.]+)%").unwrap());
+//! }
+//! let name = Field::new("h1.cpuname").or("h1").text(&document)?;
+//! let feedback = FEEDBACK.number(&document)? * 0.01;
+//! // error: selector `#si-fb` matched nothing
+//! ```
+
+use std::{any::type_name, fmt::Display, str::FromStr};
+
+use anyhow::anyhow;
+use kuchiki::NodeRef;
+use regex::Regex;
+
+use super::ParseError;
+
+/// A field of a page: the elements it's found in, and how its value is read from them.
+#[derive(Clone, Debug)]
+pub struct Field {
+    /// Selectors to try in order, e.g. for the old and new layouts of a page.
+    selectors: Vec<String>,
+    /// Read this attribute instead of the text.
+    attribute: Option<String>,
+    capture: Option<Regex>,
+}
+
+impl Field {
+    /// A field read from the text of the first element matching `selector`.
+    pub fn new(selector: &str) -> Self {
+        Self {
+            selectors: vec![selector.to_string()],
+            attribute: None,
+            capture: None,
+        }
+    }
+
+    /// If no element matches the selectors so far, try `selector`.
+    pub fn or(mut self, selector: &str) -> Self {
+        self.selectors.push(selector.to_string());
+        self
+    }
+
+    /// Read the value of an attribute (e.g. `href`) instead of the element's text.
+    pub fn attr(mut self, name: &str) -> Self {
+        self.attribute = Some(name.to_string());
+        self
+    }
+
+    /// Keep only the part of the value matched by `regex`: its first group if it has one,
+    /// or else the whole match.
+    pub fn capture(mut self, regex: Regex) -> Self {
+        self.capture = Some(regex);
+        self
+    }
+
+    fn describe(&self) -> String {
+        self.selectors
+            .iter()
+            .map(|selector| format!("`{}`", selector))
+            .collect::<Vec<_>>()
+            .join(" or ")
+    }
+
+    /// Read the field's value from one element, before the regex is applied.
+    fn raw(&self, element: &NodeRef) -> Option<String> {
+        let value = match &self.attribute {
+            Some(name) => element
+                .as_element()?
+                .attributes
+                .borrow()
+                .get(name.as_str())?
+                .to_string(),
+            /* collapse the whitespace of the page's layout */
+            None => element
+                .text_contents()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+        };
+        (!value.trim().is_empty()).then_some(value)
+    }
+
+    fn captured(&self, value: String) -> anyhow::Result<String> {
+        match &self.capture {
+            None => Ok(value),
+            Some(regex) => {
+                let captures = regex.captures(&value).ok_or_else(|| {
+                    ParseError::new(format!(
+                        "`{}` did not match `{}` (from {})",
+                        regex,
+                        value,
+                        self.describe()
+                    ))
+                })?;
+                Ok(captures
+                    .get(1)
+                    .or_else(|| captures.get(0))
+                    .map_or("", |m| m.as_str())
+                    .to_string())
+            }
+        }
+    }
+
+    /// The field's value in `node` (e.g. a whole document, or a row of search results).
+    ///
+    /// # Errors
+    /// Errors if no element matches, if the matching element has no value, or if the value
+    /// doesn't match the field's regex.
+    pub fn text(&self, node: &NodeRef) -> anyhow::Result<String> {
+        for selector in &self.selectors {
+            let element = match node.select_first(selector) {
+                Ok(element) => element,
+                Err(()) if kuchiki::Selectors::compile(selector).is_err() => {
+                    return Err(anyhow!("invalid selector `{}`", selector))
+                }
+                Err(()) => continue,
+            };
+            let value = self.raw(element.as_node()).ok_or_else(|| {
+                ParseError::new(match &self.attribute {
+                    Some(name) => format!(
+                        "selector `{}` matched an element with no `{}`",
+                        selector, name
+                    ),
+                    None => format!("selector `{}` matched an empty element", selector),
+                })
+            })?;
+            return self.captured(value);
+        }
+
+        Err(ParseError::new(format!(
+            "{} {} matched nothing",
+            if self.selectors.len() > 1 {
+                "selectors"
+            } else {
+                "selector"
+            },
+            self.describe()
+        ))
+        .into())
+    }
+
+    /// Like [`Field::text`], for optional fields.
+    pub fn get(&self, node: &NodeRef) -> Option<String> {
+        self.text(node).ok()
+    }
+
+    /// The field's value, parsed as a `T`.
+    ///
+    /// # Errors
+    /// Errors if the value could not be read (see [`Field::text`]) or parsed.
+    pub fn parse<T>(&self, node: &NodeRef) -> anyhow::Result<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let text = self.text(node)?;
+        text.trim().parse().map_err(|e| {
+            ParseError::new(format!(
+                "could not read `{}` (from {}) as {}: {}",
+                text,
+                self.describe(),
+                type_name::<T>(),
+                e
+            ))
+            .into()
+        })
+    }
+
+    /// The field's value as a number, ignoring thousands separators (e.g. `1,204`).
+    ///
+    /// # Errors
+    /// Errors if the value could not be read (see [`Field::text`]) or isn't a number.
+    pub fn number(&self, node: &NodeRef) -> anyhow::Result<f64> {
+        let text = self.text(node)?;
+        text.trim().replace(',', "").parse().map_err(|_| {
+            ParseError::new(format!(
+                "`{}` (from {}) is not a number",
+                text,
+                self.describe()
+            ))
+            .into()
+        })
+    }
+
+    /// The field's value in every element that matches the first selector matching any, in
+    /// document order. Elements without a value are left out.
+    pub fn all(&self, node: &NodeRef) -> Vec<String> {
+        self.selectors
+            .iter()
+            .filter_map(|selector| node.select(selector).ok())
+            .map(|elements| {
+                elements
+                    .filter_map(|element| self.raw(element.as_node()))
+                    .filter_map(|value| self.captured(value).ok())
+                    .collect::<Vec<_>>()
+            })
+            .find(|values| !values.is_empty())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use kuchiki::{parse_html, traits::TendrilSink};
+    use regex::Regex;
+
+    use super::{Field, ParseError};
+
+    #[test]
+    fn test_field() {
+        let document = parse_html().one(
+            r#"
+            <h1 class="name">  AMD   Ryzen 5
+                2600 </h1>
+            <span id="fb">99.5% positive feedback</span>
+            <span class="count">1,204 results</span>
+            <a class="link" href="/itm/1">one</a>
+            <a class="link" href="/itm/2">two</a>
+            <span class="empty"> </span>
+        "#,
+        );
+
+        assert_eq!(
+            Field::new(".name").text(&document).unwrap(),
+            "AMD Ryzen 5 2600"
+        );
+        assert_eq!(
+            Field::new("h1.cpuname")
+                .or(".name")
+                .text(&document)
+                .unwrap(),
+            "AMD Ryzen 5 2600"
+        );
+        assert_eq!(
+            Field::new("#fb")
+                .capture(Regex::new(r"([0-9.]+)%").unwrap())
+                .number(&document)
+                .unwrap(),
+            99.5
+        );
+        assert_eq!(
+            Field::new(".count")
+                .capture(Regex::new(r"[0-9,]+").unwrap())
+                .number(&document)
+                .unwrap(),
+            1204.0
+        );
+        assert_eq!(
+            Field::new(".link")
+                .attr("href")
+                .capture(Regex::new(r"/itm/([0-9]+)").unwrap())
+                .parse::<u64>(&document)
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            Field::new(".link").attr("href").all(&document),
+            vec!["/itm/1", "/itm/2"]
+        );
+
+        let error = |field: Field| field.text(&document).unwrap_err().to_string();
+        assert_eq!(
+            error(Field::new("#itemTitle")),
+            "selector `#itemTitle` matched nothing"
+        );
+        assert_eq!(
+            error(Field::new("#itemTitle").or(".title")),
+            "selectors `#itemTitle` or `.title` matched nothing"
+        );
+        assert_eq!(
+            error(Field::new(".empty")),
+            "selector `.empty` matched an empty element"
+        );
+        assert_eq!(
+            error(Field::new(".name").capture(Regex::new(r"Intel").unwrap())),
+            "`Intel` did not match `AMD Ryzen 5 2600` (from `.name`)"
+        );
+        assert_eq!(error(Field::new("[[")), "invalid selector `[[`");
+        assert!(Field::new(".name")
+            .parse::<u32>(&document)
+            .unwrap_err()
+            .to_string()
+            .starts_with("could not read `AMD Ryzen 5 2600` (from `.name`) as u32"));
+        assert!(Field::new("#itemTitle")
+            .text(&document)
+            .unwrap_err()
+            .is::<ParseError>());
+    }
+}
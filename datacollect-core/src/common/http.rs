@@ -0,0 +1,703 @@
+use std::{
+    fmt::Display,
+    future::Future,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use kuchiki::{traits::TendrilSink, NodeRef};
+use reqwest::{header::HeaderMap, StatusCode, Url};
+use serde::{de::DeserializeOwned, Serialize};
+
+use super::{
+    auth::Session,
+    cache::Cache,
+    cassette::Cassette,
+    meta::{canonical_link, meta_refresh},
+    metrics,
+    ratelimit::RateLimit,
+    robots::RobotsPolicy,
+    Client, Layers,
+};
+
+/// A request being built by a [`Client`](super::Client).
+///
+/// This wraps a [`reqwest::RequestBuilder`], so that every request a module sends is
+/// logged and counted in the [`metrics`] the same way.
+pub struct RequestBuilder {
+    inner: reqwest::RequestBuilder,
+    cassette: Option<Arc<Cassette>>,
+    cache: Option<Arc<Cache>>,
+    rate_limit: Option<Arc<RateLimit>>,
+    max_response_size: u64,
+    timeouts: Timeouts,
+    /// The login to send the request with, and the client that logs in.
+    session: Option<(Arc<Session>, Client<true>)>,
+    /// The robots.txt policy to check the request against, and the client that fetches
+    /// robots.txt.
+    robots: Option<(Arc<RobotsPolicy>, Client<false>)>,
+}
+
+/// How long requests may take; `None` is no limit.
+///
+/// The default gives up on a server that can't be reached in 10 seconds, or that sends
+/// nothing for 30, but lets a slow download take as long as it keeps going.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Timeouts {
+    /// Connecting to the server. This is part of the client's connection settings, so it
+    /// can't be changed for one request.
+    pub connect: Option<Duration>,
+    /// Waiting for the response headers, or for each chunk of the body.
+    pub read: Option<Duration>,
+    /// The whole request, from sending it to the end of the body.
+    pub total: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Some(Duration::from_secs(10)),
+            read: Some(Duration::from_secs(30)),
+            total: None,
+        }
+    }
+}
+
+/// How a client keeps its connections, for reuse by later requests to the same host.
+///
+/// Clones of a [`Client`](super::Client) share its connections, so a stream of requests
+/// (e.g. the pages of a search) only connects and does the TLS handshake once per host.
+/// The default keeps idle connections for 90 seconds, and uses HTTP/2 when the server
+/// offers it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Connections {
+    /// The most idle connections to keep open to each host; `None` is no limit.
+    pub max_idle_per_host: Option<usize>,
+    /// How long an idle connection is kept open; `None` keeps it until the server closes it.
+    pub idle_timeout: Option<Duration>,
+    /// How often to send TCP keep-alive probes on idle connections; `None` sends none.
+    pub tcp_keepalive: Option<Duration>,
+    /// Speak HTTP/2 from the start, without asking the server first. Only for servers that
+    /// are known to support it (HTTP/1-only servers will fail every request).
+    pub http2_prior_knowledge: bool,
+    /// How often to ping HTTP/2 connections to keep them open; `None` sends no pings.
+    pub http2_keep_alive: Option<Duration>,
+}
+
+impl Default for Connections {
+    fn default() -> Self {
+        Self {
+            max_idle_per_host: None,
+            idle_timeout: Some(Duration::from_secs(90)),
+            tcp_keepalive: None,
+            http2_prior_knowledge: false,
+            http2_keep_alive: None,
+        }
+    }
+}
+
+/// Settings for one request, overriding the client's (see [`RequestBuilder::options`]).
+///
+/// ## Example
+/// ```txt
+/// const SEARCH: RequestOptions = RequestOptions {
+///     read_timeout: Some(Duration::from_secs(15)),
+///     total_timeout: Some(Duration::from_secs(45)),
+/// };
+/// client.get(url).await?.options(SEARCH).send().await?
+/// ```
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct RequestOptions {
+    pub read_timeout: Option<Duration>,
+    pub total_timeout: Option<Duration>,
+}
+
+impl RequestBuilder {
+    pub(crate) fn new(inner: reqwest::RequestBuilder, layers: &Layers) -> Self {
+        Self {
+            inner,
+            cassette: layers.cassette.clone(),
+            cache: layers.cache.clone(),
+            rate_limit: layers.rate_limit.clone(),
+            max_response_size: layers
+                .max_response_size
+                .unwrap_or(DEFAULT_MAX_RESPONSE_SIZE),
+            timeouts: layers.timeouts,
+            session: None,
+            robots: None,
+        }
+    }
+
+    pub(crate) fn with_robots(self, policy: Arc<RobotsPolicy>, client: Client<false>) -> Self {
+        Self {
+            robots: Some((policy, client)),
+            ..self
+        }
+    }
+
+    pub(crate) fn with_session(self, session: Arc<Session>, client: Client<true>) -> Self {
+        Self {
+            session: Some((session, client)),
+            ..self
+        }
+    }
+
+    /// A copy of the request, for sending it again; `None` for streaming bodies.
+    fn try_clone(&self) -> Option<Self> {
+        Some(Self {
+            inner: self.inner.try_clone()?,
+            cassette: self.cassette.clone(),
+            cache: self.cache.clone(),
+            rate_limit: self.rate_limit.clone(),
+            max_response_size: self.max_response_size,
+            timeouts: self.timeouts,
+            session: self.session.clone(),
+            robots: self.robots.clone(),
+        })
+    }
+
+    /// Override the client's settings for this request.
+    pub fn options(mut self, options: RequestOptions) -> Self {
+        if let Some(read) = options.read_timeout {
+            self.timeouts.read = Some(read);
+        }
+        if let Some(total) = options.total_timeout {
+            self.timeouts.total = Some(total);
+        }
+        self
+    }
+
+    pub fn query<T: Serialize + ?Sized>(self, query: &T) -> Self {
+        Self {
+            inner: self.inner.query(query),
+            ..self
+        }
+    }
+
+    pub fn header(self, key: &str, value: &str) -> Self {
+        Self {
+            inner: self.inner.header(key, value),
+            ..self
+        }
+    }
+
+    pub fn json<T: Serialize + ?Sized>(self, json: &T) -> Self {
+        Self {
+            inner: self.inner.json(json),
+            ..self
+        }
+    }
+
+    /// Send `form` as the body, URL-encoded like an HTML form.
+    pub fn form<T: Serialize + ?Sized>(self, form: &T) -> Self {
+        Self {
+            inner: self.inner.form(form),
+            ..self
+        }
+    }
+
+    /// Send the request and download the whole response body.
+    ///
+    /// # Errors
+    /// Errors if the request could not be sent, or if the body could not be downloaded.
+    /// Responses with error statuses are *not* errors; check [`Response::status`].
+    /// Requests started with [`Client::get`] also error if the client's [`RobotsPolicy`]
+    /// refuses their URL (with its query).
+    ///
+    /// If the client has a [`Cassette`], the response is replayed from it or recorded to it.
+    /// Otherwise, GET requests are answered from the client's [`Cache`] if it has a fresh
+    /// response. Requests that do go to the network wait for the client's [`RateLimit`].
+    ///
+    /// A block page (see [`Response::block_kind`]) is a [`BlockedError`], and makes the
+    /// client's [`RateLimit`] back off from the host.
+    ///
+    /// If the client has a [`Session`] for the URL's site, the request is
+    /// sent logged in, and sent again (once) after logging in again if the session turns out
+    /// to be over. Requests sent logged in are neither cached nor recorded to the cassette.
+    #[tracing::instrument(name = "request", skip_all)]
+    pub async fn send(mut self) -> anyhow::Result<Response> {
+        /* checked here rather than when the request is started, so that the query added
+         * since is checked too (boxed, since checking may fetch robots.txt) */
+        if let Some((policy, client)) = self.robots.take() {
+            let request = self
+                .inner
+                .try_clone()
+                .context("cannot send a streaming request")?
+                .build()?;
+            Box::pin(policy.check(&client, request.url())).await?;
+        }
+
+        let (session, client) = match self.session.take() {
+            Some(session) => session,
+            None => return self.send_once().await,
+        };
+        let url = match self.inner.try_clone().and_then(|inner| inner.build().ok()) {
+            Some(request) if session.covers(&client, request.url()) => request.url().clone(),
+            /* the session isn't sent to other sites, or with bodies that can't be checked */
+            _ => return self.send_once().await,
+        };
+        let retry = self.try_clone();
+        let response = self.logged_in(&session, &client).await?.send_once().await?;
+        match retry {
+            Some(retry) if session.is_expired(&client, &url, &response) => {
+                tracing::info!(
+                    module = session.module(),
+                    "session is over; logging in again"
+                );
+                session.log_out().await;
+                retry.logged_in(&session, &client).await?.send_once().await
+            }
+            _ => Ok(response),
+        }
+    }
+
+    /// Add the session's token to the request, logging in first if need be.
+    async fn logged_in(mut self, session: &Session, client: &Client<true>) -> anyhow::Result<Self> {
+        if let Some(token) = session.authorize(client).await? {
+            self.inner = self.inner.bearer_auth(token);
+        }
+        /* an account's own pages must not be served to anyone else, or recorded */
+        self.cache = None;
+        if self.cassette.as_ref().is_some_and(|c| !c.is_replaying()) {
+            self.cassette = None;
+        }
+        self.session = None;
+        Ok(self)
+    }
+
+    async fn send_once(self) -> anyhow::Result<Response> {
+        let request = self
+            .inner
+            .try_clone()
+            .context("cannot send a streaming request")?
+            .build()?;
+        let (method, url) = (request.method().to_string(), request.url().clone());
+
+        if let Some(cassette) = &self.cassette {
+            if cassette.is_replaying() {
+                tracing::debug!(%method, %url, "replaying response");
+                return cassette.replay(&method, &url);
+            }
+        }
+
+        let cache = self.cache.as_ref().filter(|_| method == "GET");
+        if let Some(response) = cache.and_then(|cache| cache.get(&url)) {
+            tracing::debug!(%url, "cached response");
+            return Ok(response);
+        }
+
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.wait(url.host_str().unwrap_or_default()).await;
+        }
+        let response =
+            Self::send_inner(self.inner, &url, self.max_response_size, self.timeouts).await?;
+        /* before recording or caching, so a block doesn't outlive itself */
+        if let Some(kind) = response.block_kind() {
+            let host = url.host_str().unwrap_or_default();
+            tracing::warn!(%url, %kind, "blocked");
+            if let Some(rate_limit) = &self.rate_limit {
+                rate_limit.back_off(host).await;
+            }
+            return Err(BlockedError { url, kind }.into());
+        }
+
+        if let Some(cassette) = &self.cassette {
+            cassette.record(&method, &url, &response)?;
+        }
+        if let Some(cache) = cache {
+            /* a cache that can't be written shouldn't fail the request */
+            if let Err(e) = cache.put(&url, &response) {
+                tracing::warn!(%url, error = %e, "could not cache response");
+            }
+        }
+        Ok(response)
+    }
+
+    async fn send_inner(
+        inner: reqwest::RequestBuilder,
+        url: &Url,
+        max_size: u64,
+        timeouts: Timeouts,
+    ) -> anyhow::Result<Response> {
+        /* reqwest can only time out a whole request, so reads are timed out here */
+        async fn read<T>(
+            url: &Url,
+            timeout: Option<Duration>,
+            future: impl Future<Output = reqwest::Result<T>>,
+        ) -> anyhow::Result<T> {
+            match timeout {
+                Some(after) => match tokio::time::timeout(after, future).await {
+                    Ok(result) => Ok(result?),
+                    Err(_) => Err(TimeoutError {
+                        url: url.clone(),
+                        after,
+                    }
+                    .into()),
+                },
+                None => Ok(future.await?),
+            }
+        }
+
+        let start = Instant::now();
+        let download = async {
+            let mut response = read(url, timeouts.read, inner.send()).await?;
+            let status = response.status();
+            let url = response.url().clone();
+            let headers = response.headers().clone();
+
+            /* the length isn't known up front for compressed or chunked responses */
+            let too_large = || TooLargeError {
+                url: url.clone(),
+                limit: max_size,
+            };
+            if response.content_length().is_some_and(|len| len > max_size) {
+                return Err(too_large().into());
+            }
+            let mut body = Vec::new();
+            while let Some(chunk) = read(&url, timeouts.read, response.chunk()).await? {
+                if (body.len() + chunk.len()) as u64 > max_size {
+                    return Err(too_large().into());
+                }
+                body.extend_from_slice(&chunk);
+            }
+
+            anyhow::Result::<_>::Ok(Response {
+                status,
+                url,
+                headers,
+                body,
+                fetched_at: Utc::now(),
+            })
+        };
+        let result = match timeouts.total {
+            Some(after) => tokio::time::timeout(after, download)
+                .await
+                .unwrap_or_else(|_| {
+                    Err(TimeoutError {
+                        url: url.clone(),
+                        after,
+                    }
+                    .into())
+                }),
+            None => download.await,
+        };
+
+        match &result {
+            Ok(response) => {
+                tracing::debug!(
+                    url = %response.url,
+                    status = response.status.as_u16(),
+                    duration_ms = start.elapsed().as_millis() as u64,
+                    bytes = response.body.len(),
+                    "response"
+                );
+                metrics::record(
+                    response.url.host_str().unwrap_or_default(),
+                    !(response.status.is_client_error() || response.status.is_server_error()),
+                    response.body.len() as u64,
+                );
+            }
+            Err(e) => {
+                tracing::warn!(
+                    %url,
+                    duration_ms = start.elapsed().as_millis() as u64,
+                    error = %e,
+                    "request failed"
+                );
+                metrics::record(url.host_str().unwrap_or_default(), false, 0);
+            }
+        }
+
+        result
+    }
+}
+
+/// A 4xx or 5xx response, as an error (see [`Response::error_for_status`]).
+///
+/// Callers can find it in an error's chain to tell e.g. a missing page from a parse failure.
+#[derive(Debug)]
+pub struct StatusError {
+    /// The URL of the response, if it can be shown (it may contain an API key).
+    pub url: Option<Url>,
+    pub status: StatusCode,
+}
+
+impl Display for StatusError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.url {
+            Some(url) => write!(f, "{} returned {}", url, self.status),
+            None => write!(f, "server returned {}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for StatusError {}
+
+/// The most a response body may be, unless the client says otherwise (see
+/// [`Client::with_max_response_size`](super::Client::with_max_response_size)).
+pub const DEFAULT_MAX_RESPONSE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A response body bigger than the client allows, as an error. Its download is abandoned.
+#[derive(Debug)]
+pub struct TooLargeError {
+    pub url: Url,
+    /// The limit, in bytes.
+    pub limit: u64,
+}
+
+impl Display for TooLargeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} returned more than the {} byte limit",
+            self.url, self.limit
+        )
+    }
+}
+
+impl std::error::Error for TooLargeError {}
+
+/// A request that took longer than one of its [`Timeouts`], as an error.
+#[derive(Debug)]
+pub struct TimeoutError {
+    pub url: Url,
+    pub after: Duration,
+}
+
+impl Display for TimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} timed out after {:?}", self.url, self.after)
+    }
+}
+
+impl std::error::Error for TimeoutError {}
+
+/// The kind of page a site sends instead of content when it thinks a request is a bot.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockKind {
+    /// An interstitial like eBay's "Pardon Our Interruption".
+    Interruption,
+    /// A Cloudflare challenge ("Just a moment...").
+    Cloudflare,
+    /// A CAPTCHA.
+    Captcha,
+}
+
+impl Display for BlockKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Interruption => "bot check",
+            Self::Cloudflare => "Cloudflare challenge",
+            Self::Captcha => "CAPTCHA",
+        })
+    }
+}
+
+/// A block page (see [`BlockKind`]) instead of the content, as an error.
+///
+/// Like [`StatusError`], callers can find it in an error's chain, e.g. to back off or to
+/// switch proxies rather than report a parse failure.
+#[derive(Debug)]
+pub struct BlockedError {
+    pub url: Url,
+    pub kind: BlockKind,
+}
+
+impl Display for BlockedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} returned a {} page instead of content",
+            self.url, self.kind
+        )
+    }
+}
+
+impl std::error::Error for BlockedError {}
+
+/* block pages are small; real pages that merely mention a CAPTCHA are usually much bigger */
+const MAX_BLOCK_PAGE: usize = 256 * 1024;
+
+/// The longest delay of a `<meta http-equiv="refresh">` that is taken as a redirect (see
+/// [`Response::meta_refresh`]); pages that refresh later than that (e.g. to log out) are
+/// left alone.
+pub const MAX_REFRESH_DELAY: Duration = Duration::from_secs(10);
+
+/// How many `<meta http-equiv="refresh">` redirects [`Client::get_page`] follows.
+pub const MAX_REFRESHES: usize = 5;
+
+/// A response whose body has been fully downloaded.
+pub struct Response {
+    pub(crate) status: StatusCode,
+    pub(crate) url: Url,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: Vec<u8>,
+    pub(crate) fetched_at: DateTime<Utc>,
+}
+
+impl Response {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// The final URL of the response, after redirects.
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// When the response came from the server. For a response from a cache or a cassette,
+    /// that's when it was first fetched, not when it was read back.
+    pub fn fetched_at(&self) -> DateTime<Utc> {
+        self.fetched_at
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Turn 4xx and 5xx responses into errors.
+    ///
+    /// # Errors
+    /// Errors with a [`StatusError`] if the response has an error status.
+    pub fn error_for_status(self) -> anyhow::Result<Self> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            return Err(StatusError {
+                url: Some(self.url),
+                status: self.status,
+            }
+            .into());
+        }
+        Ok(self)
+    }
+
+    /// The body as text. Invalid UTF-8 is replaced rather than rejected.
+    ///
+    /// # Errors
+    /// This never fails; it returns a [`Result`] to match [`reqwest::Response::text`].
+    pub async fn text(self) -> anyhow::Result<String> {
+        Ok(String::from_utf8_lossy(&self.body).into_owned())
+    }
+
+    /// Whether the response is a block page rather than the content that was asked for.
+    pub fn block_kind(&self) -> Option<BlockKind> {
+        if self
+            .headers
+            .get("cf-mitigated")
+            .is_some_and(|v| v == "challenge")
+        {
+            return Some(BlockKind::Cloudflare);
+        }
+        let is_html = self
+            .headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.contains("html"));
+        if !is_html || self.body.len() > MAX_BLOCK_PAGE {
+            return None;
+        }
+
+        let body = String::from_utf8_lossy(&self.body).to_lowercase();
+        let title = body
+            .split_once("<title")
+            .and_then(|(_, rest)| rest.split_once('>'))
+            .and_then(|(_, rest)| rest.split_once("</title>"))
+            .map_or("", |(title, _)| title.trim());
+        let is_error = self.status.is_client_error() || self.status.is_server_error();
+
+        if title.contains("pardon our interruption") {
+            Some(BlockKind::Interruption)
+        } else if title == "just a moment..."
+            /* Cloudflare also puts its script on pages that loaded fine */
+            || (is_error && body.contains("/cdn-cgi/challenge-platform/"))
+        {
+            Some(BlockKind::Cloudflare)
+        } else if title.contains("captcha")
+            || title.contains("robot check")
+            || (is_error && body.contains("captcha"))
+        {
+            Some(BlockKind::Captcha)
+        } else {
+            None
+        }
+    }
+
+    /// The body parsed as HTML, unless it's known to be something else. Cassettes and caches
+    /// from before headers were saved have no content type, so those are parsed too.
+    pub fn document(&self) -> Option<NodeRef> {
+        let is_other = self
+            .headers
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| !v.contains("html"));
+        (!is_other).then(|| kuchiki::parse_html().one(String::from_utf8_lossy(&self.body).as_ref()))
+    }
+
+    /// The canonical URL of the page in `document` (this response's, see [`canonical_link`]),
+    /// or else its own URL, so that the same page fetched from different URLs is recorded
+    /// under one of them.
+    pub fn canonical_url(&self, document: &NodeRef) -> Url {
+        canonical_link(document)
+            .and_then(|href| self.url.join(&href).ok())
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .unwrap_or_else(|| self.url.clone())
+    }
+
+    /// Where the `<meta http-equiv="refresh">` of the page in `document` (this response's)
+    /// redirects to, if it has one that goes to another page within [`MAX_REFRESH_DELAY`].
+    pub fn meta_refresh(&self, document: &NodeRef) -> Option<Url> {
+        let refresh = meta_refresh(document)?;
+        let url = self.url.join(&refresh.url).ok()?;
+        (refresh.delay <= MAX_REFRESH_DELAY
+            && matches!(url.scheme(), "http" | "https")
+            && url != self.url)
+            .then_some(url)
+    }
+
+    /// Parse the body as JSON.
+    ///
+    /// # Errors
+    /// Errors if the body is not valid JSON for `T`.
+    pub async fn json<T: DeserializeOwned>(self) -> anyhow::Result<T> {
+        serde_json::from_slice(&self.body)
+            .map_err(|e| {
+                tracing::warn!(url = %self.url, error = %e, "could not parse JSON response");
+                e
+            })
+            .with_context(|| format!("could not parse JSON from {}", self.url))
+    }
+}
+
+/// A page from [`Client::get_page`]: the response, and its body parsed as HTML, once, for
+/// the refreshes, the canonical URL and a module's parser to all read.
+///
+/// The document can't be sent between threads, so a page should be read before anything
+/// else is awaited.
+pub struct Page {
+    pub response: Response,
+    /// The body as HTML; an empty document if the body isn't HTML.
+    pub document: NodeRef,
+}
+
+impl Page {
+    /// The page's canonical URL; see [`Response::canonical_url`].
+    pub fn canonical_url(&self) -> Url {
+        self.response.canonical_url(&self.document)
+    }
+
+    /// Turn 4xx and 5xx responses into errors, as [`Response::error_for_status`] does.
+    ///
+    /// # Errors
+    /// Errors if the status is 4xx or 5xx.
+    pub fn error_for_status(self) -> anyhow::Result<Self> {
+        Ok(Self {
+            response: self.response.error_for_status()?,
+            document: self.document,
+        })
+    }
+}
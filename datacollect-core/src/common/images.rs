@@ -0,0 +1,207 @@
+use std::{fmt::Display, str::FromStr, time::Duration};
+
+use anyhow::Context;
+use image::{imageops::FilterType, DynamicImage};
+use serde::{Deserialize, Serialize};
+use serde_with::{DeserializeFromStr, SerializeDisplay};
+
+use super::Client;
+
+/// A 64-bit perceptual hash, written as 16 hex digits.
+#[derive(SerializeDisplay, DeserializeFromStr, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Hash64(pub u64);
+
+impl Hash64 {
+    /// The number of bits that differ between two hashes.
+    pub fn distance(self, other: Self) -> u32 {
+        (self.0 ^ other.0).count_ones()
+    }
+}
+
+impl Display for Hash64 {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Hash64 {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(
+            u64::from_str_radix(s, 16).context("expected 16 hex digits")?,
+        ))
+    }
+}
+
+/// The perceptual hashes of an image.
+///
+/// Images that look the same (e.g. the same photo, resized or recompressed by a different
+/// site) have hashes that differ by only a few bits.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct ImageHash {
+    /// A hash of the image's low frequencies (DCT-based); robust to resizing and compression.
+    pub phash: Hash64,
+    /// A hash of the image's horizontal gradients; cheap, and robust to brightness changes.
+    pub dhash: Hash64,
+}
+
+impl ImageHash {
+    pub fn of(image: &DynamicImage) -> Self {
+        Self {
+            phash: phash(image),
+            dhash: dhash(image),
+        }
+    }
+
+    /// Whether two images are probably the same picture.
+    ///
+    /// Both hashes have to differ by at most `max_distance` bits; around 10 works well.
+    pub fn is_similar(&self, other: &Self, max_distance: u32) -> bool {
+        self.phash.distance(other.phash) <= max_distance
+            && self.dhash.distance(other.dhash) <= max_distance
+    }
+}
+
+/// Compute the difference hash of an image.
+///
+/// The image is shrunk to 9x8 grayscale pixels, and each bit says whether a pixel is
+/// brighter than the one to its right.
+pub fn dhash(image: &DynamicImage) -> Hash64 {
+    let small = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let bit = small.get_pixel(x, y).0[0] > small.get_pixel(x + 1, y).0[0];
+            hash = (hash << 1) | u64::from(bit);
+        }
+    }
+    Hash64(hash)
+}
+
+/// Compute the perceptual hash of an image.
+///
+/// The image is shrunk to 32x32 grayscale pixels and transformed with a DCT. Each bit says
+/// whether one of the 8x8 lowest frequencies is above their median.
+pub fn phash(image: &DynamicImage) -> Hash64 {
+    const N: usize = 32;
+    const LOW: usize = 8;
+
+    let small = image
+        .resize_exact(N as u32, N as u32, FilterType::Triangle)
+        .to_luma8();
+    let pixels = (0..N)
+        .map(|y| {
+            (0..N)
+                .map(|x| f64::from(small.get_pixel(x as u32, y as u32).0[0]))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    /* only the low frequencies are needed, so only compute those */
+    let cos = (0..LOW)
+        .map(|u| {
+            (0..N)
+                .map(|x| {
+                    (std::f64::consts::PI * u as f64 * (2 * x + 1) as f64 / (2 * N) as f64).cos()
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let mut coefficients = Vec::with_capacity(LOW * LOW);
+    for v in 0..LOW {
+        for u in 0..LOW {
+            let mut sum = 0.0;
+            for (y, row) in pixels.iter().enumerate() {
+                for (x, pixel) in row.iter().enumerate() {
+                    sum += pixel * cos[u][x] * cos[v][y];
+                }
+            }
+            coefficients.push(sum);
+        }
+    }
+
+    /* the DC term is just the average brightness, so leave it out of the median */
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let median = sorted[sorted.len() / 2];
+
+    Hash64(
+        coefficients
+            .iter()
+            .fold(0u64, |hash, c| (hash << 1) | u64::from(*c > median)),
+    )
+}
+
+/// Download an image and hash it.
+///
+/// # Errors
+/// Errors if the request failed, or if the response is not an image in a supported
+/// format (JPEG, PNG, GIF or WebP).
+pub async fn hash_url<const COOKIES: bool>(
+    client: &Client<COOKIES>,
+    url: &str,
+) -> anyhow::Result<ImageHash> {
+    let response = client.get(url).await?.send().await?.error_for_status()?;
+    let image = image::load_from_memory(response.bytes())
+        .with_context(|| format!("could not decode image from {}", url))?;
+    if image.width() == 0 || image.height() == 0 {
+        anyhow::bail!("image from {} is empty", url);
+    }
+    Ok(ImageHash::of(&image))
+}
+
+/// Download and hash several images, one at a time.
+///
+/// This waits a few hundred milliseconds between downloads (on top of the client's rate
+/// limit, and any crawl delay from robots.txt), since images are usually on the same host.
+pub async fn hash_urls<const COOKIES: bool>(
+    client: &Client<COOKIES>,
+    urls: &[String],
+) -> Vec<anyhow::Result<ImageHash>> {
+    let mut hashes = Vec::with_capacity(urls.len());
+    for (i, url) in urls.iter().enumerate() {
+        if i > 0 {
+            /* be nice! */
+            tokio::time::sleep(Duration::from_millis(300)).await;
+        }
+        hashes.push(hash_url(client, url).await);
+    }
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use image::{DynamicImage, ImageBuffer, Luma};
+
+    use super::{Hash64, ImageHash};
+
+    /// A picture of smooth waves, which looks the same at any size.
+    fn waves(width: u32, height: u32, fx: f64, fy: f64) -> DynamicImage {
+        DynamicImage::ImageLuma8(ImageBuffer::from_fn(width, height, |x, y| {
+            let (x, y) = (x as f64 / width as f64, y as f64 / height as f64);
+            let v = (x * fx * TAU).sin() + (y * fy * TAU).cos() + x;
+            Luma([(v * 60.0 + 128.0) as u8])
+        }))
+    }
+
+    #[test]
+    fn test_hashes() {
+        let original = ImageHash::of(&waves(640, 480, 1.5, 1.0));
+        let resized = ImageHash::of(&waves(200, 150, 1.5, 1.0));
+        let different = ImageHash::of(&waves(640, 480, 1.0, 2.5));
+
+        assert!(original.is_similar(&resized, 10));
+        assert!(!original.is_similar(&different, 10));
+    }
+
+    #[test]
+    fn test_hash64() {
+        let hash = Hash64(0x00ff_00ff_00ff_00ff);
+        assert_eq!(hash.to_string(), "00ff00ff00ff00ff");
+        assert_eq!("00ff00ff00ff00ff".parse::<Hash64>().unwrap(), hash);
+        assert_eq!(hash.distance(Hash64(0)), 32);
+    }
+}
@@ -0,0 +1,167 @@
+//! Normalizing and fuzzily matching product titles.
+//!
+//! Listing titles for the same product rarely look the same: sellers add marketing words,
+//! write units differently (`16 GB` vs `16GB`) and order words however they like.
+//! These helpers turn titles into sets of comparable tokens and score how well they match.
+
+use std::collections::BTreeSet;
+
+use lazy_static::lazy_static;
+
+/// Words that sellers add to titles which say nothing about the product itself.
+const MARKETING_WORDS: &[&str] = &[
+    "new",
+    "brand",
+    "sealed",
+    "genuine",
+    "authentic",
+    "original",
+    "oem",
+    "free",
+    "shipping",
+    "fast",
+    "ship",
+    "ships",
+    "sale",
+    "hot",
+    "best",
+    "deal",
+    "wow",
+    "look",
+    "l@@k",
+    "rare",
+    "tested",
+    "working",
+    "great",
+    "excellent",
+    "mint",
+    "nib",
+    "nwt",
+    "bnib",
+    "us",
+    "usa",
+    "seller",
+];
+
+/// Units that are often written both with and without a space after the number.
+const UNITS: &[&str] = &[
+    "tb", "gb", "mb", "kb", "ghz", "mhz", "hz", "w", "nm", "mm", "cm", "in", "mah", "v",
+];
+
+/// Normalize a product title into lowercase, space-separated tokens.
+///
+/// Punctuation is dropped (except inside numbers and model names like `i7-8700k`),
+/// marketing words are removed, and numbers are joined with the unit after them.
+///
+/// ## Example
+/// ```txt
+/// "NEW Corsair Vengeance 16 GB DDR4 3200MHz - FAST SHIPPING!" -> "corsair vengeance 16gb ddr4 3200mhz"
+/// ```
+pub fn normalize_title(title: &str) -> String {
+    lazy_static! {
+        static ref RE_SEPARATOR: regex::Regex =
+            regex::Regex::new(r"[^a-z0-9@.\-]+|-(?:\s|$)|(?:^|\s)-|\.(?:\s|$)").unwrap();
+    }
+
+    let lower = title.to_lowercase();
+    let words = RE_SEPARATOR
+        .replace_all(&lower, " ")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    let mut tokens: Vec<String> = Vec::with_capacity(words.len());
+    for word in words {
+        let follows_number = tokens
+            .last()
+            .is_some_and(|t| t.chars().all(|c| c.is_ascii_digit() || c == '.'));
+        if follows_number && UNITS.contains(&word.as_str()) {
+            tokens.last_mut().unwrap().push_str(&word);
+        } else if !MARKETING_WORDS.contains(&word.as_str()) {
+            tokens.push(word);
+        }
+    }
+
+    tokens.join(" ")
+}
+
+/// The set of tokens in a normalized title.
+pub fn tokens(title: &str) -> BTreeSet<String> {
+    normalize_title(title)
+        .split_whitespace()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Score how well two titles match, from `0.0` (nothing in common) to `1.0`.
+///
+/// This is a token-set comparison: word order doesn't matter, and the score is the fraction of
+/// the *shorter* title's tokens that are found in the longer one. That way a short name like
+/// `Ryzen 5 2600` matches a long listing title that contains it perfectly.
+pub fn token_set_score(a: &str, b: &str) -> f64 {
+    let (a, b) = (tokens(a), tokens(b));
+    let smaller = a.len().min(b.len());
+    if smaller == 0 {
+        return 0.0;
+    }
+
+    a.intersection(&b).count() as f64 / smaller as f64
+}
+
+/// Find the candidate that best matches `needle`, returning its index and score.
+///
+/// Candidates scoring below `threshold` are never returned. Ties go to the earliest candidate.
+pub fn best_match<I, S>(needle: &str, candidates: I, threshold: f64) -> Option<(usize, f64)>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    candidates
+        .into_iter()
+        .enumerate()
+        .map(|(i, c)| (i, token_set_score(needle, c.as_ref())))
+        .filter(|(_, score)| *score >= threshold)
+        .fold(None, |best, (i, score)| match best {
+            Some((_, best_score)) if best_score >= score => best,
+            _ => Some((i, score)),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{best_match, normalize_title, token_set_score};
+
+    #[test]
+    fn test_normalize_title() {
+        assert_eq!(
+            normalize_title("NEW Corsair Vengeance 16 GB DDR4 3200MHz - FAST SHIPPING!"),
+            "corsair vengeance 16gb ddr4 3200mhz"
+        );
+        assert_eq!(
+            normalize_title("Intel Core i7-8700K 3.7 GHz"),
+            "intel core i7-8700k 3.7ghz"
+        );
+        assert_eq!(normalize_title("16GB"), normalize_title("16 gb"));
+    }
+
+    #[test]
+    fn test_scores() {
+        assert_eq!(
+            token_set_score(
+                "AMD Ryzen 5 2600",
+                "AMD Ryzen 5 2600 Six-Core 3.4GHz CPU NEW"
+            ),
+            1.0
+        );
+        assert_eq!(token_set_score("Ryzen 5 2600", "Ryzen 7 2700"), 1.0 / 3.0);
+        assert_eq!(token_set_score("", "anything"), 0.0);
+
+        let titles = [
+            "AMD Ryzen 7 2700",
+            "Intel Core i5-9400",
+            "AMD Ryzen 5 2600 CPU",
+        ];
+        assert_eq!(best_match("Ryzen 5 2600", titles, 0.5), Some((2, 1.0)));
+        assert_eq!(best_match("GeForce GTX 1080", titles, 0.5), None);
+    }
+}
@@ -0,0 +1,338 @@
+//! Reading a page's OpenGraph (`og:*`, and `product:*`) and Twitter card (`twitter:*`) `<meta>`
+//! tags, which many sites publish instead of (or as well as) schema.org microdata, so that a
+//! page's title, images and price can still be read when it has no microdata.
+//!
+//! ## Example
+//! ```txt
+//! <meta property="og:title" content="Handmade Mug">
+//! <meta property="product:price:amount" content="34.00">
+//! <meta property="product:price:currency" content="USD">
+//! <meta name="twitter:card" content="summary_large_image">
+//!
+//! -> PageMeta { title: Some("Handmade Mug"), price: Some(34.00 USD),
+//!               twitter_card: Some("summary_large_image"), .. }
+//! ```
+//!
+//! It also reads the `<link rel="canonical">` and `<meta http-equiv="refresh">` tags that say
+//! where a page really is; see [`canonical_link`] and [`meta_refresh`].
+
+use std::time::Duration;
+
+use kuchiki::{traits::TendrilSink, NodeRef};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::Money;
+
+/// The OpenGraph and Twitter card metadata of a page. Where both have something, OpenGraph's
+/// is used, and where a tag is repeated, the first one is (except for images).
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug, Default)]
+pub struct PageMeta {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// The canonical link to the page.
+    pub url: Option<String>,
+    pub site_name: Option<String>,
+    /// What the page is about (`og:type`), e.g. `product` or `article`.
+    pub kind: Option<String>,
+    /// Links to images, in the order they're given, without duplicates.
+    pub images: Vec<String>,
+    /// The price, from `product:price:*` or `og:price:*`, or else a Twitter card's `Price`
+    /// label.
+    pub price: Option<Money>,
+    /// e.g. `instock` or `in stock`, as the page says it.
+    pub availability: Option<String>,
+    /// The kind of Twitter card (`twitter:card`), e.g. `summary_large_image`.
+    pub twitter_card: Option<String>,
+    /// The site's Twitter handle (`twitter:site`), e.g. `@Etsy`.
+    pub twitter_site: Option<String>,
+}
+
+impl PageMeta {
+    /// Read the metadata of a page.
+    pub fn from_html(html: &str) -> Self {
+        Self::from_document(&kuchiki::parse_html().one(html))
+    }
+
+    /// Like [`PageMeta::from_html`], for a page that's already parsed.
+    pub fn from_document(document: &NodeRef) -> Self {
+        /* OpenGraph uses `property`, Twitter `name`, but pages mix them up */
+        let tags = document
+            .select("meta[content]")
+            .into_iter()
+            .flatten()
+            .filter_map(|meta| {
+                let attributes = meta.attributes.borrow();
+                let key = attributes
+                    .get("property")
+                    .or_else(|| attributes.get("name"))?
+                    .trim()
+                    .to_lowercase();
+                let value = attributes.get("content")?.trim().to_string();
+                (!value.is_empty()).then_some((key, value))
+            })
+            .collect::<Vec<_>>();
+        let first = |keys: &[&str]| {
+            keys.iter().find_map(|key| {
+                tags.iter()
+                    .find(|(k, _)| k == key)
+                    .map(|(_, value)| value.clone())
+            })
+        };
+
+        let images = tags
+            .iter()
+            .filter(|(key, _)| {
+                matches!(
+                    key.as_str(),
+                    "og:image" | "og:image:url" | "og:image:secure_url" | "twitter:image"
+                )
+            })
+            .fold(Vec::new(), |mut images, (_, url)| {
+                if !images.contains(url) {
+                    images.push(url.clone());
+                }
+                images
+            });
+
+        let price = first(&["product:price:amount", "og:price:amount"])
+            .and_then(|amount| {
+                let currency = first(&["product:price:currency", "og:price:currency"]);
+                Money::from_amount(&amount, currency.as_deref()).ok()
+            })
+            .or_else(|| {
+                /* e.g. `twitter:label1` = `Price` and `twitter:data1` = `$34.00` */
+                tags.iter().find_map(|(key, label)| {
+                    let n = key.strip_prefix("twitter:label")?;
+                    if !label.eq_ignore_ascii_case("price") {
+                        return None;
+                    }
+                    first(&[&format!("twitter:data{}", n)])?.parse().ok()
+                })
+            });
+
+        Self {
+            title: first(&["og:title", "twitter:title"]),
+            description: first(&["og:description", "twitter:description"]),
+            url: first(&["og:url"]),
+            site_name: first(&["og:site_name"]),
+            kind: first(&["og:type"]),
+            images,
+            price,
+            availability: first(&["product:availability", "og:availability"]),
+            twitter_card: first(&["twitter:card"]),
+            twitter_site: first(&["twitter:site"]),
+        }
+    }
+}
+
+/// The `href` of a page's `<link rel="canonical">`, as it's written (so it may be relative).
+///
+/// Sites link to the same page in many ways (with tracking queries, slugs, or other hosts),
+/// but give it one canonical link, so it's what records should be told apart by.
+pub fn canonical_link(document: &NodeRef) -> Option<String> {
+    document
+        .select("link[rel][href]")
+        .into_iter()
+        .flatten()
+        .find_map(|link| {
+            let attributes = link.attributes.borrow();
+            let canonical = attributes
+                .get("rel")?
+                .split_ascii_whitespace()
+                .any(|rel| rel.eq_ignore_ascii_case("canonical"));
+            let href = attributes.get("href")?.trim();
+            (canonical && !href.is_empty()).then(|| href.to_string())
+        })
+}
+
+/// A `<meta http-equiv="refresh">` that sends the browser to another page.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Refresh {
+    /// How long the browser waits first.
+    pub delay: Duration,
+    /// Where it goes, as it's written (so it may be relative).
+    pub url: String,
+}
+
+/// Read a page's `<meta http-equiv="refresh">`, if it has one that goes to another URL (one
+/// without a URL only reloads the page).
+///
+/// ## Example
+/// ```txt
+/// <meta http-equiv="refresh" content="0; url='/itm/254625474154'">
+///
+/// -> Refresh { delay: 0s, url: "/itm/254625474154" }
+/// ```
+pub fn meta_refresh(document: &NodeRef) -> Option<Refresh> {
+    document
+        .select("meta[http-equiv][content]")
+        .into_iter()
+        .flatten()
+        .find_map(|meta| {
+            let attributes = meta.attributes.borrow();
+            if !attributes
+                .get("http-equiv")?
+                .trim()
+                .eq_ignore_ascii_case("refresh")
+            {
+                return None;
+            }
+            parse_refresh(attributes.get("content")?)
+        })
+}
+
+/// Parse the `content` of a refresh, roughly as browsers do: a delay in seconds, then
+/// optionally `;` or `,`, then the URL, optionally after `url=` and in quotes.
+fn parse_refresh(content: &str) -> Option<Refresh> {
+    let content = content.trim_start();
+    let end = content
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(content.len());
+    let (delay, rest) = content.split_at(end);
+    /* only the whole seconds count, as in browsers */
+    let delay = delay.split('.').next()?;
+    if delay.is_empty() && !content.starts_with('.') {
+        return None;
+    }
+    let delay = Duration::from_secs(delay.parse().unwrap_or_default());
+
+    let rest = rest.trim_start();
+    let rest = rest
+        .strip_prefix(|c| c == ';' || c == ',')
+        .unwrap_or(rest)
+        .trim_start();
+    let rest = match rest.get(..3) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("url") => {
+            match rest[3..].trim_start().strip_prefix('=') {
+                Some(url) => url.trim_start(),
+                None => rest,
+            }
+        }
+        _ => rest,
+    };
+    let url = match rest.chars().next() {
+        Some(quote @ ('\'' | '"')) => rest[1..].split(quote).next().unwrap_or_default(),
+        _ => rest,
+    }
+    .trim();
+
+    (!url.is_empty()).then(|| Refresh {
+        delay,
+        url: url.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use kuchiki::traits::TendrilSink;
+
+    use super::{canonical_link, meta_refresh, parse_refresh, PageMeta, Refresh};
+    use crate::common::Money;
+
+    #[test]
+    fn test_page_meta() {
+        let meta = PageMeta::from_html(
+            r#"<html><head>
+                <meta property="og:type" content="product">
+                <meta property="og:title" content=" Handmade Mug ">
+                <meta name="twitter:title" content="Handmade Mug | Etsy">
+                <meta name="twitter:description" content="A speckled stoneware mug.">
+                <meta property="og:image" content="https://i.etsystatic.com/1.jpg">
+                <meta property="og:image" content="https://i.etsystatic.com/2.jpg">
+                <meta name="twitter:image" content="https://i.etsystatic.com/1.jpg">
+                <meta property="product:price:amount" content="1.234,50">
+                <meta property="product:price:currency" content="USD">
+                <meta property="og:availability" content="instock">
+                <meta name="twitter:card" content="summary_large_image">
+                <meta name="twitter:site" content="@Etsy">
+                <meta property="og:url" content="">
+            </head></html>"#,
+        );
+        assert_eq!(meta.kind.as_deref(), Some("product"));
+        assert_eq!(meta.title.as_deref(), Some("Handmade Mug"));
+        assert_eq!(
+            meta.description.as_deref(),
+            Some("A speckled stoneware mug.")
+        );
+        assert_eq!(
+            meta.images,
+            [
+                "https://i.etsystatic.com/1.jpg",
+                "https://i.etsystatic.com/2.jpg"
+            ]
+        );
+        assert_eq!(meta.price, Some(Money::from(1234.5)));
+        assert_eq!(meta.availability.as_deref(), Some("instock"));
+        assert_eq!(meta.twitter_card.as_deref(), Some("summary_large_image"));
+        assert_eq!(meta.twitter_site.as_deref(), Some("@Etsy"));
+        assert_eq!(meta.url, None);
+
+        let meta = PageMeta::from_html(
+            r#"<meta name="twitter:label1" content="Ships from">
+            <meta name="twitter:data1" content="United States">
+            <meta name="twitter:label2" content="Price">
+            <meta name="twitter:data2" content="$34.00">"#,
+        );
+        assert_eq!(meta.price, Some(Money::from(34.0)));
+        assert_eq!(
+            PageMeta::from_html("<p>No metadata</p>"),
+            PageMeta::default()
+        );
+    }
+
+    #[test]
+    fn test_canonical_link() {
+        let document = kuchiki::parse_html().one(
+            r#"<head>
+                <link rel="stylesheet" href="/style.css">
+                <link rel="alternate canonical" href=" /itm/254625474154 ">
+                <link rel="canonical" href="https://www.ebay.com/itm/1">
+            </head>"#,
+        );
+        assert_eq!(
+            canonical_link(&document).as_deref(),
+            Some("/itm/254625474154")
+        );
+        let document = kuchiki::parse_html().one(r#"<link rel="canonical" href="">"#);
+        assert_eq!(canonical_link(&document), None);
+    }
+
+    #[test]
+    fn test_meta_refresh() {
+        let refresh = |url: &str, delay| {
+            Some(Refresh {
+                delay: Duration::from_secs(delay),
+                url: url.to_string(),
+            })
+        };
+        assert_eq!(parse_refresh("0; url=/a"), refresh("/a", 0));
+        assert_eq!(parse_refresh("5;URL='/a b'"), refresh("/a b", 5));
+        assert_eq!(parse_refresh(" 1.5 , url = \"/a\";x"), refresh("/a", 1));
+        assert_eq!(
+            parse_refresh("0;https://example.com/"),
+            refresh("https://example.com/", 0)
+        );
+        assert_eq!(parse_refresh("0 /a"), refresh("/a", 0));
+        assert_eq!(parse_refresh("300"), None);
+        assert_eq!(parse_refresh("0; url="), None);
+        assert_eq!(parse_refresh("soon; url=/a"), None);
+
+        let document = kuchiki::parse_html().one(
+            r#"<head>
+                <meta http-equiv="content-type" content="text/html">
+                <meta http-equiv="Refresh" content="0; URL=https://www.etsy.com/listing/1043239412">
+            </head>"#,
+        );
+        assert_eq!(
+            meta_refresh(&document),
+            refresh("https://www.etsy.com/listing/1043239412", 0)
+        );
+        assert_eq!(
+            meta_refresh(&kuchiki::parse_html().one("<p>Nothing here</p>")),
+            None
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    },
+};
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+lazy_static! {
+    static ref HOSTS: Mutex<HashMap<String, HostStats>> = Mutex::new(HashMap::new());
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Counters for the requests sent to one host.
+#[derive(Serialize, Clone, Default, Debug)]
+pub struct HostStats {
+    pub requests: u64,
+    /// Requests that failed to send, or that got a 4xx/5xx response.
+    pub errors: u64,
+    /// Bytes of response bodies downloaded.
+    pub bytes: u64,
+}
+
+/// Start recording metrics for every request sent through a [`Client`](super::Client).
+///
+/// Metrics are process-wide and off by default.
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Record the outcome of a single request.
+pub(crate) fn record(host: &str, ok: bool, bytes: u64) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+
+    let mut hosts = HOSTS.lock().unwrap();
+    let stats = hosts.entry(host.to_string()).or_default();
+    stats.requests += 1;
+    stats.errors += u64::from(!ok);
+    stats.bytes += bytes;
+}
+
+/// The metrics recorded so far, by host.
+pub fn snapshot() -> HashMap<String, HostStats> {
+    HOSTS.lock().unwrap().clone()
+}
+
+/// Forget every metric recorded so far.
+pub fn reset() {
+    HOSTS.lock().unwrap().clear();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{enable, record, snapshot};
+
+    #[test]
+    fn test_record() {
+        enable();
+        record("metrics.test", true, 100);
+        record("metrics.test", false, 0);
+
+        let stats = &snapshot()["metrics.test"];
+        assert_eq!(stats.requests, 2);
+        assert_eq!(stats.errors, 1);
+        assert_eq!(stats.bytes, 100);
+    }
+}
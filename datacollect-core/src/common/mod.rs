@@ -0,0 +1,1478 @@
+#[cfg(feature = "net")]
+pub mod auth;
+#[cfg(feature = "render-js")]
+pub mod browser;
+#[cfg(feature = "net")]
+pub mod cache;
+#[cfg(feature = "net")]
+pub mod cassette;
+pub mod collected;
+#[cfg(feature = "net")]
+pub mod cookies;
+pub mod credentials;
+pub mod dates;
+#[cfg(feature = "net")]
+pub mod dns;
+pub mod endpoints;
+pub mod extract;
+#[cfg(feature = "net")]
+pub mod http;
+#[cfg(feature = "net")]
+pub mod images;
+pub mod matching;
+pub mod meta;
+#[cfg(feature = "net")]
+pub mod metrics;
+pub mod price;
+#[cfg(feature = "net")]
+pub mod ratelimit;
+pub mod report;
+#[cfg(feature = "net")]
+pub mod robots;
+#[cfg(feature = "net")]
+pub mod sitemap;
+#[cfg(feature = "net")]
+pub mod streams;
+
+use anyhow::{bail, Context};
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Schema, SchemaObject},
+    JsonSchema,
+};
+use serde::{de::Visitor, Deserialize, Serialize};
+use serde_with::{DeserializeAs, DeserializeFromStr, SerializeDisplay};
+use std::{convert::TryFrom, fmt::Display, marker::PhantomData, path::PathBuf, str::FromStr};
+#[cfg(feature = "net")]
+use std::{
+    path::Path,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+#[cfg(feature = "net")]
+use lazy_static::lazy_static;
+use rust_decimal::{prelude::FromPrimitive, Decimal, RoundingStrategy};
+
+pub use self::collected::Collected;
+pub use self::credentials::Credentials;
+pub use self::endpoints::Endpoints;
+#[cfg(feature = "net")]
+pub use self::http::{Connections, RequestOptions, Timeouts};
+pub use self::price::{NumberLocale, PriceError};
+pub use self::report::{FieldIssue, ParseError, ParseFailure, WithReport};
+#[cfg(feature = "net")]
+use self::{
+    auth::Session,
+    cache::Cache,
+    cassette::Cassette,
+    cookies::CookieJar,
+    dns::DohResolver,
+    http::{Page, RequestBuilder},
+    ratelimit::RateLimit,
+    robots::RobotsPolicy,
+};
+
+/// A currency - some type of money.
+#[derive(SerializeDisplay, DeserializeFromStr, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Currency {
+    USD,
+}
+
+impl Currency {
+    /// Given a price with a currency symbol and an amount, try to extract a [`Currency`] from the symbol.
+    pub fn from_price<S: AsRef<str>>(s: S) -> Option<Self> {
+        s.as_ref()
+            .split(|c: char| c.is_whitespace() || c.is_numeric())
+            .find_map(|s| {
+                (!s.is_empty())
+                    .then(|| Self::from_abbreviation(s))
+                    .flatten()
+            })
+    }
+
+    /// Given an abbreviation/symbol, try to return the corresponding [`Currency`].
+    /// Only considers alphabetic characters - `$` is filtered out, for example.
+    pub fn from_abbreviation<S: AsRef<str>>(s: S) -> Option<Self> {
+        match s
+            .as_ref()
+            .chars()
+            .flat_map(char::to_lowercase)
+            .filter(|c| c.is_alphabetic())
+            .collect::<String>()
+            .as_str()
+        {
+            "" | "us" | "usd" => Some(Self::USD),
+            _ => None,
+        }
+    }
+
+    /// How many digits of minor units (e.g. cents) the currency has.
+    pub fn minor_units(self) -> u32 {
+        match self {
+            Self::USD => 2,
+        }
+    }
+}
+
+impl FromStr for Currency {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match Self::from_abbreviation(s) {
+            Some(thing) => Ok(thing),
+            None => bail!("no such abbreviation"),
+        }
+    }
+}
+
+/* serialized with `Display`, so the derived schema (an enum of variant names) could drift */
+impl JsonSchema for Currency {
+    fn schema_name() -> String {
+        "Currency".to_string()
+    }
+
+    fn json_schema(_gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            instance_type: Some(InstanceType::String.into()),
+            enum_values: Some(vec![Self::USD.to_string().into()]),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+impl Display for Currency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::USD => "USD",
+            }
+        )
+    }
+}
+
+/// Currency ([`Currency`]), and some amount of it ([`Decimal`]).
+/// Currently, money with no [`Currency`] is assumed to be USD.
+///
+/// The amount is serialized as a number, and can be deserialized from a number or a string
+/// (e.g. `["USD", 312.03]` or `["USD", "312.03"]`).
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct Money(
+    pub(crate) Currency,
+    #[serde(serialize_with = "rust_decimal::serde::float::serialize")]
+    #[schemars(with = "f64")]
+    pub(crate) Decimal,
+);
+
+impl Money {
+    pub fn new(currency: Currency, amount: Decimal) -> Self {
+        Self(currency, amount)
+    }
+
+    pub fn currency(&self) -> Currency {
+        self.0
+    }
+
+    /// The amount, in major units (e.g. dollars, not cents).
+    pub fn amount(&self) -> Decimal {
+        self.1
+    }
+
+    /// Add two amounts of money, or `None` if they are in different currencies (or the sum
+    /// overflows).
+    pub fn checked_add(&self, other: &Self) -> Option<Self> {
+        if self.0 != other.0 {
+            return None;
+        }
+        Some(Self(self.0, self.1.checked_add(other.1)?))
+    }
+
+    /// Subtract `other` from this amount, or `None` if they are in different currencies (or
+    /// the difference overflows).
+    pub fn checked_sub(&self, other: &Self) -> Option<Self> {
+        if self.0 != other.0 {
+            return None;
+        }
+        Some(Self(self.0, self.1.checked_sub(other.1)?))
+    }
+
+    /// An amount with its currency given separately (e.g. `19.95` and `USD`, as microdata and
+    /// OpenGraph give prices), or read as a price (see [`price`]) if the currency isn't given
+    /// or known.
+    ///
+    /// # Errors
+    /// Errors if `amount` has no number in it.
+    pub fn from_amount(amount: &str, currency: Option<&str>) -> anyhow::Result<Self> {
+        match currency.and_then(Currency::from_abbreviation) {
+            Some(cur) => {
+                let amount = price::parse_amount(amount, NumberLocale::Auto)
+                    .context("could not parse currency amount")?;
+                Ok(Self(cur, amount))
+            }
+            None => Self::from_str(amount),
+        }
+    }
+
+    /// An amount given as a float, rounded to the currency's minor units (as [`Money::round`])
+    /// so that float artifacts like `0.1 + 0.2` don't carry over. `None` if it's not finite,
+    /// or too large for a [`Decimal`].
+    ///
+    /// ## Example
+    /// ```txt
+    /// 0.30000000000000004 USD -> 0.30 USD
+    /// NaN                 USD -> None
+    /// ```
+    pub fn from_f64(currency: Currency, amount: f64) -> Option<Self> {
+        Some(Self(currency, Decimal::from_f64(amount)?).round())
+    }
+
+    /// The median of some prices, with the middle two averaged if there's an even number of
+    /// them. Only one currency can be compared, so only prices in the first one's count.
+    /// `None` if there are no prices.
+    ///
+    /// ## Example
+    /// ```txt
+    /// [3, 1, 2]    USD -> 2 USD
+    /// [3, 1, 2, 4] USD -> 2.50 USD
+    /// ```
+    pub fn median(prices: &[Self]) -> Option<Self> {
+        let currency = prices.first()?.0;
+        let mut amounts = prices
+            .iter()
+            .filter(|price| price.0 == currency)
+            .map(Self::amount)
+            .collect::<Vec<_>>();
+        amounts.sort_unstable();
+        let n = amounts.len();
+        let median = match n % 2 {
+            1 => amounts[n / 2],
+            _ => (amounts[n / 2 - 1] + amounts[n / 2]) / Decimal::TWO,
+        };
+        Some(Self(currency, median).round())
+    }
+
+    /// Round to the currency's minor units (e.g. whole cents), with halves rounded away
+    /// from zero.
+    ///
+    /// ## Example
+    /// ```txt
+    /// 312.125 USD -> 312.13 USD
+    /// 42.5    USD -> 42.50 USD
+    /// ```
+    pub fn round(&self) -> Self {
+        Self(
+            self.0,
+            self.1.round_dp_with_strategy(
+                self.0.minor_units(),
+                RoundingStrategy::MidpointAwayFromZero,
+            ),
+        )
+    }
+}
+
+/// For code that still works in floats: an amount of USD, rounded to whole cents with halves
+/// rounded away from zero; see [`Money::from_f64`].
+///
+/// ## Panics
+/// Panics if the amount is not finite, or too large for a [`Decimal`]; use
+/// [`Money::from_f64`] for amounts that might be.
+impl From<f64> for Money {
+    fn from(amount: f64) -> Self {
+        Self::from_f64(Currency::USD, amount)
+            .unwrap_or_else(|| panic!("{} is not an amount of money", amount))
+    }
+}
+
+/* amounts in different currencies can't be compared without an exchange rate */
+impl PartialOrd for Money {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        (self.0 == other.0).then(|| self.1.cmp(&other.1))
+    }
+}
+
+/// The first amount in a price (see [`price`]), so the lower end of a range.
+impl FromStr for Money {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cur = Currency::from_price(s).unwrap_or(Currency::USD);
+        let amounts = price::parse_amounts(s, NumberLocale::Auto)?;
+        Ok(Self(cur, amounts[0]))
+    }
+}
+
+impl TryFrom<crate::schema_org::Scope> for Money {
+    type Error = anyhow::Error;
+    fn try_from(scope: crate::schema_org::Scope) -> anyhow::Result<Self> {
+        let price = scope
+            .get_value("price")
+            .context("could not get price of item through schema.org microdata")?;
+        Self::from_amount(&price, scope.get_value("priceCurrency").as_deref())
+    }
+}
+
+/// A range of prices, e.g. of a listing whose variations cost different amounts.
+///
+/// ## Example
+/// ```txt
+/// "$10.99 to $24.99" -> 10.99 USD to 24.99 USD
+/// "$24.99"           -> 24.99 USD to 24.99 USD
+/// ```
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct MoneyRange {
+    pub min: Money,
+    pub max: Money,
+}
+
+impl MoneyRange {
+    /// The range between two amounts, in either order.
+    pub fn new(a: Money, b: Money) -> Self {
+        if b < a {
+            Self { min: b, max: a }
+        } else {
+            Self { min: a, max: b }
+        }
+    }
+
+    /// Whether the range is a single price.
+    pub fn is_single(&self) -> bool {
+        self.min == self.max
+    }
+}
+
+impl From<Money> for MoneyRange {
+    fn from(money: Money) -> Self {
+        Self {
+            min: money.clone(),
+            max: money,
+        }
+    }
+}
+
+/// A price with one amount, or a range with two.
+impl FromStr for MoneyRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let cur = Currency::from_price(s).unwrap_or(Currency::USD);
+        match price::parse_amounts(s, NumberLocale::Auto)?.as_slice() {
+            [amount] => Ok(Money(cur, *amount).into()),
+            [min, max] => Ok(Self::new(Money(cur, *min), Money(cur, *max))),
+            _ => Err(PriceError::Several(s.to_string()).into()),
+        }
+    }
+}
+
+/// Ignore commas when parsing number formats.
+/// e.g. 13,096,340.3 -> 13096340.3
+pub struct IgnoreComma<T>
+where
+    T: FromStr,
+{
+    _t: PhantomData<T>,
+}
+
+impl<'de, T> DeserializeAs<'de, T> for IgnoreComma<T>
+where
+    T: FromStr,
+{
+    fn deserialize_as<D>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Helper<TT>
+        where
+            TT: FromStr,
+        {
+            _tt: PhantomData<TT>,
+        }
+
+        impl<'de, TT> Visitor<'de> for Helper<TT>
+        where
+            TT: FromStr,
+        {
+            type Value = TT;
+
+            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+            where
+                E: serde::de::Error,
+            {
+                TT::from_str(v.replace(',', "").as_str())
+                    .map_err(|_| E::custom("format error while parsing in IgnoreComma"))
+            }
+
+            fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+                /* TODO: I really wish the error message could be implemented better */
+                formatter.write_fmt(format_args!("a FromStr (probably number), ignoring commas"))
+            }
+        }
+
+        deserializer.deserialize_str(Helper::<T> { _tt: PhantomData })
+    }
+}
+
+/// A wrapped [`reqwest::Client`].
+/// Some scrapers require cookies, while some don't need cookies.
+/// This struct takes advantage of Rust's static typing to make sure
+/// that scrapers that require cookies are never given a [`reqwest::Client`]
+/// that does not have a cookie jar.
+///
+/// Modules send their requests through [`Client::get`], which applies the client's
+/// [`RobotsPolicy`] (if it has one), and logs and counts each request (see [`metrics`]).
+///
+/// [`Client::default`] uses the process-wide [`ClientConfig`] (see [`set_default_config`]).
+/// Clones of a client share its connection pool, and so do all the default clients
+/// without cookies.
+#[cfg(feature = "net")]
+#[derive(Clone)]
+pub struct Client<const COOKIES: bool>(pub reqwest::Client, Layers);
+
+/// Optional behaviour added on top of a [`Client`]'s requests.
+#[cfg(feature = "net")]
+#[derive(Clone, Default)]
+pub(crate) struct Layers {
+    robots: Option<Arc<RobotsPolicy>>,
+    cassette: Option<Arc<Cassette>>,
+    cache: Option<Arc<Cache>>,
+    rate_limit: Option<Arc<RateLimit>>,
+    /// `(origin, base)` pairs; an origin of `None` matches every URL.
+    base_urls: Vec<(Option<String>, String)>,
+    /// The largest response body to download; see [`http::DEFAULT_MAX_RESPONSE_SIZE`].
+    max_response_size: Option<u64>,
+    timeouts: Timeouts,
+    connections: Connections,
+    /// Looks up hosts instead of the system's resolver.
+    doh: Option<Arc<DohResolver>>,
+    /// The cookie jar of a client with cookies, kept for rebuilding the inner client.
+    cookies: Option<Arc<CookieJar>>,
+    /// The login that requests are sent with, for a client with cookies.
+    session: Option<Arc<Session>>,
+    /// The proxy, kept for rebuilding the inner client (and for the browser
+    /// [`Client::render_js`] starts).
+    proxy: Option<String>,
+}
+
+/// Settings shared by every [`Client`] that is configured with them.
+///
+/// The cache and rate limit are shared too, so that (for example) two clients talking to
+/// the same host are limited together.
+#[cfg(feature = "net")]
+#[derive(Clone, Default)]
+pub struct ClientConfig {
+    /// A proxy for all requests, e.g. `http://127.0.0.1:3128` or `socks5://127.0.0.1:1080`.
+    pub proxy: Option<String>,
+    pub cache: Option<Arc<Cache>>,
+    pub rate_limit: Option<Arc<RateLimit>>,
+    pub robots: Option<Arc<RobotsPolicy>>,
+    /// The largest response body to download, in bytes.
+    pub max_response_size: Option<u64>,
+    pub timeouts: Option<Timeouts>,
+    pub connections: Option<Connections>,
+    /// Look up hosts with DNS-over-HTTPS, instead of the system's resolver.
+    pub doh: Option<Arc<DohResolver>>,
+}
+
+/// The process-wide [`ClientConfig`], and the [`reqwest::Client`] built from it.
+///
+/// Clients without cookies made by [`Client::default`] all share the one inner client,
+/// and so its connection pool; clients with cookies each get their own, so their cookie
+/// jars are kept apart.
+#[cfg(feature = "net")]
+#[derive(Default)]
+struct Defaults {
+    config: ClientConfig,
+    client: OnceLock<reqwest::Client>,
+}
+
+#[cfg(feature = "net")]
+lazy_static! {
+    static ref DEFAULTS: RwLock<Defaults> = RwLock::new(Defaults::default());
+}
+
+/// Set the configuration used by [`Client::default`], including the clients that modules
+/// create for themselves (e.g. for searches).
+///
+/// # Errors
+/// Errors if the proxy URL is invalid; the previous configuration is kept.
+#[cfg(feature = "net")]
+pub fn set_default_config(config: ClientConfig) -> anyhow::Result<()> {
+    if let Some(proxy) = &config.proxy {
+        reqwest::Proxy::all(proxy.as_str()).with_context(|| format!("bad proxy {}", proxy))?;
+    }
+    *DEFAULTS.write().unwrap() = Defaults {
+        config,
+        client: OnceLock::new(),
+    };
+    Ok(())
+}
+
+#[cfg(feature = "net")]
+impl Layers {
+    /// Apply a [`ClientConfig`]; settings it leaves out are not changed.
+    ///
+    /// Returns whether the inner [`reqwest::Client`] needs rebuilding.
+    fn apply(&mut self, config: &ClientConfig) -> bool {
+        let mut rebuild = false;
+        if let Some(proxy) = &config.proxy {
+            rebuild = true;
+            self.proxy = Some(proxy.clone());
+        }
+        if let Some(cache) = &config.cache {
+            self.cache = Some(cache.clone());
+        }
+        if let Some(rate_limit) = &config.rate_limit {
+            self.rate_limit = Some(rate_limit.clone());
+        }
+        if let Some(robots) = &config.robots {
+            self.robots = Some(robots.clone());
+        }
+        if let Some(max) = config.max_response_size {
+            self.max_response_size = Some(max);
+        }
+        if let Some(timeouts) = config.timeouts {
+            rebuild |= timeouts.connect != self.timeouts.connect;
+            self.timeouts = timeouts;
+        }
+        if let Some(connections) = config.connections {
+            rebuild |= connections != self.connections;
+            self.connections = connections;
+        }
+        if let Some(doh) = &config.doh {
+            rebuild = true;
+            self.doh = Some(doh.clone());
+        }
+        rebuild
+    }
+}
+
+#[cfg(feature = "net")]
+impl<const COOKIES: bool> Client<COOKIES> {
+    /// Make a new client with the given configuration, ignoring the process-wide one.
+    ///
+    /// # Errors
+    /// Errors if the proxy URL is invalid.
+    pub fn from_config(config: &ClientConfig) -> anyhow::Result<Self> {
+        let mut layers = Layers::default();
+        layers.apply(config);
+        if COOKIES {
+            layers.cookies = Some(Default::default());
+        }
+        Ok(Self(Self::build(&layers)?, layers))
+    }
+
+    /// Build the inner client for the given proxy, connect timeout, [`Connections`] and
+    /// DoH resolver.
+    fn build(layers: &Layers) -> anyhow::Result<reqwest::Client> {
+        let connections = &layers.connections;
+        let mut builder = reqwest::Client::builder()
+            .gzip(true)
+            .brotli(true)
+            .deflate(true)
+            .pool_idle_timeout(connections.idle_timeout)
+            .tcp_keepalive(connections.tcp_keepalive)
+            .http2_keep_alive_interval(connections.http2_keep_alive);
+        if let Some(timeout) = layers.timeouts.connect {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(max) = connections.max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if connections.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        match &layers.cookies {
+            Some(jar) if COOKIES => builder = builder.cookie_provider(jar.clone()),
+            _ => builder = builder.cookie_store(COOKIES),
+        }
+        if let Some(doh) = &layers.doh {
+            builder = builder.dns_resolver(doh.clone());
+        }
+        if let Some(proxy) = &layers.proxy {
+            builder = builder
+                .proxy(reqwest::Proxy::all(proxy).with_context(|| format!("bad proxy {}", proxy))?);
+        }
+        Ok(builder.build()?)
+    }
+}
+
+#[cfg(feature = "net")]
+impl Client<true> {
+    /// Keep cookies in `jar`, e.g. one opened from a file so that sessions last between
+    /// runs, or one per module from [`CookieJars`](cookies::CookieJars) so that sites don't
+    /// share cookies.
+    ///
+    /// Like [`Client::with_proxy`], this replaces the inner [`reqwest::Client`].
+    pub fn with_cookie_jar(mut self, jar: Arc<CookieJar>) -> Self {
+        self.1.cookies = Some(jar);
+        /* the proxy was checked when it was set */
+        self.0 = Self::build(&self.1).unwrap();
+        self
+    }
+
+    /// Send every request with a logged-in [`Session`], logging in before the first one
+    /// (see [`auth`]).
+    ///
+    /// Responses to these requests are never cached, since they're the account's own.
+    pub fn with_session(mut self, session: Session) -> Self {
+        self.1.session = Some(Arc::new(session));
+        self
+    }
+
+    pub fn session(&self) -> Option<&Arc<Session>> {
+        self.1.session.as_ref()
+    }
+
+    /// The client's cookies, or `None` if the inner [`reqwest::Client`] was made outside of
+    /// this crate (see [`From<reqwest::Client>`](#impl-From<Client>-for-Client<COOKIES>)).
+    pub fn cookie_jar(&self) -> Option<&Arc<CookieJar>> {
+        self.1.cookies.as_ref()
+    }
+}
+
+#[cfg(feature = "net")]
+impl<const COOKIES: bool> Default for Client<COOKIES> {
+    fn default() -> Self {
+        let defaults = DEFAULTS.read().unwrap();
+        let mut layers = Layers::default();
+        layers.apply(&defaults.config);
+        /* the proxy was checked by set_default_config */
+        let client = if COOKIES {
+            layers.cookies = Some(Default::default());
+            Self::build(&layers).unwrap()
+        } else {
+            defaults
+                .client
+                .get_or_init(|| Self::build(&layers).unwrap())
+                .clone()
+        };
+        Self(client, layers)
+    }
+}
+
+#[cfg(feature = "net")]
+impl<const COOKIES: bool> From<reqwest::Client> for Client<COOKIES> {
+    fn from(client: reqwest::Client) -> Self {
+        Self(client, Layers::default())
+    }
+}
+
+#[cfg(feature = "net")]
+impl<const COOKIES: bool> Client<COOKIES> {
+    /// Make every request through this client follow robots.txt.
+    ///
+    /// This is off by default.
+    pub fn with_robots(mut self, policy: RobotsPolicy) -> Self {
+        self.1.robots = Some(Arc::new(policy));
+        self
+    }
+
+    /// Record every response to the cassette at `path`, or replay them from it if it
+    /// already exists. See [`Cassette`].
+    ///
+    /// # Errors
+    /// Errors if the cassette exists but could not be read.
+    pub fn with_cassette<P: AsRef<Path>>(mut self, path: P) -> anyhow::Result<Self> {
+        self.1.cassette = Some(Arc::new(Cassette::open(path)?));
+        Ok(self)
+    }
+
+    /// Cache GET responses in the given [`Cache`].
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.1.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Leave time between requests to the same host, as set by the [`RateLimit`].
+    pub fn with_rate_limit(mut self, rate_limit: RateLimit) -> Self {
+        self.1.rate_limit = Some(Arc::new(rate_limit));
+        self
+    }
+
+    /// Give up on responses with bodies bigger than `bytes` (after decompression), with a
+    /// [`TooLargeError`](http::TooLargeError). The default is
+    /// [`DEFAULT_MAX_RESPONSE_SIZE`](http::DEFAULT_MAX_RESPONSE_SIZE).
+    pub fn with_max_response_size(mut self, bytes: u64) -> Self {
+        self.1.max_response_size = Some(bytes);
+        self
+    }
+
+    /// Send every request through a proxy, e.g. `http://127.0.0.1:3128`.
+    ///
+    /// This replaces the inner [`reqwest::Client`] with a new one, so any settings it was
+    /// built with outside of this crate are lost. The [`CookieJar`] is kept.
+    ///
+    /// # Errors
+    /// Errors if the proxy URL is invalid.
+    pub fn with_proxy(mut self, proxy: &str) -> anyhow::Result<Self> {
+        self.1.proxy = Some(proxy.to_string());
+        self.0 = Self::build(&self.1)?;
+        Ok(self)
+    }
+
+    /// Set how long requests may take. Single requests can override the read and total
+    /// timeouts with [`RequestOptions`](http::RequestOptions).
+    ///
+    /// Like [`Client::with_proxy`], a new connect timeout replaces the inner
+    /// [`reqwest::Client`].
+    pub fn with_timeouts(mut self, timeouts: Timeouts) -> Self {
+        if timeouts.connect != self.1.timeouts.connect {
+            self.1.timeouts = timeouts;
+            /* the proxy was checked when it was set */
+            self.0 = Self::build(&self.1).unwrap();
+        }
+        self.1.timeouts = timeouts;
+        self
+    }
+
+    /// Set how the client keeps its connections open (see [`Connections`]).
+    ///
+    /// Like [`Client::with_proxy`], this replaces the inner [`reqwest::Client`].
+    pub fn with_connections(mut self, connections: Connections) -> Self {
+        self.1.connections = connections;
+        /* the proxy was checked when it was set */
+        self.0 = Self::build(&self.1).unwrap();
+        self
+    }
+
+    /// Look up hosts with DNS-over-HTTPS (see [`DohResolver`]), for networks whose resolver
+    /// is broken or censored. Hosts reached through a proxy are looked up by the proxy.
+    ///
+    /// Like [`Client::with_proxy`], this replaces the inner [`reqwest::Client`].
+    pub fn with_doh(mut self, resolver: DohResolver) -> Self {
+        self.1.doh = Some(Arc::new(resolver));
+        /* the proxy was checked when it was set */
+        self.0 = Self::build(&self.1).unwrap();
+        self
+    }
+
+    /// Apply a [`ClientConfig`]; settings it leaves out are not changed.
+    ///
+    /// # Errors
+    /// Errors if the proxy URL is invalid.
+    pub fn with_config(mut self, config: &ClientConfig) -> anyhow::Result<Self> {
+        if self.1.apply(config) {
+            self.0 = Self::build(&self.1)?;
+        }
+        Ok(self)
+    }
+
+    /// Send requests for `origin` (e.g. `https://www.ebay.com`) to `base` instead,
+    /// keeping the path and query. This is mostly useful for pointing modules at a mirror
+    /// or a mock server; see [`crate::testing`].
+    pub fn with_base_url_override(mut self, origin: &str, base: &str) -> Self {
+        self.1.base_urls.push((
+            Some(origin.trim_end_matches('/').to_string()),
+            base.to_string(),
+        ));
+        self
+    }
+
+    /// Send every request to `base` instead, keeping the path and query.
+    pub(crate) fn with_base_url_override_all(mut self, base: &str) -> Self {
+        self.1.base_urls.push((None, base.to_string()));
+        self
+    }
+
+    /// Apply the client's base URL overrides to `url`.
+    ///
+    /// URLs that can't be parsed are returned as they are, to fail when they're sent.
+    pub(crate) fn resolve(&self, url: &str) -> String {
+        let parsed = match reqwest::Url::parse(url) {
+            Ok(parsed) if !self.1.base_urls.is_empty() => parsed,
+            _ => return url.to_string(),
+        };
+        let origin = parsed.origin().ascii_serialization();
+
+        match self
+            .1
+            .base_urls
+            .iter()
+            .find(|(o, _)| o.as_ref().is_none_or(|o| *o == origin))
+        {
+            Some((_, base)) => {
+                let mut url = format!("{}{}", base.trim_end_matches('/'), parsed.path());
+                if let Some(query) = parsed.query() {
+                    url = format!("{}?{}", url, query);
+                }
+                url
+            }
+            None => url.to_string(),
+        }
+    }
+
+    /// Wrap a request built with the inner [`reqwest::Client`].
+    pub(crate) fn request(&self, request: reqwest::RequestBuilder) -> RequestBuilder {
+        let builder = RequestBuilder::new(request, &self.1);
+        match &self.1.session {
+            /* sessions are only set on clients with cookies; the one that logs in has none,
+             * so that its own requests don't wait for the login */
+            Some(session) => {
+                let layers = Layers {
+                    session: None,
+                    ..self.1.clone()
+                };
+                builder.with_session(session.clone(), Client::<true>(self.0.clone(), layers))
+            }
+            None => builder,
+        }
+    }
+
+    /// Start a GET request to the given URL.
+    ///
+    /// If the client has a [`RobotsPolicy`], the request is checked against it when it's
+    /// sent, so that a query added to it (e.g. with [`RequestBuilder::query`]) is too.
+    ///
+    /// # Errors
+    /// Errors if the URL is invalid.
+    pub async fn get(&self, url: &str) -> anyhow::Result<RequestBuilder> {
+        let url = self.resolve(url);
+        let request = self.request(self.0.get(url));
+        Ok(match &self.1.robots {
+            Some(policy) => request.with_robots(
+                policy.clone(),
+                Client::<false>(self.0.clone(), self.1.clone()),
+            ),
+            None => request,
+        })
+    }
+
+    /// Send a GET request to the given URL, following the `<meta http-equiv="refresh">`
+    /// redirects of the pages it leads to (see [`meta_refresh`](http::Response::meta_refresh))
+    /// as a browser would, for sites that redirect that way instead of with a status. The
+    /// refreshes of error pages (4xx and 5xx) aren't followed.
+    ///
+    /// The page is parsed once, here, so its parser should read the [`Page::document`].
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed (see [`Client::get`]), or if there are more than
+    /// [`MAX_REFRESHES`](http::MAX_REFRESHES) refreshes (e.g. a loop).
+    pub async fn get_page(&self, url: &str) -> anyhow::Result<Page> {
+        let mut response = self.get(url).await?.send().await?;
+        let mut refreshes = 0;
+        loop {
+            /* the document isn't `Send`, so it's gone by the time the next page is fetched */
+            let next = {
+                let document = response.document();
+                let is_error =
+                    response.status().is_client_error() || response.status().is_server_error();
+                let next = document
+                    .as_ref()
+                    .filter(|_| !is_error)
+                    .and_then(|document| response.meta_refresh(document));
+                match next {
+                    Some(next) => next,
+                    None => {
+                        return Ok(Page {
+                            response,
+                            document: document.unwrap_or_else(kuchiki::NodeRef::new_document),
+                        })
+                    }
+                }
+            };
+
+            if refreshes == http::MAX_REFRESHES {
+                bail!("{} refreshed more than {} times", url, http::MAX_REFRESHES);
+            }
+            refreshes += 1;
+            tracing::debug!(from = %response.url(), to = %next, "following meta refresh");
+            response = self.get(next.as_str()).await?.send().await?;
+        }
+    }
+
+    /// Load a page in a headless browser, and parse its DOM once its scripts have run, for
+    /// pages that fill in their content (e.g. prices) with JavaScript.
+    ///
+    /// Like [`Client::get`], this follows the client's [`RobotsPolicy`] and base URL
+    /// overrides, and waits for its [`RateLimit`]. The browser uses the client's proxy,
+    /// but not its cookies, cache or cassette.
+    ///
+    /// # Errors
+    /// Errors if the URL is invalid or refused, if the browser could not be started (e.g.
+    /// Chrome isn't installed), or if the page could not be loaded.
+    #[cfg(feature = "render-js")]
+    #[tracing::instrument(skip(self), err)]
+    pub async fn render_js(&self, url: &str) -> anyhow::Result<kuchiki::NodeRef> {
+        use kuchiki::traits::TendrilSink;
+
+        let url = self.resolve(url);
+        let parsed = reqwest::Url::parse(&url).with_context(|| format!("bad URL {}", url))?;
+        let host = parsed.host_str().unwrap_or_default();
+        if let Some(policy) = &self.1.robots {
+            policy.check(self, &parsed).await?;
+        }
+        if let Some(rate_limit) = &self.1.rate_limit {
+            rate_limit.wait(host).await;
+        }
+
+        let result = browser::render(&url, self.1.proxy.as_deref()).await;
+        metrics::record(
+            host,
+            result.is_ok(),
+            result.as_ref().map_or(0, |html| html.len() as u64),
+        );
+        Ok(kuchiki::parse_html().one(result?))
+    }
+
+    /// Start a POST request to the given URL.
+    ///
+    /// POST requests aren't crawling, so they are not checked against robots.txt.
+    pub fn post(&self, url: &str) -> RequestBuilder {
+        self.request(self.0.post(self.resolve(url)))
+    }
+}
+
+/// The directory datacollect's own configuration lives in, e.g. `~/.config/datacollect`.
+///
+/// This is `$XDG_CONFIG_HOME/datacollect` if that is set, and `$HOME/.config/datacollect` otherwise.
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("datacollect"))
+}
+
+/// The directory datacollect keeps state in between runs (e.g. cookies), e.g.
+/// `~/.local/share/datacollect`.
+///
+/// This is `$XDG_DATA_HOME/datacollect` if that is set, and `$HOME/.local/share/datacollect` otherwise.
+pub fn data_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|dir| dir.join("datacollect"))
+}
+
+/// How much a module should collect about each thing it fetches.
+///
+/// Higher levels cost more requests (or more parsing); what exactly each level means
+/// is documented on the functions that take a [`Detail`].
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub enum Detail {
+    /// Only the essentials, skipping anything expensive to parse.
+    Minimal,
+    /// Everything on the main page, without extra requests.
+    #[default]
+    Default,
+    /// Everything available, including extra sub-requests.
+    Full,
+}
+
+impl FromStr for Detail {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(Self::Minimal),
+            "default" => Ok(Self::Default),
+            "full" => Ok(Self::Full),
+            _ => bail!("no such detail level (expected `minimal`, `default` or `full`)"),
+        }
+    }
+}
+
+/// Checks if all the characters in `needle` can be found in `haystack` in the same order.
+///
+/// Some platforms like to obfuscate certain visible text fields from bots.
+/// For example, eBay makes it more difficult to identify sponsored items by using several
+/// `display: none;` fields.
+/// A human may read the text as `Sponsored`, while a robot may read it as `ddSQpOonhsortied`,
+/// because the extra characters are actually their own `<span>`'s that are not displayed in a browser.
+/// This is presumably to make it harder to automatically block sponsored listings from appearing using extensions.
+pub fn has_hidden_word(needle: &str, haystack: &str) -> bool {
+    let mut needle = needle.chars().peekable();
+    for c in haystack.chars() {
+        match needle.peek() {
+            /* we've finished! */
+            None => break,
+            /* they matched! now just chop off the first char of the needle */
+            Some(first) if *first == c => {
+                needle.next();
+            }
+            /* they didn't match. this char must have been added to fool us! */
+            Some(_) => {}
+        }
+    }
+    needle.peek().is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::has_hidden_word;
+
+    use std::str::FromStr;
+    #[cfg(feature = "net")]
+    use std::time::{Duration, Instant};
+
+    use rust_decimal::Decimal;
+
+    #[cfg(feature = "net")]
+    use super::{
+        cache::Cache,
+        http::{self, BlockKind, BlockedError, StatusError, TimeoutError, TooLargeError},
+        ratelimit::RateLimit,
+        robots::RobotsPolicy,
+        Client, RequestOptions, Timeouts,
+    };
+    use super::{Currency, Money, MoneyRange};
+    #[cfg(feature = "net")]
+    use crate::testing::MockServer;
+
+    #[allow(clippy::if_same_then_else)]
+    fn roughly_equal(a: f64, b: f64) -> bool {
+        if a == b {
+            true
+        } else if ((a > 0.0) && (b < 0.0)) || ((a < 0.0) && (b > 0.0)) {
+            false
+        } else if ((a == 0.0) && (b != 0.0)) || ((a != 0.0) && (b == 0.0)) {
+            false
+        } else {
+            fn dif(x: f64, y: f64) -> f64 {
+                (x.abs().ln() - y.abs().ln()).abs()
+            }
+
+            dif(a, b) <= dif(1.0, 1.00001)
+        }
+    }
+
+    #[test]
+    fn test_roughly_equal() {
+        assert!(roughly_equal(0.0, 0.0));
+        assert!(roughly_equal(0.02, 0.02));
+        assert!(roughly_equal(0.02, 0.02000001));
+        assert!(roughly_equal(0.1 + 0.2, 0.3));
+        assert!(roughly_equal(0.1 - 0.2, 0.2 - 0.3));
+        assert!(roughly_equal(4000000.0, 4000000.2));
+        assert!(roughly_equal(-4000000.0, -4000000.2));
+
+        assert!(!roughly_equal(0.02, 0.03));
+        assert!(!roughly_equal(0.00002, 0.00003));
+        assert!(!roughly_equal(0.0, 0.00003));
+        assert!(!roughly_equal(0.00002, 0.00003));
+        assert!(!roughly_equal(1000.0, 1001.0));
+        assert!(!roughly_equal(2.0, -2.0));
+    }
+
+    #[test]
+    fn test_money() {
+        let a = Money::new(Currency::USD, Decimal::new(312125, 3));
+        let b = Money::from_str("$1,000").unwrap();
+        assert_eq!(
+            (a.currency(), a.amount()),
+            (Currency::USD, Decimal::new(312125, 3))
+        );
+        assert_eq!(a.round(), Money::from(312.13));
+        assert_eq!(Money::from(-0.125), Money::from(-0.13));
+        assert_eq!(Money::from(0.1 + 0.2), Money::from(0.3));
+        assert_eq!(
+            Money::from_f64(Currency::USD, 2.675),
+            Some(Money::new(Currency::USD, Decimal::new(268, 2)))
+        );
+        for amount in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, 1e30] {
+            assert_eq!(Money::from_f64(Currency::USD, amount), None);
+        }
+        assert!(std::panic::catch_unwind(|| Money::from(f64::NAN)).is_err());
+
+        let prices = [3.0, 1.0, 2.0, 4.5].map(Money::from);
+        assert_eq!(Money::median(&prices[..3]), Some(Money::from(2.0)));
+        assert_eq!(Money::median(&prices), Some(Money::from(2.5)));
+        assert_eq!(Money::median(&[]), None);
+
+        assert!(a < b);
+        assert_eq!(
+            b.checked_sub(&a).map(|m| m.round()),
+            Some(Money::from(687.88))
+        );
+        assert_eq!(
+            a.checked_add(&b).map(|m| m.amount()),
+            Some(Decimal::new(1312125, 3))
+        );
+
+        /* no float artifacts when adding up lots of prices */
+        let dime = Money::from_str("$0.10").unwrap();
+        let total = (0..1000).try_fold(Money::from(0.0), |total, _| total.checked_add(&dime));
+        assert_eq!(total, Some(Money::from(100.0)));
+
+        assert_eq!(
+            serde_json::to_string(&a.round()).unwrap(),
+            r#"["USD",312.13]"#
+        );
+        for json in [r#"["USD",312.13]"#, r#"["USD","312.13"]"#] {
+            assert_eq!(
+                serde_json::from_str::<Money>(json).unwrap(),
+                Money::from(312.13)
+            );
+        }
+
+        let range = MoneyRange::from_str("US $10.99 to $24.99").unwrap();
+        assert_eq!(
+            (range.min, range.max),
+            (Money::from(10.99), Money::from(24.99))
+        );
+        assert!(MoneyRange::from_str("$24.99").unwrap().is_single());
+        assert!(MoneyRange::from_str("$1 $2 $3").is_err());
+    }
+
+    #[test]
+    #[allow(clippy::bool_assert_comparison)]
+    fn test_has_hidden_word() {
+        assert_eq!(has_hidden_word("cookie", "cooOOOkie"), true);
+        assert_eq!(has_hidden_word("cookie", "cookie"), true);
+        assert_eq!(has_hidden_word("cookie", "423TGRcoAFoGRkHiDSDGRTe"), true);
+        assert_eq!(
+            has_hidden_word("baking cookies", "some cookie baking"),
+            false
+        );
+        assert_eq!(has_hidden_word("candy canes", "candy"), false);
+        assert_eq!(has_hidden_word("Gesponsert €", "GxesponsertY €"), true);
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_base_url_override() {
+        let client = Client::<false>::default()
+            .with_base_url_override("https://www.ebay.com", "http://localhost:8080/ebay/");
+        assert_eq!(
+            client.resolve("https://www.ebay.com/sch/i.html?_nkw=cpu"),
+            "http://localhost:8080/ebay/sch/i.html?_nkw=cpu"
+        );
+        assert_eq!(
+            client.resolve("https://rdap.org/domain/google.com"),
+            "https://rdap.org/domain/google.com"
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_cache() {
+        let dir = std::env::temp_dir().join(format!(
+            "datacollect-client-cache-{}",
+            rand::random::<u64>()
+        ));
+        let server = MockServer::start().await.unwrap();
+        server.mock("/a", 200, "hello").mock("/b", 500, "oops");
+
+        let client = server.client::<false>().with_cache(Cache::new(&dir));
+        for _ in 0..2 {
+            for path in ["/a", "/b"] {
+                let url = format!("{}{}", server.uri(), path);
+                client.get(&url).await.unwrap().send().await.unwrap();
+            }
+        }
+        /* errors aren't cached */
+        assert_eq!(server.requests(), vec!["/a", "/b", "/b"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_robots_query() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/robots.txt",
+                200,
+                "User-agent: *\nDisallow: /search?private",
+            )
+            .mock("/search", 200, "<p>Results</p>");
+        let client = server
+            .client::<false>()
+            .with_robots(RobotsPolicy::new("datacollect"));
+        let search = format!("{}/search", server.uri());
+
+        let request = client.get(&search).await.unwrap();
+        assert!(request.query(&[("private", "1")]).send().await.is_err());
+        let request = client.get(&search).await.unwrap();
+        assert!(request.query(&[("q", "cpu")]).send().await.is_ok());
+        assert_eq!(server.requests(), ["/robots.txt", "/search?q=cpu"]);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_robots_crawl_delay() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/robots.txt", 200, "User-agent: *\nCrawl-delay: 0.2")
+            .mock("/a", 200, "<p>A</p>");
+        let client = server
+            .client::<false>()
+            .with_robots(RobotsPolicy::new("datacollect"))
+            .with_rate_limit(RateLimit::per_host(Duration::ZERO));
+        let url = format!("{}/a", server.uri());
+
+        /* the crawl delay is the rate limit's, not added to it */
+        let start = Instant::now();
+        for _ in 0..3 {
+            client.get(&url).await.unwrap().send().await.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(400));
+        let host = reqwest::Url::parse(&url)
+            .unwrap()
+            .host_str()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            client.1.rate_limit.as_ref().unwrap().interval(&host),
+            Duration::from_millis(200)
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_get_page() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/old",
+                200,
+                r#"<meta http-equiv="refresh" content="0; url=/new?ref=old">"#,
+            )
+            .mock(
+                "/new",
+                200,
+                r#"<link rel="canonical" href="https://example.com/item/1">
+                <meta http-equiv="refresh" content="600; url=/logout">"#,
+            )
+            .mock("/plain", 200, "<p>No links</p>")
+            .mock(
+                "/gone",
+                404,
+                r#"<meta http-equiv="refresh" content="0; url=/new">"#,
+            )
+            .mock(
+                "/a",
+                200,
+                r#"<meta http-equiv="refresh" content="0; url=/b">"#,
+            )
+            .mock(
+                "/b",
+                200,
+                r#"<meta http-equiv="refresh" content="0; url=/a">"#,
+            );
+        let client = server.client::<false>();
+
+        let response = client
+            .get_page(&format!("{}/old", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.response.url().as_str(),
+            format!("{}/new?ref=old", server.uri())
+        );
+        assert_eq!(
+            response.canonical_url().as_str(),
+            "https://example.com/item/1"
+        );
+        assert_eq!(response.response.meta_refresh(&response.document), None);
+
+        let response = client
+            .get_page(&format!("{}/plain", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(
+            response.canonical_url().as_str(),
+            format!("{}/plain", server.uri())
+        );
+
+        /* an error page's refresh isn't followed */
+        let page = client
+            .get_page(&format!("{}/gone", server.uri()))
+            .await
+            .unwrap();
+        assert_eq!(page.response.status(), 404);
+        assert!(page.error_for_status().is_err());
+
+        assert!(client
+            .get_page(&format!("{}/a", server.uri()))
+            .await
+            .is_err());
+        assert_eq!(server.requests().len(), 2 + 1 + 1 + http::MAX_REFRESHES + 1,);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_status_error() {
+        let server = MockServer::start().await.unwrap();
+        server.mock("/gone", 404, "");
+
+        let error = server
+            .client::<false>()
+            .get(&format!("{}/gone", server.uri()))
+            .await
+            .unwrap()
+            .send()
+            .await
+            .unwrap()
+            .error_for_status()
+            .err()
+            .unwrap();
+        let status = error.downcast_ref::<StatusError>().unwrap();
+        assert_eq!(status.status, 404);
+        assert_eq!(
+            error.to_string(),
+            format!("{}/gone returned 404 Not Found", server.uri())
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_max_response_size() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/small", 200, &"x".repeat(1000))
+            .mock("/large", 200, &"x".repeat(5000));
+
+        let client = server.client::<false>().with_max_response_size(1000);
+        let small = client
+            .get(&format!("{}/small", server.uri()))
+            .await
+            .unwrap()
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(small.bytes().len(), 1000);
+
+        let error = client
+            .get(&format!("{}/large", server.uri()))
+            .await
+            .unwrap()
+            .send()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.downcast_ref::<TooLargeError>().unwrap().limit, 1000);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_timeouts() {
+        /* accepts connections, but never answers */
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut connections = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                connections.push(stream);
+            }
+        });
+
+        let client = Client::<false>::default().with_timeouts(Timeouts {
+            read: Some(Duration::from_millis(200)),
+            ..Default::default()
+        });
+        let error = client.get(&url).await.unwrap().send().await.err().unwrap();
+        assert_eq!(
+            error.downcast_ref::<TimeoutError>().unwrap().after,
+            Duration::from_millis(200)
+        );
+
+        let options = RequestOptions {
+            total_timeout: Some(Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let error = client
+            .get(&url)
+            .await
+            .unwrap()
+            .options(options)
+            .send()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.downcast_ref::<TimeoutError>().unwrap().after,
+            Duration::from_millis(100)
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_connections_reused() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        /* counts the connections it accepts, and keeps each one open */
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0; 4096];
+                    while matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {
+                        let response = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok";
+                        if stream.write_all(response).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+
+        /* each default client shares the same connections */
+        for _ in 0..3 {
+            let client = Client::<false>::default();
+            let response = client.get(&url).await.unwrap().send().await.unwrap();
+            assert_eq!(response.text().await.unwrap(), "ok");
+        }
+        assert_eq!(accepted.load(Ordering::SeqCst), 1);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_blocked() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/ebay",
+                200,
+                "<html><head><title>Pardon Our Interruption...</title></head></html>",
+            )
+            .mock(
+                "/cloudflare",
+                403,
+                "<html><head><title>Just a moment...</title></head>\
+                 <script src=\"/cdn-cgi/challenge-platform/h/g/orchestrate/jsch/v1\"></script></html>",
+            )
+            .mock(
+                "/captcha",
+                429,
+                "<html><body><div class=\"g-recaptcha\"></div></body></html>",
+            )
+            .mock(
+                "/login",
+                200,
+                "<html><head><title>Sign in</title></head><div class=\"g-recaptcha\"></div></html>",
+            )
+            .mock("/api", 200, r#"{"captcha": true}"#)
+            .mock(
+                "/shop",
+                200,
+                "<html><head><title>Shop</title></head>\
+                 <script src=\"/cdn-cgi/challenge-platform/scripts/jsd/main.js\"></script></html>",
+            );
+
+        let client = server.client::<false>();
+        for (path, kind) in [
+            ("/ebay", BlockKind::Interruption),
+            ("/cloudflare", BlockKind::Cloudflare),
+            ("/captcha", BlockKind::Captcha),
+        ] {
+            let url = format!("{}{}", server.uri(), path);
+            let error = client.get(&url).await.unwrap().send().await.err().unwrap();
+            assert_eq!(error.downcast_ref::<BlockedError>().unwrap().kind, kind);
+        }
+        /* a CAPTCHA (or Cloudflare's script) on a page that loaded fine is part of the page */
+        for path in ["/login", "/api", "/shop"] {
+            let url = format!("{}{}", server.uri(), path);
+            assert!(client.get(&url).await.unwrap().send().await.is_ok());
+        }
+    }
+}
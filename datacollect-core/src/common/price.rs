@@ -0,0 +1,230 @@
+//! Reading amounts out of prices as sites write them, in any of the common number formats.
+//!
+//! ## Example
+//! ```txt
+//! "$312.03"          -> 312.03
+//! "1,299.00 USD"     -> 1299.00
+//! "1.299,00 €"       -> 1299.00
+//! "1 299,00 €"       -> 1299.00
+//! "$.99"             -> 0.99
+//! "-$5.00"           -> -5.00
+//! "$10.99 to $24.99" -> 10.99 and 24.99 (with parse_amounts)
+//! "8.8.4.4"          -> error: not a number
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use rust_decimal::Decimal;
+
+lazy_static! {
+    /// A number, with digit groups separated by spaces (`1 299,00`), or by points, commas or
+    /// apostrophes (`1,299.00`, `1'299.00`), which are sorted out in [`NumberLocale::read`],
+    /// or just decimals (`.99`).
+    static ref NUMBER: Regex = Regex::new(
+        r"\d{1,3}(?:[ \u{a0}\u{202f}\u{2009}]\d{3})+(?:[.,]\d+)?|\d+(?:[.,'’]\d+)*|[.,]\d+"
+    )
+    .unwrap();
+}
+
+/// Which character separates the decimals in a number.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum NumberLocale {
+    /// Guess from the number: the last of `.` and `,` is the decimal separator if both are
+    /// there (`1,299.00`, `1.299,00`), and a lone `,` before three digits separates
+    /// thousands (`1,299`), as on US sites.
+    #[default]
+    Auto,
+    /// `1,299.00`, as in the US and the UK.
+    DecimalPoint,
+    /// `1.299,00`, as in most of Europe.
+    DecimalComma,
+}
+
+/// Why a price could not be read.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum PriceError {
+    /// There is no number in the text at all.
+    NoNumber(String),
+    /// A number's separators don't make sense, e.g. `8.8.4.4`, or `1.29.00`.
+    Malformed(String),
+    /// There are several numbers where one was expected, e.g. a range.
+    Several(String),
+    /// The number is too big to be an amount.
+    Overflow(String),
+}
+
+impl Display for PriceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoNumber(text) => write!(f, "there is no number in {:?}", text),
+            Self::Malformed(number) => write!(f, "{:?} is not a number", number),
+            Self::Several(text) => write!(f, "there is more than one number in {:?}", text),
+            Self::Overflow(number) => write!(f, "{:?} is too big to be a price", number),
+        }
+    }
+}
+
+impl std::error::Error for PriceError {}
+
+/// Whether `groups` (what is between the thousands separators) are digit groups, as in
+/// `1,299,000`: one to three digits, then three at a time.
+fn is_grouped(groups: &[&str]) -> bool {
+    groups.first().is_some_and(|g| (1..=3).contains(&g.len()))
+        && groups[1..].iter().all(|g| g.len() == 3)
+}
+
+impl NumberLocale {
+    /// The decimal separator of `number` (which has only digits, `.` and `,`), if it has one.
+    fn decimal_separator(self, number: &str) -> Option<char> {
+        match self {
+            Self::DecimalPoint => Some('.'),
+            Self::DecimalComma => Some(','),
+            Self::Auto => {
+                let last = number.rfind(['.', ','])?;
+                let separator = number[last..].chars().next()?;
+                let other = if separator == '.' { ',' } else { '.' };
+                let once = number.matches(separator).count() == 1;
+                match separator {
+                    _ if number.contains(other) => Some(separator),
+                    '.' if once => Some('.'),
+                    ',' if once && number.len() - last - 1 != 3 => Some(','),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Read one number, as matched by [`NUMBER`].
+    fn read(self, number: &str) -> Result<Decimal, PriceError> {
+        let malformed = || PriceError::Malformed(number.to_string());
+        /* spaces and apostrophes only ever separate thousands */
+        let digits = number
+            .chars()
+            .filter(|c| !c.is_whitespace() && !matches!(c, '\'' | '’'))
+            .collect::<String>();
+        let (whole, fraction) = match self.decimal_separator(&digits) {
+            Some(separator) => match digits.rsplit_once(separator) {
+                Some((whole, fraction)) => (whole, Some(fraction)),
+                None => (digits.as_str(), None),
+            },
+            None => (digits.as_str(), None),
+        };
+        if fraction.is_some_and(|f| f.contains(['.', ','])) {
+            return Err(malformed());
+        }
+        let groups = whole.split(['.', ',']).collect::<Vec<_>>();
+        if groups.len() > 1 && !is_grouped(&groups) {
+            return Err(malformed());
+        }
+        let whole = match groups.concat() {
+            /* `.99` */
+            whole if whole.is_empty() => "0".to_string(),
+            whole => whole,
+        };
+        let text = match fraction {
+            Some(fraction) => format!("{}.{}", whole, fraction),
+            None => whole,
+        };
+        Decimal::from_str(&text).map_err(|_| PriceError::Overflow(number.to_string()))
+    }
+}
+
+/// Whether the text before the first number of a price makes it negative, as in `-$5.00`
+/// or `$-5.00`. A `-` between numbers is a range (`$10.99 - $24.99`), so only the first
+/// number can be negative.
+fn is_negative(before: &str) -> bool {
+    before.chars().filter(|c| matches!(c, '-' | '−')).count() == 1
+        && !before.contains(|c: char| c.is_ascii_digit())
+}
+
+/// Every amount in `text`, in order, e.g. both ends of a range (`$10.99 to $24.99`).
+///
+/// # Errors
+/// Errors if there is no number in `text`, or one of them can't be read.
+pub fn parse_amounts(text: &str, locale: NumberLocale) -> Result<Vec<Decimal>, PriceError> {
+    let amounts = NUMBER
+        .find_iter(text)
+        .enumerate()
+        .map(|(i, number)| {
+            let amount = locale.read(number.as_str())?;
+            Ok(match i == 0 && is_negative(&text[..number.start()]) {
+                true => -amount,
+                false => amount,
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if amounts.is_empty() {
+        return Err(PriceError::NoNumber(text.to_string()));
+    }
+    Ok(amounts)
+}
+
+/// The amount in `text`, which should have exactly one number (besides the currency).
+///
+/// # Errors
+/// Errors if there isn't exactly one number in `text`, or it can't be read.
+pub fn parse_amount(text: &str, locale: NumberLocale) -> Result<Decimal, PriceError> {
+    match parse_amounts(text, locale)?.as_slice() {
+        [amount] => Ok(*amount),
+        _ => Err(PriceError::Several(text.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rust_decimal::Decimal;
+
+    use super::{parse_amount, parse_amounts, NumberLocale, PriceError};
+
+    #[test]
+    fn test_parse_amount() {
+        let auto = |text| parse_amount(text, NumberLocale::Auto);
+        assert_eq!(auto("$312.04"), Ok(Decimal::new(31204, 2)));
+        assert_eq!(auto("42"), Ok(Decimal::new(42, 0)));
+        assert_eq!(auto("$42.567"), Ok(Decimal::new(42567, 3)));
+        assert_eq!(auto("$1,000"), Ok(Decimal::new(1000, 0)));
+        assert_eq!(auto("US $1,299.00"), Ok(Decimal::new(129900, 2)));
+        assert_eq!(auto("1.299,00 €"), Ok(Decimal::new(129900, 2)));
+        assert_eq!(auto("1\u{a0}299,00 €"), Ok(Decimal::new(129900, 2)));
+        assert_eq!(auto("CHF 1'299.50"), Ok(Decimal::new(129950, 2)));
+        assert_eq!(auto("12,99 €"), Ok(Decimal::new(1299, 2)));
+        assert_eq!(auto("1.000.000"), Ok(Decimal::new(1000000, 0)));
+        assert_eq!(auto("$.99"), Ok(Decimal::new(99, 2)));
+        assert_eq!(auto("-$5.00"), Ok(Decimal::new(-500, 2)));
+        assert_eq!(auto("$-5.00"), Ok(Decimal::new(-500, 2)));
+
+        assert_eq!(
+            auto("8.8.4.4"),
+            Err(PriceError::Malformed("8.8.4.4".to_string()))
+        );
+        assert_eq!(auto("free"), Err(PriceError::NoNumber("free".to_string())));
+        assert!(matches!(
+            auto("$10.99 to $24.99"),
+            Err(PriceError::Several(_))
+        ));
+        assert_eq!(
+            parse_amounts("$10.99 to $24.99", NumberLocale::Auto),
+            Ok(vec![Decimal::new(1099, 2), Decimal::new(2499, 2)])
+        );
+        assert_eq!(
+            parse_amounts("$10.99-$24.99", NumberLocale::Auto),
+            Ok(vec![Decimal::new(1099, 2), Decimal::new(2499, 2)])
+        );
+
+        /* a lone separator before three digits is ambiguous without the locale */
+        assert_eq!(
+            parse_amount("1.299", NumberLocale::DecimalComma),
+            Ok(Decimal::new(1299, 0))
+        );
+        assert_eq!(
+            parse_amount("1,299", NumberLocale::DecimalComma),
+            Ok(Decimal::new(1299, 3))
+        );
+        assert_eq!(
+            parse_amount("1,299.5", NumberLocale::DecimalComma),
+            Err(PriceError::Malformed("1,299.5".to_string()))
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex as SyncMutex,
+    time::{Duration, Instant},
+};
+
+use tokio::sync::Mutex;
+
+/// A minimum interval between requests to the same host.
+///
+/// This is on top of the delays modules already leave between requests; it's for keeping a
+/// whole collection run (or several modules hitting the same site) under a site's limits.
+/// A host's crawl delay from its robots.txt (see [`RobotsPolicy`](super::robots::RobotsPolicy))
+/// is kept here too, so a host is waited on for the longer of the two, not both.
+///
+/// ## Example
+/// ```txt
+/// let limit = RateLimit::per_host(Duration::from_millis(500))
+///     .host("www.ebay.com", Duration::from_secs(2));
+/// let client = Client::default().with_rate_limit(limit);
+/// ```
+pub struct RateLimit {
+    default: Duration,
+    hosts: HashMap<String, Duration>,
+    /// The crawl delays hosts' robots.txt files ask for.
+    crawl_delays: SyncMutex<HashMap<String, Duration>>,
+    /// How long to leave a host alone after it blocks a request.
+    backoff: Duration,
+    /// When the next request to each host may be sent.
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl RateLimit {
+    /// Leave at least `interval` between requests to any one host.
+    pub fn per_host(interval: Duration) -> Self {
+        Self {
+            default: interval,
+            hosts: HashMap::new(),
+            crawl_delays: SyncMutex::new(HashMap::new()),
+            backoff: Duration::from_secs(60),
+            next: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use a different interval for one host (e.g. `www.ebay.com`).
+    pub fn host(mut self, host: &str, interval: Duration) -> Self {
+        self.hosts.insert(host.to_lowercase(), interval);
+        self
+    }
+
+    /// How long to wait before the next request to a host that blocked one, e.g. with a
+    /// CAPTCHA. This is a minute by default.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// The interval between requests to `host`, including its crawl delay.
+    pub fn interval(&self, host: &str) -> Duration {
+        let host = host.to_lowercase();
+        let interval = self.hosts.get(&host).copied().unwrap_or(self.default);
+        match self.crawl_delays.lock().unwrap().get(&host) {
+            Some(delay) => interval.max(*delay),
+            None => interval,
+        }
+    }
+
+    /// Leave at least `delay` between requests to `host`, which its robots.txt asks for.
+    pub(crate) fn crawl_delay(&self, host: &str, delay: Duration) {
+        self.crawl_delays
+            .lock()
+            .unwrap()
+            .insert(host.to_lowercase(), delay);
+    }
+
+    /// Wait until a request to `host` is allowed, and reserve the slot.
+    pub(crate) async fn wait(&self, host: &str) {
+        let interval = self.interval(host);
+        if interval.is_zero() {
+            return;
+        }
+
+        let start = {
+            let mut next = self.next.lock().await;
+            let now = Instant::now();
+            let start = next
+                .get(host)
+                .copied()
+                .filter(|at| *at > now)
+                .unwrap_or(now);
+            next.insert(host.to_string(), start + interval);
+            start
+        };
+        tokio::time::sleep_until(start.into()).await;
+    }
+
+    /// Hold off the next request to `host`, which has just blocked one.
+    pub(crate) async fn back_off(&self, host: &str) {
+        let mut next = self.next.lock().await;
+        let at = Instant::now() + self.backoff;
+        if next.get(host).is_none_or(|next| *next < at) {
+            next.insert(host.to_string(), at);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::RateLimit;
+
+    #[tokio::test]
+    async fn test_rate_limit() {
+        let limit = RateLimit::per_host(Duration::from_millis(100))
+            .host("slow.test", Duration::from_millis(300))
+            .host("fast.test", Duration::ZERO);
+        assert_eq!(limit.interval("SLOW.test"), Duration::from_millis(300));
+        /* a crawl delay only counts if it's longer */
+        limit.crawl_delay("slow.test", Duration::from_millis(200));
+        limit.crawl_delay("fast.test", Duration::from_millis(50));
+        assert_eq!(limit.interval("slow.test"), Duration::from_millis(300));
+        assert_eq!(limit.interval("fast.test"), Duration::from_millis(50));
+        limit.crawl_delay("fast.test", Duration::ZERO);
+
+        let start = Instant::now();
+        for _ in 0..3 {
+            limit.wait("a.test").await;
+            limit.wait("fast.test").await;
+        }
+        /* the first request goes straight away */
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let limit =
+            RateLimit::per_host(Duration::from_millis(10)).backoff(Duration::from_millis(300));
+        limit.back_off("a.test").await;
+        let start = Instant::now();
+        limit.wait("a.test").await;
+        assert!(start.elapsed() >= Duration::from_millis(250));
+    }
+}
@@ -0,0 +1,244 @@
+//! Reports of what a module couldn't read from a page, so that a partly parsed record can be
+//! told apart from a complete one, and the quality of a whole crawl measured (e.g. "the
+//! seller was missing from 12% of listings") rather than guessed from its `None`s.
+//!
+//! ## Example
+//! ```txt
+//! let mut tally = Tally::default();
+//! for page in pages {
+//!     let product = Product::from_html_with_report(&page, id, Detail::Default)?;
+//!     tally.add(&product);
+//! }
+//! println!("{:.0}% without a seller", tally.rate("seller") * 100.0);
+//! ```
+
+use std::{collections::BTreeMap, fmt};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// What went wrong with a field.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Problem {
+    /// The page doesn't have it, at least not where the module looks.
+    Missing,
+    /// The page has it, but it could not be read (e.g. a price that isn't a number).
+    Unreadable,
+}
+
+/// A field that a module expected, but couldn't fill in.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct FieldIssue {
+    /// The field, as it's named in the record, e.g. `seller`.
+    pub field: String,
+    pub problem: Problem,
+    /// Why, if there's more to say, e.g. the text that could not be read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl FieldIssue {
+    pub fn missing(field: &str) -> Self {
+        Self {
+            field: field.to_string(),
+            problem: Problem::Missing,
+            detail: None,
+        }
+    }
+
+    pub fn unreadable(field: &str, detail: impl fmt::Display) -> Self {
+        Self {
+            field: field.to_string(),
+            problem: Problem::Unreadable,
+            detail: Some(detail.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for FieldIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.problem, &self.detail) {
+            (Problem::Missing, None) => write!(f, "{} is missing", self.field),
+            (Problem::Missing, Some(detail)) => write!(f, "{} is missing ({})", self.field, detail),
+            (Problem::Unreadable, None) => write!(f, "{} could not be read", self.field),
+            (Problem::Unreadable, Some(detail)) => {
+                write!(f, "{} could not be read: {}", self.field, detail)
+            }
+        }
+    }
+}
+
+/// An item of a list (e.g. a CPU in Passmark's mega list) that couldn't be read, and so was
+/// left out of it.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct ParseFailure {
+    /// Where the item is in the list, from 0.
+    pub index: usize,
+    /// The field that couldn't be read, e.g. `id`, or `.` if it was the whole item.
+    pub path: String,
+    pub message: String,
+}
+
+/// A page (or file) that a module couldn't read at all, rather than some of its fields, e.g.
+/// an eBay listing without a title. The modules' parsers fail with it, so that a page that
+/// couldn't be understood can be told apart from one that couldn't be fetched.
+#[derive(Debug)]
+pub struct ParseError {
+    message: String,
+    source: Option<anyhow::Error>,
+}
+
+impl ParseError {
+    /// A parse error with the given message.
+    pub fn new(message: impl fmt::Display) -> Self {
+        Self {
+            message: message.to_string(),
+            source: None,
+        }
+    }
+
+    /// A parse error because of another error, e.g. from the CSV reader.
+    pub fn caused_by(message: impl fmt::Display, source: impl Into<anyhow::Error>) -> Self {
+        Self {
+            message: message.to_string(),
+            source: Some(source.into()),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.as_ref().map(|e| e.as_ref() as _)
+    }
+}
+
+/// A record, with the fields that couldn't be filled in; see the
+/// [module documentation](self).
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct WithReport<T> {
+    pub data: T,
+    pub parse_report: Vec<FieldIssue>,
+}
+
+impl<T> WithReport<T> {
+    /// `data`, with nothing to report yet.
+    pub fn new(data: T) -> Self {
+        Self {
+            data,
+            parse_report: Vec::new(),
+        }
+    }
+
+    /// Report that `field` is missing, if `missing`.
+    pub fn missing_if(&mut self, missing: bool, field: &str) {
+        if missing {
+            self.parse_report.push(FieldIssue::missing(field));
+        }
+    }
+
+    /// Report an issue.
+    pub fn push(&mut self, issue: FieldIssue) {
+        self.parse_report.push(issue);
+    }
+
+    /// Whether every field was filled in.
+    pub fn is_complete(&self) -> bool {
+        self.parse_report.is_empty()
+    }
+
+    /// Convert the data, keeping the report.
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> WithReport<U> {
+        WithReport {
+            data: f(self.data),
+            parse_report: self.parse_report,
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.data
+    }
+}
+
+/// How often each field had an issue, over many records.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug, Default)]
+pub struct Tally {
+    /// How many records were counted.
+    pub records: usize,
+    /// How many records had an issue with each field, by field.
+    pub issues: BTreeMap<String, usize>,
+}
+
+impl Tally {
+    /// Count a record's report. A field is only counted once per record.
+    pub fn add<T>(&mut self, record: &WithReport<T>) {
+        self.records += 1;
+        let mut fields = record
+            .parse_report
+            .iter()
+            .map(|issue| issue.field.as_str())
+            .collect::<Vec<_>>();
+        fields.sort_unstable();
+        fields.dedup();
+        for field in fields {
+            *self.issues.entry(field.to_string()).or_default() += 1;
+        }
+    }
+
+    /// The share of records (from 0 to 1) that had an issue with `field`.
+    pub fn rate(&self, field: &str) -> f64 {
+        match self.records {
+            0 => 0.0,
+            records => self.issues.get(field).copied().unwrap_or_default() as f64 / records as f64,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FieldIssue, Problem, Tally, WithReport};
+
+    #[test]
+    fn test_report() {
+        let mut full = WithReport::new(1);
+        full.missing_if(false, "seller");
+        assert!(full.is_complete());
+
+        let mut partial = WithReport::new(2);
+        partial.missing_if(true, "seller");
+        partial.push(FieldIssue::unreadable("price", "\"free\" is not a price"));
+        partial.push(FieldIssue::unreadable("price", "twice"));
+        assert!(!partial.is_complete());
+        assert_eq!(partial.parse_report[0].problem, Problem::Missing);
+        assert_eq!(
+            partial.parse_report[1].to_string(),
+            "price could not be read: \"free\" is not a price"
+        );
+        assert_eq!(
+            serde_json::to_value(partial.clone().map(|n| n * 2)).unwrap(),
+            serde_json::json!({
+                "data": 4,
+                "parse_report": [
+                    {"field": "seller", "problem": "missing"},
+                    {"field": "price", "problem": "unreadable", "detail": "\"free\" is not a price"},
+                    {"field": "price", "problem": "unreadable", "detail": "twice"},
+                ]
+            })
+        );
+
+        let mut tally = Tally::default();
+        for record in [&full, &partial, &partial, &full] {
+            tally.add(record);
+        }
+        assert_eq!(tally.records, 4);
+        assert_eq!(tally.rate("price"), 0.5);
+        assert_eq!(tally.rate("seller"), 0.5);
+        assert_eq!(tally.rate("images"), 0.0);
+    }
+}
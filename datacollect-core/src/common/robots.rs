@@ -0,0 +1,279 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::bail;
+use reqwest::Url;
+
+use super::{Client, RateLimit};
+
+/// The rules from a robots.txt file that apply to one user agent.
+#[derive(Default, Debug)]
+pub struct Robots {
+    /// `(allow, pattern)` pairs, in the order they were written.
+    rules: Vec<(bool, String)>,
+    crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// Parse a robots.txt file, keeping only the group that applies to `user_agent`.
+    ///
+    /// The group whose `User-agent` line is contained in `user_agent` (ignoring case) is used,
+    /// falling back to the `*` group.
+    pub fn parse(text: &str, user_agent: &str) -> Self {
+        let user_agent = user_agent.to_lowercase();
+        let mut specific: Option<Self> = None;
+        let mut wildcard: Option<Self> = None;
+
+        /* consecutive `User-agent` lines share the group that follows them */
+        let mut agents: Vec<String> = Vec::new();
+        let mut current = Self::default();
+        let mut in_rules = false;
+
+        let mut finish = |agents: &mut Vec<String>, current: &mut Self| {
+            let group = std::mem::take(current);
+            if agents
+                .iter()
+                .any(|a| a != "*" && user_agent.contains(a.as_str()))
+            {
+                specific.get_or_insert(group);
+            } else if agents.iter().any(|a| a == "*") {
+                wildcard.get_or_insert(group);
+            }
+            agents.clear();
+        };
+
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let (key, value) = match line.split_once(':') {
+                Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+                None => continue,
+            };
+
+            match key.as_str() {
+                "user-agent" => {
+                    if in_rules {
+                        finish(&mut agents, &mut current);
+                        in_rules = false;
+                    }
+                    agents.push(value.to_lowercase());
+                }
+                "allow" | "disallow" => {
+                    in_rules = true;
+                    /* an empty `Disallow:` allows everything, which is the default anyway */
+                    if !value.is_empty() {
+                        current.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    in_rules = true;
+                    current.crawl_delay = value.parse::<f64>().ok().map(Duration::from_secs_f64);
+                }
+                _ => {}
+            }
+        }
+        finish(&mut agents, &mut current);
+
+        specific.or(wildcard).unwrap_or_default()
+    }
+
+    /// Check whether the given path (including the query string, if any) may be crawled.
+    ///
+    /// The longest matching rule wins; if an `Allow` and a `Disallow` rule are equally long,
+    /// the `Allow` rule wins.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|(_, pattern)| matches(pattern, path))
+            .max_by_key(|(allow, pattern)| (pattern.len(), *allow))
+            .is_none_or(|(allow, _)| *allow)
+    }
+
+    /// How long to wait between requests, if the file asks for it.
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Match a robots.txt path pattern, where `*` matches anything and a trailing `$`
+/// anchors the pattern to the end of the path.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match path.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+
+    let parts = parts.collect::<Vec<_>>();
+    for (i, part) in parts.iter().enumerate() {
+        if anchored && i == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+
+    !anchored || rest.is_empty()
+}
+
+/// What to do when a request is disallowed by robots.txt.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RobotsMode {
+    /// Refuse to send the request.
+    #[default]
+    Enforce,
+    /// Log a warning, then send the request anyway.
+    Warn,
+}
+
+/// Makes a [`Client`](super::Client) follow each host's robots.txt.
+///
+/// robots.txt is fetched the first time a host is requested, and cached for as long as
+/// the policy lives. If the file asks for a crawl delay, the client's [`RateLimit`] spaces
+/// out requests to that host by at least that long (for a client without one, the policy
+/// does).
+pub struct RobotsPolicy {
+    user_agent: String,
+    mode: RobotsMode,
+    cache: Mutex<HashMap<String, Arc<Robots>>>,
+    /// Waits out crawl delays for clients without a [`RateLimit`] of their own.
+    rate_limit: RateLimit,
+}
+
+impl RobotsPolicy {
+    /// Create a policy that picks rules for the given user agent (e.g. `datacollect`).
+    pub fn new(user_agent: &str) -> Self {
+        Self {
+            user_agent: user_agent.to_string(),
+            mode: RobotsMode::default(),
+            cache: Mutex::new(HashMap::new()),
+            rate_limit: RateLimit::per_host(Duration::ZERO),
+        }
+    }
+
+    /// Change what happens to disallowed requests.
+    pub fn mode(mut self, mode: RobotsMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Get the rules for the host of `url`, fetching them if they aren't cached.
+    async fn robots<const COOKIES: bool>(
+        &self,
+        client: &Client<COOKIES>,
+        url: &Url,
+    ) -> anyhow::Result<Arc<Robots>> {
+        let origin = url.origin().ascii_serialization();
+        if let Some(robots) = self.cache.lock().unwrap().get(&origin) {
+            return Ok(robots.clone());
+        }
+
+        let res = client
+            .request(client.0.get(format!("{}/robots.txt", origin)))
+            .send()
+            .await?;
+        let robots = if res.status().is_success() {
+            Robots::parse(&res.text().await?, &self.user_agent)
+        } else if res.status().is_client_error() {
+            /* no robots.txt: everything is allowed */
+            Robots::default()
+        } else {
+            bail!(
+                "could not fetch robots.txt for {}: {}",
+                origin,
+                res.status()
+            );
+        };
+
+        let robots = Arc::new(robots);
+        self.cache.lock().unwrap().insert(origin, robots.clone());
+        Ok(robots)
+    }
+
+    /// Check that `url` may be requested, and pass the host's crawl delay on to the client's
+    /// [`RateLimit`] (or wait it out here, if the client has none).
+    ///
+    /// # Errors
+    /// Errors if robots.txt could not be fetched, or if the policy is [`RobotsMode::Enforce`]
+    /// and the URL is disallowed.
+    pub(crate) async fn check<const COOKIES: bool>(
+        &self,
+        client: &Client<COOKIES>,
+        url: &Url,
+    ) -> anyhow::Result<()> {
+        let robots = self.robots(client, url).await?;
+
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        if !robots.is_allowed(&path) {
+            match self.mode {
+                RobotsMode::Enforce => bail!("{} is disallowed by robots.txt", url),
+                RobotsMode::Warn => tracing::warn!(%url, "disallowed by robots.txt"),
+            }
+        }
+
+        if let Some(delay) = robots.crawl_delay() {
+            let host = url.host_str().unwrap_or_default();
+            match &client.1.rate_limit {
+                /* which the request waits for when it's sent */
+                Some(rate_limit) => rate_limit.crawl_delay(host, delay),
+                None => {
+                    self.rate_limit.crawl_delay(host, delay);
+                    self.rate_limit.wait(host).await;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Robots;
+
+    #[test]
+    fn test_robots() {
+        let text = "
+            # comments are ignored
+            User-agent: *
+            Disallow: /sch/
+            Allow: /sch/i.html
+            Disallow: /*.pdf$
+
+            User-agent: BadBot
+            User-agent: datacollect
+            Disallow: /usr/
+            Crawl-delay: 1.5
+        ";
+
+        let robots = Robots::parse(text, "datacollect/0.1");
+        assert!(!robots.is_allowed("/usr/someone"));
+        assert!(robots.is_allowed("/sch/foo"));
+        assert_eq!(robots.crawl_delay(), Some(Duration::from_millis(1500)));
+
+        let robots = Robots::parse(text, "SomeOtherBot");
+        assert!(!robots.is_allowed("/sch/foo"));
+        assert!(robots.is_allowed("/sch/i.html?_nkw=cpu"));
+        assert!(!robots.is_allowed("/files/manual.pdf"));
+        assert!(robots.is_allowed("/files/manual.pdf?download"));
+        assert!(robots.is_allowed("/usr/someone"));
+        assert_eq!(robots.crawl_delay(), None);
+
+        assert!(Robots::parse("", "datacollect").is_allowed("/anything"));
+    }
+}
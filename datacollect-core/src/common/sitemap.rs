@@ -0,0 +1,261 @@
+//! Reading a site's sitemaps, to find its pages (e.g. every product) without paging through
+//! its search.
+//!
+//! ## Example
+//! ```txt
+//! let pages = sitemap::urls(client, "https://www.example.com/sitemap.xml")
+//!     .filter_map(|entry| async move { entry.ok() })
+//!     .filter(|entry| futures::future::ready(entry.url.contains("/product/")));
+//! ```
+
+use std::{
+    collections::{HashSet, VecDeque},
+    io::Read,
+};
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, NaiveDate, Utc};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+
+use super::Client;
+
+/// A page (or, in an index, another sitemap) listed in a sitemap.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Entry {
+    pub url: String,
+    /// When the page last changed, if the sitemap says.
+    pub lastmod: Option<DateTime<Utc>>,
+}
+
+/// A sitemap file: either a list of pages, or an index of other sitemaps.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Sitemap {
+    Urls(Vec<Entry>),
+    Index(Vec<Entry>),
+}
+
+/// Read a `lastmod`, which may be just a date (`2021-11-20`) or a full timestamp.
+fn parse_lastmod(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    DateTime::parse_from_rfc3339(text)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+                .map(|t| t.and_utc())
+        })
+}
+
+impl Sitemap {
+    /// Parse a sitemap, which may be gzipped (e.g. `sitemap.xml.gz`).
+    ///
+    /// # Errors
+    /// Errors if the file isn't valid XML, or isn't a sitemap or a sitemap index.
+    pub fn parse(body: &[u8]) -> anyhow::Result<Self> {
+        let mut xml = String::new();
+        /* gzip's magic number; servers send `.xml.gz` files as they are */
+        if body.starts_with(&[0x1f, 0x8b]) {
+            flate2::read::GzDecoder::new(body)
+                .read_to_string(&mut xml)
+                .context("could not decompress the sitemap")?;
+        } else {
+            xml = String::from_utf8_lossy(body).into_owned();
+        }
+
+        let document = roxmltree::Document::parse(&xml).context("the sitemap is not valid XML")?;
+        let root = document.root_element();
+        let entry_tag = match root.tag_name().name() {
+            "urlset" => "url",
+            "sitemapindex" => "sitemap",
+            other => bail!("expected a sitemap, but the document is a <{}>", other),
+        };
+
+        let child_text = |node: roxmltree::Node, name: &str| {
+            node.children()
+                .find(|child| child.tag_name().name() == name)
+                .and_then(|child| child.text())
+                .map(str::trim)
+                .filter(|text| !text.is_empty())
+                .map(str::to_string)
+        };
+        let entries = root
+            .children()
+            .filter(|node| node.tag_name().name() == entry_tag)
+            .filter_map(|node| {
+                Some(Entry {
+                    url: child_text(node, "loc")?,
+                    lastmod: child_text(node, "lastmod").and_then(|text| parse_lastmod(&text)),
+                })
+            })
+            .collect();
+
+        Ok(match entry_tag {
+            "url" => Self::Urls(entries),
+            _ => Self::Index(entries),
+        })
+    }
+
+    /// Download and parse a sitemap.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or the response isn't a sitemap.
+    #[tracing::instrument(skip(client), err)]
+    pub async fn get<const COOKIES: bool>(
+        client: &Client<COOKIES>,
+        url: &str,
+    ) -> anyhow::Result<Self> {
+        let res = client.get(url).await?.send().await?.error_for_status()?;
+        Self::parse(res.bytes()).with_context(|| format!("could not read sitemap {}", url))
+    }
+}
+
+/// Every page listed in the sitemap at `url`, following sitemap indexes (each sitemap is
+/// only read once, even if indexes list it more than once).
+///
+/// A sitemap that can't be read is an error in the stream, and the rest are still read.
+pub fn urls<const COOKIES: bool>(
+    client: Client<COOKIES>,
+    url: &str,
+) -> impl Stream<Item = anyhow::Result<Entry>> {
+    struct State<const COOKIES: bool> {
+        client: Client<COOKIES>,
+        sitemaps: VecDeque<String>,
+        seen: HashSet<String>,
+        entries: VecDeque<Entry>,
+    }
+
+    let state = State {
+        client,
+        sitemaps: VecDeque::from([url.to_string()]),
+        seen: HashSet::from([url.to_string()]),
+        entries: VecDeque::new(),
+    };
+    futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(entry) = state.entries.pop_front() {
+                return Some((Ok(entry), state));
+            }
+
+            let url = state.sitemaps.pop_front()?;
+            match Sitemap::get(&state.client, &url).await {
+                Ok(Sitemap::Urls(entries)) => state.entries.extend(entries),
+                Ok(Sitemap::Index(sitemaps)) => {
+                    for sitemap in sitemaps {
+                        if state.seen.insert(sitemap.url.clone()) {
+                            state.sitemaps.push_back(sitemap.url);
+                        }
+                    }
+                }
+                Err(e) => return Some((Err(e), state)),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use chrono::{TimeZone, Utc};
+    use futures::StreamExt;
+
+    use super::{urls, Entry, Sitemap};
+    use crate::{common::Client, testing::MockServer};
+
+    #[test]
+    fn test_parse() {
+        let sitemap = Sitemap::parse(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+            <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9"
+                    xmlns:image="http://www.google.com/schemas/sitemap-image/1.1">
+                <url>
+                    <loc>https://www.example.com/product/1</loc>
+                    <lastmod>2021-11-20T14:53:00+01:00</lastmod>
+                    <image:image><image:loc>https://www.example.com/1.jpg</image:loc></image:image>
+                </url>
+                <url><loc><![CDATA[https://www.example.com/product/2?a=1&b=2]]></loc><lastmod>2021-11-21</lastmod></url>
+                <url><lastmod>2021-11-21</lastmod></url>
+            </urlset>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            sitemap,
+            Sitemap::Urls(vec![
+                Entry {
+                    url: "https://www.example.com/product/1".to_string(),
+                    lastmod: Some(Utc.with_ymd_and_hms(2021, 11, 20, 13, 53, 0).unwrap()),
+                },
+                Entry {
+                    url: "https://www.example.com/product/2?a=1&b=2".to_string(),
+                    lastmod: Some(Utc.with_ymd_and_hms(2021, 11, 21, 0, 0, 0).unwrap()),
+                },
+            ])
+        );
+
+        assert!(Sitemap::parse(b"<html><body>not found</body></html>").is_err());
+        assert!(Sitemap::parse(b"User-agent: *").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_urls() {
+        let mut gzipped = flate2::write::GzEncoder::new(Vec::new(), Default::default());
+        gzipped
+            .write_all(b"<urlset><url><loc>https://www.example.com/c</loc></url></urlset>")
+            .unwrap();
+        let gzipped = gzipped.finish().unwrap();
+
+        let server = MockServer::start().await.unwrap();
+        let index = format!(
+            "<sitemapindex>
+                <sitemap><loc>{0}/a.xml</loc></sitemap>
+                <sitemap><loc>{0}/missing.xml</loc></sitemap>
+                <sitemap><loc>{0}/nested.xml</loc></sitemap>
+            </sitemapindex>",
+            server.uri()
+        );
+        let nested = format!(
+            "<sitemapindex>
+                <sitemap><loc>{0}/a.xml</loc></sitemap>
+                <sitemap><loc>{0}/c.xml.gz</loc></sitemap>
+            </sitemapindex>",
+            server.uri()
+        );
+        server
+            .mock("/sitemap.xml", 200, &index)
+            .mock("/nested.xml", 200, &nested)
+            .mock(
+                "/a.xml",
+                200,
+                "<urlset>
+                    <url><loc>https://www.example.com/a</loc></url>
+                    <url><loc>https://www.example.com/b</loc></url>
+                </urlset>",
+            )
+            .mock_bytes("/c.xml.gz", 200, &gzipped);
+
+        let results = urls(
+            Client::<false>::default(),
+            &format!("{}/sitemap.xml", server.uri()),
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(results.len(), 4);
+        assert!(results[2].is_err());
+        let pages = results
+            .into_iter()
+            .filter_map(|r| r.ok())
+            .map(|entry| entry.url)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pages,
+            vec![
+                "https://www.example.com/a",
+                "https://www.example.com/b",
+                "https://www.example.com/c"
+            ]
+        );
+    }
+}
@@ -0,0 +1,210 @@
+//! Helpers for the `Stream<Item = anyhow::Result<T>>` that searches and other long-running
+//! collections produce (e.g. [`Product::search`](crate::modules::ebay::Product::search)).
+//!
+//! ## Example
+//! ```txt
+//! let products = streams::take_until_err_ratio(Product::search(query, detail), 0.5, 10);
+//! let products = streams::dedupe_by_key(products, |product| product.id);
+//! ```
+
+use std::{collections::HashSet, future::ready, hash::Hash, path::Path, sync::Arc};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+
+use super::ratelimit::RateLimit;
+use crate::checkpoint::SearchCheckpoint;
+
+/// Stop once more than `max_ratio` (e.g. `0.5`) of the items so far are errors, which
+/// usually means the site is blocking or has changed, rather than carrying on failing.
+///
+/// The ratio is only checked after the first `min_items` items. The error that crosses the
+/// ratio is the last item.
+pub fn take_until_err_ratio<T>(
+    stream: impl Stream<Item = anyhow::Result<T>>,
+    max_ratio: f64,
+    min_items: usize,
+) -> impl Stream<Item = anyhow::Result<T>> {
+    stream.scan(
+        (0usize, 0usize, false),
+        move |(items, errors, done), item| {
+            if *done {
+                return ready(None);
+            }
+            *items += 1;
+            if item.is_err() {
+                *errors += 1;
+            }
+            *done = *items >= min_items && *errors as f64 / *items as f64 > max_ratio;
+            ready(Some(item))
+        },
+    )
+}
+
+/// Wait before passing on each item, so that items for the same host (as found by `host`)
+/// are at least the [`RateLimit`]'s interval apart. This is for streams whose items are
+/// then fetched, e.g. sitemap entries.
+///
+/// The rate limit can be the same one the client uses (see
+/// [`ClientConfig`](super::ClientConfig)), to share its timing.
+pub fn throttle_per_host<T, F>(
+    stream: impl Stream<Item = anyhow::Result<T>>,
+    rate_limit: Arc<RateLimit>,
+    host: F,
+) -> impl Stream<Item = anyhow::Result<T>>
+where
+    F: Fn(&T) -> Option<String>,
+{
+    stream.then(move |item| {
+        let rate_limit = rate_limit.clone();
+        let host = item.as_ref().ok().and_then(&host);
+        async move {
+            if let Some(host) = host {
+                rate_limit.wait(&host).await;
+            }
+            item
+        }
+    })
+}
+
+/// Leave out items whose key (e.g. an id) has already been seen. Errors are kept.
+pub fn dedupe_by_key<T, K, F>(
+    stream: impl Stream<Item = anyhow::Result<T>>,
+    key: F,
+) -> impl Stream<Item = anyhow::Result<T>>
+where
+    K: Eq + Hash,
+    F: Fn(&T) -> K,
+{
+    let mut seen = HashSet::new();
+    stream.filter(move |item| {
+        ready(match item {
+            Ok(item) => seen.insert(key(item)),
+            Err(_) => true,
+        })
+    })
+}
+
+/// How many items [`checkpointed`] records between saves of the checkpoint.
+pub const SAVE_EVERY: usize = 50;
+
+/// Record each item of a resumable search (e.g.
+/// [`Product::search_resume_with`](crate::modules::ebay::Product::search_resume_with)) in
+/// `checkpoint`, saving it to `path` every [`SAVE_EVERY`] items and when the stream ends.
+///
+/// If the stream is dropped before it ends, up to [`SAVE_EVERY`] items are left out of the
+/// saved checkpoint, and are collected again when the search is resumed. A checkpoint that
+/// can't be saved is an error in the stream.
+pub fn checkpointed<'a, T, F>(
+    stream: impl Stream<Item = anyhow::Result<(u32, T)>> + 'a,
+    checkpoint: &'a mut SearchCheckpoint<T>,
+    path: impl AsRef<Path> + 'a,
+    id: F,
+) -> impl Stream<Item = anyhow::Result<T>> + 'a
+where
+    T: Serialize + Clone + 'a,
+    F: Fn(&T) -> u64 + 'a,
+{
+    futures::stream::unfold(
+        (Box::pin(stream.fuse()), checkpoint, path, id, 0usize),
+        |(mut stream, checkpoint, path, id, mut unsaved)| async move {
+            let item = match stream.next().await {
+                Some(Ok((page, item))) => {
+                    checkpoint.record(page, id(&item), item.clone());
+                    unsaved += 1;
+                    if unsaved < SAVE_EVERY {
+                        Ok(item)
+                    } else {
+                        unsaved = 0;
+                        checkpoint.save(&path).map(|()| item)
+                    }
+                }
+                Some(Err(e)) => Err(e),
+                None if unsaved > 0 => {
+                    unsaved = 0;
+                    /* a failed save is the last item; a successful one ends the stream */
+                    Err(checkpoint.save(&path).err()?)
+                }
+                None => return None,
+            };
+            Some((item, (stream, checkpoint, path, id, unsaved)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
+
+    use anyhow::anyhow;
+    use futures::StreamExt;
+
+    use super::{checkpointed, dedupe_by_key, take_until_err_ratio, throttle_per_host};
+    use crate::{checkpoint::SearchCheckpoint, common::ratelimit::RateLimit};
+
+    fn items(pattern: &str) -> impl futures::Stream<Item = anyhow::Result<char>> + '_ {
+        futures::stream::iter(pattern.chars().map(|c| match c {
+            'x' => Err(anyhow!("failed")),
+            c => Ok(c),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_take_until_err_ratio() {
+        let taken = take_until_err_ratio(items("abxcxxxde"), 0.5, 4)
+            .collect::<Vec<_>>()
+            .await;
+        /* 3 of 6 is only half, but 4 of 7 is too many */
+        assert_eq!(taken.len(), 7);
+        assert!(taken[6].is_err());
+
+        let taken = take_until_err_ratio(items("xxxab"), 0.5, 3)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(taken.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_dedupe_by_key() {
+        let deduped = dedupe_by_key(items("abAxBcx"), |c| c.to_ascii_lowercase())
+            .map(|item| item.map_or('x', |c| c))
+            .collect::<String>()
+            .await;
+        assert_eq!(deduped, "abxcx");
+    }
+
+    #[tokio::test]
+    async fn test_throttle_per_host() {
+        let limit = Arc::new(RateLimit::per_host(Duration::from_millis(100)));
+        let start = Instant::now();
+        /* the hosts take turns, so each only waits once */
+        let throttled = throttle_per_host(items("abxab"), limit, |c| Some(c.to_string()))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(throttled.len(), 5);
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_checkpointed() {
+        let path = std::env::temp_dir().join(format!(
+            "datacollect-streams-{}.json",
+            rand::random::<u64>()
+        ));
+        let mut checkpoint = SearchCheckpoint::new("query");
+        let found = futures::stream::iter(vec![Ok((1, 10)), Ok((2, 20)), Err(anyhow!("failed"))]);
+        let mut collected = Box::pin(checkpointed(found, &mut checkpoint, &path, |id| *id));
+
+        /* the checkpoint is saved in batches, so not after the first item */
+        assert_eq!(collected.next().await.unwrap().unwrap(), 10);
+        assert!(!path.exists());
+        assert_eq!(collected.collect::<Vec<_>>().await.len(), 2);
+
+        let saved = SearchCheckpoint::<u64>::load(&path).unwrap().unwrap();
+        assert_eq!((saved.page, saved.results), (2, vec![10, 20]));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
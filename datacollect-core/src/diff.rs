@@ -0,0 +1,268 @@
+//! Field-level differences between two versions of a record, e.g. the same eBay listing a day
+//! apart, so that what changed can be said (`price changed ["USD",449.0] → ["USD",399.0]`)
+//! instead of showing both versions.
+//!
+//! Records are compared as JSON. Objects are compared field by field, with nested fields named
+//! by dotted paths (`seller.feedback`); anything else, lists included, is compared as a whole,
+//! since a list's items can't be told apart by position.
+//!
+//! ## Example
+//! ```txt
+//! for change in diff_records(&yesterday, &today)? {
+//!     println!("{}", change); // "price changed ["USD",449.0] → ["USD",399.0]"
+//! }
+//! ```
+
+use std::{collections::HashMap, fmt};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// What happened to a field.
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// A change to one field of a record.
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct FieldChange {
+    /// The field, as a dotted path from the top of the record; empty if the records
+    /// themselves aren't objects.
+    pub path: String,
+    pub change: Change,
+    /// The value before, unless the field was added.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    /// The value after, unless the field was removed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+impl fmt::Display for FieldChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() {
+            "value"
+        } else {
+            &self.path
+        };
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "{} changed {} → {}", path, old, new),
+            (None, Some(new)) => write!(f, "{} added: {}", path, new),
+            (Some(old), None) => write!(f, "{} removed (was {})", path, old),
+            (None, None) => write!(f, "{} unchanged", path),
+        }
+    }
+}
+
+/// Whether two values are the same, with numbers compared by value (`449` is `449.0`).
+fn same(old: &Value, new: &Value) -> bool {
+    match (old, new) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| same(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len()
+                && a.iter()
+                    .all(|(name, a)| b.get(name).is_some_and(|b| same(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn diff_at(path: &str, old: &Value, new: &Value, changes: &mut Vec<FieldChange>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (name, old_value) in old {
+                let path = join(path, name);
+                match new.get(name) {
+                    Some(new_value) => diff_at(&path, old_value, new_value, changes),
+                    None => changes.push(FieldChange {
+                        path,
+                        change: Change::Removed,
+                        old: Some(old_value.clone()),
+                        new: None,
+                    }),
+                }
+            }
+            for (name, new_value) in new {
+                if !old.contains_key(name) {
+                    changes.push(FieldChange {
+                        path: join(path, name),
+                        change: Change::Added,
+                        old: None,
+                        new: Some(new_value.clone()),
+                    });
+                }
+            }
+        }
+        (old, new) if !same(old, new) => changes.push(FieldChange {
+            path: path.to_string(),
+            change: Change::Changed,
+            old: Some(old.clone()),
+            new: Some(new.clone()),
+        }),
+        _ => {}
+    }
+}
+
+/// The changes from `old` to `new`, in the order of `old`'s fields, then the added ones.
+pub fn diff_values(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+    diff_at("", old, new, &mut changes);
+    changes
+}
+
+/// The changes from `old` to `new`; see the [module documentation](self).
+///
+/// # Errors
+/// Errors if either record could not be serialized as JSON.
+pub fn diff_records<T: Serialize>(old: &T, new: &T) -> serde_json::Result<Vec<FieldChange>> {
+    Ok(diff_values(
+        &serde_json::to_value(old)?,
+        &serde_json::to_value(new)?,
+    ))
+}
+
+/// What happened to a record between two lists of them; see [`diff_lists`].
+#[derive(Serialize, Clone, PartialEq, Debug)]
+pub struct RecordChange {
+    /// The record's value of the key field.
+    pub key: Value,
+    pub change: Change,
+    /// The changes to its fields, if it was in both lists.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<FieldChange>,
+}
+
+/// The records added to, removed from and changed between two lists, matched by their
+/// `key` field (e.g. `id`). Records without the key are left out, and unchanged records
+/// aren't listed.
+pub fn diff_lists(old: &[Value], new: &[Value], key: &str) -> Vec<RecordChange> {
+    let key_of = |record: &Value| {
+        record
+            .get(key)
+            .filter(|k| !k.is_null())
+            .map(Value::to_string)
+    };
+    let new_by_key = new
+        .iter()
+        .filter_map(|record| Some((key_of(record)?, record)))
+        .collect::<HashMap<_, _>>();
+    let old_by_key = old
+        .iter()
+        .filter_map(|record| Some((key_of(record)?, record)))
+        .collect::<HashMap<_, _>>();
+
+    let mut changes = Vec::new();
+    for record in old {
+        let (k, value) = match (key_of(record), record.get(key)) {
+            (Some(k), Some(value)) => (k, value.clone()),
+            _ => continue,
+        };
+        match new_by_key.get(&k) {
+            Some(new_record) => {
+                let fields = diff_values(record, new_record);
+                if !fields.is_empty() {
+                    changes.push(RecordChange {
+                        key: value,
+                        change: Change::Changed,
+                        fields,
+                    });
+                }
+            }
+            None => changes.push(RecordChange {
+                key: value,
+                change: Change::Removed,
+                fields: Vec::new(),
+            }),
+        }
+    }
+    for record in new {
+        if let (Some(k), Some(value)) = (key_of(record), record.get(key)) {
+            if !old_by_key.contains_key(&k) {
+                changes.push(RecordChange {
+                    key: value.clone(),
+                    change: Change::Added,
+                    fields: Vec::new(),
+                });
+            }
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Serialize;
+    use serde_json::json;
+
+    use super::{diff_lists, diff_records, Change};
+    use crate::common::Money;
+
+    #[derive(Serialize)]
+    struct Listing {
+        id: u64,
+        price: Money,
+        seller: serde_json::Value,
+        shipping: Option<Money>,
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = Listing {
+            id: 1,
+            price: Money::from(449.0),
+            seller: json!({"name": "a", "feedback": 99}),
+            shipping: None,
+        };
+        let new = Listing {
+            id: 1,
+            price: Money::from(399.0),
+            seller: json!({"name": "a", "feedback": 99.0, "top_rated": true}),
+            shipping: None,
+        };
+        let changes = diff_records(&old, &new).unwrap();
+        assert_eq!(
+            changes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            vec![
+                r#"price changed ["USD",449.0] → ["USD",399.0]"#,
+                "seller.top_rated added: true",
+            ]
+        );
+        assert!(diff_records(&old, &old).unwrap().is_empty());
+        assert_eq!(
+            diff_records(&1, &2).unwrap()[0].to_string(),
+            "value changed 1 → 2"
+        );
+
+        let changes = diff_lists(
+            &[json!({"id": 1, "score": 10}), json!({"id": 2, "score": 20})],
+            &[json!({"id": 2, "score": 21}), json!({"id": 3, "score": 30})],
+            "id",
+        );
+        assert_eq!(
+            changes
+                .iter()
+                .map(|c| (c.key.clone(), c.change, c.fields.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                (json!(1), Change::Removed, 0),
+                (json!(2), Change::Changed, 1),
+                (json!(3), Change::Added, 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,353 @@
+//! Watching registered domains until they can be registered again ("drop-catching").
+//!
+//! Each domain is looked up over RDAP, more often the closer it gets to being deleted, and a
+//! [`Notification`] is sent once it can be registered.
+//!
+//! ## Example
+//! ```txt
+//! let mut watcher = Watcher::from_file(Endpoints::default(), "domains.txt")?;
+//! watcher.run(&mut client, &notify::Config::default().notifiers(), tokio::signal::ctrl_c()).await;
+//! ```
+
+use std::{future::Future, path::Path};
+
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{Client, Detail},
+    modules::rdap::{DomainRecord, Endpoints},
+    notify::{self, Notification, Notifier},
+};
+
+/* from expiring to being deleted, a gTLD domain goes through up to 45 days of auto-renew
+grace, 30 days of redemption, and 5 days pending delete */
+const DAYS_TO_DELETION: i64 = 80;
+
+/// A domain being watched, and what's known about it so far.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WatchedDomain {
+    /// The domain's name, e.g. `example.com`.
+    pub name: String,
+    /// Whether the domain could be registered at the last check. `None` until it's checked.
+    pub buyable: Option<bool>,
+    /// When the domain expires (or expired), if its record says.
+    pub expires_at: Option<DateTime<Utc>>,
+    pub checked_at: Option<DateTime<Utc>>,
+    pub next_check: DateTime<Utc>,
+    /// Why the last check failed, if it did.
+    pub error: Option<String>,
+}
+
+/* statuses of an expired domain that the registry hasn't released yet */
+const HELD: [&str; 4] = [
+    "auto renew period",
+    "redemption period",
+    "pending restore",
+    "pending delete",
+];
+
+fn has_status(record: &DomainRecord, status: &str) -> bool {
+    record.status.iter().any(|s| s.eq_ignore_ascii_case(status))
+}
+
+/// Whether a domain can be registered, given its record (`None` if it isn't registered).
+///
+/// An expired domain is only [buyable](DomainRecord::is_buyable_at) once it has also left
+/// the registry's grace periods.
+fn is_buyable(record: Option<&DomainRecord>, now: DateTime<Utc>) -> bool {
+    record.is_none_or(|record| {
+        record.is_buyable_at(&now) && !HELD.iter().any(|status| has_status(record, status))
+    })
+}
+
+/// How long to wait before checking a domain again, given its record (`None` if it isn't
+/// registered). The closer the domain is to being deleted, the sooner.
+fn poll_interval(record: Option<&DomainRecord>, now: DateTime<Utc>) -> Duration {
+    let record = match record {
+        Some(record) => record,
+        /* already available; just keep an eye on it */
+        None => return Duration::days(1),
+    };
+    if has_status(record, "pending delete") {
+        return Duration::minutes(5);
+    }
+    if has_status(record, "redemption period") {
+        return Duration::hours(1);
+    }
+
+    let deletion = match record.expires_at() {
+        /* it may still be renewed in time, so check on it around its expiration */
+        Some(expires) if expires > now => expires,
+        Some(expires) => expires + Duration::days(DAYS_TO_DELETION),
+        None => return Duration::days(1),
+    };
+    ((deletion - now) / 4).clamp(Duration::minutes(5), Duration::days(1))
+}
+
+/// Watches a list of domains, checking each when it's due.
+pub struct Watcher {
+    endpoints: Endpoints,
+    domains: Vec<WatchedDomain>,
+}
+
+impl Watcher {
+    /// Watch the given domains, all of them due for a check now.
+    pub fn new<I: IntoIterator<Item = String>>(endpoints: Endpoints, domains: I) -> Self {
+        let now = Utc::now();
+        let mut names = domains
+            .into_iter()
+            .map(|name| name.trim().trim_end_matches('.').to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        names.sort();
+        names.dedup();
+
+        Self {
+            endpoints,
+            domains: names
+                .into_iter()
+                .map(|name| WatchedDomain {
+                    name,
+                    buyable: None,
+                    expires_at: None,
+                    checked_at: None,
+                    next_check: now,
+                    error: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Watch the domains listed in a file, one per line. Empty lines and lines starting
+    /// with `#` are skipped.
+    ///
+    /// # Errors
+    /// Errors if the file could not be read.
+    pub fn from_file<P: AsRef<Path>>(endpoints: Endpoints, path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        Ok(Self::new(
+            endpoints,
+            text.lines()
+                .filter(|line| !line.trim_start().starts_with('#'))
+                .map(str::to_string),
+        ))
+    }
+
+    /// The domains being watched, and what's known about each.
+    pub fn domains(&self) -> &[WatchedDomain] {
+        &self.domains
+    }
+
+    /// When the next domain is due for a check.
+    pub fn next_check(&self) -> Option<DateTime<Utc>> {
+        self.domains.iter().map(|domain| domain.next_check).min()
+    }
+
+    /// Check every domain that's due at `now` (or hasn't been checked yet), and schedule its
+    /// next check.
+    ///
+    /// Returns a [`Notification`] for each domain that became buyable since its last check
+    /// (or is buyable at its first). A failed check is kept in [`WatchedDomain::error`] and
+    /// retried after a while.
+    pub async fn check_due(
+        &mut self,
+        client: &mut Client<false>,
+        now: DateTime<Utc>,
+    ) -> Vec<Notification> {
+        let mut notifications = Vec::new();
+        let mut first = true;
+
+        for domain in self
+            .domains
+            .iter_mut()
+            .filter(|d| d.checked_at.is_none() || d.next_check <= now)
+        {
+            if !first {
+                /* be nice! */
+                tokio::time::sleep(std::time::Duration::from_millis(600)).await;
+            }
+            first = false;
+
+            match DomainRecord::get_with(&self.endpoints, client, &domain.name, Detail::Minimal)
+                .await
+            {
+                Ok(record) => {
+                    let buyable = is_buyable(record.as_ref(), now);
+                    if buyable && domain.buyable != Some(true) {
+                        notifications.push(Notification {
+                            item: format!("domain:{}", domain.name),
+                            at: now,
+                            message: format!("{} can be registered", domain.name),
+                        });
+                    }
+
+                    domain.buyable = Some(buyable);
+                    domain.expires_at = record.as_ref().and_then(DomainRecord::expires_at);
+                    domain.next_check = now + poll_interval(record.as_ref(), now);
+                    domain.error = None;
+                }
+                Err(e) => {
+                    domain.next_check = now + Duration::minutes(15);
+                    domain.error = Some(format!("{:#}", e));
+                }
+            }
+            domain.checked_at = Some(now);
+        }
+
+        notifications
+    }
+
+    /// Check domains as they come due, sending notifications to every notifier, until `until`
+    /// completes (e.g. `tokio::signal::ctrl_c()`).
+    ///
+    /// Notifications that can't be delivered are logged, and the watch carries on. If `until`
+    /// completes partway through checking domains (e.g. during the first pass over a long
+    /// list), the watch stops there, keeping what was found so far.
+    pub async fn run<F: Future>(
+        &mut self,
+        client: &mut Client<false>,
+        notifiers: &[Box<dyn Notifier>],
+        until: F,
+    ) {
+        tokio::pin!(until);
+        loop {
+            let notifications = tokio::select! {
+                notifications = self.check_due(client, Utc::now()) => notifications,
+                _ = &mut until => return,
+            };
+            for notification in notifications {
+                if let Err(e) = notify::dispatch(notifiers, &notification).await {
+                    tracing::warn!(
+                        "could not send notification for {}: {:#}",
+                        notification.item,
+                        e
+                    );
+                }
+            }
+
+            let wait = match self.next_check() {
+                Some(next) => (next - Utc::now()).to_std().unwrap_or_default(),
+                None => return,
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = &mut until => return,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone, Utc};
+
+    use super::{WatchedDomain, Watcher};
+    use crate::{common::Client, modules::rdap::Endpoints, testing::MockServer};
+
+    fn domain(watcher: &Watcher, name: &str) -> WatchedDomain {
+        watcher
+            .domains()
+            .iter()
+            .find(|domain| domain.name == name)
+            .unwrap()
+            .clone()
+    }
+
+    fn record(expiration: &str, status: &str) -> String {
+        format!(
+            r#"{{
+                "ldhName": "x.com",
+                "status": ["{}"],
+                "events": [
+                    {{"eventAction": "registration", "eventDate": "2010-01-01T00:00:00Z"}},
+                    {{"eventAction": "expiration", "eventDate": "{}"}}
+                ]
+            }}"#,
+            status, expiration
+        )
+    }
+
+    #[tokio::test]
+    async fn test_watcher() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/domain/pending.com",
+                200,
+                &record("2021-09-01T00:00:00Z", "pending delete"),
+            )
+            .mock(
+                "/domain/renewed.com",
+                200,
+                &record("2031-01-01T00:00:00Z", "active"),
+            )
+            .mock(
+                "/domain/expired.com",
+                200,
+                &record("2021-11-01T00:00:00Z", "auto renew period"),
+            )
+            .mock("/domain/free.com", 404, "");
+
+        let mut watcher = Watcher::new(
+            Endpoints { base: server.uri() },
+            vec![
+                "Pending.com".to_string(),
+                "renewed.com".to_string(),
+                "expired.com.".to_string(),
+                "free.com".to_string(),
+                "free.com".to_string(),
+                "".to_string(),
+            ],
+        );
+        assert_eq!(watcher.domains().len(), 4);
+
+        let mut client = Client::default();
+        let now = Utc.with_ymd_and_hms(2021, 11, 20, 0, 0, 0).unwrap();
+        let notifications = watcher.check_due(&mut client, now).await;
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].item, "domain:free.com");
+
+        assert_eq!(domain(&watcher, "pending.com").buyable, Some(false));
+        assert_eq!(
+            domain(&watcher, "pending.com").next_check,
+            now + Duration::minutes(5)
+        );
+        assert_eq!(
+            domain(&watcher, "renewed.com").next_check,
+            now + Duration::days(1)
+        );
+        /* 61 days to its estimated deletion */
+        assert_eq!(
+            domain(&watcher, "expired.com").next_check,
+            now + Duration::days(1)
+        );
+        assert_eq!(
+            domain(&watcher, "expired.com").expires_at,
+            Some(Utc.with_ymd_and_hms(2021, 11, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(watcher.next_check(), Some(now + Duration::minutes(5)));
+
+        /* only pending.com is due, and it has dropped */
+        server.mock("/domain/pending.com", 404, "");
+        let later = now + Duration::minutes(5);
+        let notifications = watcher.check_due(&mut client, later).await;
+        assert_eq!(notifications.len(), 1);
+        assert_eq!(notifications[0].item, "domain:pending.com");
+        assert_eq!(domain(&watcher, "pending.com").buyable, Some(true));
+        assert_eq!(domain(&watcher, "renewed.com").checked_at, Some(now));
+
+        /* an error is retried, and doesn't notify again */
+        server.mock("/domain/pending.com", 500, "");
+        let later = later + Duration::days(1);
+        assert!(watcher.check_due(&mut client, later).await.is_empty());
+        assert!(domain(&watcher, "pending.com").error.is_some());
+        assert_eq!(
+            domain(&watcher, "pending.com").next_check,
+            later + Duration::minutes(15)
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::Serialize;
+
+use crate::{
+    common::{Client, Detail, Money},
+    modules::{ebay, openlibrary},
+    schemas::product::Product,
+};
+
+/// A module that can look up products by their GTIN (UPC, EAN or ISBN).
+#[async_trait]
+pub trait RetailSource: Send + Sync {
+    /// The name of the source, matching [`Product::source`].
+    fn name(&self) -> &'static str;
+
+    /// Find products with the given GTIN.
+    ///
+    /// # Errors
+    /// Errors if the source could not be searched.
+    async fn find_by_gtin(&self, gtin: &str) -> anyhow::Result<Vec<Product>>;
+}
+
+/// Looks up other eBay listings of the same product.
+pub struct EbaySource {
+    /// How many search results to check. Each result costs one request.
+    pub limit: usize,
+}
+
+impl Default for EbaySource {
+    fn default() -> Self {
+        Self { limit: 10 }
+    }
+}
+
+#[async_trait]
+impl RetailSource for EbaySource {
+    fn name(&self) -> &'static str {
+        "ebay"
+    }
+
+    async fn find_by_gtin(&self, gtin: &str) -> anyhow::Result<Vec<Product>> {
+        Ok(ebay::Product::search(gtin, Detail::Default)
+            .filter_map(|r| futures::future::ready(r.ok()))
+            .take(self.limit)
+            .filter(|p| futures::future::ready(p.gtin() == Some(gtin)))
+            .map(Product::from)
+            .collect()
+            .await)
+    }
+}
+
+/// The same product, as offered by several sources.
+#[derive(Serialize)]
+pub struct ProductComparison {
+    pub gtin: String,
+    /// The product the comparison was made for.
+    pub original: Product,
+    /// Other offers of the same product, from every source that was asked.
+    pub offers: Vec<Product>,
+}
+
+impl ProductComparison {
+    /// Look up the GTIN of an eBay product on every source.
+    ///
+    /// # Errors
+    /// Errors if the product has no GTIN in its item specifics, or if one of the sources failed.
+    pub async fn for_ebay_product(
+        product: ebay::Product,
+        sources: &[Box<dyn RetailSource>],
+    ) -> anyhow::Result<Self> {
+        let gtin = product
+            .gtin()
+            .context("product has no UPC/EAN in its item specifics")?
+            .to_string();
+        let original = Product::from(product);
+
+        let mut offers = Vec::new();
+        for source in sources {
+            let found = source
+                .find_by_gtin(&gtin)
+                .await
+                .with_context(|| format!("could not search {}", source.name()))?;
+            offers.extend(found.into_iter().filter(|p| p.url != original.url));
+        }
+
+        Ok(Self {
+            gtin,
+            original,
+            offers,
+        })
+    }
+
+    /// The cheapest offer in the same currency as the original product, if any.
+    pub fn cheapest(&self) -> Option<&Product> {
+        let currency = self.original.price.as_ref().map(Money::currency);
+        self.offers
+            .iter()
+            .filter(|p| p.price.is_some() && p.price.as_ref().map(Money::currency) == currency)
+            .min_by(|a, b| {
+                a.price
+                    .partial_cmp(&b.price)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+    }
+}
+
+/// An eBay listing of a book, with the book's details from Open Library.
+#[derive(Serialize)]
+pub struct BookListing {
+    pub listing: Product,
+    pub book: openlibrary::Book,
+}
+
+impl BookListing {
+    /// Look up the ISBN of an eBay product on Open Library.
+    ///
+    /// # Errors
+    /// Errors if the product has no ISBN in its item specifics, if the lookup failed, or if
+    /// Open Library doesn't know the book.
+    pub async fn for_ebay_product(
+        product: ebay::Product,
+        client: &mut Client<false>,
+    ) -> anyhow::Result<Self> {
+        let isbn = product
+            .isbn()
+            .context("product has no ISBN in its item specifics")?;
+        let book = openlibrary::Book::by_isbn(client, &isbn)
+            .await?
+            .with_context(|| format!("Open Library has no book with ISBN {}", isbn))?;
+
+        Ok(Self {
+            listing: Product::from(product),
+            book,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::{ProductComparison, RetailSource};
+    use crate::{
+        common::Money,
+        modules::{bestbuy, ebay},
+        schemas::product::Product,
+    };
+
+    /// A source with a fixed list of products, found by their GTIN.
+    struct Fixed(&'static str, Vec<Product>);
+
+    #[async_trait]
+    impl RetailSource for Fixed {
+        fn name(&self) -> &'static str {
+            self.0
+        }
+
+        async fn find_by_gtin(&self, gtin: &str) -> anyhow::Result<Vec<Product>> {
+            Ok(self
+                .1
+                .iter()
+                .filter(|p| p.gtin.as_deref() == Some(gtin))
+                .cloned()
+                .collect())
+        }
+    }
+
+    fn ebay_product() -> ebay::Product {
+        let mut product = ebay::Product {
+            id: 254625474154,
+            name: "The Rust Programming Language".to_string(),
+            price: Some(Money::from(31.42)),
+            ..Default::default()
+        };
+        product
+            .item_specifics
+            .insert("UPC".to_string(), "9781718500440".to_string());
+        product
+    }
+
+    fn bestbuy_product(sku: u64, sale_price: f64, upc: &str) -> Product {
+        let product: bestbuy::Product = serde_json::from_value(serde_json::json!({
+            "sku": sku,
+            "name": "The Rust Programming Language - Steve Klabnik",
+            "salePrice": sale_price,
+            "regularPrice": 39.99,
+            "url": format!("https://www.bestbuy.com/site/{}.p", sku),
+            "upc": upc,
+            "image": format!("https://pisces.bbystatic.com/{}.jpg", sku),
+        }))
+        .unwrap();
+        Product::from(product)
+    }
+
+    #[tokio::test]
+    async fn test_comparison() {
+        let sources: Vec<Box<dyn RetailSource>> = vec![
+            /* the listing itself turns up in its own search */
+            Box::new(Fixed("ebay", vec![Product::from(ebay_product())])),
+            Box::new(Fixed(
+                "bestbuy",
+                vec![
+                    bestbuy_product(6409387, 34.99, "9781718500440"),
+                    bestbuy_product(6409388, 29.99, "9781718500440"),
+                    bestbuy_product(6409389, 9.99, "0000000000000"),
+                ],
+            )),
+        ];
+        let comparison = ProductComparison::for_ebay_product(ebay_product(), &sources)
+            .await
+            .unwrap();
+
+        assert_eq!(comparison.gtin, "9781718500440");
+        assert_eq!(comparison.original.source, "ebay");
+        assert_eq!(
+            comparison.original.url.as_deref(),
+            Some("https://www.ebay.com/itm/254625474154")
+        );
+        assert_eq!(comparison.offers.len(), 2);
+
+        /* the Best Buy offers are in the same shape, with the sale price as the price */
+        let offer = &comparison.offers[0];
+        assert_eq!(offer.source, "bestbuy");
+        assert_eq!(offer.name, "The Rust Programming Language - Steve Klabnik");
+        assert_eq!(offer.price, Some(Money::from(34.99)));
+        assert_eq!(
+            offer.url.as_deref(),
+            Some("https://www.bestbuy.com/site/6409387.p")
+        );
+        assert_eq!(offer.gtin.as_deref(), Some("9781718500440"));
+        assert_eq!(offer.images.len(), 1);
+
+        let cheapest = comparison.cheapest().unwrap();
+        assert_eq!(cheapest.price, Some(Money::from(29.99)));
+        assert!(cheapest.price < comparison.original.price);
+
+        /* a product without a GTIN can't be compared */
+        assert!(
+            ProductComparison::for_ebay_product(ebay::Product::default(), &sources)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Golden-file tests for the HTML parsers: saved pages, and the records the parsers read from
+//! them, so that a change to what a parser reads (or to a site's markup, once a page is saved
+//! again) shows up as a list of changed fields instead of a failing live request.
+//!
+//! Each fixture is a page at `fixtures/golden/<module>/<name>.html`, with the record read from
+//! it next to it in `<name>.json`. The name starts with the page's ID, if the parser needs one
+//! (e.g. `254625474154` or `254625474154-auction`). Pages are [sanitized](sanitize) before
+//! they're saved, and parsed as if it were [`now`].
+//!
+//! To save a new page, or to accept a change to the records, use the `refresh-golden` binary:
+//! ```txt
+//! cargo run -p datacollect-core --features golden --bin refresh-golden                    # every record
+//! cargo run -p datacollect-core --features golden --bin refresh-golden ebay 254625474154  # one record
+//! cargo run -p datacollect-core --features golden --bin refresh-golden ebay 254625474154 https://www.ebay.com/itm/254625474154
+//! ```
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use chrono::{DateTime, TimeZone, Utc};
+use kuchiki::{traits::TendrilSink, NodeRef};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::{
+    common::Detail,
+    diff::{diff_values, FieldChange},
+    modules::{ebay, etsy, geekbench, techpowerup},
+};
+
+/// Where the fixtures are.
+pub const DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/golden");
+
+/// The modules with a parser to test, i.e. the directories in [`DIR`].
+pub const MODULES: &[&str] = &["ebay", "etsy", "geekbench", "techpowerup"];
+
+/// When pages are parsed as if it were, so that times left and dates without a year are read
+/// the same way every time.
+pub fn now() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+}
+
+/// Elements that no parser reads, and that only make pages bigger (or leak the session they
+/// were saved from).
+const UNREAD: &str = "script[src], style, link[rel='stylesheet'], link[rel='preload'], iframe, \
+                      noscript, svg, img[src^='data:']";
+
+/// A saved page, and the record read from it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Fixture {
+    pub module: String,
+    pub name: String,
+}
+
+impl Fixture {
+    pub fn new(module: &str, name: &str) -> Self {
+        Self {
+            module: module.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    pub fn html_path(&self) -> PathBuf {
+        Path::new(DIR)
+            .join(&self.module)
+            .join(format!("{}.html", self.name))
+    }
+
+    pub fn json_path(&self) -> PathBuf {
+        Path::new(DIR)
+            .join(&self.module)
+            .join(format!("{}.json", self.name))
+    }
+
+    /// Every saved page, by module and name.
+    ///
+    /// # Errors
+    /// Errors if [`DIR`] could not be read.
+    pub fn all() -> anyhow::Result<Vec<Self>> {
+        let mut fixtures = Vec::new();
+        for module in MODULES {
+            let dir = Path::new(DIR).join(module);
+            if !dir.exists() {
+                continue;
+            }
+            for entry in
+                fs::read_dir(&dir).with_context(|| format!("could not read {}", dir.display()))?
+            {
+                let path = entry?.path();
+                if path.extension().is_some_and(|ext| ext == "html") {
+                    if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                        fixtures.push(Self::new(module, name));
+                    }
+                }
+            }
+        }
+        fixtures.sort_by(|a, b| (&a.module, &a.name).cmp(&(&b.module, &b.name)));
+        Ok(fixtures)
+    }
+
+    /// The ID at the start of the name.
+    fn id(&self) -> anyhow::Result<u64> {
+        let id = self.name.split('-').next().unwrap_or_default();
+        id.parse()
+            .with_context(|| format!("fixture {} doesn't start with an ID", self))
+    }
+
+    /// Read the saved page with its module's parser, as JSON.
+    ///
+    /// # Errors
+    /// Errors if the page could not be read, or parsed.
+    pub fn parse(&self) -> anyhow::Result<Value> {
+        let html = fs::read_to_string(self.html_path())
+            .with_context(|| format!("could not read {}", self.html_path().display()))?;
+        fn json<T: Serialize>(record: T) -> anyhow::Result<Value> {
+            Ok(serde_json::to_value(record)?)
+        }
+
+        match self.module.as_str() {
+            "ebay" => json(ebay::Product::from_document_at(
+                &kuchiki::parse_html().one(html.as_str()),
+                self.id()?,
+                Detail::Default,
+                now(),
+            )?),
+            "etsy" => json(etsy::Listing::from_html(&html, self.id()?)?),
+            "geekbench" => json(geekbench::BenchmarkResult::from_html(&html, self.id()?)?),
+            "techpowerup" => json(techpowerup::CPUSpecs::from_html(&html)?),
+            module => bail!("there is no parser for {}", module),
+        }
+    }
+
+    /// How the record read from the page differs from the saved one.
+    ///
+    /// # Errors
+    /// Errors if the page could not be parsed, or the record could not be read.
+    pub fn check(&self) -> anyhow::Result<Vec<FieldChange>> {
+        let text = fs::read_to_string(self.json_path())
+            .with_context(|| format!("could not read {}", self.json_path().display()))?;
+        let saved: Value = serde_json::from_str(&text)
+            .with_context(|| format!("could not parse {}", self.json_path().display()))?;
+        /* written and read back like the saved one, since floats don't always read back the
+         * same (e.g. 0.9800000000000001 is read as 0.98) */
+        let parsed: Value = serde_json::from_str(&serde_json::to_string(&self.parse()?)?)?;
+        Ok(diff_values(&saved, &parsed))
+    }
+
+    /// Save the record read from the page, replacing the one saved before.
+    ///
+    /// # Errors
+    /// Errors if the page could not be parsed, or the record could not be written.
+    pub fn refresh(&self) -> anyhow::Result<()> {
+        let mut text = serde_json::to_string_pretty(&self.parse()?)?;
+        text.push('\n');
+        fs::write(self.json_path(), text)
+            .with_context(|| format!("could not write {}", self.json_path().display()))
+    }
+
+    /// Save a page (after [sanitizing](sanitize) it), replacing the one saved before. The
+    /// record isn't saved; see [`Fixture::refresh`].
+    ///
+    /// # Errors
+    /// Errors if the page could not be written.
+    pub fn save_page(&self, html: &str) -> anyhow::Result<()> {
+        let path = self.html_path();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, sanitize(html))
+            .with_context(|| format!("could not write {}", path.display()))
+    }
+}
+
+impl std::fmt::Display for Fixture {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.module, self.name)
+    }
+}
+
+/// Remove what no parser reads from a page: comments, styles, external scripts (inline ones
+/// are kept, since some have data in them), frames and inline images, and `style` and event
+/// handler attributes.
+pub fn sanitize(html: &str) -> String {
+    let document = kuchiki::parse_html().one(html);
+
+    let unread = document
+        .select(UNREAD)
+        .map(|elements| elements.collect::<Vec<_>>())
+        .unwrap_or_default();
+    for element in unread {
+        element.as_node().detach();
+    }
+    let comments = document
+        .descendants()
+        .filter(|node| node.as_comment().is_some())
+        .collect::<Vec<NodeRef>>();
+    for comment in comments {
+        comment.detach();
+    }
+    for node in document.descendants() {
+        if let Some(element) = node.as_element() {
+            element
+                .attributes
+                .borrow_mut()
+                .map
+                .retain(|name, _| name.local.as_ref() != "style" && !name.local.starts_with("on"));
+        }
+    }
+
+    document.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{sanitize, Fixture};
+
+    #[test]
+    fn test_golden() {
+        let mut failures = Vec::new();
+        for fixture in Fixture::all().unwrap() {
+            match fixture.check() {
+                Ok(changes) if changes.is_empty() => {}
+                Ok(changes) => failures.push(format!(
+                    "{}:\n    {}",
+                    fixture,
+                    changes
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join("\n    ")
+                )),
+                Err(e) => failures.push(format!("{}: {:#}", fixture, e)),
+            }
+        }
+        assert!(
+            failures.is_empty(),
+            "records differ from the saved ones (if that's intended, run \
+             `cargo run -p datacollect-core --bin refresh-golden`):\n{}",
+            failures.join("\n")
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let page = r#"<html><head>
+            <script src="https://example.com/tracker.js"></script>
+            <script type="application/ld+json">{"name": "Mug"}</script>
+            <style>h1 { color: red }</style>
+        </head><body>
+            <!-- session 1234 -->
+            <h1 style="font-weight: bold" onclick="track()" class="title">Mug</h1>
+            <iframe src="https://example.com/ad"></iframe>
+        </body></html>"#;
+        let sanitized = sanitize(page);
+        assert!(
+            sanitized.contains(r#"<script type="application/ld+json">{"name": "Mug"}</script>"#)
+        );
+        assert!(sanitized.contains(r#"<h1 class="title">Mug</h1>"#));
+        for removed in ["tracker.js", "color: red", "session", "track()", "iframe"] {
+            assert!(!sanitized.contains(removed), "{} wasn't removed", removed);
+        }
+    }
+}
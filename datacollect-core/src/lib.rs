@@ -1,10 +1,43 @@
 #![feature(try_blocks)]
-#![feature(result_into_ok_or_err)]
 
+//! Without the `net` feature (on by default), only the parsers are built: the modules'
+//! types and whatever reads them from a page, but nothing that fetches one.
+
+pub mod analysis;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "net")]
+pub mod checkpoint;
+#[cfg(feature = "net")]
+pub mod collector;
 pub mod common;
+pub mod diff;
+#[cfg(feature = "net")]
+pub mod dropcatch;
+#[cfg(feature = "net")]
+pub mod enrichment;
+#[cfg(any(test, feature = "golden"))]
+pub mod golden;
 pub mod modules;
+#[cfg(feature = "net")]
+pub mod notify;
 pub mod schema_org;
+pub mod schemas;
+pub mod sinks;
+#[cfg(feature = "net")]
+pub mod testing;
+#[cfg(feature = "net")]
+pub mod tracking;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
 pub use anyhow;
+#[cfg(feature = "arrow")]
+pub use arrow_array;
+#[cfg(feature = "arrow")]
+pub use arrow_schema;
 pub use chrono;
+#[cfg(feature = "net")]
+pub use collector::Datacollect;
 pub use futures::stream;
+pub use rust_decimal;
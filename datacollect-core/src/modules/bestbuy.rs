@@ -0,0 +1,349 @@
+//! Best Buy's [products API](https://bestbuyapis.github.io/api-documentation/#products-api).
+//!
+//! Unlike the other modules, this one talks to an official API, which needs an API key
+//! (free, from <https://developer.bestbuy.com>).
+
+#[cfg(feature = "net")]
+use std::time::Duration;
+
+use anyhow::Context;
+#[cfg(feature = "net")]
+use futures::Stream;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "net")]
+use crate::common::{
+    http::{Response, StatusError},
+    Client, Collected,
+};
+
+/// The API the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://api.bestbuy.com/v1";
+
+crate::common::endpoints::endpoints!();
+
+/// The product attributes to ask the API for; it returns a lot more than we need otherwise.
+#[cfg(feature = "net")]
+const ATTRIBUTES: &str = "sku,name,salePrice,regularPrice,onSale,url,upc,image,onlineAvailability,customerReviewAverage,customerReviewCount";
+
+/// How many products to ask for per search page (the API's maximum).
+#[cfg(feature = "net")]
+const PAGE_SIZE: u32 = 100;
+
+/// A Best Buy product, as returned by the products API.
+#[derive(Deserialize, Serialize, JsonSchema, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct Product {
+    pub sku: u64,
+    pub name: String,
+    /// The current price, in USD.
+    pub sale_price: Option<f64>,
+    /// The price when not on sale, in USD.
+    pub regular_price: Option<f64>,
+    #[serde(default)]
+    pub on_sale: bool,
+    /// A link to the product's page.
+    pub url: Option<String>,
+    pub upc: Option<String>,
+    /// A link to the product's main picture.
+    pub image: Option<String>,
+    /// Whether the product can be bought online right now.
+    pub online_availability: Option<bool>,
+    /// The average customer review, out of 5 stars.
+    pub customer_review_average: Option<f64>,
+    pub customer_review_count: Option<u64>,
+}
+
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchPage {
+    total_pages: u32,
+    products: Vec<Product>,
+}
+
+/// Fail on error statuses, without the URL in the message (it contains the API key).
+///
+/// For the same reason, responses are parsed with [`serde_json`] directly rather than
+/// [`Response::json`], whose errors include the URL.
+#[cfg(feature = "net")]
+fn check_status(response: Response) -> anyhow::Result<Response> {
+    let error = || {
+        anyhow::Error::new(StatusError {
+            url: None,
+            status: response.status(),
+        })
+    };
+    match response.status().as_u16() {
+        401 | 403 => Err(error().context("Best Buy rejected the API key")),
+        429 => Err(error().context("Best Buy API rate limit exceeded")),
+        400..=599 => Err(error().context("Best Buy API returned an error")),
+        _ => Ok(response),
+    }
+}
+
+impl Product {
+    /// Find a Best Buy product using its SKU.
+    ///
+    /// # Errors
+    /// Errors if the request failed, if the API key was rejected, or if the response
+    /// could not be parsed.
+    /// # Returns
+    /// If there is no product with that SKU, `Ok(None)` is returned.
+    #[cfg(feature = "net")]
+    pub async fn by_sku(
+        client: &mut Client<false>,
+        key: &str,
+        sku: u64,
+    ) -> anyhow::Result<Option<Self>> {
+        Self::by_sku_with(&Endpoints::default(), client, key, sku).await
+    }
+
+    /// Like [`Product::by_sku`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, if the API key was rejected, or if the response
+    /// could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client, key), err)]
+    pub async fn by_sku_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        key: &str,
+        sku: u64,
+    ) -> anyhow::Result<Option<Self>> {
+        Ok(Self::by_sku_enveloped_with(endpoints, client, key, sku)
+            .await?
+            .map(|collected| collected.data))
+    }
+
+    /// Like [`Product::by_sku`], recording where and when the product was collected. The
+    /// URL is the API's, without the key.
+    ///
+    /// # Errors
+    /// Errors if the request failed, if the API key was rejected, or if the response
+    /// could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_sku_enveloped(
+        client: &mut Client<false>,
+        key: &str,
+        sku: u64,
+    ) -> anyhow::Result<Option<Collected<Self>>> {
+        Self::by_sku_enveloped_with(&Endpoints::default(), client, key, sku).await
+    }
+
+    /// Like [`Product::by_sku_enveloped`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, if the API key was rejected, or if the response
+    /// could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_sku_enveloped_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        key: &str,
+        sku: u64,
+    ) -> anyhow::Result<Option<Collected<Self>>> {
+        let url = endpoints.url(&format!("/products/{}.json", sku));
+        let response = client
+            .get(&url)
+            .await?
+            .query(&[("apiKey", key), ("show", ATTRIBUTES)])
+            .send()
+            .await?;
+        if response.status() == 404 {
+            return Ok(None);
+        }
+        let response = check_status(response)?;
+        let product = Self::from_json(&String::from_utf8_lossy(response.bytes()))?;
+        Ok(Some(Collected::from_response(
+            "bestbuy",
+            &response,
+            Some(url),
+            product,
+        )))
+    }
+
+    /// Parse a product as the products API returns it.
+    ///
+    /// # Errors
+    /// Errors if `json` is not a product.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("could not parse Best Buy product")
+    }
+
+    /// Search Best Buy's products for all of the words in `query`.
+    ///
+    /// This endpoint will wait a few hundred milliseconds between pages,
+    /// to stay under the API's rate limit.
+    ///
+    /// # Returns
+    /// Returns a [`Stream`] of [`anyhow::Result<Self>`].
+    /// The stream ends after the last page of results, or after the first page that fails.
+    #[cfg(feature = "net")]
+    pub fn search<'a>(
+        key: &'a str,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_with(&Endpoints::default(), key, query)
+    }
+
+    /// Like [`Product::search`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn search_with<'a>(
+        endpoints: &Endpoints,
+        key: &'a str,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_using(Client::default(), endpoints, key, query)
+    }
+
+    /// Like [`Product::search_with`], sending every request through `client`.
+    #[cfg(feature = "net")]
+    pub(crate) fn search_using<'a>(
+        client: Client<false>,
+        endpoints: &Endpoints,
+        key: &'a str,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        /* e.g. `/products((search=ryzen&search=5600x))`; the API has no escaping, so drop
+         * anything that would be read as part of the query syntax */
+        let terms = query
+            .split_whitespace()
+            .map(|word| {
+                word.chars()
+                    .filter(|c| c.is_alphanumeric() || "-_.".contains(*c))
+                    .collect::<String>()
+            })
+            .filter(|word| !word.is_empty())
+            .map(|word| format!("search={}", word))
+            .collect::<Vec<_>>()
+            .join("&");
+        let url = endpoints.url(&format!("/products(({}))", terms));
+
+        let pages = futures::stream::unfold(Some(1), move |page| {
+            let (url, client) = (url.clone(), client.clone());
+            async move {
+                let page = page?;
+                if page > 1 {
+                    /* be nice! */
+                    tokio::time::sleep(Duration::from_millis(600)).await;
+                }
+
+                let result: anyhow::Result<SearchPage> = try {
+                    let response = client
+                        .get(&url)
+                        .await?
+                        .query(&[
+                            ("apiKey", key),
+                            ("show", ATTRIBUTES),
+                            ("format", "json"),
+                            ("pageSize", PAGE_SIZE.to_string().as_str()),
+                            ("page", page.to_string().as_str()),
+                        ])
+                        .send()
+                        .await?;
+                    serde_json::from_slice(check_status(response)?.bytes())
+                        .context("could not parse Best Buy search results")?
+                };
+
+                Some(match result {
+                    Ok(SearchPage {
+                        total_pages,
+                        products,
+                    }) => (
+                        products.into_iter().map(Ok).collect::<Vec<_>>(),
+                        (page < total_pages).then_some(page + 1),
+                    ),
+                    Err(e) => (vec![Err(e)], None),
+                })
+            }
+        });
+
+        futures::StreamExt::flat_map(pages, futures::stream::iter)
+    }
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use futures::StreamExt;
+
+    use super::{Endpoints, Product};
+    use crate::{common::Client, testing::MockServer};
+
+    const PRODUCT: &str = r#"{
+        "sku": 6438942,
+        "name": "AMD - Ryzen 5 5600X 4th Gen 6-core, 12-threads Unlocked Desktop Processor with Wraith Stealth Cooler",
+        "salePrice": 159.99,
+        "regularPrice": 199.99,
+        "onSale": true,
+        "url": "https://api.bestbuy.com/click/-/6438942/pdp",
+        "upc": "730143312042",
+        "image": "https://pisces.bbystatic.com/image2/BestBuy_US/images/products/6438/6438942_sd.jpg",
+        "onlineAvailability": true,
+        "customerReviewAverage": 4.8,
+        "customerReviewCount": 3417
+    }"#;
+
+    #[tokio::test]
+    async fn test_by_sku() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/v1/products/6438942.json", 200, PRODUCT)
+            .mock("/v1/products/1.json", 404, r#"{"error": {"code": 404}}"#)
+            .mock("/v1/products/2.json", 403, "<h1>403 Forbidden</h1>");
+
+        let endpoints = Endpoints {
+            base: format!("{}/v1", server.uri()),
+        };
+        let mut client = Client::default();
+        let product = Product::by_sku_with(&endpoints, &mut client, "secret", 6438942)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(product.sale_price, Some(159.99));
+        assert_eq!(product.upc.as_deref(), Some("730143312042"));
+
+        assert!(Product::by_sku_with(&endpoints, &mut client, "secret", 1)
+            .await
+            .unwrap()
+            .is_none());
+
+        let error = Product::by_sku_with(&endpoints, &mut client, "secret", 2)
+            .await
+            .unwrap_err();
+        assert!(!format!("{:#}", error).contains("secret"));
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await.unwrap();
+        let page = |n: u32| {
+            format!(
+                r#"{{"currentPage": {}, "totalPages": 2, "products": [{}]}}"#,
+                n, PRODUCT
+            )
+        };
+        server
+            .mock("/v1/products((search=ryzen&search=5600x))", 200, &page(1))
+            .mock(
+                &format!(
+                    "/v1/products((search=ryzen&search=5600x))?apiKey=secret&show={}&format=json&pageSize=100&page=2",
+                    super::ATTRIBUTES.replace(',', "%2C")
+                ),
+                200,
+                &page(2),
+            );
+
+        let endpoints = Endpoints {
+            base: format!("{}/v1", server.uri()),
+        };
+        let products = Product::search_with(&endpoints, "secret", "ryzen 5600x &")
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(products.len(), 2);
+        assert!(products.iter().all(|p| p.is_ok()));
+        assert_eq!(server.requests().len(), 2);
+    }
+}
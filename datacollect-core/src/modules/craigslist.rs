@@ -0,0 +1,282 @@
+use std::str::FromStr;
+#[cfg(feature = "net")]
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+#[cfg(feature = "net")]
+use futures::Stream;
+use kuchiki::{parse_html, traits::TendrilSink};
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[cfg(feature = "net")]
+use crate::common::Client;
+use crate::common::{extract::Field, Money};
+
+pub use crate::common::Endpoints;
+
+/// The site for a region, as in `https://<region>.craigslist.org`.
+pub fn for_region(region: &str) -> Endpoints {
+    Endpoints::new(format!("https://{}.craigslist.org", region))
+}
+
+/// A single listing from a Craigslist search.
+#[derive(Serialize, JsonSchema, Clone)]
+pub struct Listing {
+    /// The posting ID.
+    pub id: u64,
+    pub title: String,
+    /// A link to the posting.
+    pub url: String,
+    pub price: Option<Money>,
+    /// The neighbourhood the seller gave, e.g. `oakland rockridge`.
+    pub location: Option<String>,
+    /// When the listing was posted, in the region's local time.
+    pub posted_at: Option<NaiveDateTime>,
+    /// Links to the listing's pictures.
+    pub images: Vec<String>,
+}
+
+/// How many results Craigslist shows per search page.
+#[cfg(feature = "net")]
+const PAGE_SIZE: u32 = 120;
+
+impl Listing {
+    /// Parse one page of search results, returning the listings and the total number of results
+    /// (if the page says).
+    pub fn from_search_html(html: &str) -> (Vec<Self>, Option<u32>) {
+        lazy_static! {
+            static ref RE_ID: regex::Regex = regex::Regex::new(r"/([0-9]+)\.html").unwrap();
+        }
+
+        let document = &parse_html().one(html);
+        let total = Field::new(".totalcount").parse(document).ok();
+
+        /* the classic layout, and the static one served to clients without javascript */
+        let listings = document
+            .select(".result-row, .cl-static-search-result")
+            .into_iter()
+            .flatten()
+            .filter_map(|row| {
+                let row = row.as_node();
+                let link = row
+                    .select_first("a.result-title")
+                    .or_else(|_| row.select_first("a[href]"))
+                    .ok()?;
+                let url = link.attributes.borrow().get("href")?.to_string();
+                let id = RE_ID.captures(&url)?.get(1)?.as_str().parse().ok()?;
+                let title = Field::new(".title")
+                    .get(row)
+                    .or_else(|| Some(link.text_contents().trim().to_string()))
+                    .filter(|t| !t.is_empty())?;
+
+                let price = Field::new(".result-price")
+                    .or(".price")
+                    .get(row)
+                    .and_then(|p| Money::from_str(&p).ok());
+                let location = Field::new(".result-hood")
+                    .or(".location")
+                    .get(row)
+                    .map(|l| {
+                        l.trim_start_matches('(')
+                            .trim_end_matches(')')
+                            .trim()
+                            .to_string()
+                    });
+                let posted_at = row.select_first("time[datetime]").ok().and_then(|time| {
+                    let attributes = time.attributes.borrow();
+                    NaiveDateTime::parse_from_str(attributes.get("datetime")?, "%Y-%m-%d %H:%M")
+                        .ok()
+                });
+                /* e.g. data-ids="3:00K0K_2dYyVmxHLr5z_0CI0t2,3:00p0p_..." */
+                let images = row
+                    .select_first("[data-ids]")
+                    .ok()
+                    .and_then(|a| Some(a.attributes.borrow().get("data-ids")?.to_string()))
+                    .map(|ids| {
+                        ids.split(',')
+                            .filter_map(|id| id.split(':').nth(1))
+                            .map(|id| format!("https://images.craigslist.org/{}_600x450.jpg", id))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+
+                Some(Self {
+                    id,
+                    title,
+                    url,
+                    price,
+                    location,
+                    posted_at,
+                    images,
+                })
+            })
+            .collect();
+
+        (listings, total)
+    }
+
+    /// Search all of a region's for-sale listings, e.g. `Listing::search("sfbay", "ryzen")`.
+    ///
+    /// This endpoint will wait a few hundred milliseconds between pages
+    /// to avoid being IP banned.
+    ///
+    /// # Returns
+    /// Returns a [`Stream`] of [`anyhow::Result<Self>`], newest first.
+    /// The stream ends after the last page of results, or after the first page that fails.
+    #[cfg(feature = "net")]
+    pub fn search<'a>(
+        region: &str,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_with(&for_region(region), query)
+    }
+
+    /// Like [`Listing::search`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn search_with<'a>(
+        endpoints: &Endpoints,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_using(Client::default(), endpoints, query)
+    }
+
+    /// Like [`Listing::search_with`], sending every request through `client`.
+    #[cfg(feature = "net")]
+    pub(crate) fn search_using<'a>(
+        client: Client<false>,
+        endpoints: &Endpoints,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        let endpoints = endpoints.clone();
+
+        let pages = futures::stream::unfold(Some(0), move |offset| {
+            let endpoints = endpoints.clone();
+            let client = client.clone();
+            async move {
+                let offset = offset?;
+                if offset > 0 {
+                    /* be nice! */
+                    tokio::time::sleep(Duration::from_millis(600)).await;
+                }
+
+                let page: anyhow::Result<_> = try {
+                    let text = client
+                        .get(&endpoints.url("/search/sss"))
+                        .await?
+                        .query(&[("query", query), ("s", offset.to_string().as_str())])
+                        .send()
+                        .await?
+                        .text()
+                        .await?;
+                    Self::from_search_html(&text)
+                };
+
+                Some(match page {
+                    Ok((listings, total)) => {
+                        let next = offset + PAGE_SIZE;
+                        let more = !listings.is_empty() && total.is_some_and(|t| next < t);
+                        (
+                            listings.into_iter().map(Ok).collect::<Vec<_>>(),
+                            more.then_some(next),
+                        )
+                    }
+                    Err(e) => (vec![Err(e)], None),
+                })
+            }
+        });
+
+        futures::StreamExt::flat_map(pages, futures::stream::iter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    #[cfg(feature = "net")]
+    use futures::StreamExt;
+
+    #[cfg(feature = "net")]
+    use super::Endpoints;
+    use super::Listing;
+    use crate::common::Money;
+    #[cfg(feature = "net")]
+    use crate::testing::MockServer;
+
+    const PAGE: &str = r#"
+        <span class="totalcount">2</span>
+        <ul class="rows">
+            <li class="result-row" data-pid="7421234567">
+                <a href="https://sfbay.craigslist.org/eby/sop/d/oakland-amd-ryzen-2600/7421234567.html"
+                   class="result-image gallery" data-ids="3:00K0K_2dYyVmxHLr5z,3:00p0p_hZ9sPj0Ofbz"></a>
+                <div class="result-info">
+                    <time class="result-date" datetime="2021-12-01 10:22" title="Wed 01 Dec 10:22:31 AM">Dec  1</time>
+                    <h3 class="result-heading">
+                        <a href="https://sfbay.craigslist.org/eby/sop/d/oakland-amd-ryzen-2600/7421234567.html"
+                           class="result-title hdrlnk" id="postid_7421234567">AMD Ryzen 5 2600 CPU</a>
+                    </h3>
+                    <span class="result-meta">
+                        <span class="result-price">$90</span>
+                        <span class="result-hood"> (oakland rockridge)</span>
+                    </span>
+                </div>
+            </li>
+            <li class="cl-static-search-result" title="Intel i7-8700K">
+                <a href="https://sfbay.craigslist.org/sfc/sys/d/san-francisco-intel-i7/7421230000.html">
+                    <div class="title">Intel i7-8700K</div>
+                    <div class="details">
+                        <div class="price">$150</div>
+                        <div class="location">SOMA / south beach</div>
+                    </div>
+                </a>
+            </li>
+        </ul>
+    "#;
+
+    #[test]
+    fn test_search_page() {
+        let (listings, total) = Listing::from_search_html(PAGE);
+        assert_eq!(total, Some(2));
+        assert_eq!(listings.len(), 2);
+
+        let ryzen = &listings[0];
+        assert_eq!(ryzen.id, 7421234567);
+        assert_eq!(ryzen.title, "AMD Ryzen 5 2600 CPU");
+        assert_eq!(ryzen.price, Some(Money::from(90.0)));
+        assert_eq!(ryzen.location.as_deref(), Some("oakland rockridge"));
+        assert_eq!(
+            ryzen.posted_at,
+            Some(
+                NaiveDate::from_ymd_opt(2021, 12, 1)
+                    .unwrap()
+                    .and_hms_opt(10, 22, 0)
+                    .unwrap()
+            )
+        );
+        assert_eq!(
+            ryzen.images[0],
+            "https://images.craigslist.org/00K0K_2dYyVmxHLr5z_600x450.jpg"
+        );
+
+        let intel = &listings[1];
+        assert_eq!(intel.id, 7421230000);
+        assert_eq!(intel.location.as_deref(), Some("SOMA / south beach"));
+        assert_eq!(intel.posted_at, None);
+        assert!(intel.images.is_empty());
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await.unwrap();
+        server.mock("/search/sss", 200, PAGE);
+
+        let endpoints = Endpoints { base: server.uri() };
+        let listings = Listing::search_with(&endpoints, "cpu")
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(listings.len(), 2);
+        assert_eq!(server.requests(), vec!["/search/sss?query=cpu&s=0"]);
+    }
+}
@@ -1,64 +1,941 @@
-use std::{convert::TryInto, sync::Arc, time::Duration};
+use std::{collections::HashMap, convert::TryInto, str::FromStr};
+#[cfg(feature = "net")]
+use std::{
+    collections::{HashSet, VecDeque},
+    time::Duration,
+};
 
-use anyhow::{bail, Context};
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+#[cfg(feature = "net")]
 use futures::{Stream, StreamExt};
-use kuchiki::{parse_html, traits::TendrilSink};
+use kuchiki::{iter::NodeIterator, traits::TendrilSink, NodeRef};
 use lazy_static::lazy_static;
-use serde::Serialize;
-use tokio::sync::Mutex;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
+#[cfg(feature = "net")]
+use crate::{
+    checkpoint::SearchCheckpoint,
+    common::{
+        auth::{Field as LoginField, LoginFlow},
+        Client, Collected, RequestOptions,
+    },
+};
 use crate::{
-    common::{has_hidden_word, Client, Money},
+    common::{
+        dates::{self, DateOrder},
+        extract::Field,
+        has_hidden_word,
+        meta::canonical_link,
+        Currency, Detail, FieldIssue, Money, MoneyRange, ParseError, WithReport,
+    },
+    modules::openlibrary,
     schema_org::Scope,
+    schemas::common::Rating,
+};
+
+/// The eBay site the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://www.ebay.com";
+
+crate::common::endpoints::endpoints! {
+    /// Where [`PurchaseHistory`] signs in.
+    signin = "https://signin.ebay.com",
+}
+
+/// Settings for [`Product::search_with_options`].
+#[derive(Clone, Debug)]
+pub struct SearchOptions {
+    /// Also yield products that were already found on an earlier results page. eBay repeats
+    /// sponsored listings from page to page, so these are left out by default.
+    pub include_duplicates: bool,
+    /// How many item IDs to remember for leaving out duplicates. Past this, the oldest are
+    /// forgotten, so that a very long search doesn't keep growing.
+    pub max_seen: usize,
+    /// Leave out sponsored listings, without visiting them (which saves a request each).
+    pub skip_sponsored: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            include_duplicates: false,
+            max_seen: 10_000,
+            skip_sponsored: false,
+        }
+    }
+}
+
+/// The item IDs a search has found, forgetting the oldest past a limit.
+#[cfg(feature = "net")]
+struct SeenIds {
+    ids: HashSet<u64>,
+    order: VecDeque<u64>,
+    max: usize,
+}
+
+#[cfg(feature = "net")]
+impl SeenIds {
+    fn new(max: usize) -> Self {
+        Self {
+            ids: HashSet::new(),
+            order: VecDeque::new(),
+            max,
+        }
+    }
+
+    /// Remember `id`, returning whether it's new.
+    fn insert(&mut self, id: u64) -> bool {
+        if !self.ids.insert(id) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > self.max {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/* search pages are paged through one after another, so one that hangs holds up the rest */
+#[cfg(feature = "net")]
+const SEARCH_OPTIONS: RequestOptions = RequestOptions {
+    read_timeout: Some(Duration::from_secs(15)),
+    total_timeout: Some(Duration::from_secs(45)),
 };
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
 pub struct Seller {
     pub name: String,
-    pub feedback: Option<f64>,
+    /// The seller's positive feedback percentage, as a [`Rating`].
+    pub feedback: Option<Rating>,
+    /// How many items the seller has sold.
+    /// Only filled in with [`Detail::Full`], from the seller's profile page.
+    pub items_sold: Option<u64>,
+    /// When the seller joined eBay, as written on their profile (e.g. `Jun 2009`).
+    /// Only filled in with [`Detail::Full`], from the seller's profile page.
+    pub member_since: Option<String>,
+}
+
+impl Seller {
+    /// Fill in the details that are only on the seller's profile page.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the profile page has an error status.
+    #[cfg(feature = "net")]
+    pub async fn fetch_profile(&mut self, client: &mut Client<false>) -> anyhow::Result<()> {
+        self.fetch_profile_with(&Endpoints::default(), client).await
+    }
+
+    /// Like [`Seller::fetch_profile`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(self, client), fields(seller = %self.name), err)]
+    pub async fn fetch_profile_with(
+        &mut self,
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+    ) -> anyhow::Result<()> {
+        let text = client
+            .get(&endpoints.url(&format!("/usr/{}", self.name)))
+            .await?
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        self.parse_profile(&text);
+        Ok(())
+    }
+
+    /// Fill in the details from the seller's profile page, as
+    /// [`fetch_profile`](Seller::fetch_profile) does.
+    pub fn parse_profile(&mut self, text: &str) {
+        lazy_static! {
+            static ref RE_SOLD: regex::Regex =
+                regex::Regex::new(r"([0-9][0-9,.]*)\s*([KM]?)\s+items? sold").unwrap();
+            static ref RE_JOINED: regex::Regex =
+                regex::Regex::new(r"(?:Joined|Member since:?)\s+([A-Z][a-z]{2} [0-9]{4})").unwrap();
+        }
+
+        let text = kuchiki::parse_html().one(text).text_contents();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        self.items_sold = RE_SOLD.captures(&text).and_then(|c| {
+            let n = c.get(1)?.as_str().replace(',', "").parse::<f64>().ok()?;
+            let multiplier = match c.get(2)?.as_str() {
+                "K" => 1e3,
+                "M" => 1e6,
+                _ => 1.0,
+            };
+            Some((n * multiplier) as u64)
+        });
+        self.member_since = RE_JOINED
+            .captures(&text)
+            .and_then(|c| Some(c.get(1)?.as_str().to_string()));
+    }
+}
+
+/// What shipping a listing costs.
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ShippingCost {
+    Free,
+    Paid(Money),
+}
+
+/// The range of dates an item is estimated to be delivered between.
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct DeliveryEstimate {
+    pub earliest: NaiveDate,
+    pub latest: NaiveDate,
+}
+
+/// Shipping information for a listing, as shown to a visitor from the US.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct Shipping {
+    /// The cost of the cheapest shipping option, if it is shown on the page.
+    /// Listings with calculated shipping usually don't show a cost.
+    pub cost: Option<ShippingCost>,
+    /// Where the item ships from, e.g. `Austin, Texas, United States`.
+    pub ships_from: Option<String>,
+    pub delivery_estimate: Option<DeliveryEstimate>,
+}
+
+impl Shipping {
+    /// Parse the shipping section of an item page.
+    ///
+    /// Delivery estimates are written without a year, so they are assumed to be
+    /// the first matching dates on or after `today`.
+    fn from_item_page(document: &NodeRef, today: NaiveDate) -> Option<Self> {
+        lazy_static! {
+            static ref RE_LOCATION: regex::Regex = regex::Regex::new(
+                r"(?:Located in|Item location):?\s*([^\n]+?)\s*(?:$|Ships to|Delivery)"
+            )
+            .unwrap();
+            static ref RE_DATE: regex::Regex =
+                regex::Regex::new(r"([A-Z][a-z]{2})[a-z]*\.?\s+([0-9]{1,2})\b").unwrap();
+        }
+
+        let text_of = |selectors: &[&str]| {
+            selectors.iter().find_map(|selector| {
+                let text = document.select_first(selector).ok()?.text_contents();
+                let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+                (!text.is_empty()).then_some(text)
+            })
+        };
+
+        let cost = text_of(&[
+            "#fshippingCost",
+            ".ux-labels-values--shipping .ux-labels-values__values",
+        ])
+        .and_then(|text| {
+            if text.to_lowercase().contains("free") {
+                Some(ShippingCost::Free)
+            } else {
+                Money::from_str(&text).ok().map(ShippingCost::Paid)
+            }
+        });
+
+        let ships_from = text_of(&[
+            "#itemLocation",
+            ".ux-labels-values--shipping",
+            "#shippingSummary",
+        ])
+        .and_then(|text| Some(RE_LOCATION.captures(&text)?.get(1)?.as_str().to_string()));
+
+        let delivery_estimate = text_of(&[".vi-acc-del-range", ".ux-labels-values--deliverto"])
+            .and_then(|text| {
+                let mut dates = RE_DATE.captures_iter(&text).filter_map(|c| {
+                    let (month, day) = (c.get(1)?.as_str(), c.get(2)?.as_str());
+                    [today.year(), today.year() + 1].iter().find_map(|year| {
+                        NaiveDate::parse_from_str(
+                            &format!("{} {} {}", month, day, year),
+                            "%b %d %Y",
+                        )
+                        .ok()
+                        .filter(|date| *date >= today)
+                    })
+                });
+                let earliest = dates.next()?;
+                let latest = dates.next().unwrap_or(earliest);
+                Some(DeliveryEstimate { earliest, latest })
+            });
+
+        (cost.is_some() || ships_from.is_some() || delivery_estimate.is_some()).then_some(Self {
+            cost,
+            ships_from,
+            delivery_estimate,
+        })
+    }
+}
+
+/// How an item is being sold.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ListingType {
+    Auction,
+    BuyItNow,
+    /// An auction that can also be ended early by buying the item outright.
+    Both,
+}
+
+/// The condition of an item, as eBay's sellers pick it from a list.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Condition {
+    /// New, and unopened (with or without tags).
+    New,
+    /// New, but the box was opened, or it has small defects (eBay's `New other`).
+    OpenBox,
+    /// Refurbished by the manufacturer or an approved vendor, as new.
+    CertifiedRefurbished,
+    /// Refurbished, like new.
+    ExcellentRefurbished,
+    /// Refurbished, with small signs of use.
+    VeryGoodRefurbished,
+    /// Refurbished, with signs of use.
+    GoodRefurbished,
+    /// Refurbished (or remanufactured) by the seller, or by someone else eBay doesn't vouch
+    /// for.
+    SellerRefurbished,
+    /// Used, however well kept (e.g. books' `Like New` or `Acceptable`).
+    Used,
+    /// For parts or not working.
+    ForParts,
+}
+
+impl Condition {
+    /// Read the condition an item page shows, e.g. `Used` or `Excellent - Refurbished`.
+    ///
+    /// ## Example
+    /// ```txt
+    /// "New with tags"            -> New
+    /// "Open box"                 -> OpenBox
+    /// "Very Good – Refurbished"  -> VeryGoodRefurbished
+    /// "Pre-owned"                -> Used
+    /// "For parts or not working" -> ForParts
+    /// ```
+    pub fn from_label(label: &str) -> Option<Self> {
+        let label = label
+            .to_lowercase()
+            .split(|c: char| c.is_whitespace() || matches!(c, '-' | '–' | '—' | ':'))
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let label = label.as_str();
+
+        Some(match label {
+            _ if label.contains("for parts") => Self::ForParts,
+            _ if label.contains("refurbished") || label.contains("remanufactured") => {
+                if label.contains("certified") || label.contains("manufacturer refurbished") {
+                    Self::CertifiedRefurbished
+                } else if label.contains("excellent") {
+                    Self::ExcellentRefurbished
+                } else if label.contains("very good") {
+                    Self::VeryGoodRefurbished
+                } else if label.contains("good") {
+                    Self::GoodRefurbished
+                } else {
+                    Self::SellerRefurbished
+                }
+            }
+            _ if label.contains("open box")
+                || label.starts_with("new other")
+                || label.starts_with("new with defects") =>
+            {
+                Self::OpenBox
+            }
+            _ if label.starts_with("new") || label.starts_with("brand new") => Self::New,
+            _ if [
+                "used",
+                "pre owned",
+                "like new",
+                "very good",
+                "good",
+                "acceptable",
+            ]
+            .iter()
+            .any(|used| label.starts_with(used)) =>
+            {
+                Self::Used
+            }
+            _ => return None,
+        })
+    }
+
+    /// Read the condition of an item page, and the seller's notes on it (or else eBay's
+    /// description of the condition).
+    fn from_item_page(
+        document: &NodeRef,
+        item_specifics: &HashMap<String, String>,
+    ) -> (Option<Self>, Option<String>) {
+        lazy_static! {
+            /* new layout first, then old layout */
+            static ref LABEL: Field =
+                Field::new(".x-item-condition-text .ux-textspans").or("#vi-itm-cond");
+            static ref DESCRIPTION: Field =
+                Field::new(".x-item-condition-desc .ux-textspans").or("#vi-cond-addl-info");
+            static ref READ_MORE: regex::Regex =
+                regex::Regex::new(r"(?i)\s*(?:see all condition definitions|read more).*$").unwrap();
+        }
+
+        /* the item specifics have it too, as e.g. `Used: An item that has been used...` */
+        let specific = item_specifics
+            .get("Condition")
+            .map(|text| match text.split_once(':') {
+                Some((label, description)) => (label.trim(), Some(description.trim())),
+                None => (text.trim(), None),
+            });
+        let condition = LABEL
+            .get(document)
+            .and_then(|label| Self::from_label(&label))
+            .or_else(|| Self::from_label(specific?.0));
+
+        let description = DESCRIPTION
+            .get(document)
+            .or_else(|| {
+                let notes = item_specifics.get("Seller Notes")?;
+                Some(
+                    notes
+                        .trim()
+                        .trim_matches(|c| matches!(c, '"' | '“' | '”'))
+                        .to_string(),
+                )
+            })
+            .or_else(|| specific?.1.map(str::to_string))
+            .map(|description| READ_MORE.replace(&description, "").trim().to_string())
+            .filter(|description| !description.is_empty());
+
+        (condition, description)
+    }
+}
+
+/// The state of an auction.
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
+pub struct Auction {
+    /// How many bids have been placed so far.
+    pub bids: Option<u32>,
+    pub ends_at: Option<DateTime<Utc>>,
+    /// The Buy It Now price, if the auction has one.
+    pub buy_it_now_price: Option<Money>,
+}
+
+impl Auction {
+    /// Parse the auction section of an item page, returning `None` if the item is not up for auction.
+    ///
+    /// If the page only shows the time left (e.g. `2d 3h`), the end time is counted from `now`;
+    /// see [`dates::parse_datetime`].
+    fn from_item_page(document: &NodeRef, now: DateTime<Utc>) -> Option<Self> {
+        lazy_static! {
+            static ref RE_NUMBER: regex::Regex = regex::Regex::new(r"[0-9][0-9,]*").unwrap();
+        }
+
+        let text_of = |selector: &str| {
+            let text = document.select_first(selector).ok()?.text_contents();
+            let text = text.trim().to_string();
+            (!text.is_empty()).then_some(text)
+        };
+
+        /* old layout first, then new layout */
+        let bid_price = text_of("#prcIsum_bidPrice").or_else(|| text_of(".x-bid-price"));
+        let bid_count = text_of("#qty-test").or_else(|| text_of(".x-bid-count"));
+        if bid_price.is_none() && bid_count.is_none() {
+            return None;
+        }
+
+        let bids = bid_count.and_then(|text| {
+            RE_NUMBER
+                .find(&text)?
+                .as_str()
+                .replace(',', "")
+                .parse()
+                .ok()
+        });
+
+        let ends_at = document
+            .select_first(".timeMs[timems]")
+            .ok()
+            .and_then(|span| {
+                let ms = span
+                    .attributes
+                    .borrow()
+                    .get("timems")?
+                    .parse::<i64>()
+                    .ok()?;
+                Utc.timestamp_millis_opt(ms).single()
+            })
+            .or_else(|| {
+                /* the end time, e.g. `(Dec 21, 2021 08:00:00 PST)`, or else the time left */
+                let text = text_of(".vi-tm-left")
+                    .or_else(|| text_of("#vi-cdown_timeLeft"))
+                    .or_else(|| text_of(".ux-timer__text"))?;
+                dates::parse_datetime(&text, now, DateOrder::MonthFirst)
+            });
+
+        let buy_it_now_price = text_of("#prcIsum")
+            .or_else(|| text_of(".x-bin-price"))
+            .and_then(|text| Money::from_str(&text).ok());
+
+        Some(Self {
+            bids,
+            ends_at,
+            buy_it_now_price,
+        })
+    }
+
+    /// The kind of listing this auction belongs to.
+    pub fn listing_type(&self) -> ListingType {
+        if self.buy_it_now_price.is_some() {
+            ListingType::Both
+        } else {
+            ListingType::Auction
+        }
+    }
+}
+
+/// Find the links to a listing's pictures on its item page.
+///
+/// eBay serves each picture in several sizes, named like `s-l64.jpg`; the links are
+/// rewritten to the largest size (`s-l1600`).
+fn image_urls(document: &NodeRef) -> Vec<String> {
+    lazy_static! {
+        static ref RE_SIZE: regex::Regex = regex::Regex::new(r"/s-l[0-9]+\.").unwrap();
+    }
+
+    let mut urls: Vec<String> = Vec::new();
+    let selectors = [
+        "#icImg",
+        "#vi_main_img_fs img",
+        ".ux-image-carousel-item img",
+        "meta[property='og:image']",
+    ];
+    for selector in selectors.iter() {
+        for element in document.select(selector).into_iter().flatten() {
+            let attributes = element.attributes.borrow();
+            let url = ["data-zoom-src", "data-src", "src", "content"]
+                .iter()
+                .find_map(|key| attributes.get(*key))
+                .filter(|url| url.starts_with("http"));
+            if let Some(url) = url {
+                let url = RE_SIZE.replace(url, "/s-l1600.").to_string();
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+        }
+    }
+    urls
 }
 
 /// A single eBay product.
-#[derive(Serialize, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Default)]
 pub struct Product {
+    /// The eBay item ID.
+    pub id: u64,
     /// The title of the product.
     pub name: String,
     /// The seller, if available.
     pub seller: Option<Seller>,
     /// The price before shipping, if available.
+    /// For auctions, this is the current bid. `None` if the listing has a
+    /// [`price_range`](Self::price_range) instead.
     pub price: Option<Money>,
+    /// The cheapest and dearest prices, for listings whose variations (e.g. sizes or colors)
+    /// cost different amounts, which show a range like `$10.99 to $24.99`.
+    pub price_range: Option<MoneyRange>,
+    /// The condition the seller gives, if the listing says.
+    pub condition: Option<Condition>,
+    /// The seller's notes on the condition (e.g. `Small scratch on the lid`), or else eBay's
+    /// description of it, if the listing has either.
+    pub condition_description: Option<String>,
+    /// Whether the item is sold by auction, Buy It Now, or both.
+    pub listing_type: Option<ListingType>,
+    /// Bids, end time and Buy It Now price, if the item is up for auction.
+    pub auction: Option<Auction>,
     /// Whether this item was from a sponsored listing.
     /// This option is only filled (and only makes sense) when the [`Product`]
     /// comes from certain endpoints, e.g. [`Product::search`].
     pub sponsored: Option<bool>,
+    /// The "item specifics" table of the listing (e.g. `Brand`, `UPC`), by label.
+    pub item_specifics: HashMap<String, String>,
+    /// Shipping cost, location and delivery estimate, if available.
+    pub shipping: Option<Shipping>,
+    /// Links to the listing's pictures, largest size available.
+    pub images: Vec<String>,
+    /// How many are left, if the listing says. `More than 10 available` is read as 10.
+    pub quantity_available: Option<u64>,
+    /// How many have sold from the listing, if it says.
+    pub quantity_sold: Option<u64>,
+    /// How many people are watching the listing, if it says.
+    pub watchers: Option<u64>,
+    /// The categories the listing is in, from its breadcrumb trail, broadest first.
+    pub breadcrumbs: Vec<Category>,
+    /// The variations a buyer picks from (e.g. sizes and colors), if the listing has them.
+    pub variations: Vec<Variation>,
+}
+
+/// Read the category trail at the top of an item page. Links that aren't to a category (e.g.
+/// `Back to search results`) are left out.
+fn breadcrumbs(document: &NodeRef) -> Vec<Category> {
+    lazy_static! {
+        /* e.g. `/b/Laptops-Netbooks/175672/bn_1648276`, or `/sch/175672/i.html` on old pages */
+        static ref RE_CATEGORY: regex::Regex =
+            regex::Regex::new(r"/(?:b/[^/?#]+|sch)/([0-9]+)(?:[/?#]|$)").unwrap();
+    }
+
+    document
+        .select(".seo-breadcrumbs-container a[href], #vi-VR-brumb-lnkLst a[href]")
+        .ok()
+        .into_iter()
+        .flatten()
+        .filter_map(|link| {
+            let id = {
+                let attributes = link.attributes.borrow();
+                let href = attributes.get("href")?;
+                RE_CATEGORY.captures(href)?.get(1)?.as_str().parse().ok()?
+            };
+            let name = link.text_contents().trim().to_string();
+            (!name.is_empty()).then_some(Category { id, name })
+        })
+        .collect()
+}
+
+/// One of the variations of a listing, e.g. a shirt in one size and color.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct Variation {
+    /// What sets it apart, by the name of the menu it's picked from, e.g. `Size`: `M`.
+    pub attributes: HashMap<String, String>,
+    pub price: Option<Money>,
+    /// How many are left, if the listing says; 0 if it's out of stock.
+    pub quantity: Option<u32>,
+}
+
+/* the variations JSON ("MSKU") embedded in an item page; the menus list which values
+ * (e.g. `M`) can be picked for what (e.g. `Size`), and each combination of values is
+ * one variation */
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Msku {
+    #[serde(default)]
+    select_menus: Vec<MskuMenu>,
+    #[serde(default)]
+    menu_item_map: HashMap<String, MskuMenuItem>,
+    /// Variation IDs, by the value IDs of the combination joined with `_`, e.g. `1_3`.
+    #[serde(default)]
+    variation_combinations: HashMap<String, u64>,
+    #[serde(default)]
+    variations_map: HashMap<String, MskuVariation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MskuMenu {
+    display_label: String,
+    #[serde(default)]
+    menu_item_value_ids: Vec<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MskuMenuItem {
+    #[serde(alias = "displayName")]
+    value_name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MskuVariation {
+    bin_model: Option<MskuBinModel>,
+    quantity: Option<MskuQuantity>,
+    #[serde(default)]
+    out_of_stock: bool,
+}
+
+#[derive(Deserialize)]
+struct MskuBinModel {
+    price: Option<MskuPrice>,
+}
+
+#[derive(Deserialize)]
+struct MskuPrice {
+    value: MskuAmount,
+}
+
+#[derive(Deserialize)]
+struct MskuAmount {
+    value: Decimal,
+    currency: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MskuQuantity {
+    available_quantity: Option<u32>,
+}
+
+impl Msku {
+    /// Find the variations JSON in the scripts of an item page.
+    fn from_item_page(document: &NodeRef) -> Option<Self> {
+        document.select("script").ok()?.find_map(|script| {
+            let text = script.text_contents();
+            let start = text.find("\"MSKU\":")? + "\"MSKU\":".len();
+            /* the JSON goes on after the object, so only the first value is read */
+            serde_json::Deserializer::from_str(&text[start..])
+                .into_iter::<Self>()
+                .next()?
+                .ok()
+        })
+    }
+
+    /// The variations, in the order of their IDs.
+    fn variations(&self) -> Vec<Variation> {
+        let menu_of = |value_id: u64| {
+            self.select_menus
+                .iter()
+                .find(|menu| menu.menu_item_value_ids.contains(&value_id))
+        };
+        let mut combinations = self.variation_combinations.iter().collect::<Vec<_>>();
+        combinations.sort_by_key(|(_, id)| **id);
+
+        combinations
+            .into_iter()
+            .filter_map(|(values, id)| {
+                let variation = self.variations_map.get(&id.to_string())?;
+                let attributes = values
+                    .split('_')
+                    .filter_map(|value_id| {
+                        let menu = menu_of(value_id.parse().ok()?)?;
+                        let item = self.menu_item_map.get(value_id)?;
+                        Some((menu.display_label.clone(), item.value_name.clone()))
+                    })
+                    .collect();
+                let price = variation
+                    .bin_model
+                    .as_ref()
+                    .and_then(|model| model.price.as_ref())
+                    .and_then(|price| {
+                        let currency = Currency::from_abbreviation(&price.value.currency)?;
+                        Some(Money::new(currency, price.value.value))
+                    });
+                let quantity = if variation.out_of_stock {
+                    Some(0)
+                } else {
+                    variation
+                        .quantity
+                        .as_ref()
+                        .and_then(|quantity| quantity.available_quantity)
+                };
+                Some(Variation {
+                    attributes,
+                    price,
+                    quantity,
+                })
+            })
+            .collect()
+    }
+}
+
+/// The quantities an item page shows in its buy box.
+#[derive(Default, PartialEq, Debug)]
+struct Quantities {
+    available: Option<u64>,
+    sold: Option<u64>,
+    watchers: Option<u64>,
+}
+
+impl Quantities {
+    fn from_item_page(document: &NodeRef) -> Self {
+        lazy_static! {
+            /* the new layout shows e.g. `3 available 12 sold` together; the old one apart */
+            static ref AVAILABLE: Field = Field::new(".x-quantity__availability")
+                .or("#qtySubTxt")
+                .capture(regex::Regex::new(r"(?i)([0-9][0-9,]*) available").unwrap());
+            static ref LAST_ONE: Field = Field::new(".x-quantity__availability")
+                .or("#qtySubTxt")
+                .capture(regex::Regex::new(r"(?i)last one").unwrap());
+            static ref SOLD: Field = Field::new(".x-quantity__availability")
+                .or(".vi-qtyS-hot-red")
+                .or(".vi-qty-vert-algn")
+                .capture(regex::Regex::new(r"(?i)([0-9][0-9,]*) sold").unwrap());
+            static ref WATCHERS: Field = Field::new(".d-urgency")
+                .or("#vi-bybox-watchers")
+                .or(".vi-buybox-watchcount")
+                .capture(regex::Regex::new(r"(?i)([0-9][0-9,]*) (?:watchers|watching|people are watching)").unwrap());
+        }
+        let count = |field: &Field| field.number(document).ok().map(|n| n as u64);
+
+        Self {
+            available: count(&AVAILABLE).or_else(|| LAST_ONE.get(document).map(|_| 1)),
+            sold: count(&SOLD),
+            watchers: count(&WATCHERS),
+        }
+    }
 }
 
 impl Product {
-    /// Find an eBay product using its item ID.
+    /// Extract the item ID from a link to an eBay listing.
     ///
-    /// # Errors
-    /// Errors if one of the requests failed, or if one of the responses could not be parsed.
-    pub async fn by_id(client: &mut Client<false>, id: u64) -> anyhow::Result<Self> {
+    /// ## Example
+    /// ```txt
+    /// "https://www.ebay.com/itm/254625474154"          -> 254625474154
+    /// "https://www.ebay.com/itm/foo/254625474154?x=y"  -> 254625474154
+    /// ```
+    pub fn id_from_url(url: &str) -> Option<u64> {
         lazy_static! {
-            static ref RE_USR: regex::Regex =
-                regex::Regex::new(r"https://(?:www\.)?ebay\.com/usr/([a-zA-Z0-9_\-]+)(?:\?.*)?")
+            static ref RE_ID: regex::Regex =
+                regex::Regex::new(r"^https?://(?:www\.)?ebay\.com/itm/(?:[^/?#]+/)?([0-9]+)")
                     .unwrap();
-            static ref RE_PERCENT: regex::Regex =
-                regex::Regex::new(r"([0-9]+(?:\.[0-9]+)?)%").unwrap();
+        }
+
+        RE_ID.captures(url)?.get(1)?.as_str().parse().ok()
+    }
+
+    /// Find the item ID of an item page from its canonical link, e.g. for a page that was
+    /// downloaded some other way.
+    pub fn id_from_html(html: &str) -> Option<u64> {
+        canonical_link(&kuchiki::parse_html().one(html)).and_then(|url| Self::id_from_url(&url))
+    }
+
+    /// Find an eBay product using its item ID.
+    ///
+    /// With [`Detail::Minimal`], only the title and price are parsed.
+    /// With [`Detail::Full`], the seller's profile page is also fetched; if that fails, the
+    /// failure is logged and the profile's fields are left out.
+    ///
+    /// # Errors
+    /// Errors if the item page could not be fetched or parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id(
+        client: &mut Client<false>,
+        id: u64,
+        detail: Detail,
+    ) -> anyhow::Result<Self> {
+        Self::by_id_with(&Endpoints::default(), client, id, detail).await
+    }
+
+    /// Like [`Product::by_id`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the item page could not be fetched or parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn by_id_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        id: u64,
+        detail: Detail,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::by_id_enveloped_with(endpoints, client, id, detail)
+            .await?
+            .data)
+    }
+
+    /// Like [`Product::by_id`], recording where and when the product was collected.
+    ///
+    /// # Errors
+    /// Errors if the item page could not be fetched or parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id_enveloped(
+        client: &mut Client<false>,
+        id: u64,
+        detail: Detail,
+    ) -> anyhow::Result<Collected<Self>> {
+        Self::by_id_enveloped_with(&Endpoints::default(), client, id, detail).await
+    }
+
+    /// Like [`Product::by_id_enveloped`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the item page could not be fetched or parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id_enveloped_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        id: u64,
+        detail: Detail,
+    ) -> anyhow::Result<Collected<Self>> {
+        let link = endpoints.url(&format!("/itm/foo/{}", id));
+        let mut collected = {
+            let page = client.get_page(&link).await?;
+            let product = Self::from_document(&page.document, id, detail)?;
+            Collected::from_page_with_report("ebay", &page, product)
         };
 
-        let link = format!("https://www.ebay.com/itm/foo/{}", id);
+        if detail == Detail::Full {
+            if let Some(seller) = collected.data.seller.as_mut() {
+                /* the product is still worth having without the seller's details */
+                if let Err(e) = seller.fetch_profile_with(endpoints, client).await {
+                    tracing::warn!(seller = %seller.name, error = %format!("{:#}", e), "could not fetch the seller's profile");
+                }
+            }
+        }
+
+        Ok(collected)
+    }
 
-        let response = client.0.get(link.clone()).send().await?;
-        let text = response.text().await?;
-        let document = kuchiki::parse_html().one(text);
+    /// Parse the item page of the listing `id`, as [`Product::by_id`] does.
+    ///
+    /// Nothing more is fetched, so [`Detail::Full`] is the same as [`Detail::Default`]; the
+    /// seller's profile can be read with [`Seller::parse_profile`].
+    ///
+    /// # Errors
+    /// Errors if the page could not be parsed.
+    pub fn from_html(text: &str, id: u64, detail: Detail) -> anyhow::Result<Self> {
+        Self::from_html_with_report(text, id, detail).map(WithReport::into_inner)
+    }
+
+    /// Like [`Product::from_html`], with a report of the fields the page should have had
+    /// (at this `detail`), but didn't, or that could not be read.
+    ///
+    /// # Errors
+    /// Errors if the page could not be parsed.
+    pub fn from_html_with_report(
+        text: &str,
+        id: u64,
+        detail: Detail,
+    ) -> anyhow::Result<WithReport<Self>> {
+        Self::from_document(&kuchiki::parse_html().one(text), id, detail)
+    }
+
+    /// Like [`Product::from_html_with_report`], for a page that's already parsed (e.g. a
+    /// [`Page`](crate::common::http::Page)).
+    ///
+    /// # Errors
+    /// Errors if the page could not be parsed.
+    pub fn from_document(
+        document: &NodeRef,
+        id: u64,
+        detail: Detail,
+    ) -> anyhow::Result<WithReport<Self>> {
+        Self::from_document_at(document, id, detail, Utc::now())
+    }
+
+    /// Like [`Product::from_document`], as if it were `now` (for the time left on an
+    /// auction, and delivery dates without a year).
+    pub(crate) fn from_document_at(
+        document: &NodeRef,
+        id: u64,
+        detail: Detail,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<WithReport<Self>> {
+        lazy_static! {
+            static ref RE_USR: regex::Regex =
+                regex::Regex::new(r"https?://[^/]+/usr/([a-zA-Z0-9_\-]+)(?:\?.*)?").unwrap();
+            static ref FEEDBACK: Field =
+                Field::new("#si-fb").capture(regex::Regex::new(r"([0-9]+(?:\.[0-9]+)?)%").unwrap());
+        };
 
         let product = try {
             let name = {
                 document
                     .select_first("#itemTitle")
-                    .ok()
-                    .context("trying to get title")?
+                    .map_err(|()| anyhow!(ParseError::new("the listing has no title")))?
                     .as_node()
                     .children()
                     .find_map(|node| {
@@ -70,60 +947,230 @@ impl Product {
                             Some(s.to_string())
                         }
                     })
-                    .context("trying to get title")?
+                    .ok_or_else(|| anyhow!(ParseError::new("the listing's title is empty")))?
             };
 
-            let seller: Option<Seller> = try {
-                let seller_info = document.select_first(".si-content").ok()?;
-                let name: String = seller_info
-                    .as_node()
-                    .select("a[href]")
-                    .ok()?
-                    .find_map(|a| {
-                        let href = {
-                            let attributes = a.attributes.borrow();
-                            attributes.get("href")?.to_string()
-                        };
-                        let username = RE_USR.captures(href.as_str())?.get(1)?.as_str().to_string();
-                        Some(username)
-                    })?;
-                let feedback: Option<f64> = try {
+            let seller: Option<Seller> = if detail == Detail::Minimal {
+                None
+            } else {
+                try {
+                    let seller_info = document.select_first(".si-content").ok()?;
+                    let name: String =
+                        seller_info
+                            .as_node()
+                            .select("a[href]")
+                            .ok()?
+                            .find_map(|a| {
+                                let href = {
+                                    let attributes = a.attributes.borrow();
+                                    attributes.get("href")?.to_string()
+                                };
+                                let username =
+                                    RE_USR.captures(href.as_str())?.get(1)?.as_str().to_string();
+                                Some(username)
+                            })?;
                     /* TODO: work on sold eBay listings (e.g. 255166134948) */
-                    let text = seller_info
-                        .as_node()
-                        .select_first("#si-fb")
-                        .ok()?
+                    let feedback = FEEDBACK
+                        .number(seller_info.as_node())
+                        .ok()
+                        .and_then(|percent| Rating::from_scale(percent, 100.0));
+
+                    Seller {
+                        name,
+                        feedback,
+                        ..Default::default()
+                    }
+                }
+            };
+
+            /* TODO: work on sold eBay listings (e.g. 255166134948) */
+            let main_price = document
+                .select_first(".mainPrice")
+                .or_else(|_| document.select_first(".vi-price"))
+                .ok();
+            /* the microdata of a listing with variations only has the lowest price, so the
+             * range is read from the shown price only (leaving out e.g. `Approximately EUR
+             * 28.50`, which is elsewhere in `.mainPrice`) */
+            let price_range = main_price
+                .as_ref()
+                .and_then(|main_price| {
+                    main_price
                         .as_node()
-                        .text_contents();
-                    let percent = RE_PERCENT.captures(text.as_str())?.get(1)?.as_str();
-                    percent.parse::<f64>().ok()? * 0.01
-                };
+                        .select_first("[itemprop='price'], .x-price-primary")
+                        .ok()
+                })
+                .and_then(|shown| MoneyRange::from_str(&shown.text_contents()).ok())
+                .filter(|range| !range.is_single());
+            let price: Option<Money> = match (&main_price, &price_range) {
+                (Some(main_price), None) => {
+                    Scope::from(main_price.as_node().clone()).try_into().ok()
+                }
+                _ => None,
+            };
+
+            let item_specifics = if detail == Detail::Minimal {
+                HashMap::new()
+            } else {
+                /* the old and new listing layouts use different markup for item specifics */
+                let old_layout = document
+                    .select(".itemAttr td.attrLabels")
+                    .ok()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|label| {
+                        let value = label.as_node().following_siblings().elements().next()?;
+                        Some((label.text_contents(), value.text_contents()))
+                    });
+                let new_layout = document
+                    .select(".ux-labels-values")
+                    .ok()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|row| {
+                        let row = row.as_node();
+                        let label = row.select_first(".ux-labels-values__labels").ok()?;
+                        let value = row.select_first(".ux-labels-values__values").ok()?;
+                        Some((label.text_contents(), value.text_contents()))
+                    });
 
-                Seller { name, feedback }
+                old_layout
+                    .chain(new_layout)
+                    .map(|(label, value)| {
+                        (
+                            label.trim().trim_end_matches(':').trim().to_string(),
+                            value.trim().to_string(),
+                        )
+                    })
+                    .filter(|(label, value)| !label.is_empty() && !value.is_empty())
+                    .collect()
             };
 
-            let price: Option<Money> = try {
-                /* TODO: work on sold eBay listings (e.g. 255166134948) */
-                let main_price = document
-                    .select_first(".mainPrice")
-                    .or_else(|_| document.select_first(".vi-price"))
-                    .ok()?;
+            let (condition, condition_description) = if detail == Detail::Minimal {
+                (None, None)
+            } else {
+                Condition::from_item_page(document, &item_specifics)
+            };
 
-                let scope = Scope::from(main_price.as_node().clone());
-                scope.try_into().ok()?
+            let auction = if detail == Detail::Minimal {
+                None
+            } else {
+                Auction::from_item_page(document, now)
+            };
+            let listing_type = match &auction {
+                _ if detail == Detail::Minimal => None,
+                Some(auction) => Some(auction.listing_type()),
+                None => (price.is_some() || price_range.is_some()).then_some(ListingType::BuyItNow),
+            };
+
+            let shipping = if detail == Detail::Minimal {
+                None
+            } else {
+                Shipping::from_item_page(document, now.date_naive())
+            };
+
+            let images = if detail == Detail::Minimal {
+                Vec::new()
+            } else {
+                image_urls(document)
+            };
+
+            let quantities = if detail == Detail::Minimal {
+                Quantities::default()
+            } else {
+                Quantities::from_item_page(document)
+            };
+
+            let breadcrumbs = if detail == Detail::Minimal {
+                Vec::new()
+            } else {
+                breadcrumbs(document)
+            };
+
+            let variations = if detail == Detail::Minimal {
+                Vec::new()
+            } else {
+                Msku::from_item_page(document)
+                    .map(|msku| msku.variations())
+                    .unwrap_or_default()
             };
 
-            Self {
+            let product = Self {
+                id,
                 name,
                 seller,
                 price,
+                price_range,
+                condition,
+                condition_description,
+                listing_type,
+                auction,
+                item_specifics,
+                shipping,
+                images,
+                quantity_available: quantities.available,
+                quantity_sold: quantities.sold,
+                watchers: quantities.watchers,
+                breadcrumbs,
+                variations,
                 ..Default::default()
+            };
+
+            let mut missing = vec![("price", main_price.is_none())];
+            if detail != Detail::Minimal {
+                missing.extend([
+                    ("seller", product.seller.is_none()),
+                    ("condition", product.condition.is_none()),
+                    ("item_specifics", product.item_specifics.is_empty()),
+                    ("shipping", product.shipping.is_none()),
+                    ("images", product.images.is_empty()),
+                    ("breadcrumbs", product.breadcrumbs.is_empty()),
+                ]);
+            }
+            let unreadable_price = match &main_price {
+                Some(main_price) if product.price.is_none() && product.price_range.is_none() => {
+                    let text = main_price.text_contents();
+                    Some(text.split_whitespace().collect::<Vec<_>>().join(" "))
+                }
+                _ => None,
+            };
+
+            let mut report = WithReport::new(product);
+            for (field, missing) in missing {
+                report.missing_if(missing, field);
+            }
+            if let Some(text) = unreadable_price {
+                report.push(FieldIssue::unreadable("price", text));
             }
+            report
         };
 
         product
     }
 
+    /// The GTIN (UPC, EAN or ISBN) of the product, if the seller listed one in the item specifics.
+    pub fn gtin(&self) -> Option<&str> {
+        ["UPC", "EAN", "ISBN", "GTIN"]
+            .iter()
+            .filter_map(|key| self.item_specifics.get(*key))
+            .map(|value| value.trim())
+            .find(|value| !value.is_empty() && value.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    /// The ISBN of the product (without hyphens), if it's a book and the seller listed one in
+    /// the item specifics.
+    pub fn isbn(&self) -> Option<String> {
+        ["ISBN", "ISBN-13", "ISBN-10"]
+            .iter()
+            .filter_map(|key| self.item_specifics.get(*key))
+            .find_map(|value| openlibrary::normalize_isbn(value))
+            /* books are often listed with only their ISBN-13 as the EAN */
+            .or_else(|| {
+                self.gtin()
+                    .filter(|gtin| gtin.starts_with("978") || gtin.starts_with("979"))
+                    .and_then(openlibrary::normalize_isbn)
+            })
+    }
+
     /// Search for products given a query string.
     ///
     /// This endpoint will wait a few hundred milliseconds between product
@@ -132,140 +1179,1921 @@ impl Product {
     /// # Returns
     /// Returns a [`Stream`] of [`anyhow::Result<Self>`].
     ///
-    /// The stream terminates when one or both of the following:
+    /// The stream ends after a results page with no results that weren't on an earlier page
+    /// (past the last page, eBay shows it again). It stops early, with a [`SearchInterrupted`]
+    /// error, when:
     ///
     /// - Getting the next search results page returns an error
     /// - All results on one page return errors
     ///
-    /// Results listing page errors are not returned, but product pages themselves are
-    /// (through the returned stream).
-    pub fn search(query: &str) -> impl Stream<Item = anyhow::Result<Self>> + '_ {
-        lazy_static! {
-            static ref RE_ITM: regex::Regex =
-                regex::Regex::new(r"https://(?:www\.)?ebay\.com/itm/([a-zA-Z0-9_\-]+)(?:\?.*)?")
-                    .unwrap();
-        }
+    /// Errors fetching each product are returned in the stream too.
+    ///
+    /// Each product is fetched with the given [`Detail`], as in [`Product::by_id`].
+    #[cfg(feature = "net")]
+    pub fn search(query: &str, detail: Detail) -> impl Stream<Item = anyhow::Result<Self>> + '_ {
+        Self::search_with(&Endpoints::default(), query, detail)
+    }
 
-        let stream_stream = futures::stream::iter(1..).then(move |page| {
-            let ok = Arc::new(Mutex::new(true));
-            let query = query.to_string();
-            let client = Arc::new(Mutex::new(Client::default()));
-            async move {
-                {
-                    let guard = ok.lock().await;
-                    if !*guard {
-                        bail!("something failed; pages ended, maybe?");
-                    }
-                }
+    /// Like [`Product::search`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn search_with<'a>(
+        endpoints: &Endpoints,
+        query: &'a str,
+        detail: Detail,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_with_options(endpoints, query, detail, SearchOptions::default())
+    }
 
-                let text = {
-                    let mut guard = client.lock().await;
-                    let reqwest_client = &mut guard.0;
-                    reqwest_client
-                        .get("https://www.ebay.com/sch/i.html")
-                        .query(&[("_nkw", query), ("_pgn", page.to_string())])
-                        .send()
-                        .await?
-                        .text()
-                        .await?
-                };
+    /// Like [`Product::search_with`], with the given [`SearchOptions`].
+    #[cfg(feature = "net")]
+    pub fn search_with_options<'a>(
+        endpoints: &Endpoints,
+        query: &'a str,
+        detail: Detail,
+        options: SearchOptions,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_using(Client::default(), endpoints, query, detail, options)
+    }
 
-                let ids = {
-                    let node = parse_html().one(text);
-                    let main = node
-                        .select_first("#mainContent")
-                        .ok()
-                        .context("could not find main content")?;
-                    main.as_node()
-                        .select(".s-item")
-                        .ok()
-                        .context("could not find any items")?
-                        .filter_map(|n| {
-                            n.as_node()
-                                .descendants()
-                                .find_map(|d| {
-                                    let s = d.as_element()?.attributes.borrow();
-                                    let a = s.get("href")?;
-                                    RE_ITM.captures(a)?.get(1)?.as_str().parse::<u64>().ok()
-                                })
-                                .and_then(|id| {
-                                    let sponsored =
-                                        n.as_node().select(".s-item__detail").ok()?.any(|e| {
-                                            has_hidden_word("Sponsored", e.text_contents().as_str())
-                                        });
-                                    Some((id, sponsored))
-                                })
-                        })
-                        .collect::<Vec<(u64, bool)>>()
-                    /* ^ we have to collect this here because kuchiki is not thread-safe ^ */
-                };
+    /// Like [`Product::search_with_options`], sending every request through `client`.
+    #[cfg(feature = "net")]
+    pub(crate) fn search_using<'a>(
+        client: Client<false>,
+        endpoints: &Endpoints,
+        query: &'a str,
+        detail: Detail,
+        options: SearchOptions,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_from_using(client, endpoints, query, detail, options, 1, HashSet::new())
+            .map(|r| r.map(|(_, product)| product))
+    }
 
-                /* make sure at least one exists */
-                {
-                    let mut guard = ok.lock().await;
-                    *guard = false;
-                }
+    /// Search for products, one results page at a time.
+    ///
+    /// Unlike [`Product::search`], this doesn't visit each product's item page: each page
+    /// only has the [`SearchResultSummary`]'s shown in the results, so a page costs one
+    /// request.
+    ///
+    /// The stream ends after the last page: the first with no results, or with none that
+    /// weren't on an earlier page (past the last page, eBay shows it again). It also ends
+    /// after the first error.
+    #[cfg(feature = "net")]
+    pub fn search_pages(query: &str) -> impl Stream<Item = anyhow::Result<SearchPage>> + '_ {
+        Self::search_pages_with(&Endpoints::default(), query)
+    }
 
-                Ok(futures::stream::iter(ids).then(move |(id, sponsored)| {
-                    let ok = ok.clone();
-                    let client = client.clone();
-                    async move {
+    /// Like [`Product::search_pages`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn search_pages_with<'a>(
+        endpoints: &Endpoints,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<SearchPage>> + 'a {
+        Self::search_pages_using(Client::default(), endpoints, query)
+    }
+
+    /// Like [`Product::search_pages_with`], sending every request through `client`.
+    #[cfg(feature = "net")]
+    pub(crate) fn search_pages_using<'a>(
+        client: Client<false>,
+        endpoints: &Endpoints,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<SearchPage>> + 'a {
+        until_repeated(results_pages(
+            client,
+            endpoints.url("/sch/i.html"),
+            vec![("_nkw", query.to_string())],
+        ))
+    }
+
+    /// Like [`Product::search_with`], resuming from a [`SearchCheckpoint`]: the search starts
+    /// at the checkpoint's page, and skips the products already collected from it.
+    ///
+    /// Each product comes with the number of the results page it was found on, to
+    /// [`record`](SearchCheckpoint::record) in the checkpoint.
+    #[cfg(feature = "net")]
+    pub fn search_resume_with<'a>(
+        endpoints: &Endpoints,
+        query: &'a str,
+        detail: Detail,
+        options: SearchOptions,
+        checkpoint: &SearchCheckpoint<Self>,
+    ) -> impl Stream<Item = anyhow::Result<(u32, Self)>> + 'a {
+        Self::search_from_using(
+            Client::default(),
+            endpoints,
+            query,
+            detail,
+            options,
+            checkpoint.page,
+            checkpoint.done.iter().copied().collect(),
+        )
+    }
+
+    /// Search from results page `first_page` on, skipping the products in `skip`.
+    #[cfg(feature = "net")]
+    pub(crate) fn search_from_using<'a>(
+        client: Client<false>,
+        endpoints: &Endpoints,
+        query: &'a str,
+        detail: Detail,
+        options: SearchOptions,
+        first_page: u32,
+        skip: HashSet<u64>,
+    ) -> impl Stream<Item = anyhow::Result<(u32, Self)>> + 'a {
+        struct State {
+            client: Client<false>,
+            endpoints: Endpoints,
+            options: SearchOptions,
+            /// The products collected before, e.g. by the run a checkpoint is from.
+            skip: HashSet<u64>,
+            seen: SeenIds,
+            /// The products on `page`, to tell when eBay shows it again.
+            page_ids: HashSet<u64>,
+            page: u32,
+            /// The products still to visit from `page`, and whether they're sponsored.
+            queue: VecDeque<(u64, bool)>,
+            /// Whether a product from `page` was fetched (or there were none to fetch).
+            page_ok: bool,
+            done: bool,
+        }
+
+        let state = State {
+            client,
+            endpoints: endpoints.clone(),
+            seen: SeenIds::new(options.max_seen),
+            options,
+            skip,
+            page_ids: HashSet::new(),
+            page: first_page.saturating_sub(1),
+            queue: VecDeque::new(),
+            page_ok: true,
+            done: false,
+        };
+        futures::stream::unfold(state, move |mut state| {
+            async move {
+                loop {
+                    if state.done {
+                        return None;
+                    }
+
+                    if let Some((id, sponsored)) = state.queue.pop_front() {
                         /* be nice! */
                         let sleep = tokio::time::sleep(Duration::from_millis(600));
-                        let fut = async {
-                            let mut guard = client.lock().await;
-                            let real_client = &mut guard;
-                            Self::by_id(real_client, id).await
+                        let fetch =
+                            Self::by_id_with(&state.endpoints, &mut state.client, id, detail);
+                        let result = match tokio::join!(fetch, sleep).0 {
+                            Ok(mut product) => {
+                                state.page_ok = true;
+                                product.sponsored = Some(sponsored);
+                                Ok((state.page, product))
+                            }
+                            Err(e) => Err(e),
                         };
+                        return Some((result, state));
+                    }
+
+                    if !state.page_ok {
+                        state.done = true;
+                        let error = anyhow::anyhow!("every product on the page failed")
+                            .context(SearchInterrupted { page: state.page });
+                        return Some((Err(error), state));
+                    }
+
+                    state.page += 1;
+                    let page = state.page;
+                    let result: anyhow::Result<SearchPage> = try {
+                        let text = state
+                            .client
+                            .get(&state.endpoints.url("/sch/i.html"))
+                            .await?
+                            .query(&[("_nkw", query.to_string()), ("_pgn", page.to_string())])
+                            .options(SEARCH_OPTIONS)
+                            .send()
+                            .await?
+                            .text()
+                            .await?;
+                        SearchPage::from_html(&text, page)?
+                    };
+                    let items = match result {
+                        Ok(results) => results.items,
+                        Err(e) => {
+                            state.done = true;
+                            return Some((Err(e.context(SearchInterrupted { page })), state));
+                        }
+                    };
 
-                        let mut prod = tokio::join!(fut, sleep).0?;
-                        /* mark that at least one of the links worked */
+                    /* past the last page, eBay shows it again, so a page with nothing that
+                     * wasn't on the one before ends the search, whatever is left out of the
+                     * results (and however few IDs `seen` keeps) */
+                    let page_ids = items.iter().map(|item| item.id).collect::<HashSet<_>>();
+                    if page_ids.is_subset(&state.page_ids) {
+                        return None;
+                    }
+                    state.page_ids = page_ids;
+                    for item in items {
+                        let new = state.seen.insert(item.id);
+                        if !state.skip.contains(&item.id)
+                            && !(state.options.skip_sponsored && item.sponsored)
+                            && (state.options.include_duplicates || new)
                         {
-                            let mut guard = ok.lock().await;
-                            *guard = true;
+                            state.queue.push_back((item.id, item.sponsored));
                         }
+                    }
+                    state.page_ok = state.queue.is_empty();
+                }
+            }
+        })
+    }
+}
+
+/// The error a search (e.g. [`Product::search_resume_with`]) ends with when it stops before
+/// running out of results: results page `page` could not be fetched or read, or every
+/// product on it failed. The cause is the next error in the chain.
+///
+/// A search that ends without it has every result, so its checkpoint can be removed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SearchInterrupted {
+    pub page: u32,
+}
+
+impl std::fmt::Display for SearchInterrupted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the search stopped at results page {}", self.page)
+    }
+}
+
+impl std::error::Error for SearchInterrupted {}
+
+/// Get results pages (e.g. of a search) one at a time, from page 1 until one has no results
+/// or fails.
+#[cfg(feature = "net")]
+fn results_pages(
+    client: Client<false>,
+    url: String,
+    query: Vec<(&'static str, String)>,
+) -> impl Stream<Item = anyhow::Result<SearchPage>> {
+    futures::stream::unfold(Some(1), move |page: Option<u32>| {
+        let (url, client, mut query) = (url.clone(), client.clone(), query.clone());
+        async move {
+            let page = page?;
+            if page > 1 {
+                /* be nice! */
+                tokio::time::sleep(Duration::from_millis(600)).await;
+            }
+
+            query.push(("_pgn", page.to_string()));
+            let result: anyhow::Result<SearchPage> = try {
+                let text = client
+                    .get(&url)
+                    .await?
+                    .query(&query)
+                    .options(SEARCH_OPTIONS)
+                    .send()
+                    .await?
+                    .text()
+                    .await?;
+                SearchPage::from_html(&text, page)?
+            };
+
+            match result {
+                Ok(results) if results.items.is_empty() => None,
+                Ok(results) => Some((Ok(results), Some(page + 1))),
+                Err(e) => Some((Err(e), None)),
+            }
+        }
+    })
+}
+
+/// Results pages, up to the first with nothing that wasn't on an earlier page (past the last
+/// page, eBay shows it again).
+#[cfg(feature = "net")]
+fn until_repeated(
+    pages: impl Stream<Item = anyhow::Result<SearchPage>>,
+) -> impl Stream<Item = anyhow::Result<SearchPage>> {
+    let mut seen = HashSet::new();
+    pages.take_while(move |page| {
+        futures::future::ready(match page {
+            /* every ID is remembered, not just up to the first new one */
+            Ok(page) => page
+                .items
+                .iter()
+                .fold(false, |new, item| seen.insert(item.id) | new),
+            Err(_) => true,
+        })
+    })
+}
+
+/// The listings on results pages, leaving out those already seen on an earlier page. The
+/// stream ends at the first page with nothing new (past the last page, eBay shows it again),
+/// or after the first error.
+#[cfg(feature = "net")]
+fn new_listings(
+    pages: impl Stream<Item = anyhow::Result<SearchPage>>,
+) -> impl Stream<Item = anyhow::Result<SearchResultSummary>> {
+    let mut seen = HashSet::new();
+    pages
+        .map(move |page| {
+            page.map(|page| {
+                page.items
+                    .into_iter()
+                    .filter(|item| seen.insert(item.id))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .take_while(|items| futures::future::ready(!matches!(items, Ok(items) if items.is_empty())))
+        .flat_map(|items| {
+            futures::stream::iter(match items {
+                Ok(items) => items.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            })
+        })
+}
+
+/// An eBay category, e.g. `Laptops & Netbooks` (175672).
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+pub struct Category {
+    /// The category ID, as in the category's URL.
+    pub id: u64,
+    pub name: String,
+}
+
+impl Category {
+    /// Every active listing in a category (and its subcategories), given its ID, as shown
+    /// on the category's results pages.
+    ///
+    /// Like [`Product::search_pages`], this doesn't visit each listing. The stream ends after
+    /// the last page, or after the first error.
+    #[cfg(feature = "net")]
+    pub fn browse(
+        client: Client<false>,
+        id: u64,
+    ) -> impl Stream<Item = anyhow::Result<SearchResultSummary>> {
+        Self::browse_with(&Endpoints::default(), client, id)
+    }
+
+    /// Like [`Category::browse`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn browse_with(
+        endpoints: &Endpoints,
+        client: Client<false>,
+        id: u64,
+    ) -> impl Stream<Item = anyhow::Result<SearchResultSummary>> {
+        new_listings(results_pages(
+            client,
+            endpoints.url(&format!("/sch/{}/i.html", id)),
+            Vec::new(),
+        ))
+    }
+}
+
+/// An eBay store: everything one seller has listed.
+pub struct Store;
+
+#[cfg(feature = "net")]
+impl Store {
+    /// Every active listing in a store, given the seller's username (as in their store's
+    /// URL, e.g. `https://www.ebay.com/str/<name>`), as shown in the store's item list.
+    ///
+    /// Like [`Product::search_pages`], this doesn't visit each listing. The stream ends after
+    /// the last page, or after the first error.
+    pub fn listings(
+        client: Client<false>,
+        store: &str,
+    ) -> impl Stream<Item = anyhow::Result<SearchResultSummary>> {
+        Self::listings_with(&Endpoints::default(), client, store)
+    }
+
+    /// Like [`Store::listings`], using the given [`Endpoints`].
+    pub fn listings_with(
+        endpoints: &Endpoints,
+        client: Client<false>,
+        store: &str,
+    ) -> impl Stream<Item = anyhow::Result<SearchResultSummary>> {
+        new_listings(results_pages(
+            client,
+            endpoints.url("/sch/m.html"),
+            vec![("_ssn", store.to_string())],
+        ))
+    }
+}
+
+/// How far along an order is, from the status eBay shows for it.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackingState {
+    /// Paid for, but not shipped yet.
+    Processing,
+    Shipped,
+    InTransit,
+    Delivered,
+    Returned,
+    Cancelled,
+    /// A status this module doesn't know.
+    Unknown,
+}
+
+impl TrackingState {
+    /// Read an order status, e.g. `Delivered on Mar 7` or `Estimated delivery Fri, Mar 10`.
+    pub fn from_status(status: &str) -> Self {
+        let status = status.to_lowercase();
+        /* in order, since e.g. a return is shown on a delivered order */
+        [
+            ("cancel", Self::Cancelled),
+            ("refund", Self::Returned),
+            ("return", Self::Returned),
+            ("delivered", Self::Delivered),
+            ("in transit", Self::InTransit),
+            ("out for delivery", Self::InTransit),
+            ("estimated delivery", Self::InTransit),
+            ("not yet shipped", Self::Processing),
+            ("shipped", Self::Shipped),
+            ("paid", Self::Processing),
+            ("processing", Self::Processing),
+        ]
+        .iter()
+        .find(|(word, _)| status.contains(word))
+        .map_or(Self::Unknown, |(_, state)| *state)
+    }
+}
+
+/// One item bought on eBay, from the account's purchase history.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct Purchase {
+    /// The order number, e.g. `12-34567-89012`. An order can have several items.
+    pub order_id: Option<String>,
+    /// The eBay item ID of the listing it was bought from.
+    pub item_id: Option<u64>,
+    pub title: String,
+    /// What was paid for the item.
+    pub price: Option<Money>,
+    /// When the order was placed.
+    pub date: Option<NaiveDate>,
+    /// The seller's username.
+    pub seller: Option<String>,
+    pub tracking: TrackingState,
+    /// The status as eBay shows it, e.g. `Delivered on Mar 7`.
+    pub status: Option<String>,
+}
+
+/// The signed-in account's own orders, from `My eBay > Purchases`.
+///
+/// This needs a [`Client<true>`](crate::common::Client) with a
+/// [`Session`](crate::common::auth::Session) logged in with [`PurchaseHistory::login_flow`],
+/// which takes the `username` and `password` credentials of the `ebay` module. eBay often
+/// asks for a CAPTCHA or a code when signing in from a new device; if it does, sign in with
+/// a browser and copy its cookies into the client's cookie jar instead, which the session
+/// then uses as they are.
+pub struct PurchaseHistory;
 
-                        prod.sponsored = Some(sponsored);
+impl PurchaseHistory {
+    /// How to sign in to eBay.
+    #[cfg(feature = "net")]
+    pub fn login_flow() -> LoginFlow {
+        Self::login_flow_with(&Endpoints::default())
+    }
+
+    /// Like [`PurchaseHistory::login_flow`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn login_flow_with(endpoints: &Endpoints) -> LoginFlow {
+        LoginFlow::Form {
+            url: crate::common::endpoints::join(&endpoints.signin, "/signin/s"),
+            fields: vec![
+                (
+                    "userid".to_string(),
+                    LoginField::Credential("username".to_string()),
+                ),
+                (
+                    "pass".to_string(),
+                    LoginField::Credential("password".to_string()),
+                ),
+            ],
+            failure: Some("that's not a match".to_string()),
+        }
+    }
 
-                        Ok(prod)
+    /// Every purchase in the account's history, newest first, a page at a time. The
+    /// stream ends after the last page, or after the first error.
+    #[cfg(feature = "net")]
+    pub fn purchases(client: Client<true>) -> impl Stream<Item = anyhow::Result<Purchase>> {
+        Self::purchases_with(&Endpoints::default(), client)
+    }
+
+    /// Like [`PurchaseHistory::purchases`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn purchases_with(
+        endpoints: &Endpoints,
+        client: Client<true>,
+    ) -> impl Stream<Item = anyhow::Result<Purchase>> {
+        let url = endpoints.url("/mye/myebay/purchase");
+        let mut seen = HashSet::new();
+        futures::stream::unfold(Some(1), move |page: Option<u32>| {
+            let (url, client) = (url.clone(), client.clone());
+            async move {
+                let page = page?;
+                if page > 1 {
+                    /* be nice! */
+                    tokio::time::sleep(Duration::from_millis(600)).await;
+                }
+
+                let result: anyhow::Result<Vec<Purchase>> = try {
+                    if client.session().is_none() {
+                        Err(anyhow::anyhow!(
+                            "the purchase history needs a client with an eBay session"
+                        ))?;
                     }
-                }))
+                    let text = client
+                        .get(&url)
+                        .await?
+                        .query(&[("page", page)])
+                        .send()
+                        .await?
+                        .error_for_status()?
+                        .text()
+                        .await?;
+                    Self::from_html(&text)?
+                };
+                match result {
+                    Ok(purchases) if purchases.is_empty() => None,
+                    Ok(purchases) => Some((Ok(purchases), Some(page + 1))),
+                    Err(e) => Some((Err(e), None)),
+                }
             }
+        })
+        /* past the last page, eBay shows it again */
+        .map(move |purchases| {
+            purchases.map(|purchases| {
+                purchases
+                    .into_iter()
+                    .filter(|p| seen.insert((p.order_id.clone(), p.item_id, p.title.clone())))
+                    .collect::<Vec<_>>()
+            })
+        })
+        .take_while(|purchases| {
+            futures::future::ready(!matches!(purchases, Ok(purchases) if purchases.is_empty()))
+        })
+        .flat_map(|purchases| {
+            futures::stream::iter(match purchases {
+                Ok(purchases) => purchases.into_iter().map(Ok).collect(),
+                Err(e) => vec![Err(e)],
+            })
+        })
+    }
+
+    /// Read the purchases on one page of the purchase history.
+    ///
+    /// # Errors
+    /// Errors if the page isn't a purchase history page (e.g. it's the sign-in page).
+    pub fn from_html(html: &str) -> anyhow::Result<Vec<Purchase>> {
+        lazy_static! {
+            static ref ITEM_ID: regex::Regex =
+                regex::Regex::new(r"/itm/(?:[^/?]+/)?([0-9]+)").unwrap();
+            static ref ORDER_ID: Field = Field::new(".m-order-card__order-number")
+                .or(".order-number")
+                .capture(regex::Regex::new(r"[0-9]+-[0-9]+-[0-9]+").unwrap());
+            static ref DATE: Field = Field::new(".m-order-card__date")
+                .or(".order-date")
+                .capture(regex::Regex::new(r"[A-Z][a-z]{2} [0-9]{1,2}, [0-9]{4}").unwrap());
+            static ref TITLE: Field = Field::new(".m-item-card__title").or(".item-title");
+            static ref LINK: Field = Field::new(".m-item-card__title a")
+                .or(".item-title a")
+                .attr("href");
+            static ref PRICE: Field = Field::new(".m-item-card__price").or(".item-price");
+            static ref SELLER: Field = Field::new(".m-item-card__seller")
+                .or(".seller-id")
+                .capture(regex::Regex::new(r"(?:[Ss]old by|[Ss]eller:?)?\s*([^\s(]+)").unwrap());
+            static ref STATUS: Field = Field::new(".m-item-card__status")
+                .or(".m-order-card__status")
+                .or(".order-status");
+        }
+
+        let document = kuchiki::parse_html().one(html);
+        let orders = document
+            .select(".m-order-card, .order-r")
+            .ok()
+            .context("invalid selector")?
+            .collect::<Vec<_>>();
+        if orders.is_empty()
+            && document
+                .select_first("#purchase-history, .m-purchase-history")
+                .is_err()
+        {
+            anyhow::bail!("this is not a purchase history page; is the session signed in?");
+        }
+
+        Ok(orders
+            .iter()
+            .flat_map(|order| {
+                let order = order.as_node();
+                let order_id = ORDER_ID.get(order);
+                let date = DATE
+                    .get(order)
+                    .and_then(|date| NaiveDate::parse_from_str(&date, "%b %d, %Y").ok());
+                let items = order
+                    .select(".m-item-card, .item-level-wrap")
+                    .map(|items| items.collect::<Vec<_>>())
+                    .unwrap_or_default();
+                items
+                    .into_iter()
+                    .filter_map(|item| {
+                        let item = item.as_node();
+                        let title = TITLE.get(item)?;
+                        let status = STATUS.get(item).or_else(|| STATUS.get(order));
+                        Some(Purchase {
+                            order_id: order_id.clone(),
+                            item_id: LINK.get(item).and_then(|link| {
+                                ITEM_ID.captures(&link)?.get(1)?.as_str().parse().ok()
+                            }),
+                            title,
+                            price: PRICE.get(item).and_then(|p| Money::from_str(&p).ok()),
+                            date,
+                            seller: SELLER.get(item),
+                            tracking: status
+                                .as_deref()
+                                .map_or(TrackingState::Unknown, TrackingState::from_status),
+                            status,
+                        })
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect())
+    }
+}
+
+/// How many equal-width price ranges a [`PriceGuide`]'s histogram has.
+const HISTOGRAM_BUCKETS: usize = 10;
+
+/// How many results pages a [`PriceGuide`] reads at most, so that a very common query
+/// doesn't take forever.
+#[cfg(feature = "net")]
+const PRICE_GUIDE_PAGES: usize = 20;
+
+/// A range of prices in a [`PriceGuide`]'s histogram, from `low` up to (but not
+/// including) `high`; the last range includes the highest price too.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct PriceBucket {
+    pub low: Money,
+    pub high: Money,
+    pub count: usize,
+}
+
+/// What something actually sold for on eBay: statistics of the prices of sold listings
+/// over a recent time window.
+///
+/// eBay keeps sold listings for about 90 days, so a longer window can't find more.
+/// Listings without a price (e.g. best offers) are counted, but left out of the
+/// statistics.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct PriceGuide {
+    pub query: String,
+    /// The first day of the window.
+    pub from: NaiveDate,
+    /// The last day of the window.
+    pub to: NaiveDate,
+    /// How many listings sold in the window.
+    pub count: usize,
+    pub min: Option<Money>,
+    pub median: Option<Money>,
+    pub mean: Option<Money>,
+    pub max: Option<Money>,
+    /// How many sold at what prices, lowest first; empty if none had a price.
+    pub histogram: Vec<PriceBucket>,
+}
+
+impl PriceGuide {
+    /// The price guide for `query`, from the listings that sold in the last `window`.
+    ///
+    /// # Errors
+    /// Errors if a results page could not be read.
+    #[cfg(feature = "net")]
+    pub async fn for_query(query: &str, window: chrono::Duration) -> anyhow::Result<Self> {
+        Self::for_query_with(&Endpoints::default(), Client::default(), query, window).await
+    }
+
+    /// Like [`PriceGuide::for_query`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub async fn for_query_with(
+        endpoints: &Endpoints,
+        client: Client<false>,
+        query: &str,
+        window: chrono::Duration,
+    ) -> anyhow::Result<Self> {
+        let to = Utc::now().date_naive();
+        let from = to - window;
+        /* sorted by when they ended, newest first, so that the window ends the search */
+        let listings = new_listings(
+            results_pages(
+                client,
+                endpoints.url("/sch/i.html"),
+                vec![
+                    ("_nkw", query.to_string()),
+                    ("LH_Sold", "1".to_string()),
+                    ("LH_Complete", "1".to_string()),
+                    ("_sop", "13".to_string()),
+                ],
+            )
+            .take(PRICE_GUIDE_PAGES),
+        )
+        .take_while(|listing| {
+            futures::future::ready(
+                !matches!(listing, Ok(listing) if listing.sold.is_some_and(|sold| sold < from)),
+            )
+        })
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self::from_listings(query, from, to, &listings))
+    }
+
+    /// Compute the price guide from sold listings, leaving out those that didn't sell
+    /// between `from` and `to`, and sponsored ones.
+    pub fn from_listings(
+        query: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+        listings: &[SearchResultSummary],
+    ) -> Self {
+        let sold = listings
+            .iter()
+            .filter(|listing| !listing.sponsored)
+            .filter(|listing| listing.sold.is_none_or(|sold| from <= sold && sold <= to))
+            .collect::<Vec<_>>();
+
+        let mut prices = sold
+            .iter()
+            .filter_map(|listing| listing.price.clone())
+            .collect::<Vec<_>>();
+        /* only one currency can be summed up; take the first one's */
+        if let Some(currency) = prices.first().map(Money::currency) {
+            prices.retain(|price| price.currency() == currency);
+        }
+        prices.sort_by_key(Money::amount);
+
+        let (min, max) = (prices.first().cloned(), prices.last().cloned());
+        let median = Money::median(&prices);
+        let mean = min.as_ref().map(|min| {
+            let total = prices.iter().map(Money::amount).sum::<Decimal>();
+            Money::new(min.currency(), total / Decimal::from(prices.len())).round()
         });
 
-        stream_stream
-            .take_while(|r| futures::future::ready(r.is_ok()))
-            .filter_map(|r| futures::future::ready(r.ok()))
-            .flatten()
+        let histogram = match (&min, &max) {
+            (Some(min), Some(max)) => {
+                let width = (max.amount() - min.amount()) / Decimal::from(HISTOGRAM_BUCKETS);
+                let mut buckets = (0..HISTOGRAM_BUCKETS)
+                    .map(|i| PriceBucket {
+                        low: Money::new(min.currency(), min.amount() + width * Decimal::from(i))
+                            .round(),
+                        high: Money::new(
+                            min.currency(),
+                            min.amount() + width * Decimal::from(i + 1),
+                        )
+                        .round(),
+                        count: 0,
+                    })
+                    .collect::<Vec<_>>();
+                for price in &prices {
+                    let i = if width.is_zero() {
+                        0
+                    } else {
+                        ((price.amount() - min.amount()) / width)
+                            .floor()
+                            .to_usize()
+                            .unwrap_or_default()
+                            .min(HISTOGRAM_BUCKETS - 1)
+                    };
+                    buckets[i].count += 1;
+                }
+                /* if every price is the same, one bucket says it all */
+                if width.is_zero() {
+                    buckets.truncate(1);
+                }
+                buckets
+            }
+            _ => Vec::new(),
+        };
+
+        Self {
+            query: query.to_string(),
+            from,
+            to,
+            count: sold.len(),
+            min,
+            median,
+            mean,
+            max,
+            histogram,
+        }
+    }
+}
+
+/// A product as shown on a search results page.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct SearchResultSummary {
+    /// The eBay item ID.
+    pub id: u64,
+    /// The title of the listing.
+    pub title: String,
+    /// The price shown in the results, if any. For listings with a price range
+    /// (e.g. `$10.00 to $20.00`), this is the lowest price.
+    pub price: Option<Money>,
+    /// A link to the listing's thumbnail picture.
+    pub thumbnail: Option<String>,
+    /// Whether this is a sponsored listing.
+    pub sponsored: bool,
+    /// When it sold, for the results of a search for sold listings (see [`PriceGuide`]).
+    pub sold: Option<NaiveDate>,
+}
+
+/// One page of search results; see [`Product::search_pages`].
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct SearchPage {
+    /// The number of the page, counting from 1.
+    pub page_no: u32,
+    /// How many results eBay says the whole search has, if it says.
+    pub total_results: Option<u64>,
+    pub items: Vec<SearchResultSummary>,
+}
+
+impl SearchPage {
+    /// The median price of the page's results, leaving out sponsored ones, e.g. for an idea
+    /// of what something goes for (see [`Money::median`]). `None` if none of them have a
+    /// price.
+    pub fn median_price(&self) -> Option<Money> {
+        let prices = self
+            .items
+            .iter()
+            .filter(|item| !item.sponsored)
+            .filter_map(|item| item.price.clone())
+            .collect::<Vec<_>>();
+        Money::median(&prices)
+    }
+
+    /// Read the results from a search results page, which is page `page_no` of the search.
+    ///
+    /// # Errors
+    /// Errors if the page has no results list at all (e.g. it's an error page).
+    pub fn from_html(text: &str, page_no: u32) -> anyhow::Result<Self> {
+        lazy_static! {
+            static ref RE_ITM: regex::Regex =
+                regex::Regex::new(r"https?://[^/]+/itm/([a-zA-Z0-9_\-]+)(?:\?.*)?").unwrap();
+            static ref TOTAL_RESULTS: Field = Field::new(".srp-controls__count-heading")
+                .capture(regex::Regex::new(r"[0-9][0-9,]*").unwrap());
+            static ref SOLD: Field = Field::new(".s-item__caption--signal")
+                .or(".s-item__title--tagblock .POSITIVE")
+                .capture(
+                    regex::Regex::new(r"Sold\s+([A-Z][a-z]{2} [0-9]{1,2}, [0-9]{4})").unwrap()
+                );
+            static ref TITLE: Field = Field::new(".s-item__title");
+            static ref PRICE: Field = Field::new(".s-item__price");
+            static ref THUMBNAILS: [Field; 2] =
+                ["data-src", "src"].map(|key| Field::new(".s-item__image img").attr(key));
+        }
+
+        let document = kuchiki::parse_html().one(text);
+        let main = document
+            .select_first("#mainContent")
+            .ok()
+            .context("could not find main content")?;
+
+        let total_results = TOTAL_RESULTS
+            .number(main.as_node())
+            .ok()
+            .map(|total| total as u64);
+
+        let items = main
+            .as_node()
+            .select(".s-item")
+            .ok()
+            .context("could not find any items")?
+            .filter_map(|n| {
+                let n = n.as_node();
+                let id = n.descendants().find_map(|d| {
+                    let s = d.as_element()?.attributes.borrow();
+                    let a = s.get("href")?;
+                    RE_ITM.captures(a)?.get(1)?.as_str().parse::<u64>().ok()
+                })?;
+                /* new listings have a `New Listing` label in front of the title */
+                let title = TITLE
+                    .get(n)
+                    .map(|title| title.trim_start_matches("New Listing").trim().to_string())
+                    .unwrap_or_default();
+                let price = PRICE.get(n).and_then(|price| Money::from_str(&price).ok());
+                let thumbnail = THUMBNAILS
+                    .iter()
+                    .find_map(|field| field.get(n))
+                    .filter(|url| url.starts_with("http"));
+                let sold = SOLD
+                    .get(n)
+                    .and_then(|sold| NaiveDate::parse_from_str(&sold, "%b %d, %Y").ok());
+                let sponsored = n.select(".s-item__detail").is_ok_and(|mut details| {
+                    details.any(|e| has_hidden_word("Sponsored", e.text_contents().as_str()))
+                });
+
+                Some(SearchResultSummary {
+                    id,
+                    title,
+                    price,
+                    thumbnail,
+                    sponsored,
+                    sold,
+                })
+            })
+            .collect();
+
+        Ok(Self {
+            page_no,
+            total_results,
+            items,
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "net")]
     use futures::StreamExt;
 
-    use crate::common::Client;
+    use crate::common::{Detail, Money, MoneyRange};
 
-    use super::Product;
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use kuchiki::{parse_html, traits::TendrilSink};
 
+    use super::{
+        breadcrumbs, Auction, Category, Condition, ListingType, Product, Quantities, Seller,
+        Shipping, ShippingCost, Variation,
+    };
+    #[cfg(feature = "net")]
+    use super::{
+        Endpoints, PriceGuide, PurchaseHistory, SearchInterrupted, SearchOptions, SeenIds, Store,
+        TrackingState,
+    };
+    #[cfg(feature = "net")]
+    use crate::{
+        checkpoint::SearchCheckpoint,
+        common::{auth::Session, Client, Credentials},
+        testing::MockServer,
+    };
+
+    #[cfg(feature = "net")]
     #[tokio::test]
     async fn test_by_id() {
-        let mut client = Client::default();
+        let mut client = Client::default()
+            .with_cassette(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/ebay.json"))
+            .unwrap();
 
-        let prod = Product::by_id(&mut client, 254625474154).await.unwrap();
+        let prod = Product::by_id(&mut client, 254625474154, Detail::Default)
+            .await
+            .unwrap();
 
         assert_eq!(prod.seller.as_ref().unwrap().name, "bellwetherbooks_usa");
 
-        assert!(prod.seller.as_ref().unwrap().feedback.unwrap() > 0.9);
-        assert!(prod.seller.as_ref().unwrap().feedback.unwrap() < 1.0);
+        assert!(prod.seller.as_ref().unwrap().feedback.unwrap().value > 0.9);
+        assert!(prod.seller.as_ref().unwrap().feedback.unwrap().value < 1.0);
 
         assert!(prod.name.contains("Rust Programming Language"));
+        assert_eq!(prod.gtin(), Some("9781718500440"));
+        assert_eq!(prod.price, Some(Money::from(31.42)));
+        assert_eq!(
+            prod.images,
+            vec![
+                "https://i.ebayimg.com/images/g/3bUAAOSwVYBfEH8Z/s-l1600.jpg",
+                "https://i.ebayimg.com/images/g/Y7YAAOSw3fBfEH8d/s-l1600.jpg",
+            ]
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_by_id_enveloped() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/itm/foo/42",
+                200,
+                r#"<meta http-equiv="refresh" content="0; url=/itm/42?hash=item42">"#,
+            )
+            .mock(
+                "/itm/42",
+                200,
+                r#"<link rel="canonical" href="/itm/42"><h1 id="itemTitle">Graphics card</h1>"#,
+            );
+
+        let endpoints = Endpoints::new(server.uri());
+        let before = Utc::now();
+        let collected =
+            Product::by_id_enveloped_with(&endpoints, &mut Client::default(), 42, Detail::Minimal)
+                .await
+                .unwrap();
+        assert_eq!(collected.source, "ebay");
+        assert_eq!(collected.url, Some(format!("{}/itm/42", server.uri())));
+        assert_eq!(server.requests(), ["/itm/foo/42", "/itm/42?hash=item42"]);
+        assert!(collected.fetched_at >= before);
+        /* the page has no price, which the envelope reports */
+        assert_eq!(
+            collected
+                .parse_report
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["price is missing"]
+        );
+        assert_eq!(collected.map(|product| product.name).data, "Graphics card");
+
+        /* a seller profile that can't be fetched doesn't fail the product */
+        server
+            .mock(
+                "/itm/foo/43",
+                200,
+                r#"<h1 id="itemTitle">Graphics card</h1><div class="si-content"><a href="https://www.ebay.com/usr/bob">bob</a></div>"#,
+            )
+            .mock("/usr/bob", 500, "");
+        let product = Product::by_id_with(&endpoints, &mut Client::default(), 43, Detail::Full)
+            .await
+            .unwrap();
+        let seller = product.seller.unwrap();
+        assert_eq!(seller.name, "bob");
+        assert_eq!(seller.items_sold, None);
+    }
+
+    #[test]
+    fn test_gtin() {
+        let mut prod = Product::default();
+        assert_eq!(prod.gtin(), None);
+
+        prod.item_specifics
+            .insert("UPC".to_string(), "Does not apply".to_string());
+        assert_eq!(prod.gtin(), None);
+
+        prod.item_specifics
+            .insert("EAN".to_string(), "9781718500440".to_string());
+        assert_eq!(prod.gtin(), Some("9781718500440"));
+        assert_eq!(prod.isbn().as_deref(), Some("9781718500440"));
+
+        prod.item_specifics
+            .insert("ISBN-10".to_string(), "1-7185-0044-0".to_string());
+        assert_eq!(prod.isbn().as_deref(), Some("1718500440"));
+    }
+
+    #[test]
+    fn test_from_html() {
+        let page = r#"<html><head>
+            <link rel="canonical" href="https://www.ebay.com/itm/254625474154">
+        </head><body>
+            <h1 id="itemTitle"><span>Details about</span> The Rust Programming Language</h1>
+            <div class="mainPrice">
+                <span itemprop="price" content="31.42">US $31.42</span>
+                <span itemprop="priceCurrency" content="USD"></span>
+            </div>
+        </body></html>"#;
+        assert_eq!(Product::id_from_html(page), Some(254625474154));
+        let prod = Product::from_html(page, 254625474154, Detail::Minimal).unwrap();
+        assert_eq!(prod.id, 254625474154);
+        assert_eq!(prod.name, "The Rust Programming Language");
+        assert_eq!(prod.price, Some(Money::from(31.42)));
+        assert_eq!(prod.price_range, None);
+
+        let page = r#"<h1 id="itemTitle">T-shirt</h1>
+            <div class="mainPrice">
+                <span itemprop="price" content="10.99">US $10.99 to $24.99</span>
+                <span itemprop="priceCurrency" content="USD"></span>
+            </div>"#;
+        let prod = Product::from_html(page, 1, Detail::Default).unwrap();
+        assert_eq!(prod.price, None);
+        assert_eq!(
+            prod.price_range,
+            Some(MoneyRange::new(Money::from(24.99), Money::from(10.99)))
+        );
+        assert_eq!(prod.listing_type, Some(ListingType::BuyItNow));
+
+        assert_eq!(
+            Product::id_from_html("<h1 id=\"itemTitle\">No link</h1>"),
+            None
+        );
+        assert!(Product::from_html("<p>gone</p>", 1, Detail::Minimal).is_err());
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let page = r#"<h1 id="itemTitle">Laptop</h1>
+            <div class="mainPrice"><span itemprop="price">Best offer</span></div>
+            <div class="x-item-condition-text"><span class="ux-textspans">Used</span></div>"#;
+        let report = Product::from_html_with_report(page, 1, Detail::Minimal).unwrap();
+        assert_eq!(
+            report
+                .parse_report
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["price could not be read: Best offer"]
+        );
+
+        let report = Product::from_html_with_report(page, 1, Detail::Default).unwrap();
+        assert_eq!(report.data.condition, Some(Condition::Used));
+        assert_eq!(
+            report
+                .parse_report
+                .iter()
+                .map(|issue| issue.field.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "seller",
+                "item_specifics",
+                "shipping",
+                "images",
+                "breadcrumbs",
+                "price"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_condition() {
+        for (label, condition) in [
+            ("New with tags", Condition::New),
+            ("Brand New", Condition::New),
+            ("New other (see details)", Condition::OpenBox),
+            ("Open box", Condition::OpenBox),
+            ("Certified - Refurbished", Condition::CertifiedRefurbished),
+            ("Very Good – Refurbished", Condition::VeryGoodRefurbished),
+            ("Good - Refurbished", Condition::GoodRefurbished),
+            ("Seller refurbished", Condition::SellerRefurbished),
+            ("Pre-owned", Condition::Used),
+            ("Like New", Condition::Used),
+            ("For parts or not working", Condition::ForParts),
+        ] {
+            assert_eq!(Condition::from_label(label), Some(condition), "{}", label);
+        }
+        assert_eq!(Condition::from_label("Mint"), None);
+
+        let page = r#"<h1 id="itemTitle">Laptop</h1>
+            <div class="x-item-condition-text"><span class="ux-textspans">Used</span></div>
+            <div class="ux-labels-values">
+                <div class="ux-labels-values__labels">Seller Notes:</div>
+                <div class="ux-labels-values__values">“Small scratch on the lid”</div>
+            </div>"#;
+        let prod = Product::from_html(page, 1, Detail::Default).unwrap();
+        assert_eq!(prod.condition, Some(Condition::Used));
+        assert_eq!(
+            prod.condition_description.as_deref(),
+            Some("Small scratch on the lid")
+        );
+
+        /* only in the item specifics */
+        let page = r#"<h1 id="itemTitle">Laptop</h1>
+            <table class="itemAttr"><tr>
+                <td class="attrLabels">Condition:</td>
+                <td>For parts or not working: An item that does not function as intended. See all condition definitions</td>
+            </tr></table>"#;
+        let prod = Product::from_html(page, 1, Detail::Default).unwrap();
+        assert_eq!(prod.condition, Some(Condition::ForParts));
+        assert_eq!(
+            prod.condition_description.as_deref(),
+            Some("An item that does not function as intended.")
+        );
+        let prod = Product::from_html(page, 1, Detail::Minimal).unwrap();
+        assert_eq!((prod.condition, prod.condition_description), (None, None));
+    }
+
+    #[test]
+    fn test_variations() {
+        let page = r#"<h1 id="itemTitle">T-shirt</h1>
+            <script>$vi_init({"MSKU":{"selectMenus":[
+                {"displayLabel":"Size","menuItemValueIds":[1,2]},
+                {"displayLabel":"Color","menuItemValueIds":[3]}
+            ],"menuItemMap":{
+                "1":{"valueId":1,"valueName":"S"},
+                "2":{"valueId":2,"valueName":"M"},
+                "3":{"valueId":3,"valueName":"Blue"}
+            },"variationCombinations":{"2_3":502,"1_3":501},"variationsMap":{
+                "501":{"binModel":{"price":{"value":{"value":10.99,"currency":"USD"}}},
+                       "quantity":{"availableQuantity":4}},
+                "502":{"binModel":{"price":{"value":{"value":24.99,"currency":"USD"}}},
+                       "outOfStock":true}
+            }},"other":[1,2]});</script>"#;
+        let variations = Product::from_html(page, 1, Detail::Default)
+            .unwrap()
+            .variations;
+        assert_eq!(
+            variations,
+            vec![
+                Variation {
+                    attributes: [("Size", "S"), ("Color", "Blue")]
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                    price: Some(Money::from(10.99)),
+                    quantity: Some(4),
+                },
+                Variation {
+                    attributes: [("Size", "M"), ("Color", "Blue")]
+                        .iter()
+                        .map(|(k, v)| (k.to_string(), v.to_string()))
+                        .collect(),
+                    price: Some(Money::from(24.99)),
+                    quantity: Some(0),
+                },
+            ]
+        );
+
+        assert!(Product::from_html(page, 1, Detail::Minimal)
+            .unwrap()
+            .variations
+            .is_empty());
+    }
+
+    #[test]
+    fn test_seller_profile() {
+        let mut seller = Seller {
+            name: "bellwetherbooks_usa".to_string(),
+            ..Default::default()
+        };
+        seller.parse_profile(
+            r#"
+            <div class="str-seller-card__stats">
+                <span>99.6% positive feedback</span>
+                <span>1.2M items sold</span>
+            </div>
+            <div class="str-about"><span>Joined Jun 2009</span></div>
+        "#,
+        );
+        assert_eq!(seller.items_sold, Some(1_200_000));
+        assert_eq!(seller.member_since.as_deref(), Some("Jun 2009"));
+    }
+
+    #[test]
+    fn test_auction() {
+        let now = Utc.with_ymd_and_hms(2021, 12, 20, 12, 0, 0).unwrap();
+
+        let node = parse_html().one(
+            r#"
+            <span id="prcIsum_bidPrice">US $41.00</span>
+            <a id="vi-VR-bid-lnk"><span id="qty-test">12</span> bids</a>
+            <span id="vi-cdown_timeLeft">2d 03h</span>
+            <span id="prcIsum">US $99.99</span>
+        "#,
+        );
+        let auction = Auction::from_item_page(&node, now).unwrap();
+        assert_eq!(auction.bids, Some(12));
+        assert_eq!(
+            auction.ends_at,
+            Some(Utc.with_ymd_and_hms(2021, 12, 22, 15, 0, 0).unwrap())
+        );
+        assert_eq!(auction.buy_it_now_price, Some(Money::from(99.99)));
+        assert_eq!(auction.listing_type(), ListingType::Both);
+
+        let node = parse_html().one(
+            r#"
+            <div class="x-bid-price"><span>US $5.50</span></div>
+            <div class="x-bid-count"><span>1,204 bids</span></div>
+            <span class="timeMs" timems="1640044800000">Dec 21, 2021</span>
+        "#,
+        );
+        let auction = Auction::from_item_page(&node, now).unwrap();
+        assert_eq!(auction.bids, Some(1204));
+        assert_eq!(
+            auction.ends_at,
+            Some(Utc.with_ymd_and_hms(2021, 12, 21, 0, 0, 0).unwrap())
+        );
+        assert_eq!(auction.listing_type(), ListingType::Auction);
+
+        let node = parse_html().one(
+            r#"
+            <span id="prcIsum_bidPrice">US $41.00</span>
+            <span class="vi-tm-left">(Dec 21, 2021 08:00:00 PST)</span>
+            <span id="vi-cdown_timeLeft">20h 0m</span>
+        "#,
+        );
+        assert_eq!(
+            Auction::from_item_page(&node, now).unwrap().ends_at,
+            Some(Utc.with_ymd_and_hms(2021, 12, 21, 16, 0, 0).unwrap())
+        );
+
+        let node = parse_html().one(r#"<span id="prcIsum">US $99.99</span>"#);
+        assert!(Auction::from_item_page(&node, now).is_none());
+    }
+
+    #[test]
+    fn test_shipping() {
+        let node = parse_html().one(
+            r#"
+            <span id="fshippingCost"><span>$5.99</span></span>
+            <div id="itemLocation"><div>Item location:</div><div>Austin, Texas, United States</div></div>
+            <span class="vi-acc-del-range"><b>Thu. Dec. 30 and Tue. Jan. 4</b></span>
+        "#,
+        );
+
+        let shipping =
+            Shipping::from_item_page(&node, NaiveDate::from_ymd_opt(2021, 12, 20).unwrap())
+                .unwrap();
+        assert!(matches!(shipping.cost, Some(ShippingCost::Paid(_))));
+        assert_eq!(
+            shipping.ships_from.as_deref(),
+            Some("Austin, Texas, United States")
+        );
+        let estimate = shipping.delivery_estimate.unwrap();
+        assert_eq!(
+            estimate.earliest,
+            NaiveDate::from_ymd_opt(2021, 12, 30).unwrap()
+        );
+        assert_eq!(
+            estimate.latest,
+            NaiveDate::from_ymd_opt(2022, 1, 4).unwrap()
+        );
+
+        let node = parse_html().one(r#"<span id="fshippingCost">FREE</span>"#);
+        let shipping =
+            Shipping::from_item_page(&node, NaiveDate::from_ymd_opt(2021, 12, 20).unwrap())
+                .unwrap();
+        assert!(matches!(shipping.cost, Some(ShippingCost::Free)));
+    }
+
+    #[test]
+    fn test_breadcrumbs() {
+        let node = parse_html().one(
+            r#"
+            <nav class="seo-breadcrumbs-container"><ul>
+                <li><a href="https://www.ebay.com/sch/i.html?_nkw=laptop"><span>Back to search results</span></a></li>
+                <li><a href="https://www.ebay.com/b/Computers-Tablets-Networking/58058/bn_1865247"><span>Computers/Tablets &amp; Networking</span></a></li>
+                <li><a href="https://www.ebay.com/b/Laptops-Netbooks/175672/bn_1648276"><span>Laptops &amp; Netbooks</span></a></li>
+                <li><a href="https://www.ebay.com/b/PC-Laptops-Netbooks/177/bn_317584?_trksid=p1"><span>PC Laptops &amp; Netbooks</span></a></li>
+            </ul></nav>
+        "#,
+        );
+        assert_eq!(
+            breadcrumbs(&node),
+            vec![
+                Category {
+                    id: 58058,
+                    name: "Computers/Tablets & Networking".to_string()
+                },
+                Category {
+                    id: 175672,
+                    name: "Laptops & Netbooks".to_string()
+                },
+                Category {
+                    id: 177,
+                    name: "PC Laptops & Netbooks".to_string()
+                },
+            ]
+        );
+
+        let node = parse_html().one(
+            r#"<ul id="vi-VR-brumb-lnkLst"><li><a href="https://www.ebay.com/sch/58058/i.html">Computers</a></li></ul>"#,
+        );
+        assert_eq!(breadcrumbs(&node)[0].id, 58058);
+        assert!(breadcrumbs(&parse_html().one("<h1>no trail</h1>")).is_empty());
+    }
+
+    #[test]
+    fn test_quantities() {
+        let node = parse_html().one(
+            r#"
+            <div class="x-quantity__availability">
+                <span class="ux-textspans">More than 10 available</span>
+                <span class="ux-textspans">1,204 sold</span>
+            </div>
+            <div class="d-urgency"><span class="ux-textspans">25 people are watching this.</span></div>
+        "#,
+        );
+        assert_eq!(
+            Quantities::from_item_page(&node),
+            Quantities {
+                available: Some(10),
+                sold: Some(1204),
+                watchers: Some(25),
+            }
+        );
+
+        let node = parse_html().one(
+            r#"
+            <span id="qtySubTxt"><span>Last one</span></span>
+            <span class="vi-qtyS-hot-red"><a>3 sold</a></span>
+            <span id="vi-bybox-watchers"><span>7 watchers</span></span>
+        "#,
+        );
+        assert_eq!(
+            Quantities::from_item_page(&node),
+            Quantities {
+                available: Some(1),
+                sold: Some(3),
+                watchers: Some(7),
+            }
+        );
+
+        let node = parse_html().one(r#"<div class="d-urgency">Almost gone</div>"#);
+        assert_eq!(Quantities::from_item_page(&node), Quantities::default());
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_search_pages() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/sch/i.html?_nkw=cpu&_pgn=1",
+                200,
+                r#"
+                <div id="mainContent">
+                    <h1 class="srp-controls__count-heading"><span>1,204</span> results for cpu</h1>
+                    <ul>
+                        <li class="s-item">
+                            <div class="s-item__image"><img src="https://i.ebayimg.com/images/g/a/s-l225.jpg"></div>
+                            <a href="https://www.ebay.com/itm/254625474154?hash=item1">
+                                <h3 class="s-item__title"><span>New Listing</span>AMD Ryzen 5 5600X</h3>
+                            </a>
+                            <span class="s-item__price">$10.00 to $20.00</span>
+                        </li>
+                        <li class="s-item">
+                            <a href="https://www.ebay.com/itm/1234"><h3 class="s-item__title">Intel i7</h3></a>
+                            <span class="s-item__detail">Sponsored</span>
+                        </li>
+                    </ul>
+                </div>
+            "#,
+            )
+            .mock(
+                "/sch/i.html?_nkw=cpu&_pgn=2",
+                200,
+                r#"<div id="mainContent"><ul></ul></div>"#,
+            );
+
+        let endpoints = Endpoints::new(server.uri());
+        let pages = Product::search_pages_with(&endpoints, "cpu")
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(pages.len(), 1);
+
+        let page = pages[0].as_ref().unwrap();
+        assert_eq!(page.page_no, 1);
+        assert_eq!(page.total_results, Some(1204));
+        let ids = page.items.iter().map(|item| item.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![254625474154, 1234]);
+
+        let first = &page.items[0];
+        assert_eq!(first.title, "AMD Ryzen 5 5600X");
+        assert_eq!(first.price, Some(Money::from(10.0)));
+        assert_eq!(
+            first.thumbnail.as_deref(),
+            Some("https://i.ebayimg.com/images/g/a/s-l225.jpg")
+        );
+        assert!(!first.sponsored);
+        assert!(page.items[1].sponsored);
+        assert!(page.items[1].price.is_none());
+        assert_eq!(page.median_price(), Some(Money::from(10.0)));
+
+        /* past the last page, eBay shows it again */
+        let last = r#"<div id="mainContent"><ul><li class="s-item"><a href="https://www.ebay.com/itm/42"><h3 class="s-item__title">GPU</h3></a></li></ul></div>"#;
+        server.mock("/sch/i.html?_nkw=gpu&_pgn=1", 200, last).mock(
+            "/sch/i.html?_nkw=gpu&_pgn=2",
+            200,
+            last,
+        );
+        let pages = Product::search_pages_with(&endpoints, "gpu")
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(pages.len(), 1);
+        assert_eq!(
+            server.requests()[2..],
+            ["/sch/i.html?_nkw=gpu&_pgn=1", "/sch/i.html?_nkw=gpu&_pgn=2"]
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_price_guide() {
+        let today = Utc::now().date_naive();
+        let page = |items: &[(u64, &str, i64)]| {
+            let items = items
+                .iter()
+                .map(|(id, price, days_ago)| {
+                    let sold = (today - chrono::Duration::days(*days_ago)).format("%b %-d, %Y");
+                    format!(
+                        r#"<li class="s-item"><div class="s-item__caption--signal"><span>Sold  {}</span></div><a href="https://www.ebay.com/itm/{}"><h3 class="s-item__title">GPU</h3></a><span class="s-item__price">{}</span></li>"#,
+                        sold, id, price
+                    )
+                })
+                .collect::<String>();
+            format!(r#"<div id="mainContent"><ul>{}</ul></div>"#, items)
+        };
+        let server = MockServer::start().await.unwrap();
+        let url = "/sch/i.html?_nkw=gpu&LH_Sold=1&LH_Complete=1&_sop=13&_pgn=";
+        server
+            .mock(
+                &format!("{}1", url),
+                200,
+                &page(&[(1, "$100.00", 0), (2, "$300.00", 1), (3, "$150.00", 2)]),
+            )
+            .mock(
+                &format!("{}2", url),
+                200,
+                &page(&[(4, "$200.00", 5), (5, "$50.00", 40)]),
+            )
+            .mock(&format!("{}3", url), 200, &page(&[(6, "$10.00", 41)]));
+
+        let endpoints = Endpoints::new(server.uri());
+        let guide = PriceGuide::for_query_with(
+            &endpoints,
+            Client::default(),
+            "gpu",
+            chrono::Duration::days(30),
+        )
+        .await
+        .unwrap();
+        /* the listing sold 40 days ago ends the search */
+        assert_eq!(server.requests().len(), 2);
+        assert_eq!(guide.count, 4);
+        assert_eq!(guide.from, today - chrono::Duration::days(30));
+        assert_eq!(guide.min, Some(Money::from(100.0)));
+        assert_eq!(guide.median, Some(Money::from(175.0)));
+        assert_eq!(guide.mean, Some(Money::from(187.5)));
+        assert_eq!(guide.max, Some(Money::from(300.0)));
+        assert_eq!(guide.histogram.len(), 10);
+        assert_eq!(guide.histogram[0].high, Money::from(120.0));
+        assert_eq!(
+            guide.histogram.iter().map(|b| b.count).collect::<Vec<_>>(),
+            vec![1, 0, 1, 0, 0, 1, 0, 0, 0, 1]
+        );
+
+        let empty = PriceGuide::from_listings("gpu", today, today, &[]);
+        assert_eq!(
+            (empty.count, empty.median, empty.histogram.len()),
+            (0, None, 0)
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    fn test_seen_ids() {
+        let mut seen = SeenIds::new(2);
+        assert!(seen.insert(1));
+        assert!(seen.insert(2));
+        assert!(!seen.insert(1));
+        /* 1 is forgotten to make room */
+        assert!(seen.insert(3));
+        assert!(seen.insert(1));
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_search_options() {
+        /* item 3 is sponsored */
+        let results = |ids: &[u64]| {
+            let items = ids
+                .iter()
+                .map(|id| {
+                    format!(
+                        r#"<li class="s-item"><a href="https://www.ebay.com/itm/{0}"><h3 class="s-item__title">Item {0}</h3></a>{1}</li>"#,
+                        id,
+                        if *id == 3 { r#"<span class="s-item__detail">Sponsored</span>"# } else { "" }
+                    )
+                })
+                .collect::<String>();
+            format!(r#"<div id="mainContent"><ul>{}</ul></div>"#, items)
+        };
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/sch/i.html?_nkw=cpu&_pgn=1", 200, &results(&[1, 2]))
+            .mock("/sch/i.html?_nkw=cpu&_pgn=2", 200, &results(&[2, 3]))
+            .mock("/sch/i.html?_nkw=cpu&_pgn=3", 200, &results(&[]));
+        for id in 1..=3 {
+            server.mock(
+                &format!("/itm/foo/{}", id),
+                200,
+                &format!(r#"<h1 id="itemTitle">Item {}</h1>"#, id),
+            );
+        }
+
+        let endpoints = Endpoints::new(server.uri());
+        let ids = |options| async {
+            Product::search_with_options(&endpoints, "cpu", Detail::Minimal, options)
+                .map(|product| product.unwrap().id)
+                .collect::<Vec<_>>()
+                .await
+        };
+        assert_eq!(ids(SearchOptions::default()).await, vec![1, 2, 3]);
+        let options = SearchOptions {
+            include_duplicates: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(options).await, vec![1, 2, 2, 3]);
+
+        let requests = server.requests().len();
+        let options = SearchOptions {
+            skip_sponsored: true,
+            ..Default::default()
+        };
+        assert_eq!(ids(options).await, vec![1, 2]);
+        assert!(!server.requests()[requests..].contains(&"/itm/foo/3".to_string()));
+    }
+
+    /// A search results page with the given items, and whether each is sponsored.
+    #[cfg(feature = "net")]
+    fn results_page(items: &[(u64, bool)]) -> String {
+        let items = items
+            .iter()
+            .map(|(id, sponsored)| {
+                format!(
+                    r#"<li class="s-item"><a href="https://www.ebay.com/itm/{0}"><h3 class="s-item__title">Item {0}</h3></a>{1}</li>"#,
+                    id,
+                    if *sponsored { r#"<span class="s-item__detail">Sponsored</span>"# } else { "" }
+                )
+            })
+            .collect::<String>();
+        format!(r#"<div id="mainContent"><ul>{}</ul></div>"#, items)
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_search_end() {
+        let server = MockServer::start().await.unwrap();
+        server
+            /* eBay shows the last page again past it */
+            .mock(
+                "/sch/i.html?_nkw=gpu&_pgn=1",
+                200,
+                &results_page(&[(1, false), (2, false)]),
+            )
+            .mock(
+                "/sch/i.html?_nkw=gpu&_pgn=2",
+                200,
+                &results_page(&[(1, false), (2, false)]),
+            )
+            /* a page of only sponsored listings isn't the end */
+            .mock(
+                "/sch/i.html?_nkw=ram&_pgn=1",
+                200,
+                &results_page(&[(3, true)]),
+            )
+            .mock(
+                "/sch/i.html?_nkw=ram&_pgn=2",
+                200,
+                &results_page(&[(4, false)]),
+            )
+            .mock("/sch/i.html?_nkw=ram&_pgn=3", 200, &results_page(&[]));
+        for id in 1..=4 {
+            server.mock(
+                &format!("/itm/foo/{}", id),
+                200,
+                &format!(r#"<h1 id="itemTitle">Item {}</h1>"#, id),
+            );
+        }
+
+        let endpoints = Endpoints::new(server.uri());
+        let ids = |query, options| {
+            Product::search_with_options(&endpoints, query, Detail::Minimal, options)
+                .map(|product| product.unwrap().id)
+                .collect::<Vec<_>>()
+        };
+        let include_duplicates = SearchOptions {
+            include_duplicates: true,
+            ..Default::default()
+        };
+        assert_eq!(ids("gpu", include_duplicates).await, vec![1, 2]);
+        assert_eq!(ids("gpu", SearchOptions::default()).await, vec![1, 2]);
+        let skip_sponsored = SearchOptions {
+            skip_sponsored: true,
+            ..Default::default()
+        };
+        assert_eq!(ids("ram", skip_sponsored).await, vec![4]);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_search_end_few_seen() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/sch/i.html?_nkw=ssd&_pgn=1",
+                200,
+                &results_page(&[(1, false), (2, false), (3, false)]),
+            )
+            .mock(
+                "/sch/i.html?_nkw=ssd&_pgn=2",
+                200,
+                &results_page(&[(4, false), (5, false)]),
+            )
+            .mock(
+                "/sch/i.html?_nkw=ssd&_pgn=3",
+                200,
+                &results_page(&[(4, false), (5, false)]),
+            );
+        for id in 1..=5 {
+            server.mock(
+                &format!("/itm/foo/{}", id),
+                200,
+                &format!(r#"<h1 id="itemTitle">Item {}</h1>"#, id),
+            );
+        }
+
+        /* fewer IDs are remembered than there are on a page, so they can't tell the end */
+        let endpoints = Endpoints::new(server.uri());
+        let options = SearchOptions {
+            max_seen: 1,
+            ..Default::default()
+        };
+        let ids = Product::search_with_options(&endpoints, "ssd", Detail::Minimal, options)
+            .map(|product| product.unwrap().id)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_search_resume() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/sch/i.html?_nkw=cpu&_pgn=2",
+                200,
+                &results_page(&[(1, false), (2, false)]),
+            )
+            .mock(
+                "/sch/i.html?_nkw=cpu&_pgn=3",
+                200,
+                &results_page(&[(3, false)]),
+            )
+            .mock(
+                "/sch/i.html?_nkw=cpu&_pgn=4",
+                500,
+                "<p>Something went wrong</p>",
+            );
+        for id in 1..=3 {
+            server.mock(
+                &format!("/itm/foo/{}", id),
+                200,
+                &format!(r#"<h1 id="itemTitle">Item {}</h1>"#, id),
+            );
+        }
+
+        /* everything on page 2 was collected before */
+        let mut checkpoint = SearchCheckpoint::new("cpu");
+        checkpoint.record(2, 1, Product::default());
+        checkpoint.record(2, 2, Product::default());
+
+        let endpoints = Endpoints::new(server.uri());
+        let results = Product::search_resume_with(
+            &endpoints,
+            "cpu",
+            Detail::Minimal,
+            SearchOptions::default(),
+            &checkpoint,
+        )
+        .collect::<Vec<_>>()
+        .await;
+        assert_eq!(results.len(), 2);
+        let (page, product) = results[0].as_ref().unwrap();
+        assert_eq!((*page, product.id), (3, 3));
+        let error = results[1].as_ref().err().unwrap();
+        assert_eq!(
+            error.downcast_ref::<SearchInterrupted>(),
+            Some(&SearchInterrupted { page: 4 })
+        );
+        assert!(!server
+            .requests()
+            .iter()
+            .any(|r| r.starts_with("/itm/foo/1")));
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_listings() {
+        let page = |ids: &[u64]| {
+            let items = ids
+                .iter()
+                .map(|id| {
+                    format!(
+                        r#"<li class="s-item"><a href="https://www.ebay.com/itm/{0}"><h3 class="s-item__title">Laptop {0}</h3></a><span class="s-item__price">$199.99</span></li>"#,
+                        id
+                    )
+                })
+                .collect::<String>();
+            format!(r#"<div id="mainContent"><ul>{}</ul></div>"#, items)
+        };
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/sch/m.html?_ssn=refurbco&_pgn=1", 200, &page(&[1, 2]))
+            .mock("/sch/m.html?_ssn=refurbco&_pgn=2", 200, &page(&[3]))
+            .mock("/sch/m.html?_ssn=refurbco&_pgn=3", 200, &page(&[3]));
+
+        let endpoints = Endpoints::new(server.uri());
+        let listings = Store::listings_with(&endpoints, Client::default(), "refurbco")
+            .map(|listing| listing.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        let ids = listings
+            .iter()
+            .map(|listing| listing.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(listings[0].price, Some(Money::from(199.99)));
+        assert_eq!(server.requests().len(), 3);
+
+        server
+            .mock("/sch/175672/i.html?_pgn=1", 200, &page(&[4, 5]))
+            .mock("/sch/175672/i.html?_pgn=2", 200, &page(&[5, 4]));
+        let ids = Category::browse_with(&endpoints, Client::default(), 175672)
+            .map(|listing| listing.unwrap().id)
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(ids, vec![4, 5]);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_purchase_history() {
+        let order = |id: &str, items: &[(u64, &str)]| {
+            let items = items
+                .iter()
+                .map(|(item, status)| {
+                    format!(
+                        r#"<div class="m-item-card"><div class="m-item-card__title"><a href="https://www.ebay.com/itm/{0}">Laptop {0}</a></div><div class="m-item-card__price">$199.99</div><div class="m-item-card__seller">Sold by refurbco (1234)</div><div class="m-item-card__status">{1}</div></div>"#,
+                        item, status
+                    )
+                })
+                .collect::<String>();
+            format!(
+                r#"<div class="m-order-card"><span class="m-order-card__date">Order date Mar 3, 2023</span><span class="m-order-card__order-number">Order number: {}</span>{}</div>"#,
+                id, items
+            )
+        };
+        let page = |orders: &[String]| {
+            format!(
+                r#"<div class="m-purchase-history">{}</div>"#,
+                orders.concat()
+            )
+        };
+
+        let first = page(&[order(
+            "12-34567-89012",
+            &[(1, "Delivered on Mar 7"), (2, "Not yet shipped")],
+        )]);
+        let purchases = PurchaseHistory::from_html(&first).unwrap();
+        assert_eq!(purchases.len(), 2);
+        assert_eq!(purchases[0].order_id.as_deref(), Some("12-34567-89012"));
+        assert_eq!(purchases[0].item_id, Some(1));
+        assert_eq!(purchases[0].title, "Laptop 1");
+        assert_eq!(purchases[0].price, Some(Money::from(199.99)));
+        assert_eq!(
+            purchases[0].date,
+            Some(NaiveDate::from_ymd_opt(2023, 3, 3).unwrap())
+        );
+        assert_eq!(purchases[0].seller.as_deref(), Some("refurbco"));
+        assert_eq!(purchases[0].tracking, TrackingState::Delivered);
+        assert_eq!(purchases[1].tracking, TrackingState::Processing);
+        assert_eq!(
+            TrackingState::from_status("Return started"),
+            TrackingState::Returned
+        );
+        /* e.g. the sign-in page, which eBay shows when the session is over */
+        assert!(PurchaseHistory::from_html("<form id=signin-form></form>").is_err());
+
+        let server = MockServer::start().await.unwrap();
+        let second = page(&[order("98-76543-21098", &[(3, "In transit")])]);
+        server
+            .mock("/mye/myebay/purchase?page=1", 200, &first)
+            .mock("/mye/myebay/purchase?page=2", 200, &second)
+            .mock("/mye/myebay/purchase?page=3", 200, &second);
+        let endpoints = Endpoints::new(server.uri());
+
+        /* a session kept from an earlier run, so there's no signing in */
+        let client = Client::<true>::default();
+        client
+            .cookie_jar()
+            .unwrap()
+            .insert(
+                &"https://signin.ebay.com/".parse().unwrap(),
+                "ebay=session; Domain=ebay.com; Path=/",
+            )
+            .unwrap();
+        let session = Session::new(
+            "ebay",
+            PurchaseHistory::login_flow(),
+            Credentials::from_sources(None, |_| None),
+        );
+        let ids = PurchaseHistory::purchases_with(&endpoints, client.with_session(session))
+            .map(|purchase| purchase.unwrap().item_id.unwrap())
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(server.requests().len(), 3);
+
+        let without_session =
+            PurchaseHistory::purchases_with(&endpoints, Client::<true>::default())
+                .collect::<Vec<_>>()
+                .await;
+        assert!(without_session.len() == 1 && without_session[0].is_err());
     }
 
+    #[cfg(feature = "net")]
     #[tokio::test]
     #[ignore]
     async fn test_search() {
-        let products = Product::search("cpu").take(20).collect::<Vec<_>>().await;
+        let products = Product::search("cpu", Detail::Default)
+            .take(20)
+            .collect::<Vec<_>>()
+            .await;
         let products = products
             .into_iter()
             .filter_map(|r| r.ok())
@@ -0,0 +1,492 @@
+#[cfg(feature = "net")]
+use std::time::Duration;
+use std::{collections::HashSet, convert::TryInto};
+
+use anyhow::Context;
+#[cfg(feature = "net")]
+use futures::{Stream, StreamExt};
+use kuchiki::{parse_html, traits::TendrilSink, NodeRef};
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[cfg(feature = "net")]
+use crate::common::{Client, Collected};
+use crate::{
+    common::{meta::PageMeta, FieldIssue, Money, WithReport},
+    schema_org::Scope,
+    schemas::common::Rating,
+};
+
+/// The Etsy site the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://www.etsy.com";
+
+crate::common::endpoints::endpoints!();
+
+/// An Etsy listing.
+#[derive(Serialize, JsonSchema, Clone)]
+pub struct Listing {
+    pub id: u64,
+    pub title: String,
+    pub price: Option<Money>,
+    /// The name of the shop selling the listing.
+    pub shop: Option<String>,
+    /// The shop's reviews, as shown on the listing.
+    pub rating: Option<Rating>,
+    /// Where the listing ships from, as written on the listing (e.g. `United States`).
+    pub ships_from: Option<String>,
+    /// Links to the listing's pictures.
+    pub images: Vec<String>,
+}
+
+impl Listing {
+    /// Find an Etsy listing using its ID.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the response could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id(client: &mut Client<false>, id: u64) -> anyhow::Result<Self> {
+        Self::by_id_with(&Endpoints::default(), client, id).await
+    }
+
+    /// Like [`Listing::by_id`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the response could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn by_id_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        id: u64,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::by_id_enveloped_with(endpoints, client, id)
+            .await?
+            .data)
+    }
+
+    /// Like [`Listing::by_id`], recording where and when the listing was collected.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the response could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id_enveloped(
+        client: &mut Client<false>,
+        id: u64,
+    ) -> anyhow::Result<Collected<Self>> {
+        Self::by_id_enveloped_with(&Endpoints::default(), client, id).await
+    }
+
+    /// Like [`Listing::by_id_enveloped`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the response could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id_enveloped_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        id: u64,
+    ) -> anyhow::Result<Collected<Self>> {
+        let url = endpoints.url(&format!("/listing/{}", id));
+        let page = client.get_page(&url).await?.error_for_status()?;
+        let listing = Self::from_document(&page.document, id)?;
+        Ok(Collected::from_page_with_report("etsy", &page, listing))
+    }
+
+    /// Parse the page of the listing `id`, using its schema.org microdata where possible, and
+    /// else its OpenGraph metadata (see [`PageMeta`]), if that says the page is a product.
+    ///
+    /// # Errors
+    /// Errors if the page has no title (e.g. the listing is gone).
+    pub fn from_html(html: &str, id: u64) -> anyhow::Result<Self> {
+        Self::from_html_with_report(html, id).map(WithReport::into_inner)
+    }
+
+    /// Like [`Listing::from_html`], with a report of the fields the page didn't have, or
+    /// that could not be read.
+    ///
+    /// # Errors
+    /// Errors if the page has no title (e.g. the listing is gone).
+    pub fn from_html_with_report(html: &str, id: u64) -> anyhow::Result<WithReport<Self>> {
+        Self::from_document(&parse_html().one(html), id)
+    }
+
+    /// Like [`Listing::from_html_with_report`], for a page that's already parsed (e.g. a
+    /// [`Page`](crate::common::http::Page)).
+    ///
+    /// # Errors
+    /// Errors if the page has no title (e.g. the listing is gone).
+    pub fn from_document(document: &NodeRef, id: u64) -> anyhow::Result<WithReport<Self>> {
+        let clean = |s: String| {
+            let s = s.split_whitespace().collect::<Vec<_>>().join(" ");
+            (!s.is_empty()).then_some(s)
+        };
+
+        let product = Scope::find(document.clone(), "https://schema.org/Product")
+            .or_else(|| Scope::find(document.clone(), "http://schema.org/Product"));
+        /* other pages (e.g. a shop's, for a listing that's gone) have a title and images too */
+        let meta = Some(PageMeta::from_document(document))
+            .filter(|meta| meta.kind.as_deref() == Some("product"))
+            .unwrap_or_default();
+
+        let title = product
+            .as_ref()
+            .and_then(|product| product.get_value("name"))
+            .or_else(|| meta.title.clone())
+            .and_then(clean)
+            .context("could not find a title in the microdata or OpenGraph metadata")?;
+
+        let offers = product
+            .as_ref()
+            .and_then(|product| product.select_prop("offers"));
+        let offered_price = offers
+            .as_ref()
+            .and_then(|offers| offers.get_value("price"))
+            .and_then(clean);
+        let price: Option<Money> = offers
+            .and_then(|offers| offers.try_into().ok())
+            .or_else(|| meta.price.clone());
+        /* the offer is there, with a price that isn't one (e.g. "Sold out") */
+        let unreadable_price = offered_price.filter(|_| price.is_none());
+
+        let shop = product
+            .as_ref()
+            .and_then(|product| {
+                product
+                    .select_prop("brand")
+                    .and_then(|brand| brand.get_value("name"))
+                    .or_else(|| product.get_value("brand"))
+            })
+            .and_then(clean);
+
+        let rating: Option<Rating> = product
+            .as_ref()
+            .and_then(|product| product.select_prop("aggregateRating"))
+            .and_then(|rating| rating.try_into().ok());
+
+        /* not in the microdata; the shipping panel says e.g. "Ships from United States" */
+        let ships_from = document
+            .select("p, span, div")
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|node| clean(node.text_contents()))
+            .find_map(|text| {
+                let rest = text.strip_prefix("Ships from")?;
+                /* skip containers that hold the rest of the panel too */
+                clean(rest.trim_start_matches(':').to_string()).filter(|s| s.len() <= 64)
+            });
+
+        let images = document
+            .select("[itemprop=image]")
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|node| {
+                let attributes = node.attributes.borrow();
+                ["content", "src", "href"]
+                    .iter()
+                    .find_map(|key| attributes.get(*key))
+                    .map(str::to_string)
+            })
+            .fold(Vec::new(), |mut images, url| {
+                if !images.contains(&url) {
+                    images.push(url);
+                }
+                images
+            });
+        let images = if images.is_empty() {
+            meta.images
+        } else {
+            images
+        };
+
+        let listing = Self {
+            id,
+            title,
+            price,
+            shop,
+            rating,
+            ships_from,
+            images,
+        };
+        let missing = [
+            (
+                "price",
+                listing.price.is_none() && unreadable_price.is_none(),
+            ),
+            ("shop", listing.shop.is_none()),
+            ("rating", listing.rating.is_none()),
+            ("ships_from", listing.ships_from.is_none()),
+            ("images", listing.images.is_empty()),
+        ];
+
+        let mut report = WithReport::new(listing);
+        for (field, missing) in missing {
+            report.missing_if(missing, field);
+        }
+        if let Some(text) = unreadable_price {
+            report.push(FieldIssue::unreadable("price", text));
+        }
+        Ok(report)
+    }
+
+    /// Get the IDs of the listings on a search results page, in order.
+    pub fn ids_from_search_html(html: &str) -> Vec<u64> {
+        lazy_static! {
+            static ref RE_LISTING: regex::Regex =
+                regex::Regex::new(r"/listing/([0-9]+)(?:[/?]|$)").unwrap();
+        }
+
+        let mut seen = HashSet::new();
+        parse_html()
+            .one(html)
+            .select("a[href]")
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|a| {
+                let attributes = a.attributes.borrow();
+                RE_LISTING
+                    .captures(attributes.get("href")?)?
+                    .get(1)?
+                    .as_str()
+                    .parse()
+                    .ok()
+            })
+            /* each result links to its listing several times */
+            .filter(|id| seen.insert(*id))
+            .collect()
+    }
+
+    /// Search Etsy using the given query.
+    ///
+    /// This endpoint will wait a few hundred milliseconds between requests
+    /// to avoid being IP banned.
+    ///
+    /// # Returns
+    /// Returns a [`Stream`] of [`anyhow::Result<Self>`].
+    ///
+    /// The stream ends when a search results page fails or has no results.
+    /// Errors fetching individual listings are returned through the stream.
+    #[cfg(feature = "net")]
+    pub fn search(query: &str) -> impl Stream<Item = anyhow::Result<Self>> + '_ {
+        Self::search_with(&Endpoints::default(), query)
+    }
+
+    /// Like [`Listing::search`], using the given [`Endpoints`].
+    #[cfg(feature = "net")]
+    pub fn search_with<'a>(
+        endpoints: &Endpoints,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        Self::search_using(Client::default(), endpoints, query)
+    }
+
+    /// Like [`Listing::search_with`], sending every request through `client`.
+    #[cfg(feature = "net")]
+    pub(crate) fn search_using<'a>(
+        client: Client<false>,
+        endpoints: &Endpoints,
+        query: &'a str,
+    ) -> impl Stream<Item = anyhow::Result<Self>> + 'a {
+        let endpoints = endpoints.clone();
+
+        let pages = futures::stream::iter(1..).then({
+            let (endpoints, client) = (endpoints.clone(), client.clone());
+            move |page| {
+                let (endpoints, client) = (endpoints.clone(), client.clone());
+                async move {
+                    if page > 1 {
+                        /* be nice! */
+                        tokio::time::sleep(Duration::from_millis(600)).await;
+                    }
+
+                    let ids: anyhow::Result<_> = try {
+                        let text = client
+                            .get(&endpoints.url("/search"))
+                            .await?
+                            .query(&[("q", query), ("page", page.to_string().as_str())])
+                            .send()
+                            .await?
+                            .error_for_status()?
+                            .text()
+                            .await?;
+                        Self::ids_from_search_html(&text)
+                    };
+                    ids
+                }
+            }
+        });
+
+        pages
+            .take_while(|ids| futures::future::ready(ids.as_ref().is_ok_and(|ids| !ids.is_empty())))
+            .flat_map(|ids| futures::stream::iter(ids.unwrap_or_default()))
+            .then(move |id| {
+                let endpoints = endpoints.clone();
+                let mut client = client.clone();
+                async move {
+                    /* be nice! */
+                    let sleep = tokio::time::sleep(Duration::from_millis(600));
+                    tokio::join!(Self::by_id_with(&endpoints, &mut client, id), sleep).0
+                }
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "net")]
+    use futures::StreamExt;
+
+    #[cfg(feature = "net")]
+    use super::Endpoints;
+    use super::Listing;
+    use crate::common::Money;
+    #[cfg(feature = "net")]
+    use crate::testing::MockServer;
+
+    const LISTING: &str = r#"
+        <html>
+            <body>
+                <div itemscope itemtype="https://schema.org/Product">
+                    <h1 itemprop="name">
+                        Handmade Stoneware Coffee Mug, Speckled Glaze
+                    </h1>
+                    <img itemprop="image" src="https://i.etsystatic.com/123/r/il/abc/1_794xN.jpg" />
+                    <img itemprop="image" src="https://i.etsystatic.com/123/r/il/def/2_794xN.jpg" />
+                    <div itemprop="brand" itemscope itemtype="https://schema.org/Brand">
+                        <a href="/shop/ClayAndKiln"><span itemprop="name">ClayAndKiln</span></a>
+                    </div>
+                    <div itemprop="aggregateRating" itemscope itemtype="https://schema.org/AggregateRating">
+                        <meta itemprop="ratingValue" content="4.9" />
+                        <meta itemprop="reviewCount" content="1,284" />
+                    </div>
+                    <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                        <p class="wt-text-title-03">$34.00</p>
+                        <meta itemprop="price" content="34.00" />
+                        <meta itemprop="priceCurrency" content="USD" />
+                    </div>
+                </div>
+                <div id="shipping-variant-div">
+                    <p class="wt-text-body-01">Ships from United States</p>
+                </div>
+            </body>
+        </html>
+    "#;
+
+    #[cfg(feature = "net")]
+    const SEARCH: &str = r#"
+        <ul class="wt-grid">
+            <li><a class="listing-link" href="https://www.etsy.com/listing/1043239412/handmade-mug?ref=search">
+                <img src="https://i.etsystatic.com/1.jpg" /></a>
+                <a href="https://www.etsy.com/listing/1043239412/handmade-mug?ref=search_title">Handmade Mug</a>
+            </li>
+            <li><a class="listing-link" href="https://www.etsy.com/listing/987654321/speckled-mug">Speckled Mug</a></li>
+            <li><a href="https://www.etsy.com/shop/ClayAndKiln">ClayAndKiln</a></li>
+        </ul>
+    "#;
+
+    #[test]
+    fn test_listing_page() {
+        let listing = Listing::from_html(LISTING, 1043239412).unwrap();
+        assert_eq!(
+            listing.title,
+            "Handmade Stoneware Coffee Mug, Speckled Glaze"
+        );
+        assert_eq!(listing.price, Some(Money::from(34.0)));
+        assert_eq!(listing.shop.as_deref(), Some("ClayAndKiln"));
+        let rating = listing.rating.unwrap();
+        assert!((rating.on_scale(5.0) - 4.9).abs() < 1e-9);
+        assert_eq!(rating.count, Some(1284));
+        assert_eq!(listing.ships_from.as_deref(), Some("United States"));
+        assert_eq!(listing.images.len(), 2);
+
+        assert!(Listing::from_html("<p>gone</p>", 1).is_err());
+
+        let listing = Listing::from_html(
+            r#"<head>
+                <meta property="og:type" content="product">
+                <meta property="og:title" content="Speckled Mug">
+                <meta property="og:image" content="https://i.etsystatic.com/1.jpg">
+                <meta property="product:price:amount" content="28.00">
+                <meta property="product:price:currency" content="USD">
+            </head>"#,
+            987654321,
+        )
+        .unwrap();
+        assert_eq!(listing.title, "Speckled Mug");
+        assert_eq!(listing.price, Some(Money::from(28.0)));
+        assert_eq!(listing.images, ["https://i.etsystatic.com/1.jpg"]);
+        assert_eq!(listing.shop, None);
+
+        assert!(Listing::from_html(
+            r#"<head>
+                <meta property="og:type" content="website">
+                <meta property="og:title" content="ClayAndKiln on Etsy">
+            </head>"#,
+            987654321,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_report() {
+        assert!(Listing::from_html_with_report(LISTING, 1043239412)
+            .unwrap()
+            .is_complete());
+
+        let report = Listing::from_html_with_report(
+            r#"<div itemscope itemtype="https://schema.org/Product">
+                <h1 itemprop="name">Speckled Mug</h1>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <span itemprop="price">Sold out</span>
+                </div>
+            </div>"#,
+            1,
+        )
+        .unwrap();
+        assert_eq!(
+            report
+                .parse_report
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "shop is missing",
+                "rating is missing",
+                "ships_from is missing",
+                "images is missing",
+                "price could not be read: Sold out"
+            ]
+        );
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/search?q=mug&page=1", 200, SEARCH)
+            .mock("/search?q=mug&page=2", 200, "<p>No results</p>")
+            .mock("/listing/1043239412", 200, LISTING)
+            .mock("/listing/987654321", 404, "");
+
+        let endpoints = Endpoints { base: server.uri() };
+        let listings = Listing::search_with(&endpoints, "mug")
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(listings.len(), 2);
+        assert_eq!(listings[0].as_ref().unwrap().id, 1043239412);
+        assert!(listings[1].is_err());
+        assert_eq!(
+            server.requests(),
+            vec![
+                "/search?q=mug&page=1",
+                "/listing/1043239412",
+                "/listing/987654321",
+                "/search?q=mug&page=2",
+            ]
+        );
+    }
+}
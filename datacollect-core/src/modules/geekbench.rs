@@ -0,0 +1,333 @@
+use anyhow::Context;
+use kuchiki::{iter::NodeIterator, parse_html, traits::TendrilSink};
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[cfg(feature = "net")]
+use crate::common::{Client, Collected};
+use crate::schemas::computing::{CPUBenchmark, CPUBenchmarkMetric, CPU};
+
+/// The Geekbench Browser server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://browser.geekbench.com";
+
+crate::common::endpoints::endpoints!();
+
+/// A single Geekbench 5 CPU benchmark run, as shown on the Geekbench Browser.
+#[derive(Serialize, JsonSchema, Clone)]
+pub struct BenchmarkResult {
+    /// The ID of the result, as in `https://browser.geekbench.com/v5/cpu/<id>`.
+    pub id: u64,
+    /// The device (system/motherboard or phone model) the benchmark ran on.
+    pub device: String,
+    /// The name of the processor, without its clock speed and core count.
+    pub cpu: String,
+    pub single_core: Option<u32>,
+    pub multi_core: Option<u32>,
+}
+
+/// Strip the clock speed and core count from a Geekbench processor description.
+///
+/// ## Example
+/// ```txt
+/// "AMD Ryzen 5 2600 3400 MHz (6 cores)" -> "AMD Ryzen 5 2600"
+/// "Intel Core i7-8700K"                 -> "Intel Core i7-8700K"
+/// ```
+fn cpu_name(model: &str) -> String {
+    lazy_static! {
+        static ref RE_SUFFIX: regex::Regex =
+            regex::Regex::new(r"\s+[0-9]+(?:\.[0-9]+)?\s*[MG]Hz.*$").unwrap();
+    }
+
+    RE_SUFFIX.replace(model.trim(), "").to_string()
+}
+
+fn parse_score(s: &str) -> Option<u32> {
+    s.trim().replace(',', "").parse().ok()
+}
+
+impl BenchmarkResult {
+    /// Parse the results listed on one Geekbench Browser search page.
+    pub fn from_search_html(html: &str) -> Vec<Self> {
+        lazy_static! {
+            static ref RE_RESULT: regex::Regex = regex::Regex::new(r"/v5/cpu/([0-9]+)$").unwrap();
+        }
+
+        parse_html()
+            .one(html)
+            .select(".list-col")
+            .into_iter()
+            .flatten()
+            .filter_map(|col| {
+                let col = col.as_node();
+                let (id, device) = col.select("a[href]").ok()?.find_map(|a| {
+                    let href = a.attributes.borrow().get("href")?.to_string();
+                    let id = RE_RESULT.captures(&href)?.get(1)?.as_str().parse().ok()?;
+                    Some((id, a.text_contents().trim().to_string()))
+                })?;
+                let cpu = cpu_name(&col.select_first(".list-col-model").ok()?.text_contents());
+                let mut scores = col
+                    .select(".list-col-text-score")
+                    .ok()?
+                    .map(|s| parse_score(&s.text_contents()));
+
+                Some(Self {
+                    id,
+                    device,
+                    cpu,
+                    single_core: scores.next().flatten(),
+                    multi_core: scores.next().flatten(),
+                })
+            })
+            .collect()
+    }
+
+    /// Parse the Geekbench Browser page of the result `id`.
+    ///
+    /// # Errors
+    /// Errors if the page has no scores.
+    pub fn from_html(html: &str, id: u64) -> anyhow::Result<Self> {
+        let document = &parse_html().one(html);
+        let system_value = |name: &str| {
+            let label = document
+                .select("td.system-name")
+                .ok()?
+                .find(|td| td.text_contents().trim() == name)?;
+            let value = label.as_node().following_siblings().elements().next()?;
+            Some(value.text_contents().trim().to_string())
+        };
+
+        let cpu = system_value("Name")
+            .or_else(|| system_value("Processor"))
+            .map(|s| cpu_name(&s))
+            .context("could not find processor name")?;
+        let device = system_value("Model").unwrap_or_default();
+
+        let mut scores = document
+            .select(".score-container .score")
+            .ok()
+            .into_iter()
+            .flatten()
+            .map(|s| parse_score(&s.text_contents()));
+
+        Ok(Self {
+            id,
+            device,
+            cpu,
+            single_core: scores.next().flatten(),
+            multi_core: scores.next().flatten(),
+        })
+    }
+
+    /// Get a single result from the Geekbench Browser by its ID.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id(client: &mut Client<false>, id: u64) -> anyhow::Result<Self> {
+        Self::by_id_with(&Endpoints::default(), client, id).await
+    }
+
+    /// Like [`BenchmarkResult::by_id`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn by_id_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        id: u64,
+    ) -> anyhow::Result<Self> {
+        Ok(Self::by_id_enveloped_with(endpoints, client, id)
+            .await?
+            .data)
+    }
+
+    /// Like [`BenchmarkResult::by_id`], recording where and when the result was collected.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id_enveloped(
+        client: &mut Client<false>,
+        id: u64,
+    ) -> anyhow::Result<Collected<Self>> {
+        Self::by_id_enveloped_with(&Endpoints::default(), client, id).await
+    }
+
+    /// Like [`BenchmarkResult::by_id_enveloped`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id_enveloped_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        id: u64,
+    ) -> anyhow::Result<Collected<Self>> {
+        let response = client
+            .get(&endpoints.url(&format!("/v5/cpu/{}", id)))
+            .await?
+            .send()
+            .await?;
+        let result = Self::from_html(&String::from_utf8_lossy(response.bytes()), id)?;
+        let url = response.url().to_string();
+        Ok(Collected::from_response(
+            "geekbench",
+            &response,
+            Some(url),
+            result,
+        ))
+    }
+
+    /// Search the Geekbench Browser, returning the results on the given page (starting at 1).
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    #[cfg(feature = "net")]
+    pub async fn search(
+        client: &mut Client<false>,
+        query: &str,
+        page: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        Self::search_with(&Endpoints::default(), client, query, page).await
+    }
+
+    /// Like [`BenchmarkResult::search`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn search_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        query: &str,
+        page: u32,
+    ) -> anyhow::Result<Vec<Self>> {
+        let text = client
+            .get(&endpoints.url("/v5/cpu/search"))
+            .await?
+            .query(&[("q", query), ("page", page.to_string().as_str())])
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        Ok(Self::from_search_html(&text))
+    }
+}
+
+/// Average the given results into a single [`CPUBenchmark`].
+pub fn summarize(results: &[BenchmarkResult]) -> CPUBenchmark {
+    fn mean(scores: impl Iterator<Item = u32>) -> Option<f64> {
+        let (sum, n) = scores.fold((0.0, 0), |(sum, n), s| (sum + f64::from(s), n + 1));
+        (n > 0).then(|| sum / f64::from(n))
+    }
+
+    CPUBenchmark {
+        single_core: mean(results.iter().filter_map(|r| r.single_core)),
+        multi_core: mean(results.iter().filter_map(|r| r.multi_core)),
+        score: None,
+        samples: Some(results.len() as u32),
+    }
+}
+
+/// Merge the results that were run on the given CPU into its benchmarks,
+/// under [`CPUBenchmarkMetric::Geekbench5`].
+///
+/// Results are matched to the CPU by name, ignoring case.
+/// If none of the results match, the CPU is left unchanged.
+pub fn merge_into(cpu: &mut CPU, results: &[BenchmarkResult]) {
+    let matching = results
+        .iter()
+        .filter(|r| r.cpu.eq_ignore_ascii_case(&cpu.name))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if !matching.is_empty() {
+        cpu.benchmarks
+            .insert(CPUBenchmarkMetric::Geekbench5, summarize(&matching));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::{cpu_name, merge_into, BenchmarkResult};
+    use crate::schemas::computing::{CPUBenchmarkMetric, CPU};
+
+    #[test]
+    fn test_cpu_name() {
+        assert_eq!(
+            cpu_name("AMD Ryzen 5 2600 3400 MHz (6 cores)"),
+            "AMD Ryzen 5 2600"
+        );
+        assert_eq!(
+            cpu_name("Intel Core i7-8700K 3.70 GHz (6 cores)"),
+            "Intel Core i7-8700K"
+        );
+        assert_eq!(cpu_name("Apple M1"), "Apple M1");
+    }
+
+    #[test]
+    fn test_search_page() {
+        let page = r#"
+            <div class="list-col">
+                <div class="list-col-inner">
+                    <span class="list-col-subtitle">System</span>
+                    <a href="/v5/cpu/12345">Gigabyte B450M DS3H</a>
+                    <span class="list-col-model">AMD Ryzen 5 2600 3400 MHz (6 cores)</span>
+                    <span class="list-col-subtitle-score">Single-Core Score</span>
+                    <span class="list-col-text-score">1089</span>
+                    <span class="list-col-subtitle-score">Multi-Core Score</span>
+                    <span class="list-col-text-score">5912</span>
+                </div>
+            </div>
+            <div class="list-col">
+                <div class="list-col-inner">
+                    <a href="/v5/cpu/12346">ASUS PRIME B350-PLUS</a>
+                    <span class="list-col-model">AMD Ryzen 5 2600 3400 MHz (6 cores)</span>
+                    <span class="list-col-text-score">1111</span>
+                    <span class="list-col-text-score">6088</span>
+                </div>
+            </div>
+        "#;
+
+        let results = BenchmarkResult::from_search_html(page);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, 12345);
+        assert_eq!(results[0].device, "Gigabyte B450M DS3H");
+        assert_eq!(results[0].cpu, "AMD Ryzen 5 2600");
+        assert_eq!(results[0].single_core, Some(1089));
+        assert_eq!(results[1].multi_core, Some(6088));
+
+        let mut cpu = CPU {
+            name: "AMD Ryzen 5 2600".to_string(),
+            ..Default::default()
+        };
+        merge_into(&mut cpu, &results);
+        let benchmark = &cpu.benchmarks[&CPUBenchmarkMetric::Geekbench5];
+        assert_eq!(benchmark.single_core, Some(1100.0));
+        assert_eq!(benchmark.samples, Some(2));
+    }
+
+    #[test]
+    fn test_result_page() {
+        let page = r#"
+            <div class="score-container"><div class="score">1089</div></div>
+            <div class="score-container"><div class="score">5,912</div></div>
+            <table class="system-table">
+                <tr><td class="system-name">Model</td><td class="system-value">Gigabyte B450M DS3H</td></tr>
+                <tr><td class="system-name">Name</td><td class="system-value">AMD Ryzen 5 2600</td></tr>
+            </table>
+        "#;
+
+        let result = BenchmarkResult::from_html(page, 12345).unwrap();
+        assert_eq!(result.cpu, "AMD Ryzen 5 2600");
+        assert_eq!(result.device, "Gigabyte B450M DS3H");
+        assert_eq!(result.single_core, Some(1089));
+        assert_eq!(result.multi_core, Some(5912));
+    }
+}
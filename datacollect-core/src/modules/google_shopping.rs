@@ -0,0 +1,197 @@
+use std::str::FromStr;
+
+use kuchiki::{parse_html, traits::TendrilSink};
+use lazy_static::lazy_static;
+use reqwest::Url;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{extract::Field, Client, Money};
+
+/// The Google server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://www.google.com";
+
+crate::common::endpoints::endpoints!();
+
+/// One store's offer of a product, from Google Shopping's results.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Offer {
+    pub title: String,
+    /// The store selling it, if the result is for one store (rather than e.g. `& more`).
+    pub merchant: Option<String>,
+    pub price: Option<Money>,
+    /// A link to the offer on the store's site, or to Google's page for the product.
+    pub link: String,
+}
+
+/// The cheapest and most expensive of a set of offers; see [`Offer::spread`].
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct PriceSpread {
+    pub lowest: Money,
+    pub highest: Money,
+    /// How many offers had a price (in the same currency as the cheapest).
+    pub offers: usize,
+}
+
+/* Google's class names are generated, and differ between layouts; each selector lists the
+ * ones seen so far, for the grid and list layouts */
+const RESULTS: &str = ".sh-dgr__content, .sh-dlr__list-result";
+
+lazy_static! {
+    static ref TITLE: Field = Field::new("h3").or("h4").or(".Xjkr3b");
+    static ref MERCHANT: Field = Field::new(".aULzUe").or(".IuHnof").or(".E5ocAb");
+    static ref PRICE: Field = Field::new(".a8Pemb").or(".kHxwFf");
+    static ref LINK: Field = Field::new("a.shntl[href]").or("a[href]").attr("href");
+}
+
+impl Offer {
+    /// Search Google Shopping, returning the offers on the first page of results.
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    pub async fn search(client: &mut Client<false>, query: &str) -> anyhow::Result<Vec<Self>> {
+        Self::search_with(&Endpoints::default(), client, query).await
+    }
+
+    /// Like [`Offer::search`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    #[tracing::instrument(skip(client), err)]
+    pub async fn search_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        query: &str,
+    ) -> anyhow::Result<Vec<Self>> {
+        let text = client
+            .get(&endpoints.url("/search"))
+            .await?
+            .query(&[("tbm", "shop"), ("q", query)])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Ok(Self::from_html_with(endpoints, &text))
+    }
+
+    /// Parse a page of shopping results. Results without a title or link are left out.
+    pub fn from_html(html: &str) -> Vec<Self> {
+        Self::from_html_with(&Endpoints::default(), html)
+    }
+
+    /// Like [`Offer::from_html`], for a page from the given [`Endpoints`] (which relative
+    /// links are resolved against).
+    pub fn from_html_with(endpoints: &Endpoints, html: &str) -> Vec<Self> {
+        let base = Url::parse(&endpoints.url("/")).ok();
+
+        parse_html()
+            .one(html)
+            .select(RESULTS)
+            .into_iter()
+            .flatten()
+            .filter_map(|result| {
+                let result = result.as_node();
+                let href = LINK.get(result)?;
+                let link = base.as_ref()?.join(&href).ok()?;
+                /* links through Google look like `/url?url=<link>&...` */
+                let link = link
+                    .query_pairs()
+                    .find(|(key, _)| link.path() == "/url" && (key == "url" || key == "q"))
+                    .map_or_else(|| link.to_string(), |(_, url)| url.into_owned());
+
+                Some(Self {
+                    title: TITLE.get(result)?,
+                    merchant: MERCHANT.get(result),
+                    price: PRICE
+                        .get(result)
+                        .and_then(|price| Money::from_str(&price).ok()),
+                    link,
+                })
+            })
+            .collect()
+    }
+
+    /// The range of prices of `offers`, or `None` if none of them has a price.
+    pub fn spread(offers: &[Self]) -> Option<PriceSpread> {
+        let mut prices = offers.iter().filter_map(|offer| offer.price.as_ref());
+        let first = prices.next()?;
+        let (lowest, highest, count) = prices
+            .filter(|price| price.currency() == first.currency())
+            .fold((first, first, 1), |(lowest, highest, count), price| {
+                (
+                    if price < lowest { price } else { lowest },
+                    if price > highest { price } else { highest },
+                    count + 1,
+                )
+            });
+        Some(PriceSpread {
+            lowest: lowest.clone(),
+            highest: highest.clone(),
+            offers: count,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        common::{Client, Money},
+        testing::MockServer,
+    };
+
+    use super::{Endpoints, Offer};
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await.unwrap();
+        server.mock(
+            "/search?tbm=shop&q=ryzen+5+5600x",
+            200,
+            r#"
+            <div class="sh-dgr__content">
+                <a class="shntl" href="/url?url=https://www.bestbuy.com/site/6438942.p&amp;rct=j">
+                    <h3>AMD Ryzen 5 5600X</h3>
+                </a>
+                <span class="a8Pemb">$159.99</span>
+                <div class="aULzUe">Best Buy</div>
+            </div>
+            <div class="sh-dgr__content">
+                <a class="shntl" href="/shopping/product/123"><h3>AMD Ryzen 5 5600X 6-Core</h3></a>
+                <span class="a8Pemb">$1,149.00</span>
+                <div class="aULzUe">Newegg</div>
+            </div>
+            <div class="sh-dlr__list-result">
+                <a href="https://www.ebay.com/itm/1"><h3>Ryzen 5 5600X (used)</h3></a>
+                <span class="kHxwFf">$120.00</span>
+            </div>
+            <div class="sh-dgr__content"><span class="a8Pemb">$1.00</span></div>
+        "#,
+        );
+
+        let endpoints = Endpoints { base: server.uri() };
+        let offers = Offer::search_with(&endpoints, &mut Client::default(), "ryzen 5 5600x")
+            .await
+            .unwrap();
+        assert_eq!(offers.len(), 3);
+
+        assert_eq!(offers[0].title, "AMD Ryzen 5 5600X");
+        assert_eq!(offers[0].merchant.as_deref(), Some("Best Buy"));
+        assert_eq!(offers[0].link, "https://www.bestbuy.com/site/6438942.p");
+        assert_eq!(offers[0].price, Some(Money::from(159.99)));
+        assert_eq!(
+            offers[1].link,
+            format!("{}/shopping/product/123", server.uri())
+        );
+        assert_eq!(offers[2].merchant, None);
+
+        let spread = Offer::spread(&offers).unwrap();
+        assert_eq!(
+            (spread.lowest, spread.highest),
+            (Money::from(120.0), Money::from(1149.0))
+        );
+        assert_eq!(spread.offers, 3);
+        assert!(Offer::spread(&[]).is_none());
+    }
+}
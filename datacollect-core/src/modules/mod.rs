@@ -1,3 +1,147 @@
+pub mod bestbuy;
+pub mod craigslist;
 pub mod ebay;
+pub mod etsy;
+pub mod geekbench;
+#[cfg(feature = "net")]
+pub mod google_shopping;
+#[cfg(feature = "net")]
+pub mod netprobe;
+pub mod openlibrary;
 pub mod passmark;
+pub mod pcpartpicker;
 pub mod rdap;
+pub mod stocks;
+pub mod techpowerup;
+pub mod userbenchmark;
+#[cfg(feature = "net")]
+pub mod wayback;
+pub mod weather;
+pub mod wikidata;
+
+#[cfg(feature = "net")]
+use anyhow::Context;
+#[cfg(feature = "net")]
+use reqwest::Url;
+
+#[cfg(feature = "net")]
+use ebay::Product;
+
+/// What a link points to, as far as the modules can tell; see [`Target::from_url`].
+#[cfg(feature = "net")]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Target {
+    /// An eBay listing, by item ID.
+    EbayProduct(u64),
+    /// An Etsy listing, by ID.
+    EtsyListing(u64),
+    /// A Geekbench Browser result, by ID.
+    GeekbenchResult(u64),
+    /// A CPU in Passmark's list, by ID.
+    PassmarkCPU(u32),
+    /// A TechPowerUp CPU spec page, by URL.
+    TechpowerupCPU(String),
+    /// A Wikidata entity, by ID (e.g. `Q42`).
+    WikidataEntity(String),
+    /// Anything else: the domain the link is on, without `www.`, e.g. for an RDAP lookup.
+    Domain(String),
+}
+
+#[cfg(feature = "net")]
+impl Target {
+    /// Work out which module a link is for. A bare domain (e.g. `example.com`) is a
+    /// [`Target::Domain`].
+    ///
+    /// ## Example
+    /// ```txt
+    /// "https://www.ebay.com/itm/254625474154"                  -> EbayProduct(254625474154)
+    /// "https://www.cpubenchmark.net/cpu.php?cpu=X&id=1907"     -> PassmarkCPU(1907)
+    /// "https://www.wikidata.org/wiki/Q42"                      -> WikidataEntity("Q42")
+    /// "https://blog.example.com/post"                          -> Domain("blog.example.com")
+    /// ```
+    ///
+    /// # Errors
+    /// Errors if `url` is neither a link nor a domain.
+    pub fn from_url(url: &str) -> anyhow::Result<Self> {
+        let parsed = match Url::parse(url) {
+            Ok(parsed) => parsed,
+            Err(_) => Url::parse(&format!("https://{}", url))
+                .with_context(|| format!("`{}` is not a link or a domain", url))?,
+        };
+        let host = parsed
+            .host_str()
+            .with_context(|| format!("`{}` has no domain", url))?;
+        let domain = host.trim_start_matches("www.");
+        let mut segments = parsed.path_segments().into_iter().flatten();
+        let id = |segment: Option<&str>| segment.and_then(|s| s.parse().ok());
+
+        let target = match (domain, segments.next()) {
+            ("ebay.com", Some("itm")) => {
+                Product::id_from_url(parsed.as_str()).map(Self::EbayProduct)
+            }
+            ("etsy.com", Some("listing")) => id(segments.next()).map(Self::EtsyListing),
+            ("browser.geekbench.com", Some("v5")) if segments.next() == Some("cpu") => {
+                id(segments.next()).map(Self::GeekbenchResult)
+            }
+            ("cpubenchmark.net", Some("cpu.php")) => parsed
+                .query_pairs()
+                .find(|(key, _)| key == "id")
+                .and_then(|(_, id)| id.parse().ok())
+                .map(Self::PassmarkCPU),
+            ("techpowerup.com", Some("cpu-specs")) => {
+                Some(Self::TechpowerupCPU(parsed.to_string()))
+            }
+            ("wikidata.org", Some("wiki")) => segments
+                .next()
+                .filter(|id| id.starts_with('Q'))
+                .map(|id| Self::WikidataEntity(id.to_string())),
+            _ => None,
+        };
+        Ok(target.unwrap_or_else(|| Self::Domain(domain.to_string())))
+    }
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use super::Target;
+
+    #[test]
+    fn test_target_from_url() {
+        let target = |url| Target::from_url(url).unwrap();
+        assert_eq!(
+            target("https://www.ebay.com/itm/foo/254625474154?x=y"),
+            Target::EbayProduct(254625474154)
+        );
+        assert_eq!(
+            target("https://www.etsy.com/listing/1043239412/handmade-mug"),
+            Target::EtsyListing(1043239412)
+        );
+        assert_eq!(
+            target("https://browser.geekbench.com/v5/cpu/123"),
+            Target::GeekbenchResult(123)
+        );
+        assert_eq!(
+            target("https://www.cpubenchmark.net/cpu.php?cpu=Intel+Core+i7-4770&id=1907"),
+            Target::PassmarkCPU(1907)
+        );
+        assert_eq!(
+            target("https://www.techpowerup.com/cpu-specs/ryzen-5-2600.c2011"),
+            Target::TechpowerupCPU(
+                "https://www.techpowerup.com/cpu-specs/ryzen-5-2600.c2011".to_string()
+            )
+        );
+        assert_eq!(
+            target("https://www.wikidata.org/wiki/Q42"),
+            Target::WikidataEntity("Q42".to_string())
+        );
+        assert_eq!(
+            target("https://www.ebay.com/sch/i.html?_nkw=cpu"),
+            Target::Domain("ebay.com".to_string())
+        );
+        assert_eq!(
+            target("example.com"),
+            Target::Domain("example.com".to_string())
+        );
+        assert!(Target::from_url("not a domain").is_err());
+    }
+}
@@ -0,0 +1,170 @@
+//! Connectivity metrics: how long a URL takes to respond, and how fast it downloads.
+//!
+//! Probes go straight to the network through the client's connection settings (e.g. its
+//! proxy), skipping its cache, cassette and rate limit, which would make the numbers
+//! meaningless.
+
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::common::Client;
+
+/// A URL to measure.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Probe {
+    pub url: String,
+    /// Stop downloading after this many bytes, so a large file can be used to measure
+    /// throughput without downloading all of it.
+    pub max_bytes: Option<u64>,
+}
+
+impl Probe {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            max_bytes: None,
+        }
+    }
+
+    /// A few probes for when none are configured: a tiny response for latency, a site the
+    /// other modules scrape, and a 10 MB download for throughput.
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::new("https://www.google.com/generate_204"),
+            Self::new("https://www.ebay.com/robots.txt"),
+            Self {
+                url: "https://speed.cloudflare.com/__down?bytes=10000000".to_string(),
+                max_bytes: Some(10_000_000),
+            },
+        ]
+    }
+}
+
+/// The result of one probe.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Measurement {
+    pub url: String,
+    /// When the request was sent.
+    pub measured_at: DateTime<Utc>,
+    /// The response's HTTP status, if there was a response.
+    pub status: Option<u16>,
+    /// Milliseconds from sending the request to receiving the response headers.
+    pub latency_ms: Option<f64>,
+    /// Bytes of the response body downloaded.
+    pub bytes: u64,
+    /// Milliseconds from sending the request to the end of the download (or the error).
+    pub duration_ms: f64,
+    /// Bytes per second downloading the body, after the headers arrived.
+    pub throughput: Option<f64>,
+    /// Why the request failed, if it did.
+    pub error: Option<String>,
+}
+
+impl Measurement {
+    /// Whether the request got a successful response and downloaded without errors.
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none() && self.status.is_some_and(|status| status < 400)
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Measure one URL. Failures are part of the [`Measurement`] rather than errors, since
+/// they're what the metrics are for.
+#[tracing::instrument(skip(client))]
+pub async fn measure<const COOKIES: bool>(client: &Client<COOKIES>, probe: &Probe) -> Measurement {
+    let mut measurement = Measurement {
+        url: probe.url.clone(),
+        measured_at: Utc::now(),
+        status: None,
+        latency_ms: None,
+        bytes: 0,
+        duration_ms: 0.0,
+        throughput: None,
+        error: None,
+    };
+
+    let start = Instant::now();
+    let result: reqwest::Result<()> = try {
+        let mut response = client.0.get(&probe.url).send().await?;
+        let headers_at = Instant::now();
+        measurement.status = Some(response.status().as_u16());
+        measurement.latency_ms = Some(millis(headers_at - start));
+
+        while let Some(chunk) = response.chunk().await? {
+            measurement.bytes += chunk.len() as u64;
+            if probe.max_bytes.is_some_and(|max| measurement.bytes >= max) {
+                break;
+            }
+        }
+        let download = headers_at.elapsed().as_secs_f64();
+        /* too short to say anything about the connection */
+        measurement.throughput =
+            (download > 0.0 && measurement.bytes > 0).then(|| measurement.bytes as f64 / download);
+    };
+    measurement.duration_ms = millis(start.elapsed());
+    measurement.error = result.err().map(|e| e.to_string());
+
+    measurement
+}
+
+/// Measure each URL in turn. They aren't measured at the same time, so that they don't
+/// compete for bandwidth.
+pub async fn measure_all<const COOKIES: bool>(
+    client: &Client<COOKIES>,
+    probes: &[Probe],
+) -> Vec<Measurement> {
+    let mut measurements = Vec::with_capacity(probes.len());
+    for probe in probes {
+        measurements.push(measure(client, probe).await);
+    }
+    measurements
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{common::Client, testing::MockServer};
+
+    use super::{measure_all, Probe};
+
+    #[tokio::test]
+    async fn test_measure() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/small", 204, "")
+            .mock("/large", 200, &"x".repeat(100_000))
+            .mock("/missing", 404, "not found");
+
+        let probes = vec![
+            Probe::new(&format!("{}/small", server.uri())),
+            Probe {
+                url: format!("{}/large", server.uri()),
+                max_bytes: Some(1_000_000),
+            },
+            Probe::new(&format!("{}/missing", server.uri())),
+            /* nothing listens on port 9 */
+            Probe::new("http://127.0.0.1:9/"),
+        ];
+        let measurements = measure_all(&Client::<false>::default(), &probes).await;
+
+        assert!(measurements[0].is_ok());
+        assert_eq!(measurements[0].status, Some(204));
+        assert!(measurements[0].latency_ms.is_some());
+
+        assert!(measurements[1].is_ok());
+        assert_eq!(measurements[1].bytes, 100_000);
+        assert!(measurements[1].duration_ms >= measurements[1].latency_ms.unwrap());
+
+        assert!(!measurements[2].is_ok());
+        assert_eq!(measurements[2].status, Some(404));
+
+        assert!(!measurements[3].is_ok());
+        assert!(measurements[3].error.is_some());
+        assert_eq!(measurements[3].status, None);
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+
+#[cfg(feature = "net")]
+use anyhow::bail;
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "net")]
+use crate::common::Client;
+
+/// The Open Library server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://openlibrary.org";
+
+crate::common::endpoints::endpoints!();
+
+/// An edition of a book, from Open Library.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Book {
+    /// The ISBN the book was looked up by, without hyphens.
+    pub isbn: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub authors: Vec<String>,
+    /// When the edition was published, as written by Open Library (e.g. `2019`, or `Aug 12, 2019`).
+    pub publish_date: Option<String>,
+    pub publishers: Vec<String>,
+    pub pages: Option<u32>,
+    /// A link to the largest picture of the cover, if there is one.
+    pub cover_url: Option<String>,
+    /// A link to the edition's page on Open Library.
+    pub url: Option<String>,
+}
+
+/* the shape of one book in the Books API's `jscmd=data` responses */
+#[derive(Deserialize)]
+struct Data {
+    title: String,
+    subtitle: Option<String>,
+    #[serde(default)]
+    authors: Vec<Named>,
+    publish_date: Option<String>,
+    #[serde(default)]
+    publishers: Vec<Named>,
+    number_of_pages: Option<u32>,
+    #[serde(default)]
+    cover: HashMap<String, String>,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Named {
+    name: String,
+}
+
+/// Remove the hyphens and spaces from an ISBN-10 or ISBN-13, or `None` if it isn't one.
+///
+/// ## Example
+/// ```txt
+/// "978-1-7185-0044-0" -> "9781718500440"
+/// "0-306-40615-X"     -> "030640615X"
+/// ```
+pub fn normalize_isbn(isbn: &str) -> Option<String> {
+    let isbn = isbn
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_uppercase();
+    let valid = match isbn.len() {
+        10 => isbn
+            .chars()
+            .enumerate()
+            .all(|(i, c)| c.is_ascii_digit() || (i == 9 && c == 'X')),
+        13 => isbn.chars().all(|c| c.is_ascii_digit()),
+        _ => false,
+    };
+    valid.then_some(isbn)
+}
+
+#[cfg(feature = "net")]
+impl Book {
+    /// Look up a book by its ISBN-10 or ISBN-13.
+    ///
+    /// Returns `None` if Open Library doesn't know the book.
+    ///
+    /// # Errors
+    /// Errors if `isbn` isn't an ISBN, if the request failed, or if the response could not
+    /// be parsed.
+    pub async fn by_isbn(client: &mut Client<false>, isbn: &str) -> anyhow::Result<Option<Self>> {
+        Self::by_isbn_with(&Endpoints::default(), client, isbn).await
+    }
+
+    /// Like [`Book::by_isbn`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if `isbn` isn't an ISBN, if the request failed, or if the response could not
+    /// be parsed.
+    #[tracing::instrument(skip(client), err)]
+    pub async fn by_isbn_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        isbn: &str,
+    ) -> anyhow::Result<Option<Self>> {
+        let isbn = match normalize_isbn(isbn) {
+            Some(isbn) => isbn,
+            None => bail!("`{}` is not an ISBN", isbn),
+        };
+        let key = format!("ISBN:{}", isbn);
+        let text = client
+            .get(&endpoints.url("/api/books"))
+            .await?
+            .query(&[
+                ("bibkeys", key.as_str()),
+                ("format", "json"),
+                ("jscmd", "data"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        Self::from_json(&text, &isbn)
+    }
+}
+
+impl Book {
+    /// Read the book with the given ISBN (without hyphens) from a response of Open Library's
+    /// books API, as [`Book::by_isbn`] does.
+    ///
+    /// Returns `None` if the book isn't in the response.
+    ///
+    /// # Errors
+    /// Errors if the response could not be parsed.
+    pub fn from_json(json: &str, isbn: &str) -> anyhow::Result<Option<Self>> {
+        let mut books: HashMap<String, Data> =
+            serde_json::from_str(json).context("could not parse Open Library's response")?;
+
+        /* unknown books are left out of the response, rather than being an error */
+        Ok(books.remove(&format!("ISBN:{}", isbn)).map(|data| {
+            let cover = data.cover;
+            Self {
+                isbn: isbn.to_string(),
+                title: data.title,
+                subtitle: data.subtitle,
+                authors: data.authors.into_iter().map(|a| a.name).collect(),
+                publish_date: data.publish_date,
+                publishers: data.publishers.into_iter().map(|p| p.name).collect(),
+                pages: data.number_of_pages,
+                cover_url: ["large", "medium", "small"]
+                    .iter()
+                    .find_map(|size| cover.get(*size).cloned()),
+                url: data.url,
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "net")]
+    use crate::{common::Client, testing::MockServer};
+
+    use super::normalize_isbn;
+    #[cfg(feature = "net")]
+    use super::{Book, Endpoints};
+
+    #[test]
+    fn test_normalize_isbn() {
+        assert_eq!(
+            normalize_isbn("978-1-7185-0044-0").as_deref(),
+            Some("9781718500440")
+        );
+        assert_eq!(
+            normalize_isbn("0-306-40615-x").as_deref(),
+            Some("030640615X")
+        );
+        assert_eq!(normalize_isbn("Does not apply"), None);
+        assert_eq!(normalize_isbn("X306406150"), None);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_by_isbn() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/api/books?bibkeys=ISBN%3A9781718500440&format=json&jscmd=data",
+                200,
+                r#"{"ISBN:9781718500440": {
+                    "url": "https://openlibrary.org/books/OL28145422M/The_Rust_Programming_Language",
+                    "title": "The Rust Programming Language",
+                    "subtitle": "2nd Edition",
+                    "authors": [{"url": "https://openlibrary.org/authors/OL7560565A", "name": "Steve Klabnik"},
+                                {"url": "https://openlibrary.org/authors/OL7560566A", "name": "Carol Nichols"}],
+                    "number_of_pages": 560,
+                    "publishers": [{"name": "No Starch Press"}],
+                    "publish_date": "2019",
+                    "cover": {"small": "https://covers.openlibrary.org/b/id/1-S.jpg",
+                              "large": "https://covers.openlibrary.org/b/id/1-L.jpg"}
+                }}"#,
+            )
+            .mock(
+                "/api/books?bibkeys=ISBN%3A0000000000&format=json&jscmd=data",
+                200,
+                "{}",
+            );
+
+        let endpoints = Endpoints { base: server.uri() };
+        let mut client = Client::default();
+        let book = Book::by_isbn_with(&endpoints, &mut client, "978-1-7185-0044-0")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(book.isbn, "9781718500440");
+        assert_eq!(book.title, "The Rust Programming Language");
+        assert_eq!(book.authors, vec!["Steve Klabnik", "Carol Nichols"]);
+        assert_eq!(book.publish_date.as_deref(), Some("2019"));
+        assert_eq!(book.pages, Some(560));
+        assert_eq!(
+            book.cover_url.as_deref(),
+            Some("https://covers.openlibrary.org/b/id/1-L.jpg")
+        );
+
+        assert!(Book::by_isbn_with(&endpoints, &mut client, "0000000000")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(Book::by_isbn_with(&endpoints, &mut client, "nope")
+            .await
+            .is_err());
+    }
+}
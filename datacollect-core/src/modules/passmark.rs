@@ -1,74 +1,575 @@
+use std::{collections::HashMap, str::FromStr};
+
+use anyhow::bail;
+#[cfg(feature = "net")]
+use anyhow::Context;
+#[cfg(feature = "net")]
+use chrono::NaiveDate;
+use kuchiki::{parse_html, traits::TendrilSink};
+use lazy_static::lazy_static;
+use rust_decimal::prelude::ToPrimitive;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DefaultOnError, DisplayFromStr, PickFirst};
 
-use crate::common::{Client, IgnoreComma, Money};
+use crate::common::{Currency, IgnoreComma, Money};
+#[cfg(feature = "net")]
+use crate::{
+    common::Client,
+    modules::wayback::{self, Archived, Snapshot},
+};
+
+pub use crate::common::{ParseError, ParseFailure};
+
+/// The Passmark server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://www.cpubenchmark.net";
+
+crate::common::endpoints::endpoints!();
 
 #[serde_as]
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, JsonSchema)]
 pub struct CPU {
     #[serde_as(as = "PickFirst<(_, DisplayFromStr)>")]
+    #[schemars(with = "u32")]
     pub id: u32,
     pub name: String,
     #[serde(default)]
     #[serde_as(as = "DefaultOnError<PickFirst<(_, Option<IgnoreComma<Money>>)>>")]
+    #[schemars(with = "Option<Money>")]
     pub price: Option<Money>,
     #[serde(default)]
     #[serde_as(as = "DefaultOnError<PickFirst<(_, Option<IgnoreComma<u32>>)>>")]
+    #[schemars(with = "Option<u32>")]
     pub cpumark: Option<u32>,
     #[serde(default)]
     #[serde_as(as = "DefaultOnError<PickFirst<(_, Option<IgnoreComma<u32>>)>>")]
+    #[schemars(with = "Option<u32>")]
     pub thread: Option<u32>,
+    /* older payloads don't always have these */
+    #[serde(default)]
     pub socket: String,
+    #[serde(default)]
     pub cat: String,
     #[serde(default)]
     #[serde_as(as = "DefaultOnError<PickFirst<(_, Option<DisplayFromStr>)>>")]
+    #[schemars(with = "Option<u32>")]
     pub cores: Option<u32>,
     #[serde(default)]
     #[serde_as(as = "DefaultOnError<PickFirst<(_, Option<DisplayFromStr>)>>")]
+    #[schemars(with = "Option<u32>")]
     pub logicals: Option<u32>,
+    #[serde(default)]
     #[serde_as(as = "DefaultOnError<PickFirst<(_, Option<DisplayFromStr>)>>")]
+    #[schemars(with = "Option<f64>")]
     pub tdp: Option<f64>,
 }
 
-#[derive(Serialize, Deserialize)]
+impl CPU {
+    /// The CPU mark per dollar of the price, for comparing CPU's by value for money. `None`
+    /// if either is missing, or the price is zero or isn't in dollars.
+    pub fn marks_per_dollar(&self) -> Option<f64> {
+        let price = self.price.as_ref()?;
+        if price.currency() != Currency::USD {
+            return None;
+        }
+        let price = price.amount().to_f64()?;
+        let cpumark = f64::from(self.cpumark?);
+        (price > 0.0).then(|| cpumark / price)
+    }
+}
+
+/// A CPU with its value for money; see [`CPUMegaList::best_value`].
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct ValuedCPU {
+    #[serde(flatten)]
+    pub cpu: CPU,
+    /// See [`CPU::marks_per_dollar`].
+    pub marks_per_dollar: f64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
 pub struct CPUMegaList {
-    data: Vec<CPU>,
+    pub data: Vec<CPU>,
+}
+
+/// A field of [`CPU`] to sort the mega list by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SortKey {
+    CPUMark,
+    ThreadMark,
+    Price,
+    TDP,
+    Cores,
+}
+
+impl SortKey {
+    fn value(self, cpu: &CPU) -> Option<f64> {
+        match self {
+            Self::CPUMark => cpu.cpumark.map(f64::from),
+            Self::ThreadMark => cpu.thread.map(f64::from),
+            Self::Price => cpu.price.as_ref().and_then(|price| price.amount().to_f64()),
+            Self::TDP => cpu.tdp,
+            Self::Cores => cpu.cores.map(f64::from),
+        }
+    }
+}
+
+/// How to sort the mega list: by a [`SortKey`], one way or the other.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Sort {
+    pub key: SortKey,
+    pub descending: bool,
+}
+
+impl FromStr for Sort {
+    type Err = anyhow::Error;
+
+    /// ## Example
+    /// ```txt
+    /// "cpumark:desc" -> highest CPU mark first
+    /// "price"        -> cheapest first
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (key, order) = s.split_once(':').unwrap_or((s, "asc"));
+        let key = match key {
+            "cpumark" => SortKey::CPUMark,
+            "thread" => SortKey::ThreadMark,
+            "price" => SortKey::Price,
+            "tdp" => SortKey::TDP,
+            "cores" => SortKey::Cores,
+            _ => bail!(
+                "can't sort by `{}` (expected `cpumark`, `thread`, `price`, `tdp` or `cores`)",
+                key
+            ),
+        };
+        let descending = match order {
+            "asc" => false,
+            "desc" => true,
+            _ => bail!("no such sort order `{}` (expected `asc` or `desc`)", order),
+        };
+        Ok(Self { key, descending })
+    }
+}
+
+/// Settings for [`CPUMegaList::query`]. CPU's that don't have a value for a field being
+/// filtered on are left out.
+#[derive(Clone, Debug, Default)]
+pub struct Query {
+    /// Only CPU's for this socket (e.g. `AM4`), ignoring case.
+    pub socket: Option<String>,
+    /// Only CPU's in this category (e.g. `Desktop`), ignoring case. Some CPU's are in more
+    /// than one.
+    pub category: Option<String>,
+    pub min_cpumark: Option<u32>,
+    pub max_tdp: Option<f64>,
+    pub max_price: Option<Money>,
+    /// CPU's that don't have a value to sort by go last.
+    pub sort: Option<Sort>,
+    /// Keep only this many CPU's, after sorting.
+    pub top: Option<usize>,
+}
+
+impl Query {
+    fn matches(&self, cpu: &CPU) -> bool {
+        let ignoring_case = |wanted: &Option<String>, value: &str| {
+            wanted.as_ref().is_none_or(|wanted| {
+                value
+                    .split(',')
+                    .any(|value| value.trim().eq_ignore_ascii_case(wanted))
+            })
+        };
+
+        ignoring_case(&self.socket, &cpu.socket)
+            && ignoring_case(&self.category, &cpu.cat)
+            && self
+                .min_cpumark
+                .is_none_or(|min| cpu.cpumark.is_some_and(|mark| mark >= min))
+            && self
+                .max_tdp
+                .is_none_or(|max| cpu.tdp.is_some_and(|tdp| tdp <= max))
+            && self
+                .max_price
+                .as_ref()
+                .is_none_or(|max| cpu.price.as_ref().is_some_and(|price| price <= max))
+    }
+}
+
+/// Log the CPU's that [`CPUMegaList::parse`] left out of the list.
+fn warn_failures(failures: &[ParseFailure]) {
+    for failure in failures {
+        tracing::warn!(
+            "left out CPU {} of the mega list: {}: {}",
+            failure.index,
+            failure.path,
+            failure.message
+        );
+    }
 }
 
 impl CPUMegaList {
+    /// Keep only the CPU's that match `query`, sorted and cut down as it says.
+    pub fn query(self, query: &Query) -> Self {
+        let mut data = self
+            .data
+            .into_iter()
+            .filter(|cpu| query.matches(cpu))
+            .collect::<Vec<_>>();
+        if let Some(sort) = query.sort {
+            data.sort_by(|a, b| match (sort.key.value(a), sort.key.value(b)) {
+                (Some(a), Some(b)) if sort.descending => b.total_cmp(&a),
+                (Some(a), Some(b)) => a.total_cmp(&b),
+                (a, b) => b.is_some().cmp(&a.is_some()),
+            });
+        }
+        if let Some(top) = query.top {
+            data.truncate(top);
+        }
+        Self { data }
+    }
+
+    /// The CPU's that cost at most `budget`, best value for money first. CPU's without a
+    /// price or a CPU mark are left out.
+    pub fn best_value(self, budget: &Money) -> Vec<ValuedCPU> {
+        let mut valued = self
+            .data
+            .into_iter()
+            .filter(|cpu| cpu.price.as_ref().is_some_and(|price| price <= budget))
+            .filter_map(|cpu| {
+                Some(ValuedCPU {
+                    marks_per_dollar: cpu.marks_per_dollar()?,
+                    cpu,
+                })
+            })
+            .collect::<Vec<_>>();
+        valued.sort_by(|a, b| b.marks_per_dollar.total_cmp(&a.marks_per_dollar));
+        valued
+    }
+
     /// Get the big list of CPU's from Passmark's website.
+    /// CPU's that can't be read are left out, with a warning for each.
     ///
     /// # Errors
     /// Errors if one of the requests failed, or if parsing one of the responses failed.
+    #[cfg(feature = "net")]
     pub async fn get(client: &mut Client<true>) -> anyhow::Result<Self> {
-        /* there's a session cookie we need here */
-        client
-            .0
-            .get("https://www.cpubenchmark.net/CPU_mega_page.html")
-            .send()
-            .await?;
+        Self::get_with(&Endpoints::default(), client).await
+    }
+
+    /// Like [`CPUMegaList::get`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed, or if parsing one of the responses failed.
+    #[cfg(feature = "net")]
+    pub async fn get_with(
+        endpoints: &Endpoints,
+        client: &mut Client<true>,
+    ) -> anyhow::Result<Self> {
+        let (list, failures) = Self::get_checked_with(endpoints, client).await?;
+        warn_failures(&failures);
+        Ok(list)
+    }
 
+    /// Like [`CPUMegaList::get`], but also returns the CPU's that couldn't be read, so that
+    /// changes to Passmark's format don't go unnoticed.
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed, or if parsing one of the responses failed.
+    #[cfg(feature = "net")]
+    pub async fn get_checked(
+        client: &mut Client<true>,
+    ) -> anyhow::Result<(Self, Vec<ParseFailure>)> {
+        Self::get_checked_with(&Endpoints::default(), client).await
+    }
+
+    /// Like [`CPUMegaList::get_checked`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed, or if parsing one of the responses failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn get_checked_with(
+        endpoints: &Endpoints,
+        client: &mut Client<true>,
+    ) -> anyhow::Result<(Self, Vec<ParseFailure>)> {
+        /* the data needs a session cookie, which a visit to the mega page sets; one kept
+         * from an earlier run may still work, but may also have been dropped by the server */
+        let page = endpoints.url("/CPU_mega_page.html");
+        let has_session = client
+            .cookie_jar()
+            .zip(reqwest::Url::parse(&client.resolve(&page)).ok())
+            .is_some_and(|(jar, url)| !jar.cookies_for(&url).is_empty());
+        if has_session {
+            match Self::get_data(endpoints, client).await {
+                Ok((list, failures)) if !list.data.is_empty() => return Ok((list, failures)),
+                _ => tracing::debug!("the saved Passmark session didn't work; starting a new one"),
+            }
+        }
+
+        client.get(&page).await?.send().await?;
+        Self::get_data(endpoints, client).await
+    }
+
+    /// Get the list from the data endpoint, with the client's session.
+    #[cfg(feature = "net")]
+    async fn get_data(
+        endpoints: &Endpoints,
+        client: &mut Client<true>,
+    ) -> anyhow::Result<(Self, Vec<ParseFailure>)> {
         let res = client
-            .0
-            .get("https://www.cpubenchmark.net/data/")
+            .get(&endpoints.url("/data/"))
+            .await?
             .header("X-Requested-With", "XMLHttpRequest")
             .send()
             .await?;
 
-        let json: Self = res.json().await?;
-        Ok(json)
+        Self::parse(&res.text().await?)
+    }
+
+    /// Get the mega list as it was on `date`, from the copies archived by the Wayback Machine.
+    ///
+    /// The format of the list has changed over the years: older copies of the mega page
+    /// have the whole list in a table, while newer ones load it from the data endpoint.
+    /// Both are read, and CPU's that can't be read at all are left out, with a warning for
+    /// each.
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed, if neither page was archived, or if no CPU's
+    /// could be read from the archived copies.
+    #[cfg(feature = "net")]
+    pub async fn get_historical<const COOKIES: bool>(
+        client: &mut Client<COOKIES>,
+        date: NaiveDate,
+    ) -> anyhow::Result<Archived<Self>> {
+        Self::get_historical_with(
+            &Endpoints::default(),
+            &wayback::Endpoints::default(),
+            client,
+            date,
+        )
+        .await
+    }
+
+    /// Like [`CPUMegaList::get_historical`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed, if neither page was archived, or if no CPU's
+    /// could be read from the archived copies.
+    #[cfg(feature = "net")]
+    pub async fn get_historical_with<const COOKIES: bool>(
+        endpoints: &Endpoints,
+        archive: &wayback::Endpoints,
+        client: &mut Client<COOKIES>,
+        date: NaiveDate,
+    ) -> anyhow::Result<Archived<Self>> {
+        let (list, failures) =
+            Self::get_historical_checked_with(endpoints, archive, client, date).await?;
+        warn_failures(&failures);
+        Ok(list)
+    }
+
+    /// Like [`CPUMegaList::get_historical`], but also returns the CPU's in the archived data
+    /// that couldn't be read, as [`CPUMegaList::get_checked`] does.
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed, if neither page was archived, or if no CPU's
+    /// could be read from the archived copies.
+    #[cfg(feature = "net")]
+    pub async fn get_historical_checked<const COOKIES: bool>(
+        client: &mut Client<COOKIES>,
+        date: NaiveDate,
+    ) -> anyhow::Result<(Archived<Self>, Vec<ParseFailure>)> {
+        Self::get_historical_checked_with(
+            &Endpoints::default(),
+            &wayback::Endpoints::default(),
+            client,
+            date,
+        )
+        .await
+    }
+
+    /// Like [`CPUMegaList::get_historical_checked`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if one of the requests failed, if neither page was archived, or if no CPU's
+    /// could be read from the archived copies.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn get_historical_checked_with<const COOKIES: bool>(
+        endpoints: &Endpoints,
+        archive: &wayback::Endpoints,
+        client: &mut Client<COOKIES>,
+        date: NaiveDate,
+    ) -> anyhow::Result<(Archived<Self>, Vec<ParseFailure>)> {
+        let page =
+            Snapshot::closest_with(archive, client, &endpoints.url("/CPU_mega_page.html"), date)
+                .await;
+        if let Ok(page) = &page {
+            let list = Self::from_html(&page.body);
+            if !list.data.is_empty() {
+                return Ok((page.archived(list), Vec::new()));
+            }
+        }
+
+        let data = Snapshot::closest_with(archive, client, &endpoints.url("/data/"), date)
+            .await
+            .context("could not find an archived copy of the mega list")?;
+        let (list, failures) = Self::parse(&data.body)?;
+        if list.data.is_empty() {
+            bail!("no CPU's in the archived copy of {}", data.url);
+        }
+        Ok((data.archived(list), failures))
+    }
+
+    /// Read the list from the data endpoint, which is either `{"data": [...]}` or (in older
+    /// copies) just the list. CPU's that can't be read are left out of the list, and
+    /// returned alongside it.
+    ///
+    /// # Errors
+    /// Errors if `body` isn't JSON, or has no list of CPU's in it.
+    pub fn parse(body: &str) -> anyhow::Result<(Self, Vec<ParseFailure>)> {
+        let json: serde_json::Value = serde_json::from_str(body)
+            .map_err(|e| ParseError::caused_by("could not parse the mega list", e))?;
+        let cpus = match json {
+            serde_json::Value::Array(cpus) => cpus,
+            serde_json::Value::Object(mut fields) => match fields.remove("data") {
+                Some(serde_json::Value::Array(cpus)) => cpus,
+                _ => return Err(ParseError::new("the mega list has no data").into()),
+            },
+            _ => return Err(ParseError::new("the mega list has no data").into()),
+        };
+
+        let mut data = Vec::with_capacity(cpus.len());
+        let mut failures = Vec::new();
+        for (index, cpu) in cpus.into_iter().enumerate() {
+            match serde_path_to_error::deserialize(cpu) {
+                Ok(cpu) => data.push(cpu),
+                Err(e) => failures.push(ParseFailure {
+                    index,
+                    path: e.path().to_string(),
+                    message: e.into_inner().to_string(),
+                }),
+            }
+        }
+        Ok((Self { data }, failures))
+    }
+
+    /// Like [`CPUMegaList::parse`], leaving out the CPU's that can't be read with a warning
+    /// for each, as [`CPUMegaList::get`] does.
+    ///
+    /// # Errors
+    /// Errors if `body` isn't JSON, or has no list of CPU's in it.
+    pub fn from_json(body: &str) -> anyhow::Result<Self> {
+        let (list, failures) = Self::parse(body)?;
+        warn_failures(&failures);
+        Ok(list)
+    }
+
+    /// Read the list from the table on an old copy of the mega page. Columns are found by
+    /// their headings, which have been renamed now and then.
+    pub fn from_html(body: &str) -> Self {
+        lazy_static! {
+            static ref RE_ID: regex::Regex = regex::Regex::new(r"[?&]id=([0-9]+)").unwrap();
+        }
+
+        let number = |text: &str| text.trim().replace(',', "");
+        let document = parse_html().one(body);
+        let headings = document
+            .select("#cputable thead th")
+            .into_iter()
+            .flatten()
+            .map(|th| th.text_contents().trim().to_lowercase())
+            .collect::<Vec<_>>();
+
+        let data = document
+            .select("#cputable tbody tr")
+            .into_iter()
+            .flatten()
+            .filter_map(|row| {
+                let cells = row.as_node().select("td").ok()?.collect::<Vec<_>>();
+                let mut columns = HashMap::new();
+                let mut id = None;
+                for (heading, cell) in headings.iter().zip(&cells) {
+                    let column = match heading.as_str() {
+                        h if h.contains("name") => {
+                            id = cell.as_node().select("a").ok()?.find_map(|a| {
+                                let attributes = a.attributes.borrow();
+                                RE_ID.captures(attributes.get("href")?)?[1].parse().ok()
+                            });
+                            "name"
+                        }
+                        h if h.starts_with("price") => "price",
+                        h if h.contains("value") => continue,
+                        h if h.contains("thread") => "thread",
+                        h if h.contains("mark") => "cpumark",
+                        h if h.starts_with("tdp") => "tdp",
+                        h if h.starts_with("socket") => "socket",
+                        h if h.starts_with("category") => "cat",
+                        h if h.starts_with("cores") => "cores",
+                        _ => continue,
+                    };
+                    columns.insert(column, cell.text_contents().trim().to_string());
+                }
+
+                Some(CPU {
+                    id: id?,
+                    name: columns.remove("name").filter(|name| !name.is_empty())?,
+                    price: columns
+                        .get("price")
+                        .and_then(|price| Money::from_str(&number(price)).ok()),
+                    cpumark: columns.get("cpumark").and_then(|n| number(n).parse().ok()),
+                    thread: columns.get("thread").and_then(|n| number(n).parse().ok()),
+                    socket: columns.remove("socket").unwrap_or_default(),
+                    cat: columns.remove("cat").unwrap_or_default(),
+                    cores: columns.get("cores").and_then(|n| number(n).parse().ok()),
+                    logicals: None,
+                    tdp: columns.get("tdp").and_then(|n| number(n).parse().ok()),
+                })
+            })
+            .collect();
+
+        Self { data }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::common::Client;
+    #[cfg(feature = "net")]
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use crate::common::Money;
+    #[cfg(feature = "net")]
+    use crate::{common::Client, modules::wayback, testing::MockServer};
 
-    use super::CPUMegaList;
+    #[cfg(feature = "net")]
+    use super::Endpoints;
+    use super::{CPUMegaList, ParseFailure, Query, Sort, CPU};
 
+    const MEGA_PAGE: &str = r#"
+        <table id="cputable">
+            <thead><tr>
+                <th>CPU Name</th><th>Price (USD)</th><th>CPU Mark</th><th>CPU Value</th>
+                <th>Thread Mark</th><th>TDP (W)</th><th>Socket</th><th>Category</th>
+            </tr></thead>
+            <tbody>
+                <tr>
+                    <td><a href="cpu.php?cpu=Intel+Core+i7-4770+%40+3.40GHz&amp;id=1907">Intel Core i7-4770 @ 3.40GHz</a></td>
+                    <td>$1,303.99*</td><td>9,948</td><td>30.25</td><td>2,174</td><td>84</td>
+                    <td>LGA1150</td><td>Desktop</td>
+                </tr>
+                <tr><td>no link</td><td>NA</td><td>1</td><td>NA</td><td>1</td><td>NA</td><td></td><td></td></tr>
+            </tbody>
+        </table>
+    "#;
+
+    #[cfg(feature = "net")]
     #[tokio::test]
     async fn test_producer() {
-        let mut client = Client::<true>::default();
+        let mut client = Client::<true>::default()
+            .with_cassette(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/fixtures/passmark.json"
+            ))
+            .unwrap();
         let cpus = CPUMegaList::get(&mut client).await.unwrap();
         let my_cpu = cpus
             .data
@@ -77,4 +578,220 @@ mod tests {
             .unwrap();
         assert_eq!(my_cpu.tdp, Some(65.0));
     }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_saved_session() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/CPU_mega_page.html", 200, "<html></html>")
+            .mock(
+                "/data/",
+                200,
+                r#"{"data": [{"id": 1, "name": "AMD Ryzen 5 2600"}, {"id": "x"}]}"#,
+            );
+        let endpoints = Endpoints { base: server.uri() };
+        let mut client = Client::<true>::default();
+        let jar = client.cookie_jar().unwrap().clone();
+        let page = reqwest::Url::parse(&format!("{}/CPU_mega_page.html", server.uri())).unwrap();
+        jar.insert(&page, "PHPSESSID=abc; Path=/").unwrap();
+
+        /* with a session, the mega page is skipped */
+        let (list, _) = CPUMegaList::get_checked_with(&endpoints, &mut client)
+            .await
+            .unwrap();
+        assert_eq!(list.data.len(), 1);
+        assert_eq!(server.requests(), vec!["/data/"]);
+
+        /* ...unless the session no longer works */
+        server.mock("/data/", 200, r#"{"data": []}"#);
+        CPUMegaList::get_checked_with(&endpoints, &mut client)
+            .await
+            .unwrap();
+        assert_eq!(
+            server.requests(),
+            vec!["/data/", "/data/", "/CPU_mega_page.html", "/data/"]
+        );
+    }
+
+    #[test]
+    fn test_from_html() {
+        let list = CPUMegaList::from_html(MEGA_PAGE);
+        assert_eq!(list.data.len(), 1);
+        let cpu = &list.data[0];
+        assert_eq!(
+            (cpu.id, cpu.name.as_str()),
+            (1907, "Intel Core i7-4770 @ 3.40GHz")
+        );
+        assert_eq!(cpu.price, Some(Money::from(1303.99)));
+        assert_eq!(
+            (cpu.cpumark, cpu.thread, cpu.tdp),
+            (Some(9948), Some(2174), Some(84.0))
+        );
+        assert_eq!(
+            (cpu.socket.as_str(), cpu.cat.as_str()),
+            ("LGA1150", "Desktop")
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let (list, failures) = CPUMegaList::parse(
+            r#"{"data": [{"id": "1907", "name": "Intel Core i7-4770 @ 3.40GHz", "cpumark": "9,948"}, {"id": "x"}, {"id": 2, "name": 3}]}"#,
+        )
+        .unwrap();
+        assert_eq!(list.data.len(), 1);
+        assert_eq!(list.data[0].cpumark, Some(9948));
+        assert_eq!(failures.len(), 2);
+        assert_eq!((failures[0].index, failures[0].path.as_str()), (1, "id"));
+        assert_eq!(
+            failures[1],
+            ParseFailure {
+                index: 2,
+                path: "name".to_string(),
+                message: "invalid type: integer `3`, expected a string".to_string(),
+            }
+        );
+
+        let (list, failures) = CPUMegaList::parse(r#"[{"id": 1, "name": "old"}]"#).unwrap();
+        assert_eq!(list.data[0].name, "old");
+        assert!(failures.is_empty());
+
+        assert!(CPUMegaList::parse(r#"{"error": true}"#).is_err());
+    }
+
+    #[test]
+    fn test_query() {
+        let cpu = |id, socket: &str, cpumark, tdp| CPU {
+            id,
+            name: format!("CPU {}", id),
+            price: None,
+            cpumark,
+            thread: None,
+            socket: socket.to_string(),
+            cat: "Desktop, Server".to_string(),
+            cores: None,
+            logicals: None,
+            tdp,
+        };
+        let list = CPUMegaList {
+            data: vec![
+                cpu(1, "AM4", Some(20_500), Some(65.0)),
+                cpu(2, "AM4", Some(25_000), Some(105.0)),
+                cpu(3, "am4", Some(30_000), Some(95.0)),
+                cpu(4, "LGA1200", Some(28_000), Some(65.0)),
+                cpu(5, "AM4", None, Some(65.0)),
+                cpu(6, "AM4", Some(22_000), Some(65.0)),
+            ],
+        };
+
+        let query = Query {
+            socket: Some("AM4".to_string()),
+            category: Some("server".to_string()),
+            min_cpumark: Some(20_000),
+            max_tdp: Some(95.0),
+            sort: Some("cpumark:desc".parse().unwrap()),
+            top: Some(2),
+            ..Default::default()
+        };
+        let ids = list
+            .query(&query)
+            .data
+            .iter()
+            .map(|cpu| cpu.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, vec![3, 6]);
+
+        assert!("cpumark:sideways".parse::<Sort>().is_err());
+        assert!("name".parse::<Sort>().is_err());
+    }
+
+    #[test]
+    fn test_best_value() {
+        let cpu = |id, price: Option<f64>, cpumark| CPU {
+            id,
+            name: format!("CPU {}", id),
+            price: price.map(Money::from),
+            cpumark,
+            thread: None,
+            socket: String::new(),
+            cat: String::new(),
+            cores: None,
+            logicals: None,
+            tdp: None,
+        };
+        assert_eq!(
+            cpu(1, Some(200.0), Some(20_000)).marks_per_dollar(),
+            Some(100.0)
+        );
+        assert_eq!(cpu(1, Some(0.0), Some(20_000)).marks_per_dollar(), None);
+        assert_eq!(cpu(1, None, Some(20_000)).marks_per_dollar(), None);
+
+        let list = CPUMegaList {
+            data: vec![
+                cpu(1, Some(200.0), Some(20_000)),
+                cpu(2, Some(100.0), Some(15_000)),
+                cpu(3, Some(400.0), Some(60_000)),
+                cpu(4, Some(50.0), None),
+                cpu(5, None, Some(10_000)),
+            ],
+        };
+        let best = list.best_value(&Money::from(300.0));
+        let ranked = best
+            .iter()
+            .map(|valued| (valued.cpu.id, valued.marks_per_dollar))
+            .collect::<Vec<_>>();
+        assert_eq!(ranked, vec![(2, 150.0), (1, 100.0)]);
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_get_historical() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/web/20140601id_/https://www.cpubenchmark.net/CPU_mega_page.html",
+                200,
+                MEGA_PAGE,
+            )
+            .mock(
+                "/web/20200601id_/https://www.cpubenchmark.net/CPU_mega_page.html",
+                200,
+                "<div>loading...</div>",
+            )
+            .mock(
+                "/web/20200601id_/https://www.cpubenchmark.net/data/",
+                200,
+                r#"{"data": [{"id": 1, "name": "AMD Ryzen 5 2600"}, {"id": "x"}]}"#,
+            );
+
+        let archive = wayback::Endpoints { base: server.uri() };
+        let mut client = Client::<true>::default();
+
+        let date = NaiveDate::from_ymd_opt(2014, 6, 1).unwrap();
+        let old =
+            CPUMegaList::get_historical_with(&Endpoints::default(), &archive, &mut client, date)
+                .await
+                .unwrap();
+        assert_eq!(
+            old.archived_at,
+            Utc.with_ymd_and_hms(2014, 6, 1, 0, 0, 0).unwrap()
+        );
+        assert_eq!(old.item.data[0].id, 1907);
+
+        let date = NaiveDate::from_ymd_opt(2020, 6, 1).unwrap();
+        let (new, failures) = CPUMegaList::get_historical_checked_with(
+            &Endpoints::default(),
+            &archive,
+            &mut client,
+            date,
+        )
+        .await
+        .unwrap();
+        assert_eq!(new.url, "https://www.cpubenchmark.net/data/");
+        assert_eq!(new.item.data.len(), 1);
+        assert_eq!(new.item.data[0].name, "AMD Ryzen 5 2600");
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].index, 1);
+    }
 }
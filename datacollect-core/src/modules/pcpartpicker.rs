@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use kuchiki::{parse_html, traits::TendrilSink};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[cfg(feature = "net")]
+use crate::common::{Client, Collected};
+use crate::{
+    common::extract::Field,
+    schemas::computing::{MemorySize, Motherboard},
+};
+
+/// The specifications of a motherboard, from its PCPartPicker product page.
+#[derive(Serialize, JsonSchema, Default)]
+pub struct MotherboardSpecs {
+    pub name: String,
+    pub manufacturer: Option<String>,
+    pub socket: Option<String>,
+    pub chipset: Option<String>,
+    pub form_factor: Option<String>,
+    pub memory_type: Option<String>,
+    pub memory_slots: Option<u32>,
+    /// The most memory the board supports in total.
+    pub max_memory: Option<MemorySize>,
+}
+
+impl MotherboardSpecs {
+    /// Parse a PCPartPicker motherboard product page.
+    ///
+    /// # Errors
+    /// Errors if the page has no product name.
+    pub fn from_html(html: &str) -> anyhow::Result<Self> {
+        let document = &parse_html().one(html);
+        let name = Field::new("h1.pageTitle")
+            .or("h1")
+            .text(document)
+            .context("could not find motherboard name")?;
+
+        /* every spec is a `<h3 class="group__title">` with its value in the next element */
+        let specs: HashMap<String, String> = document
+            .select(".group--spec")
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|group| {
+                let title = group.as_node().select_first(".group__title").ok()?;
+                let content = group.as_node().select_first(".group__content").ok()?;
+                Some((
+                    title.text_contents().trim().to_string(),
+                    content.text_contents().trim().to_string(),
+                ))
+            })
+            .collect();
+        let spec = |title: &str| {
+            specs
+                .get(title)
+                .map(String::as_str)
+                .filter(|v| !v.is_empty() && *v != "None")
+        };
+
+        Ok(Self {
+            name,
+            manufacturer: spec("Manufacturer").map(str::to_string),
+            socket: spec("Socket / CPU").map(str::to_string),
+            chipset: spec("Chipset").map(str::to_string),
+            form_factor: spec("Form Factor").map(str::to_string),
+            memory_type: spec("Memory Type").map(str::to_string),
+            memory_slots: spec("Memory Slots").and_then(|v| v.parse().ok()),
+            max_memory: spec("Memory Max").and_then(|v| v.parse().ok()),
+        })
+    }
+
+    /// Get the specifications of a motherboard from its PCPartPicker product page,
+    /// e.g. `https://pcpartpicker.com/product/9nm323/asus-prime-b550-plus-atx-am4-motherboard-prime-b550-plus`.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn by_url(client: &mut Client<false>, url: &str) -> anyhow::Result<Self> {
+        Ok(Self::by_url_enveloped(client, url).await?.data)
+    }
+
+    /// Like [`MotherboardSpecs::by_url`], recording where and when the specifications were
+    /// collected.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_url_enveloped(
+        client: &mut Client<false>,
+        url: &str,
+    ) -> anyhow::Result<Collected<Self>> {
+        let response = client.get(url).await?.send().await?.error_for_status()?;
+        let specs = Self::from_html(&String::from_utf8_lossy(response.bytes()))?;
+        let url = response.url().to_string();
+        Ok(Collected::from_response(
+            "pcpartpicker",
+            &response,
+            Some(url),
+            specs,
+        ))
+    }
+}
+
+impl From<MotherboardSpecs> for Motherboard {
+    fn from(specs: MotherboardSpecs) -> Self {
+        Self {
+            name: specs.name,
+            manufacturer: specs.manufacturer,
+            socket: specs.socket,
+            chipset: specs.chipset,
+            memory_type: specs.memory_type,
+            memory_slots: specs.memory_slots,
+            max_memory: specs.max_memory,
+            form_factor: specs.form_factor,
+            price: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MotherboardSpecs;
+    use crate::schemas::computing::{
+        compat::{cpu_fits_socket, memory_type_fits},
+        MemorySize, Motherboard, CPU,
+    };
+
+    #[test]
+    fn test_product_page() {
+        let page = r#"
+            <h1 class="pageTitle">Asus PRIME B550-PLUS ATX AM4 Motherboard</h1>
+            <div class="specs">
+                <div class="group group--spec">
+                    <h3 class="group__title">Manufacturer</h3>
+                    <div class="group__content"><p>Asus</p></div>
+                </div>
+                <div class="group group--spec">
+                    <h3 class="group__title">Socket / CPU</h3>
+                    <div class="group__content"><p>AM4</p></div>
+                </div>
+                <div class="group group--spec">
+                    <h3 class="group__title">Form Factor</h3>
+                    <div class="group__content"><p>ATX</p></div>
+                </div>
+                <div class="group group--spec">
+                    <h3 class="group__title">Chipset</h3>
+                    <div class="group__content"><p>AMD B550</p></div>
+                </div>
+                <div class="group group--spec">
+                    <h3 class="group__title">Memory Max</h3>
+                    <div class="group__content"><p>128 GB</p></div>
+                </div>
+                <div class="group group--spec">
+                    <h3 class="group__title">Memory Type</h3>
+                    <div class="group__content"><p>DDR4</p></div>
+                </div>
+                <div class="group group--spec">
+                    <h3 class="group__title">Memory Slots</h3>
+                    <div class="group__content"><p>4</p></div>
+                </div>
+                <div class="group group--spec">
+                    <h3 class="group__title">Wireless Networking</h3>
+                    <div class="group__content"><p>None</p></div>
+                </div>
+            </div>
+        "#;
+
+        let specs = MotherboardSpecs::from_html(page).unwrap();
+        assert_eq!(specs.name, "Asus PRIME B550-PLUS ATX AM4 Motherboard");
+        assert_eq!(specs.manufacturer.as_deref(), Some("Asus"));
+        assert_eq!(specs.chipset.as_deref(), Some("AMD B550"));
+        assert_eq!(specs.form_factor.as_deref(), Some("ATX"));
+        assert_eq!(specs.memory_slots, Some(4));
+        assert_eq!(specs.max_memory, Some(MemorySize(131072.0)));
+
+        let board = Motherboard::from(specs);
+        let cpu = CPU {
+            name: "AMD Ryzen 5 5600X".to_string(),
+            socket: Some("AM4".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(cpu_fits_socket(&cpu, &board), Some(true));
+        assert_eq!(memory_type_fits("DDR5", &board), Some(false));
+    }
+}
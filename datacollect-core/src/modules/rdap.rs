@@ -1,9 +1,19 @@
+#[cfg(feature = "net")]
+use anyhow::bail;
+use anyhow::Context;
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::common::Client;
+#[cfg(feature = "net")]
+use crate::common::{Client, Detail};
 
-#[derive(Deserialize, Serialize, Clone)]
+/// The RDAP server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://rdap.org";
+
+crate::common::endpoints::endpoints!();
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct Event {
     pub event_action: String,
@@ -11,10 +21,258 @@ pub struct Event {
     pub event_date: DateTime<Utc>,
 }
 
-#[derive(Deserialize, Serialize)]
+/// What happened in an [`Event`], from the values registered for RFC 7483.
+#[derive(Serialize, JsonSchema, Clone, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EventAction {
+    Registration,
+    Reregistration,
+    LastChanged,
+    Expiration,
+    Deletion,
+    Reinstantiation,
+    Transfer,
+    Locked,
+    Unlocked,
+    LastUpdateOfRdapDatabase,
+    RegistrarExpiration,
+    EnumValidationExpiration,
+    /// An action RFC 7483 doesn't list, as the server wrote it.
+    Other(String),
+}
+
+impl From<&str> for EventAction {
+    fn from(action: &str) -> Self {
+        match action {
+            "registration" => Self::Registration,
+            "reregistration" => Self::Reregistration,
+            "last changed" => Self::LastChanged,
+            "expiration" => Self::Expiration,
+            "deletion" => Self::Deletion,
+            "reinstantiation" => Self::Reinstantiation,
+            "transfer" => Self::Transfer,
+            "locked" => Self::Locked,
+            "unlocked" => Self::Unlocked,
+            "last update of RDAP database" => Self::LastUpdateOfRdapDatabase,
+            "registrar expiration" => Self::RegistrarExpiration,
+            "enum validation expiration" => Self::EnumValidationExpiration,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+/// An [`Event`] with its action read into an [`EventAction`].
+#[derive(Serialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct DomainEvent {
+    pub action: EventAction,
+    pub actor: Option<String>,
+    pub date: DateTime<Utc>,
+}
+
+impl From<&Event> for DomainEvent {
+    fn from(event: &Event) -> Self {
+        Self {
+            action: event.event_action.as_str().into(),
+            actor: event.event_actor.clone(),
+            date: event.event_date,
+        }
+    }
+}
+
+/// When a domain's current registration started, and when it ends (if the record says).
+#[derive(Serialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct Period {
+    pub from: DateTime<Utc>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Clone)]
+pub struct Link {
+    pub rel: Option<String>,
+    pub href: String,
+}
+
+/// A person or organization related to a domain, e.g. its registrar or registrant.
+#[derive(Deserialize, Serialize, JsonSchema, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Entity {
+    pub handle: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    /// Contact information, as a jCard (RFC 7095).
+    pub vcard_array: Option<serde_json::Value>,
+    #[serde(default)]
+    pub links: Vec<Link>,
+    #[serde(default)]
+    pub events: Vec<Event>,
+    /// e.g. `active`, `validated`.
+    #[serde(default)]
+    pub status: Vec<String>,
+    /// Other entities related to this one, e.g. a registrar's abuse contact.
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+}
+
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EntitySearchResults {
+    #[serde(default)]
+    entity_search_results: Vec<Entity>,
+}
+
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DomainSearchResults {
+    #[serde(default)]
+    domain_search_results: Vec<DomainRecord>,
+}
+
+impl Entity {
+    /// Get an entity's record from a registry, by its handle (e.g. from a domain's
+    /// [`entities`](DomainRecord::entities)). Unlike domains, entities aren't found through
+    /// `rdap.org`, so the registry has to be given.
+    ///
+    /// # Errors
+    /// Errors if sending the request failed, or if the response could not be parsed.
+    /// # Returns
+    /// `Ok(None)` if the registry doesn't know the handle.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn get(
+        client: &mut Client<false>,
+        handle: &str,
+        registry: &Endpoints,
+    ) -> anyhow::Result<Option<Self>> {
+        let res = client
+            .get(&registry.url(&format!("/entity/{}", handle)))
+            .await?
+            .send()
+            .await?;
+        if res.status() == 404 {
+            return Ok(None);
+        }
+
+        Ok(Some(res.error_for_status()?.json().await?))
+    }
+
+    /// Search registries for entities by name (`fn`), e.g. `Example Inc*`; a trailing `*`
+    /// matches any suffix. Registries that don't support entity search (many don't, as it's
+    /// optional) are skipped.
+    ///
+    /// # Errors
+    /// Errors if a request failed, or if a response could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client, registries), err)]
+    pub async fn search(
+        client: &mut Client<false>,
+        name: &str,
+        registries: &[Endpoints],
+    ) -> anyhow::Result<Vec<Self>> {
+        let mut entities = Vec::new();
+        for registry in registries {
+            let res = client
+                .get(&registry.url("/entities"))
+                .await?
+                .query(&[("fn", name)])
+                .send()
+                .await?;
+            if is_unsupported(res.status()) {
+                continue;
+            }
+
+            let results: EntitySearchResults =
+                res.error_for_status()?.json().await.with_context(|| {
+                    format!("could not read search results from {}", registry.base)
+                })?;
+            entities.extend(results.entity_search_results);
+        }
+        Ok(entities)
+    }
+
+    /// The domains this entity is related to at a registry, e.g. the other domains of a
+    /// registrant, using reverse search (RFC 9536). Only a registrar's own domains are
+    /// found, not those it sells.
+    ///
+    /// # Errors
+    /// Errors if the entity has no handle, if the registry doesn't support reverse search, or
+    /// if the request or parsing failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(self, client), fields(handle = ?self.handle), err)]
+    pub async fn domains(
+        &self,
+        client: &mut Client<false>,
+        registry: &Endpoints,
+    ) -> anyhow::Result<Vec<DomainRecord>> {
+        let handle = self.handle.as_deref().context("entity has no handle")?;
+        let res = client
+            .get(&registry.url("/domains/reverse_search/entity"))
+            .await?
+            .query(&[("handle", handle)])
+            .send()
+            .await?;
+        if is_unsupported(res.status()) {
+            bail!("{} does not support reverse search", registry.base);
+        }
+
+        let results: DomainSearchResults = res.error_for_status()?.json().await?;
+        Ok(results.domain_search_results)
+    }
+
+    /// The formatted name (`fn`) from the entity's contact information, if it has one.
+    pub fn name(&self) -> Option<&str> {
+        self.vcard_array
+            .as_ref()?
+            .get(1)?
+            .as_array()?
+            .iter()
+            .find(|property| property.get(0).and_then(|p| p.as_str()) == Some("fn"))?
+            .get(3)?
+            .as_str()
+    }
+
+    /// Follow the entity's `self` link to get its full record.
+    ///
+    /// # Errors
+    /// Errors if the entity has no `self` link, or if the request or parsing failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(self, client), fields(handle = ?self.handle), err)]
+    pub async fn fetch_self(&self, client: &mut Client<false>) -> anyhow::Result<Self> {
+        let link = self
+            .links
+            .iter()
+            .find(|l| l.rel.as_deref() == Some("self"))
+            .ok_or_else(|| anyhow::anyhow!("entity has no self link"))?;
+        client
+            .get(link.href.as_str())
+            .await?
+            .send()
+            .await?
+            .json()
+            .await
+    }
+}
+
+/// Whether a registry answered that it doesn't do a kind of query at all.
+#[cfg(feature = "net")]
+fn is_unsupported(status: reqwest::StatusCode) -> bool {
+    /* RFC 7480 says 501, but registries also answer 400 or 404 */
+    [400, 404, 501].contains(&status.as_u16())
+}
+
+#[derive(Deserialize, Serialize, JsonSchema)]
 pub struct DomainRecord {
     /* TODO: add more fields. see: https://datatracker.ietf.org/doc/html/rfc7483#section-4 */
+    /// The domain's name, e.g. `example.com`.
+    #[serde(rename = "ldhName")]
+    pub ldh_name: Option<String>,
     pub events: Vec<Event>,
+    /// e.g. `active`, `redemption period`, `pending delete`.
+    #[serde(default)]
+    pub status: Vec<String>,
+    #[serde(default)]
+    pub entities: Vec<Entity>,
 }
 
 impl DomainRecord {
@@ -25,49 +283,140 @@ impl DomainRecord {
     /// If the response was a 404, `Ok(None)` is returned. This means that the domain was probably never registered,
     /// or maybe that the TLD was invalid.
     /// Otherwise, the JSON is parsed, and wrapped in `Ok(Some(...))`.
-    pub async fn get(client: &mut Client<false>, domain: &str) -> anyhow::Result<Option<Self>> {
+    ///
+    /// With [`Detail::Minimal`], entities are dropped. With [`Detail::Full`], entities
+    /// without contact information are looked up through their `self` link.
+    #[cfg(feature = "net")]
+    pub async fn get(
+        client: &mut Client<false>,
+        domain: &str,
+        detail: Detail,
+    ) -> anyhow::Result<Option<Self>> {
+        Self::get_with(&Endpoints::default(), client, domain, detail).await
+    }
+
+    /// Like [`DomainRecord::get`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if sending the request failed, or if the response could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn get_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        domain: &str,
+        detail: Detail,
+    ) -> anyhow::Result<Option<Self>> {
         let res = client
-            .0
-            .get(format!("https://rdap.org/domain/{}", domain))
+            .get(&endpoints.url(&format!("/domain/{}", domain)))
+            .await?
             .send()
             .await?;
         if res.status() == 404 {
-            Ok(None)
-        } else {
-            Ok(Some(res.json().await?))
+            return Ok(None);
         }
+
+        let mut record = Self::from_json(&res.text().await?)?;
+        match detail {
+            Detail::Minimal => record.entities.clear(),
+            Detail::Default => {}
+            Detail::Full => {
+                for entity in record.entities.iter_mut() {
+                    if entity.vcard_array.is_none() {
+                        /* not every registry links to (or serves) its entities; keep what we have */
+                        if let Ok(full) = entity.fetch_self(client).await {
+                            *entity = full;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Some(record))
+    }
+
+    /// Parse a domain record, as an RDAP server returns it.
+    ///
+    /// # Errors
+    /// Errors if `json` is not a domain record.
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        serde_json::from_str(json).context("could not parse the domain record")
     }
 
-    fn events_in_time_backwards(&self) -> Vec<Event> {
-        let mut events = self.events.clone();
-        events.sort_by_key(|e| -e.event_date.timestamp_millis());
+    /// The record's events, oldest first.
+    pub fn timeline(&self) -> Vec<DomainEvent> {
+        let mut events = self
+            .events
+            .iter()
+            .map(DomainEvent::from)
+            .collect::<Vec<_>>();
+        events.sort_by_key(|e| e.date);
         events
     }
 
+    /// When the domain expires (or expired): the latest expiration in its record.
+    pub fn expires_at(&self) -> Option<DateTime<Utc>> {
+        self.timeline()
+            .into_iter()
+            .rev()
+            .find(|e| e.action == EventAction::Expiration)
+            .map(|e| e.date)
+    }
+
+    /// The domain's latest registration period: from its latest (re)registration, until it
+    /// expires.
+    pub fn registered_period(&self) -> Option<Period> {
+        let from = self
+            .timeline()
+            .into_iter()
+            .rev()
+            .find(|e| {
+                matches!(
+                    e.action,
+                    EventAction::Registration | EventAction::Reregistration
+                )
+            })?
+            .date;
+        Some(Period {
+            from,
+            until: self.expires_at().filter(|until| *until > from),
+        })
+    }
+
+    /// The last event before `now` that `f` gives an answer for.
+    fn latest_before<F: Fn(&EventAction) -> Option<bool>>(
+        &self,
+        now: &DateTime<Utc>,
+        f: F,
+    ) -> Option<bool> {
+        self.timeline()
+            .iter()
+            .rev()
+            .filter(|e| &e.date < now)
+            .find_map(|e| f(&e.action))
+    }
+
     /// Returns whether the domain is/was/will be "locked" at the given time per RFC7483.
     pub fn is_locked_at(&self, now: &DateTime<Utc>) -> bool {
-        self.events_in_time_backwards()
-            .iter()
-            .filter(|e| &e.event_date < now)
-            .find_map(|e| match e.event_action.as_str() {
-                "locked" => Some(true),
-                "unlocked" => Some(false),
-                _ => None,
-            })
-            .unwrap_or(false)
+        self.latest_before(now, |action| match action {
+            EventAction::Locked => Some(true),
+            EventAction::Unlocked => Some(false),
+            _ => None,
+        })
+        .unwrap_or(false)
     }
 
     /// Returns whether the domain is/was (will be?) registered at the given time.
     pub fn is_registered_at(&self, now: &DateTime<Utc>) -> bool {
-        self.events_in_time_backwards()
-            .iter()
-            .filter(|e| &e.event_date < now)
-            .find_map(|e| match e.event_action.as_str() {
-                "reregistration" | "registration" | "reinstantiation" | "transfer" => Some(true),
-                "expiration" | "deletion" => Some(false),
-                _ => None,
-            })
-            .unwrap_or(false)
+        self.latest_before(now, |action| match action {
+            EventAction::Registration
+            | EventAction::Reregistration
+            | EventAction::Reinstantiation
+            | EventAction::Transfer => Some(true),
+            EventAction::Expiration | EventAction::Deletion => Some(false),
+            _ => None,
+        })
+        .unwrap_or(false)
     }
 
     /// Returns whether the domain is/was/will be unlocked and unregistered at the given time.
@@ -84,29 +433,204 @@ impl DomainRecord {
 
 #[cfg(test)]
 mod tests {
-    use hex::ToHex;
+    use chrono::{TimeZone, Utc};
 
-    use super::DomainRecord;
+    #[cfg(feature = "net")]
+    use super::Endpoints;
+    use super::{DomainRecord, Entity, EventAction, Period};
+    #[cfg(feature = "net")]
+    use crate::{
+        common::{Client, Detail},
+        testing::MockServer,
+    };
 
+    #[cfg(feature = "net")]
+    fn client() -> Client<false> {
+        Client::default()
+            .with_cassette(concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures/rdap.json"))
+            .unwrap()
+    }
+
+    #[cfg(feature = "net")]
     #[tokio::test]
+    #[allow(clippy::bool_assert_comparison)]
     async fn test_google() {
-        let record = DomainRecord::get(&mut Default::default(), "google.com")
+        let record = DomainRecord::get(&mut client(), "google.com", Detail::Default)
             .await
             .unwrap()
             .unwrap();
-        let now = chrono::Utc::now();
+        let now = Utc.with_ymd_and_hms(2021, 12, 1, 0, 0, 0).unwrap();
         assert_eq!(record.is_locked_at(&now), false);
         assert_eq!(record.is_registered_at(&now), true);
         assert_eq!(record.is_buyable_at(&now), false);
     }
 
+    #[test]
+    fn test_timeline() {
+        let record: DomainRecord = serde_json::from_str(
+            r#"{
+                "ldhName": "example.com",
+                "events": [
+                    {"eventAction": "expiration", "eventDate": "2022-08-14T04:00:00Z"},
+                    {"eventAction": "registration", "eventDate": "1995-08-14T04:00:00Z"},
+                    {"eventAction": "last changed", "eventDate": "2021-08-14T07:01:44Z"},
+                    {"eventAction": "last update of RDAP database", "eventDate": "2021-12-01T00:00:00Z"},
+                    {"eventAction": "auction", "eventDate": "2000-01-01T00:00:00Z"}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let actions = record
+            .timeline()
+            .into_iter()
+            .map(|e| e.action)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![
+                EventAction::Registration,
+                EventAction::Other("auction".to_string()),
+                EventAction::LastChanged,
+                EventAction::LastUpdateOfRdapDatabase,
+                EventAction::Expiration,
+            ]
+        );
+
+        let expires = Utc.with_ymd_and_hms(2022, 8, 14, 4, 0, 0).unwrap();
+        assert_eq!(record.expires_at(), Some(expires));
+        assert_eq!(
+            record.registered_period(),
+            Some(Period {
+                from: Utc.with_ymd_and_hms(1995, 8, 14, 4, 0, 0).unwrap(),
+                until: Some(expires),
+            })
+        );
+        assert!(record.is_registered_at(&Utc.with_ymd_and_hms(2021, 12, 1, 0, 0, 0).unwrap()));
+        assert!(record.is_buyable_at(&Utc.with_ymd_and_hms(2022, 9, 1, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_entity_name() {
+        let entity: Entity = serde_json::from_str(
+            r#"{
+                "handle": "292",
+                "roles": ["registrar"],
+                "vcardArray": ["vcard", [
+                    ["version", {}, "text", "4.0"],
+                    ["fn", {}, "text", "MarkMonitor Inc."]
+                ]]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(entity.name(), Some("MarkMonitor Inc."));
+    }
+
+    #[cfg(feature = "net")]
     #[tokio::test]
+    #[allow(clippy::bool_assert_comparison)]
     async fn test_random() {
         // This domain will almost certainly not exist.
-        let domain = format!("{}.net", rand::random::<[u8; 10]>().encode_hex::<String>());
-        let record = DomainRecord::get(&mut Default::default(), domain.as_str())
+        let record = DomainRecord::get(&mut client(), "a5f1c0e9b27d4e3f8a61.net", Detail::Default)
             .await
             .unwrap();
         assert_eq!(record.is_none(), true);
     }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_entity() {
+        let server = MockServer::start().await.unwrap();
+        let registry = Endpoints {
+            base: format!("{}/rdap", server.uri()),
+        };
+        let other = Endpoints {
+            base: format!("{}/other", server.uri()),
+        };
+        server
+            .mock(
+                "/rdap/entity/EX-123",
+                200,
+                r#"{
+                    "objectClassName": "entity",
+                    "handle": "EX-123",
+                    "roles": ["registrant"],
+                    "vcardArray": ["vcard", [["fn", {}, "text", "Example Inc."]]],
+                    "status": ["active"],
+                    "events": [{"eventAction": "registration", "eventDate": "2001-02-03T04:05:06Z"}],
+                    "entities": [{"handle": "EX-ABUSE", "roles": ["abuse"]}]
+                }"#,
+            )
+            .mock("/rdap/entity/NOPE", 404, "")
+            .mock(
+                "/rdap/entities?fn=Example+Inc*",
+                200,
+                r#"{"entitySearchResults": [{"handle": "EX-123"}, {"handle": "EX-456"}]}"#,
+            )
+            .mock("/other/entities?fn=Example+Inc*", 501, "")
+            .mock(
+                "/rdap/domains/reverse_search/entity?handle=EX-123",
+                200,
+                r#"{"domainSearchResults": [
+                    {"ldhName": "example.com", "events": []},
+                    {"ldhName": "example.net", "events": []}
+                ]}"#,
+            );
+
+        let mut client = Client::default();
+        let entity = Entity::get(&mut client, "EX-123", &registry)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entity.name(), Some("Example Inc."));
+        assert_eq!(entity.status, vec!["active"]);
+        assert_eq!(entity.events.len(), 1);
+        assert_eq!(entity.entities[0].roles, vec!["abuse"]);
+        assert!(Entity::get(&mut client, "NOPE", &registry)
+            .await
+            .unwrap()
+            .is_none());
+
+        let found = Entity::search(
+            &mut client,
+            "Example Inc*",
+            &[other.clone(), registry.clone()],
+        )
+        .await
+        .unwrap();
+        let handles = found
+            .iter()
+            .filter_map(|entity| entity.handle.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(handles, vec!["EX-123", "EX-456"]);
+
+        let domains = entity.domains(&mut client, &registry).await.unwrap();
+        let names = domains
+            .iter()
+            .filter_map(|domain| domain.ldh_name.as_deref())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["example.com", "example.net"]);
+        assert!(entity.domains(&mut client, &other).await.is_err());
+    }
+
+    #[cfg(feature = "net")]
+    #[tokio::test]
+    async fn test_endpoints() {
+        let server = MockServer::start().await.unwrap();
+        server.mock("/com/v1/domain/example.com", 404, "");
+
+        let endpoints = Endpoints {
+            base: format!("{}/com/v1/", server.uri()),
+        };
+        let record = DomainRecord::get_with(
+            &endpoints,
+            &mut Client::default(),
+            "example.com",
+            Detail::Default,
+        )
+        .await
+        .unwrap();
+        assert!(record.is_none());
+        assert_eq!(server.requests(), vec!["/com/v1/domain/example.com"]);
+    }
 }
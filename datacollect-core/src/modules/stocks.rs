@@ -0,0 +1,347 @@
+//! Stock prices, from Stooq's CSV downloads.
+
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, Context};
+use chrono::{NaiveDate, NaiveTime, Utc};
+use rust_decimal::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "net")]
+use crate::common::Client;
+use crate::common::{Currency, Money, ParseError};
+
+/// The Stooq server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://stooq.com";
+
+crate::common::endpoints::endpoints!();
+
+/// A stock's prices over one trading day.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Quote {
+    /// The symbol, as Stooq writes it (e.g. `AAPL.US`).
+    pub symbol: String,
+    pub date: NaiveDate,
+    /// When the quote was last updated, in Stooq's time (CET). Only known for the latest quote.
+    pub time: Option<NaiveTime>,
+    pub open: Money,
+    pub high: Money,
+    pub low: Money,
+    /// The last price, for a day that's still trading.
+    pub close: Money,
+    pub volume: Option<u64>,
+}
+
+/// The days to get a stock's history for, both included.
+#[derive(Clone, Copy, Debug)]
+pub struct Range {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+}
+
+impl FromStr for Range {
+    type Err = anyhow::Error;
+
+    /// ## Example
+    /// ```txt
+    /// "2021-01-01..2021-12-31" -> 2021-01-01 to 2021-12-31
+    /// "2021-01-01.."           -> 2021-01-01 to today
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (from, to) = s
+            .split_once("..")
+            .context("expected a range like `2021-01-01..2021-12-31`")?;
+        let from = from.parse().context("bad start date")?;
+        let to = match to {
+            "" => Utc::now().date_naive(),
+            to => to.parse().context("bad end date")?,
+        };
+        if to < from {
+            bail!("the range ends before it starts");
+        }
+        Ok(Self { from, to })
+    }
+}
+
+/* one row of either CSV file; the latest quote also has the symbol and time */
+#[derive(Deserialize)]
+struct Row {
+    #[serde(rename = "Symbol")]
+    symbol: Option<String>,
+    #[serde(rename = "Date")]
+    date: String,
+    #[serde(rename = "Time")]
+    time: Option<String>,
+    #[serde(rename = "Open")]
+    open: String,
+    #[serde(rename = "High")]
+    high: String,
+    #[serde(rename = "Low")]
+    low: String,
+    #[serde(rename = "Close")]
+    close: String,
+    #[serde(rename = "Volume")]
+    volume: Option<String>,
+}
+
+impl Row {
+    /// Stooq writes `N/D` ("no data") in every column for symbols it doesn't know.
+    fn is_empty(&self) -> bool {
+        self.date == "N/D" || self.close == "N/D"
+    }
+
+    fn into_quote(self, symbol: &str) -> anyhow::Result<Quote> {
+        let money = |amount: &str| -> anyhow::Result<Money> {
+            let amount = Decimal::from_str(amount)
+                .map_err(|e| anyhow!("could not read price `{}`: {}", amount, e))?;
+            Ok(Money::new(Currency::USD, amount))
+        };
+
+        Ok(Quote {
+            symbol: self.symbol.unwrap_or_else(|| symbol.to_uppercase()),
+            date: self.date.parse().context("bad date")?,
+            time: self.time.and_then(|time| time.parse().ok()),
+            open: money(&self.open)?,
+            high: money(&self.high)?,
+            low: money(&self.low)?,
+            close: money(&self.close)?,
+            /* indices have no volume */
+            volume: self.volume.and_then(|volume| volume.parse().ok()),
+        })
+    }
+}
+
+/// Stooq's name for a US stock, e.g. `AAPL` -> `aapl.us`.
+///
+/// Prices are read as USD, the only [`Currency`] so far, so other markets are refused.
+#[cfg(feature = "net")]
+fn stooq_symbol(symbol: &str) -> anyhow::Result<String> {
+    let symbol = symbol.trim().to_lowercase();
+    match symbol.split_once('.') {
+        None if !symbol.is_empty() => Ok(format!("{}.us", symbol)),
+        Some((_, "us")) => Ok(symbol),
+        _ => bail!("`{}` is not a US stock symbol (like `AAPL`)", symbol),
+    }
+}
+
+fn rows(csv: &[u8]) -> anyhow::Result<Vec<Row>> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(csv)
+        .deserialize()
+        .enumerate()
+        .map(|(i, row)| {
+            row.map_err(|e| ParseError::caused_by(format!("bad row on line {}", i + 2), e).into())
+        })
+        .collect()
+}
+
+impl Quote {
+    /// Get the latest quote for a US stock (e.g. `AAPL`).
+    ///
+    /// Returns `None` if Stooq doesn't know the symbol.
+    ///
+    /// # Errors
+    /// Errors if the symbol isn't a US one, if the request failed, or if the quote could not
+    /// be parsed.
+    #[cfg(feature = "net")]
+    pub async fn get(client: &mut Client<false>, symbol: &str) -> anyhow::Result<Option<Self>> {
+        Self::get_with(&Endpoints::default(), client, symbol).await
+    }
+
+    /// Like [`Quote::get`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the symbol isn't a US one, if the request failed, or if the quote could not
+    /// be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn get_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        symbol: &str,
+    ) -> anyhow::Result<Option<Self>> {
+        let symbol = stooq_symbol(symbol)?;
+        let res = client
+            .get(&endpoints.url("/q/l/"))
+            .await?
+            .query(&[
+                ("s", symbol.as_str()),
+                /* symbol, date, time, open, high, low, close, volume */
+                ("f", "sd2t2ohlcv"),
+                ("h", ""),
+                ("e", "csv"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Self::from_csv(res.bytes(), &symbol)
+    }
+
+    /// Read the quote from a CSV file of the kind [`Quote::get`] downloads. `symbol` is used
+    /// if the file doesn't name it.
+    ///
+    /// Returns `None` if the file has no data (e.g. the symbol is unknown).
+    ///
+    /// # Errors
+    /// Errors if the quote could not be parsed.
+    pub fn from_csv(csv: &[u8], symbol: &str) -> anyhow::Result<Option<Self>> {
+        rows(csv)
+            .context("could not parse the quote")?
+            .into_iter()
+            .find(|row| !row.is_empty())
+            .map(|row| row.into_quote(symbol))
+            .transpose()
+    }
+
+    /// Get the daily prices of a US stock (e.g. `AAPL`) over a range of days, oldest first.
+    /// Days the market was closed are left out.
+    ///
+    /// # Errors
+    /// Errors if the symbol isn't a US one, if the request failed, or if the prices could not
+    /// be parsed.
+    #[cfg(feature = "net")]
+    pub async fn history(
+        client: &mut Client<false>,
+        symbol: &str,
+        range: Range,
+    ) -> anyhow::Result<Vec<Self>> {
+        Self::history_with(&Endpoints::default(), client, symbol, range).await
+    }
+
+    /// Like [`Quote::history`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the symbol isn't a US one, if the request failed, or if the prices could not
+    /// be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn history_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        symbol: &str,
+        range: Range,
+    ) -> anyhow::Result<Vec<Self>> {
+        let symbol = stooq_symbol(symbol)?;
+        let res = client
+            .get(&endpoints.url("/q/d/l/"))
+            .await?
+            .query(&[
+                ("s", symbol.as_str()),
+                ("d1", &range.from.format("%Y%m%d").to_string()),
+                ("d2", &range.to.format("%Y%m%d").to_string()),
+                ("i", "d"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Self::history_from_csv(res.bytes(), &symbol)
+    }
+
+    /// Read the daily prices from a CSV file of the kind [`Quote::history`] downloads.
+    /// `symbol` is used if the file doesn't name it.
+    ///
+    /// # Errors
+    /// Errors if the prices could not be parsed.
+    pub fn history_from_csv(csv: &[u8], symbol: &str) -> anyhow::Result<Vec<Self>> {
+        /* unknown symbols and empty ranges are a plain-text "No data" instead of a CSV file */
+        if csv.trim_ascii().eq_ignore_ascii_case(b"no data") {
+            return Ok(Vec::new());
+        }
+        rows(csv)
+            .context("could not parse the price history")?
+            .into_iter()
+            .filter(|row| !row.is_empty())
+            .map(|row| row.into_quote(symbol))
+            .collect()
+    }
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        common::{Client, Money},
+        testing::MockServer,
+    };
+
+    use super::{stooq_symbol, Endpoints, Quote, Range};
+
+    #[test]
+    fn test_symbol_and_range() {
+        assert_eq!(stooq_symbol("AAPL").unwrap(), "aapl.us");
+        assert_eq!(stooq_symbol("msft.us").unwrap(), "msft.us");
+        assert!(stooq_symbol("sap.de").is_err());
+
+        let range: Range = "2021-01-04..2021-01-08".parse().unwrap();
+        assert_eq!(range.from, NaiveDate::from_ymd_opt(2021, 1, 4).unwrap());
+        assert!("2021-01-08..2021-01-04".parse::<Range>().is_err());
+        assert!("2021-01-04".parse::<Range>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_quote() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/q/l/?s=aapl.us&f=sd2t2ohlcv&h=&e=csv",
+                200,
+                "Symbol,Date,Time,Open,High,Low,Close,Volume\r\n\
+                 AAPL.US,2021-01-08,22:00:01,132.43,132.63,130.23,132.05,105158245\r\n",
+            )
+            .mock(
+                "/q/l/?s=nope.us&f=sd2t2ohlcv&h=&e=csv",
+                200,
+                "Symbol,Date,Time,Open,High,Low,Close,Volume\r\n\
+                 NOPE.US,N/D,N/D,N/D,N/D,N/D,N/D,N/D\r\n",
+            )
+            .mock(
+                "/q/d/l/?s=aapl.us&d1=20210104&d2=20210106&i=d",
+                200,
+                "Date,Open,High,Low,Close,Volume\r\n\
+                 2021-01-04,133.52,133.6116,126.76,129.41,143301887\r\n\
+                 2021-01-05,128.89,131.74,128.43,131.01,97664898\r\n\
+                 2021-01-06,127.72,131.0499,126.382,126.6,155087970\r\n",
+            )
+            .mock(
+                "/q/d/l/?s=nope.us&d1=20210104&d2=20210106&i=d",
+                200,
+                "No data",
+            );
+
+        let endpoints = Endpoints { base: server.uri() };
+        let mut client = Client::default();
+        let quote = Quote::get_with(&endpoints, &mut client, "AAPL")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(quote.symbol, "AAPL.US");
+        assert_eq!(quote.close, Money::from(132.05));
+        assert_eq!(quote.volume, Some(105158245));
+        assert!(quote.time.is_some());
+        assert!(Quote::get_with(&endpoints, &mut client, "nope")
+            .await
+            .unwrap()
+            .is_none());
+
+        let range = "2021-01-04..2021-01-06".parse().unwrap();
+        let history = Quote::history_with(&endpoints, &mut client, "AAPL", range)
+            .await
+            .unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].symbol, "AAPL.US");
+        assert_eq!(
+            history[2].date,
+            NaiveDate::from_ymd_opt(2021, 1, 6).unwrap()
+        );
+        assert_eq!(history[2].low.amount().to_string(), "126.382");
+        assert!(Quote::history_with(&endpoints, &mut client, "nope", range)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
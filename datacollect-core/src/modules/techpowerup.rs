@@ -0,0 +1,288 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use kuchiki::{iter::NodeIterator, parse_html, traits::TendrilSink};
+use lazy_static::lazy_static;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[cfg(feature = "net")]
+use crate::common::{Client, Collected};
+use crate::{
+    common::extract::Field,
+    schemas::computing::{number_and_unit, Frequency, MemorySize, Power, CPU},
+};
+
+/// The TechPowerUp server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://www.techpowerup.com";
+
+crate::common::endpoints::endpoints!();
+
+/// A CPU found through TechPowerUp's CPU database search.
+#[derive(Serialize, JsonSchema)]
+pub struct SearchResult {
+    pub name: String,
+    /// A link to the CPU's spec page.
+    pub url: String,
+}
+
+/// The specifications of a CPU, from its TechPowerUp spec page.
+#[derive(Serialize, JsonSchema, Default)]
+pub struct CPUSpecs {
+    pub name: String,
+    pub socket: Option<String>,
+    pub cores: Option<u32>,
+    pub threads: Option<u32>,
+    /// Base clock speed.
+    pub base_clock: Option<Frequency>,
+    /// Maximum boost clock speed.
+    pub boost_clock: Option<Frequency>,
+    /// L1 cache size, as listed (usually per core).
+    pub l1_cache: Option<MemorySize>,
+    /// L2 cache size, as listed (usually per core).
+    pub l2_cache: Option<MemorySize>,
+    /// L3 cache size.
+    pub l3_cache: Option<MemorySize>,
+    pub tdp: Option<Power>,
+    /// Process size, in nm.
+    pub lithography: Option<u32>,
+    pub release_date: Option<NaiveDate>,
+    pub integrated_graphics: Option<String>,
+}
+
+/// "Apr 19th, 2018" -> 2018-04-19
+fn parse_release_date(s: &str) -> Option<NaiveDate> {
+    lazy_static! {
+        static ref RE_ORDINAL: regex::Regex = regex::Regex::new(r"([0-9])(?:st|nd|rd|th)").unwrap();
+    }
+
+    NaiveDate::parse_from_str(RE_ORDINAL.replace(s.trim(), "$1").as_ref(), "%b %d, %Y").ok()
+}
+
+impl CPUSpecs {
+    /// Parse a TechPowerUp CPU spec page.
+    ///
+    /// # Errors
+    /// Errors if the page has no CPU name.
+    pub fn from_html(html: &str) -> anyhow::Result<Self> {
+        let document = &parse_html().one(html);
+        let name = Field::new("h1.cpuname")
+            .or("h1")
+            .text(document)
+            .context("could not find CPU name")?;
+
+        /* every spec is a `<th>Label:</th><td>value</td>` row */
+        let rows: HashMap<String, String> = document
+            .select("tr")
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|tr| {
+                let th = tr.as_node().select_first("th").ok()?;
+                let td = th.as_node().following_siblings().elements().next()?;
+                Some((
+                    th.text_contents().trim().trim_end_matches(':').to_string(),
+                    td.text_contents().trim().to_string(),
+                ))
+            })
+            .collect();
+        let row = |label: &str| {
+            rows.get(label)
+                .map(String::as_str)
+                .filter(|v| !v.is_empty() && *v != "N/A" && *v != "Unknown")
+        };
+
+        Ok(Self {
+            name,
+            socket: row("Socket").map(str::to_string),
+            cores: row("# of Cores").and_then(|v| v.parse().ok()),
+            threads: row("# of Threads").and_then(|v| v.parse().ok()),
+            base_clock: row("Frequency").and_then(|v| v.parse().ok()),
+            boost_clock: row("Turbo Clock").and_then(|v| v.parse().ok()),
+            l1_cache: row("Cache L1").and_then(|v| v.parse().ok()),
+            l2_cache: row("Cache L2").and_then(|v| v.parse().ok()),
+            l3_cache: row("Cache L3").and_then(|v| v.parse().ok()),
+            tdp: row("TDP").and_then(|v| v.parse().ok()),
+            lithography: row("Process Size")
+                .and_then(number_and_unit)
+                .map(|(n, _)| n as u32),
+            release_date: row("Release Date").and_then(parse_release_date),
+            integrated_graphics: row("Integrated Graphics").map(str::to_string),
+        })
+    }
+
+    /// Get the specifications of a CPU from its TechPowerUp spec page,
+    /// e.g. `https://www.techpowerup.com/cpu-specs/ryzen-5-2600.c2011`.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn by_url(client: &mut Client<false>, url: &str) -> anyhow::Result<Self> {
+        Ok(Self::by_url_enveloped(client, url).await?.data)
+    }
+
+    /// Like [`CPUSpecs::by_url`], recording where and when the specifications were collected.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_url_enveloped(
+        client: &mut Client<false>,
+        url: &str,
+    ) -> anyhow::Result<Collected<Self>> {
+        let response = client.get(url).await?.send().await?;
+        let specs = Self::from_html(&String::from_utf8_lossy(response.bytes()))?;
+        let url = response.url().to_string();
+        Ok(Collected::from_response(
+            "techpowerup",
+            &response,
+            Some(url),
+            specs,
+        ))
+    }
+
+    /// Search TechPowerUp's CPU database by name.
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    #[cfg(feature = "net")]
+    pub async fn search(
+        client: &mut Client<false>,
+        query: &str,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        Self::search_with(&Endpoints::default(), client, query).await
+    }
+
+    /// Like [`CPUSpecs::search`], using the given [`Endpoints`].
+    /// The links in the results point at the same server.
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn search_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        query: &str,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        lazy_static! {
+            static ref RE_SPEC: regex::Regex =
+                regex::Regex::new(r"^/cpu-specs/[^/]+\.c[0-9]+$").unwrap();
+        }
+
+        let text = client
+            .get(&endpoints.url("/cpu-specs/"))
+            .await?
+            .query(&[("ajaxsrch", query)])
+            .send()
+            .await?
+            .text()
+            .await?;
+
+        Ok(parse_html()
+            .one(text)
+            .select("a[href]")
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|a| {
+                let href = a.attributes.borrow().get("href")?.to_string();
+                RE_SPEC.is_match(&href).then(|| SearchResult {
+                    name: a.text_contents().trim().to_string(),
+                    url: endpoints.url(&href),
+                })
+            })
+            .collect())
+    }
+
+    /// Fill in the fields of `cpu` that are missing, using these specifications.
+    ///
+    /// Fields that `cpu` already has are left alone.
+    pub fn merge_into(&self, cpu: &mut CPU) {
+        fn fill<T: Clone>(field: &mut Option<T>, value: &Option<T>) {
+            if field.is_none() {
+                *field = value.clone();
+            }
+        }
+
+        fill(&mut cpu.socket, &self.socket);
+        fill(&mut cpu.cores, &self.cores);
+        fill(&mut cpu.threads, &self.threads);
+        fill(&mut cpu.tdp, &self.tdp);
+        fill(&mut cpu.base_clock, &self.base_clock);
+        fill(&mut cpu.boost_clock, &self.boost_clock);
+        fill(&mut cpu.l1_cache, &self.l1_cache);
+        fill(&mut cpu.l2_cache, &self.l2_cache);
+        fill(&mut cpu.l3_cache, &self.l3_cache);
+        fill(&mut cpu.integrated_graphics, &self.integrated_graphics);
+        fill(&mut cpu.release_date, &self.release_date);
+        fill(&mut cpu.lithography, &self.lithography);
+    }
+}
+
+impl From<CPUSpecs> for CPU {
+    fn from(specs: CPUSpecs) -> Self {
+        let mut cpu = CPU {
+            name: specs.name.clone(),
+            ..Default::default()
+        };
+        specs.merge_into(&mut cpu);
+        cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::CPUSpecs;
+    use crate::schemas::computing::{Frequency, MemorySize, Power, CPU};
+
+    #[test]
+    fn test_spec_page() {
+        let page = r#"
+            <h1 class="cpuname">AMD Ryzen 5 2600</h1>
+            <section class="details">
+                <table>
+                    <tr><th>Socket:</th><td>AMD Socket AM4</td></tr>
+                    <tr><th>Process Size:</th><td>12 nm</td></tr>
+                    <tr><th>Release Date:</th><td>Apr 19th, 2018</td></tr>
+                    <tr><th>Frequency:</th><td>3.4 GHz</td></tr>
+                    <tr><th>Turbo Clock:</th><td>up to 3.9 GHz</td></tr>
+                    <tr><th>TDP:</th><td>65 W</td></tr>
+                    <tr><th># of Cores:</th><td>6</td></tr>
+                    <tr><th># of Threads:</th><td>12</td></tr>
+                    <tr><th>Integrated Graphics:</th><td>N/A</td></tr>
+                    <tr><th>Cache L1:</th><td>96 KB (per core)</td></tr>
+                    <tr><th>Cache L3:</th><td>16 MB (shared)</td></tr>
+                </table>
+            </section>
+        "#;
+
+        let specs = CPUSpecs::from_html(page).unwrap();
+        assert_eq!(specs.name, "AMD Ryzen 5 2600");
+        assert_eq!(specs.socket.as_deref(), Some("AMD Socket AM4"));
+        assert_eq!(specs.lithography, Some(12));
+        assert_eq!(
+            specs.release_date,
+            Some(NaiveDate::from_ymd_opt(2018, 4, 19).unwrap())
+        );
+        assert_eq!(specs.base_clock, Some(Frequency(3400.0)));
+        assert_eq!(specs.boost_clock, Some(Frequency(3900.0)));
+        assert_eq!(specs.threads, Some(12));
+        assert_eq!(specs.integrated_graphics, None);
+        assert_eq!(specs.l1_cache, Some(MemorySize(0.09375)));
+        assert_eq!(specs.l3_cache, Some(MemorySize(16.0)));
+
+        let mut cpu = CPU {
+            name: specs.name.clone(),
+            tdp: Some(Power(60.0)),
+            ..Default::default()
+        };
+        specs.merge_into(&mut cpu);
+        assert_eq!(cpu.tdp, Some(Power(60.0)));
+        assert_eq!(cpu.boost_clock, Some(Frequency(3900.0)));
+    }
+}
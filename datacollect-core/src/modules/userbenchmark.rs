@@ -0,0 +1,264 @@
+use std::str::FromStr;
+
+use anyhow::bail;
+#[cfg(feature = "net")]
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "net")]
+use crate::common::Client;
+
+pub use crate::common::{ParseError, ParseFailure};
+
+/// The UserBenchmark server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://www.userbenchmark.com";
+
+crate::common::endpoints::endpoints!();
+
+/// The kinds of parts UserBenchmark publishes a list of.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Category {
+    CPU,
+    GPU,
+    SSD,
+}
+
+impl Category {
+    /// The name of the category's list, e.g. `CPU_UserBenchmarks.csv`.
+    #[cfg(feature = "net")]
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::CPU => "CPU_UserBenchmarks.csv",
+            Self::GPU => "GPU_UserBenchmarks.csv",
+            Self::SSD => "SSD_UserBenchmarks.csv",
+        }
+    }
+}
+
+impl FromStr for Category {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cpu" => Ok(Self::CPU),
+            "gpu" => Ok(Self::GPU),
+            "ssd" => Ok(Self::SSD),
+            _ => bail!("no such category (expected `cpu`, `gpu` or `ssd`)"),
+        }
+    }
+}
+
+/// A part from one of UserBenchmark's lists.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Part {
+    #[serde(rename = "Type")]
+    pub category: Category,
+    #[serde(rename = "Part Number")]
+    pub part_number: String,
+    #[serde(rename = "Brand")]
+    pub brand: String,
+    #[serde(rename = "Model")]
+    pub model: String,
+    /// The part's place in the list, from 1 for the fastest.
+    #[serde(rename = "Rank")]
+    pub rank: u32,
+    /// The part's effective speed, in percent of a reference part.
+    #[serde(rename = "Benchmark")]
+    pub benchmark: f64,
+    /// How many users' benchmark runs the score was computed from.
+    #[serde(rename = "Samples")]
+    pub samples: u32,
+    /// The part's page on UserBenchmark.
+    #[serde(rename = "URL")]
+    pub url: String,
+}
+
+impl Part {
+    /// The brand and model, e.g. `Intel Core i9-9900K`.
+    pub fn name(&self) -> String {
+        format!("{} {}", self.brand, self.model)
+    }
+
+    /// Get the list of every part in a category, from the CSV file UserBenchmark publishes.
+    /// Parts that can't be read are left out, with a warning for each.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the list could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn list(client: &mut Client<false>, category: Category) -> anyhow::Result<Vec<Self>> {
+        Self::list_with(&Endpoints::default(), client, category).await
+    }
+
+    /// Like [`Part::list`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the list could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn list_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        category: Category,
+    ) -> anyhow::Result<Vec<Self>> {
+        let (parts, failures) = Self::list_checked_with(endpoints, client, category).await?;
+        warn_failures(&failures);
+        Ok(parts)
+    }
+
+    /// Like [`Part::list`], but also returns the parts that couldn't be read, so that
+    /// changes to UserBenchmark's format don't go unnoticed.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the list could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn list_checked(
+        client: &mut Client<false>,
+        category: Category,
+    ) -> anyhow::Result<(Vec<Self>, Vec<ParseFailure>)> {
+        Self::list_checked_with(&Endpoints::default(), client, category).await
+    }
+
+    /// Like [`Part::list_checked`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the list could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn list_checked_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        category: Category,
+    ) -> anyhow::Result<(Vec<Self>, Vec<ParseFailure>)> {
+        let res = client
+            .get(&endpoints.url(&format!("/resources/download/csv/{}", category.file_name())))
+            .await?
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Self::parse(res.bytes())
+            .with_context(|| format!("could not parse {}", category.file_name()))
+    }
+
+    /// Parse one of the CSV files UserBenchmark publishes. Parts that can't be read are
+    /// left out of the list, and returned alongside it.
+    ///
+    /// # Errors
+    /// Errors if the header of the file could not be read.
+    pub fn parse(csv: &[u8]) -> anyhow::Result<(Vec<Self>, Vec<ParseFailure>)> {
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(csv);
+        let headers = reader
+            .headers()
+            .map_err(|e| ParseError::caused_by("could not read the header", e))?
+            .clone();
+
+        let mut parts = Vec::new();
+        let mut failures = Vec::new();
+        for (index, part) in reader.deserialize().enumerate() {
+            match part {
+                Ok(part) => parts.push(part),
+                Err(e) => failures.push(match e.kind() {
+                    csv::ErrorKind::Deserialize { err, .. } => ParseFailure {
+                        index,
+                        /* the column, where the error is about one */
+                        path: err
+                            .field()
+                            .and_then(|field| headers.get(field as usize))
+                            .unwrap_or(".")
+                            .to_string(),
+                        message: err.kind().to_string(),
+                    },
+                    _ => ParseFailure {
+                        index,
+                        path: ".".to_string(),
+                        message: e.to_string(),
+                    },
+                }),
+            }
+        }
+        Ok((parts, failures))
+    }
+
+    /// Like [`Part::parse`], leaving out the parts that can't be read with a warning for
+    /// each, as [`Part::list`] does.
+    ///
+    /// # Errors
+    /// Errors if the header of the file could not be read.
+    pub fn from_csv(csv: &[u8]) -> anyhow::Result<Vec<Self>> {
+        let (parts, failures) = Self::parse(csv)?;
+        warn_failures(&failures);
+        Ok(parts)
+    }
+}
+
+/// Log the parts that [`Part::parse`] left out of the list.
+fn warn_failures(failures: &[ParseFailure]) {
+    for failure in failures {
+        tracing::warn!(
+            "left out part {} of the UserBenchmark list: {}: {}",
+            failure.index,
+            failure.path,
+            failure.message
+        );
+    }
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use crate::{
+        common::Client,
+        schemas::computing::{CPUBenchmarkMetric, CPU},
+        testing::MockServer,
+    };
+
+    use super::{Category, Endpoints, Part};
+
+    const CPUS: &str = "Type,Part Number,Brand,Model,Rank,Benchmark,Samples,URL
+CPU,BX80684I99900K,Intel,Core i9-9900K,6,101,201637,https://cpu.userbenchmark.com/Intel-Core-i9-9900K/Rating/4028
+CPU,100-100000065BOX,AMD,Ryzen 5 5600X,15,95.3,55129,https://cpu.userbenchmark.com/AMD-Ryzen-5-5600X/Rating/4084
+";
+
+    #[tokio::test]
+    async fn test_list() {
+        let server = MockServer::start().await.unwrap();
+        server.mock("/resources/download/csv/CPU_UserBenchmarks.csv", 200, CPUS);
+
+        let endpoints = Endpoints { base: server.uri() };
+        let parts = Part::list_with(&endpoints, &mut Client::default(), Category::CPU)
+            .await
+            .unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(parts[1].name(), "AMD Ryzen 5 5600X");
+        assert_eq!((parts[1].rank, parts[1].samples), (15, 55129));
+
+        let cpu = CPU::from(parts[1].clone());
+        let benchmark = &cpu.benchmarks[&CPUBenchmarkMetric::UserBenchmark];
+        assert_eq!(benchmark.score, Some(95.3));
+        assert_eq!(benchmark.samples, Some(55129));
+
+        assert!(
+            Part::list_with(&endpoints, &mut Client::default(), Category::GPU)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let csv = format!(
+            "{}CPU,X,AMD,Ryzen 5 3600,first,80.1,100,https://cpu.userbenchmark.com/\nCPU,Y,AMD\n",
+            CPUS
+        );
+        let (parts, failures) = Part::parse(csv.as_bytes()).unwrap();
+        assert_eq!(parts.len(), 2);
+        assert_eq!(
+            failures
+                .iter()
+                .map(|f| (f.index, f.path.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(2, "Rank"), (3, ".")]
+        );
+        assert_eq!(Part::from_csv(csv.as_bytes()).unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,152 @@
+//! Old copies of pages from the Internet Archive's Wayback Machine.
+
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+use crate::common::Client;
+
+/// The Wayback Machine server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://web.archive.org";
+
+crate::common::endpoints::endpoints!();
+
+/// Something read from an archived copy of a page.
+#[derive(Serialize)]
+pub struct Archived<T> {
+    /// When the copy was archived. This is the copy closest to the date asked for, so it
+    /// may be some time before or after it.
+    pub archived_at: DateTime<Utc>,
+    /// The page that was archived.
+    pub url: String,
+    pub item: T,
+}
+
+/// An archived copy of a page, as it was originally served.
+pub struct Snapshot {
+    pub archived_at: DateTime<Utc>,
+    pub url: String,
+    pub body: String,
+}
+
+impl Snapshot {
+    /// Get the archived copy of `url` closest to `date`.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page was never archived.
+    pub async fn closest<const COOKIES: bool>(
+        client: &mut Client<COOKIES>,
+        url: &str,
+        date: NaiveDate,
+    ) -> anyhow::Result<Self> {
+        Self::closest_with(&Endpoints::default(), client, url, date).await
+    }
+
+    /// Like [`Snapshot::closest`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the page was never archived.
+    #[tracing::instrument(skip(client), err)]
+    pub async fn closest_with<const COOKIES: bool>(
+        endpoints: &Endpoints,
+        client: &mut Client<COOKIES>,
+        url: &str,
+        date: NaiveDate,
+    ) -> anyhow::Result<Self> {
+        /* `id_` asks for the page as it was served, without the archive's toolbar and
+         * rewritten links; the server redirects to the closest copy it has */
+        let res = client
+            .get(&endpoints.url(&format!("/web/{}id_/{}", date.format("%Y%m%d"), url)))
+            .await?
+            .send()
+            .await?
+            .error_for_status()
+            .with_context(|| format!("could not find an archived copy of {}", url))?;
+
+        let archived_at = archived_at(res.url().path())
+            .with_context(|| format!("could not tell when {} was archived", res.url()))?;
+        Ok(Self {
+            archived_at,
+            url: url.to_string(),
+            body: res.text().await?,
+        })
+    }
+
+    /// Wrap something read from this snapshot with where it came from.
+    pub fn archived<T>(&self, item: T) -> Archived<T> {
+        Archived {
+            archived_at: self.archived_at,
+            url: self.url.clone(),
+            item,
+        }
+    }
+}
+
+/// Read the time a copy was archived from its path, e.g. `/web/20150101093012id_/...`.
+/// The server accepts shorter timestamps (e.g. just the date), so those are read too.
+fn archived_at(path: &str) -> Option<DateTime<Utc>> {
+    lazy_static! {
+        static ref RE_TIMESTAMP: regex::Regex = regex::Regex::new(r"^/web/([0-9]{4,14})").unwrap();
+    }
+
+    let timestamp = RE_TIMESTAMP.captures(path)?.get(1)?.as_str();
+    /* e.g. 2015 -> 20150101000000 */
+    let padded = format!("{}{}", timestamp, &"00000101000000"[timestamp.len()..]);
+    NaiveDateTime::parse_from_str(&padded, "%Y%m%d%H%M%S")
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::{archived_at, Endpoints, Snapshot};
+    use crate::{common::Client, testing::MockServer};
+
+    #[test]
+    fn test_archived_at() {
+        assert_eq!(
+            archived_at("/web/20150101093012id_/https://example.com/"),
+            Some(Utc.with_ymd_and_hms(2015, 1, 1, 9, 30, 12).unwrap())
+        );
+        assert_eq!(
+            archived_at("/web/201503id_/https://example.com/"),
+            Some(Utc.with_ymd_and_hms(2015, 3, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(archived_at("/https://example.com/"), None);
+    }
+
+    #[tokio::test]
+    async fn test_closest() {
+        let server = MockServer::start().await.unwrap();
+        server.mock("/web/20150101id_/https://example.com/", 200, "<p>old</p>");
+
+        let endpoints = Endpoints { base: server.uri() };
+        let date = NaiveDate::from_ymd_opt(2015, 1, 1).unwrap();
+        let snapshot = Snapshot::closest_with(
+            &endpoints,
+            &mut Client::<false>::default(),
+            "https://example.com/",
+            date,
+        )
+        .await
+        .unwrap();
+        assert_eq!(snapshot.body, "<p>old</p>");
+        assert_eq!(
+            snapshot.archived_at,
+            Utc.with_ymd_and_hms(2015, 1, 1, 0, 0, 0).unwrap()
+        );
+
+        let date = NaiveDate::from_ymd_opt(1990, 1, 1).unwrap();
+        assert!(Snapshot::closest_with(
+            &endpoints,
+            &mut Client::<false>::default(),
+            "https://example.com/",
+            date
+        )
+        .await
+        .is_err());
+    }
+}
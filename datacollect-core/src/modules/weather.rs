@@ -0,0 +1,371 @@
+//! Weather observations in the US, from the National Weather Service's API.
+
+#[cfg(feature = "net")]
+use anyhow::bail;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+#[cfg(feature = "net")]
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "net")]
+use crate::common::Client;
+
+/// The NWS API server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://api.weather.gov";
+
+crate::common::endpoints::endpoints!();
+
+/* the API refuses requests without a User-Agent, and asks for a way to get in touch */
+#[cfg(feature = "net")]
+const USER_AGENT: &str = "datacollect (https://github.com/hle0/datacollect)";
+
+/// A temperature.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+pub struct Temperature {
+    pub celsius: f64,
+}
+
+impl Temperature {
+    pub fn fahrenheit(self) -> f64 {
+        self.celsius * 9.0 / 5.0 + 32.0
+    }
+}
+
+/// The wind at a station. Each part is `None` when the station didn't report it.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Default, Debug)]
+pub struct Wind {
+    pub speed_kmh: Option<f64>,
+    pub gust_kmh: Option<f64>,
+    /// Where the wind is blowing from, in degrees clockwise from north.
+    pub direction: Option<f64>,
+}
+
+/// A weather station.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Station {
+    /// The station's identifier, e.g. `KMHK`.
+    pub id: String,
+    pub name: String,
+}
+
+/// The weather at a station, as last observed.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Observation {
+    pub station: Station,
+    pub observed_at: DateTime<Utc>,
+    /// A short description, e.g. `Mostly Cloudy`.
+    pub conditions: Option<String>,
+    pub temperature: Option<Temperature>,
+    pub dewpoint: Option<Temperature>,
+    pub wind: Wind,
+    /// Relative humidity, in percent.
+    pub humidity: Option<f64>,
+    /// Barometric pressure, in pascals.
+    pub pressure: Option<f64>,
+    /// Visibility, in meters.
+    pub visibility: Option<f64>,
+}
+
+/* the API's responses are GeoJSON, with everything of interest under `properties` */
+#[derive(Deserialize)]
+struct Feature<T> {
+    properties: T,
+}
+
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Point {
+    grid_id: String,
+    grid_x: u32,
+    grid_y: u32,
+}
+
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+struct Stations {
+    features: Vec<Feature<StationData>>,
+}
+
+#[cfg(feature = "net")]
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StationData {
+    station_identifier: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ObservationData {
+    timestamp: DateTime<Utc>,
+    text_description: Option<String>,
+    #[serde(default)]
+    temperature: Quantity,
+    #[serde(default)]
+    dewpoint: Quantity,
+    #[serde(default)]
+    wind_direction: Quantity,
+    #[serde(default)]
+    wind_speed: Quantity,
+    #[serde(default)]
+    wind_gust: Quantity,
+    #[serde(default)]
+    barometric_pressure: Quantity,
+    #[serde(default)]
+    visibility: Quantity,
+    #[serde(default)]
+    relative_humidity: Quantity,
+}
+
+/// A measurement, e.g. `{"unitCode": "wmoUnit:degC", "value": 12.2}`. The value is `null`
+/// when the station didn't report it.
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Quantity {
+    #[serde(default)]
+    unit_code: String,
+    value: Option<f64>,
+}
+
+impl Quantity {
+    fn unit(&self) -> &str {
+        self.unit_code.trim_start_matches("wmoUnit:")
+    }
+
+    fn temperature(&self) -> Option<Temperature> {
+        let value = self.value?;
+        let celsius = match self.unit() {
+            "degF" => (value - 32.0) * 5.0 / 9.0,
+            "K" => value - 273.15,
+            _ => value,
+        };
+        Some(Temperature { celsius })
+    }
+
+    fn speed_kmh(&self) -> Option<f64> {
+        let value = self.value?;
+        Some(match self.unit() {
+            "m_s-1" => value * 3.6,
+            "km_h-1" => value,
+            /* knots */
+            "kn" => value * 1.852,
+            _ => value,
+        })
+    }
+}
+
+/// Format a coordinate the way the API wants it: at most 4 decimal places, with no
+/// trailing zeroes (it redirects requests for anything else).
+#[cfg(feature = "net")]
+fn coordinate(value: f64) -> String {
+    let value = format!("{:.4}", value);
+    value
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+#[cfg(feature = "net")]
+async fn get<T: DeserializeOwned>(client: &mut Client<false>, url: &str) -> anyhow::Result<T> {
+    client
+        .get(url)
+        .await?
+        .header("User-Agent", USER_AGENT)
+        .header("Accept", "application/geo+json")
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await
+}
+
+impl Observation {
+    /// Get the latest observation from the weather station closest to a place in the US.
+    ///
+    /// # Errors
+    /// Errors if the place isn't covered by the NWS, if it has no stations, or if a request
+    /// failed.
+    #[cfg(feature = "net")]
+    pub async fn current(
+        client: &mut Client<false>,
+        latitude: f64,
+        longitude: f64,
+    ) -> anyhow::Result<Self> {
+        Self::current_with(&Endpoints::default(), client, latitude, longitude).await
+    }
+
+    /// Like [`Observation::current`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the place isn't covered by the NWS, if it has no stations, or if a request
+    /// failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn current_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        latitude: f64,
+        longitude: f64,
+    ) -> anyhow::Result<Self> {
+        /* points -> gridpoint -> stations (closest first) -> latest observation */
+        let point: Feature<Point> = get(
+            client,
+            &endpoints.url(&format!(
+                "/points/{},{}",
+                coordinate(latitude),
+                coordinate(longitude)
+            )),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "no forecast area for {},{} (only the US is covered)",
+                latitude, longitude
+            )
+        })?;
+        let point = point.properties;
+
+        let stations: Stations = get(
+            client,
+            &endpoints.url(&format!(
+                "/gridpoints/{}/{},{}/stations",
+                point.grid_id, point.grid_x, point.grid_y
+            )),
+        )
+        .await
+        .context("could not get the area's weather stations")?;
+        let station = match stations.features.into_iter().next() {
+            Some(station) => station.properties,
+            None => bail!(
+                "there are no weather stations near {},{}",
+                latitude,
+                longitude
+            ),
+        };
+
+        let observation: Feature<ObservationData> = get(
+            client,
+            &endpoints.url(&format!(
+                "/stations/{}/observations/latest",
+                station.station_identifier
+            )),
+        )
+        .await
+        .with_context(|| {
+            format!(
+                "could not get the latest observation from {}",
+                station.station_identifier
+            )
+        })?;
+        Ok(Self::from_data(
+            observation.properties,
+            Station {
+                id: station.station_identifier,
+                name: station.name,
+            },
+        ))
+    }
+
+    /// Read an observation made at `station`, from the API's response for the station's
+    /// latest (or any other) observation.
+    ///
+    /// # Errors
+    /// Errors if `json` is not an observation.
+    pub fn from_json(json: &str, station: Station) -> anyhow::Result<Self> {
+        let observation: Feature<ObservationData> =
+            serde_json::from_str(json).context("could not parse the observation")?;
+        Ok(Self::from_data(observation.properties, station))
+    }
+
+    fn from_data(data: ObservationData, station: Station) -> Self {
+        Self {
+            station,
+            observed_at: data.timestamp,
+            conditions: data.text_description.filter(|text| !text.is_empty()),
+            temperature: data.temperature.temperature(),
+            dewpoint: data.dewpoint.temperature(),
+            wind: Wind {
+                speed_kmh: data.wind_speed.speed_kmh(),
+                gust_kmh: data.wind_gust.speed_kmh(),
+                direction: data.wind_direction.value,
+            },
+            humidity: data.relative_humidity.value,
+            pressure: data.barometric_pressure.value,
+            visibility: data.visibility.value,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use crate::{common::Client, testing::MockServer};
+
+    use super::{coordinate, Endpoints, Observation};
+
+    #[tokio::test]
+    async fn test_current() {
+        assert_eq!(coordinate(39.745_62), "39.7456");
+        assert_eq!(coordinate(-97.1), "-97.1");
+        assert_eq!(coordinate(40.0), "40");
+
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/points/39.7456,-97.0892",
+                200,
+                r#"{"properties": {"gridId": "TOP", "gridX": 32, "gridY": 81}}"#,
+            )
+            .mock(
+                "/gridpoints/TOP/32,81/stations",
+                200,
+                r#"{"features": [
+                    {"properties": {"stationIdentifier": "KCNK", "name": "Concordia, Blosser Municipal Airport"}},
+                    {"properties": {"stationIdentifier": "KMYZ", "name": "Marysville Municipal Airport"}}
+                ]}"#,
+            )
+            .mock(
+                "/stations/KCNK/observations/latest",
+                200,
+                r#"{"properties": {
+                    "timestamp": "2021-11-20T14:53:00+00:00",
+                    "textDescription": "Mostly Cloudy",
+                    "temperature": {"unitCode": "wmoUnit:degC", "value": 10, "qualityControl": "V"},
+                    "dewpoint": {"unitCode": "wmoUnit:degC", "value": null, "qualityControl": "Z"},
+                    "windDirection": {"unitCode": "wmoUnit:degree_(angle)", "value": 180},
+                    "windSpeed": {"unitCode": "wmoUnit:m_s-1", "value": 5},
+                    "windGust": {"unitCode": "wmoUnit:km_h-1", "value": null},
+                    "relativeHumidity": {"unitCode": "wmoUnit:percent", "value": 61.2}
+                }}"#,
+            );
+
+        let endpoints = Endpoints { base: server.uri() };
+        let mut client = Client::default();
+        let observation = Observation::current_with(&endpoints, &mut client, 39.7456, -97.0892)
+            .await
+            .unwrap();
+        assert_eq!(observation.station.id, "KCNK");
+        assert_eq!(
+            observation.observed_at,
+            Utc.with_ymd_and_hms(2021, 11, 20, 14, 53, 0).unwrap()
+        );
+        assert_eq!(observation.conditions.as_deref(), Some("Mostly Cloudy"));
+        assert_eq!(observation.temperature.unwrap().fahrenheit(), 50.0);
+        assert!(observation.dewpoint.is_none());
+        assert_eq!(observation.wind.speed_kmh, Some(18.0));
+        assert_eq!(observation.wind.direction, Some(180.0));
+        assert_eq!(observation.humidity, Some(61.2));
+        assert!(observation.pressure.is_none());
+
+        /* e.g. outside the US */
+        assert!(
+            Observation::current_with(&endpoints, &mut client, 51.5, -0.12)
+                .await
+                .is_err()
+        );
+    }
+}
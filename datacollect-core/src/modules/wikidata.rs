@@ -0,0 +1,497 @@
+//! Items and properties from Wikidata, e.g. to join records from other modules on a
+//! common identifier.
+
+use std::collections::{BTreeMap, HashMap};
+
+#[cfg(feature = "net")]
+use anyhow::{bail, Context};
+use chrono::NaiveDate;
+#[cfg(feature = "net")]
+use lazy_static::lazy_static;
+use rust_decimal::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "net")]
+use crate::common::Client;
+
+/// The Wikidata server the module talks to, unless it's given other [`Endpoints`].
+pub const BASE: &str = "https://www.wikidata.org";
+
+crate::common::endpoints::endpoints!();
+
+/// The value of a claim, e.g. `P31` (instance of) -> `Q5` (human).
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum Value {
+    /// Another entity, by its id (e.g. `Q5`).
+    Item(String),
+    String(String),
+    /// An identifier in another database, e.g. a GTIN or an ISBN.
+    ExternalId(String),
+    Url(String),
+    Text {
+        text: String,
+        language: String,
+    },
+    Quantity {
+        #[serde(serialize_with = "rust_decimal::serde::float::serialize")]
+        #[schemars(with = "f64")]
+        amount: Decimal,
+        /// The unit's entity id (e.g. `Q11570` for kilograms), if the quantity has one.
+        unit: Option<String>,
+    },
+    /// A point in time, as Wikidata writes it (e.g. `+1952-03-11T00:00:00Z`); see
+    /// [`Value::date`].
+    Time {
+        time: String,
+        /// How much of `time` is meaningful: 9 for a year, 10 for a month, 11 for a day.
+        precision: u8,
+    },
+    Coordinate {
+        latitude: f64,
+        longitude: f64,
+    },
+}
+
+impl Value {
+    /// The id of the entity, for [`Value::Item`].
+    pub fn as_item(&self) -> Option<&str> {
+        match self {
+            Self::Item(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The text, for values that are plain strings (including identifiers and URLs).
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) | Self::ExternalId(s) | Self::Url(s) => Some(s),
+            Self::Text { text, .. } => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The day, for a [`Value::Time`] precise to the day.
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self {
+            Self::Time { time, precision } if *precision >= 11 => {
+                NaiveDate::parse_from_str(time.trim_start_matches('+').get(..10)?, "%Y-%m-%d").ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+/// An entity (an item like `Q42`, or a property like `P31`) from Wikidata.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct Entity {
+    pub id: String,
+    /// The entity's name in each language it has one in, by language code (e.g. `en`).
+    pub labels: BTreeMap<String, String>,
+    pub descriptions: BTreeMap<String, String>,
+    /// The best-ranked values of each of the entity's properties (e.g. `P31`). Claims with
+    /// unknown or no values are left out.
+    pub claims: BTreeMap<String, Vec<Value>>,
+}
+
+/// An entity found through Wikidata's search.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+pub struct SearchResult {
+    pub id: String,
+    pub label: Option<String>,
+    pub description: Option<String>,
+}
+
+/* the shape of an entity in `Special:EntityData` responses */
+#[derive(Deserialize)]
+struct EntityData {
+    id: String,
+    #[serde(default)]
+    labels: HashMap<String, Text>,
+    #[serde(default)]
+    descriptions: HashMap<String, Text>,
+    #[serde(default)]
+    claims: HashMap<String, Vec<Statement>>,
+}
+
+#[derive(Deserialize)]
+struct Text {
+    value: String,
+}
+
+#[derive(Deserialize)]
+struct Statement {
+    mainsnak: Snak,
+    rank: String,
+}
+
+#[derive(Deserialize)]
+struct Snak {
+    datatype: Option<String>,
+    /* missing for "unknown value" and "no value" claims */
+    datavalue: Option<DataValue>,
+}
+
+#[derive(Deserialize)]
+struct DataValue {
+    #[serde(rename = "type")]
+    kind: String,
+    value: serde_json::Value,
+}
+
+impl Snak {
+    fn value(self) -> Option<Value> {
+        #[derive(Deserialize)]
+        struct EntityId {
+            id: String,
+        }
+        #[derive(Deserialize)]
+        struct Quantity {
+            amount: String,
+            unit: String,
+        }
+        #[derive(Deserialize)]
+        struct Time {
+            time: String,
+            precision: u8,
+        }
+        #[derive(Deserialize)]
+        struct Coordinate {
+            latitude: f64,
+            longitude: f64,
+        }
+        #[derive(Deserialize)]
+        struct MonolingualText {
+            text: String,
+            language: String,
+        }
+
+        let DataValue { kind, value } = self.datavalue?;
+        Some(match kind.as_str() {
+            "string" => {
+                let s = serde_json::from_value(value).ok()?;
+                match self.datatype.as_deref() {
+                    Some("external-id") => Value::ExternalId(s),
+                    Some("url") => Value::Url(s),
+                    _ => Value::String(s),
+                }
+            }
+            "wikibase-entityid" => Value::Item(serde_json::from_value::<EntityId>(value).ok()?.id),
+            "monolingualtext" => {
+                let text: MonolingualText = serde_json::from_value(value).ok()?;
+                Value::Text {
+                    text: text.text,
+                    language: text.language,
+                }
+            }
+            "quantity" => {
+                let quantity: Quantity = serde_json::from_value(value).ok()?;
+                Value::Quantity {
+                    amount: quantity.amount.trim_start_matches('+').parse().ok()?,
+                    /* e.g. `http://www.wikidata.org/entity/Q11570`, or `1` for no unit */
+                    unit: quantity.unit.rsplit_once('/').map(|(_, id)| id.to_string()),
+                }
+            }
+            "time" => {
+                let time: Time = serde_json::from_value(value).ok()?;
+                Value::Time {
+                    time: time.time,
+                    precision: time.precision,
+                }
+            }
+            "globecoordinate" => {
+                let coordinate: Coordinate = serde_json::from_value(value).ok()?;
+                Value::Coordinate {
+                    latitude: coordinate.latitude,
+                    longitude: coordinate.longitude,
+                }
+            }
+            _ => return None,
+        })
+    }
+}
+
+impl From<EntityData> for Entity {
+    fn from(data: EntityData) -> Self {
+        let text = |map: HashMap<String, Text>| {
+            map.into_iter()
+                .map(|(language, text)| (language, text.value))
+                .collect()
+        };
+
+        let claims = data
+            .claims
+            .into_iter()
+            .filter_map(|(property, statements)| {
+                /* as on Wikidata itself: the preferred values if there are any, otherwise the
+                 * normal ones, and never the deprecated ones */
+                let rank = if statements.iter().any(|s| s.rank == "preferred") {
+                    "preferred"
+                } else {
+                    "normal"
+                };
+                let values = statements
+                    .into_iter()
+                    .filter(|s| s.rank == rank)
+                    .filter_map(|s| s.mainsnak.value())
+                    .collect::<Vec<_>>();
+                (!values.is_empty()).then_some((property, values))
+            })
+            .collect();
+
+        Self {
+            id: data.id,
+            labels: text(data.labels),
+            descriptions: text(data.descriptions),
+            claims,
+        }
+    }
+}
+
+impl Entity {
+    /// The entity's name in `language` (e.g. `en`), if it has one.
+    pub fn label(&self, language: &str) -> Option<&str> {
+        self.labels.get(language).map(String::as_str)
+    }
+
+    /// The entity's description in `language` (e.g. `en`), if it has one.
+    pub fn description(&self, language: &str) -> Option<&str> {
+        self.descriptions.get(language).map(String::as_str)
+    }
+
+    /// The values of a property (e.g. `P31`), or nothing if the entity has no such claim.
+    pub fn claim(&self, property: &str) -> &[Value] {
+        self.claims.get(property).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get an entity by its id, e.g. `Q42`.
+    ///
+    /// Returns `None` if there is no such entity.
+    ///
+    /// # Errors
+    /// Errors if `id` isn't an entity id, if the request failed, or if the entity could not
+    /// be parsed.
+    #[cfg(feature = "net")]
+    pub async fn by_id(client: &mut Client<false>, id: &str) -> anyhow::Result<Option<Self>> {
+        Self::by_id_with(&Endpoints::default(), client, id).await
+    }
+
+    /// Like [`Entity::by_id`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if `id` isn't an entity id, if the request failed, or if the entity could not
+    /// be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn by_id_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        id: &str,
+    ) -> anyhow::Result<Option<Self>> {
+        lazy_static! {
+            static ref RE_ID: regex::Regex = regex::Regex::new(r"^[QPL][0-9]+$").unwrap();
+        }
+
+        let id = id.trim().to_uppercase();
+        if !RE_ID.is_match(&id) {
+            bail!("`{}` is not a Wikidata id (like `Q42`)", id);
+        }
+
+        let res = client
+            .get(&endpoints.url(&format!("/wiki/Special:EntityData/{}.json", id)))
+            .await?
+            .send()
+            .await?;
+        if res.status() == 404 {
+            return Ok(None);
+        }
+
+        let text = res.error_for_status()?.text().await?;
+        Self::from_json(&text).with_context(|| format!("could not parse {}", id))
+    }
+
+    /// Read the entity from a `Special:EntityData` response, as [`Entity::by_id`] does.
+    ///
+    /// Returns `None` if the response has no entity in it.
+    ///
+    /// # Errors
+    /// Errors if the response could not be parsed.
+    pub fn from_json(json: &str) -> anyhow::Result<Option<Self>> {
+        #[derive(Deserialize)]
+        struct Response {
+            entities: HashMap<String, EntityData>,
+        }
+
+        /* merged entities are keyed by the id that was asked for, but carry their new id */
+        let response: Response = serde_json::from_str(json)?;
+        Ok(response.entities.into_values().next().map(Self::from))
+    }
+
+    /// Search for entities by their labels and aliases, in English.
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the results could not be parsed.
+    #[cfg(feature = "net")]
+    pub async fn search(
+        client: &mut Client<false>,
+        query: &str,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        Self::search_with(&Endpoints::default(), client, query).await
+    }
+
+    /// Like [`Entity::search`], using the given [`Endpoints`].
+    ///
+    /// # Errors
+    /// Errors if the request failed, or if the results could not be parsed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn search_with(
+        endpoints: &Endpoints,
+        client: &mut Client<false>,
+        query: &str,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        #[derive(Deserialize)]
+        struct Response {
+            search: Vec<SearchResult>,
+        }
+
+        let response: Response = client
+            .get(&endpoints.url("/w/api.php"))
+            .await?
+            .query(&[
+                ("action", "wbsearchentities"),
+                ("search", query),
+                ("language", "en"),
+                ("uselang", "en"),
+                ("type", "item"),
+                ("limit", "20"),
+                ("format", "json"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .context("could not parse the search results")?;
+        Ok(response.search)
+    }
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    use crate::{common::Client, testing::MockServer};
+
+    use super::{Endpoints, Entity, Value};
+
+    const Q42: &str = r#"{"entities": {"Q42": {
+        "id": "Q42",
+        "labels": {"en": {"language": "en", "value": "Douglas Adams"},
+                   "fr": {"language": "fr", "value": "Douglas Adams"}},
+        "descriptions": {"en": {"language": "en", "value": "English writer and humorist"}},
+        "claims": {
+            "P31": [{"mainsnak": {"snaktype": "value", "property": "P31", "datatype": "wikibase-item",
+                                  "datavalue": {"type": "wikibase-entityid", "value": {"entity-type": "item", "id": "Q5"}}},
+                     "rank": "normal"}],
+            "P569": [{"mainsnak": {"snaktype": "value", "property": "P569", "datatype": "time",
+                                   "datavalue": {"type": "time", "value": {"time": "+1952-03-11T00:00:00Z", "precision": 11}}},
+                      "rank": "normal"}],
+            "P2048": [{"mainsnak": {"snaktype": "value", "property": "P2048", "datatype": "quantity",
+                                    "datavalue": {"type": "quantity", "value": {"amount": "+1.96", "unit": "http://www.wikidata.org/entity/Q11573"}}},
+                       "rank": "normal"}],
+            "P214": [{"mainsnak": {"snaktype": "value", "property": "P214", "datatype": "external-id",
+                                   "datavalue": {"type": "string", "value": "113230702"}},
+                      "rank": "deprecated"},
+                     {"mainsnak": {"snaktype": "value", "property": "P214", "datatype": "external-id",
+                                   "datavalue": {"type": "string", "value": "113230703"}},
+                      "rank": "normal"}],
+            "P1559": [{"mainsnak": {"snaktype": "value", "property": "P1559", "datatype": "monolingualtext",
+                                    "datavalue": {"type": "monolingualtext", "value": {"text": "Douglas Adams", "language": "en"}}},
+                       "rank": "normal"}],
+            "P551": [{"mainsnak": {"snaktype": "value", "property": "P551", "datatype": "wikibase-item",
+                                   "datavalue": {"type": "wikibase-entityid", "value": {"id": "Q84"}}},
+                      "rank": "normal"},
+                     {"mainsnak": {"snaktype": "value", "property": "P551", "datatype": "wikibase-item",
+                                   "datavalue": {"type": "wikibase-entityid", "value": {"id": "Q159288"}}},
+                      "rank": "preferred"}],
+            "P570": [{"mainsnak": {"snaktype": "somevalue", "property": "P570", "datatype": "time"},
+                      "rank": "normal"}]
+        }
+    }}}"#;
+
+    #[tokio::test]
+    async fn test_by_id() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/wiki/Special:EntityData/Q42.json", 200, Q42)
+            .mock("/wiki/Special:EntityData/Q0.json", 404, "");
+
+        let endpoints = Endpoints { base: server.uri() };
+        let mut client = Client::default();
+        let entity = Entity::by_id_with(&endpoints, &mut client, "q42")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entity.id, "Q42");
+        assert_eq!(entity.label("en"), Some("Douglas Adams"));
+        assert_eq!(
+            entity.description("en"),
+            Some("English writer and humorist")
+        );
+        assert_eq!(entity.description("fr"), None);
+
+        assert_eq!(entity.claim("P31")[0].as_item(), Some("Q5"));
+        assert_eq!(
+            entity.claim("P569")[0].date(),
+            NaiveDate::from_ymd_opt(1952, 3, 11)
+        );
+        assert_eq!(
+            entity.claim("P2048"),
+            [Value::Quantity {
+                amount: Decimal::new(196, 2),
+                unit: Some("Q11573".to_string()),
+            }]
+        );
+        assert_eq!(
+            entity.claim("P214"),
+            [Value::ExternalId("113230703".to_string())]
+        );
+        assert_eq!(entity.claim("P1559")[0].as_str(), Some("Douglas Adams"));
+        assert_eq!(entity.claim("P551"), [Value::Item("Q159288".to_string())]);
+        assert!(entity.claim("P570").is_empty());
+
+        assert!(Entity::by_id_with(&endpoints, &mut client, "Q0")
+            .await
+            .unwrap()
+            .is_none());
+        assert!(Entity::by_id_with(&endpoints, &mut client, "Douglas Adams")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search() {
+        let server = MockServer::start().await.unwrap();
+        server.mock(
+            "/w/api.php?action=wbsearchentities&search=ryzen+5+5600x&language=en&uselang=en&type=item&limit=20&format=json",
+            200,
+            r#"{"searchinfo": {"search": "ryzen 5 5600x"}, "search": [
+                {"id": "Q104523432", "label": "Ryzen 5 5600X", "description": "microprocessor model by AMD",
+                 "match": {"type": "label", "language": "en", "text": "Ryzen 5 5600X"}},
+                {"id": "Q1", "match": {"type": "alias", "language": "en", "text": "5600X"}}
+            ], "success": 1}"#,
+        );
+
+        let endpoints = Endpoints { base: server.uri() };
+        let results = Entity::search_with(&endpoints, &mut Client::default(), "ryzen 5 5600x")
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "Q104523432");
+        assert_eq!(results[0].label.as_deref(), Some("Ryzen 5 5600X"));
+        assert_eq!(results[1].description, None);
+    }
+}
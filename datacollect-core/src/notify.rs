@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::common::Client;
+
+/// Something happened to a tracked value that someone wants to hear about.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Notification {
+    /// The key of the item the notification is about (e.g. `ebay:254625474154`).
+    pub item: String,
+    pub at: DateTime<Utc>,
+    /// A human readable description of what happened.
+    pub message: String,
+}
+
+/// A destination for [`Notification`]'s.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    /// Deliver a single notification.
+    ///
+    /// # Errors
+    /// Errors if the notification could not be delivered.
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()>;
+}
+
+/// Prints each notification to stdout as a line of JSON, e.g. for a script reading the
+/// notifications of a tracker that writes no data of its own.
+pub struct Stdout;
+
+#[async_trait]
+impl Notifier for Stdout {
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()> {
+        println!("{}", serde_json::to_string(notification)?);
+        Ok(())
+    }
+}
+
+/// Prints each notification to stderr as a line of JSON, so that it isn't mixed into the
+/// data written to stdout.
+pub struct Stderr;
+
+#[async_trait]
+impl Notifier for Stderr {
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()> {
+        eprintln!("{}", serde_json::to_string(notification)?);
+        Ok(())
+    }
+}
+
+/// POSTs each notification as JSON to a URL.
+pub struct Webhook {
+    client: Client<false>,
+    url: String,
+}
+
+impl Webhook {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: Client::default(),
+            url,
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for Webhook {
+    async fn notify(&self, notification: &Notification) -> anyhow::Result<()> {
+        self.client
+            .post(self.url.as_str())
+            .json(notification)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Where notifications should go, as written in a configuration file.
+///
+/// ## Example
+/// ```toml
+/// stdout = true
+/// webhooks = ["https://example.com/hooks/prices"]
+/// ```
+#[derive(Deserialize, Serialize, Default)]
+pub struct Config {
+    /// Print notifications to stdout.
+    #[serde(default)]
+    pub stdout: bool,
+    /// Print notifications to stderr.
+    #[serde(default)]
+    pub stderr: bool,
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+}
+
+impl Config {
+    /// Build the [`Notifier`]'s described by this configuration.
+    pub fn notifiers(&self) -> Vec<Box<dyn Notifier>> {
+        let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+        if self.stdout {
+            notifiers.push(Box::new(Stdout));
+        }
+        if self.stderr {
+            notifiers.push(Box::new(Stderr));
+        }
+        for url in &self.webhooks {
+            notifiers.push(Box::new(Webhook::new(url.clone())));
+        }
+        notifiers
+    }
+}
+
+/// Send a notification to every notifier.
+///
+/// All notifiers are tried, even if some of them fail.
+///
+/// # Errors
+/// Errors with the first failure, if any notifier failed.
+pub async fn dispatch(
+    notifiers: &[Box<dyn Notifier>],
+    notification: &Notification,
+) -> anyhow::Result<()> {
+    let mut result = Ok(());
+    for notifier in notifiers {
+        if let Err(e) = notifier.notify(notification).await {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::{dispatch, Config, Notification, Notifier, Webhook};
+    use crate::testing::MockServer;
+
+    fn notification() -> Notification {
+        Notification {
+            item: "ebay:254625474154".to_string(),
+            at: Utc::now(),
+            message: "price dropped below $30.00".to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_webhook() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock("/hooks/prices", 200, "")
+            .mock("/hooks/down", 500, "");
+
+        let webhook = Webhook::new(format!("{}/hooks/prices", server.uri()));
+        assert!(webhook.notify(&notification()).await.is_ok());
+        let webhook = Webhook::new(format!("{}/hooks/down", server.uri()));
+        assert!(webhook.notify(&notification()).await.is_err());
+        assert_eq!(server.requests(), ["/hooks/prices", "/hooks/down"]);
+    }
+
+    #[tokio::test]
+    async fn test_dispatch() {
+        let server = MockServer::start().await.unwrap();
+        server.mock("/hooks/a", 200, "").mock("/hooks/c", 200, "");
+
+        let config: Config = toml::from_str(&format!(
+            "webhooks = [\"{0}/hooks/a\", \"{0}/hooks/b\", \"{0}/hooks/c\"]",
+            server.uri()
+        ))
+        .unwrap();
+        let notifiers = config.notifiers();
+        assert_eq!(notifiers.len(), 3);
+
+        /* the failing webhook doesn't stop the ones after it */
+        assert!(dispatch(&notifiers, &notification()).await.is_err());
+        assert_eq!(server.requests(), ["/hooks/a", "/hooks/b", "/hooks/c"]);
+        assert!(dispatch(&notifiers[..1], &notification()).await.is_ok());
+    }
+}
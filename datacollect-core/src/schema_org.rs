@@ -1,15 +1,34 @@
+use std::{
+    cell::OnceCell,
+    collections::{HashMap, HashSet},
+    rc::Rc,
+};
+
 use kuchiki::NodeRef;
+#[cfg(feature = "net")]
+use kuchiki::{parse_html, traits::TendrilSink};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[cfg(feature = "net")]
+use crate::common::Client;
 
 /// An `itemscope` as per the [schema.org] specification.
 ///
 /// [schema.org]: https://schema.org/
 pub struct Scope {
     node: NodeRef,
+    /// The descendants with an `itemprop`, by its value, found the first time a property is
+    /// looked up, so that looking up many doesn't walk the whole subtree each time.
+    props: OnceCell<HashMap<String, Vec<NodeRef>>>,
 }
 
 impl From<NodeRef> for Scope {
     fn from(node: NodeRef) -> Self {
-        Self { node }
+        Self {
+            node,
+            props: OnceCell::new(),
+        }
     }
 }
 
@@ -18,10 +37,17 @@ impl Scope {
         Self::from(node).select_type(item_type)
     }
 
+    /// The item's global identifier, from its `itemid` (e.g. `urn:isbn:0-330-34032-8`), if
+    /// it has one.
+    pub fn id(&self) -> Option<String> {
+        Self::get_node_property(&self.node, "itemid")
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+    }
+
     /// Gets the value of a given [`NodeRef`]'s DOM attribute (given by `key`), if it exists.
     fn get_node_property(node: &NodeRef, key: &'static str) -> Option<String> {
-        node.as_element()
-            .and_then(|e| e.attributes.borrow().get(key).map(|s| s.to_string()))
+        attribute(node, key)
     }
 
     /// Checks whether a given [`NodeRef`] has a DOM attribute `key` which equals `value`.
@@ -31,16 +57,23 @@ impl Scope {
             .is_some()
     }
 
-    /// Select all descendant [`NodeRef`]'s where an attribute (given by `key`) exists
-    /// and equals `value`.
-    fn select_nodes_by_property_and_value<'x>(
-        &self,
-        key: &'static str,
-        value: &'x str,
-    ) -> impl Iterator<Item = NodeRef> + 'x {
-        self.node
-            .descendants()
-            .filter(move |d| Self::node_property_eq(d, key, value))
+    /// Select the [`NodeRef`]'s of the scope's properties (see [`properties`]) named `prop`,
+    /// in document order.
+    fn select_nodes_by_prop(&self, prop: &str) -> std::vec::IntoIter<NodeRef> {
+        let props = self.props.get_or_init(|| {
+            let mut props = HashMap::<String, Vec<NodeRef>>::new();
+            for node in properties(&self.node) {
+                let names = Self::get_node_property(&node, "itemprop").unwrap_or_default();
+                for name in names.split_whitespace() {
+                    props
+                        .entry(name.to_string())
+                        .or_default()
+                        .push(node.clone());
+                }
+            }
+            props
+        });
+        props.get(prop).cloned().unwrap_or_default().into_iter()
     }
 
     /// Get an [`Iterator`] of descendant [`Scope`]'s where the `itemtype` attribute equals `item_type`.
@@ -48,7 +81,9 @@ impl Scope {
     /// Note that these are descendant scopes, not just child scopes - children of children (and so on)
     /// are included in the returned [`Iterator`].
     pub fn select_types<'x>(&self, item_type: &'x str) -> impl Iterator<Item = Self> + 'x {
-        self.select_nodes_by_property_and_value("itemtype", item_type)
+        self.node
+            .descendants()
+            .filter(move |d| Self::node_property_eq(d, "itemtype", item_type))
             .map(Self::from)
     }
 
@@ -57,32 +92,32 @@ impl Scope {
         self.select_types(item_type).next()
     }
 
-    /// Get an [`Iterator`] of descendant [`Scope`]'s where the `itemprop` attribute equals `prop`.
+    /// Get an [`Iterator`] of the [`Scope`]'s of this scope's properties named `prop` (in their
+    /// `itemprop`).
     ///
-    /// Note that these are descendant scopes, not just child scopes - children of children (and so on)
-    /// are included in the returned [`Iterator`].
+    /// Only this scope's own properties are included, as the microdata spec says: those in nested
+    /// scopes belong to them (so the `price` of an `offers` isn't the product's), and those
+    /// outside it that its `itemref` points to are included too.
     pub fn select_props<'x>(&self, prop: &'x str) -> impl Iterator<Item = Self> + 'x {
-        self.select_nodes_by_property_and_value("itemprop", prop)
-            .map(Self::from)
+        self.select_nodes_by_prop(prop).map(Self::from)
     }
 
-    /// Get the first descendant [`Scope`] where the `itemprop` attribute equals `prop`.
+    /// Get the [`Scope`] of the first of this scope's properties named `prop`.
     pub fn select_prop(&self, prop: &str) -> Option<Self> {
         self.select_props(prop).next()
     }
 
-    /// Get an [`Iterator`] of the values of descendants where the `itemprop` attribute equals `prop`.
+    /// Get an [`Iterator`] of the values of this scope's properties named `prop`.
     ///
     /// This is equivalent to the `content` attribute if it exists, otherwise the concatenated text contents of the node.
     ///
-    /// Note that these are descendant values, not just child values - values of children of children (and so on)
-    /// are included in the returned [`Iterator`].
+    /// As with [`Scope::select_props`], properties of nested scopes aren't included.
     pub fn get_values<'x>(&self, prop: &'x str) -> impl Iterator<Item = String> + 'x {
-        self.select_nodes_by_property_and_value("itemprop", prop)
+        self.select_nodes_by_prop(prop)
             .map(|n| Self::get_node_property(&n, "content").unwrap_or_else(|| n.text_contents()))
     }
 
-    /// Get the value of the first descendant where the `itemprop` attribute equals `prop`.
+    /// Get the value of the first of this scope's properties named `prop`.
     ///
     /// This is equivalent to the `content` attribute if it exists, otherwise the concatenated text contents of the node.
     pub fn get_value(&self, prop: &str) -> Option<String> {
@@ -90,10 +125,470 @@ impl Scope {
     }
 }
 
+/// Whether a schema.org type matches another, ignoring the scheme and the `schema.org/`
+/// prefix (JSON-LD usually says just `Product`, and microdata `http://schema.org/Product`).
+fn type_matches(actual: &str, wanted: &str) -> bool {
+    let short = |t: &str| {
+        let t = t.trim().trim_end_matches('/');
+        let t = t
+            .strip_prefix("https://")
+            .or_else(|| t.strip_prefix("http://"))
+            .unwrap_or(t);
+        t.strip_prefix("schema.org/").unwrap_or(t).to_string()
+    };
+    short(actual) == short(wanted)
+}
+
+/// The value of an attribute of `node`, if it's an element and has it.
+///
+/// Going through the attributes is quicker than kuchiki's lookup by name (which turns `name`
+/// into an atom first), and this is done to every node of a page.
+fn attribute(node: &NodeRef, name: &str) -> Option<String> {
+    let attributes = node.as_element()?.attributes.borrow();
+    attributes
+        .map
+        .iter()
+        .find(|(key, _)| key.ns.is_empty() && &*key.local == name)
+        .map(|(_, attribute)| attribute.value.clone())
+}
+
+/// The elements that are properties of `item` (or of the page, if it isn't an item), in tree
+/// order, as the [microdata spec] finds them: the ones under it, and under the elements its
+/// `itemref` names, without going into nested items (whose properties are theirs).
+///
+/// [microdata spec]: https://html.spec.whatwg.org/multipage/microdata.html#associating-names-with-items
+fn properties(item: &NodeRef) -> Vec<NodeRef> {
+    let ptr = |node: &NodeRef| Rc::as_ptr(&node.0);
+
+    /* the first element with each ID in `itemref`, anywhere on the page */
+    let ids = attribute(item, "itemref").unwrap_or_default();
+    let mut ids = ids.split_whitespace().collect::<Vec<_>>();
+    let mut referenced = Vec::new();
+    if !ids.is_empty() {
+        let root = item
+            .inclusive_ancestors()
+            .last()
+            .unwrap_or_else(|| item.clone());
+        for node in root.descendants() {
+            if let Some(id) = attribute(&node, "id") {
+                if ids.contains(&id.as_str()) {
+                    ids.retain(|wanted| *wanted != id);
+                    referenced.push(node);
+                }
+            }
+        }
+    }
+
+    /* children are popped before referenced elements, in tree order */
+    let mut pending = referenced.clone();
+    pending.extend(item.children().rev());
+    let mut seen = HashSet::new();
+    seen.insert(ptr(item));
+    let mut properties = Vec::new();
+    while let Some(node) = pending.pop() {
+        /* an element can only be reached twice through `itemref` */
+        if !referenced.is_empty() && !seen.insert(ptr(&node)) {
+            continue;
+        }
+        let (is_item, is_property) = match node.as_element() {
+            Some(element) => element.attributes.borrow().map.iter().fold(
+                (false, false),
+                |(is_item, is_property), (name, attribute)| match &*name.local {
+                    _ if !name.ns.is_empty() => (is_item, is_property),
+                    "itemscope" => (true, is_property),
+                    "itemprop" => (is_item, !attribute.value.trim().is_empty()),
+                    _ => (is_item, is_property),
+                },
+            ),
+            None => (false, false),
+        };
+        if !is_item {
+            pending.extend(node.children().rev());
+        }
+        if is_property {
+            properties.push(node);
+        }
+    }
+
+    if !referenced.is_empty() {
+        let root = item
+            .inclusive_ancestors()
+            .last()
+            .unwrap_or_else(|| item.clone());
+        let found = properties.iter().map(ptr).collect::<HashSet<_>>();
+        properties = root
+            .descendants()
+            .filter(|node| found.contains(&ptr(node)))
+            .collect();
+    }
+    properties
+}
+
+/// The value of a microdata property, as the HTML spec defines it (e.g. `href` for links).
+/// `items` are the items it's (nested) in; see [`microdata`].
+fn property_value(node: &NodeRef, items: &mut Vec<NodeRef>) -> Value {
+    if let Some(element) = node.as_element() {
+        if element.attributes.borrow().contains("itemscope") {
+            return microdata_in(node, items);
+        }
+        let from = match &*element.name.local {
+            "meta" => Some("content"),
+            "audio" | "embed" | "iframe" | "img" | "source" | "track" | "video" => Some("src"),
+            "a" | "area" | "link" => Some("href"),
+            "object" => Some("data"),
+            "data" | "meter" => Some("value"),
+            "time" => Some("datetime"),
+            _ => None,
+        };
+        if let Some(value) = from.and_then(|name| attribute(node, name)) {
+            return Value::String(value);
+        }
+    }
+    Value::String(node.text_contents().trim().to_string())
+}
+
+/// An item in the JSON form the microdata spec uses, e.g.
+/// `{"type": ["https://schema.org/Offer"], "properties": {"price": ["$19.95"]}}`, with an `id`
+/// if it has an `itemid`.
+fn microdata(node: &NodeRef) -> Value {
+    microdata_in(node, &mut Vec::new())
+}
+
+/// Like [`microdata`], for an item nested in `items`. An item that's (through `itemref`) a
+/// property of itself is `"ERROR"`, as the spec says, instead of going on forever.
+fn microdata_in(node: &NodeRef, items: &mut Vec<NodeRef>) -> Value {
+    if items.contains(node) {
+        return Value::String("ERROR".to_string());
+    }
+    items.push(node.clone());
+
+    let mut item = Map::new();
+    if let Some(types) = attribute(node, "itemtype") {
+        item.insert(
+            "type".to_string(),
+            types.split_whitespace().map(Value::from).collect(),
+        );
+    }
+    if let Some(id) = Scope::from(node.clone()).id() {
+        item.insert("id".to_string(), Value::String(id));
+    }
+    let mut properties = Map::new();
+    for property in self::properties(node) {
+        let value = property_value(&property, items);
+        let names = attribute(&property, "itemprop").unwrap_or_default();
+        for name in names.split_whitespace() {
+            if let Value::Array(values) = properties
+                .entry(name)
+                .or_insert_with(|| Value::Array(Vec::new()))
+            {
+                values.push(value.clone());
+            }
+        }
+    }
+    item.insert("properties".to_string(), Value::Object(properties));
+
+    items.pop();
+    Value::Object(item)
+}
+
+/// An item in the standard JSON form of microdata, as the HTML spec converts it: its `type`,
+/// its `id` if it has an `itemid`, and its `properties` by name, with nested items in the same
+/// form. A page's items can be found (in this form) with [`Items::find`].
+///
+/// ## Example
+/// ```txt
+/// {
+///     "type": ["https://schema.org/Product"],
+///     "id": "urn:upc:012345678905",
+///     "properties": {
+///         "name": ["Toaster"],
+///         "offers": [{"type": ["https://schema.org/Offer"], "properties": {"price": ["24.99"]}}]
+///     }
+/// }
+/// ```
+pub fn to_json(scope: &Scope) -> Value {
+    microdata(&scope.node)
+}
+
+/// Every JSON-LD object in a page's `<script type="application/ld+json">` blocks, with
+/// arrays and `@graph`s flattened out. Blocks that aren't valid JSON are skipped.
+pub fn json_ld(document: &NodeRef) -> Vec<Value> {
+    fn flatten(value: Value, items: &mut Vec<Value>) {
+        match value {
+            Value::Array(values) => values.into_iter().for_each(|v| flatten(v, items)),
+            Value::Object(mut object) => match object.remove("@graph") {
+                Some(graph) => flatten(graph, items),
+                None => items.push(Value::Object(object)),
+            },
+            _ => {}
+        }
+    }
+
+    let mut items = Vec::new();
+    for script in document
+        .select("script[type='application/ld+json']")
+        .into_iter()
+        .flatten()
+    {
+        if let Ok(value) = serde_json::from_str(&script.text_contents()) {
+            flatten(value, &mut items);
+        }
+    }
+    items
+}
+
+/// The schema.org items on a page, in both of the forms pages publish them in.
+#[derive(Serialize, Default, Debug)]
+pub struct Items {
+    /// Microdata items, in the JSON form the microdata spec uses.
+    pub microdata: Vec<Value>,
+    pub json_ld: Vec<Value>,
+}
+
+impl Items {
+    /// Find the items on a page. With an `item_type` (e.g. `https://schema.org/Product`, or
+    /// just `Product`), every item of that type is found, including ones nested in others;
+    /// without one, every top-level item is.
+    pub fn find(document: &NodeRef, item_type: Option<&str>) -> Self {
+        let is_wanted = |types: &[&str]| {
+            item_type.is_none_or(|wanted| types.iter().any(|t| type_matches(t, wanted)))
+        };
+
+        let microdata = document
+            .descendants()
+            .filter(|node| {
+                let element = match node.as_element() {
+                    Some(element) => element,
+                    None => return false,
+                };
+                let attributes = element.attributes.borrow();
+                let types = attributes
+                    .get("itemtype")
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .collect::<Vec<_>>();
+                attributes.contains("itemscope")
+                    && (item_type.is_some() || !attributes.contains("itemprop"))
+                    && is_wanted(&types)
+            })
+            .map(|node| self::microdata(&node))
+            .collect();
+
+        /* nested items are objects with a type, e.g. the `Offer` in a `Product`'s `offers` */
+        fn nested(value: &Value, items: &mut Vec<Value>) {
+            match value {
+                Value::Array(values) => values.iter().for_each(|v| nested(v, items)),
+                Value::Object(object) => {
+                    if object.contains_key("@type") {
+                        items.push(value.clone());
+                    }
+                    object.values().for_each(|v| nested(v, items));
+                }
+                _ => {}
+            }
+        }
+        let mut json_ld = self::json_ld(document);
+        if item_type.is_some() {
+            let mut items = Vec::new();
+            json_ld.iter().for_each(|item| nested(item, &mut items));
+            json_ld = items;
+        }
+        let json_ld = json_ld
+            .into_iter()
+            .filter(|item| {
+                let types = match item.get("@type") {
+                    Some(Value::String(t)) => vec![t.as_str()],
+                    Some(Value::Array(ts)) => ts.iter().filter_map(Value::as_str).collect(),
+                    _ => Vec::new(),
+                };
+                is_wanted(&types)
+            })
+            .collect();
+
+        Self { microdata, json_ld }
+    }
+
+    /// Download a page and find the items on it, as [`Items::find`] does.
+    ///
+    /// # Errors
+    /// Errors if the request failed.
+    #[cfg(feature = "net")]
+    #[tracing::instrument(skip(client), err)]
+    pub async fn get<const COOKIES: bool>(
+        client: &Client<COOKIES>,
+        url: &str,
+        item_type: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let text = client
+            .get(url)
+            .await?
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(Self::find(&parse_html().one(text), item_type))
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Scope;
+    use super::{to_json, Items, Scope};
     use kuchiki::{parse_html, traits::TendrilSink};
+    use serde_json::json;
+
+    #[test]
+    fn test_items() {
+        let document = parse_html().one(
+            r#"
+            <script type="application/ld+json">
+                {"@context": "https://schema.org", "@graph": [
+                    {"@type": "Product", "name": "Blend-O-Matic", "offers": {"@type": "Offer", "price": "19.95"}},
+                    {"@type": "BreadcrumbList", "itemListElement": []}
+                ]}
+            </script>
+            <script type="application/ld+json">{not json</script>
+            <div itemscope itemtype="http://schema.org/Product">
+                <h1 itemprop="name">Blend-O-Matic</h1>
+                <a itemprop="url" href="/blend-o-matic">link</a>
+                <div itemprop="offers" itemscope itemtype="http://schema.org/Offer">
+                    <meta itemprop="price" content="19.95" />
+                    <span itemprop="name">New</span>
+                </div>
+            </div>
+            <div itemscope itemtype="https://schema.org/Organization"><span itemprop="name">ACME</span></div>
+        "#,
+        );
+
+        let products = Items::find(&document, Some("https://schema.org/Product"));
+        assert_eq!(
+            products.microdata,
+            vec![json!({
+                "type": ["http://schema.org/Product"],
+                "properties": {
+                    "name": ["Blend-O-Matic"],
+                    "url": ["/blend-o-matic"],
+                    "offers": [{
+                        "type": ["http://schema.org/Offer"],
+                        "properties": {"price": ["19.95"], "name": ["New"]}
+                    }]
+                }
+            })]
+        );
+        assert_eq!(products.json_ld.len(), 1);
+        assert_eq!(products.json_ld[0]["name"], "Blend-O-Matic");
+
+        let offers = Items::find(&document, Some("Offer"));
+        assert_eq!(offers.microdata.len(), 1);
+        assert_eq!(
+            offers.json_ld,
+            vec![json!({"@type": "Offer", "price": "19.95"})]
+        );
+
+        let everything = Items::find(&document, None);
+        assert_eq!(everything.microdata.len(), 2);
+        assert_eq!(everything.json_ld.len(), 2);
+    }
+
+    #[test]
+    fn test_scope_boundaries() {
+        let document = parse_html().one(
+            r#"
+            <p id="made-by"><span itemprop="brand">ACME</span> <span itemprop="color">red</span></p>
+            <div itemscope itemtype="https://schema.org/Product" itemref="made-by missing">
+                <h1 itemprop="name">Blend-O-Matic</h1>
+                <span itemprop="color">blue</span>
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer">
+                    <span itemprop="name">New</span>
+                    <span itemprop="price lowPrice">$19.95</span>
+                </div>
+            </div>
+        "#,
+        );
+
+        let product = Scope::find(document.clone(), "https://schema.org/Product").unwrap();
+        assert_eq!(
+            product.get_values("name").collect::<Vec<_>>(),
+            ["Blend-O-Matic"]
+        );
+        assert_eq!(product.get_value("price"), None);
+        assert_eq!(product.get_value("brand").as_deref(), Some("ACME"));
+        assert_eq!(
+            product.get_values("color").collect::<Vec<_>>(),
+            ["red", "blue"]
+        );
+
+        let offer = product.select_prop("offers").unwrap();
+        assert_eq!(offer.get_value("price").as_deref(), Some("$19.95"));
+        assert_eq!(offer.get_value("lowPrice").as_deref(), Some("$19.95"));
+        assert_eq!(offer.get_value("brand"), None);
+
+        /* an item that's a property of itself, through `itemref` */
+        let document = parse_html().one(
+            r#"
+            <div id="a" itemprop="related" itemscope itemtype="https://schema.org/Thing" itemref="b">
+                <span itemprop="name">A</span>
+            </div>
+            <div id="b" itemprop="related" itemscope itemref="a"></div>
+        "#,
+        );
+        let things = Items::find(&document, Some("Thing"));
+        assert_eq!(
+            things.microdata,
+            vec![json!({
+                "type": ["https://schema.org/Thing"],
+                "properties": {
+                    "name": ["A"],
+                    "related": [{"properties": {"related": ["ERROR"]}}]
+                }
+            })]
+        );
+    }
+
+    #[test]
+    fn test_itemid_and_itemref() {
+        /* a product template with its price box and seller elsewhere on the page */
+        let document = parse_html().one(
+            r#"
+            <div itemscope itemtype="https://schema.org/Product" itemid=" urn:upc:012345678905 " itemref="price-box">
+                <h1 itemprop="name">Toaster</h1>
+            </div>
+            <aside id="price-box">
+                <div itemprop="offers" itemscope itemtype="https://schema.org/Offer" itemref="seller">
+                    <meta itemprop="price" content="24.99" />
+                </div>
+            </aside>
+            <footer id="seller"><span itemprop="seller">ACME</span></footer>
+        "#,
+        );
+
+        let product = Scope::find(document.clone(), "https://schema.org/Product").unwrap();
+        assert_eq!(product.id().as_deref(), Some("urn:upc:012345678905"));
+        assert_eq!(
+            to_json(&product),
+            Items::find(&document, Some("Product")).microdata[0]
+        );
+        assert_eq!(product.get_value("seller"), None);
+        let offer = product.select_prop("offers").unwrap();
+        assert_eq!(offer.id(), None);
+        assert_eq!(offer.get_value("price").as_deref(), Some("24.99"));
+        assert_eq!(offer.get_value("seller").as_deref(), Some("ACME"));
+
+        assert_eq!(
+            Items::find(&document, Some("Product")).microdata,
+            vec![json!({
+                "type": ["https://schema.org/Product"],
+                "id": "urn:upc:012345678905",
+                "properties": {
+                    "name": ["Toaster"],
+                    "offers": [{
+                        "type": ["https://schema.org/Offer"],
+                        "properties": {"price": ["24.99"], "seller": ["ACME"]}
+                    }]
+                }
+            })]
+        );
+    }
 
     #[test]
     fn do_tests() {
@@ -0,0 +1,120 @@
+//! Small source-independent types that the modules share.
+
+use std::convert::TryFrom;
+
+use anyhow::{bail, Context};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::schema_org::Scope;
+
+/// A rating, scaled to between 0 and 1 so that ratings on different scales (an eBay seller's
+/// positive feedback percentage, a shop's 5 stars, a 10-point score) can be compared.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, PartialEq, Debug)]
+pub struct Rating {
+    /// The rating as a fraction of the best possible one: 1 is a perfect score.
+    pub value: f64,
+    /// How many ratings (e.g. reviews) this is the average of, if known.
+    pub count: Option<u64>,
+}
+
+impl Rating {
+    /// A rating of `value` on a scale from 0 to `max`.
+    ///
+    /// Returns `None` if `max` isn't positive, or `value` isn't on the scale.
+    ///
+    /// ## Example
+    /// ```txt
+    /// Rating::from_scale(99.6, 100.0) -> 0.996 (eBay feedback)
+    /// Rating::from_scale(4.5, 5.0)    -> 0.9   (stars)
+    /// Rating::from_scale(8.0, 10.0)   -> 0.8   (a score out of 10)
+    /// ```
+    pub fn from_scale(value: f64, max: f64) -> Option<Self> {
+        if !(max > 0.0 && (0.0..=max).contains(&value)) {
+            return None;
+        }
+        Some(Self {
+            value: value / max,
+            count: None,
+        })
+    }
+
+    /// The same rating, saying how many ratings it's the average of.
+    pub fn with_count(self, count: Option<u64>) -> Self {
+        Self { count, ..self }
+    }
+
+    /// The rating on a scale from 0 to `max`, e.g. `rating.on_scale(5.0)` for stars.
+    pub fn on_scale(self, max: f64) -> f64 {
+        self.value * max
+    }
+}
+
+/// Read a schema.org `AggregateRating` (or `Rating`). Its `bestRating` is 5 if not given, as
+/// the spec says.
+impl TryFrom<Scope> for Rating {
+    type Error = anyhow::Error;
+    fn try_from(scope: Scope) -> anyhow::Result<Self> {
+        let number = |prop: &str| {
+            scope
+                .get_value(prop)
+                .and_then(|value| value.trim().replace(',', "").parse::<f64>().ok())
+        };
+
+        let value = number("ratingValue").context("the rating has no ratingValue")?;
+        let best = number("bestRating").unwrap_or(5.0);
+        let count = number("reviewCount")
+            .or_else(|| number("ratingCount"))
+            .map(|count| count as u64);
+        match Self::from_scale(value, best) {
+            Some(rating) => Ok(rating.with_count(count)),
+            None => bail!("rating {} is not on a scale to {}", value, best),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryInto;
+
+    use kuchiki::{parse_html, traits::TendrilSink};
+
+    use super::Rating;
+    use crate::schema_org::Scope;
+
+    #[test]
+    fn test_rating() {
+        assert_eq!(Rating::from_scale(99.5, 100.0).unwrap().value, 0.995);
+        assert_eq!(Rating::from_scale(8.0, 10.0).unwrap().on_scale(5.0), 4.0);
+        assert!(Rating::from_scale(6.0, 5.0).is_none());
+        assert!(Rating::from_scale(-1.0, 5.0).is_none());
+        assert!(Rating::from_scale(1.0, 0.0).is_none());
+        assert!(Rating::from_scale(f64::NAN, 5.0).is_none());
+
+        let node = parse_html().one(
+            r#"
+            <div itemscope itemtype="https://schema.org/AggregateRating">
+                <meta itemprop="ratingValue" content="8" />
+                <meta itemprop="bestRating" content="10" />
+                Based on <span itemprop="ratingCount">1,250</span> user ratings
+            </div>
+            <div itemscope itemtype="https://schema.org/Rating">
+                <span itemprop="ratingValue">4.5</span> stars
+            </div>
+        "#,
+        );
+        let rating: Rating = Scope::find(node.clone(), "https://schema.org/AggregateRating")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(rating.value, 0.8);
+        assert_eq!(rating.count, Some(1250));
+
+        let rating: Rating = Scope::find(node, "https://schema.org/Rating")
+            .unwrap()
+            .try_into()
+            .unwrap();
+        assert_eq!(rating.on_scale(5.0), 4.5);
+        assert_eq!(rating.count, None);
+    }
+}
@@ -0,0 +1,559 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use anyhow::{bail, Context};
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    common::Money,
+    modules::{passmark, userbenchmark},
+};
+
+pub mod compat;
+
+/// Split a value like `3.4 GHz`, `up to 3.9 GHz` or `1,024 MB` into its number and
+/// (lowercase) unit.
+pub(crate) fn number_and_unit(s: &str) -> Option<(f64, String)> {
+    lazy_static! {
+        static ref RE_NUMBER: regex::Regex =
+            regex::Regex::new(r"((?:[0-9]{1,3}(?:,[0-9]{3})+|[0-9]+)(?:\.[0-9]+)?)\s*([a-zA-Z]*)")
+                .unwrap();
+    }
+
+    let captures = RE_NUMBER.captures(s)?;
+    let number = captures.get(1)?.as_str().replace(',', "").parse().ok()?;
+    let unit = captures.get(2).map_or("", |m| m.as_str()).to_lowercase();
+    Some((number, unit))
+}
+
+/// A quantity in some unit, read from text like `65 W` and written the same way.
+///
+/// The number is stored in the type's base unit; a number without a unit (in text, or
+/// as a JSON number) is taken to be in the base unit too.
+macro_rules! unit {
+    ($(#[$meta:meta])* $name:ident, { $($($unit:literal)|+ => $factor:expr),* $(,)? }) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, PartialOrd, Debug)]
+        pub struct $name(pub f64);
+
+        impl FromStr for $name {
+            type Err = anyhow::Error;
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let (number, unit) = number_and_unit(s)
+                    .with_context(|| format!("could not find a number in `{}`", s))?;
+                let factor = match unit.as_str() {
+                    "" => 1.0,
+                    $($($unit)|+ => $factor,)*
+                    _ => bail!("`{}` is not a unit of {}", unit, stringify!($name)),
+                };
+                Ok(Self(number * factor))
+            }
+        }
+
+        impl Serialize for $name {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                #[derive(Deserialize)]
+                #[serde(untagged)]
+                enum Repr {
+                    Number(f64),
+                    Text(String),
+                }
+
+                match Repr::deserialize(deserializer)? {
+                    Repr::Number(n) => Ok(Self(n)),
+                    Repr::Text(s) => s.parse().map_err(serde::de::Error::custom),
+                }
+            }
+        }
+
+        impl JsonSchema for $name {
+            fn schema_name() -> String {
+                stringify!($name).to_string()
+            }
+
+            fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+                /* serialized with `Display`, e.g. `65 W` */
+                String::json_schema(gen)
+            }
+        }
+    };
+}
+
+unit!(
+    /// A power, in watts (e.g. a TDP).
+    Power, { "w" => 1.0, "kw" => 1000.0 }
+);
+unit!(
+    /// A frequency, in MHz (e.g. a clock speed).
+    Frequency, { "hz" => 1e-6, "khz" => 1e-3, "mhz" => 1.0, "ghz" => 1000.0 }
+);
+unit!(
+    /// An amount of memory, in MB (e.g. a cache size). Units are binary: 1 MB is 1024 KB.
+    MemorySize, {
+        "b" => 1.0 / 1024.0 / 1024.0,
+        "kb" | "kib" => 1.0 / 1024.0,
+        "mb" | "mib" => 1.0,
+        "gb" | "gib" => 1024.0,
+        "tb" | "tib" => 1024.0 * 1024.0,
+    }
+);
+
+impl fmt::Display for Power {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} W", self.0)
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 >= 1000.0 {
+            write!(f, "{} GHz", self.0 / 1000.0)
+        } else {
+            write!(f, "{} MHz", self.0)
+        }
+    }
+}
+
+impl fmt::Display for MemorySize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0 >= 1024.0 {
+            write!(f, "{} GB", self.0 / 1024.0)
+        } else if self.0 < 1.0 {
+            write!(f, "{} KB", self.0 * 1024.0)
+        } else {
+            write!(f, "{} MB", self.0)
+        }
+    }
+}
+
+/// A benchmark that CPU's are scored by.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CPUBenchmarkMetric {
+    /// Passmark's CPU Mark (multi-core) and single thread rating.
+    Passmark,
+    /// Geekbench 5's single-core and multi-core scores.
+    Geekbench5,
+    /// UserBenchmark's effective speed, in percent.
+    UserBenchmark,
+}
+
+/// The scores of a CPU in one benchmark.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct CPUBenchmark {
+    pub single_core: Option<f64>,
+    pub multi_core: Option<f64>,
+    /// The overall score, for benchmarks that only have one.
+    pub score: Option<f64>,
+    /// How many benchmark runs the scores were computed from, if known.
+    pub samples: Option<u32>,
+}
+
+/// A CPU model, combining what every source knows about it.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CPU {
+    pub name: String,
+    pub socket: Option<String>,
+    pub cores: Option<u32>,
+    pub threads: Option<u32>,
+    pub tdp: Option<Power>,
+    /// Base clock speed.
+    pub base_clock: Option<Frequency>,
+    /// Maximum boost clock speed.
+    pub boost_clock: Option<Frequency>,
+    /// L1 cache size, as listed by the source (some list it per core).
+    pub l1_cache: Option<MemorySize>,
+    /// L2 cache size, as listed by the source (some list it per core).
+    pub l2_cache: Option<MemorySize>,
+    /// L3 cache size.
+    pub l3_cache: Option<MemorySize>,
+    /// The name of the integrated GPU, if the CPU has one.
+    pub integrated_graphics: Option<String>,
+    pub release_date: Option<NaiveDate>,
+    /// Process size, in nm.
+    pub lithography: Option<u32>,
+    pub price: Option<Money>,
+    pub benchmarks: HashMap<CPUBenchmarkMetric, CPUBenchmark>,
+}
+
+/// A benchmark that GPU's are scored by.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GPUBenchmarkMetric {
+    /// Passmark's G3D Mark.
+    G3DMark,
+    /// Passmark's G2D Mark.
+    G2DMark,
+    /// 3DMark's Time Spy graphics score.
+    TimeSpy,
+    /// UserBenchmark's effective 3D speed, in percent.
+    UserBenchmark,
+}
+
+/// The score of a GPU in one benchmark.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct GPUBenchmark {
+    pub score: Option<f64>,
+    /// How many benchmark runs the score was computed from, if known.
+    pub samples: Option<u32>,
+}
+
+/// A GPU model, combining what every source knows about it.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct GPU {
+    pub name: String,
+    /// The company that designed the chip (e.g. `NVIDIA`), not the board partner.
+    pub manufacturer: Option<String>,
+    /// The graphics processor (e.g. `GA104`).
+    pub chip: Option<String>,
+    pub vram: Option<MemorySize>,
+    /// The kind of video memory (e.g. `GDDR6`).
+    pub memory_type: Option<String>,
+    /// Memory bus width, in bits.
+    pub bus_width: Option<u32>,
+    pub tdp: Option<Power>,
+    /// Base clock speed.
+    pub base_clock: Option<Frequency>,
+    /// Maximum boost clock speed.
+    pub boost_clock: Option<Frequency>,
+    pub release_date: Option<NaiveDate>,
+    pub price: Option<Money>,
+    pub benchmarks: HashMap<GPUBenchmarkMetric, GPUBenchmark>,
+}
+
+/// A motherboard model, with what's needed to tell which parts fit it; see [`compat`].
+///
+/// [`pcpartpicker`](crate::modules::pcpartpicker) collects these.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct Motherboard {
+    pub name: String,
+    pub manufacturer: Option<String>,
+    /// The CPU socket (e.g. `AM4` or `LGA1200`).
+    pub socket: Option<String>,
+    /// The chipset (e.g. `B550`).
+    pub chipset: Option<String>,
+    /// The kind of memory the board takes (e.g. `DDR4`).
+    pub memory_type: Option<String>,
+    pub memory_slots: Option<u32>,
+    /// The most memory the board supports in total.
+    pub max_memory: Option<MemorySize>,
+    /// The board's form factor (e.g. `ATX`).
+    pub form_factor: Option<String>,
+    pub price: Option<Money>,
+}
+
+impl From<passmark::CPU> for CPU {
+    fn from(cpu: passmark::CPU) -> Self {
+        let mut benchmarks = HashMap::new();
+        benchmarks.insert(
+            CPUBenchmarkMetric::Passmark,
+            CPUBenchmark {
+                single_core: cpu.thread.map(f64::from),
+                multi_core: cpu.cpumark.map(f64::from),
+                score: None,
+                samples: None,
+            },
+        );
+
+        Self {
+            name: cpu.name,
+            socket: Some(cpu.socket).filter(|s| !s.is_empty()),
+            cores: cpu.cores,
+            threads: cpu.logicals,
+            tdp: cpu.tdp.map(Power),
+            price: cpu.price,
+            benchmarks,
+            ..Default::default()
+        }
+    }
+}
+
+impl From<userbenchmark::Part> for CPU {
+    fn from(part: userbenchmark::Part) -> Self {
+        let mut benchmarks = HashMap::new();
+        benchmarks.insert(
+            CPUBenchmarkMetric::UserBenchmark,
+            CPUBenchmark {
+                score: Some(part.benchmark),
+                samples: Some(part.samples),
+                ..Default::default()
+            },
+        );
+
+        Self {
+            name: part.name(),
+            benchmarks,
+            ..Default::default()
+        }
+    }
+}
+
+/// How to resolve a field that more than one source has a value for, when merging [`CPU`]'s.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum MergeStrategy {
+    /// Keep the value from the source that was given first.
+    #[default]
+    PreferFirst,
+    /// Keep the value from the source that was given last.
+    PreferLast,
+}
+
+impl FromStr for MergeStrategy {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first" => Ok(Self::PreferFirst),
+            "last" => Ok(Self::PreferLast),
+            _ => bail!("no such merge strategy (expected `first` or `last`)"),
+        }
+    }
+}
+
+/// Normalize a CPU name so that the same model is named the same by every source.
+///
+/// ## Example
+/// ```txt
+/// "Intel(R) Core(TM) i7-8700K CPU @ 3.70GHz" -> "core i7-8700k"
+/// "AMD Ryzen 5 2600 Six-Core Processor"      -> "ryzen 5 2600"
+/// ```
+pub fn normalize_name(name: &str) -> String {
+    lazy_static! {
+        static ref RE_NOISE: regex::Regex = regex::Regex::new(
+            r"(?i)\((?:r|tm)\)|@.*$|\b(?:intel|amd|cpu|processor|apu|[a-z]+-core)\b"
+        )
+        .unwrap();
+    }
+
+    RE_NOISE
+        .replace_all(name, " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Set `field` to `value`, as [`MergeStrategy`] says.
+fn pick<T>(field: &mut Option<T>, value: Option<T>, strategy: MergeStrategy) {
+    if value.is_some() && (field.is_none() || strategy == MergeStrategy::PreferLast) {
+        *field = value;
+    }
+}
+
+impl CPU {
+    /// Merge another record of the same CPU into this one.
+    ///
+    /// Fields that only one of the records has are always kept;
+    /// fields that both have are resolved using `strategy`.
+    pub fn merge(&mut self, other: CPU, strategy: MergeStrategy) {
+        pick(&mut self.socket, other.socket, strategy);
+        pick(&mut self.cores, other.cores, strategy);
+        pick(&mut self.threads, other.threads, strategy);
+        pick(&mut self.tdp, other.tdp, strategy);
+        pick(&mut self.base_clock, other.base_clock, strategy);
+        pick(&mut self.boost_clock, other.boost_clock, strategy);
+        pick(&mut self.l1_cache, other.l1_cache, strategy);
+        pick(&mut self.l2_cache, other.l2_cache, strategy);
+        pick(&mut self.l3_cache, other.l3_cache, strategy);
+        pick(
+            &mut self.integrated_graphics,
+            other.integrated_graphics,
+            strategy,
+        );
+        pick(&mut self.release_date, other.release_date, strategy);
+        pick(&mut self.lithography, other.lithography, strategy);
+        pick(&mut self.price, other.price, strategy);
+
+        for (metric, benchmark) in other.benchmarks {
+            let mut existing = self.benchmarks.remove(&metric);
+            pick(&mut existing, Some(benchmark), strategy);
+            self.benchmarks.insert(metric, existing.unwrap());
+        }
+    }
+}
+
+impl GPU {
+    /// Merge another record of the same GPU into this one, as [`CPU::merge`] does.
+    pub fn merge(&mut self, other: GPU, strategy: MergeStrategy) {
+        pick(&mut self.manufacturer, other.manufacturer, strategy);
+        pick(&mut self.chip, other.chip, strategy);
+        pick(&mut self.vram, other.vram, strategy);
+        pick(&mut self.memory_type, other.memory_type, strategy);
+        pick(&mut self.bus_width, other.bus_width, strategy);
+        pick(&mut self.tdp, other.tdp, strategy);
+        pick(&mut self.base_clock, other.base_clock, strategy);
+        pick(&mut self.boost_clock, other.boost_clock, strategy);
+        pick(&mut self.release_date, other.release_date, strategy);
+        pick(&mut self.price, other.price, strategy);
+
+        for (metric, benchmark) in other.benchmarks {
+            let mut existing = self.benchmarks.remove(&metric);
+            pick(&mut existing, Some(benchmark), strategy);
+            self.benchmarks.insert(metric, existing.unwrap());
+        }
+    }
+}
+
+/// Join the CPU's from several sources into one record per model.
+///
+/// CPU's are matched by their [`normalize_name`]. The output is in the order each model
+/// was first seen, and keeps the name it was first seen with.
+pub fn merge<I>(sources: I, strategy: MergeStrategy) -> Vec<CPU>
+where
+    I: IntoIterator<Item = Vec<CPU>>,
+{
+    let mut merged: Vec<CPU> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+
+    for cpu in sources.into_iter().flatten() {
+        match index.get(&normalize_name(&cpu.name)) {
+            Some(&i) => merged[i].merge(cpu, strategy),
+            None => {
+                index.insert(normalize_name(&cpu.name), merged.len());
+                merged.push(cpu);
+            }
+        }
+    }
+
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        merge, normalize_name, CPUBenchmark, CPUBenchmarkMetric, Frequency, GPUBenchmark,
+        GPUBenchmarkMetric, MemorySize, MergeStrategy, Power, CPU, GPU,
+    };
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(
+            normalize_name("Intel(R) Core(TM) i7-8700K CPU @ 3.70GHz"),
+            "core i7-8700k"
+        );
+        assert_eq!(
+            normalize_name("AMD Ryzen 5 2600 Six-Core Processor"),
+            "ryzen 5 2600"
+        );
+        assert_eq!(normalize_name("AMD Ryzen 5 2600"), "ryzen 5 2600");
+    }
+
+    #[test]
+    fn test_units() {
+        assert_eq!("65 W".parse::<Power>().unwrap(), Power(65.0));
+        assert_eq!(
+            "up to 3.9 GHz".parse::<Frequency>().unwrap(),
+            Frequency(3900.0)
+        );
+        assert_eq!(
+            "96 KB (per core)".parse::<MemorySize>().unwrap(),
+            MemorySize(0.09375)
+        );
+        assert_eq!("16MB".parse::<MemorySize>().unwrap(), MemorySize(16.0));
+        assert_eq!(
+            "1,024 MB".parse::<MemorySize>().unwrap(),
+            MemorySize(1024.0)
+        );
+        assert_eq!(
+            "1,048,576.5 KB".parse::<MemorySize>().unwrap(),
+            MemorySize(1024.00048828125)
+        );
+        assert!("3.9 GHz".parse::<Power>().is_err());
+        assert!("N/A".parse::<Power>().is_err());
+
+        assert_eq!(Frequency(3600.0).to_string(), "3.6 GHz");
+        assert_eq!(Frequency(800.0).to_string(), "800 MHz");
+        assert_eq!(MemorySize(0.09375).to_string(), "96 KB");
+        assert_eq!(MemorySize(16384.0).to_string(), "16 GB");
+
+        assert_eq!(serde_json::to_string(&Power(65.0)).unwrap(), r#""65 W""#);
+        assert_eq!(
+            serde_json::from_str::<Frequency>(r#""3.6 GHz""#).unwrap(),
+            Frequency(3600.0)
+        );
+        /* bare numbers are in the base unit, as these fields used to be */
+        assert_eq!(serde_json::from_str::<Power>("65").unwrap(), Power(65.0));
+    }
+
+    #[test]
+    fn test_merge() {
+        let passmark = CPU {
+            name: "AMD Ryzen 5 2600".to_string(),
+            tdp: Some(Power(65.0)),
+            benchmarks: vec![(CPUBenchmarkMetric::Passmark, CPUBenchmark::default())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let specs = CPU {
+            name: "Ryzen 5 2600".to_string(),
+            tdp: Some(Power(60.0)),
+            lithography: Some(12),
+            benchmarks: vec![(CPUBenchmarkMetric::Geekbench5, CPUBenchmark::default())]
+                .into_iter()
+                .collect(),
+            ..Default::default()
+        };
+        let other = CPU {
+            name: "Intel Core i7-8700K".to_string(),
+            ..Default::default()
+        };
+
+        let merged = merge(
+            vec![vec![passmark.clone(), other.clone()], vec![specs.clone()]],
+            MergeStrategy::PreferFirst,
+        );
+        assert_eq!(merged.len(), 2);
+        assert_eq!(merged[0].name, "AMD Ryzen 5 2600");
+        assert_eq!(merged[0].tdp, Some(Power(65.0)));
+        assert_eq!(merged[0].lithography, Some(12));
+        assert_eq!(merged[0].benchmarks.len(), 2);
+
+        let merged = merge(vec![vec![passmark], vec![specs]], MergeStrategy::PreferLast);
+        assert_eq!(merged[0].tdp, Some(Power(60.0)));
+    }
+
+    #[test]
+    fn test_gpu_merge() {
+        let mut gpu = GPU {
+            name: "GeForce RTX 3070".to_string(),
+            vram: Some(MemorySize(8192.0)),
+            tdp: Some(Power(220.0)),
+            benchmarks: vec![(
+                GPUBenchmarkMetric::G3DMark,
+                GPUBenchmark {
+                    score: Some(22_000.0),
+                    samples: None,
+                },
+            )]
+            .into_iter()
+            .collect(),
+            ..Default::default()
+        };
+        gpu.merge(
+            GPU {
+                name: "RTX 3070".to_string(),
+                bus_width: Some(256),
+                tdp: Some(Power(225.0)),
+                benchmarks: vec![(GPUBenchmarkMetric::TimeSpy, GPUBenchmark::default())]
+                    .into_iter()
+                    .collect(),
+                ..Default::default()
+            },
+            MergeStrategy::PreferFirst,
+        );
+        assert_eq!(gpu.name, "GeForce RTX 3070");
+        assert_eq!(gpu.bus_width, Some(256));
+        assert_eq!(gpu.tdp, Some(Power(220.0)));
+        assert_eq!(gpu.benchmarks.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&gpu).unwrap()["vram"],
+            serde_json::json!("8 GB")
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Basic checks of whether computer parts work together, for validating a build.
+//!
+//! Each check returns `None` when the records don't say enough to tell.
+
+use super::{MemorySize, Motherboard, CPU};
+
+/// Normalize a socket or memory type, so that the way a source writes it doesn't matter.
+///
+/// ## Example
+/// ```txt
+/// "Socket AM4" -> "am4"
+/// "LGA 1200"   -> "lga1200"
+/// "FCLGA1200"  -> "lga1200"
+/// ```
+fn normalize(name: &str) -> String {
+    let name = name
+        .to_lowercase()
+        .replace("socket", "")
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect::<String>();
+    /* Intel sometimes names its sockets by their package, e.g. `FCLGA1200` */
+    match name.strip_prefix("fc") {
+        Some(rest) if rest.starts_with("lga") => rest.to_string(),
+        _ => name,
+    }
+}
+
+/// Whether the CPU fits the motherboard's socket.
+pub fn cpu_fits_socket(cpu: &CPU, board: &Motherboard) -> Option<bool> {
+    Some(normalize(cpu.socket.as_ref()?) == normalize(board.socket.as_ref()?))
+}
+
+/// Whether the motherboard takes memory of this type (e.g. `DDR4`).
+pub fn memory_type_fits(memory_type: &str, board: &Motherboard) -> Option<bool> {
+    Some(normalize(memory_type) == normalize(board.memory_type.as_ref()?))
+}
+
+/// Whether this much memory in total is within what the motherboard supports.
+pub fn memory_size_fits(size: MemorySize, board: &Motherboard) -> Option<bool> {
+    Some(size.0 <= board.max_memory?.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cpu_fits_socket, memory_size_fits, memory_type_fits};
+    use crate::schemas::computing::{MemorySize, Motherboard, CPU};
+
+    #[test]
+    fn test_compat() {
+        let board = Motherboard {
+            name: "ASUS PRIME Z490-A".to_string(),
+            socket: Some("LGA 1200".to_string()),
+            memory_type: Some("DDR4".to_string()),
+            max_memory: Some(MemorySize(131072.0)),
+            ..Default::default()
+        };
+        let cpu = |socket: Option<&str>| CPU {
+            name: "Intel Core i7-10700K".to_string(),
+            socket: socket.map(str::to_string),
+            ..Default::default()
+        };
+
+        assert_eq!(cpu_fits_socket(&cpu(Some("FCLGA1200")), &board), Some(true));
+        assert_eq!(cpu_fits_socket(&cpu(Some("AM4")), &board), Some(false));
+        assert_eq!(cpu_fits_socket(&cpu(None), &board), None);
+
+        assert_eq!(memory_type_fits("ddr4", &board), Some(true));
+        assert_eq!(memory_type_fits("DDR5", &board), Some(false));
+        assert_eq!(memory_size_fits(MemorySize(65536.0), &board), Some(true));
+        assert_eq!(
+            memory_size_fits(MemorySize(262144.0), &Motherboard::default()),
+            None
+        );
+    }
+}
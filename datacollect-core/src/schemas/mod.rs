@@ -0,0 +1,89 @@
+//! Source-independent data models, so that data collected by different modules can be combined.
+
+pub mod common;
+pub mod computing;
+#[cfg(feature = "net")]
+pub mod product;
+pub mod sellers;
+
+#[cfg(feature = "net")]
+use schemars::{schema::RootSchema, schema_for};
+
+#[cfg(feature = "net")]
+use crate::modules::{
+    bestbuy, craigslist, ebay, etsy, geekbench, google_shopping, netprobe, openlibrary, passmark,
+    pcpartpicker, rdap, stocks, techpowerup, userbenchmark, weather, wikidata,
+};
+
+/// The modules [`json_schema`] has a schema for.
+#[cfg(feature = "net")]
+pub const MODULES: &[&str] = &[
+    "bestbuy",
+    "craigslist",
+    "ebay",
+    "etsy",
+    "geekbench",
+    "google_shopping",
+    "netprobe",
+    "openlibrary",
+    "passmark",
+    "pcpartpicker",
+    "rdap",
+    "stocks",
+    "techpowerup",
+    "userbenchmark",
+    "weather",
+    "wikidata",
+];
+
+/// The JSON Schema of the records a module collects (e.g. an eBay [`Product`](ebay::Product)
+/// for `ebay`), or `None` if there is no such module.
+#[cfg(feature = "net")]
+pub fn json_schema(module: &str) -> Option<RootSchema> {
+    Some(match module {
+        "bestbuy" => schema_for!(bestbuy::Product),
+        "craigslist" => schema_for!(craigslist::Listing),
+        "ebay" => schema_for!(ebay::Product),
+        "etsy" => schema_for!(etsy::Listing),
+        "geekbench" => schema_for!(geekbench::BenchmarkResult),
+        "google_shopping" => schema_for!(google_shopping::Offer),
+        "netprobe" => schema_for!(netprobe::Measurement),
+        "openlibrary" => schema_for!(openlibrary::Book),
+        "passmark" => schema_for!(passmark::CPU),
+        "pcpartpicker" => schema_for!(pcpartpicker::MotherboardSpecs),
+        "rdap" => schema_for!(rdap::DomainRecord),
+        "stocks" => schema_for!(stocks::Quote),
+        "techpowerup" => schema_for!(techpowerup::CPUSpecs),
+        "userbenchmark" => schema_for!(userbenchmark::Part),
+        "weather" => schema_for!(weather::Observation),
+        "wikidata" => schema_for!(wikidata::Entity),
+        _ => return None,
+    })
+}
+
+#[cfg(all(test, feature = "net"))]
+mod tests {
+    use super::{json_schema, MODULES};
+
+    #[test]
+    fn test_json_schema() {
+        for module in MODULES {
+            assert!(json_schema(module).is_some(), "{}", module);
+        }
+        assert!(json_schema("nope").is_none());
+
+        let schema = serde_json::to_value(json_schema("ebay").unwrap()).unwrap();
+        assert_eq!(schema["title"], "Product");
+        assert!(schema["properties"]["price"].is_object());
+        assert_eq!(
+            schema["definitions"]["Currency"]["enum"],
+            serde_json::json!(["USD"])
+        );
+
+        let schema = serde_json::to_value(json_schema("passmark").unwrap()).unwrap();
+        assert_eq!(
+            schema["properties"]["id"]["type"],
+            serde_json::json!("integer")
+        );
+    }
+}
@@ -0,0 +1,131 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    common::{
+        images::{self, ImageHash},
+        Client, Currency, Money,
+    },
+    modules::{bestbuy, ebay, etsy, google_shopping},
+};
+
+/// A picture of a product.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProductImage {
+    pub url: String,
+    /// Perceptual hashes of the image, if it has been downloaded (see [`Product::hash_images`]).
+    pub hash: Option<ImageHash>,
+}
+
+/// A product for sale, in a shape that is the same no matter which module it came from.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Product {
+    /// The name of the module the product came from, e.g. `ebay`.
+    pub source: String,
+    pub name: String,
+    pub price: Option<Money>,
+    /// A link to the product's page, if it has one.
+    pub url: Option<String>,
+    /// The GTIN (UPC, EAN or ISBN) of the product, if known.
+    pub gtin: Option<String>,
+    #[serde(default)]
+    pub images: Vec<ProductImage>,
+}
+
+impl Product {
+    /// Download the product's images and fill in their hashes.
+    ///
+    /// Images that fail to download or decode are left without a hash.
+    pub async fn hash_images<const COOKIES: bool>(&mut self, client: &Client<COOKIES>) {
+        let urls = self
+            .images
+            .iter()
+            .map(|image| image.url.clone())
+            .collect::<Vec<_>>();
+        for (image, hash) in self
+            .images
+            .iter_mut()
+            .zip(images::hash_urls(client, &urls).await)
+        {
+            image.hash = hash.ok();
+        }
+    }
+
+    /// Whether any of this product's images looks like one of `other`'s,
+    /// suggesting that they are the same physical item.
+    pub fn shares_image_with(&self, other: &Self, max_distance: u32) -> bool {
+        self.images.iter().filter_map(|a| a.hash).any(|a| {
+            other
+                .images
+                .iter()
+                .filter_map(|b| b.hash)
+                .any(|b| a.is_similar(&b, max_distance))
+        })
+    }
+}
+
+impl From<ebay::Product> for Product {
+    fn from(product: ebay::Product) -> Self {
+        Self {
+            source: "ebay".to_string(),
+            url: Some(format!("https://www.ebay.com/itm/{}", product.id)),
+            gtin: product.gtin().map(str::to_string),
+            name: product.name,
+            price: product.price,
+            images: product
+                .images
+                .into_iter()
+                .map(|url| ProductImage { url, hash: None })
+                .collect(),
+        }
+    }
+}
+
+impl From<etsy::Listing> for Product {
+    fn from(listing: etsy::Listing) -> Self {
+        Self {
+            source: "etsy".to_string(),
+            url: Some(format!("https://www.etsy.com/listing/{}", listing.id)),
+            gtin: None,
+            name: listing.title,
+            price: listing.price,
+            images: listing
+                .images
+                .into_iter()
+                .map(|url| ProductImage { url, hash: None })
+                .collect(),
+        }
+    }
+}
+
+impl From<bestbuy::Product> for Product {
+    fn from(product: bestbuy::Product) -> Self {
+        Self {
+            source: "bestbuy".to_string(),
+            url: product.url,
+            gtin: product.upc,
+            name: product.name,
+            price: product
+                .sale_price
+                .or(product.regular_price)
+                .and_then(|price| Money::from_f64(Currency::USD, price)),
+            images: product
+                .image
+                .into_iter()
+                .map(|url| ProductImage { url, hash: None })
+                .collect(),
+        }
+    }
+}
+
+impl From<google_shopping::Offer> for Product {
+    fn from(offer: google_shopping::Offer) -> Self {
+        Self {
+            source: "google_shopping".to_string(),
+            url: Some(offer.link),
+            gtin: None,
+            name: offer.title,
+            price: offer.price,
+            images: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::convert::TryFrom;
+
+use anyhow::Context;
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::Rating;
+use crate::modules::{ebay, etsy};
+
+/// A seller on a marketplace, in a shape that is the same no matter which module it came
+/// from, so that trust signals can be compared across platforms.
+#[derive(Serialize, Deserialize, JsonSchema, Clone, PartialEq, Debug)]
+pub struct SellerProfile {
+    /// The name of the module the seller came from, e.g. `ebay`.
+    pub platform: String,
+    /// The seller's username or shop name on the platform.
+    pub name: String,
+    pub rating: Option<Rating>,
+    /// When the seller joined the platform, to the month if that's all it says.
+    pub member_since: Option<NaiveDate>,
+    /// How many items the seller has sold, if known.
+    pub items_sold: Option<u64>,
+    /// A link to the seller's profile or shop.
+    pub url: Option<String>,
+}
+
+impl SellerProfile {
+    /// How long the seller has been on the platform, as of `today`.
+    pub fn tenure(&self, today: NaiveDate) -> Option<chrono::Duration> {
+        self.member_since.map(|since| today - since)
+    }
+}
+
+/// Read a month like `Jun 2009` as its first day.
+fn parse_month(month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("1 {}", month.trim()), "%d %b %Y").ok()
+}
+
+impl From<ebay::Seller> for SellerProfile {
+    fn from(seller: ebay::Seller) -> Self {
+        Self {
+            platform: "ebay".to_string(),
+            url: Some(format!("https://www.ebay.com/usr/{}", seller.name)),
+            name: seller.name,
+            rating: seller.feedback,
+            member_since: seller.member_since.as_deref().and_then(parse_month),
+            items_sold: seller.items_sold,
+        }
+    }
+}
+
+/// Etsy shops are only seen through their listings, which name the shop and show its rating.
+impl TryFrom<etsy::Listing> for SellerProfile {
+    type Error = anyhow::Error;
+    fn try_from(listing: etsy::Listing) -> anyhow::Result<Self> {
+        let id = listing.id;
+        let name = listing
+            .shop
+            .with_context(|| format!("listing {} doesn't say which shop sells it", id))?;
+        Ok(Self {
+            platform: "etsy".to_string(),
+            url: Some(format!("https://www.etsy.com/shop/{}", name)),
+            name,
+            rating: listing.rating,
+            member_since: None,
+            items_sold: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use chrono::NaiveDate;
+
+    use super::SellerProfile;
+    use crate::{
+        modules::{ebay, etsy},
+        schemas::common::Rating,
+    };
+
+    #[test]
+    fn test_seller_profile() {
+        let profile = SellerProfile::from(ebay::Seller {
+            name: "bellwetherbooks_usa".to_string(),
+            feedback: Rating::from_scale(99.6, 100.0),
+            items_sold: Some(1_200_000),
+            member_since: Some("Jun 2009".to_string()),
+        });
+        assert_eq!(profile.platform, "ebay");
+        assert_eq!(profile.member_since, NaiveDate::from_ymd_opt(2009, 6, 1));
+        assert_eq!(
+            profile
+                .tenure(NaiveDate::from_ymd_opt(2010, 6, 1).unwrap())
+                .unwrap()
+                .num_days(),
+            365
+        );
+        assert!((profile.rating.unwrap().value - 0.996).abs() < 1e-9);
+
+        let listing = etsy::Listing {
+            id: 1,
+            title: "Mug".to_string(),
+            price: None,
+            shop: Some("PotteryCo".to_string()),
+            rating: Rating::from_scale(4.9, 5.0).map(|r| r.with_count(Some(1284))),
+            ships_from: None,
+            images: Vec::new(),
+        };
+        let profile = SellerProfile::try_from(listing.clone()).unwrap();
+        assert_eq!(profile.name, "PotteryCo");
+        assert_eq!(
+            profile.url.as_deref(),
+            Some("https://www.etsy.com/shop/PotteryCo")
+        );
+        assert_eq!(profile.rating.unwrap().count, Some(1284));
+
+        let listing = etsy::Listing {
+            shop: None,
+            ..listing
+        };
+        assert!(SellerProfile::try_from(listing).is_err());
+    }
+}
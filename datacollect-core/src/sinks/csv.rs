@@ -0,0 +1,221 @@
+use std::{collections::HashSet, fs::File, io::Write, path::Path};
+
+use anyhow::bail;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use super::Sink;
+
+/// Writes records as rows of a CSV file, e.g. for a spreadsheet.
+///
+/// The columns are the fields of the first 100 records (which are held back
+/// until then), with nested objects flattened into dotted names (`price.amount`). Lists are
+/// written as JSON. A later record with a field that isn't a column can't be written, so
+/// it's an error rather than being left out.
+pub struct CsvSink<W: Write + Send> {
+    writer: csv::Writer<W>,
+    columns: Option<Vec<String>>,
+    /// The records seen before the columns were picked.
+    pending: Vec<Vec<(String, Value)>>,
+}
+
+/// How many records the columns of a CSV file are taken from.
+const HEADER_WINDOW: usize = 100;
+
+/// The fields of `record`, with those of nested objects named `outer.inner`.
+fn flatten(prefix: &str, record: &Map<String, Value>, fields: &mut Vec<(String, Value)>) {
+    for (name, value) in record {
+        let name = if prefix.is_empty() {
+            name.clone()
+        } else {
+            format!("{}.{}", prefix, name)
+        };
+        match value {
+            Value::Object(inner) => flatten(&name, inner, fields),
+            value => fields.push((name, value.clone())),
+        }
+    }
+}
+
+/// The fields of `records` in the order they first show up, leaving out a field that was
+/// only ever `null` where others are nested in it (`price` when there's `price.amount`).
+fn columns(records: &[Vec<(String, Value)>]) -> Vec<String> {
+    let mut columns = Vec::<String>::new();
+    let mut filled = HashSet::new();
+    for (name, value) in records.iter().flatten() {
+        if !columns.contains(name) {
+            columns.push(name.clone());
+        }
+        if !value.is_null() {
+            filled.insert(name.as_str());
+        }
+    }
+    let nested = |column: &str| {
+        columns
+            .iter()
+            .any(|other| other.starts_with(column) && other[column.len()..].starts_with('.'))
+    };
+    columns
+        .iter()
+        .filter(|column| filled.contains(column.as_str()) || !nested(column))
+        .cloned()
+        .collect()
+}
+
+/// Write a row of `fields` under `columns`.
+fn write_row<W: Write>(
+    writer: &mut csv::Writer<W>,
+    columns: &[String],
+    fields: Vec<(String, Value)>,
+) -> anyhow::Result<()> {
+    if let Some((name, _)) = fields
+        .iter()
+        .find(|(name, value)| !value.is_null() && !columns.contains(name))
+    {
+        bail!(
+            "`{}` is not one of the CSV columns, which are the fields of the first {} records",
+            name,
+            HEADER_WINDOW
+        );
+    }
+    let fields = fields.into_iter().collect::<Map<_, _>>();
+    writer.write_record(columns.iter().map(|column| cell(fields.get(column))))?;
+    Ok(())
+}
+
+fn cell(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(value) => value.to_string(),
+    }
+}
+
+impl<W: Write + Send> CsvSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: csv::Writer::from_writer(writer),
+            columns: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Pick the columns from the records held back so far, and write them.
+    fn write_pending(&mut self) -> anyhow::Result<()> {
+        let columns = columns(&self.pending);
+        self.writer.write_record(&columns)?;
+        for fields in std::mem::take(&mut self.pending) {
+            write_row(&mut self.writer, &columns, fields)?;
+        }
+        self.columns = Some(columns);
+        Ok(())
+    }
+
+    /// The writer, e.g. to look at what was written to a `Vec<u8>`.
+    ///
+    /// # Errors
+    /// Errors if what was buffered could not be written.
+    pub fn into_inner(self) -> anyhow::Result<W> {
+        Ok(self.writer.into_inner().map_err(|e| e.into_error())?)
+    }
+}
+
+impl CsvSink<File> {
+    /// Write to a new file at `path`, replacing it if it exists.
+    ///
+    /// # Errors
+    /// Errors if the file could not be created.
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(File::create(path)?))
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send> Sink for CsvSink<W> {
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        let record = match record {
+            Value::Object(record) => record,
+            _ => bail!("only objects can be written as CSV rows"),
+        };
+        let mut fields = Vec::new();
+        flatten("", record, &mut fields);
+
+        match &self.columns {
+            Some(columns) => write_row(&mut self.writer, columns, fields),
+            None => {
+                self.pending.push(fields);
+                if self.pending.len() < HEADER_WINDOW {
+                    return Ok(());
+                }
+                self.write_pending()
+            }
+        }
+    }
+
+    /// Also picks the columns, if there are still records held back.
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        if self.columns.is_none() && !self.pending.is_empty() {
+            self.write_pending()?;
+        }
+        Ok(self.writer.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{CsvSink, HEADER_WINDOW};
+    use crate::sinks::Sink;
+
+    #[tokio::test]
+    async fn test_csv() {
+        let mut sink = CsvSink::new(Vec::new());
+        for record in [
+            json!({"name": "Ryzen 5", "price": {"amount": 199.0, "currency": "USD"}, "tags": ["am4"]}),
+            json!({"name": "i7, 8th gen", "price": null, "cores": 6}),
+        ] {
+            sink.write(&record).await.unwrap();
+        }
+        assert!(sink.write(&json!([1, 2])).await.is_err());
+        sink.close().await.unwrap();
+        assert_eq!(
+            String::from_utf8(sink.into_inner().unwrap()).unwrap(),
+            "name,price.amount,price.currency,tags,cores\n\
+             Ryzen 5,199.0,USD,\"[\"\"am4\"\"]\",\n\
+             \"i7, 8th gen\",,,,6\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_csv_later_fields() {
+        /* a field is only known to be nested once a record has it */
+        let mut sink = CsvSink::new(Vec::new());
+        for record in [
+            json!({"name": "i7", "price": null}),
+            json!({"name": "Ryzen 5", "price": {"amount": 199.0}}),
+        ] {
+            sink.write(&record).await.unwrap();
+        }
+        sink.close().await.unwrap();
+        assert_eq!(
+            String::from_utf8(sink.into_inner().unwrap()).unwrap(),
+            "name,price.amount\ni7,\nRyzen 5,199.0\n"
+        );
+
+        /* once the columns are picked, a new field can't be written */
+        let mut sink = CsvSink::new(Vec::new());
+        for _ in 0..HEADER_WINDOW {
+            sink.write(&json!({"name": "i7", "price": null}))
+                .await
+                .unwrap();
+        }
+        sink.write(&json!({"name": "i5", "cores": null}))
+            .await
+            .unwrap();
+        assert!(sink
+            .write(&json!({"name": "i3", "cores": 4}))
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,325 @@
+//! Skipping records that haven't changed since they were last written, e.g. for a mega list
+//! that is pulled every day and is almost the same every time.
+//!
+//! Each record is hashed (see [`content_hash`]) without its [volatile fields](VOLATILE_FIELDS),
+//! and a [`HashStore`] keeps the hash of the last version of each record written, by the
+//! record's [key](DEFAULT_KEYS), in a file so that they last between runs. A record whose
+//! hash is the one kept for its key is left out, so only new and changed records reach the
+//! sink, including a record that changed back to what it was before.
+//!
+//! ## Example
+//! ```txt
+//! let store = HashStore::open("cpus.hashes")?;
+//! let mut sink = Dedup::new(JsonSink::append("cpus.jsonl")?, store).key(["name"]);
+//! for cpu in CPUMegaList::get(&mut client).await?.data {
+//!     sink.write(&serde_json::to_value(&cpu)?).await?;
+//! }
+//! sink.close().await?;
+//! ```
+
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+
+use super::Sink;
+
+/// Fields that change on every fetch without the record changing, so they aren't hashed.
+pub const VOLATILE_FIELDS: &[&str] = &["fetched_at", "checked_at"];
+
+/// The fields that say which record a record is, by default: the first of them that a
+/// record has. A record with none of them is known by its content alone.
+pub const DEFAULT_KEYS: &[&str] = &["id", "url"];
+
+/// `value` with the keys of its objects sorted and without the `ignore`d fields (at any
+/// depth), so that equal records look the same however they were built.
+fn canonical(value: &Value, ignore: &[String]) -> Value {
+    match value {
+        Value::Object(object) => {
+            let mut fields = object
+                .iter()
+                .filter(|(name, _)| !ignore.contains(name))
+                .collect::<Vec<_>>();
+            fields.sort_by_key(|(name, _)| *name);
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(name, value)| (name.clone(), canonical(value, ignore)))
+                    .collect::<Map<_, _>>(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|v| canonical(v, ignore)).collect()),
+        value => value.clone(),
+    }
+}
+
+/// A hash of `record` without the `ignore`d fields, which stays the same between builds
+/// and runs.
+pub fn content_hash(record: &Value, ignore: &[String]) -> u64 {
+    /* FNV-1a, like the cache's file names */
+    canonical(record, ignore)
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        })
+}
+
+/// The hash of the last version of each record written so far; see the
+/// [module documentation](self).
+///
+/// The file has a line per record, with the hash of its key and of its content, in hex.
+/// It's rewritten whole when saved, so it only ever has a line per record.
+#[derive(Default)]
+pub struct HashStore {
+    path: Option<PathBuf>,
+    hashes: HashMap<u64, u64>,
+    /// Whether there are hashes that aren't in the file yet.
+    changed: bool,
+}
+
+impl HashStore {
+    /// The hashes in the file at `path`, which is created when the first hashes are saved.
+    ///
+    /// # Errors
+    /// Errors if the file exists but could not be read, or has a line that isn't a pair of
+    /// hashes.
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let hashes = match std::fs::read_to_string(path) {
+            Ok(text) => text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    let (key, hash) = line.trim().split_once(' ')?;
+                    Some((
+                        u64::from_str_radix(key, 16).ok()?,
+                        u64::from_str_radix(hash, 16).ok()?,
+                    ))
+                })
+                .collect::<Option<_>>()
+                .with_context(|| format!("{} is not a hash store", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e).with_context(|| format!("could not read {}", path.display())),
+        };
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            hashes,
+            changed: false,
+        })
+    }
+
+    /// A store that only lasts as long as it does, e.g. to drop duplicates within one run.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// The hash of the last version of the record with this key.
+    pub fn get(&self, key: u64) -> Option<u64> {
+        self.hashes.get(&key).copied()
+    }
+
+    /// Set the hash of the record with this key, returning whether it changed.
+    pub fn insert(&mut self, key: u64, hash: u64) -> bool {
+        let changed = self.hashes.insert(key, hash) != Some(hash);
+        self.changed |= changed;
+        changed
+    }
+
+    /// Write the hashes to the file, if they changed since the last save.
+    ///
+    /// The file is replaced all at once, so it's never left half-written.
+    ///
+    /// # Errors
+    /// Errors if the file could not be written.
+    pub fn save(&mut self) -> anyhow::Result<()> {
+        let path = match &self.path {
+            Some(path) if self.changed => path,
+            _ => return Ok(()),
+        };
+        let mut hashes = self.hashes.iter().collect::<Vec<_>>();
+        hashes.sort();
+        let lines = hashes
+            .into_iter()
+            .map(|(key, hash)| format!("{:016x} {:016x}\n", key, hash))
+            .collect::<String>();
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        let context = || format!("could not write {}", path.display());
+        std::fs::File::create(&temp)
+            .and_then(|mut file| file.write_all(lines.as_bytes()))
+            .with_context(context)?;
+        std::fs::rename(&temp, path).with_context(context)?;
+        self.changed = false;
+        Ok(())
+    }
+}
+
+/// Writes only the records whose content isn't in a [`HashStore`] yet to another sink; see
+/// the [module documentation](self).
+pub struct Dedup<S: Sink> {
+    inner: S,
+    store: HashStore,
+    keys: Vec<String>,
+    ignore: Vec<String>,
+    skipped: usize,
+}
+
+impl<S: Sink> Dedup<S> {
+    /// Write to `inner` the records that aren't in `store`, known by the [`DEFAULT_KEYS`]
+    /// and ignoring the [`VOLATILE_FIELDS`].
+    pub fn new(inner: S, store: HashStore) -> Self {
+        Self {
+            inner,
+            store,
+            keys: DEFAULT_KEYS.iter().map(|f| f.to_string()).collect(),
+            ignore: VOLATILE_FIELDS.iter().map(|f| f.to_string()).collect(),
+            skipped: 0,
+        }
+    }
+
+    /// The fields to leave out of the hash, instead of the [`VOLATILE_FIELDS`].
+    pub fn ignore<I: IntoIterator<Item = T>, T: Into<String>>(self, fields: I) -> Self {
+        Self {
+            ignore: fields.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// The fields that say which record a record is, instead of the [`DEFAULT_KEYS`]: the
+    /// first of them that a record has.
+    pub fn key<I: IntoIterator<Item = T>, T: Into<String>>(self, fields: I) -> Self {
+        Self {
+            keys: fields.into_iter().map(Into::into).collect(),
+            ..self
+        }
+    }
+
+    /// The hash of the key of `record`, or of all of it if it has none.
+    fn key_hash(&self, record: &Value) -> u64 {
+        let key = self
+            .keys
+            .iter()
+            .find_map(|field| Some((field, record.get(field).filter(|v| !v.is_null())?)));
+        match key {
+            Some((field, value)) => content_hash(
+                &Value::Object(std::iter::once((field.clone(), value.clone())).collect()),
+                &[],
+            ),
+            None => content_hash(record, &self.ignore),
+        }
+    }
+
+    /// How many records were left out, because they were the same as when last written.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+#[async_trait]
+impl<S: Sink> Sink for Dedup<S> {
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        let (key, hash) = (self.key_hash(record), content_hash(record, &self.ignore));
+        if self.store.get(key) == Some(hash) {
+            self.skipped += 1;
+            return Ok(());
+        }
+        self.inner.write(record).await?;
+        self.store.insert(key, hash);
+        Ok(())
+    }
+
+    /// Flush the sink, then save the hashes of what it wrote, so that a record that failed
+    /// to be written isn't skipped next time.
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        self.inner.flush().await?;
+        self.store.save()
+    }
+
+    async fn close(&mut self) -> anyhow::Result<()> {
+        self.inner.close().await?;
+        self.store.save()?;
+        tracing::info!("skipped {} unchanged records", self.skipped);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{content_hash, Dedup, HashStore};
+    use crate::sinks::{JsonSink, Sink};
+
+    #[tokio::test]
+    async fn test_dedup() {
+        let volatile = ["fetched_at".to_string()];
+        assert_eq!(
+            content_hash(
+                &json!({"name": "i7", "fetched_at": "2021-11-20T14:53:00Z", "price": {"a": 1, "b": 2}}),
+                &volatile
+            ),
+            content_hash(&json!({"price": {"b": 2, "a": 1}, "name": "i7"}), &volatile)
+        );
+        assert_ne!(
+            content_hash(&json!({"name": "i7", "score": 1}), &volatile),
+            content_hash(&json!({"name": "i7", "score": 2}), &volatile)
+        );
+
+        let path = std::env::temp_dir().join(format!("datacollect-{}.hashes", std::process::id()));
+        let records = [
+            json!({"name": "Ryzen 5", "score": 21000, "fetched_at": "2021-11-20T14:53:00Z"}),
+            json!({"name": "i7", "score": 25000, "fetched_at": "2021-11-20T14:53:00Z"}),
+        ];
+        let mut sink = Dedup::new(JsonSink::new(Vec::new()), HashStore::open(&path).unwrap());
+        for record in &records {
+            sink.write(record).await.unwrap();
+        }
+        sink.close().await.unwrap();
+        let written = String::from_utf8(sink.into_inner().into_inner()).unwrap();
+        assert_eq!(written.lines().count(), 2);
+
+        /* the next day, one of them changed */
+        let mut sink = Dedup::new(JsonSink::new(Vec::new()), HashStore::open(&path).unwrap());
+        for record in [
+            json!({"name": "Ryzen 5", "score": 21000, "fetched_at": "2021-11-21T14:53:00Z"}),
+            json!({"name": "i7", "score": 24000, "fetched_at": "2021-11-21T14:53:00Z"}),
+        ] {
+            sink.write(&record).await.unwrap();
+        }
+        sink.close().await.unwrap();
+        assert_eq!(sink.skipped(), 1);
+        let written = String::from_utf8(sink.into_inner().into_inner()).unwrap();
+        assert!(written.contains("24000") && !written.contains("Ryzen"));
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 3);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dedup_changed_back() {
+        /* A, then B, then A again: the last one is a change too */
+        let mut sink = Dedup::new(JsonSink::new(Vec::new()), HashStore::in_memory()).key(["name"]);
+        for score in [21000, 22000, 21000, 21000] {
+            sink.write(&json!({"name": "Ryzen 5", "score": score}))
+                .await
+                .unwrap();
+        }
+        sink.write(&json!({"name": "i7", "score": 21000}))
+            .await
+            .unwrap();
+        sink.close().await.unwrap();
+        assert_eq!(sink.skipped(), 1);
+        let written = String::from_utf8(sink.into_inner().into_inner()).unwrap();
+        assert_eq!(written.lines().count(), 4);
+    }
+}
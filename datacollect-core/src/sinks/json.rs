@@ -0,0 +1,84 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::Sink;
+
+/// Writes records as JSON lines (one compact JSON value per line), e.g. to a `.jsonl` file.
+pub struct JsonSink<W: Write + Send> {
+    writer: W,
+}
+
+impl<W: Write + Send> JsonSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// The writer, e.g. to look at what was written to a `Vec<u8>`.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl JsonSink<BufWriter<File>> {
+    /// Write to a new file at `path`, replacing it if it exists.
+    ///
+    /// # Errors
+    /// Errors if the file could not be created.
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self::new(BufWriter::new(File::create(path)?)))
+    }
+
+    /// Write to the end of the file at `path`, creating it if it doesn't exist, e.g. for a
+    /// log that several runs add to.
+    ///
+    /// # Errors
+    /// Errors if the file could not be opened.
+    pub fn append(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self::new(BufWriter::new(file)))
+    }
+}
+
+#[async_trait]
+impl<W: Write + Send> Sink for JsonSink<W> {
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(self.writer.flush()?)
+    }
+}
+
+/// Writes records to stdout as JSON lines, as soon as they're written, so that another
+/// program can read them as they come.
+#[derive(Default)]
+pub struct StdoutSink;
+
+impl StdoutSink {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[async_trait]
+impl Sink for StdoutSink {
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        serde_json::to_writer(&mut stdout, record)?;
+        stdout.write_all(b"\n")?;
+        Ok(stdout.flush()?)
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        Ok(std::io::stdout().flush()?)
+    }
+}
@@ -0,0 +1,407 @@
+//! Destinations for collected records: files, databases or stdout, all behind the [`Sink`]
+//! trait, so that the same records can be written to any of them (or to several at once).
+//!
+//! A record is a JSON value, usually an object. Most sinks buffer what they are given, so
+//! [`Sink::close`] must be called for everything to be written.
+//!
+//! ## Example
+//! ```txt
+//! let mut sinks: Vec<Box<dyn Sink>> = vec![
+//!     Box::new(JsonSink::create("cpus.jsonl")?),
+//!     Box::new(CsvSink::create("cpus.csv")?),
+//! ];
+//! for cpu in CPUMegaList::get(&mut client).await?.data {
+//!     let record = serde_json::to_value(&cpu)?;
+//!     for sink in &mut sinks {
+//!         sink.write(&record).await?;
+//!     }
+//! }
+//! for sink in &mut sinks {
+//!     sink.close().await?;
+//! }
+//! ```
+
+mod csv;
+pub mod dedup;
+mod json;
+#[cfg(feature = "postgres")]
+pub mod postgres;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+
+use async_trait::async_trait;
+use chrono::DateTime;
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+
+pub use self::{
+    csv::CsvSink,
+    dedup::{Dedup, HashStore},
+    json::{JsonSink, StdoutSink},
+};
+
+/// Somewhere records can be written to.
+#[async_trait]
+pub trait Sink: Send {
+    /// Write a record (or buffer it, to be written later).
+    ///
+    /// # Errors
+    /// Errors if the record doesn't fit the destination (e.g. it has no key), or if
+    /// writing failed.
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()>;
+
+    /// Write everything that has been buffered.
+    ///
+    /// # Errors
+    /// Errors if writing failed.
+    async fn flush(&mut self) -> anyhow::Result<()>;
+
+    /// Write everything that has been buffered, and let go of the destination (e.g. close
+    /// the connection to a database). Nothing can be written after this.
+    ///
+    /// # Errors
+    /// Errors if writing failed.
+    async fn close(&mut self) -> anyhow::Result<()> {
+        self.flush().await
+    }
+}
+
+#[async_trait]
+impl<S: Sink + ?Sized> Sink for Box<S> {
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        (**self).write(record).await
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        (**self).flush().await
+    }
+
+    async fn close(&mut self) -> anyhow::Result<()> {
+        (**self).close().await
+    }
+}
+
+/// Several sinks, which each get every record.
+#[async_trait]
+impl Sink for Vec<Box<dyn Sink>> {
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        for sink in self {
+            sink.write(record).await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        for sink in self {
+            sink.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn close(&mut self) -> anyhow::Result<()> {
+        for sink in self {
+            sink.close().await?;
+        }
+        Ok(())
+    }
+}
+
+/// Write every record in a stream to `sink`, and close it, returning how many records
+/// were written.
+///
+/// # Errors
+/// Errors if a record could not be serialized, or as the sink does.
+pub async fn write_all<T: Serialize>(
+    sink: &mut dyn Sink,
+    records: impl Stream<Item = T>,
+) -> anyhow::Result<usize> {
+    futures::pin_mut!(records);
+    let mut count = 0;
+    while let Some(record) = records.next().await {
+        sink.write(&serde_json::to_value(&record)?).await?;
+        count += 1;
+    }
+    sink.close().await?;
+    Ok(count)
+}
+
+/// The type of a database column, from the values of a field; see [`postgres`] and
+/// [`sqlite`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ColumnType {
+    Boolean,
+    BigInt,
+    Double,
+    Timestamp,
+    Text,
+    /// Objects and lists, kept as JSON.
+    Json,
+}
+
+impl ColumnType {
+    /// The type for a value; `None` for `null`, which fits any column.
+    pub fn of(value: &Value) -> Option<Self> {
+        Some(match value {
+            Value::Null => return None,
+            Value::Bool(_) => Self::Boolean,
+            Value::Number(n) if n.is_i64() => Self::BigInt,
+            Value::Number(_) => Self::Double,
+            Value::String(s) if DateTime::parse_from_rfc3339(s).is_ok() => Self::Timestamp,
+            Value::String(_) => Self::Text,
+            Value::Array(_) | Value::Object(_) => Self::Json,
+        })
+    }
+
+    /// A type that fits the values of both.
+    pub fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Self::BigInt, Self::Double) | (Self::Double, Self::BigInt) => Self::Double,
+            (Self::Timestamp, Self::Text) | (Self::Text, Self::Timestamp) => Self::Text,
+            _ => Self::Json,
+        }
+    }
+}
+
+/// Where a database sink writes to.
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Target {
+    /// The connection URL, without the `table` and `key` parameters.
+    pub url: String,
+    pub table: String,
+    /// The columns that identify a record, e.g. `id`, or `source` and `id`.
+    pub key: Vec<String>,
+}
+
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+impl Target {
+    /// Read a database URL with the table and the conflict key as parameters, e.g.
+    /// `postgres://me@warehouse/prices?table=cpus&key=name`. The key is `id` by default,
+    /// and several columns are separated by commas.
+    ///
+    /// # Errors
+    /// Errors if the URL could not be parsed, or has no `table`.
+    pub fn parse(url: &str) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let mut url = reqwest::Url::parse(url).context("not a URL")?;
+        let (mut table, mut key) = (None, None);
+        let others = url
+            .query_pairs()
+            .filter_map(|(name, value)| match name.as_ref() {
+                "table" => {
+                    table = Some(value.to_string());
+                    None
+                }
+                "key" => {
+                    key = Some(value.to_string());
+                    None
+                }
+                _ => Some((name.to_string(), value.to_string())),
+            })
+            .collect::<Vec<_>>();
+        if others.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(others);
+        }
+
+        Ok(Self {
+            url: url.to_string(),
+            table: table.context("the URL has no `table` parameter")?,
+            key: key
+                .as_deref()
+                .unwrap_or("id")
+                .split(',')
+                .map(|column| column.trim().to_string())
+                .filter(|column| !column.is_empty())
+                .collect(),
+        })
+    }
+
+    fn scheme(&self) -> &str {
+        self.url.split(':').next().unwrap_or_default()
+    }
+
+    /// The value of the key of `record`, or the first key column it doesn't have.
+    fn key_of(&self, record: &serde_json::Map<String, Value>) -> Result<Vec<String>, &str> {
+        self.key
+            .iter()
+            .map(|k| match record.get(k) {
+                Some(value) if !value.is_null() => Ok(value.to_string()),
+                _ => Err(k.as_str()),
+            })
+            .collect()
+    }
+
+    /// The last record of each key, since a statement can't change the same row twice.
+    fn last_of_each_key(&self, records: &[serde_json::Map<String, Value>]) -> Vec<Value> {
+        let mut last = std::collections::HashMap::new();
+        for (i, record) in records.iter().enumerate() {
+            last.insert(self.key_of(record).unwrap_or_default(), i);
+        }
+        let mut indices = last.into_values().collect::<Vec<_>>();
+        indices.sort_unstable();
+        indices
+            .into_iter()
+            .map(|i| Value::Object(records[i].clone()))
+            .collect()
+    }
+}
+
+/// The columns the fields of `records` need that `known` doesn't have, or has with a type
+/// that doesn't fit all of their values: new ones typed from all of their values, known ones
+/// with a type that fits both, and the key columns (as text, if they're only ever null).
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn column_changes(
+    records: &[serde_json::Map<String, Value>],
+    known: &std::collections::BTreeMap<String, ColumnType>,
+    key: &[String],
+) -> std::collections::BTreeMap<String, ColumnType> {
+    let mut types = std::collections::BTreeMap::new();
+    for record in records {
+        for (name, value) in record {
+            if let Some(column_type) = ColumnType::of(value) {
+                types
+                    .entry(name.clone())
+                    .and_modify(|t: &mut ColumnType| *t = t.merge(column_type))
+                    .or_insert(column_type);
+            }
+        }
+    }
+    for k in key {
+        types.entry(k.clone()).or_insert(ColumnType::Text);
+    }
+    types
+        .into_iter()
+        .filter_map(|(name, column_type)| match known.get(&name) {
+            None => Some((name, column_type)),
+            Some(known) if known.merge(column_type) != *known => {
+                Some((name, known.merge(column_type)))
+            }
+            Some(_) => None,
+        })
+        .collect()
+}
+
+/// The columns of the fields of `records` that aren't in `known` yet (see
+/// [`column_changes`]), for databases that don't need a column's type to fit its values.
+#[cfg(feature = "sqlite")]
+fn new_columns(
+    records: &[serde_json::Map<String, Value>],
+    known: &std::collections::BTreeMap<String, ColumnType>,
+    key: &[String],
+) -> std::collections::BTreeMap<String, ColumnType> {
+    column_changes(records, known, key)
+        .into_iter()
+        .filter(|(name, _)| !known.contains_key(name))
+        .collect()
+}
+
+/// Quote an identifier, e.g. a column name, for SQL.
+#[cfg(any(feature = "postgres", feature = "sqlite"))]
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::{write_all, ColumnType, JsonSink};
+
+    #[test]
+    fn test_column_types() {
+        let types = [
+            json!(true),
+            json!(3),
+            json!(3.5),
+            json!("2021-11-20T14:53:00Z"),
+            json!("AMD"),
+            json!(["USD", 12.5]),
+        ]
+        .iter()
+        .map(|value| ColumnType::of(value).unwrap())
+        .collect::<Vec<_>>();
+        assert_eq!(
+            types,
+            vec![
+                ColumnType::Boolean,
+                ColumnType::BigInt,
+                ColumnType::Double,
+                ColumnType::Timestamp,
+                ColumnType::Text,
+                ColumnType::Json
+            ]
+        );
+        assert_eq!(ColumnType::of(&json!(null)), None);
+        assert_eq!(
+            ColumnType::BigInt.merge(ColumnType::Double),
+            ColumnType::Double
+        );
+        assert_eq!(ColumnType::Text.merge(ColumnType::BigInt), ColumnType::Json);
+    }
+
+    #[cfg(any(feature = "postgres", feature = "sqlite"))]
+    #[test]
+    fn test_column_changes() {
+        use std::collections::BTreeMap;
+
+        use super::column_changes;
+
+        let columns = |columns: &[(&str, ColumnType)]| {
+            columns
+                .iter()
+                .map(|(name, column_type)| (name.to_string(), *column_type))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let records = |records: serde_json::Value| {
+            records
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|record| record.as_object().unwrap().clone())
+                .collect::<Vec<_>>()
+        };
+        let key = ["id".to_string()];
+
+        let first = records(json!([{"id": 1, "score": 21000, "seen": "2021-11-20T14:53:00Z"}]));
+        let known = column_changes(&first, &BTreeMap::new(), &key);
+        assert_eq!(
+            known,
+            columns(&[
+                ("id", ColumnType::BigInt),
+                ("score", ColumnType::BigInt),
+                ("seen", ColumnType::Timestamp)
+            ])
+        );
+
+        /* later values that don't fit widen the column; ones that do change nothing */
+        let later = records(json!([
+            {"id": 2, "score": 25000.5, "seen": "yesterday", "cores": 8},
+            {"id": 3, "score": null, "price": null}
+        ]));
+        assert_eq!(
+            column_changes(&later, &known, &key),
+            columns(&[
+                ("cores", ColumnType::BigInt),
+                ("score", ColumnType::Double),
+                ("seen", ColumnType::Text)
+            ])
+        );
+        assert!(column_changes(&first, &known, &key).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_write_all() {
+        let mut sink = JsonSink::new(Vec::new());
+        let records = futures::stream::iter([json!({"id": 1}), json!({"a": [true], "id": 2})]);
+        assert_eq!(write_all(&mut sink, records).await.unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(sink.into_inner()).unwrap(),
+            "{\"id\":1}\n{\"a\":[true],\"id\":2}\n"
+        );
+    }
+}
@@ -0,0 +1,387 @@
+//! Upserting records into a Postgres table, e.g. a shared warehouse.
+//!
+//! The table has a column per top-level field of the records, typed from its values:
+//! `BOOLEAN`, `BIGINT`, `DOUBLE PRECISION`, `TIMESTAMPTZ` (for RFC 3339 strings), `TEXT`, or
+//! `JSONB` for objects, lists (e.g. [`Money`](crate::common::Money)) and fields whose values
+//! disagree. It's created if it doesn't exist, with a `UNIQUE` constraint on the conflict
+//! key. Fields that show up later are added as columns, and a column whose type doesn't fit
+//! a later value (e.g. a `BIGINT` column and `25000.5`) is changed to one that fits both.
+//!
+//! A record whose key is already in the table replaces the row.
+//!
+//! ## Example
+//! ```txt
+//! let mut sink = PostgresSink::from_url("postgres://me@warehouse/prices?table=cpus&key=name").await?;
+//! for cpu in CPUMegaList::get(&mut client).await?.data {
+//!     sink.write(&serde_json::to_value(&cpu)?).await?;
+//! }
+//! sink.close().await?;
+//! ```
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use sqlx::{postgres::PgPoolOptions, types::Json, PgPool};
+
+use super::{column_changes, quote, ColumnType, Sink, Target};
+
+/// How many records are sent in one statement.
+pub const BATCH_SIZE: usize = 500;
+
+fn sql_type(column_type: ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::Boolean => "BOOLEAN",
+        ColumnType::BigInt => "BIGINT",
+        ColumnType::Double => "DOUBLE PRECISION",
+        ColumnType::Timestamp => "TIMESTAMPTZ",
+        ColumnType::Text => "TEXT",
+        ColumnType::Json => "JSONB",
+    }
+}
+
+/// Quote a table name, which may have a schema (`warehouse.cpus`).
+fn quote_table(table: &str) -> String {
+    table.split('.').map(quote).collect::<Vec<_>>().join(".")
+}
+
+fn create_sql(target: &Target, columns: &BTreeMap<String, ColumnType>) -> String {
+    let columns = columns
+        .iter()
+        .map(|(name, column_type)| format!("{} {}", quote(name), sql_type(*column_type)))
+        .collect::<Vec<_>>();
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({}, UNIQUE ({}))",
+        quote_table(&target.table),
+        columns.join(", "),
+        target
+            .key
+            .iter()
+            .map(|k| quote(k))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// The type a column of the table already has, from Postgres's name for it. Types the sink
+/// doesn't create are taken as `JSONB`, which is never changed.
+fn column_type(sql_type: &str) -> ColumnType {
+    match sql_type {
+        "boolean" => ColumnType::Boolean,
+        "bigint" | "integer" | "smallint" => ColumnType::BigInt,
+        "double precision" | "real" => ColumnType::Double,
+        "timestamp with time zone" => ColumnType::Timestamp,
+        "text" | "character varying" => ColumnType::Text,
+        _ => ColumnType::Json,
+    }
+}
+
+/// Change the type of a column to one that also fits new values, converting the values in it.
+fn alter_column_sql(target: &Target, name: &str, column_type: ColumnType) -> String {
+    let using = match column_type {
+        ColumnType::Json => format!("to_jsonb({})", quote(name)),
+        column_type => format!("{}::{}", quote(name), sql_type(column_type)),
+    };
+    format!(
+        "ALTER TABLE {} ALTER COLUMN {} TYPE {} USING {}",
+        quote_table(&target.table),
+        quote(name),
+        sql_type(column_type),
+        using
+    )
+}
+
+fn add_column_sql(target: &Target, name: &str, column_type: ColumnType) -> String {
+    format!(
+        "ALTER TABLE {} ADD COLUMN IF NOT EXISTS {} {}",
+        quote_table(&target.table),
+        quote(name),
+        sql_type(column_type)
+    )
+}
+
+/// Insert the records in `$1` (a JSON list), replacing the rows with the same key.
+fn upsert_sql(target: &Target, columns: &BTreeMap<String, ColumnType>) -> String {
+    let names = columns.keys().map(|c| quote(c)).collect::<Vec<_>>();
+    let updates = columns
+        .keys()
+        .filter(|c| !target.key.contains(c))
+        .map(|c| format!("{0} = EXCLUDED.{0}", quote(c)))
+        .collect::<Vec<_>>();
+    let table = quote_table(&target.table);
+    format!(
+        "INSERT INTO {0} ({1}) SELECT {1} FROM jsonb_populate_recordset(NULL::{0}, $1) \
+         ON CONFLICT ({2}) {3}",
+        table,
+        names.join(", "),
+        target
+            .key
+            .iter()
+            .map(|k| quote(k))
+            .collect::<Vec<_>>()
+            .join(", "),
+        if updates.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            format!("DO UPDATE SET {}", updates.join(", "))
+        }
+    )
+}
+
+/// Upserts records into a Postgres table; see the [module documentation](self).
+///
+/// Records are sent [`BATCH_SIZE`] at a time, so [`Sink::close`] (or [`Sink::flush`]) must
+/// be called for the last ones to be written.
+pub struct PostgresSink {
+    pool: PgPool,
+    target: Target,
+    /// The columns the table is known to have.
+    columns: BTreeMap<String, ColumnType>,
+    pending: Vec<Map<String, Value>>,
+    created: bool,
+}
+
+impl PostgresSink {
+    /// Connect to the database in a `postgres://` URL (see [`Target::parse`]).
+    ///
+    /// # Errors
+    /// Errors if the URL could not be read, or the database could not be connected to.
+    pub async fn from_url(url: &str) -> anyhow::Result<Self> {
+        Self::connect(Target::parse(url)?).await
+    }
+
+    /// Connect to the database of `target`.
+    ///
+    /// # Errors
+    /// Errors if the target isn't a `postgres://` URL or has no key, or if the database
+    /// could not be connected to.
+    pub async fn connect(target: Target) -> anyhow::Result<Self> {
+        if !matches!(target.scheme(), "postgres" | "postgresql") {
+            bail!("expected a postgres:// URL");
+        }
+        if target.key.is_empty() {
+            bail!("a conflict key is needed to upsert records");
+        }
+        let pool = PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&target.url)
+            .await
+            .context("could not connect to Postgres")?;
+        Ok(Self {
+            pool,
+            target,
+            columns: BTreeMap::new(),
+            pending: Vec::new(),
+            created: false,
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for PostgresSink {
+    /// Add a record, which must be an object with the key's fields.
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        let record = match record {
+            Value::Object(record) => record,
+            _ => bail!("only objects can be written to Postgres"),
+        };
+        if let Err(missing) = self.target.key_of(record) {
+            bail!("the record has no `{}`, which is part of the key", missing);
+        }
+        self.pending.push(record.clone());
+        if self.pending.len() >= BATCH_SIZE {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        if !self.created {
+            /* the table may have been there already, with columns of its own */
+            let existing = sqlx::query_as::<_, (String, String)>(
+                "SELECT attname::TEXT, format_type(atttypid, NULL) FROM pg_attribute \
+                 WHERE attrelid = to_regclass($1) AND attnum > 0 AND NOT attisdropped",
+            )
+            .bind(quote_table(&self.target.table))
+            .fetch_all(&mut *transaction)
+            .await
+            .with_context(|| format!("could not read the columns of {}", self.target.table))?;
+            self.columns = existing
+                .into_iter()
+                .map(|(name, sql_type)| (name, column_type(&sql_type)))
+                .collect();
+        }
+
+        let changes = column_changes(&self.pending, &self.columns, &self.target.key);
+        if self.columns.is_empty() {
+            sqlx::query(&create_sql(&self.target, &changes))
+                .execute(&mut *transaction)
+                .await
+                .with_context(|| format!("could not create table {}", self.target.table))?;
+        } else {
+            for (name, column_type) in &changes {
+                let sql = match self.columns.contains_key(name) {
+                    true => alter_column_sql(&self.target, name, *column_type),
+                    false => add_column_sql(&self.target, name, *column_type),
+                };
+                sqlx::query(&sql)
+                    .execute(&mut *transaction)
+                    .await
+                    .with_context(|| format!("could not add or change column {}", name))?;
+            }
+        }
+        self.columns.extend(changes);
+
+        let records = self.target.last_of_each_key(&self.pending);
+        sqlx::query(&upsert_sql(&self.target, &self.columns))
+            .bind(Json(Value::Array(records)))
+            .execute(&mut *transaction)
+            .await
+            .with_context(|| format!("could not write to {}", self.target.table))?;
+        transaction.commit().await?;
+
+        self.created = true;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Write the remaining records, and disconnect.
+    async fn close(&mut self) -> anyhow::Result<()> {
+        self.flush().await?;
+        self.pool.close().await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde_json::json;
+    use sqlx::Row;
+
+    use super::{alter_column_sql, create_sql, upsert_sql, PostgresSink};
+    use crate::sinks::{ColumnType, Sink, Target};
+
+    #[tokio::test]
+    async fn test_target() {
+        let target = Target::parse(
+            "postgres://me@localhost/prices?sslmode=disable&table=cpus&key=source,id",
+        )
+        .unwrap();
+        assert_eq!(target.url, "postgres://me@localhost/prices?sslmode=disable");
+        assert_eq!(target.table, "cpus");
+        assert_eq!(target.key, vec!["source", "id"]);
+        assert_eq!(
+            Target::parse("postgresql://localhost/db?table=t")
+                .unwrap()
+                .key,
+            vec!["id"]
+        );
+        assert!(Target::parse("postgres://localhost/db").is_err());
+        assert!(PostgresSink::from_url("mysql://localhost/db?table=t")
+            .await
+            .is_err());
+
+        let columns = [
+            ("id", ColumnType::BigInt),
+            ("name", ColumnType::Text),
+            ("price", ColumnType::Json),
+        ]
+        .iter()
+        .map(|(name, column_type)| (name.to_string(), *column_type))
+        .collect::<BTreeMap<_, _>>();
+        let target = Target::parse("postgres://localhost/db?table=warehouse.cpus").unwrap();
+        assert_eq!(
+            create_sql(&target, &columns),
+            r#"CREATE TABLE IF NOT EXISTS "warehouse"."cpus" ("id" BIGINT, "name" TEXT, "price" JSONB, UNIQUE ("id"))"#
+        );
+        assert_eq!(
+            alter_column_sql(&target, "price", ColumnType::Json),
+            r#"ALTER TABLE "warehouse"."cpus" ALTER COLUMN "price" TYPE JSONB USING to_jsonb("price")"#
+        );
+        assert_eq!(
+            alter_column_sql(&target, "score", ColumnType::Double),
+            r#"ALTER TABLE "warehouse"."cpus" ALTER COLUMN "score" TYPE DOUBLE PRECISION USING "score"::DOUBLE PRECISION"#
+        );
+        assert_eq!(
+            upsert_sql(&target, &columns),
+            r#"INSERT INTO "warehouse"."cpus" ("id", "name", "price") SELECT "id", "name", "price" FROM jsonb_populate_recordset(NULL::"warehouse"."cpus", $1) ON CONFLICT ("id") DO UPDATE SET "name" = EXCLUDED."name", "price" = EXCLUDED."price""#
+        );
+    }
+
+    /// Needs a database to write to, e.g. `DATABASE_URL=postgres://localhost/test`.
+    #[tokio::test]
+    #[ignore]
+    async fn test_postgres() {
+        let url = std::env::var("DATABASE_URL").unwrap();
+        let table = format!("cpus_{}", rand::random::<u32>());
+        let target = format!("{}?table={}&key=name", url, table);
+
+        let mut sink = PostgresSink::from_url(&target).await.unwrap();
+        for record in [
+            json!({"name": "Ryzen 5", "score": 21000, "price": ["USD", 199.0]}),
+            json!({"name": "i7", "score": 25000.5, "price": null}),
+            json!({"name": "Ryzen 5", "score": 22000, "price": ["USD", 189.0]}),
+        ] {
+            sink.write(&record).await.unwrap();
+        }
+        assert!(sink.write(&json!({"score": 1})).await.is_err());
+        sink.close().await.unwrap();
+
+        /* a new field adds a column, and an existing key replaces the row */
+        let mut sink = PostgresSink::from_url(&target).await.unwrap();
+        sink.write(&json!({"name": "i7", "score": 26000, "cores": 8}))
+            .await
+            .unwrap();
+        sink.flush().await.unwrap();
+        /* a value that doesn't fit the column changes its type */
+        sink.write(&json!({"name": "i9", "score": 30000, "cores": 8.5}))
+            .await
+            .unwrap();
+        sink.close().await.unwrap();
+
+        let pool = sqlx::PgPool::connect(&url).await.unwrap();
+        let rows = sqlx::query(&format!(
+            "SELECT name, score, price->>1 AS amount, cores FROM {} ORDER BY name",
+            table
+        ))
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let rows = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<String, _>("name"),
+                    row.get::<f64, _>("score"),
+                    row.get::<Option<String>, _>("amount"),
+                    row.get::<Option<f64>, _>("cores"),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "Ryzen 5".to_string(),
+                    22000.0,
+                    Some("189.0".to_string()),
+                    None
+                ),
+                ("i7".to_string(), 26000.0, None, Some(8.0)),
+                ("i9".to_string(), 30000.0, None, Some(8.5)),
+            ]
+        );
+        sqlx::query(&format!("DROP TABLE {}", table))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+}
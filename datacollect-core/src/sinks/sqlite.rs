@@ -0,0 +1,280 @@
+//! Upserting records into a table of a SQLite file, e.g. to query a collection locally.
+//!
+//! Like [`postgres`](super::postgres), the table has a column per top-level field of the
+//! records, and is created (with a `UNIQUE` constraint on the conflict key) if it doesn't
+//! exist. SQLite only has a few types, so columns are `INTEGER` (also for booleans),
+//! `REAL`, or `TEXT`, which holds timestamps and the JSON of objects and lists.
+//!
+//! ## Example
+//! ```txt
+//! let mut sink = SqliteSink::from_url("sqlite://prices.db?table=cpus&key=name").await?;
+//! for cpu in CPUMegaList::get(&mut client).await?.data {
+//!     sink.write(&serde_json::to_value(&cpu)?).await?;
+//! }
+//! sink.close().await?;
+//! ```
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use anyhow::{bail, Context};
+use async_trait::async_trait;
+use serde_json::{Map, Value};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    SqlitePool,
+};
+
+use super::{new_columns, quote, ColumnType, Sink, Target};
+
+/// How many records are sent in one statement.
+pub const BATCH_SIZE: usize = 500;
+
+fn sql_type(column_type: ColumnType) -> &'static str {
+    match column_type {
+        ColumnType::Boolean | ColumnType::BigInt => "INTEGER",
+        ColumnType::Double => "REAL",
+        ColumnType::Timestamp | ColumnType::Text | ColumnType::Json => "TEXT",
+    }
+}
+
+fn create_sql(target: &Target, columns: &BTreeMap<String, ColumnType>) -> String {
+    let columns = columns
+        .iter()
+        .map(|(name, column_type)| format!("{} {}", quote(name), sql_type(*column_type)))
+        .collect::<Vec<_>>();
+    format!(
+        "CREATE TABLE IF NOT EXISTS {} ({}, UNIQUE ({}))",
+        quote(&target.table),
+        columns.join(", "),
+        target
+            .key
+            .iter()
+            .map(|k| quote(k))
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
+
+/// Insert the records in `?1` (a JSON list), replacing the rows with the same key.
+fn upsert_sql(target: &Target, columns: &BTreeMap<String, ColumnType>) -> String {
+    let names = columns.keys().map(|c| quote(c)).collect::<Vec<_>>();
+    let values = columns
+        .keys()
+        .map(|c| format!("json_extract(value, '$.{}')", quote(c).replace('\'', "''")))
+        .collect::<Vec<_>>();
+    let updates = columns
+        .keys()
+        .filter(|c| !target.key.contains(c))
+        .map(|c| format!("{0} = excluded.{0}", quote(c)))
+        .collect::<Vec<_>>();
+    /* `WHERE true` keeps `ON CONFLICT` from being read as part of the `SELECT` */
+    format!(
+        "INSERT INTO {} ({}) SELECT {} FROM json_each(?1) WHERE true ON CONFLICT ({}) {}",
+        quote(&target.table),
+        names.join(", "),
+        values.join(", "),
+        target
+            .key
+            .iter()
+            .map(|k| quote(k))
+            .collect::<Vec<_>>()
+            .join(", "),
+        if updates.is_empty() {
+            "DO NOTHING".to_string()
+        } else {
+            format!("DO UPDATE SET {}", updates.join(", "))
+        }
+    )
+}
+
+/// Upserts records into a SQLite table; see the [module documentation](self).
+///
+/// Records are sent [`BATCH_SIZE`] at a time, so [`Sink::close`] (or [`Sink::flush`]) must
+/// be called for the last ones to be written.
+pub struct SqliteSink {
+    pool: SqlitePool,
+    target: Target,
+    /// The columns the table is known to have.
+    columns: BTreeMap<String, ColumnType>,
+    pending: Vec<Map<String, Value>>,
+    created: bool,
+}
+
+impl SqliteSink {
+    /// Open (or create) the database file in a `sqlite://` URL (see [`Target::parse`]).
+    ///
+    /// # Errors
+    /// Errors if the URL could not be read, or the database could not be opened.
+    pub async fn from_url(url: &str) -> anyhow::Result<Self> {
+        Self::connect(Target::parse(url)?).await
+    }
+
+    /// Open (or create) the database of `target`.
+    ///
+    /// # Errors
+    /// Errors if the target isn't a `sqlite://` URL or has no key, or if the database could
+    /// not be opened.
+    pub async fn connect(target: Target) -> anyhow::Result<Self> {
+        if target.scheme() != "sqlite" {
+            bail!("expected a sqlite:// URL");
+        }
+        if target.key.is_empty() {
+            bail!("a conflict key is needed to upsert records");
+        }
+        let options = SqliteConnectOptions::from_str(&target.url)?.create_if_missing(true);
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .context("could not open the SQLite database")?;
+        Ok(Self {
+            pool,
+            target,
+            columns: BTreeMap::new(),
+            pending: Vec::new(),
+            created: false,
+        })
+    }
+}
+
+#[async_trait]
+impl Sink for SqliteSink {
+    /// Add a record, which must be an object with the key's fields.
+    async fn write(&mut self, record: &Value) -> anyhow::Result<()> {
+        let record = match record {
+            Value::Object(record) => record,
+            _ => bail!("only objects can be written to SQLite"),
+        };
+        if let Err(missing) = self.target.key_of(record) {
+            bail!("the record has no `{}`, which is part of the key", missing);
+        }
+        self.pending.push(record.clone());
+        if self.pending.len() >= BATCH_SIZE {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> anyhow::Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        if !self.created {
+            let new = new_columns(&self.pending, &self.columns, &self.target.key);
+            sqlx::query(&create_sql(&self.target, &new))
+                .execute(&mut *transaction)
+                .await
+                .with_context(|| format!("could not create table {}", self.target.table))?;
+            /* the table may have been there already, with other columns */
+            let existing =
+                sqlx::query_scalar::<_, String>("SELECT name FROM pragma_table_info(?1)")
+                    .bind(&self.target.table)
+                    .fetch_all(&mut *transaction)
+                    .await?;
+            self.columns
+                .extend(existing.into_iter().map(|name| (name, ColumnType::Text)));
+        }
+        /* SQLite has no `ADD COLUMN IF NOT EXISTS`, so only the ones known to be new */
+        let new = new_columns(&self.pending, &self.columns, &self.target.key);
+        for (name, column_type) in &new {
+            sqlx::query(&format!(
+                "ALTER TABLE {} ADD COLUMN {} {}",
+                quote(&self.target.table),
+                quote(name),
+                sql_type(*column_type)
+            ))
+            .execute(&mut *transaction)
+            .await
+            .with_context(|| format!("could not add column {}", name))?;
+        }
+        self.columns.extend(new);
+
+        let records = self.target.last_of_each_key(&self.pending);
+        sqlx::query(&upsert_sql(&self.target, &self.columns))
+            .bind(Value::Array(records).to_string())
+            .execute(&mut *transaction)
+            .await
+            .with_context(|| format!("could not write to {}", self.target.table))?;
+        transaction.commit().await?;
+
+        self.created = true;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Write the remaining records, and close the database.
+    async fn close(&mut self) -> anyhow::Result<()> {
+        self.flush().await?;
+        self.pool.close().await;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use sqlx::Row;
+
+    use super::SqliteSink;
+    use crate::sinks::Sink;
+
+    #[tokio::test]
+    async fn test_sqlite() {
+        let path = std::env::temp_dir().join(format!("datacollect-{}.db", rand::random::<u32>()));
+        let url = format!("sqlite://{}?table=cpus&key=name", path.display());
+
+        let mut sink = SqliteSink::from_url(&url).await.unwrap();
+        for record in [
+            json!({"name": "Ryzen 5", "score": 21000, "price": ["USD", 199.0]}),
+            json!({"name": "i7", "score": 25000.5, "price": null}),
+            json!({"name": "Ryzen 5", "score": 22000, "price": ["USD", 189.0]}),
+        ] {
+            sink.write(&record).await.unwrap();
+        }
+        assert!(sink.write(&json!({"score": 1})).await.is_err());
+        sink.close().await.unwrap();
+        assert!(SqliteSink::from_url("postgres://localhost/db?table=t")
+            .await
+            .is_err());
+
+        /* a new field adds a column, and an existing key replaces the row */
+        let mut sink = SqliteSink::from_url(&url).await.unwrap();
+        sink.write(&json!({"name": "i7", "score": 26000, "cores": 8, "sale": true}))
+            .await
+            .unwrap();
+        sink.close().await.unwrap();
+
+        let pool = sqlx::SqlitePool::connect(&format!("sqlite://{}", path.display()))
+            .await
+            .unwrap();
+        let rows = sqlx::query(
+            "SELECT name, score, price ->> 1 AS amount, cores, sale FROM cpus ORDER BY name",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        let rows = rows
+            .iter()
+            .map(|row| {
+                (
+                    row.get::<String, _>("name"),
+                    row.get::<f64, _>("score"),
+                    row.get::<Option<f64>, _>("amount"),
+                    row.get::<Option<i64>, _>("cores"),
+                    row.get::<Option<bool>, _>("sale"),
+                )
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            rows,
+            vec![
+                ("Ryzen 5".to_string(), 22000.0, Some(189.0), None, None),
+                ("i7".to_string(), 26000.0, None, Some(8), Some(true)),
+            ]
+        );
+        pool.close().await;
+        std::fs::remove_file(path).unwrap();
+    }
+}
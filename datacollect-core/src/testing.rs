@@ -0,0 +1,222 @@
+//! Helpers for testing code that uses datacollect, without touching live sites.
+//!
+//! Start a [`MockServer`], tell it what to respond with, and hand [`MockServer::client`]
+//! to the module under test. Every request the client makes goes to the mock server,
+//! with the same path and query it would have had on the real site.
+//!
+//! ## Example
+//! ```txt
+//! let server = MockServer::start().await?;
+//! server.mock("/domain/example.com", 404, "");
+//! let record = DomainRecord::get(&mut server.client(), "example.com", Detail::Default).await?;
+//! assert!(record.is_none());
+//! ```
+
+use std::{
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::common::Client;
+
+/// Point a client at `base` (e.g. `http://localhost:8080`) for every request it makes.
+pub fn redirect<const COOKIES: bool>(client: Client<COOKIES>, base: &str) -> Client<COOKIES> {
+    client.with_base_url_override_all(base)
+}
+
+#[derive(Clone)]
+struct Mock {
+    path: String,
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+/// A tiny HTTP server that answers with canned responses.
+///
+/// Responses are matched by path. A mock whose path includes a query string
+/// (e.g. `/sch/i.html?_nkw=cpu`) only matches requests with exactly that query.
+/// Requests that match no mock get a 404. The server stops when it is dropped.
+pub struct MockServer {
+    addr: SocketAddr,
+    mocks: Arc<Mutex<Vec<Mock>>>,
+    requests: Arc<Mutex<Vec<String>>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Start a server on a free local port.
+    ///
+    /// # Errors
+    /// Errors if no port could be bound.
+    pub async fn start() -> anyhow::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let mocks = Arc::new(Mutex::new(Vec::<Mock>::new()));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let handle = tokio::spawn({
+            let (mocks, requests) = (mocks.clone(), requests.clone());
+            async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let (mocks, requests) = (mocks.clone(), requests.clone());
+                    tokio::spawn(async move {
+                        /* a broken connection only fails the request that made it */
+                        let _ = Self::serve(stream, mocks, requests).await;
+                    });
+                }
+            }
+        });
+
+        Ok(Self {
+            addr,
+            mocks,
+            requests,
+            handle,
+        })
+    }
+
+    async fn serve(
+        mut stream: TcpStream,
+        mocks: Arc<Mutex<Vec<Mock>>>,
+        requests: Arc<Mutex<Vec<String>>>,
+    ) -> anyhow::Result<()> {
+        /* only the request line matters; read until the end of the headers */
+        let mut buf = Vec::new();
+        let mut chunk = [0; 4096];
+        while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            let n = stream.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            buf.extend_from_slice(&chunk[..n]);
+        }
+
+        let head = String::from_utf8_lossy(&buf);
+        let target = head
+            .lines()
+            .next()
+            .and_then(|line| line.split_whitespace().nth(1))
+            .unwrap_or("/")
+            .to_string();
+        requests.lock().unwrap().push(target.clone());
+
+        let path = target.split('?').next().unwrap_or_default();
+        let mock = mocks
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|m| m.path == target || (!m.path.contains('?') && m.path == path))
+            .cloned()
+            .unwrap_or(Mock {
+                path: target,
+                status: 404,
+                content_type: "text/html; charset=utf-8",
+                body: Vec::new(),
+            });
+
+        let head = format!(
+            "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            mock.status,
+            mock.content_type,
+            mock.body.len(),
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(&mock.body).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+
+    /// The base URL of the server, e.g. `http://127.0.0.1:41234`.
+    pub fn uri(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Respond to requests for `path` with the given status and body.
+    ///
+    /// Bodies that look like JSON are sent as `application/json`, and everything else as HTML.
+    /// Later mocks take priority over earlier ones for the same path.
+    pub fn mock(&self, path: &str, status: u16, body: &str) -> &Self {
+        let content_type = match body.trim_start().chars().next() {
+            Some('{') | Some('[') => "application/json",
+            _ => "text/html; charset=utf-8",
+        };
+        self.mocks.lock().unwrap().push(Mock {
+            path: path.to_string(),
+            status,
+            content_type,
+            body: body.as_bytes().to_vec(),
+        });
+        self
+    }
+
+    /// Like [`MockServer::mock`], for binary bodies (e.g. images or gzipped files), which are
+    /// sent as `application/octet-stream`.
+    pub fn mock_bytes(&self, path: &str, status: u16, body: &[u8]) -> &Self {
+        self.mocks.lock().unwrap().push(Mock {
+            path: path.to_string(),
+            status,
+            content_type: "application/octet-stream",
+            body: body.to_vec(),
+        });
+        self
+    }
+
+    /// The paths (with queries) of every request received so far, in order.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// A client that sends every request to this server.
+    pub fn client<const COOKIES: bool>(&self) -> Client<COOKIES> {
+        redirect(Client::default(), &self.uri())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MockServer;
+    use crate::{common::Detail, modules::rdap::DomainRecord};
+
+    #[tokio::test]
+    async fn test_mock_server() {
+        let server = MockServer::start().await.unwrap();
+        server
+            .mock(
+                "/domain/example.com",
+                200,
+                r#"{"events": [{"eventAction": "registration", "eventDate": "1995-08-14T04:00:00Z"}]}"#,
+            )
+            .mock("/domain/example.net", 404, "");
+
+        let mut client = server.client();
+        let record = DomainRecord::get(&mut client, "example.com", Detail::Default)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.events.len(), 1);
+
+        let record = DomainRecord::get(&mut client, "example.net", Detail::Default)
+            .await
+            .unwrap();
+        assert!(record.is_none());
+
+        assert_eq!(
+            server.requests(),
+            vec!["/domain/example.com", "/domain/example.net"]
+        );
+    }
+}
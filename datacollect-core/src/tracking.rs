@@ -0,0 +1,825 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_with::{serde_as, DeserializeAs, DisplayFromStr, PickFirst};
+
+use crate::{
+    analysis::{self, Point},
+    common::{Client, Currency, Detail, Money},
+    modules::{ebay::Product, rdap::DomainRecord},
+    notify::{self, Notification},
+};
+
+/// Something whose price should be followed over time.
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(rename_all = "snake_case")]
+pub enum TrackedItem {
+    /// An eBay item ID.
+    Ebay(u64),
+    /// A link to a page one of the modules understands (currently only eBay items).
+    Url(String),
+    /// A domain, e.g. `example.com`. It has no price, but is looked up over RDAP to see
+    /// whether it can be registered; see [`Rule::available`].
+    Domain(String),
+}
+
+impl TrackedItem {
+    /// A stable key identifying this item in a [`Store`].
+    ///
+    /// URLs are resolved to the item they point to where possible, so that the same
+    /// eBay listing is tracked as one item whether it was given by ID or by link.
+    pub fn key(&self) -> String {
+        match self {
+            Self::Ebay(id) => format!("ebay:{}", id),
+            Self::Url(url) => match Product::id_from_url(url) {
+                Some(id) => Self::Ebay(id).key(),
+                None => url.clone(),
+            },
+            Self::Domain(name) => format!("domain:{}", name.to_ascii_lowercase()),
+        }
+    }
+
+    /// Fetch the current price of the item.
+    ///
+    /// # Errors
+    /// Errors if the item could not be fetched, if the URL is not understood by any module,
+    /// or if the item is a domain.
+    pub async fn fetch_price(&self, client: &mut Client<false>) -> anyhow::Result<Option<Money>> {
+        let id = match self {
+            Self::Ebay(id) => *id,
+            Self::Url(url) => Product::id_from_url(url)
+                .with_context(|| format!("don't know how to track {}", url))?,
+            Self::Domain(name) => anyhow::bail!("{} is a domain, which has no price", name),
+        };
+
+        Ok(Product::by_id(client, id, Detail::Minimal).await?.price)
+    }
+
+    /// Fetch the record of the item for now: its price or, for a domain, whether it can be
+    /// registered (whether RDAP doesn't find it).
+    ///
+    /// # Errors
+    /// Errors if the item could not be fetched, or if the URL is not understood by any module.
+    pub async fn fetch(&self, client: &mut Client<false>) -> anyhow::Result<PriceRecord> {
+        let (price, available) = match self {
+            Self::Domain(name) => (
+                None,
+                Some(
+                    DomainRecord::get(client, name, Detail::Minimal)
+                        .await?
+                        .is_none(),
+                ),
+            ),
+            _ => (self.fetch_price(client).await?, None),
+        };
+        Ok(PriceRecord {
+            item: self.key(),
+            fetched_at: Utc::now(),
+            price,
+            available,
+        })
+    }
+}
+
+/// The contents of a tracking configuration file (e.g. `track.toml`).
+///
+/// ## Example
+/// ```toml
+/// store = "prices.jsonl"
+/// items = [
+///     { ebay = 254625474154 },
+///     { url = "https://www.ebay.com/itm/254625474154" },
+///     { domain = "example.com" },
+/// ]
+///
+/// [notify]
+/// stderr = true
+/// webhooks = ["https://example.com/hooks/prices"]
+/// rules = [
+///     { item = { ebay = 254625474154 }, below = 30.0 },
+///     { item = { ebay = 254625474154 }, change_percent = -10.0, days = 14 },
+///     { item = { domain = "example.com" }, available = true },
+/// ]
+/// ```
+#[derive(Deserialize, Serialize)]
+pub struct Config {
+    /// Where price records are appended to: a JSON lines file, or (with the `sqlite`
+    /// feature) a SQLite database ending in `.db`, `.sqlite` or `.sqlite3`; see [`Store`].
+    pub store: PathBuf,
+    pub items: Vec<TrackedItem>,
+    #[serde(default)]
+    pub notify: Notify,
+}
+
+impl Config {
+    /// Read a [`Config`] from a TOML file.
+    ///
+    /// # Errors
+    /// Errors if the file could not be read or is not a valid configuration.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("could not read {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("could not parse {}", path.display()))
+    }
+}
+
+/// The `notify` section of a tracking [`Config`].
+#[derive(Deserialize, Serialize, Default)]
+pub struct Notify {
+    #[serde(flatten)]
+    pub targets: notify::Config,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+/// An amount of USD given as a number in a [`Rule`], e.g. `30.0`.
+struct Dollars;
+
+impl<'de> DeserializeAs<'de, Money> for Dollars {
+    fn deserialize_as<D: Deserializer<'de>>(deserializer: D) -> Result<Money, D::Error> {
+        let amount = f64::deserialize(deserializer)?;
+        Money::from_f64(Currency::USD, amount)
+            .ok_or_else(|| D::Error::custom(format!("{} is not an amount of money", amount)))
+    }
+}
+
+/// Notify when the price of `item` crosses a threshold, or when its trend does (or, for a
+/// domain, when it can be registered).
+///
+/// A rule only fires on the run where the price crosses the threshold,
+/// not on every run where it stays past it.
+#[serde_as]
+#[derive(Deserialize, Serialize, Clone)]
+pub struct Rule {
+    pub item: TrackedItem,
+    /// Fire when the price drops below this amount, e.g. `30.0`, `"$30"` or `["USD", 30.0]`.
+    /// Prices in another currency never cross it.
+    #[serde(default)]
+    #[serde_as(as = "Option<PickFirst<(_, Dollars, DisplayFromStr)>>")]
+    pub below: Option<Money>,
+    /// Fire when the price rises above this amount; see `below`.
+    #[serde(default)]
+    #[serde_as(as = "Option<PickFirst<(_, Dollars, DisplayFromStr)>>")]
+    pub above: Option<Money>,
+    /// Fire when the price changed by at least this many percent over the last `days`:
+    /// a drop if negative (e.g. `-10.0`), a rise if positive.
+    pub change_percent: Option<f64>,
+    /// Fire when the price is at least this many standard deviations (e.g. `3.0`) from
+    /// the prices of the last `days`, either way.
+    pub z_score: Option<f64>,
+    /// The span of history `change_percent` and `z_score` look at, in days.
+    #[serde(default = "Rule::default_days")]
+    pub days: u32,
+    /// Fire when a domain can be registered: RDAP found it on the last run, and doesn't now.
+    #[serde(default)]
+    pub available: bool,
+}
+
+impl Rule {
+    fn default_days() -> u32 {
+        7
+    }
+
+    /// Whether the rule looks at the item's history, with [`Rule::check_trend`].
+    pub fn has_trend(&self) -> bool {
+        self.change_percent.is_some() || self.z_score.is_some()
+    }
+
+    /// Check whether the trend conditions of this rule fire for the history of the item
+    /// with the given key (see [`series`]), ending with the latest price, returning a
+    /// message if they do.
+    pub fn check_trend(&self, item: &str, series: &[Point]) -> Option<String> {
+        if self.item.key() != item {
+            return None;
+        }
+        let (_, earlier) = series.split_last()?;
+        let period = chrono::Duration::days(self.days.into());
+
+        if let Some(threshold) = self.change_percent {
+            let past = |change: f64| {
+                if threshold < 0.0 {
+                    change <= threshold
+                } else {
+                    change >= threshold
+                }
+            };
+            if let Some(change) = analysis::change_over(series, period).filter(|c| past(*c)) {
+                if !analysis::change_over(earlier, period).is_some_and(past) {
+                    return Some(format!(
+                        "price changed {:+.1}% over {} days",
+                        change, self.days
+                    ));
+                }
+            }
+        }
+        if let Some(threshold) = self.z_score {
+            let latest = analysis::z_scores(series, period).pop()?;
+            let previous = analysis::z_scores(earlier, period).pop();
+            if latest.at == series.last()?.at
+                && latest.z_score.abs() >= threshold
+                && !previous.is_some_and(|p| p.z_score.abs() >= threshold)
+            {
+                return Some(format!(
+                    "price is unusual: {} is {:+.1} standard deviations from the last {} days",
+                    latest.value, latest.z_score, self.days
+                ));
+            }
+        }
+
+        None
+    }
+
+    /// Check whether this rule fires for the given [`Delta`], returning a message if it does.
+    pub fn check(&self, delta: &Delta) -> Option<String> {
+        if self.item.key() != delta.item {
+            return None;
+        }
+
+        if self.available && delta.was_available == Some(false) && delta.available == Some(true) {
+            return Some("can be registered: RDAP no longer finds it".to_string());
+        }
+
+        let current = delta.current.as_ref()?;
+        let previous = delta.previous.as_ref();
+
+        if let Some(below) = &self.below {
+            if current < below && !previous.is_some_and(|p| p < below) {
+                return Some(format!(
+                    "price dropped below {}: now {}",
+                    below.amount(),
+                    current.amount()
+                ));
+            }
+        }
+        if let Some(above) = &self.above {
+            if current > above && !previous.is_some_and(|p| p > above) {
+                return Some(format!(
+                    "price rose above {}: now {}",
+                    above.amount(),
+                    current.amount()
+                ));
+            }
+        }
+
+        None
+    }
+}
+
+/// A single price observation, as stored in a [`Store`].
+#[derive(Deserialize, Serialize, Clone)]
+pub struct PriceRecord {
+    /// The [`TrackedItem::key`] of the observed item.
+    pub item: String,
+    pub fetched_at: DateTime<Utc>,
+    pub price: Option<Money>,
+    /// For a domain, whether it could be registered (RDAP didn't find it).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub available: Option<bool>,
+}
+
+/// Where a [`Store`] keeps its records.
+enum Backend {
+    /// An append-only JSON lines file.
+    JsonLines(PathBuf),
+    /// A `prices` table in a SQLite database.
+    #[cfg(feature = "sqlite")]
+    Sqlite(sqlx::SqlitePool),
+}
+
+/// The [`PriceRecord`]'s of every run: an append-only JSON lines file or, with the `sqlite`
+/// feature, a SQLite database (a path ending in `.db`, `.sqlite` or `.sqlite3`).
+///
+/// Every record is read once, when the store is opened, so that looking up an item's
+/// history doesn't read the whole store again.
+pub struct Store {
+    backend: Backend,
+    /// Every record, oldest first.
+    records: Vec<PriceRecord>,
+    /// The index in `records` of the latest record per item.
+    latest: HashMap<String, usize>,
+}
+
+/// Whether `path` is a SQLite database, going by its extension.
+fn is_sqlite(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| ["db", "sqlite", "sqlite3"].contains(&e.to_ascii_lowercase().as_str()))
+}
+
+impl Store {
+    /// Open a store, creating it if it doesn't exist, and read its records.
+    ///
+    /// # Errors
+    /// Errors if the store exists but could not be read, or if one of its records is not a
+    /// [`PriceRecord`]. Without the `sqlite` feature, errors if the path is a SQLite database.
+    pub async fn open<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let (backend, records) = if is_sqlite(path) {
+            Self::open_sqlite(path).await?
+        } else {
+            (
+                Backend::JsonLines(path.to_path_buf()),
+                Self::read_json_lines(path)?,
+            )
+        };
+
+        let latest = records
+            .iter()
+            .enumerate()
+            .map(|(i, record)| (record.item.clone(), i))
+            .collect();
+        Ok(Self {
+            backend,
+            records,
+            latest,
+        })
+    }
+
+    fn read_json_lines(path: &Path) -> anyhow::Result<Vec<PriceRecord>> {
+        let mut records = Vec::new();
+        if !path.exists() {
+            return Ok(records);
+        }
+        let reader = BufReader::new(File::open(path)?);
+        for (i, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(
+                serde_json::from_str(&line).with_context(|| {
+                    format!("bad record on line {} of {}", i + 1, path.display())
+                })?,
+            );
+        }
+        Ok(records)
+    }
+
+    #[cfg(feature = "sqlite")]
+    async fn open_sqlite(path: &Path) -> anyhow::Result<(Backend, Vec<PriceRecord>)> {
+        let options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect_with(options)
+            .await
+            .with_context(|| format!("could not open {}", path.display()))?;
+        /* the price is kept as the JSON of a `Money`, so it reads back exactly */
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS prices (item TEXT NOT NULL, fetched_at TEXT NOT NULL, price TEXT, available INTEGER)",
+        )
+        .execute(&pool)
+        .await
+        .context("could not create the prices table")?;
+        /* stores from before domains were tracked don't have the column yet */
+        let (has_available,) = sqlx::query_as::<_, (bool,)>(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('prices') WHERE name = 'available'",
+        )
+        .fetch_one(&pool)
+        .await
+        .context("could not read the prices table")?;
+        if !has_available {
+            sqlx::query("ALTER TABLE prices ADD COLUMN available INTEGER")
+                .execute(&pool)
+                .await
+                .context("could not update the prices table")?;
+        }
+
+        let rows = sqlx::query_as::<_, (String, String, Option<String>, Option<bool>)>(
+            "SELECT item, fetched_at, price, available FROM prices ORDER BY rowid",
+        )
+        .fetch_all(&pool)
+        .await
+        .context("could not read the prices table")?;
+        let records = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, (item, fetched_at, price, available))| {
+                let record = (|| -> anyhow::Result<PriceRecord> {
+                    Ok(PriceRecord {
+                        item,
+                        fetched_at: DateTime::parse_from_rfc3339(&fetched_at)?.with_timezone(&Utc),
+                        price: price.as_deref().map(serde_json::from_str).transpose()?,
+                        available,
+                    })
+                })();
+                record.with_context(|| format!("bad record in row {} of {}", i + 1, path.display()))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok((Backend::Sqlite(pool), records))
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    async fn open_sqlite(path: &Path) -> anyhow::Result<(Backend, Vec<PriceRecord>)> {
+        anyhow::bail!(
+            "{} is a SQLite database, which needs the `sqlite` feature",
+            path.display()
+        )
+    }
+
+    /// Every record for the item with the given key, oldest first.
+    pub fn history(&self, item: &str) -> Vec<PriceRecord> {
+        self.records
+            .iter()
+            .filter(|record| record.item == item)
+            .cloned()
+            .collect()
+    }
+
+    /// The most recent record for the item with the given key.
+    pub fn latest(&self, item: &str) -> Option<&PriceRecord> {
+        self.latest.get(item).map(|i| &self.records[*i])
+    }
+
+    /// Append a record to the end of the store.
+    ///
+    /// # Errors
+    /// Errors if the record could not be written.
+    pub async fn append(&mut self, record: PriceRecord) -> anyhow::Result<()> {
+        match &self.backend {
+            Backend::JsonLines(path) => {
+                let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+                writeln!(file, "{}", serde_json::to_string(&record)?)?;
+            }
+            #[cfg(feature = "sqlite")]
+            Backend::Sqlite(pool) => {
+                sqlx::query(
+                    "INSERT INTO prices (item, fetched_at, price, available) VALUES (?1, ?2, ?3, ?4)",
+                )
+                    .bind(&record.item)
+                    .bind(record.fetched_at.to_rfc3339())
+                    .bind(
+                        record
+                            .price
+                            .as_ref()
+                            .map(serde_json::to_string)
+                            .transpose()?,
+                    )
+                    .bind(record.available)
+                    .execute(pool)
+                    .await
+                    .context("could not write to the prices table")?;
+            }
+        }
+        self.latest.insert(record.item.clone(), self.records.len());
+        self.records.push(record);
+        Ok(())
+    }
+}
+
+/// The prices of `records` as a time series, for [`analysis`], leaving out records
+/// without a price.
+pub fn series(records: &[PriceRecord]) -> Vec<Point> {
+    records
+        .iter()
+        .filter_map(|record| {
+            let value = record.price.as_ref()?.amount().to_f64()?;
+            Some(Point::new(record.fetched_at, value))
+        })
+        .collect()
+}
+
+/// How the price of a [`TrackedItem`] (or whether a domain can be registered) changed since
+/// the last time it was tracked.
+#[derive(Serialize)]
+pub struct Delta {
+    pub item: String,
+    pub fetched_at: DateTime<Utc>,
+    pub previous: Option<Money>,
+    pub current: Option<Money>,
+    /// `current - previous`, if both are known and in the same currency.
+    #[serde(serialize_with = "rust_decimal::serde::float_option::serialize")]
+    pub change: Option<Decimal>,
+    /// For a domain, whether it could be registered at the last run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub was_available: Option<bool>,
+    /// For a domain, whether it can be registered now.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub available: Option<bool>,
+    /// Why the current price could not be fetched, if it couldn't.
+    pub error: Option<String>,
+}
+
+impl Delta {
+    fn new(item: String, previous: Option<PriceRecord>, current: PriceRecord) -> Self {
+        let was_available = previous.as_ref().and_then(|r| r.available);
+        let previous = previous.and_then(|r| r.price);
+        let change = match (&previous, &current.price) {
+            (Some(previous), Some(current)) => current.checked_sub(previous).map(|m| m.amount()),
+            _ => None,
+        };
+
+        Self {
+            item,
+            fetched_at: current.fetched_at,
+            previous,
+            current: current.price,
+            change,
+            was_available,
+            available: current.available,
+            error: None,
+        }
+    }
+}
+
+/// Fetch the current price of every item in the [`Config`] (or, for a domain, whether it can
+/// be registered), append them to its [`Store`], and report how each one changed since the
+/// previous run.
+///
+/// Items that fail to fetch are reported with an [`Delta::error`] and are not recorded.
+/// Once everything has been fetched, the [`Rule`]'s in the `notify` section are checked
+/// (those with trend conditions, against each item's history in the store) and any
+/// resulting [`Notification`]'s are sent. Notifications that can't be delivered are logged,
+/// as the prices have already been recorded by then.
+///
+/// # Errors
+/// Errors if the store could not be read or written.
+pub async fn run(client: &mut Client<false>, config: &Config) -> anyhow::Result<Vec<Delta>> {
+    let mut store = Store::open(&config.store).await?;
+    let mut deltas = Vec::with_capacity(config.items.len());
+
+    for (i, item) in config.items.iter().enumerate() {
+        if i > 0 {
+            /* be nice! */
+            tokio::time::sleep(Duration::from_millis(600)).await;
+        }
+
+        let key = item.key();
+        let previous = store.latest(&key).cloned();
+
+        match item.fetch(client).await {
+            Ok(record) => {
+                store.append(record.clone()).await?;
+                deltas.push(Delta::new(key, previous, record));
+            }
+            Err(e) => deltas.push(Delta {
+                item: key,
+                fetched_at: Utc::now(),
+                was_available: previous.as_ref().and_then(|r| r.available),
+                previous: previous.and_then(|r| r.price),
+                current: None,
+                change: None,
+                available: None,
+                error: Some(format!("{:#}", e)),
+            }),
+        }
+    }
+
+    let notifiers = config.notify.targets.notifiers();
+    for delta in &deltas {
+        let trend_rules = config
+            .notify
+            .rules
+            .iter()
+            .filter(|r| r.has_trend() && r.item.key() == delta.item)
+            .collect::<Vec<_>>();
+        let history = match (&delta.error, trend_rules.is_empty()) {
+            (None, false) => series(&store.history(&delta.item)),
+            _ => Vec::new(),
+        };
+        let messages = config
+            .notify
+            .rules
+            .iter()
+            .filter_map(|r| r.check(delta))
+            .chain(
+                trend_rules
+                    .iter()
+                    .filter_map(|r| r.check_trend(&delta.item, &history)),
+            )
+            .collect::<Vec<_>>();
+        for message in messages {
+            let notification = Notification {
+                item: delta.item.clone(),
+                at: delta.fetched_at,
+                message,
+            };
+            if let Err(e) = notify::dispatch(&notifiers, &notification).await {
+                tracing::warn!(
+                    "could not send notification for {}: {:#}",
+                    notification.item,
+                    e
+                );
+            }
+        }
+    }
+
+    Ok(deltas)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, Utc};
+
+    use super::{series, Config, Delta, PriceRecord, Rule, Store, TrackedItem};
+    use rust_decimal::Decimal;
+
+    use crate::common::Money;
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(TrackedItem::Ebay(254625474154).key(), "ebay:254625474154");
+        assert_eq!(
+            TrackedItem::Url("https://www.ebay.com/itm/foo/254625474154?hash=x".to_string()).key(),
+            "ebay:254625474154"
+        );
+        assert_eq!(
+            TrackedItem::Url("https://example.com/".to_string()).key(),
+            "https://example.com/"
+        );
+        assert_eq!(
+            TrackedItem::Domain("Example.com".to_string()).key(),
+            "domain:example.com"
+        );
+    }
+
+    #[test]
+    fn test_config() {
+        let config: Config = toml::from_str(
+            r#"
+            store = "prices.jsonl"
+            items = [
+                { ebay = 254625474154 },
+                { url = "https://www.ebay.com/itm/254625474154" },
+                { domain = "example.com" },
+            ]
+
+            [notify]
+            stdout = true
+            rules = [
+                { item = { ebay = 254625474154 }, below = 30.0 },
+                { item = { ebay = 254625474154 }, above = "$1,000" },
+                { item = { domain = "example.com" }, available = true },
+            ]
+        "#,
+        )
+        .unwrap();
+        assert_eq!(config.items.len(), 3);
+        assert_eq!(config.items[0].key(), config.items[1].key());
+        assert!(config.notify.targets.stdout);
+        assert!(!config.notify.targets.stderr);
+        let rules = &config.notify.rules;
+        assert_eq!(rules[0].below, Some(Money::from(30.0)));
+        assert_eq!(rules[1].above, Some(Money::from(1000.0)));
+        assert!(!rules[1].available && rules[2].available);
+    }
+
+    #[test]
+    fn test_rule() {
+        let rule = Rule {
+            item: TrackedItem::Ebay(1),
+            below: Some(Money::from(400.0)),
+            above: None,
+            change_percent: None,
+            z_score: None,
+            days: 7,
+            available: false,
+        };
+        let record = |price| PriceRecord {
+            item: "ebay:1".to_string(),
+            fetched_at: Utc::now(),
+            price: Some(Money::from(price)),
+            available: None,
+        };
+
+        let crossed = Delta::new("ebay:1".to_string(), Some(record(449.0)), record(399.0));
+        assert!(rule.check(&crossed).is_some());
+
+        let stayed = Delta::new("ebay:1".to_string(), Some(record(399.0)), record(389.0));
+        assert!(rule.check(&stayed).is_none());
+
+        let other = Delta::new("ebay:2".to_string(), Some(record(449.0)), record(399.0));
+        assert!(rule.check(&other).is_none());
+
+        let rule = Rule {
+            item: TrackedItem::Domain("example.com".to_string()),
+            below: None,
+            available: true,
+            ..rule
+        };
+        let record = |available| PriceRecord {
+            item: "domain:example.com".to_string(),
+            fetched_at: Utc::now(),
+            price: None,
+            available: Some(available),
+        };
+        let dropped = Delta::new(
+            "domain:example.com".to_string(),
+            Some(record(false)),
+            record(true),
+        );
+        assert!(rule.check(&dropped).is_some());
+        let still = Delta::new(
+            "domain:example.com".to_string(),
+            Some(record(true)),
+            record(true),
+        );
+        assert!(rule.check(&still).is_none());
+        let first = Delta::new("domain:example.com".to_string(), None, record(true));
+        assert!(rule.check(&first).is_none());
+    }
+
+    /// Write to the store at `path`, and check that it reads back the same.
+    async fn round_trip(path: &std::path::Path) {
+        let record = |price: Option<f64>| PriceRecord {
+            item: "ebay:1".to_string(),
+            fetched_at: Utc::now(),
+            price: price.map(Money::from),
+            available: None,
+        };
+
+        let mut store = Store::open(path).await.unwrap();
+        assert!(store.latest("ebay:1").is_none());
+        store.append(record(Some(449.0))).await.unwrap();
+        store.append(record(None)).await.unwrap();
+        store.append(record(Some(429.5))).await.unwrap();
+
+        let store = Store::open(path).await.unwrap();
+        let history = store.history("ebay:1");
+        assert_eq!(
+            history.iter().map(|r| r.price.clone()).collect::<Vec<_>>(),
+            vec![Some(Money::from(449.0)), None, Some(Money::from(429.5))]
+        );
+        assert!(store.history("ebay:2").is_empty());
+        let previous = store.latest("ebay:1").cloned();
+        assert_eq!(previous.as_ref().unwrap().fetched_at, history[2].fetched_at);
+        let delta = Delta::new("ebay:1".to_string(), previous, record(Some(399.5)));
+        assert_eq!(delta.change, Some(Decimal::new(-30, 0)));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_store_and_delta() {
+        let path = std::env::temp_dir().join(format!(
+            "datacollect-tracking-{}.jsonl",
+            rand::random::<u64>()
+        ));
+        round_trip(&path).await;
+
+        #[cfg(feature = "sqlite")]
+        round_trip(&path.with_extension("sqlite")).await;
+        #[cfg(not(feature = "sqlite"))]
+        assert!(Store::open(path.with_extension("sqlite")).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trend_rule() {
+        let path = std::env::temp_dir().join(format!(
+            "datacollect-tracking-{}.jsonl",
+            rand::random::<u64>()
+        ));
+        let start = Utc::now() - Duration::days(10);
+        let mut store = Store::open(&path).await.unwrap();
+        for (day, price) in [100.0, 101.0, 99.0, 100.0, 85.0, 80.0].iter().enumerate() {
+            store
+                .append(PriceRecord {
+                    item: "ebay:1".to_string(),
+                    fetched_at: start + Duration::days(day as i64),
+                    price: Some(Money::from(*price)),
+                    available: None,
+                })
+                .await
+                .unwrap();
+        }
+        assert!(store.history("ebay:2").is_empty());
+        let history = series(&store.history("ebay:1"));
+        assert_eq!(history.len(), 6);
+
+        let rule = Rule {
+            item: TrackedItem::Ebay(1),
+            below: None,
+            above: None,
+            change_percent: Some(-10.0),
+            z_score: None,
+            days: 3,
+            available: false,
+        };
+        /* -15% on the fifth day crosses the threshold; -20% on the sixth stays past it */
+        assert!(rule.check_trend("ebay:1", &history[..5]).is_some());
+        assert!(rule.check_trend("ebay:1", &history).is_none());
+        assert!(rule.check_trend("ebay:2", &history[..5]).is_none());
+
+        let rule = Rule {
+            change_percent: None,
+            z_score: Some(3.0),
+            days: 7,
+            ..rule
+        };
+        assert!(rule.check_trend("ebay:1", &history[..4]).is_none());
+        assert!(rule.check_trend("ebay:1", &history[..5]).is_some());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
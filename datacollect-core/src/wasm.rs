@@ -0,0 +1,21 @@
+//! JavaScript bindings for the parsers, for use from WebAssembly (e.g. in a browser
+//! extension that already has the page open).
+//!
+//! Each function takes a page's HTML and returns the parsed record as a JSON string, or
+//! throws the error as a string.
+
+use anyhow::Context;
+use wasm_bindgen::prelude::*;
+
+use crate::{common::Detail, modules::ebay::Product};
+
+/// Parse an eBay item page into a [`Product`], as JSON. The item ID is read from the page's
+/// canonical link.
+#[wasm_bindgen(js_name = parseEbayProduct)]
+pub fn parse_ebay_product(html: &str) -> Result<String, JsValue> {
+    let product: anyhow::Result<Product> = Product::id_from_html(html)
+        .context("could not find the item ID")
+        .and_then(|id| Product::from_html(html, id, Detail::Default));
+    let product = product.map_err(|e| JsValue::from_str(&format!("{:#}", e)))?;
+    serde_json::to_string(&product).map_err(|e| JsValue::from_str(&e.to_string()))
+}
@@ -1,6 +1,19 @@
+//! The public face of datacollect.
+//!
+//! All of the code lives in `datacollect-core`; this crate re-exports each of its modules
+//! at the same path (e.g. `datacollect::modules::ebay` is `datacollect_core::modules::ebay`),
+//! so there is one copy of everything, and code written against either crate keeps working.
+//! `datacollect::core` is the whole core crate, for anything not re-exported here.
+
 pub use datacollect_core as core;
 
-pub use datacollect_core::{anyhow, chrono, modules, stream};
+pub use datacollect_core::{
+    analysis, anyhow, checkpoint, chrono, collector, common, diff, dropcatch, enrichment, modules,
+    notify, rust_decimal, schema_org, schemas, sinks, stream, testing, tracking, Datacollect,
+};
+
+#[cfg(feature = "arrow")]
+pub use datacollect_core::{arrow, arrow_array, arrow_schema};
 
 #[cfg(feature = "extras")]
 pub mod extras;